@@ -208,6 +208,22 @@ impl Root {
     pub fn stmts(self, tree: &SyntaxTree) -> impl Iterator<Item = Stmt> + '_ {
         nodes(self, tree)
     }
+
+    pub fn usings(self, tree: &SyntaxTree) -> impl Iterator<Item = Using> + '_ {
+        nodes(self, tree)
+    }
+
+    pub fn documented_defs(self, tree: &SyntaxTree) -> Vec<(Define, Vec<Comment>)> {
+        documented_defs(self, tree)
+    }
+}
+
+def_ast_node!(Using);
+
+impl Using {
+    pub fn file(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
 }
 
 def_ast_node!(Lambda);
@@ -228,6 +244,22 @@ impl Lambda {
     pub fn r#extern(self, tree: &SyntaxTree) -> Option<Extern> {
         token(self, tree)
     }
+
+    pub fn attrs(self, tree: &SyntaxTree) -> impl Iterator<Item = Attr> + '_ {
+        nodes(self, tree)
+    }
+}
+
+def_ast_node!(Attr);
+
+impl Attr {
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn value(self, tree: &SyntaxTree) -> Option<StringLiteral> {
+        node(self, tree)
+    }
 }
 
 def_multi_node! {
@@ -255,6 +287,13 @@ def_multi_node! {
     fn r#extern() -> Option<Extern>
 }
 
+impl Define {
+    /// The attributes written before the name, like `@deprecated`
+    pub fn attrs(self, tree: &SyntaxTree) -> impl Iterator<Item = Attr> + '_ {
+        nodes(self, tree)
+    }
+}
+
 def_ast_node!(Binding);
 
 impl Binding {
@@ -395,6 +434,7 @@ def_multi_node! {
     Mut -> MutExpr
     Deref -> DerefExpr
     Binary -> BinaryExpr
+    Range -> RangeExpr
     Unary -> UnaryExpr
     IntLiteral -> IntLiteral
     FloatLiteral -> FloatLiteral
@@ -403,6 +443,8 @@ def_multi_node! {
     StringLiteral -> StringLiteral
     StructDecl -> StructDecl
     StructLiteral -> StructLiteral
+    ModuleDecl -> ModuleDecl
+    InterfaceDecl -> InterfaceDecl
     EnumDecl -> EnumDecl
     ArrayDecl -> ArrayDecl
     ArrayLiteral -> ArrayLiteral
@@ -411,11 +453,15 @@ def_multi_node! {
     Path -> Path        // `foo.bar`
     Call -> Call
     Paren -> ParenExpr
+    Tuple -> TupleExpr
     Block -> Block
     If -> IfExpr
     While -> WhileExpr
+    For -> ForExpr
     Switch -> SwitchExpr
     Distinct -> Distinct
+    OptionalDecl -> OptionalDecl
+    ClosureDecl -> ClosureDecl
     Lambda -> Lambda
     Comptime -> ComptimeExpr
     Directive -> Directive
@@ -439,6 +485,39 @@ impl BinaryExpr {
     }
 }
 
+def_ast_node!(RangeExpr);
+
+impl RangeExpr {
+    pub fn start(self, tree: &SyntaxTree) -> Option<RangeStart> {
+        node(self, tree)
+    }
+
+    pub fn end(self, tree: &SyntaxTree) -> Option<RangeEnd> {
+        node(self, tree)
+    }
+
+    /// `a..=b` includes `b`, while `a..b` doesn't
+    pub fn inclusive(self, tree: &SyntaxTree) -> Option<DoubleDotEquals> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(RangeStart);
+
+impl RangeStart {
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(RangeEnd);
+
+impl RangeEnd {
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
 def_ast_node!(CastExpr);
 
 impl CastExpr {
@@ -487,6 +566,22 @@ impl Distinct {
     }
 }
 
+def_ast_node!(OptionalDecl);
+
+impl OptionalDecl {
+    pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(ClosureDecl);
+
+impl ClosureDecl {
+    pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
+        node(self, tree)
+    }
+}
+
 def_ast_node!(ParenExpr);
 
 impl ParenExpr {
@@ -495,6 +590,14 @@ impl ParenExpr {
     }
 }
 
+def_ast_node!(TupleExpr);
+
+impl TupleExpr {
+    pub fn items(self, tree: &SyntaxTree) -> impl Iterator<Item = Expr> + '_ {
+        nodes(self, tree)
+    }
+}
+
 def_ast_node!(Block);
 
 impl Block {
@@ -551,6 +654,27 @@ impl WhileExpr {
     }
 }
 
+def_ast_node!(ForExpr);
+
+impl ForExpr {
+    pub fn label(self, tree: &SyntaxTree) -> Option<LabelDecl> {
+        node(self, tree)
+    }
+
+    /// the `x` in `for x in xs { ... }`
+    pub fn item_name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn iterable(self, tree: &SyntaxTree) -> Option<Iterable> {
+        node(self, tree)
+    }
+
+    pub fn body(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
 def_ast_node!(SwitchExpr);
 
 impl SwitchExpr {
@@ -601,6 +725,19 @@ impl Condition {
     pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
         node(self, tree)
     }
+
+    /// the `x := next()` in `while x := next() { ... }`
+    pub fn binding(self, tree: &SyntaxTree) -> Option<Define> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(Iterable);
+
+impl Iterable {
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
 }
 
 def_ast_node!(StructDecl);
@@ -609,6 +746,57 @@ impl StructDecl {
     pub fn members(self, tree: &SyntaxTree) -> impl Iterator<Item = MemberDecl> + '_ {
         nodes(self, tree)
     }
+
+    pub fn decls(self, tree: &SyntaxTree) -> impl Iterator<Item = Define> + '_ {
+        nodes(self, tree)
+    }
+
+    pub fn documented_decls(self, tree: &SyntaxTree) -> Vec<(Define, Vec<Comment>)> {
+        documented_defs(self, tree)
+    }
+}
+
+def_ast_node!(ModuleDecl);
+
+impl ModuleDecl {
+    pub fn decls(self, tree: &SyntaxTree) -> impl Iterator<Item = Define> + '_ {
+        nodes(self, tree)
+    }
+
+    pub fn documented_decls(self, tree: &SyntaxTree) -> Vec<(Define, Vec<Comment>)> {
+        documented_defs(self, tree)
+    }
+}
+
+def_ast_node!(InterfaceDecl);
+
+impl InterfaceDecl {
+    pub fn methods(self, tree: &SyntaxTree) -> impl Iterator<Item = MemberDecl> + '_ {
+        nodes(self, tree)
+    }
+}
+
+def_ast_node!(Comment);
+
+impl Comment {
+    pub fn doc_comment_leader(self, tree: &SyntaxTree) -> Option<DocCommentLeader> {
+        token(self, tree)
+    }
+
+    pub fn contents(self, tree: &SyntaxTree) -> Option<CommentContents> {
+        token(self, tree)
+    }
+
+    /// the text after the `///` if this is a doc comment
+    pub fn doc(self, tree: &SyntaxTree) -> Option<&str> {
+        self.doc_comment_leader(tree)?;
+
+        Some(
+            self.contents(tree)
+                .map(|contents| contents.text(tree))
+                .unwrap_or_default(),
+        )
+    }
 }
 
 def_ast_node!(MemberDecl);
@@ -701,6 +889,10 @@ impl ArrayLiteral {
     pub fn items(self, tree: &SyntaxTree) -> impl Iterator<Item = ArrayItem> + '_ {
         nodes(self, tree)
     }
+
+    pub fn repeat_count(self, tree: &SyntaxTree) -> Option<ArrayRepeatCount> {
+        node(self, tree)
+    }
 }
 
 def_ast_node!(ArraySize);
@@ -719,6 +911,14 @@ impl ArrayItem {
     }
 }
 
+def_ast_node!(ArrayRepeatCount);
+
+impl ArrayRepeatCount {
+    pub fn count(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
 def_ast_node!(IndexExpr);
 
 impl IndexExpr {
@@ -786,6 +986,11 @@ impl Path {
         token(self, tree)
     }
 
+    /// the `0` in `tuple.0`
+    pub fn field_index(self, tree: &SyntaxTree) -> Option<Int> {
+        token(self, tree)
+    }
+
     pub fn previous_part(self, tree: &SyntaxTree) -> Option<Expr> {
         node(self, tree)
     }
@@ -889,6 +1094,9 @@ def_multi_token! {
     // logical operations
     LAnd -> DoubleAnd
     LOr -> DoublePipe
+
+    // optional operations
+    Orelse -> Orelse
 }
 
 def_ast_node!(UnaryExpr);
@@ -939,6 +1147,7 @@ def_ast_token!(And);
 def_ast_token!(DoubleAnd);
 def_ast_token!(Pipe);
 def_ast_token!(DoublePipe);
+def_ast_token!(Orelse);
 def_ast_token!(Ident);
 def_ast_token!(Int);
 def_ast_token!(Hex);
@@ -946,15 +1155,21 @@ def_ast_token!(Bin);
 def_ast_token!(Float);
 def_ast_token!(Bool);
 def_ast_token!(Ellipsis);
+def_ast_token!(DoubleDotEquals);
 
 def_multi_token! {
     StringComponent:
     Escape -> Escape
     Contents -> StringContents
+    // the `\\` at the start of each line of a multiline string
+    LineLeader -> MultilineStringLeader
 }
 
 def_ast_token!(Escape);
 def_ast_token!(StringContents);
+def_ast_token!(MultilineStringLeader);
+def_ast_token!(DocCommentLeader);
+def_ast_token!(CommentContents);
 
 fn nodes<Parent: AstNode, Child: AstNode>(
     node: Parent,
@@ -965,6 +1180,28 @@ fn nodes<Parent: AstNode, Child: AstNode>(
         .filter_map(|n| Child::cast(n, tree))
 }
 
+/// pairs each definition with the doc comments directly above it.
+/// anything else in between (even a regular comment) detaches the doc comments
+fn documented_defs<Parent: AstNode>(
+    node: Parent,
+    tree: &SyntaxTree,
+) -> Vec<(Define, Vec<Comment>)> {
+    let mut result = Vec::new();
+    let mut docs = Vec::new();
+
+    for child in node.syntax().child_nodes(tree) {
+        if let Some(def) = Define::cast(child, tree) {
+            result.push((def, std::mem::take(&mut docs)));
+        } else if let Some(comment) = Comment::cast(child, tree).filter(|c| c.doc(tree).is_some()) {
+            docs.push(comment);
+        } else {
+            docs.clear();
+        }
+    }
+
+    result
+}
+
 fn node<Parent: AstNode, Child: AstNode>(node: Parent, tree: &SyntaxTree) -> Option<Child> {
     node.syntax()
         .child_nodes(tree)
@@ -2158,6 +2395,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_for_item_and_iterable() {
+        let (tree, root) = parse("for x in xs { bar(); }");
+        let statement = root.stmts(&tree).next().unwrap();
+        let expr = match statement {
+            Stmt::Expr(expr_stmt) => expr_stmt.expr(&tree),
+            _ => unreachable!(),
+        };
+
+        let for_expr = match expr {
+            Some(Expr::For(for_expr)) => for_expr,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(for_expr.item_name(&tree).unwrap().text(&tree), "x");
+
+        let iterable = for_expr.iterable(&tree).unwrap().value(&tree);
+
+        assert!(matches!(iterable, Some(Expr::VarRef(_))));
+        assert!(matches!(for_expr.body(&tree), Some(Expr::Block(_))));
+    }
+
     #[test]
     fn get_lambda_params() {
         let (tree, root) = parse("(x: i32, y: i32) {};");
@@ -1,6 +1,6 @@
-use crate::{AstNode, Expr, IfExpr, WhileExpr};
-use syntax::SyntaxTree;
-use text_size::TextRange;
+use crate::{AstNode, AstToken, BinaryExpr, BinaryOp, Expr, IfExpr, SwitchExpr, WhileExpr};
+use syntax::{SyntaxTree, TokenKind};
+use text_size::{TextRange, TextSize};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ValidationDiagnostic {
@@ -12,13 +12,82 @@ pub struct ValidationDiagnostic {
 pub enum ValidationDiagnosticKind {
     AlwaysTrue,
     AlwaysFalse,
+    /// the body of a `while false` loop can never run
+    LoopNeverRuns,
+    /// a comparison between two literals, like `1 < 2`
+    ConstantComparison,
     ParenInCondition,
+    /// a switch arm with exactly the same body as an earlier arm.
+    /// arms can't be merged, so this only points out what's likely a copy-paste mistake
+    IdenticalSwitchArm,
+    /// a name mixes ASCII letters with a character that looks just like one
+    ConfusableChar {
+        found: char,
+        looks_like: char,
+    },
 }
 
+/// Letters from other scripts which are practically indistinguishable from ASCII letters
+const CONFUSABLES: &[(char, char)] = &[
+    // cyrillic
+    ('\u{0430}', 'a'),
+    ('\u{0441}', 'c'),
+    ('\u{0435}', 'e'),
+    ('\u{04BB}', 'h'),
+    ('\u{0456}', 'i'),
+    ('\u{0458}', 'j'),
+    ('\u{043E}', 'o'),
+    ('\u{0440}', 'p'),
+    ('\u{0455}', 's'),
+    ('\u{0445}', 'x'),
+    ('\u{0443}', 'y'),
+    ('\u{0410}', 'A'),
+    ('\u{0412}', 'B'),
+    ('\u{0421}', 'C'),
+    ('\u{0415}', 'E'),
+    ('\u{041D}', 'H'),
+    ('\u{0406}', 'I'),
+    ('\u{0408}', 'J'),
+    ('\u{041A}', 'K'),
+    ('\u{041C}', 'M'),
+    ('\u{041E}', 'O'),
+    ('\u{0420}', 'P'),
+    ('\u{0405}', 'S'),
+    ('\u{0422}', 'T'),
+    ('\u{0425}', 'X'),
+    // greek
+    ('\u{03BF}', 'o'),
+    ('\u{03BD}', 'v'),
+    ('\u{0391}', 'A'),
+    ('\u{0392}', 'B'),
+    ('\u{0395}', 'E'),
+    ('\u{0396}', 'Z'),
+    ('\u{0397}', 'H'),
+    ('\u{0399}', 'I'),
+    ('\u{039A}', 'K'),
+    ('\u{039C}', 'M'),
+    ('\u{039D}', 'N'),
+    ('\u{039F}', 'O'),
+    ('\u{03A1}', 'P'),
+    ('\u{03A4}', 'T'),
+    ('\u{03A5}', 'Y'),
+    ('\u{03A7}', 'X'),
+];
+
 pub fn validate(ast: impl AstNode, tree: &SyntaxTree) -> Vec<ValidationDiagnostic> {
     let mut errors = Vec::new();
 
     for node in ast.syntax().descendant_nodes(tree) {
+        if let Some(binary_expr) = BinaryExpr::cast(node, tree) {
+            validate_comparison(binary_expr, tree, &mut errors);
+            continue;
+        }
+
+        if let Some(switch_expr) = SwitchExpr::cast(node, tree) {
+            validate_switch_arms(switch_expr, tree, &mut errors);
+            continue;
+        }
+
         match IfExpr::cast(node, tree)
             .and_then(|if_expr| if_expr.condition(tree))
             .or_else(|| {
@@ -38,6 +107,11 @@ pub fn validate(ast: impl AstNode, tree: &SyntaxTree) -> Vec<ValidationDiagnosti
                         kind: ValidationDiagnosticKind::AlwaysTrue,
                         range: node.range(tree),
                     });
+                } else if WhileExpr::cast(node, tree).is_some() {
+                    errors.push(ValidationDiagnostic {
+                        kind: ValidationDiagnosticKind::LoopNeverRuns,
+                        range: node.range(tree),
+                    });
                 } else {
                     errors.push(ValidationDiagnostic {
                         kind: ValidationDiagnosticKind::AlwaysFalse,
@@ -49,9 +123,126 @@ pub fn validate(ast: impl AstNode, tree: &SyntaxTree) -> Vec<ValidationDiagnosti
         }
     }
 
+    for token in ast.syntax().descendant_tokens(tree) {
+        if token.kind(tree) == TokenKind::Ident {
+            validate_ident(token.text(tree), token.range(tree), &mut errors);
+        }
+    }
+
     errors
 }
 
+fn validate_comparison(
+    binary_expr: BinaryExpr,
+    tree: &SyntaxTree,
+    errors: &mut Vec<ValidationDiagnostic>,
+) {
+    if !matches!(
+        binary_expr.op(tree),
+        Some(
+            BinaryOp::Lt(_)
+                | BinaryOp::Gt(_)
+                | BinaryOp::Le(_)
+                | BinaryOp::Ge(_)
+                | BinaryOp::Eq(_)
+                | BinaryOp::Ne(_)
+        )
+    ) {
+        return;
+    }
+
+    let (Some(lhs), Some(rhs)) = (binary_expr.lhs(tree), binary_expr.rhs(tree)) else {
+        return;
+    };
+
+    if is_literal(lhs) && is_literal(rhs) {
+        errors.push(ValidationDiagnostic {
+            kind: ValidationDiagnosticKind::ConstantComparison,
+            range: binary_expr.range(tree),
+        });
+    }
+}
+
+fn validate_switch_arms(
+    switch_expr: SwitchExpr,
+    tree: &SyntaxTree,
+    errors: &mut Vec<ValidationDiagnostic>,
+) {
+    let variable_name = switch_expr.variable_name(tree).map(|name| name.text(tree));
+
+    let mut seen_bodies = Vec::new();
+
+    for arm in switch_expr.arms(tree) {
+        let Some(variant_name) = arm.variant_name(tree) else {
+            continue;
+        };
+        if variant_name.text(tree) == "_" {
+            continue;
+        }
+
+        let Some(body) = arm.body(tree) else {
+            continue;
+        };
+        let text = body.text(tree);
+
+        // arms that do nothing are usually there on purpose, to show the variant was considered
+        if text == "{}" {
+            continue;
+        }
+
+        // the switch parameter has a different type in every arm,
+        // so the same text can still do something different
+        if variable_name.is_some_and(|variable_name| {
+            body.syntax().descendant_tokens(tree).any(|token| {
+                token.kind(tree) == TokenKind::Ident && token.text(tree) == variable_name
+            })
+        }) {
+            continue;
+        }
+
+        if seen_bodies.contains(&text) {
+            errors.push(ValidationDiagnostic {
+                kind: ValidationDiagnosticKind::IdenticalSwitchArm,
+                range: arm.range(tree),
+            });
+        } else {
+            seen_bodies.push(text);
+        }
+    }
+}
+
+fn is_literal(expr: Expr) -> bool {
+    matches!(
+        expr,
+        Expr::IntLiteral(_) | Expr::FloatLiteral(_) | Expr::BoolLiteral(_) | Expr::CharLiteral(_)
+    )
+}
+
+/// Names written entirely in another script are fine, but a name like `vаlue` (with a cyrillic
+/// `а`) is almost certainly either a typo or an attempt to sneak in a second `value`
+fn validate_ident(text: &str, range: TextRange, errors: &mut Vec<ValidationDiagnostic>) {
+    if text.is_ascii() || !text.chars().any(|ch| ch.is_ascii_alphabetic()) {
+        return;
+    }
+
+    let mut offset = range.start();
+    for ch in text.chars() {
+        let len = TextSize::of(ch);
+
+        if let Some((_, looks_like)) = CONFUSABLES.iter().find(|(found, _)| *found == ch) {
+            errors.push(ValidationDiagnostic {
+                kind: ValidationDiagnosticKind::ConfusableChar {
+                    found: ch,
+                    looks_like: *looks_like,
+                },
+                range: TextRange::at(offset, len),
+            });
+        }
+
+        offset += len;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Root;
@@ -146,10 +337,31 @@ mod tests {
     fn validate_while_always_false() {
         check_repl_line(
             "while false {}",
-            [(ValidationDiagnosticKind::AlwaysFalse, (0..14))],
+            [(ValidationDiagnosticKind::LoopNeverRuns, (0..14))],
+        );
+    }
+
+    #[test]
+    fn validate_if_constant_comparison() {
+        check_repl_line(
+            "if 1 < 2 {}",
+            [(ValidationDiagnosticKind::ConstantComparison, (3..8))],
+        );
+    }
+
+    #[test]
+    fn validate_while_constant_comparison() {
+        check_repl_line(
+            "while 'a' != 'b' {}",
+            [(ValidationDiagnosticKind::ConstantComparison, (6..16))],
         );
     }
 
+    #[test]
+    fn validate_constant_arithmetic() {
+        check_repl_line("if 1 + 2 {}", []);
+    }
+
     #[test]
     fn validate_parentheses() {
         check_repl_line("(true)", []);
@@ -170,4 +382,45 @@ mod tests {
             [(ValidationDiagnosticKind::ParenInCondition, (6..12))],
         );
     }
+
+    #[test]
+    fn validate_identical_switch_arms() {
+        check_repl_line(
+            "switch e in foo { A => 1, B => 2, C => 1, _ => 1 }",
+            [(ValidationDiagnosticKind::IdenticalSwitchArm, (34..40))],
+        );
+    }
+
+    #[test]
+    fn validate_identical_switch_arms_using_parameter() {
+        check_repl_line(
+            "switch info in foo { A => info.size, B => info.size, C => 1, D => 1 }",
+            [(ValidationDiagnosticKind::IdenticalSwitchArm, (61..67))],
+        );
+    }
+
+    #[test]
+    fn validate_empty_switch_arms() {
+        check_repl_line("switch e in foo { A => {}, B => {} }", []);
+    }
+
+    #[test]
+    fn validate_non_ascii_ident() {
+        check_repl_line("größe := 5; \u{043A}\u{043E}\u{0442} := 2;", []);
+    }
+
+    #[test]
+    fn validate_confusable_ident() {
+        // the `а` in `vаlue` is cyrillic, and takes up two bytes
+        check_repl_line(
+            "v\u{0430}lue := 5;",
+            [(
+                ValidationDiagnosticKind::ConfusableChar {
+                    found: '\u{0430}',
+                    looks_like: 'a',
+                },
+                (1..3),
+            )],
+        );
+    }
 }
@@ -0,0 +1,327 @@
+//! Compares `extern` functions and structs against the C declarations they're meant to match
+
+use hir_ty::Ty;
+use interner::Interner;
+use internment::Intern;
+
+use crate::c_header::{CFunction, CTy, CType, DataModel, Header};
+
+pub(crate) struct AbiChecker<'a> {
+    pub(crate) header: &'a Header,
+    pub(crate) model: DataModel,
+    pub(crate) mod_dir: &'a std::path::Path,
+    pub(crate) interner: &'a Interner,
+}
+
+impl AbiChecker<'_> {
+    /// Every way that the function differs from its prototype in the header
+    pub(crate) fn check_function(
+        &self,
+        name: &str,
+        param_tys: &[Intern<Ty>],
+        return_ty: Intern<Ty>,
+        c_function: &CFunction,
+    ) -> Vec<String> {
+        let mut mismatches = Vec::new();
+
+        if c_function.variadic {
+            mismatches.push(format!(
+                "`{name}` takes a variable number of arguments in the header, \
+                 which can't be passed correctly to an `extern` function"
+            ));
+        }
+
+        if param_tys.len() != c_function.params.len() {
+            mismatches.push(format!(
+                "`{name}` takes {} here, but {} in the header",
+                plural(param_tys.len(), "parameter"),
+                c_function.params.len()
+            ));
+        }
+
+        for (idx, (param_ty, c_param)) in param_tys.iter().zip(&c_function.params).enumerate() {
+            if !self.is_compatible(*param_ty, &c_param.ty) {
+                mismatches.push(format!(
+                    "parameter {} of `{name}` is {} here, but {} in the header",
+                    idx + 1,
+                    self.describe(*param_ty),
+                    self.describe_c(c_param)
+                ));
+            }
+        }
+
+        if !self.is_compatible(return_ty, &c_function.return_ty.ty) {
+            mismatches.push(format!(
+                "`{name}` returns {} here, but {} in the header",
+                self.describe(return_ty),
+                self.describe_c(&c_function.return_ty)
+            ));
+        }
+
+        mismatches
+    }
+
+    /// Every way that the struct differs from its definition in the header
+    pub(crate) fn check_struct(&self, name: &str, ty: Intern<Ty>, c_name: &str) -> Vec<String> {
+        let Some((tag, c_fields)) = self.header.struct_named(c_name) else {
+            return Vec::new();
+        };
+        // a field which couldn't be understood throws off all the offsets after it
+        let Some(c_layout) = self.header.struct_layout(tag, self.model) else {
+            return Vec::new();
+        };
+
+        let layout = codegen::layout_of(ty, self.model.pointer_size * 8);
+
+        let mut mismatches = Vec::new();
+
+        if layout.fields.len() != c_fields.len() {
+            mismatches.push(format!(
+                "`{name}` has {} here, but {} in the header",
+                plural(layout.fields.len(), "field"),
+                c_fields.len()
+            ));
+        }
+
+        for ((field, c_field), c_offset) in
+            layout.fields.iter().zip(c_fields).zip(&c_layout.offsets)
+        {
+            let field_name = field
+                .name
+                .map(|name| self.interner.lookup(name.0))
+                .unwrap_or_default();
+
+            if !self.is_compatible(field.ty, &c_field.ty.ty) {
+                mismatches.push(format!(
+                    "`{name}.{field_name}` is {} here, but `{}` is {} in the header",
+                    self.describe(field.ty),
+                    c_field.name,
+                    self.describe_c(&c_field.ty)
+                ));
+            } else if field.offset != *c_offset {
+                mismatches.push(format!(
+                    "`{name}.{field_name}` is at offset {} here, but `{}` is at offset {c_offset} in the header",
+                    field.offset, c_field.name
+                ));
+            }
+        }
+
+        if layout.stride != c_layout.size || layout.align != c_layout.align {
+            mismatches.push(format!(
+                "`{name}` takes up {} bytes with an alignment of {} here, \
+                 but {} bytes with an alignment of {} in the header",
+                layout.stride, layout.align, c_layout.size, c_layout.align
+            ));
+        }
+
+        mismatches
+    }
+
+    /// Whether or not the Capy type gets passed around the same way as the C type.
+    /// Anything that couldn't be understood in the header is assumed to be fine
+    fn is_compatible(&self, ty: Intern<Ty>, c_ty: &CTy) -> bool {
+        let size = || codegen::layout_of(ty, self.model.pointer_size * 8).size;
+
+        match (ty.absolute_ty(), c_ty) {
+            (_, CTy::Unknown) => true,
+            (Ty::Void, CTy::Void) => true,
+            (Ty::IInt(_) | Ty::UInt(_) | Ty::Char | Ty::Bool, CTy::Int { size: c_size, .. }) => {
+                size() == *c_size
+            }
+            (Ty::IInt(_) | Ty::UInt(_) | Ty::Char | Ty::Bool, CTy::Bool) => size() == 1,
+            (Ty::Float(_), CTy::Float(c_size)) => size() == *c_size,
+            (
+                Ty::Pointer { .. } | Ty::RawPtr { .. } | Ty::String | Ty::Function { .. },
+                CTy::Pointer,
+            ) => true,
+            (Ty::Struct { .. } | Ty::Tuple { .. }, CTy::Struct(_)) => {
+                let layout = codegen::layout_of(ty, self.model.pointer_size * 8);
+
+                match self.header.size_align(c_ty, self.model) {
+                    Some((c_size, c_align)) => layout.stride == c_size && layout.align == c_align,
+                    None => true,
+                }
+            }
+            (
+                Ty::Array { size, sub_ty, .. },
+                CTy::Array {
+                    sub_ty: c_sub_ty,
+                    len,
+                },
+            ) => size == len && self.is_compatible(*sub_ty, c_sub_ty),
+            _ => false,
+        }
+    }
+
+    fn describe(&self, ty: Intern<Ty>) -> String {
+        let layout = codegen::layout_of(ty, self.model.pointer_size * 8);
+
+        format!(
+            "`{}` ({})",
+            ty.display(self.mod_dir, self.interner),
+            plural(layout.size as usize, "byte")
+        )
+    }
+
+    fn describe_c(&self, c_ty: &CType) -> String {
+        match self.header.size_align(&c_ty.ty, self.model) {
+            Some((size, _)) => format!("`{}` ({})", c_ty.spelling, plural(size as usize, "byte")),
+            None => format!("`{}`", c_ty.spelling),
+        }
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use hir_ty::MemberTy;
+
+    use super::*;
+
+    const LP64: DataModel = DataModel {
+        pointer_size: 8,
+        long_size: 8,
+        wchar_size: 4,
+    };
+
+    const HEADER: &str = r#"
+        struct Point {
+            int x;
+            int y;
+        };
+
+        long distance(struct Point *a, struct Point *b);
+        void log_message(const char *fmt, ...);
+    "#;
+
+    fn check_function(name: &str, param_tys: &[Ty], return_ty: Ty) -> Vec<String> {
+        let header = Header::parse(HEADER, LP64);
+        let interner = Interner::default();
+        let checker = AbiChecker {
+            header: &header,
+            model: LP64,
+            mod_dir: Path::new(""),
+            interner: &interner,
+        };
+
+        let param_tys: Vec<_> = param_tys.iter().cloned().map(Intern::new).collect();
+        checker.check_function(
+            name,
+            &param_tys,
+            Intern::new(return_ty),
+            &header.functions[name],
+        )
+    }
+
+    fn check_struct(members: &[(&str, Ty)]) -> Vec<String> {
+        let header = Header::parse(HEADER, LP64);
+        let mut interner = Interner::default();
+        let members = members
+            .iter()
+            .map(|(name, ty)| MemberTy {
+                name: hir::Name(interner.intern(name)),
+                ty: Intern::new(ty.clone()),
+            })
+            .collect();
+        let checker = AbiChecker {
+            header: &header,
+            model: LP64,
+            mod_dir: Path::new(""),
+            interner: &interner,
+        };
+
+        checker.check_struct(
+            "Point",
+            Intern::new(Ty::Struct {
+                anonymous: true,
+                fqn: None,
+                uid: 0,
+                members,
+            }),
+            "Point",
+        )
+    }
+
+    fn ptr() -> Ty {
+        Ty::RawPtr { mutable: false }
+    }
+
+    #[test]
+    fn matching_function() {
+        assert_eq!(
+            check_function("distance", &[ptr(), ptr()], Ty::IInt(64)),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn wrong_param_type() {
+        assert_eq!(
+            check_function("distance", &[ptr(), Ty::IInt(32)], Ty::IInt(64)),
+            ["parameter 2 of `distance` is `i32` (4 bytes) here, but `struct Point*` (8 bytes) in the header"]
+        );
+    }
+
+    #[test]
+    fn wrong_param_count() {
+        assert_eq!(
+            check_function("distance", &[ptr()], Ty::IInt(64)),
+            ["`distance` takes 1 parameter here, but 2 in the header"]
+        );
+    }
+
+    #[test]
+    fn wrong_return_type() {
+        assert_eq!(
+            check_function("distance", &[ptr(), ptr()], Ty::IInt(32)),
+            ["`distance` returns `i32` (4 bytes) here, but `long` (8 bytes) in the header"]
+        );
+    }
+
+    #[test]
+    fn variadic_function() {
+        assert_eq!(
+            check_function("log_message", &[Ty::String], Ty::Void),
+            ["`log_message` takes a variable number of arguments in the header, which can't be passed correctly to an `extern` function"]
+        );
+    }
+
+    #[test]
+    fn matching_struct() {
+        assert_eq!(
+            check_struct(&[("x", Ty::IInt(32)), ("y", Ty::IInt(32))]),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn wrong_field_type() {
+        assert_eq!(
+            check_struct(&[("x", Ty::IInt(32)), ("y", Ty::IInt(64))]),
+            [
+                "`Point.y` is `i64` (8 bytes) here, but `y` is `int` (4 bytes) in the header",
+                "`Point` takes up 16 bytes with an alignment of 8 here, but 8 bytes with an alignment of 4 in the header",
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_field_count() {
+        assert_eq!(
+            check_struct(&[("x", Ty::IInt(32))]),
+            [
+                "`Point` has 1 field here, but 2 in the header",
+                "`Point` takes up 4 bytes with an alignment of 4 here, but 8 bytes with an alignment of 4 in the header",
+            ]
+        );
+    }
+}
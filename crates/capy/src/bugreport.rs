@@ -0,0 +1,135 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{exit, Command},
+};
+
+use itertools::Itertools;
+use target_lexicon::Triple;
+
+/// Builds the file by running `capy build` in a separate process, then packages up the output
+/// (including any panic message), the compiler version, the target, and the source of the file
+/// and everything it imports into a single markdown snippet.
+///
+/// A separate process is used so that an internal compiler error doesn't take down the report
+/// with it.
+pub(crate) fn create(
+    file: &str,
+    mod_dir: &Path,
+    output: Option<&str>,
+    args: &[String],
+) -> io::Result<()> {
+    let exe = env::current_exe()?;
+
+    let mut build_args = vec![
+        "build".to_string(),
+        file.to_string(),
+        "--no-exec".to_string(),
+        "--color".to_string(),
+        "never".to_string(),
+        "--mod-dir".to_string(),
+        mod_dir.to_string_lossy().to_string(),
+    ];
+    build_args.extend(args.iter().cloned());
+
+    let result = Command::new(&exe).args(&build_args).output()?;
+
+    let target = args
+        .iter()
+        .position(|arg| arg == "--target")
+        .and_then(|idx| args.get(idx + 1))
+        .map(|target| target.to_string())
+        .or_else(|| {
+            args.iter()
+                .find_map(|arg| arg.strip_prefix("--target=").map(str::to_string))
+        })
+        .unwrap_or_else(|| Triple::host().to_string());
+
+    let status = match result.status.code() {
+        Some(code) => code.to_string(),
+        None => "terminated by a signal".to_string(),
+    };
+
+    let mut report = String::new();
+
+    report.push_str("## Bug Report\n\n");
+    report.push_str(&format!(
+        "- **Compiler:** capy {}\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    report.push_str(&format!("- **Host:** {}\n", Triple::host()));
+    report.push_str(&format!("- **Target:** {}\n", target));
+    // the mod dir and the other flags added above are left out since they'd only be noise
+    report.push_str(&format!(
+        "- **Command:** `capy build {}`\n",
+        std::iter::once(file)
+            .chain(args.iter().map(String::as_str))
+            .join(" ")
+    ));
+    report.push_str(&format!("- **Exit Status:** {}\n", status));
+
+    let mut compiler_output = String::from_utf8_lossy(&result.stdout).to_string();
+    compiler_output.push_str(&String::from_utf8_lossy(&result.stderr));
+    push_code_block(&mut report, "Output", "", compiler_output.trim_end());
+
+    for (file_name, contents) in collect_files(file, mod_dir) {
+        let file_name = env::current_dir()
+            .ok()
+            .and_then(|dir| file_name.strip_prefix(dir).ok().map(Path::to_path_buf))
+            .unwrap_or(file_name);
+
+        push_code_block(
+            &mut report,
+            &format!("`{}`", file_name.display()),
+            "capy",
+            contents.trim_end(),
+        );
+    }
+
+    match output {
+        Some(output) => {
+            fs::write(output, report)?;
+            println!("wrote bug report to `{}`", output);
+        }
+        None => print!("{}", report),
+    }
+
+    Ok(())
+}
+
+/// Returns the given file and every file it imports, excluding files from the mod dir.
+///
+/// The files are only parsed and lowered, so this still works when type checking or
+/// codegen is what's broken.
+fn collect_files(file: &str, mod_dir: &Path) -> Vec<(PathBuf, String)> {
+    let project = match capy::Compiler::new(mod_dir)
+        .load(file.replace(['/', '\\'], std::path::MAIN_SEPARATOR_STR))
+    {
+        Ok(project) => project,
+        Err(why) => {
+            println!("{}", why);
+            exit(1)
+        }
+    };
+
+    project
+        .files()
+        .filter(|file| !file.is_mod())
+        .map(|file| (file.file_name().to_path_buf(), file.contents().to_string()))
+        .collect()
+}
+
+fn push_code_block(report: &mut String, title: &str, lang: &str, code: &str) {
+    // the fence has to be longer than any run of backticks within the code
+    let longest_run = code
+        .split(|ch| ch != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest_run.max(2) + 1);
+
+    report.push_str(&format!(
+        "\n### {}\n\n{fence}{lang}\n{code}\n{fence}\n",
+        title
+    ));
+}
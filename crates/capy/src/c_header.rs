@@ -0,0 +1,952 @@
+//! Just enough of a C parser to read the structs and function prototypes out of a header,
+//! so they can be checked against the `extern` declarations meant to match them.
+//!
+//! Macros aren't expanded. Unions, bitfields, `long double` and anything else which can't be
+//! understood is treated as unknown, and left unchecked.
+
+use rustc_hash::FxHashMap;
+
+/// The sizes of the C types which change between targets
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DataModel {
+    pub(crate) pointer_size: u32,
+    pub(crate) long_size: u32,
+    pub(crate) wchar_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CTy {
+    Void,
+    Int {
+        size: u32,
+        signed: bool,
+    },
+    Bool,
+    Float(u32),
+    /// pointers to anything, including functions
+    Pointer,
+    /// `struct Tag`, or a typedef of one
+    Struct(String),
+    Array {
+        sub_ty: Box<CTy>,
+        len: u64,
+    },
+    Unknown,
+}
+
+/// A type along with how it was written in the header, for error messages
+#[derive(Debug, Clone)]
+pub(crate) struct CType {
+    pub(crate) ty: CTy,
+    pub(crate) spelling: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct CField {
+    pub(crate) name: String,
+    pub(crate) ty: CType,
+}
+
+#[derive(Debug)]
+pub(crate) struct CFunction {
+    pub(crate) params: Vec<CType>,
+    pub(crate) return_ty: CType,
+    pub(crate) variadic: bool,
+}
+
+/// The size, alignment, and field offsets of a C struct. The size includes trailing padding
+#[derive(Debug)]
+pub(crate) struct CStructLayout {
+    pub(crate) size: u32,
+    pub(crate) align: u32,
+    pub(crate) offsets: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Header {
+    /// keyed by their tags. anonymous structs get a tag like `(anonymous 0)`
+    structs: FxHashMap<String, Vec<CField>>,
+    typedefs: FxHashMap<String, CType>,
+    pub(crate) functions: FxHashMap<String, CFunction>,
+}
+
+impl Header {
+    pub(crate) fn parse(text: &str, model: DataModel) -> Header {
+        let mut parser = Parser {
+            tokens: tokenize(text),
+            pos: 0,
+            model,
+            header: Header::default(),
+            anonymous_structs: 0,
+        };
+
+        parser.parse();
+
+        parser.header
+    }
+
+    /// Finds a struct by its tag or by the name of a typedef of it
+    pub(crate) fn struct_named(&self, name: &str) -> Option<(&str, &[CField])> {
+        if let Some((tag, fields)) = self.structs.get_key_value(name) {
+            return Some((tag, fields));
+        }
+
+        match &self.typedefs.get(name)?.ty {
+            CTy::Struct(tag) => self
+                .structs
+                .get(tag)
+                .map(|fields| (tag.as_str(), &fields[..])),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn size_align(&self, ty: &CTy, model: DataModel) -> Option<(u32, u32)> {
+        match ty {
+            CTy::Void => Some((0, 1)),
+            CTy::Int { size, .. } | CTy::Float(size) => Some((*size, *size)),
+            CTy::Bool => Some((1, 1)),
+            CTy::Pointer => Some((model.pointer_size, model.pointer_size)),
+            CTy::Struct(tag) => {
+                let layout = self.struct_layout(tag, model)?;
+                Some((layout.size, layout.align))
+            }
+            CTy::Array { sub_ty, len } => {
+                let (size, align) = self.size_align(sub_ty, model)?;
+                Some((size * *len as u32, align))
+            }
+            CTy::Unknown => None,
+        }
+    }
+
+    /// Lays the struct out the way a C compiler would, which is `None` if any of its fields
+    /// couldn't be understood
+    pub(crate) fn struct_layout(&self, tag: &str, model: DataModel) -> Option<CStructLayout> {
+        let fields = self.structs.get(tag)?;
+
+        let mut offsets = Vec::with_capacity(fields.len());
+        let mut offset: u32 = 0;
+        let mut max_align = 1;
+
+        for field in fields {
+            let (size, align) = self.size_align(&field.ty.ty, model)?;
+
+            offset = offset.next_multiple_of(align);
+            offsets.push(offset);
+            offset += size;
+            max_align = max_align.max(align);
+        }
+
+        Some(CStructLayout {
+            size: offset.next_multiple_of(max_align),
+            align: max_align,
+            offsets,
+        })
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    let mut line_start = true;
+
+    while idx < chars.len() {
+        let ch = chars[idx];
+
+        if ch == '\n' {
+            line_start = true;
+            idx += 1;
+        } else if ch.is_whitespace() {
+            idx += 1;
+        } else if ch == '#' && line_start {
+            // preprocessor directives, which can go on for multiple lines with `\`
+            while idx < chars.len() && chars[idx] != '\n' {
+                if chars[idx] == '\\' {
+                    idx += 1;
+                }
+                idx += 1;
+            }
+        } else if chars[idx..].starts_with(&['/', '/']) {
+            while idx < chars.len() && chars[idx] != '\n' {
+                idx += 1;
+            }
+        } else if chars[idx..].starts_with(&['/', '*']) {
+            idx += 2;
+            while idx < chars.len() && !chars[idx..].starts_with(&['*', '/']) {
+                idx += 1;
+            }
+            idx += 2;
+        } else {
+            line_start = false;
+
+            let start = idx;
+            if ch.is_alphanumeric() || ch == '_' {
+                while idx < chars.len() && (chars[idx].is_alphanumeric() || chars[idx] == '_') {
+                    idx += 1;
+                }
+            } else if ch == '"' {
+                idx += 1;
+                while idx < chars.len() && chars[idx] != '"' {
+                    if chars[idx] == '\\' {
+                        idx += 1;
+                    }
+                    idx += 1;
+                }
+                idx += 1;
+            } else if chars[idx..].starts_with(&['.', '.', '.']) {
+                idx += 3;
+            } else {
+                idx += 1;
+            }
+
+            tokens.push(chars[start..idx.min(chars.len())].iter().collect());
+        }
+    }
+
+    tokens
+}
+
+fn is_ident(token: &str) -> bool {
+    token
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_alphabetic() || ch == '_')
+}
+
+/// Words which don't change the layout of a type
+const IGNORED_WORDS: &[&str] = &[
+    "const",
+    "volatile",
+    "restrict",
+    "__restrict",
+    "__restrict__",
+    "static",
+    "inline",
+    "__inline",
+    "__inline__",
+    "extern",
+    "register",
+    "__extension__",
+];
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+    model: DataModel,
+    header: Header,
+    anonymous_structs: u32,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.as_str())
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<&str> {
+        self.tokens.get(self.pos + n).map(|token| token.as_str())
+    }
+
+    fn bump(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse(&mut self) {
+        while let Some(token) = self.peek() {
+            match token {
+                ";" | "}" => self.pos += 1,
+                // `extern "C" {` from headers which are also meant for C++
+                "extern" if self.peek_nth(1).is_some_and(|next| next.starts_with('"')) => {
+                    self.pos += 2;
+                    self.eat("{");
+                }
+                _ => self.declaration(),
+            }
+        }
+    }
+
+    /// Skips to just past the next `;` which isn't inside of any brackets
+    fn skip_declaration(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.bump() {
+            match token.as_str() {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => depth -= 1,
+                ";" if depth <= 0 => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips a balanced group of brackets, if there is one
+    fn skip_group(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token {
+                "{" | "(" | "[" => depth += 1,
+                "}" | ")" | "]" => depth -= 1,
+                _ if depth == 0 => return,
+                _ => {}
+            }
+            self.pos += 1;
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    fn skip_ignored(&mut self) {
+        while let Some(token) = self.peek() {
+            if IGNORED_WORDS.contains(&token) {
+                self.pos += 1;
+            } else if matches!(token, "__attribute__" | "__declspec" | "__asm__" | "asm") {
+                self.pos += 1;
+                self.skip_group();
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn declaration(&mut self) {
+        let is_typedef = self.eat("typedef");
+
+        let Some(base) = self.base_type() else {
+            self.skip_declaration();
+            return;
+        };
+
+        loop {
+            let Some((name, ty, function)) = self.declarator(base.clone()) else {
+                self.skip_declaration();
+                return;
+            };
+            self.skip_ignored();
+
+            match (name, function) {
+                (Some(name), _) if is_typedef => {
+                    self.header.typedefs.insert(name, ty);
+                }
+                (Some(name), Some((params, variadic))) => {
+                    self.header.functions.insert(
+                        name,
+                        CFunction {
+                            params,
+                            return_ty: ty,
+                            variadic,
+                        },
+                    );
+                }
+                // global variables don't need to be checked
+                _ => {}
+            }
+
+            // the bodies of inline functions
+            if self.peek() == Some("{") {
+                self.skip_group();
+                return;
+            }
+
+            if !self.eat(",") {
+                break;
+            }
+        }
+
+        if !self.eat(";") {
+            self.skip_declaration();
+        }
+    }
+
+    fn base_type(&mut self) -> Option<CType> {
+        let mut words = Vec::new();
+
+        loop {
+            self.skip_ignored();
+
+            match self.peek()? {
+                "struct" | "union" | "enum" if words.is_empty() => return self.tagged_type(),
+                word @ ("signed" | "unsigned" | "short" | "long" | "int" | "char" | "float"
+                | "double" | "void" | "_Bool") => {
+                    words.push(word.to_string());
+                    self.pos += 1;
+                }
+                name if words.is_empty() && is_ident(name) => {
+                    let name = name.to_string();
+                    // typedefs from other headers, like `FILE`, can still be passed by pointer
+                    let ty = self
+                        .header
+                        .typedefs
+                        .get(&name)
+                        .map(|ty| ty.ty.clone())
+                        .or_else(|| builtin_typedef(&name, self.model))
+                        .unwrap_or(CTy::Unknown);
+                    self.pos += 1;
+                    return Some(CType { ty, spelling: name });
+                }
+                _ => break,
+            }
+        }
+
+        if words.is_empty() {
+            return None;
+        }
+
+        let has = |word: &str| words.iter().any(|w| w == word);
+        let longs = words.iter().filter(|w| *w == "long").count();
+        let signed = !has("unsigned");
+
+        let ty = if has("void") {
+            CTy::Void
+        } else if has("_Bool") {
+            CTy::Bool
+        } else if has("float") {
+            CTy::Float(4)
+        } else if has("double") {
+            if longs > 0 {
+                CTy::Unknown
+            } else {
+                CTy::Float(8)
+            }
+        } else if has("char") {
+            CTy::Int { size: 1, signed }
+        } else if has("short") {
+            CTy::Int { size: 2, signed }
+        } else if longs >= 2 {
+            CTy::Int { size: 8, signed }
+        } else if longs == 1 {
+            CTy::Int {
+                size: self.model.long_size,
+                signed,
+            }
+        } else {
+            CTy::Int { size: 4, signed }
+        };
+
+        Some(CType {
+            ty,
+            spelling: words.join(" "),
+        })
+    }
+
+    /// `struct Tag`, `struct Tag { ... }`, `struct { ... }` and the same for unions and enums
+    fn tagged_type(&mut self) -> Option<CType> {
+        let keyword = self.bump()?;
+        self.skip_ignored();
+
+        let tag = match self.peek() {
+            Some(tag) if is_ident(tag) => {
+                let tag = tag.to_string();
+                self.pos += 1;
+                Some(tag)
+            }
+            _ => None,
+        };
+
+        if self.peek() == Some("{") {
+            if keyword != "struct" {
+                self.skip_group();
+            } else {
+                let tag = tag.clone().unwrap_or_else(|| {
+                    self.anonymous_structs += 1;
+                    format!("(anonymous {})", self.anonymous_structs)
+                });
+                let fields = self.struct_fields();
+                self.header.structs.insert(tag.clone(), fields);
+
+                return Some(CType {
+                    ty: CTy::Struct(tag.clone()),
+                    spelling: format!("struct {tag}"),
+                });
+            }
+        } else if tag.is_none() {
+            return None;
+        }
+
+        let spelling = format!("{keyword} {}", tag.clone().unwrap_or_default());
+        let ty = match keyword.as_str() {
+            "struct" => CTy::Struct(tag.unwrap()),
+            // enums are `int`s unless they have values which don't fit
+            "enum" => CTy::Int {
+                size: 4,
+                signed: true,
+            },
+            _ => CTy::Unknown,
+        };
+
+        Some(CType { ty, spelling })
+    }
+
+    fn struct_fields(&mut self) -> Vec<CField> {
+        self.eat("{");
+
+        let mut fields = Vec::new();
+        while !self.eat("}") {
+            if self.peek().is_none() {
+                break;
+            }
+
+            let Some(base) = self.base_type() else {
+                self.skip_declaration();
+                fields.push(CField {
+                    name: String::new(),
+                    ty: CType {
+                        ty: CTy::Unknown,
+                        spelling: String::new(),
+                    },
+                });
+                continue;
+            };
+
+            loop {
+                let Some((name, mut ty, _)) = self.declarator(base.clone()) else {
+                    self.skip_declaration();
+                    break;
+                };
+
+                // bitfields share bytes with each other, which isn't worth working out
+                if self.eat(":") {
+                    self.pos += 1;
+                    ty.ty = CTy::Unknown;
+                }
+
+                fields.push(CField {
+                    name: name.unwrap_or_default(),
+                    ty,
+                });
+
+                if !self.eat(",") {
+                    self.eat(";");
+                    break;
+                }
+            }
+        }
+
+        fields
+    }
+
+    /// The pointers, name, array sizes and parameters that come after the base type.
+    /// Returns the parameters (and whether or not it's variadic) if this is a function
+    #[allow(clippy::type_complexity)]
+    fn declarator(
+        &mut self,
+        mut ty: CType,
+    ) -> Option<(Option<String>, CType, Option<(Vec<CType>, bool)>)> {
+        while self.eat("*") {
+            ty = CType {
+                ty: CTy::Pointer,
+                spelling: format!("{}*", ty.spelling),
+            };
+            self.skip_ignored();
+        }
+
+        // function pointers, like `void (*callback)(int)`
+        if self.peek() == Some("(") && self.peek_nth(1) == Some("*") {
+            self.pos += 2;
+            self.skip_ignored();
+            let name = match self.peek() {
+                Some(name) if is_ident(name) => self.bump(),
+                _ => None,
+            };
+            if !self.eat(")") {
+                return None;
+            }
+            self.skip_group();
+
+            return Some((
+                name,
+                CType {
+                    ty: CTy::Pointer,
+                    spelling: format!("{} (*)(...)", ty.spelling),
+                },
+                None,
+            ));
+        }
+
+        let name = match self.peek() {
+            Some(name) if is_ident(name) => self.bump(),
+            _ => None,
+        };
+
+        let mut lens = Vec::new();
+        while self.eat("[") {
+            if self.eat("]") {
+                lens.push(None);
+                continue;
+            }
+
+            let len = self
+                .bump()?
+                .trim_end_matches(['u', 'U', 'l', 'L'])
+                .parse::<u64>()
+                .ok();
+            if !self.eat("]") {
+                return None;
+            }
+            lens.push(len);
+        }
+        for len in lens.into_iter().rev() {
+            ty = match len {
+                Some(len) => CType {
+                    spelling: format!("{}[{len}]", ty.spelling),
+                    ty: CTy::Array {
+                        sub_ty: Box::new(ty.ty),
+                        len,
+                    },
+                },
+                // `int values[]`
+                None => CType {
+                    ty: CTy::Unknown,
+                    spelling: format!("{}[]", ty.spelling),
+                },
+            };
+        }
+
+        if self.peek() == Some("(") {
+            let (params, variadic) = self.params()?;
+            return Some((name, ty, Some((params, variadic))));
+        }
+
+        Some((name, ty, None))
+    }
+
+    fn params(&mut self) -> Option<(Vec<CType>, bool)> {
+        self.eat("(");
+
+        if self.peek() == Some("void") && self.peek_nth(1) == Some(")") {
+            self.pos += 2;
+            return Some((Vec::new(), false));
+        }
+
+        let mut params = Vec::new();
+        let mut variadic = false;
+        while !self.eat(")") {
+            if self.eat("...") {
+                variadic = true;
+                continue;
+            }
+
+            let base = self.base_type()?;
+            let (_, mut ty, _) = self.declarator(base)?;
+
+            // arrays given to functions are really just pointers
+            if let CTy::Array { .. } = ty.ty {
+                ty.ty = CTy::Pointer;
+            }
+            params.push(ty);
+
+            if !self.eat(",") && self.peek() != Some(")") {
+                return None;
+            }
+        }
+
+        Some((params, variadic))
+    }
+}
+
+/// The typedefs from `stddef.h`, `stdint.h` and `stdbool.h`, which are usually included
+/// instead of written out
+fn builtin_typedef(name: &str, model: DataModel) -> Option<CTy> {
+    let ty = match name {
+        "bool" => CTy::Bool,
+        "size_t" | "uintptr_t" => CTy::Int {
+            size: model.pointer_size,
+            signed: false,
+        },
+        "ssize_t" | "ptrdiff_t" | "intptr_t" => CTy::Int {
+            size: model.pointer_size,
+            signed: true,
+        },
+        "wchar_t" => CTy::Int {
+            size: model.wchar_size,
+            signed: model.wchar_size == 4,
+        },
+        "int8_t" => CTy::Int {
+            size: 1,
+            signed: true,
+        },
+        "uint8_t" => CTy::Int {
+            size: 1,
+            signed: false,
+        },
+        "int16_t" => CTy::Int {
+            size: 2,
+            signed: true,
+        },
+        "uint16_t" => CTy::Int {
+            size: 2,
+            signed: false,
+        },
+        "int32_t" => CTy::Int {
+            size: 4,
+            signed: true,
+        },
+        "uint32_t" => CTy::Int {
+            size: 4,
+            signed: false,
+        },
+        "int64_t" => CTy::Int {
+            size: 8,
+            signed: true,
+        },
+        "uint64_t" => CTy::Int {
+            size: 8,
+            signed: false,
+        },
+        _ => return None,
+    };
+
+    Some(ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 64-bit Linux and macOS
+    const LP64: DataModel = DataModel {
+        pointer_size: 8,
+        long_size: 8,
+        wchar_size: 4,
+    };
+    /// 64-bit Windows
+    const LLP64: DataModel = DataModel {
+        pointer_size: 8,
+        long_size: 4,
+        wchar_size: 2,
+    };
+    /// 32-bit everything
+    const ILP32: DataModel = DataModel {
+        pointer_size: 4,
+        long_size: 4,
+        wchar_size: 4,
+    };
+
+    fn field_tys(header: &Header, name: &str) -> Vec<CTy> {
+        let (_, fields) = header.struct_named(name).unwrap();
+        fields.iter().map(|field| field.ty.ty.clone()).collect()
+    }
+
+    fn param_tys(header: &Header, name: &str) -> Vec<CTy> {
+        header.functions[name]
+            .params
+            .iter()
+            .map(|param| param.ty.clone())
+            .collect()
+    }
+
+    fn int(size: u32, signed: bool) -> CTy {
+        CTy::Int { size, signed }
+    }
+
+    #[test]
+    fn typedef_struct() {
+        let header = Header::parse(
+            r#"
+                typedef struct Point {
+                    int x;
+                    int y;
+                } Point;
+
+                typedef struct Point Point2;
+            "#,
+            LP64,
+        );
+
+        let (tag, fields) = header.struct_named("Point").unwrap();
+        assert_eq!(tag, "Point");
+        assert_eq!(fields[0].name, "x");
+        assert_eq!(fields[1].name, "y");
+
+        let (tag, _) = header.struct_named("Point2").unwrap();
+        assert_eq!(tag, "Point");
+
+        let layout = header.struct_layout("Point", LP64).unwrap();
+        assert_eq!(layout.size, 8);
+        assert_eq!(layout.align, 4);
+        assert_eq!(layout.offsets, [0, 4]);
+    }
+
+    #[test]
+    fn anonymous_structs() {
+        let header = Header::parse(
+            r#"
+                typedef struct {
+                    char tag;
+                    double value;
+                } Boxed;
+
+                struct Outer {
+                    struct {
+                        char a;
+                        int b;
+                    } inner;
+                    char c;
+                };
+            "#,
+            LP64,
+        );
+
+        let (tag, _) = header.struct_named("Boxed").unwrap();
+        assert_eq!(tag, "(anonymous 1)");
+        let layout = header.struct_layout(tag, LP64).unwrap();
+        assert_eq!(layout.size, 16);
+        assert_eq!(layout.offsets, [0, 8]);
+
+        assert_eq!(
+            field_tys(&header, "Outer"),
+            [CTy::Struct("(anonymous 2)".to_string()), int(1, true)]
+        );
+        let layout = header.struct_layout("Outer", LP64).unwrap();
+        assert_eq!(layout.size, 12);
+        assert_eq!(layout.align, 4);
+        assert_eq!(layout.offsets, [0, 8]);
+    }
+
+    #[test]
+    fn function_pointers() {
+        let header = Header::parse(
+            r#"
+                typedef void (*callback)(int code);
+
+                struct Handler {
+                    int (*on_event)(void *data, int kind);
+                    callback done;
+                };
+
+                void subscribe(void (*cb)(int), void *data);
+            "#,
+            LP64,
+        );
+
+        assert_eq!(header.typedefs["callback"].ty, CTy::Pointer);
+        assert_eq!(field_tys(&header, "Handler"), [CTy::Pointer, CTy::Pointer]);
+        assert_eq!(
+            param_tys(&header, "subscribe"),
+            [CTy::Pointer, CTy::Pointer]
+        );
+        assert_eq!(header.functions["subscribe"].return_ty.ty, CTy::Void);
+    }
+
+    #[test]
+    fn arrays() {
+        let header = Header::parse(
+            r#"
+                struct Buffer {
+                    char data[16];
+                    int grid[2][3];
+                    int rest[];
+                };
+
+                void fill(int values[4], unsigned int len);
+            "#,
+            LP64,
+        );
+
+        assert_eq!(
+            field_tys(&header, "Buffer"),
+            [
+                CTy::Array {
+                    sub_ty: Box::new(int(1, true)),
+                    len: 16,
+                },
+                CTy::Array {
+                    sub_ty: Box::new(CTy::Array {
+                        sub_ty: Box::new(int(4, true)),
+                        len: 3,
+                    }),
+                    len: 2,
+                },
+                CTy::Unknown,
+            ]
+        );
+        assert_eq!(
+            header.size_align(&field_tys(&header, "Buffer")[1], LP64),
+            Some((24, 4))
+        );
+
+        // arrays given to functions are pointers
+        assert_eq!(param_tys(&header, "fill"), [CTy::Pointer, int(4, false)]);
+    }
+
+    #[test]
+    fn bitfields() {
+        let header = Header::parse(
+            r#"
+                struct Flags {
+                    unsigned int a : 1;
+                    unsigned int b : 3;
+                    int c;
+                };
+            "#,
+            LP64,
+        );
+
+        assert_eq!(
+            field_tys(&header, "Flags"),
+            [CTy::Unknown, CTy::Unknown, int(4, true)]
+        );
+        // the offsets after a bitfield can't be known
+        assert!(header.struct_layout("Flags", LP64).is_none());
+    }
+
+    #[test]
+    fn extern_c_block() {
+        let header = Header::parse(
+            r#"
+                #ifndef MATH_H
+                #define MATH_H
+
+                #ifdef __cplusplus
+                extern "C" {
+                #endif
+
+                int add(int a, int b);
+                double scale(double x, ...);
+
+                #ifdef __cplusplus
+                }
+                #endif
+
+                #endif
+            "#,
+            LP64,
+        );
+
+        assert_eq!(param_tys(&header, "add"), [int(4, true), int(4, true)]);
+        assert!(!header.functions["add"].variadic);
+        assert_eq!(param_tys(&header, "scale"), [CTy::Float(8)]);
+        assert!(header.functions["scale"].variadic);
+    }
+
+    #[test]
+    fn long_in_each_data_model() {
+        let text = r#"
+            unsigned long hash(long seed, long long extra, size_t len);
+        "#;
+
+        for (model, long_size, size_t_size) in [(LP64, 8, 8), (LLP64, 4, 8), (ILP32, 4, 4)] {
+            let header = Header::parse(text, model);
+
+            assert_eq!(
+                header.functions["hash"].return_ty.ty,
+                int(long_size, false),
+                "{model:?}"
+            );
+            assert_eq!(
+                param_tys(&header, "hash"),
+                [int(long_size, true), int(8, true), int(size_t_size, false)],
+                "{model:?}"
+            );
+        }
+    }
+}
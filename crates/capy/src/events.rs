@@ -0,0 +1,23 @@
+//! What happens during a build, for tools like build dashboards and the playground that want to
+//! follow along without scraping the output of the `capy` command
+
+use std::path::PathBuf;
+
+use diagnostics::Severity;
+
+/// The names and types within an event are already rendered to strings, since the interner
+/// they came from only lives for as long as the build does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileEvent {
+    /// The file has been parsed and lowered, and its imports have been found
+    FileParsed { file: PathBuf },
+    /// e.g. `main::foo` and `() -> i32`
+    GlobalInferred { fqn: String, ty: String },
+    /// Sent for warnings as well as errors, once every global has been inferred.
+    /// The message is rendered the same way the `capy` command would show it, without color
+    DiagnosticEmitted {
+        file: PathBuf,
+        severity: Severity,
+        message: String,
+    },
+}
@@ -0,0 +1,1533 @@
+//! The compiler as a library, for build scripts and editor plugins that want to ask it things
+//! without going through the `capy` command.
+
+mod abi;
+mod c_header;
+pub mod events;
+pub mod manifest;
+pub mod progress;
+
+use std::{
+    cell::RefCell,
+    env, fmt, fs, io, mem,
+    path::{Path, PathBuf},
+};
+
+use ast::AstNode;
+use diagnostics::{Diagnostic, Severity};
+use enum_display::EnumDisplay;
+use hir::{FQComptime, FileName, ScopeDef, WorldBodies, WorldIndex};
+use hir_ty::{ComptimeResult, InferenceResult, ProjectInference, Signature, Ty};
+use interner::Interner;
+use internment::Intern;
+use itertools::Itertools;
+use line_index::LineIndex;
+use path_clean::PathClean;
+use rustc_hash::{FxHashMap, FxHashSet};
+use target_lexicon::{OperatingSystem, Triple};
+
+use crate::{
+    abi::AbiChecker,
+    c_header::{DataModel, Header},
+    events::CompileEvent,
+    manifest::Manifest,
+    progress::Progress,
+};
+
+/// The name of the global that `Compiler::eval_const` puts the expression into.
+const EVAL_CONST_GLOBAL: &str = "__capy_eval_const";
+/// The name of the function that `Compiler::layout_of` gives a parameter of the type
+const LAYOUT_OF_GLOBAL: &str = "__capy_layout_of";
+
+pub struct Compiler {
+    mod_dir: PathBuf,
+    pointer_bit_width: u8,
+    comptime_limits: codegen::ComptimeLimits,
+    lint_member_order: bool,
+    verbose: Verbose,
+    on_progress: Option<Box<dyn Fn(Progress)>>,
+    on_event: Option<Box<dyn Fn(CompileEvent)>>,
+}
+
+/// Which files the compiler prints its working for, see `Verbose`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, EnumDisplay)]
+#[clap(rename_all = "kebab_case")]
+#[enum_display(case = "Kebab")]
+pub enum VerboseScope {
+    #[default]
+    None,
+    /// only the files outside of the mod dir
+    Local,
+    All,
+}
+
+impl VerboseScope {
+    pub fn should_show(self, is_mod: bool) -> bool {
+        match self {
+            Self::None => false,
+            Self::Local => !is_mod,
+            Self::All => true,
+        }
+    }
+
+    pub fn is_none(self) -> bool {
+        self == Self::None
+    }
+}
+
+/// What `Compiler::load` and `Compiler::infer` print as they go, for the `--verbose-*` flags
+/// of `capy`
+#[derive(Debug, Clone, Copy)]
+pub struct Verbose {
+    pub ast: VerboseScope,
+    pub hir: VerboseScope,
+    /// the types themselves are left to whoever called `infer`,
+    /// this only adds the index of each expression to the HIR so the two can be matched up
+    pub types: VerboseScope,
+    pub comptime: codegen::Verbosity,
+    pub color: bool,
+}
+
+impl Default for Verbose {
+    fn default() -> Self {
+        Self {
+            ast: VerboseScope::None,
+            hir: VerboseScope::None,
+            types: VerboseScope::None,
+            comptime: codegen::Verbosity::None,
+            color: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EvalError {
+    Io {
+        file: PathBuf,
+        why: io::Error,
+    },
+    /// The `capy.toml` of the project couldn't be parsed
+    Manifest {
+        file: PathBuf,
+        why: String,
+    },
+    /// The project (or the expression itself) has errors.
+    /// Each one is rendered the same way the `capy` command would show it, without color
+    Diagnostics(Vec<String>),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::Io { file, why } => write!(f, "{}: {why}", file.display()),
+            EvalError::Manifest { file, why } => write!(f, "{}: {why}", file.display()),
+            EvalError::Diagnostics(diagnostics) => write!(f, "{}", diagnostics.join("\n\n")),
+        }
+    }
+}
+
+/// Something which can be referred to by name at a certain position, see `Compiler::scopes_at`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeInfo {
+    pub name: String,
+    /// Rendered the same way as in error messages, e.g. `[3]i32`.
+    /// `None` if the type couldn't be figured out, which is the case for `for` items and switch
+    /// variables that are never used
+    pub ty: Option<String>,
+    pub kind: ScopeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// Includes the items of `for` loops and the variables of switch arms
+    Local,
+    Param,
+    Global,
+}
+
+/// How a type is laid out in memory, see `Compiler::layout_of`.
+///
+/// Displaying it gives a table of the offset and size of each field,
+/// along with the padding between them and the discriminant of each variant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeLayout {
+    /// Rendered the same way as in error messages, e.g. `main::Point`
+    pub name: String,
+    pub size: u32,
+    pub align: u32,
+    /// The size rounded up to the alignment, which is what C's `sizeof` gives
+    pub stride: u32,
+    /// The members of a struct or tuple, in the order they're declared
+    pub fields: Vec<FieldLayout>,
+    /// The offset of the discriminant byte if this is an enum
+    pub discriminant_offset: Option<u32>,
+    pub variants: Vec<VariantLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// The items of a tuple are named by their index
+    pub name: String,
+    pub ty: String,
+    pub offset: u32,
+    pub size: u32,
+    /// The bytes between the end of this field and the start of the next one,
+    /// or the end of the stride for the last field
+    pub padding: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantLayout {
+    pub name: String,
+    /// The type of the variant's payload
+    pub ty: String,
+    pub discriminant: u64,
+}
+
+impl fmt::Display for TypeLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        write!(
+            f,
+            "size: {}, align: {}, stride: {}",
+            self.size, self.align, self.stride
+        )?;
+
+        if !self.fields.is_empty() {
+            write!(f, "\n\n{:<8}{:<8}field", "offset", "size")?;
+            for field in &self.fields {
+                write!(
+                    f,
+                    "\n{:<8}{:<8}{}: {}",
+                    field.offset, field.size, field.name, field.ty
+                )?;
+                if field.padding > 0 {
+                    write!(f, "\n{:<8}{:<8}(padding)", "", field.padding)?;
+                }
+            }
+        }
+
+        if let Some(offset) = self.discriminant_offset {
+            write!(f, "\n\ndiscriminant at offset {offset}, 1 byte")?;
+            write!(f, "\n\n{:<8}variant", "value")?;
+            for variant in &self.variants {
+                write!(
+                    f,
+                    "\n{:<8}{}: {}",
+                    variant.discriminant, variant.name, variant.ty
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A way in which an `extern` function or a struct doesn't match its C declaration,
+/// see `Compiler::check_abi`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiMismatch {
+    pub message: String,
+    /// The file of the Capy declaration
+    pub file: PathBuf,
+    /// 1-based, like in error messages
+    pub line: u32,
+    pub col: u32,
+}
+
+impl fmt::Display for AbiMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error: {}\n  --> at {}:{}:{}",
+            self.message,
+            self.file.display(),
+            self.line,
+            self.col
+        )
+    }
+}
+
+/// The file a build started from and everything it imports, parsed and lowered.
+/// See `Compiler::load`
+pub struct Project {
+    interner: Interner,
+    world_index: WorldIndex,
+    world_bodies: WorldBodies,
+    root_file: FileName,
+    files: Vec<LoadedFile>,
+    file_idxs: FxHashMap<FileName, usize>,
+    /// the `capy.toml` of the package that the root file is in
+    manifest: Option<Manifest>,
+    any_were_unsafe_to_compile: bool,
+}
+
+impl Project {
+    pub fn interner(&self) -> &Interner {
+        &self.interner
+    }
+
+    pub fn world_index(&self) -> &WorldIndex {
+        &self.world_index
+    }
+
+    pub fn world_bodies(&self) -> &WorldBodies {
+        &self.world_bodies
+    }
+
+    pub fn root_file(&self) -> FileName {
+        self.root_file
+    }
+
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    pub fn file(&self, file: FileName) -> &LoadedFile {
+        &self.files[self.file_idxs[&file]]
+    }
+
+    /// The root file comes first, then all of the files it imports by their path
+    pub fn files(&self) -> impl Iterator<Item = &LoadedFile> {
+        self.files
+            .iter()
+            .sorted_by_key(|file| (file.module != self.root_file, &file.file_name))
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.files.iter().any(|file| {
+            file.diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.severity() == Severity::Error)
+        })
+    }
+
+    /// Whether any comptime block wasn't evaluated because it couldn't safely be compiled,
+    /// which is only tracked while `Verbose::types` is set
+    pub fn any_were_unsafe_to_compile(&self) -> bool {
+        self.any_were_unsafe_to_compile
+    }
+
+    /// Finds the global named `name` that the program should start from
+    pub fn entry_point(&mut self, name: &str) -> Result<hir::Fqn, hir::EntryPointError> {
+        self.world_index
+            .entry_point(hir::Name(self.interner.intern(name)))
+    }
+}
+
+pub struct LoadedFile {
+    module: FileName,
+    file_name: PathBuf,
+    contents: String,
+    is_mod: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl LoadedFile {
+    pub fn module(&self) -> FileName {
+        self.module
+    }
+
+    pub fn file_name(&self) -> &Path {
+        &self.file_name
+    }
+
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Whether the file is in the mod dir, rather than being one of the user's own
+    pub fn is_mod(&self) -> bool {
+        self.is_mod
+    }
+
+    /// The problems found while loading the file, then those found while inferring it
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl Compiler {
+    /// `mod_dir` is where `#mod` looks for modules like `core`
+    pub fn new(mod_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            mod_dir: mod_dir.into(),
+            pointer_bit_width: Triple::host().pointer_width().unwrap().bits(),
+            comptime_limits: codegen::ComptimeLimits::default(),
+            lint_member_order: false,
+            verbose: Verbose::default(),
+            on_progress: None,
+            on_event: None,
+        }
+    }
+
+    /// The pointers of comptime blocks are the size that they are on the target,
+    /// which is the host unless this is called
+    pub fn with_target(mut self, target: &Triple) -> Self {
+        self.pointer_bit_width = target.pointer_width().unwrap().bits();
+        self
+    }
+
+    /// Warns about struct literals which give their members in a different order
+    /// than the struct declares them
+    pub fn with_lint_member_order(mut self, lint_member_order: bool) -> Self {
+        self.lint_member_order = lint_member_order;
+        self
+    }
+
+    pub fn with_verbose(mut self, verbose: Verbose) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// How long each comptime block can run for and how much it can allocate,
+    /// the same as the `[comptime]` section of a `capy.toml`
+    pub fn with_comptime_limits(mut self, limits: codegen::ComptimeLimits) -> Self {
+        self.comptime_limits = limits;
+        self
+    }
+
+    /// Called as files are parsed and globals are inferred, e.g. to show the status of a build
+    /// in an editor
+    pub fn with_progress(mut self, on_progress: impl Fn(Progress) + 'static) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Called with each `CompileEvent` as it happens
+    pub fn with_events(mut self, on_event: impl Fn(CompileEvent) + 'static) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+
+    fn report(&self, progress: Progress) {
+        if let Some(on_progress) = &self.on_progress {
+            on_progress(progress);
+        }
+    }
+
+    /// Evaluates the expression as if it were in a `comptime` block at the end of the given file,
+    /// so it can refer to anything that the file can, e.g. `CONFIG.port`.
+    ///
+    /// Only the file and what it imports get compiled, the program itself is never run.
+    /// The names within a `ComptimeResult::Type` belong to an interner that only lives for as
+    /// long as this call does, so only the structure of a type can be relied on.
+    pub fn eval_const(
+        &self,
+        file: impl AsRef<Path>,
+        expr_text: &str,
+    ) -> Result<ComptimeResult, EvalError> {
+        self.eval_const_with(file.as_ref(), expr_text, |result, _, _| result)
+    }
+
+    /// The same as `eval_const`, but shows the result the way it would be written in Capy,
+    /// e.g. `Point.{ x = 1, y = 2 }`
+    pub fn eval_const_display(
+        &self,
+        file: impl AsRef<Path>,
+        expr_text: &str,
+    ) -> Result<String, EvalError> {
+        self.eval_const_with(file.as_ref(), expr_text, |result, ty, interner| {
+            result.display(ty, &self.mod_dir, interner)
+        })
+    }
+
+    /// `finish` gets called while the interner is still around
+    fn eval_const_with<T>(
+        &self,
+        file: &Path,
+        expr_text: &str,
+        finish: impl FnOnce(ComptimeResult, Intern<Ty>, &Interner) -> T,
+    ) -> Result<T, EvalError> {
+        let (file_name, mut contents) = read_root(file)?;
+        // the newline makes sure a line comment at the end of the file doesn't swallow this
+        contents.push_str(&format!(
+            "\n{EVAL_CONST_GLOBAL} :: comptime {{ {expr_text} }};\n"
+        ));
+
+        let emit = |event| self.emit(event);
+
+        let mut project = self.load_source(file_name, contents, &emit)?;
+        let mut comptime_results = FxHashMap::<FQComptime, ComptimeResult>::default();
+        let tys = self.infer_with(&mut project, None, &mut comptime_results, &emit);
+
+        let errors = self.render_errors(&project.files, &project.interner);
+        if !errors.is_empty() {
+            return Err(EvalError::Diagnostics(errors));
+        }
+
+        let root_file = project.root_file;
+        let body = project.world_bodies.body(hir::Fqn {
+            file: root_file,
+            name: hir::Name(project.interner.intern(EVAL_CONST_GLOBAL)),
+        });
+        let hir::Expr::Comptime(comptime) = project.world_bodies[root_file][body] else {
+            unreachable!("the expression was put inside of a comptime block")
+        };
+        let comptime = FQComptime {
+            file: root_file,
+            expr: body,
+            comptime,
+        };
+
+        if let Err((file, diagnostic)) =
+            self.eval_comptime_blocks(&project, &tys, vec![comptime], &mut comptime_results)
+        {
+            let idx = project.file_idxs[&file];
+            project.files[idx].diagnostics.push(diagnostic);
+
+            return Err(EvalError::Diagnostics(
+                self.render_errors(&project.files, &project.interner),
+            ));
+        }
+
+        let ty = tys[root_file][body];
+        Ok(finish(
+            comptime_results.remove(&comptime).unwrap(),
+            ty,
+            &project.interner,
+        ))
+    }
+
+    /// Parses and type checks the given file and everything it imports, without compiling
+    /// or running any of it (other than `comptime` blocks which give a type).
+    ///
+    /// Returns everything that happened along the way, in order.
+    /// The events are also passed to `with_events` as they happen.
+    /// Problems with the code itself are `CompileEvent::DiagnosticEmitted`s,
+    /// so only a file which can't be read is an `Err`
+    pub fn check(
+        &self,
+        file: impl AsRef<Path>,
+    ) -> Result<impl Iterator<Item = CompileEvent>, EvalError> {
+        let (file_name, contents) = read_root(file.as_ref())?;
+
+        let events = RefCell::new(Vec::new());
+        let emit = |event: CompileEvent| {
+            self.emit(event.clone());
+            events.borrow_mut().push(event);
+        };
+
+        let mut project = self.load_source(file_name, contents, &emit)?;
+        self.infer_with(&mut project, None, &mut FxHashMap::default(), &emit);
+
+        Ok(events.into_inner().into_iter())
+    }
+
+    /// Everything that can be referred to by name at the given byte offset into the file,
+    /// innermost first: locals, then parameters, then the globals of the file.
+    /// Names which are shadowed at that position are left out.
+    ///
+    /// This is meant for completions and the like, so the file doesn't have to be free of errors
+    pub fn scopes_at(
+        &self,
+        file: impl AsRef<Path>,
+        offset: u32,
+    ) -> Result<Vec<ScopeInfo>, EvalError> {
+        let (file_name, contents) = read_root(file.as_ref())?;
+
+        let emit = |event| self.emit(event);
+
+        let mut project = self.load_source(file_name, contents, &emit)?;
+        let tys = self.infer_with(&mut project, None, &mut FxHashMap::default(), &emit);
+
+        let Project {
+            interner,
+            world_index,
+            world_bodies,
+            root_file,
+            ..
+        } = &project;
+
+        let render = |ty: &Ty| ty.display(&self.mod_dir, interner);
+
+        let mut scopes = world_bodies[*root_file]
+            .scopes_at(offset.into())
+            .into_iter()
+            .map(|(name, def)| ScopeInfo {
+                name: interner.lookup(name.0).to_string(),
+                ty: tys[*root_file].get_scope_def_ty(def).map(|ty| render(&ty)),
+                kind: match def {
+                    ScopeDef::Param { .. } => ScopeKind::Param,
+                    _ => ScopeKind::Local,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut globals = world_index
+            .get_file(*root_file)
+            .unwrap()
+            .definitions()
+            .map(|name| (name, interner.lookup(name.0)))
+            // declarations within structs and modules can't be referred to by a bare name
+            .filter(|(_, text)| !text.contains('.'))
+            .filter(|(_, text)| !scopes.iter().any(|scope| scope.name == *text))
+            .map(|(name, text)| ScopeInfo {
+                name: text.to_string(),
+                ty: tys
+                    .get_signature(hir::Fqn {
+                        file: *root_file,
+                        name,
+                    })
+                    .map(|Signature(ty)| render(&ty)),
+                kind: ScopeKind::Global,
+            })
+            .collect::<Vec<_>>();
+        globals.sort_by(|a, b| a.name.cmp(&b.name));
+        scopes.extend(globals);
+
+        Ok(scopes)
+    }
+
+    /// The size, alignment, field offsets, padding and enum discriminants of a type,
+    /// as the compiler would lay it out for the host.
+    ///
+    /// `type_text` can be anything the file can refer to which gives a type, e.g. `Point`
+    /// or `net.Header`
+    pub fn layout_of(
+        &self,
+        file: impl AsRef<Path>,
+        type_text: &str,
+    ) -> Result<TypeLayout, EvalError> {
+        let (file_name, mut contents) = read_root(file.as_ref())?;
+        // the type is given to a parameter so that it gets resolved like any other annotation,
+        // which keeps the names of structs and enums around
+        contents.push_str(&format!(
+            "\n{LAYOUT_OF_GLOBAL} :: (value: {type_text}) {{}};\n"
+        ));
+
+        let emit = |event| self.emit(event);
+
+        let mut project = self.load_source(file_name, contents, &emit)?;
+        let tys = self.infer_with(&mut project, None, &mut FxHashMap::default(), &emit);
+
+        let errors = self.render_errors(&project.files, &project.interner);
+        if !errors.is_empty() {
+            return Err(EvalError::Diagnostics(errors));
+        }
+
+        let Project {
+            interner,
+            root_file,
+            ..
+        } = &project;
+
+        let Signature(signature) = tys[hir::Fqn {
+            file: *root_file,
+            name: hir::Name(interner.get(LAYOUT_OF_GLOBAL).unwrap()),
+        }];
+        let (params, _) = signature.as_function().unwrap();
+        let ty = params[0].ty;
+
+        let render = |ty: Intern<Ty>| ty.display(&self.mod_dir, interner);
+
+        let layout = codegen::layout_of(ty, self.pointer_bit_width as u32);
+
+        Ok(TypeLayout {
+            name: render(ty),
+            size: layout.size,
+            align: layout.align,
+            stride: layout.stride,
+            fields: layout
+                .fields
+                .into_iter()
+                .enumerate()
+                .map(|(idx, field)| FieldLayout {
+                    name: match field.name {
+                        Some(name) => interner.lookup(name.0).to_string(),
+                        None => idx.to_string(),
+                    },
+                    ty: render(field.ty),
+                    offset: field.offset,
+                    size: field.size,
+                    padding: field.padding,
+                })
+                .collect(),
+            discriminant_offset: layout.discriminant_offset,
+            variants: layout
+                .variants
+                .into_iter()
+                .map(|variant| VariantLayout {
+                    name: interner.lookup(variant.name.0).to_string(),
+                    ty: render(variant.ty),
+                    discriminant: variant.discriminant,
+                })
+                .collect(),
+        })
+    }
+
+    /// Checks the `extern` functions and the structs of the given file against the function
+    /// prototypes and structs with the same names in a C header, returning every parameter,
+    /// return type and field whose size, offset or kind doesn't match.
+    ///
+    /// Types are laid out for the host. The header isn't preprocessed, so anything behind a
+    /// macro is left unchecked, along with unions and bitfields
+    pub fn check_abi(
+        &self,
+        file: impl AsRef<Path>,
+        header: impl AsRef<Path>,
+    ) -> Result<Vec<AbiMismatch>, EvalError> {
+        let header_text = fs::read_to_string(header.as_ref()).map_err(|why| EvalError::Io {
+            file: header.as_ref().to_path_buf(),
+            why,
+        })?;
+
+        let pointer_size = self.pointer_bit_width as u32 / 8;
+        let model = DataModel {
+            pointer_size,
+            long_size: if Triple::host().operating_system == OperatingSystem::Windows {
+                4
+            } else {
+                pointer_size
+            },
+            wchar_size: if Triple::host().operating_system == OperatingSystem::Windows {
+                2
+            } else {
+                4
+            },
+        };
+        let header = Header::parse(&header_text, model);
+
+        let (file_name, contents) = read_root(file.as_ref())?;
+
+        let emit = |event| self.emit(event);
+
+        let mut project = self.load_source(file_name, contents, &emit)?;
+        let tys = self.infer_with(&mut project, None, &mut FxHashMap::default(), &emit);
+
+        let errors = self.render_errors(&project.files, &project.interner);
+        if !errors.is_empty() {
+            return Err(EvalError::Diagnostics(errors));
+        }
+
+        let Project {
+            interner,
+            world_index,
+            world_bodies,
+            root_file,
+            files,
+            file_idxs,
+            ..
+        } = &project;
+
+        let checker = AbiChecker {
+            header: &header,
+            model,
+            mod_dir: &self.mod_dir,
+            interner,
+        };
+
+        let root = &files[file_idxs[root_file]];
+        let line_index = LineIndex::new(&root.contents);
+
+        let mut mismatches = Vec::new();
+        for name in world_index.get_file(*root_file).unwrap().definitions() {
+            let text = interner.lookup(name.0);
+            let fqn = hir::Fqn {
+                file: *root_file,
+                name,
+            };
+
+            let found = if let Some(c_function) = header
+                .functions
+                .get(text)
+                .filter(|_| is_c_function(world_bodies, fqn))
+            {
+                let Some((params, return_ty)) = tys[fqn].0.as_function() else {
+                    continue;
+                };
+                let param_tys: Vec<_> = params.iter().map(|param| param.ty).collect();
+
+                checker.check_function(text, &param_tys, return_ty, c_function)
+            } else if header.struct_named(text).is_some() {
+                // structs are only around as types, so the one with this name has to be found
+                let Some(ty) = tys.all_tys().find(|ty| {
+                    matches!(ty.as_ref(), Ty::Struct { fqn: Some(ty_fqn), .. } if *ty_fqn == fqn)
+                }) else {
+                    continue;
+                };
+
+                checker.check_struct(text, ty, text)
+            } else {
+                continue;
+            };
+
+            let (line, col) = line_index.line_col(world_index.range_info(fqn).name.start());
+            mismatches.extend(found.into_iter().map(|message| AbiMismatch {
+                message,
+                file: root.file_name.clone(),
+                line: line.0 + 1,
+                col: col.0 + 1,
+            }));
+        }
+
+        mismatches.sort_by_key(|mismatch| (mismatch.line, mismatch.col));
+
+        Ok(mismatches)
+    }
+
+    /// Parses and lowers the given file and everything it imports, which is all that's needed
+    /// to find the entry point before calling `infer`
+    pub fn load(&self, file: impl AsRef<Path>) -> Result<Project, EvalError> {
+        let (file_name, contents) = read_root(file.as_ref())?;
+
+        self.load_source(file_name, contents, &|event| self.emit(event))
+    }
+
+    /// Parses and lowers the file and everything it imports
+    fn load_source(
+        &self,
+        file_name: PathBuf,
+        contents: String,
+        emit: &dyn Fn(CompileEvent),
+    ) -> Result<Project, EvalError> {
+        let manifest = Manifest::find(file_name.parent().unwrap())?;
+
+        let mut interner = Interner::default();
+        let mut world_index = WorldIndex::default();
+        let mut world_bodies = WorldBodies::default();
+
+        let root_file = FileName(interner.intern(&file_name.to_string_lossy()));
+
+        let mut files = Vec::<LoadedFile>::new();
+        let mut file_idxs = FxHashMap::default();
+        let mut seen = FxHashSet::from_iter([root_file]);
+        let mut current_files = vec![(file_name, contents)];
+
+        let Verbose {
+            ast: verbose_ast,
+            hir: verbose_hir,
+            types: verbose_types,
+            color,
+            ..
+        } = self.verbose;
+
+        while !current_files.is_empty() {
+            for (file_name, contents) in mem::take(&mut current_files) {
+                let module = FileName(interner.intern(&file_name.to_string_lossy()));
+                let is_mod = module.is_mod(&self.mod_dir, &interner);
+
+                if verbose_hir.should_show(is_mod) || verbose_ast.should_show(is_mod) {
+                    println!("=== {} ===\n", file_name.display());
+                }
+
+                let parse = parser::parse_source_file(&lexer::lex(&contents), &contents);
+                if verbose_ast.should_show(is_mod) {
+                    println!("{:?}\n", parse);
+                }
+
+                let tree = parse.syntax_tree();
+                let root = ast::Root::cast(tree.root(), tree).unwrap();
+
+                let validation_diagnostics = ast::validation::validate(root, tree);
+                let (index, indexing_diagnostics) = hir::index(root, tree, &mut interner);
+                let (bodies, lowering_diagnostics) = hir::lower(
+                    root,
+                    tree,
+                    &file_name,
+                    &index,
+                    &mut interner,
+                    &self.mod_dir,
+                    false,
+                );
+
+                if verbose_hir.should_show(is_mod) {
+                    let debug = bodies.debug(
+                        module,
+                        &self.mod_dir,
+                        &interner,
+                        color,
+                        verbose_types.should_show(is_mod),
+                    );
+                    if !debug.is_empty() {
+                        println!("{}", debug);
+                    }
+                }
+
+                let diagnostics = parse
+                    .errors()
+                    .iter()
+                    .cloned()
+                    .map(Diagnostic::from_syntax)
+                    .chain(
+                        validation_diagnostics
+                            .into_iter()
+                            .map(Diagnostic::from_validation),
+                    )
+                    .chain(
+                        indexing_diagnostics
+                            .into_iter()
+                            .map(Diagnostic::from_indexing),
+                    )
+                    .chain(
+                        lowering_diagnostics
+                            .into_iter()
+                            .map(Diagnostic::from_lowering),
+                    )
+                    .collect();
+
+                for import in bodies.imports() {
+                    if !seen.insert(*import) {
+                        continue;
+                    }
+
+                    let import_name = PathBuf::from(interner.lookup(import.0));
+                    let contents =
+                        fs::read_to_string(&import_name).map_err(|why| EvalError::Io {
+                            file: import_name.clone(),
+                            why,
+                        })?;
+                    current_files.push((import_name, contents));
+                }
+
+                world_index.add_file(module, index);
+                world_bodies.add_file(module, bodies);
+                file_idxs.insert(module, files.len());
+                emit(CompileEvent::FileParsed {
+                    file: file_name.clone(),
+                });
+                files.push(LoadedFile {
+                    module,
+                    file_name,
+                    contents,
+                    is_mod,
+                    diagnostics,
+                });
+                self.report(Progress::Parsing { files: files.len() });
+            }
+        }
+
+        Ok(Project {
+            interner,
+            world_index,
+            world_bodies,
+            root_file,
+            files,
+            file_idxs,
+            manifest,
+            any_were_unsafe_to_compile: false,
+        })
+    }
+
+    /// Infers the types of every global, evaluating the comptime blocks that are needed along
+    /// the way. The type diagnostics are added to the files they're in.
+    ///
+    /// `entry_point` gets checked to have the signature of a `main` function
+    pub fn infer(
+        &self,
+        project: &mut Project,
+        entry_point: Option<hir::Fqn>,
+        comptime_results: &mut FxHashMap<FQComptime, ComptimeResult>,
+    ) -> ProjectInference {
+        self.infer_with(project, entry_point, comptime_results, &|event| {
+            self.emit(event)
+        })
+    }
+
+    /// Infers the types of every global, then adds the type diagnostics to the files they're in
+    /// and emits every diagnostic of every file.
+    ///
+    /// The `[lints]` of the `capy.toml` are applied first, so the severities are the same as
+    /// what `capy build` would show
+    fn infer_with(
+        &self,
+        project: &mut Project,
+        entry_point: Option<hir::Fqn>,
+        comptime_results: &mut FxHashMap<FQComptime, ComptimeResult>,
+        emit: &dyn Fn(CompileEvent),
+    ) -> ProjectInference {
+        let Project {
+            interner,
+            world_index,
+            world_bodies,
+            files,
+            file_idxs,
+            manifest,
+            any_were_unsafe_to_compile,
+            ..
+        } = project;
+
+        let comptime_verbosity = self.verbose.comptime;
+
+        let InferenceResult {
+            tys,
+            diagnostics: ty_diagnostics,
+            any_were_unsafe_to_compile: any_unsafe,
+        } = hir_ty::InferenceCtx::new(world_index, world_bodies, interner, |comptime, tys| {
+            if let Some(result) = comptime_results.get(&comptime) {
+                return Ok(result.clone());
+            }
+
+            if comptime_verbosity.should_show(comptime.file.is_mod(&self.mod_dir, interner)) {
+                println!("comptime JIT:\n");
+            }
+
+            codegen::eval_comptime_blocks(
+                comptime_verbosity,
+                vec![comptime],
+                comptime_results,
+                &self.mod_dir,
+                interner,
+                world_bodies,
+                tys,
+                self.pointer_bit_width,
+                self.comptime_limits,
+            )
+            .map(|()| comptime_results[&comptime].clone())
+        })
+        .lint_member_order(self.lint_member_order)
+        .on_progress(|progress| self.report(Progress::Inferring(progress)))
+        .on_global(|fqn, ty| {
+            if matches!(
+                interner.lookup(fqn.name.0),
+                EVAL_CONST_GLOBAL | LAYOUT_OF_GLOBAL
+            ) {
+                return;
+            }
+
+            emit(CompileEvent::GlobalInferred {
+                fqn: fqn.to_string(&self.mod_dir, interner),
+                ty: ty.display(&self.mod_dir, interner),
+            });
+        })
+        .finish(entry_point, !self.verbose.types.is_none());
+
+        *any_were_unsafe_to_compile = any_unsafe;
+
+        for diagnostic in ty_diagnostics {
+            if let Some(idx) = file_idxs.get(&diagnostic.file) {
+                files[*idx]
+                    .diagnostics
+                    .push(Diagnostic::from_ty(diagnostic));
+            }
+        }
+
+        if let Some(manifest) = manifest {
+            for file in files.iter_mut() {
+                // the lints only apply to the user's own files, the mod dir is left alone
+                if !file.is_mod {
+                    manifest.apply_lints(&mut file.diagnostics);
+                }
+            }
+        }
+
+        // in the same order as `capy build` shows them
+        for file in project.files() {
+            for diagnostic in &file.diagnostics {
+                emit(CompileEvent::DiagnosticEmitted {
+                    file: file.file_name.clone(),
+                    severity: diagnostic.severity(),
+                    message: self.render(file, diagnostic, &project.interner),
+                });
+            }
+        }
+
+        tys
+    }
+
+    /// Evaluates the given comptime blocks, other than those which already have a result.
+    ///
+    /// A block which goes over one of its limits is given back as a diagnostic,
+    /// along with the file it's in
+    pub fn eval_comptime_blocks(
+        &self,
+        project: &Project,
+        tys: &ProjectInference,
+        comptimes: Vec<FQComptime>,
+        comptime_results: &mut FxHashMap<FQComptime, ComptimeResult>,
+    ) -> Result<(), (FileName, Diagnostic)> {
+        codegen::eval_comptime_blocks(
+            self.verbose.comptime,
+            comptimes,
+            comptime_results,
+            &self.mod_dir,
+            &project.interner,
+            &project.world_bodies,
+            tys,
+            self.pointer_bit_width,
+            self.comptime_limits,
+        )
+        .map_err(|exceeded| {
+            let file = exceeded.comptime.file;
+            let diagnostic = Diagnostic::from_ty(hir_ty::TyDiagnostic {
+                kind: hir_ty::TyDiagnosticKind::ComptimeLimitExceeded {
+                    limit: exceeded.limit,
+                },
+                file,
+                expr: Some(exceeded.comptime.expr),
+                range: project.world_bodies[file].range_for_expr(exceeded.comptime.expr),
+                help: None,
+            });
+
+            (file, diagnostic)
+        })
+    }
+
+    fn emit(&self, event: CompileEvent) {
+        if let Some(on_event) = &self.on_event {
+            on_event(event);
+        }
+    }
+
+    /// Each error is rendered the same way the `capy` command would show it, without color
+    fn render_errors(&self, files: &[LoadedFile], interner: &Interner) -> Vec<String> {
+        files
+            .iter()
+            .flat_map(|file| {
+                file.diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity() == Severity::Error)
+                    .map(move |diagnostic| self.render(file, diagnostic, interner))
+            })
+            .collect()
+    }
+
+    fn render(&self, file: &LoadedFile, diagnostic: &Diagnostic, interner: &Interner) -> String {
+        diagnostic
+            .display(
+                &file.file_name.to_string_lossy(),
+                &file.contents,
+                &self.mod_dir,
+                interner,
+                &LineIndex::new(&file.contents),
+                false,
+                None,
+            )
+            .join("\n")
+    }
+}
+
+/// `extern` functions, and functions which are exported to be called from C
+fn is_c_function(world_bodies: &WorldBodies, fqn: hir::Fqn) -> bool {
+    let bodies = &world_bodies[fqn.file];
+    match bodies[world_bodies.body(fqn)] {
+        hir::Expr::Lambda(lambda) => {
+            bodies[lambda].is_extern || bodies[lambda].export_name.is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Reads the file that a build starts from, after making its path absolute
+fn read_root(file: &Path) -> Result<(PathBuf, String), EvalError> {
+    let file_name = env::current_dir()
+        .map_err(|why| EvalError::Io {
+            file: file.to_path_buf(),
+            why,
+        })?
+        .join(file)
+        .clean();
+
+    let contents = fs::read_to_string(&file_name).map_err(|why| EvalError::Io {
+        file: file_name.clone(),
+        why,
+    })?;
+
+    Ok((file_name, contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::expect;
+
+    use super::*;
+
+    /// Each test gets a directory of its own, since tests run at the same time
+    fn write_file(test_name: &str, contents: &str) -> PathBuf {
+        let dir = env::temp_dir().join("capy_lib_tests").join(test_name);
+        fs::create_dir_all(&dir).unwrap();
+
+        let file = dir.join("main.capy");
+        fs::write(&file, contents).unwrap();
+        file
+    }
+
+    fn compiler() -> Compiler {
+        Compiler::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("../..").clean())
+    }
+
+    const CONFIG: &str = r#"
+        Config :: struct {
+            port: u16,
+            name: str,
+        };
+
+        CONFIG :: Config.{
+            port = 8080,
+            name = "server",
+        };
+    "#;
+
+    #[test]
+    fn eval_const() {
+        let file = write_file("eval_const", CONFIG);
+
+        let result = compiler().eval_const(&file, "CONFIG.port + 1").unwrap();
+        assert!(matches!(
+            result,
+            ComptimeResult::Integer {
+                num: 8081,
+                bit_width: 16
+            }
+        ));
+
+        expect![[r#"Config.{ port = 8080, name = "server" }"#]]
+            .assert_eq(&compiler().eval_const_display(&file, "CONFIG").unwrap());
+    }
+
+    #[test]
+    fn eval_const_with_errors() {
+        let file = write_file("eval_const_with_errors", CONFIG);
+
+        let Err(EvalError::Diagnostics(errors)) = compiler().eval_const(&file, "CONFIG.host")
+        else {
+            panic!("`CONFIG.host` should be an error");
+        };
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("host"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn check_events() {
+        let file = write_file(
+            "check_events",
+            r#"
+                answer :: () -> i32 { 42 }
+
+                main :: () {
+                    x : bool = answer();
+                }
+            "#,
+        );
+
+        let seen = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let compiler = compiler().with_events({
+            let seen = seen.clone();
+            move |event| seen.borrow_mut().push(event)
+        });
+        let events: Vec<_> = compiler.check(&file).unwrap().collect();
+
+        // the events passed to `with_events` are the same as the ones given back
+        assert_eq!(events, *seen.borrow());
+
+        assert_eq!(events.len(), 4, "{events:#?}");
+        assert_eq!(events[0], CompileEvent::FileParsed { file: file.clone() });
+
+        // the module of a file is named after its path, which is different on every machine
+        let mut globals: Vec<_> = events[1..3]
+            .iter()
+            .map(|event| match event {
+                CompileEvent::GlobalInferred { fqn, ty } => {
+                    format!("{} : {ty}", fqn.rsplit('.').next().unwrap())
+                }
+                _ => panic!("expected a global to be inferred, found {event:?}"),
+            })
+            .collect();
+        globals.sort();
+        assert_eq!(
+            globals,
+            ["main::answer : () -> i32", "main::main : () -> void"]
+        );
+
+        let CompileEvent::DiagnosticEmitted {
+            file: diagnostic_file,
+            severity,
+            message,
+        } = &events[3]
+        else {
+            panic!("expected a diagnostic, found {:?}", events[3]);
+        };
+        assert_eq!(*diagnostic_file, file);
+        assert_eq!(*severity, Severity::Error);
+        assert!(
+            message.contains("expected `bool` but found `i32`"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn check_uses_manifest_lints() {
+        let file = write_file(
+            "check_uses_manifest_lints",
+            r#"
+                main :: () {
+                    x := 5;
+                    y := x == x;
+                    z := x != x;
+                }
+            "#,
+        );
+        fs::write(
+            file.with_file_name("capy.toml"),
+            r#"
+                [lints]
+                self_comparison = "deny"
+            "#,
+        )
+        .unwrap();
+
+        // the same severities that `capy build` would give
+        let severities: Vec<_> = compiler()
+            .check(&file)
+            .unwrap()
+            .filter_map(|event| match event {
+                CompileEvent::DiagnosticEmitted { severity, .. } => Some(severity),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(severities, [Severity::Error, Severity::Error]);
+
+        fs::write(
+            file.with_file_name("capy.toml"),
+            r#"
+                [lints]
+                self_comparison = "allow"
+            "#,
+        )
+        .unwrap();
+
+        let events: Vec<_> = compiler().check(&file).unwrap().collect();
+        assert!(
+            !events
+                .iter()
+                .any(|event| matches!(event, CompileEvent::DiagnosticEmitted { .. })),
+            "{events:#?}"
+        );
+    }
+
+    /// The messages of the errors that `check` found in `contents`.
+    ///
+    /// `check` only evaluates the comptime blocks it needs the value of, so the tests below use them as types
+    fn check_errors(compiler: Compiler, test_name: &str, contents: &str) -> Vec<String> {
+        let file = write_file(test_name, contents);
+
+        compiler
+            .check(&file)
+            .unwrap()
+            .filter_map(|event| match event {
+                CompileEvent::DiagnosticEmitted {
+                    severity: Severity::Error,
+                    message,
+                    ..
+                } => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn comptime_time_limit() {
+        let compiler = compiler().with_comptime_limits(codegen::ComptimeLimits {
+            time: std::time::Duration::from_millis(100),
+            ..Default::default()
+        });
+
+        // the block gets stopped, so this returns instead of hanging
+        let errors = check_errors(
+            compiler,
+            "comptime_time_limit",
+            r#"
+                Forever :: comptime {
+                    x := 0;
+                    while true {
+                        x = x + 1;
+                    }
+                    i32
+                };
+
+                answer : Forever : 42;
+            "#,
+        );
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert!(
+            errors[0].contains("ran for longer than 0.1s"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn comptime_memory_is_freed() {
+        let compiler = compiler().with_comptime_limits(codegen::ComptimeLimits {
+            memory: 64 * 1024 * 1024,
+            ..Default::default()
+        });
+
+        // this allocates 100 * 16 MiB, but never more than 16 MiB at once
+        let errors = check_errors(
+            compiler,
+            "comptime_memory_is_freed",
+            r#"
+                libc :: #mod("core").libc;
+
+                Int :: comptime {
+                    buf := libc.malloc(1024);
+                    i := 0;
+                    while i < 100 {
+                        buf = libc.realloc(buf, 16 * 1024 * 1024);
+                        libc.free(buf);
+                        buf = libc.malloc(1024);
+                        i = i + 1;
+                    }
+                    libc.free(buf);
+                    i32
+                };
+
+                answer : Int : 42;
+            "#,
+        );
+        assert_eq!(errors, Vec::<String>::new());
+    }
+
+    #[test]
+    fn comptime_exit() {
+        let errors = check_errors(
+            compiler(),
+            "comptime_exit",
+            r#"
+                core :: #mod("core");
+
+                Checked :: comptime {
+                    core.assert(1 == 2);
+                    i32
+                };
+
+                answer : Checked : 42;
+            "#,
+        );
+        assert_eq!(errors.len(), 1, "{errors:#?}");
+        assert!(errors[0].contains("this called `exit(1)`"), "{}", errors[0]);
+    }
+
+    const SHAPES: &str = r#"
+        Header :: struct {
+            tag: u8,
+            len: u32,
+            flags: u16,
+        };
+
+        Shape :: enum {
+            Circle: f32,
+            Rect: [2]u16,
+            Empty,
+        };
+    "#;
+
+    fn check_layout(test_name: &str, type_text: &str, expect: expect_test::Expect) {
+        let file = write_file(test_name, SHAPES);
+        let layout = compiler().layout_of(&file, type_text).unwrap();
+
+        // the module of a file is named after its path, which is different on every machine
+        let (module, _) = layout.name.rsplit_once('.').unwrap();
+        expect.assert_eq(&layout.to_string().replace(&format!("{module}."), ""));
+    }
+
+    #[test]
+    fn struct_layout() {
+        check_layout(
+            "struct_layout",
+            "Header",
+            expect![[r#"
+                main::Header
+                size: 10, align: 4, stride: 12
+
+                offset  size    field
+                0       1       tag: u8
+                        3       (padding)
+                4       4       len: u32
+                8       2       flags: u16
+                        2       (padding)"#]],
+        );
+    }
+
+    #[test]
+    fn enum_layout() {
+        check_layout(
+            "enum_layout",
+            "Shape",
+            expect![[r#"
+                main::Shape
+                size: 5, align: 4, stride: 8
+
+                discriminant at offset 4, 1 byte
+
+                value   variant
+                0       Circle: f32
+                1       Rect: [2]u16
+                2       Empty: void"#]],
+        );
+    }
+
+    #[test]
+    fn layout_of_unknown_type() {
+        let file = write_file("layout_of_unknown_type", SHAPES);
+
+        let Err(EvalError::Diagnostics(errors)) = compiler().layout_of(&file, "Triangle") else {
+            panic!("`Triangle` isn't defined");
+        };
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Triangle"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn eval_const_missing_file() {
+        let file = env::temp_dir().join("capy_lib_tests/does_not_exist.capy");
+
+        let Err(EvalError::Io { file: found, .. }) = compiler().eval_const(&file, "1") else {
+            panic!("a file that doesn't exist can't be read");
+        };
+        assert_eq!(found, file);
+    }
+
+    #[test]
+    fn progress() {
+        let file = write_file("progress", CONFIG);
+
+        let reported = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let compiler = compiler().with_progress({
+            let reported = reported.clone();
+            move |progress| reported.borrow_mut().push(progress)
+        });
+        compiler.check(&file).unwrap().for_each(drop);
+
+        let reported = reported.take();
+        assert_eq!(reported[0], Progress::Parsing { files: 1 });
+
+        let inferred = reported
+            .iter()
+            .filter_map(|progress| match progress {
+                Progress::Inferring(progress) => Some(*progress),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(inferred
+            .windows(2)
+            .all(|pair| pair[0].inferred <= pair[1].inferred));
+
+        let last = inferred.last().unwrap();
+        assert_eq!(last.inferred, last.total);
+        assert_eq!(last.total, 2, "there are only two globals");
+    }
+
+    #[test]
+    fn progress_display() {
+        assert_eq!(Progress::Parsing { files: 3 }.to_string(), "parsed 3 files");
+        assert_eq!(
+            Progress::Inferring(hir_ty::InferenceProgress {
+                inferred: 5,
+                total: 8,
+                cyclic: 2,
+            })
+            .to_string(),
+            "inferred 5/8 globals (2 in cycles)"
+        );
+        assert_eq!(
+            Progress::Compiling(codegen::CodegenProgress {
+                compiled: 1,
+                total: 4,
+            })
+            .to_string(),
+            "compiled 1/4 functions"
+        );
+    }
+}
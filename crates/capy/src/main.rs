@@ -1,24 +1,25 @@
+mod bugreport;
 mod git;
-mod source;
+mod reproducible;
 
 use std::{
     cell::RefCell,
     env,
-    ffi::CString,
+    ffi::{CString, OsString},
     io::{self, Write},
-    mem,
     panic::AssertUnwindSafe,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::exit,
     rc::Rc,
     str::FromStr,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use clap::{ColorChoice, Parser, Subcommand};
+use diagnostics::Severity;
 use enum_display::EnumDisplay;
-use hir::{FQComptime, WorldBodies, WorldIndex};
-use hir_ty::{ComptimeResult, InferenceResult};
+use hir::FQComptime;
+use hir_ty::ComptimeResult;
 use interner::Interner;
 use itertools::Itertools;
 use line_index::LineIndex;
@@ -27,9 +28,12 @@ use platform_dirs::AppDirs;
 use rustc_hash::FxHashMap;
 use std::fs;
 use target_lexicon::Triple;
-use uid_gen::UIDGenerator;
 
-use crate::source::SourceFile;
+use capy::{
+    manifest::Manifest,
+    progress::{Progress, ProgressBar},
+    VerboseScope,
+};
 
 macro_rules! create_build_action {
     (
@@ -142,12 +146,76 @@ macro_rules! create_build_action {
 
 #[derive(Debug, Parser)]
 #[command(name = "Capy Programming Language")]
+#[command(args_override_self = true)]
 #[command(author = "NotAFlyingGoose <notaflyinggoose@gmail.com>")]
 #[command(version)]
 #[command(about = "A statically typed, compiled programming language, largely inspired by Jai, Odin, and Zig", long_about = None)]
 struct CLIConfig {
     #[command(subcommand)]
-    action: CLIAction,
+    command: CLICommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum CLICommand {
+    #[command(flatten)]
+    Compile(CLIAction),
+    /// Builds a .capy file and packages the output, the compiler version, the target,
+    /// and the source of the file and its imports into a markdown snippet for bug reports
+    Bugreport {
+        /// The file which is causing problems
+        #[arg(required = true)]
+        file: String,
+
+        /// Where to write the report. If not supplied, the report is printed out
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// The directory to search for modules.
+        #[arg(long)]
+        mod_dir: Option<String>,
+
+        /// Any other arguments to give to `capy build`, like `--target`.
+        /// These can be passed in by using `--`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Prints the size, alignment, field offsets, padding and enum discriminants of a type,
+    /// for checking that it matches the C struct it's meant to
+    Layout {
+        /// The file which the type can be found from
+        #[arg(required = true)]
+        file: String,
+
+        /// The type to show, e.g. `Point` or `net.Header`
+        #[arg(required = true)]
+        ty: String,
+
+        /// The directory to search for modules.
+        #[arg(long)]
+        mod_dir: Option<String>,
+    },
+    /// Checks the `extern` functions and structs of a .capy file against the function prototypes
+    /// and structs with the same names in a C header
+    AbiCheck {
+        /// The file with the `extern` declarations
+        #[arg(required = true)]
+        file: String,
+
+        /// The C header which the declarations should match
+        #[arg(required = true)]
+        header: String,
+
+        /// The directory to search for modules.
+        #[arg(long)]
+        mod_dir: Option<String>,
+    },
+    /// Removes the output directory, along with every object file and executable in it
+    Clean {
+        /// The output directory to remove.
+        /// Defaults to `.capy-out` next to the `capy.toml`, or in the current directory
+        #[arg(long)]
+        out_dir: Option<String>,
+    },
 }
 
 create_build_action! {
@@ -161,10 +229,16 @@ create_build_action! {
         #[arg(long, default_value = "main")]
         entry_point: String,
 
-        /// The final executable name. This doesn't need a file extension
+        /// The final executable name. This doesn't need a file extension.
+        /// Defaults to the package name in `capy.toml`, or the name of the file
         #[arg(short, long)]
         output: Option<String>,
 
+        /// Where to put object files and executables.
+        /// They end up in `<out-dir>/<target>/<profile>/`
+        #[arg(long)]
+        out_dir: Option<String>,
+
         /// The directory to search for modules.
         /// If this folder does not contain `core` it will be downloaded
         #[arg(long)]
@@ -211,12 +285,45 @@ create_build_action! {
         /// This literally works by passing the args to gcc with "-l"
         #[arg(long)]
         libs: Vec<String>,
+
+        /// Warns about struct literals which give their members in a different order
+        /// than the struct declares them
+        #[arg(long)]
+        lint_member_order: bool,
+
+        /// Rewrites the source files to fix any warnings that can be fixed automatically
+        #[arg(long)]
+        fix: bool,
+
+        /// Shows how long each stage of compilation took, and how much was interned
+        #[arg(long)]
+        timings: bool,
+
+        /// Shows how many files have been parsed, globals inferred, and functions compiled
+        /// while the build is going
+        #[arg(long)]
+        progress: bool,
     }
     /// Takes in one or more .capy files and compiles them
     build_only {
         /// The target to compile for. If supplied, no linking will be done
         #[arg(long)]
         target: Option<String>,
+
+        /// Builds a shared library that can be loaded with `dlopen` instead of an executable.
+        /// Everything is compiled as position-independent code
+        #[arg(long)]
+        lib: bool,
+
+        /// Makes sure that building the same source always gives a byte-identical object file,
+        /// no matter where it was built from
+        #[arg(long)]
+        reproducible: bool,
+
+        /// Builds the object file twice in separate processes and fails if they aren't
+        /// byte-identical. Implies --reproducible and --no-exec
+        #[arg(long)]
+        check_reproducible: bool,
     }
     /// Takes in one or more .capy files, compiles them, and runs the compiled executable
     run_only {
@@ -272,30 +379,6 @@ impl VerboseCodegenScope {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, EnumDisplay)]
-#[clap(rename_all = "kebab_case")]
-#[enum_display(case = "Kebab")]
-pub(crate) enum VerboseScope {
-    #[default]
-    None,
-    Local,
-    All,
-}
-
-impl VerboseScope {
-    fn should_show(self, is_mod: bool) -> bool {
-        match self {
-            Self::None => false,
-            Self::Local => !is_mod,
-            Self::All => true,
-        }
-    }
-
-    fn is_none(self) -> bool {
-        self == Self::None
-    }
-}
-
 impl FinalConfig {
     fn should_run(&self) -> bool {
         matches!(self.specific, CompileMode::Run(_))
@@ -308,6 +391,33 @@ impl FinalConfig {
         )
     }
 
+    fn is_lib(&self) -> bool {
+        matches!(
+            self.specific,
+            CompileMode::Build(BuildSpecific { lib: true, .. })
+        )
+    }
+
+    fn is_reproducible(&self) -> bool {
+        matches!(
+            self.specific,
+            CompileMode::Build(BuildSpecific {
+                reproducible: true,
+                ..
+            })
+        )
+    }
+
+    fn should_check_reproducible(&self) -> bool {
+        matches!(
+            self.specific,
+            CompileMode::Build(BuildSpecific {
+                check_reproducible: true,
+                ..
+            })
+        )
+    }
+
     fn target(&self) -> Triple {
         match &self.specific {
             CompileMode::Build(BuildSpecific {
@@ -323,6 +433,13 @@ impl FinalConfig {
         }
     }
 
+    /// The directory that the object file and executable for this build get written to
+    fn output_dir(&self, manifest: Option<&Manifest>, target: &Triple) -> PathBuf {
+        get_out_dir(self.out_dir.as_deref(), manifest)
+            .join(target.to_string())
+            .join(PROFILE)
+    }
+
     fn args(&self) -> &[String] {
         match &self.specific {
             CompileMode::Run(RunSpecific { args, .. }) => args,
@@ -332,16 +449,332 @@ impl FinalConfig {
 }
 
 fn main() -> io::Result<()> {
-    let config = CLIConfig::parse();
+    let config = match CLIConfig::parse_from(cli_args()).command {
+        CLICommand::Compile(action) => action.into_final_config(),
+        CLICommand::Bugreport {
+            file,
+            output,
+            mod_dir,
+            args,
+        } => {
+            return bugreport::create(
+                &file,
+                &get_mod_dir(mod_dir.as_deref()),
+                output.as_deref(),
+                &args,
+            )
+        }
+        CLICommand::Layout { file, ty, mod_dir } => {
+            return layout(&file, &ty, &get_mod_dir(mod_dir.as_deref()));
+        }
+        CLICommand::AbiCheck {
+            file,
+            header,
+            mod_dir,
+        } => {
+            return abi_check(&file, &header, &get_mod_dir(mod_dir.as_deref()));
+        }
+        CLICommand::Clean { out_dir } => {
+            let manifest = find_manifest(&env::current_dir().unwrap());
+            return clean(&get_out_dir(out_dir.as_deref(), manifest.as_ref()));
+        }
+    };
+
+    if config.should_check_reproducible() {
+        let file = env::current_dir().unwrap().join(&config.file).clean();
+        let manifest = find_manifest(file.parent().unwrap());
+
+        let output = config
+            .output
+            .clone()
+            .unwrap_or_else(|| file.file_stem().unwrap().to_string_lossy().to_string());
+
+        return reproducible::check(
+            &output,
+            &get_out_dir(config.out_dir.as_deref(), manifest.as_ref()),
+            &config.output_dir(manifest.as_ref(), &config.target()),
+        );
+    }
 
-    compile_file(config.action.into_final_config())
+    compile_file(config)
 }
 
+/// Optimizations can't be configured yet, so every build uses the same profile
+const PROFILE: &str = "debug";
+/// Written to the root of each output directory that `capy` creates, see `clean`
+const OUT_DIR_MARKER: &str = ".capy-out-dir";
+
 const ANSI_RED: &str = "\x1B[1;91m";
 const ANSI_GREEN: &str = "\x1B[1;92m";
 const ANSI_WHITE: &str = "\x1B[1;97m";
 const ANSI_RESET: &str = "\x1B[0m";
 
+fn get_mod_dir(mod_dir: Option<&str>) -> PathBuf {
+    if let Some(mod_dir) = mod_dir {
+        env::current_dir().unwrap().join(mod_dir).clean()
+    } else if let Some(mod_dir) = AppDirs::new(Some("capy"), false) {
+        mod_dir.data_dir.join("modules")
+    } else {
+        PathBuf::new()
+            .join(std::path::MAIN_SEPARATOR_STR)
+            .join("capy")
+            .join("modules")
+            .clean()
+    }
+}
+
+/// The command line arguments, with the flags in `CAPY_FLAGS` put in front of the ones that
+/// were actually given, so that anything given on the command line still wins.
+///
+/// Only `build` and `run` get the default flags since the other commands don't share them.
+/// A `.capy` file given without a command gets ran like a script.
+fn cli_args() -> Vec<OsString> {
+    let mut args: Vec<OsString> = env::args_os().collect();
+
+    // a script starting with `#!/usr/bin/env capy` gets ran as `capy path/to/script.capy ...`,
+    // which is treated as `capy run path/to/script.capy -- ...`
+    if args
+        .get(1)
+        .is_some_and(|arg| Path::new(arg).extension() == Some("capy".as_ref()))
+    {
+        args.insert(1, "run".into());
+        if args.len() > 3 {
+            args.insert(3, "--".into());
+        }
+    }
+
+    let Ok(default_flags) = env::var("CAPY_FLAGS") else {
+        return args;
+    };
+
+    if matches!(
+        args.get(1).and_then(|command| command.to_str()),
+        Some("build" | "run")
+    ) {
+        args.splice(2..2, default_flags.split_whitespace().map(OsString::from));
+    }
+
+    args
+}
+
+/// How many columns diagnostics have to fit within.
+///
+/// `COLUMNS` takes priority over the size of the terminal. When the output isn't going to a
+/// terminal and `COLUMNS` isn't set, nothing gets wrapped or truncated.
+fn terminal_width() -> Option<usize> {
+    if let Some(columns) = env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+    {
+        return Some(columns).filter(|columns| *columns > 0);
+    }
+
+    terminal_size::terminal_size().map(|(terminal_size::Width(width), _)| width as usize)
+}
+
+/// Looks for the `capy.toml` of the package that `dir` is in
+fn find_manifest(dir: &Path) -> Option<Manifest> {
+    Manifest::find(dir).unwrap_or_else(|why| {
+        println!("{why}");
+        exit(1)
+    })
+}
+
+/// The root of the output directory, which holds a separate folder for each target.
+///
+/// This goes next to the `capy.toml` if there is one, so building from a subdirectory of a
+/// package still reuses the same output directory.
+fn get_out_dir(out_dir: Option<&str>, manifest: Option<&Manifest>) -> PathBuf {
+    match (out_dir, manifest) {
+        (Some(out_dir), _) => env::current_dir().unwrap().join(out_dir).clean(),
+        (None, Some(manifest)) => manifest.dir.join(".capy-out"),
+        (None, None) => env::current_dir().unwrap().join(".capy-out"),
+    }
+}
+
+fn layout(file: &str, ty: &str, mod_dir: &Path) -> io::Result<()> {
+    let file = env::current_dir().unwrap().join(file).clean();
+    let manifest = find_manifest(file.parent().unwrap());
+
+    let compiler = capy::Compiler::new(mod_dir).with_comptime_limits(
+        manifest
+            .as_ref()
+            .map_or_else(codegen::ComptimeLimits::default, Manifest::comptime_limits),
+    );
+
+    match compiler.layout_of(&file, ty) {
+        Ok(layout) => println!("{layout}"),
+        Err(why) => {
+            println!("{why}");
+            exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn abi_check(file: &str, header: &str, mod_dir: &Path) -> io::Result<()> {
+    let file = env::current_dir().unwrap().join(file).clean();
+    let manifest = find_manifest(file.parent().unwrap());
+
+    let compiler = capy::Compiler::new(mod_dir).with_comptime_limits(
+        manifest
+            .as_ref()
+            .map_or_else(codegen::ComptimeLimits::default, Manifest::comptime_limits),
+    );
+
+    match compiler.check_abi(&file, header) {
+        Ok(mismatches) if mismatches.is_empty() => {
+            println!("everything matches `{header}`");
+        }
+        Ok(mismatches) => {
+            for mismatch in &mismatches {
+                println!("{mismatch}\n");
+            }
+            println!(
+                "{} with `{header}`",
+                if mismatches.len() == 1 {
+                    "1 mismatch".to_string()
+                } else {
+                    format!("{} mismatches", mismatches.len())
+                }
+            );
+            exit(1);
+        }
+        Err(why) => {
+            println!("{why}");
+            exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn clean(out_dir: &Path) -> io::Result<()> {
+    if !out_dir.exists() {
+        println!("nothing to clean, `{}` doesn't exist", out_dir.display());
+        return Ok(());
+    }
+
+    if !out_dir.join(OUT_DIR_MARKER).exists() {
+        println!(
+            "error: `{}` wasn't made by capy, so it won't be removed",
+            out_dir.display()
+        );
+        exit(1);
+    }
+
+    fs::remove_dir_all(out_dir)?;
+    println!("removed `{}`", out_dir.display());
+
+    Ok(())
+}
+
+/// Prints the file's diagnostics along with any extra ones found in it,
+/// under a header with the file's name. Nothing is printed if there aren't any.
+fn print_diagnostics(
+    file: &capy::LoadedFile,
+    extra: &[diagnostics::Diagnostic],
+    mod_dir: &Path,
+    interner: &Interner,
+    with_color: bool,
+    width: Option<usize>,
+) {
+    let all = || file.diagnostics().iter().chain(extra);
+
+    if all().next().is_none() {
+        return;
+    }
+
+    let count = |severity: Severity, singular: &str, plural: &str| match all()
+        .filter(|d| d.severity() == severity)
+        .count()
+    {
+        0 => None,
+        1 => Some(format!("1 {singular}")),
+        n => Some(format!("{n} {plural}")),
+    };
+    let counts = [
+        count(Severity::Error, "error", "errors"),
+        count(Severity::Warning, "warning", "warnings"),
+        count(Severity::Help, "help", "helps"),
+    ]
+    .into_iter()
+    .flatten()
+    .join(", ");
+
+    let file_name = env::current_dir()
+        .ok()
+        .and_then(|dir| file.file_name().strip_prefix(dir).ok())
+        .unwrap_or(file.file_name());
+
+    let (ansi_white, ansi_reset) = if with_color {
+        (ANSI_WHITE, ANSI_RESET)
+    } else {
+        ("", "")
+    };
+    println!(
+        "\n{ansi_white}=== {} ({counts}) ==={ansi_reset}\n",
+        file_name.display()
+    );
+
+    let line_index = LineIndex::new(file.contents());
+    for diagnostic in all() {
+        println!(
+            "{}",
+            diagnostic
+                .display(
+                    &file.file_name().to_string_lossy(),
+                    file.contents(),
+                    mod_dir,
+                    interner,
+                    &line_index,
+                    with_color,
+                    width,
+                )
+                .join("\n")
+        );
+    }
+}
+
+/// How long each stage of compilation took, shown with `--timings`
+struct Timings {
+    stages: Vec<(&'static str, Duration)>,
+    stage_start: Instant,
+}
+
+impl Timings {
+    fn new() -> Self {
+        Self {
+            stages: Vec::new(),
+            stage_start: Instant::now(),
+        }
+    }
+
+    /// Ends the current stage and starts the next one
+    fn finish_stage(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.stages.push((name, now - self.stage_start));
+        self.stage_start = now;
+    }
+
+    fn print(&self, interner: &Interner, ansi_green: &str, ansi_reset: &str) {
+        println!(
+            "{ansi_green}Timings{ansi_reset}    {}",
+            self.stages
+                .iter()
+                .map(|(name, duration)| format!("{name} {:.2}s", duration.as_secs_f32()))
+                .join(", ")
+        );
+
+        let stats = interner.stats();
+        println!(
+            "{ansi_green}Interned{ansi_reset}   {} strings ({} bytes)",
+            stats.count, stats.bytes
+        );
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn compile_file(mut config: FinalConfig) -> io::Result<()> {
     if config.verbose_all {
@@ -371,14 +804,6 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
         )
         .clean();
 
-    let file_contents = match fs::read_to_string(&file_name) {
-        Ok(contents) => contents,
-        Err(why) => {
-            println!("{}: {}", file_name.display(), why);
-            exit(1)
-        }
-    };
-
     let with_color = match config.color {
         ColorChoice::Auto => supports_color::on(supports_color::Stream::Stdout).is_some(),
         ColorChoice::Always => true,
@@ -389,18 +814,9 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
     } else {
         ("", "", "", "")
     };
+    let width = terminal_width();
 
-    let mod_dir = if let Some(mod_dir) = &config.mod_dir {
-        env::current_dir().unwrap().join(mod_dir).clean()
-    } else if let Some(mod_dir) = AppDirs::new(Some("capy"), false) {
-        mod_dir.data_dir.join("modules")
-    } else {
-        PathBuf::new()
-            .join(std::path::MAIN_SEPARATOR_STR)
-            .join("capy")
-            .join("modules")
-            .clean()
-    };
+    let mod_dir = get_mod_dir(config.mod_dir.as_deref());
 
     let core_dir = mod_dir.join("core");
 
@@ -436,226 +852,181 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
 
     let target = config.target();
 
+    let manifest = find_manifest(file_name.parent().unwrap());
+
     println!("{ansi_green}Compiling{ansi_reset}  ...");
     let compilation_start = Instant::now();
+    let mut timings = Timings::new();
+    let progress_bar = config.progress.then(ProgressBar::new);
 
-    let interner = Rc::new(RefCell::new(interner::Interner::default()));
-    let world_index = Rc::new(RefCell::new(WorldIndex::default()));
-    let world_bodies = Rc::new(RefCell::new(WorldBodies::default()));
-    let uid_gen = Rc::new(RefCell::new(UIDGenerator::default()));
-
-    let entry_point_name = hir::Name(interner.borrow_mut().intern(&config.entry_point));
-
-    let mut line_indexes = FxHashMap::default();
-    let mut source_files = FxHashMap::default();
-
-    // parse the first source file given in the `capy` command
+    let comptime_limits = manifest
+        .as_ref()
+        .map_or_else(codegen::ComptimeLimits::default, Manifest::comptime_limits);
+
+    // the compiler reports its progress through a shared closure,
+    // but codegen still needs to report to the same bar afterwards
+    let progress_bar = Rc::new(RefCell::new(progress_bar));
+
+    let compiler = capy::Compiler::new(&mod_dir)
+        .with_target(&target)
+        .with_comptime_limits(comptime_limits)
+        .with_lint_member_order(config.lint_member_order)
+        .with_verbose(capy::Verbose {
+            ast: config.verbose_ast,
+            hir: config.verbose_hir,
+            types: config.verbose_types,
+            comptime: config.verbose_comptime.into_verbosity(),
+            color: with_color,
+        })
+        .with_progress({
+            let progress_bar = progress_bar.clone();
+            move |progress| {
+                if let Some(progress_bar) = &mut *progress_bar.borrow_mut() {
+                    progress_bar.update(progress);
+                }
+            }
+        });
 
-    let mut source_file = SourceFile::parse(
-        file_name.clone(),
-        file_contents.clone(),
-        uid_gen.clone(),
-        interner.clone(),
-        world_index.clone(),
-        world_bodies.clone(),
-        &mod_dir,
-        config.verbose_hir,
-        config.verbose_ast,
-        config.verbose_types,
-        with_color,
-    );
+    // parse the source file given in the `capy` command, along with everything it imports
 
-    line_indexes.insert(source_file.module, LineIndex::new(&file_contents));
+    let mut project = compiler.load(&file_name).unwrap_or_else(|why| {
+        println!("{why}");
+        exit(1)
+    });
 
-    let mut current_imports = source_file.build_bodies(&mod_dir);
-    source_files.insert(source_file.module, source_file);
+    timings.finish_stage("parsing");
 
-    // find all imports in the source file, compile them, then do the same for their imports
+    // every file has been indexed, so make sure there's exactly one entry point
+    // before spending any time on type checking.
+    // a shared library is just its `@export` functions, so it doesn't need one
 
-    while !current_imports.is_empty() {
-        let old_imports = mem::take(&mut current_imports);
-        for file_name in old_imports {
-            if source_files.contains_key(&file_name) {
-                continue;
+    let entry_point = match project.entry_point(&config.entry_point) {
+        Ok(entry_point) => Some(entry_point),
+        Err(hir::EntryPointError::Missing { .. }) if config.is_lib() => None,
+        Err(why) => {
+            for file in project.files() {
+                print_diagnostics(file, &[], &mod_dir, project.interner(), with_color, width);
             }
 
-            let file_name = {
-                let interner = interner.borrow();
-                PathBuf::from(interner.lookup(file_name.0))
-            };
-            let file_contents = match fs::read_to_string(&file_name) {
-                Ok(contents) => contents,
-                Err(why) => {
-                    println!("{}: {}", file_name.display(), why);
-                    exit(1)
-                }
-            };
-
-            let mut source_file = SourceFile::parse(
-                file_name,
-                file_contents.clone(),
-                uid_gen.clone(),
-                interner.clone(),
-                world_index.clone(),
-                world_bodies.clone(),
-                &mod_dir,
-                config.verbose_hir,
-                config.verbose_ast,
-                config.verbose_types,
-                with_color,
+            let line_indexes: FxHashMap<_, _> = project
+                .files()
+                .map(|file| (file.module(), LineIndex::new(file.contents())))
+                .collect();
+            println!(
+                "{}",
+                diagnostics::display_entry_point_error(
+                    &why,
+                    project.world_index(),
+                    |file| &line_indexes[&file],
+                    &mod_dir,
+                    project.interner(),
+                    with_color,
+                )
+                .join("\n")
             );
-
-            line_indexes.insert(source_file.module, LineIndex::new(&file_contents));
-
-            let imports = source_file.build_bodies(&mod_dir);
-            current_imports.extend(imports);
-
-            source_files.insert(source_file.module, source_file);
+            exit(1);
         }
-    }
+    };
+    let main_file = entry_point.map_or(project.root_file(), |entry_point| entry_point.file);
 
     // infer types
-    let main_files = source_files
-        .iter()
-        .filter(|(_, sf)| sf.has_fn_of_name(entry_point_name))
-        .map(|(name, _)| *name)
-        .collect_vec();
-    let main_file = main_files.first();
-    let entry_point = main_file.map(|file| hir::Fqn {
-        file: *file,
-        name: entry_point_name,
-    });
-
-    let comptime_verbosity = config.verbose_comptime.into_verbosity();
 
     let mut comptime_results = FxHashMap::<FQComptime, ComptimeResult>::default();
 
-    let InferenceResult {
-        tys,
-        diagnostics: ty_diagnostics,
-        any_were_unsafe_to_compile,
-    } = hir_ty::InferenceCtx::new(
-        &world_index.borrow(),
-        &world_bodies.borrow(),
-        &interner.borrow(),
-        |comptime, tys| {
-            if let Some(result) = comptime_results.get(&comptime) {
-                return result.clone();
-            }
-
-            let interner = interner.borrow();
-            let world_bodies = world_bodies.borrow();
-
-            let interner: &Interner = &interner;
-            let world_bodies: &hir::WorldBodies = &world_bodies;
+    // todo: i kinda did AssertUnwindSafe bc i wanted to get rid of the error.
+    // i *think* it should be fine.
+    let tys = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        compiler.infer(&mut project, entry_point, &mut comptime_results)
+    }))
+    .unwrap_or_else(|_| {
+        println!("\n{ansi_red}error{ansi_white}: comptime compilation panicked{ansi_reset}");
+        exit(1);
+    });
 
-            let is_mod = comptime.file.is_mod(&mod_dir, interner);
-            if comptime_verbosity.should_show(is_mod) {
-                println!("comptime JIT:\n");
-            }
+    timings.finish_stage("type checking");
 
-            // todo: i kinda did AssertUnwindSafe bc i wanted to get rid of the error.
-            // i *think* it should be fine.
-            std::panic::catch_unwind(AssertUnwindSafe(|| {
-                codegen::eval_comptime_blocks(
-                    comptime_verbosity,
-                    vec![comptime],
-                    &mut comptime_results,
-                    &mod_dir,
-                    interner,
-                    world_bodies,
-                    tys,
-                    target.pointer_width().unwrap().bits(),
-                )
-            }))
-            .unwrap_or_else(|_| {
-                println!(
-                    "\n{ansi_red}error{ansi_white}: comptime compilation panicked{ansi_reset}"
-                );
-                std::process::exit(1);
-            });
-
-            comptime_results[&comptime].clone()
-        },
-    )
-    .finish(entry_point, !config.verbose_types.is_none());
+    if let Some(progress_bar) = &mut *progress_bar.borrow_mut() {
+        progress_bar.clear();
+    }
 
     if !config.verbose_types.is_none() {
         let debug = tys.debug(
             &mod_dir,
-            &interner.borrow(),
+            project.interner(),
             config.verbose_types == VerboseScope::All,
             true,
         );
         println!("=== types ===\n");
         println!("{}", debug);
 
-        if any_were_unsafe_to_compile {
+        if project.any_were_unsafe_to_compile() {
             println!("\nSOMETHING WAS UNSAFE TO COMPILE");
         }
     }
 
-    // print out errors and warnings
+    // print out errors and warnings, grouped by the file they're in
+
+    for file in project.files() {
+        print_diagnostics(file, &[], &mod_dir, project.interner(), with_color, width);
+
+        // the files in the mod dir aren't the user's to change
+        if !config.fix || file.is_mod() {
+            continue;
+        }
+
+        let mut fixes = file
+            .diagnostics()
+            .iter()
+            .filter_map(|diagnostic| diagnostic.fix(file.contents()))
+            .collect_vec();
+        if fixes.is_empty() {
+            continue;
+        }
+
+        let mut contents = file.contents().to_string();
 
-    let has_errors = ty_diagnostics.iter().any(hir_ty::TyDiagnostic::is_error)
-        || source_files.iter().any(|(_, source)| source.has_errors());
-    source_files
-        .iter()
-        .for_each(|(_, source)| source.print_diagnostics(&mod_dir, with_color));
-    for d in ty_diagnostics {
-        let line_index = &line_indexes[&d.file];
-        let source_file = &source_files[&d.file];
+        // apply the fixes back to front so the earlier ranges stay valid
+        fixes.sort_unstable_by_key(|fix| std::cmp::Reverse(fix.range.start()));
+        for fix in fixes {
+            contents.replace_range(std::ops::Range::<usize>::from(fix.range), &fix.replacement);
+        }
 
+        if let Err(why) = fs::write(file.file_name(), contents) {
+            println!("{}: {}", file.file_name().display(), why);
+            exit(1);
+        }
         println!(
-            "{}",
-            diagnostics::Diagnostic::from_ty(d)
-                .display(
-                    &source_file.file_name.to_string_lossy(),
-                    &source_file.contents,
-                    &mod_dir,
-                    &interner.borrow(),
-                    line_index,
-                    with_color,
-                )
-                .join("\n")
-        )
+            "{ansi_green}Fixed{ansi_reset}      {}",
+            file.file_name().display()
+        );
     }
 
-    if has_errors {
+    if project.has_errors() {
         println!("\nnot compiling due to previous errors");
         exit(1);
     }
 
     // evaluate any comptimes that haven't been ran yet
-    codegen::eval_comptime_blocks(
-        comptime_verbosity,
-        world_bodies.borrow().find_comptimes(),
-        &mut comptime_results,
-        &mod_dir,
-        &interner.borrow(),
-        &world_bodies.borrow(),
+    if let Err((file, diagnostic)) = compiler.eval_comptime_blocks(
+        &project,
         &tys,
-        target.pointer_width().unwrap().bits(),
-    );
-
-    match main_files.len().cmp(&1) {
-        std::cmp::Ordering::Less => {
-            println!(
-                "{ansi_red}error{ansi_white}: there is no `{}` function{ansi_reset}",
-                interner.borrow().lookup(entry_point_name.0)
-            );
-            std::process::exit(1);
-        }
-        std::cmp::Ordering::Equal => {}
-        std::cmp::Ordering::Greater => {
-            println!(
-                "{ansi_red}error{ansi_white}: there are multiple `{}` functions{ansi_reset}",
-                interner.borrow().lookup(entry_point_name.0)
-            );
-            std::process::exit(1);
-        }
+        project.world_bodies().find_comptimes(),
+        &mut comptime_results,
+    ) {
+        print_diagnostics(
+            project.file(file),
+            &[diagnostic],
+            &mod_dir,
+            project.interner(),
+            with_color,
+            width,
+        );
+        println!("\nnot compiling due to previous errors");
+        exit(1);
     }
 
+    timings.finish_stage("comptime");
+
     let parse_finish = compilation_start.elapsed();
 
     // frontend stuff is finally over
@@ -666,7 +1037,8 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
         parse_finish.as_secs_f32()
     );
 
-    let interner = interner.borrow();
+    let interner = project.interner();
+    let world_bodies = project.world_bodies();
 
     let final_verbosity = config.verbose_binary.into_verbosity();
 
@@ -674,25 +1046,40 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
         println!("\nactual program:\n");
     }
 
+    let mut report_codegen = |progress| {
+        if let Some(progress_bar) = &mut *progress_bar.borrow_mut() {
+            progress_bar.update(Progress::Compiling(progress));
+        }
+    };
+
     if config.should_jit() {
         let jit_fn = codegen::compile_jit(
             final_verbosity,
-            entry_point.unwrap(),
+            entry_point.expect("only libraries can be built without an entry point"),
             &mod_dir,
-            &interner,
-            &world_bodies.borrow(),
+            interner,
+            world_bodies,
             &tys,
             &comptime_results,
+            Some(&mut report_codegen),
         );
 
+        if let Some(progress_bar) = &mut *progress_bar.borrow_mut() {
+            progress_bar.clear();
+        }
+        timings.finish_stage("codegen");
+
         println!(
             "{ansi_green}Finished{ansi_reset}   {} (JIT) in {:.2}s",
-            main_file.unwrap().to_string(&mod_dir, &interner),
+            main_file.to_string(&mod_dir, interner),
             compilation_start.elapsed().as_secs_f32(),
         );
+        if config.timings {
+            timings.print(interner, ansi_green, ansi_reset);
+        }
         print!(
             "{ansi_green}Running{ansi_reset}    `{}",
-            main_file.unwrap().to_string(&mod_dir, &interner)
+            main_file.to_string(&mod_dir, interner)
         );
         let args = config.args();
         for arg in args {
@@ -723,13 +1110,17 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
 
     let bytes = match codegen::compile_obj(
         final_verbosity,
-        entry_point.unwrap(),
+        main_file,
+        entry_point,
         &mod_dir,
-        &interner,
-        &world_bodies.borrow(),
+        interner,
+        world_bodies,
         &tys,
         &comptime_results,
         target.clone(),
+        config.is_lib(),
+        config.is_reproducible(),
+        Some(&mut report_codegen),
     ) {
         Ok(bytes) => bytes,
         Err(why) => {
@@ -738,15 +1129,43 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
         }
     };
 
-    // let output_folder = env::current_dir().unwrap().join("out");
-    let output_folder = PathBuf::from("out");
+    if let Some(progress_bar) = &mut *progress_bar.borrow_mut() {
+        progress_bar.clear();
+    }
+    timings.finish_stage("codegen");
+
+    let output_folder = config.output_dir(manifest.as_ref(), &target);
 
-    let _ = fs::create_dir(&output_folder);
+    let out_dir = get_out_dir(config.out_dir.as_deref(), manifest.as_ref());
+    let is_new_out_dir = !out_dir.exists();
 
-    let output = config.output.clone().unwrap_or_else(|| {
-        let main_file = std::path::PathBuf::from(interner.lookup(main_file.unwrap().0));
-        main_file.file_stem().unwrap().to_string_lossy().to_string()
+    fs::create_dir_all(&output_folder).unwrap_or_else(|why| {
+        println!("{}: {why}", output_folder.display());
+        exit(1);
     });
+
+    // `capy clean` only removes the directories with this in them, so an `--out-dir`
+    // which already had other things in it never gets marked
+    if is_new_out_dir {
+        let marker = out_dir.join(OUT_DIR_MARKER);
+        fs::write(
+            &marker,
+            "this directory was created by `capy` and can be removed with `capy clean`\n",
+        )
+        .unwrap_or_else(|why| {
+            println!("{}: {why}", marker.display());
+            exit(1);
+        });
+    }
+
+    let output = config
+        .output
+        .clone()
+        .or_else(|| manifest.and_then(|manifest| manifest.package.name))
+        .unwrap_or_else(|| {
+            let main_file = std::path::PathBuf::from(interner.lookup(main_file.0));
+            main_file.file_stem().unwrap().to_string_lossy().to_string()
+        });
     let mut object_file = output_folder.join(&output);
     object_file.set_extension("o");
     fs::write(&object_file, bytes.as_slice()).unwrap_or_else(|why| {
@@ -761,10 +1180,21 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
             target,
             compilation_start.elapsed().as_secs_f32(),
         );
+        if config.timings {
+            timings.print(interner, ansi_green, ansi_reset);
+        }
         return Ok(());
     }
 
-    let exec = match codegen::link_to_exec(&object_file, target, &config.libs) {
+    let linked = if config.is_lib() {
+        codegen::link_to_shared_lib(&object_file, target, &config.libs)
+    } else {
+        codegen::link_to_exec(&object_file, target, &config.libs)
+    };
+
+    timings.finish_stage("linking");
+
+    let exec = match linked {
         Ok(exec) => {
             println!(
                 "{ansi_green}Finished{ansi_reset}   {} ({}) in {:.2}s",
@@ -772,6 +1202,9 @@ fn compile_file(mut config: FinalConfig) -> io::Result<()> {
                 exec.display(),
                 compilation_start.elapsed().as_secs_f32(),
             );
+            if config.timings {
+                timings.print(interner, ansi_green, ansi_reset);
+            }
             exec
         }
         Err(codegen::LinkingErr::NoCommand) => {
@@ -0,0 +1,248 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use diagnostics::{Diagnostic, LintLevel};
+use serde::Deserialize;
+
+use crate::EvalError;
+
+pub const MANIFEST_NAME: &str = "capy.toml";
+
+/// The contents of a `capy.toml` file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The directory that the manifest is in
+    #[serde(skip)]
+    pub dir: PathBuf,
+    #[serde(default)]
+    pub package: Package,
+    #[serde(default)]
+    pub comptime: Comptime,
+    /// How each warning should be treated, e.g. `always_true = "deny"`
+    #[serde(default)]
+    pub lints: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Package {
+    /// The name given to the final executable or library
+    pub name: Option<String>,
+}
+
+/// Limits on each `comptime { ... }` block, so a mistake like an infinite loop
+/// gets reported instead of hanging the build
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Comptime {
+    /// How many seconds a block can run for
+    pub time_limit: Option<f64>,
+    /// How many bytes a block can allocate in total
+    pub memory_limit: Option<u64>,
+}
+
+impl Manifest {
+    /// Looks for a `capy.toml` in the given directory, then in each of its parents.
+    ///
+    /// Having no manifest at all is fine, but one that can't be read or parsed isn't.
+    pub fn find(start: &Path) -> Result<Option<Manifest>, EvalError> {
+        let Some(file) = start
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST_NAME))
+            .find(|file| file.is_file())
+        else {
+            return Ok(None);
+        };
+
+        let contents = fs::read_to_string(&file).map_err(|why| EvalError::Io {
+            file: file.clone(),
+            why,
+        })?;
+
+        Manifest::parse(&file, &contents)
+            .map(Some)
+            .map_err(|why| EvalError::Manifest { file, why })
+    }
+
+    /// `file` is where the manifest was read from
+    fn parse(file: &Path, contents: &str) -> Result<Manifest, String> {
+        let mut manifest: Manifest =
+            toml::from_str(contents).map_err(|why| why.message().to_string())?;
+        manifest.dir = file.parent().unwrap().to_path_buf();
+
+        if let Some(name) = &manifest.package.name {
+            if name.is_empty() || name.contains(['/', '\\']) {
+                return Err(
+                    "the package name must be non-empty and can't contain file separators"
+                        .to_string(),
+                );
+            }
+        }
+
+        if let Some(time_limit) = manifest.comptime.time_limit {
+            if time_limit <= 0.0 || Duration::try_from_secs_f64(time_limit).is_err() {
+                return Err(
+                    "the comptime time limit must be a positive number of seconds".to_string(),
+                );
+            }
+        }
+
+        for (lint, level) in &manifest.lints {
+            if !diagnostics::LINTS.contains(&lint.as_str()) {
+                return Err(format!(
+                    "unknown lint `{}`, expected one of {}",
+                    lint,
+                    diagnostics::LINTS
+                        .iter()
+                        .map(|lint| format!("`{lint}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            if LintLevel::parse(level).is_none() {
+                return Err(format!(
+                    "the level of `{}` must be \"allow\", \"warn\", or \"deny\"",
+                    lint
+                ));
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Sets the level of every diagnostic mentioned in `[lints]`,
+    /// and drops the ones which are allowed
+    pub fn apply_lints(&self, diagnostics: &mut Vec<Diagnostic>) {
+        if self.lints.is_empty() {
+            return;
+        }
+
+        for diagnostic in diagnostics.iter_mut() {
+            let level = diagnostic
+                .lint()
+                .and_then(|lint| self.lints.get(lint))
+                .and_then(|level| LintLevel::parse(level));
+            if let Some(level) = level {
+                diagnostic.set_lint_level(level);
+            }
+        }
+
+        diagnostics.retain(|diagnostic| !diagnostic.is_allowed());
+    }
+
+    pub fn comptime_limits(&self) -> codegen::ComptimeLimits {
+        let default = codegen::ComptimeLimits::default();
+
+        codegen::ComptimeLimits {
+            time: self
+                .comptime
+                .time_limit
+                .map_or(default.time, Duration::from_secs_f64),
+            memory: self.comptime.memory_limit.unwrap_or(default.memory),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::validation::{ValidationDiagnostic, ValidationDiagnosticKind};
+    use diagnostics::Severity;
+
+    use super::*;
+
+    fn parse(contents: &str) -> Result<Manifest, String> {
+        Manifest::parse(Path::new("/project/capy.toml"), contents)
+    }
+
+    fn warning(kind: ValidationDiagnosticKind) -> Diagnostic {
+        Diagnostic::from_validation(ValidationDiagnostic {
+            kind,
+            range: Default::default(),
+        })
+    }
+
+    #[test]
+    fn lints() {
+        let manifest = parse(
+            r#"
+                [lints]
+                always_true = "deny"
+                paren_in_condition = "allow"
+                constant_comparison = "warn"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifest.dir, Path::new("/project"));
+
+        let mut diagnostics = vec![
+            warning(ValidationDiagnosticKind::AlwaysTrue),
+            warning(ValidationDiagnosticKind::ParenInCondition),
+            warning(ValidationDiagnosticKind::ConstantComparison),
+            warning(ValidationDiagnosticKind::LoopNeverRuns),
+        ];
+        manifest.apply_lints(&mut diagnostics);
+
+        let levels: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.lint().unwrap(), diagnostic.severity()))
+            .collect();
+        assert_eq!(
+            levels,
+            [
+                ("always_true", Severity::Error),
+                ("constant_comparison", Severity::Warning),
+                ("loop_never_runs", Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_lint() {
+        let why = parse(
+            r#"
+                [lints]
+                always_ture = "deny"
+            "#,
+        )
+        .unwrap_err();
+        assert!(
+            why.starts_with("unknown lint `always_ture`, expected one of `always_true`"),
+            "{why}"
+        );
+    }
+
+    #[test]
+    fn bad_lint_level() {
+        let why = parse(
+            r#"
+                [lints]
+                always_true = "error"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            why,
+            r#"the level of `always_true` must be "allow", "warn", or "deny""#
+        );
+    }
+
+    #[test]
+    fn bad_package_name() {
+        let why = parse(
+            r#"
+                [package]
+                name = "../escape"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            why,
+            "the package name must be non-empty and can't contain file separators"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Reporting how far along a build is, for `--progress` and for anything using `Compiler`
+
+use std::{
+    fmt,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use codegen::CodegenProgress;
+use hir_ty::InferenceProgress;
+
+/// The progress bar is only redrawn this often, so that small files don't spend more time
+/// writing to the terminal than compiling
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+const BAR_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// The total isn't known until every import has been found
+    Parsing {
+        files: usize,
+    },
+    Inferring(InferenceProgress),
+    Compiling(CodegenProgress),
+}
+
+impl Progress {
+    fn fraction(self) -> Option<(usize, usize)> {
+        match self {
+            Progress::Parsing { .. } => None,
+            Progress::Inferring(progress) => Some((progress.inferred, progress.total)),
+            Progress::Compiling(progress) => Some((progress.compiled, progress.total)),
+        }
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::Parsing { files } => write!(f, "parsed {files} files"),
+            Progress::Inferring(InferenceProgress {
+                inferred,
+                total,
+                cyclic,
+            }) => {
+                write!(f, "inferred {inferred}/{total} globals")?;
+                if *cyclic > 0 {
+                    write!(f, " ({cyclic} in cycles)")?;
+                }
+                Ok(())
+            }
+            Progress::Compiling(CodegenProgress { compiled, total }) => {
+                write!(f, "compiled {compiled}/{total} functions")
+            }
+        }
+    }
+}
+
+/// Draws a single line to stderr which is overwritten as the build goes on.
+///
+/// It has to be cleared before anything else gets printed, otherwise the output ends up on the
+/// same line as the bar
+#[derive(Debug, Default)]
+pub struct ProgressBar {
+    last_drawn: Option<Instant>,
+    drawn_len: usize,
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, progress: Progress) {
+        if self
+            .last_drawn
+            .is_some_and(|last_drawn| last_drawn.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+
+        let line = match progress.fraction() {
+            Some((done, total)) => {
+                let filled = (done * BAR_WIDTH).checked_div(total).unwrap_or(0);
+                format!(
+                    "[{}{}] {progress}",
+                    "=".repeat(filled),
+                    " ".repeat(BAR_WIDTH - filled)
+                )
+            }
+            None => progress.to_string(),
+        };
+
+        self.draw(&line);
+        self.last_drawn = Some(Instant::now());
+    }
+
+    /// Erases the bar. The next update will draw it again straight away
+    pub fn clear(&mut self) {
+        if self.last_drawn.is_none() {
+            return;
+        }
+
+        self.draw("");
+        self.last_drawn = None;
+    }
+
+    fn draw(&mut self, line: &str) {
+        let mut stderr = io::stderr().lock();
+        // pad with spaces to cover up whatever was left over from the last line
+        let _ = write!(
+            stderr,
+            "\r{line}{}\r",
+            " ".repeat(self.drawn_len.saturating_sub(line.len()))
+        );
+        let _ = stderr.flush();
+        self.drawn_len = line.len();
+    }
+}
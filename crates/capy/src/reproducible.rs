@@ -0,0 +1,86 @@
+use std::{
+    env, fs, io,
+    path::Path,
+    process::{exit, Command},
+};
+
+/// Builds the program twice by running `capy build --reproducible` in two separate processes,
+/// then compares the resulting object files.
+///
+/// Separate processes are used so that anything depending on memory addresses (like the
+/// iteration order of maps keyed by interned types) actually gets a chance to change.
+pub(crate) fn check(output: &str, out_dir: &Path, output_dir: &Path) -> io::Result<()> {
+    let exe = env::current_exe()?;
+
+    // reuse the original arguments, but take control of the ones that decide the output
+    let mut args = Vec::new();
+    let mut cli_args = env::args_os().skip(1);
+    while let Some(arg) = cli_args.next() {
+        let Some(flag) = arg.to_str() else {
+            args.push(arg);
+            continue;
+        };
+
+        match flag {
+            "--check-reproducible" | "--reproducible" | "--no-exec" => {}
+            "-o" | "--output" | "--out-dir" => {
+                cli_args.next();
+            }
+            // `-o` is the only short flag, so anything else starting with it is `-o` with
+            // the output right after it, like `-ofoo` or `-o=foo`
+            _ if flag.starts_with("--output=")
+                || flag.starts_with("--out-dir=")
+                || (flag.starts_with("-o") && !flag.starts_with("--")) => {}
+            // everything after this gets passed on to the program as is
+            "--" => {
+                args.push(arg);
+                args.extend(cli_args.by_ref());
+            }
+            _ => args.push(arg),
+        }
+    }
+
+    let mut objects = Vec::with_capacity(2);
+
+    for attempt in 1..=2 {
+        let output = format!("{output}_reproducible_{attempt}");
+
+        let status = Command::new(&exe)
+            .args(&args)
+            .args(["--reproducible", "--no-exec", "--output", &output])
+            .arg("--out-dir")
+            .arg(out_dir)
+            .status()?;
+
+        if !status.success() {
+            exit(status.code().unwrap_or(1));
+        }
+
+        let mut object_file = output_dir.join(&output);
+        object_file.set_extension("o");
+
+        objects.push((fs::read(&object_file)?, object_file));
+    }
+
+    let (first, first_file) = &objects[0];
+    let (second, second_file) = &objects[1];
+
+    if first == second {
+        println!("\nthe build is reproducible ({} bytes)", first.len());
+        return Ok(());
+    }
+
+    let difference = first
+        .iter()
+        .zip(second.iter())
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| first.len().min(second.len()));
+
+    println!(
+        "\nerror: the build is not reproducible, `{}` and `{}` first differ at byte {}",
+        first_file.display(),
+        second_file.display(),
+        difference
+    );
+    exit(1)
+}
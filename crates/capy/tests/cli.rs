@@ -0,0 +1,338 @@
+//! These run the `capy` binary itself, for the parts of the CLI that the `Compiler` facade
+//! doesn't cover, like linking and the files left in the out dir
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use target_lexicon::Triple;
+
+/// Each test gets a directory of its own, since tests run at the same time
+fn test_dir(test_name: &str) -> PathBuf {
+    let dir = env::temp_dir().join("capy_cli_tests").join(test_name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs a command which takes a `--mod-dir`, using the `core` of this repo
+fn capy(args: &[&str]) -> Output {
+    run(Command::new(env!("CARGO_BIN_EXE_capy"))
+        .args(args)
+        .arg("--mod-dir")
+        .arg(mod_dir()))
+}
+
+/// The same as `capy`, but run from within `dir`,
+/// since files can only import other files inside of the current directory
+fn capy_in(dir: &Path, args: &[&str]) -> Output {
+    run(Command::new(env!("CARGO_BIN_EXE_capy"))
+        .current_dir(dir)
+        .args(args)
+        .arg("--mod-dir")
+        .arg(mod_dir()))
+}
+
+fn capy_without_mod_dir(args: &[&str]) -> Output {
+    run(Command::new(env!("CARGO_BIN_EXE_capy")).args(args))
+}
+
+fn mod_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+fn run(command: &mut Command) -> Output {
+    let output = command.output().unwrap();
+
+    println!("{}", String::from_utf8_lossy(&output.stdout));
+    eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+
+    output
+}
+
+/// Where a build with `--out-dir out_dir` puts its files
+fn build_dir(out_dir: &Path) -> PathBuf {
+    out_dir.join(Triple::host().to_string()).join("debug")
+}
+
+#[cfg(target_os = "linux")]
+mod dl {
+    use std::ffi::{c_char, c_int, c_void};
+
+    pub const RTLD_NOW: c_int = 2;
+
+    #[link(name = "dl")]
+    extern "C" {
+        pub fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        pub fn dlclose(handle: *mut c_void) -> c_int;
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn build_lib_without_main() {
+    use std::ffi::CString;
+
+    let dir = test_dir("build_lib_without_main");
+    let file = dir.join("mylib.capy");
+    fs::write(
+        &file,
+        r#"
+            @export("capy_add")
+            add :: (a: i32, b: i32) -> i32 {
+                a + b
+            }
+        "#,
+    )
+    .unwrap();
+
+    let out_dir = dir.join("out");
+    let output = capy(&[
+        "build",
+        file.to_str().unwrap(),
+        "--lib",
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let lib = CString::new(
+        build_dir(&out_dir)
+            .join("mylib.so")
+            .to_str()
+            .unwrap()
+            .as_bytes(),
+    )
+    .unwrap();
+
+    unsafe {
+        let handle = dl::dlopen(lib.as_ptr(), dl::RTLD_NOW);
+        assert!(!handle.is_null(), "couldn't load the library");
+
+        let symbol = CString::new("capy_add").unwrap();
+        let add = dl::dlsym(handle, symbol.as_ptr());
+        assert!(!add.is_null(), "`capy_add` wasn't exported");
+
+        let add = std::mem::transmute::<*mut std::ffi::c_void, extern "C" fn(i32, i32) -> i32>(add);
+        assert_eq!(add(2, 40), 42);
+
+        dl::dlclose(handle);
+    }
+}
+
+#[test]
+fn build_without_main() {
+    let dir = test_dir("build_without_main");
+    let file = dir.join("mylib.capy");
+    fs::write(&file, "add :: (a: i32, b: i32) -> i32 { a + b }").unwrap();
+
+    // only libraries can leave out the entry point
+    let output = capy(&[
+        "build",
+        file.to_str().unwrap(),
+        "--out-dir",
+        dir.join("out").to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("`main`"));
+}
+
+#[test]
+fn clean_removes_out_dir() {
+    let dir = test_dir("clean_removes_out_dir");
+    let file = dir.join("main.capy");
+    fs::write(&file, "main :: () {}").unwrap();
+
+    let out_dir = dir.join("out");
+    let output = capy(&[
+        "build",
+        file.to_str().unwrap(),
+        "--no-exec",
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(build_dir(&out_dir).join("main.o").exists());
+
+    let output = capy_without_mod_dir(&["clean", "--out-dir", out_dir.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(!out_dir.exists());
+}
+
+#[test]
+fn clean_keeps_dir_not_made_by_capy() {
+    let dir = test_dir("clean_keeps_dir_not_made_by_capy");
+    let file = dir.join("main.capy");
+    fs::write(&file, "main :: () {}").unwrap();
+
+    // building into a directory that already exists doesn't make it capy's
+    let output = capy(&[
+        "build",
+        file.to_str().unwrap(),
+        "--no-exec",
+        "--out-dir",
+        dir.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let output = capy_without_mod_dir(&["clean", "--out-dir", dir.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("wasn't made by capy"));
+    assert!(file.exists());
+}
+
+/// Reflection goes through the maps keyed by interned types, which is what used to change
+/// order between builds
+fn reflection_example() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples/reflection.capy")
+}
+
+#[test]
+fn reproducible_builds_match() {
+    let dir = test_dir("reproducible_builds_match");
+    let file = reflection_example();
+
+    let objects = ["first", "second"].map(|out_dir| {
+        let out_dir = dir.join(out_dir);
+        let output = capy(&[
+            "build",
+            file.to_str().unwrap(),
+            "--reproducible",
+            "--no-exec",
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+        ]);
+        assert!(output.status.success());
+
+        fs::read(build_dir(&out_dir).join("reflection.o")).unwrap()
+    });
+
+    assert!(objects[0] == objects[1], "the object files are different");
+}
+
+#[test]
+fn check_reproducible() {
+    let dir = test_dir("check_reproducible");
+
+    let output = capy(&[
+        "build",
+        reflection_example().to_str().unwrap(),
+        "--check-reproducible",
+        "--out-dir",
+        dir.join("out").to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("the build is reproducible"));
+}
+
+#[test]
+fn bugreport() {
+    let dir = test_dir("bugreport");
+    let file = dir.join("main.capy");
+    fs::write(
+        &file,
+        r#"
+            core :: #mod("core");
+            helpers :: #import("helpers.capy");
+
+            main :: () {
+                x : i32 = helpers.name;
+            }
+        "#,
+    )
+    .unwrap();
+    fs::write(dir.join("helpers.capy"), r#"name :: "capy";"#).unwrap();
+
+    let report = dir.join("report.md");
+    let output = capy_in(
+        &dir,
+        &[
+            "bugreport",
+            file.to_str().unwrap(),
+            "--output",
+            report.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success());
+
+    let report = fs::read_to_string(report).unwrap();
+    assert!(report.starts_with("## Bug Report"));
+    assert!(report.contains("- **Exit Status:** 1"));
+    assert!(report.contains("expected `i32` but found `str`"));
+    // the files are shown relative to where `capy bugreport` was run
+    assert!(report.contains("### `main.capy`"));
+    assert!(report.contains("### `helpers.capy`"));
+    assert!(report.contains(r#"name :: "capy";"#));
+    // only the user's own files are included
+    assert!(!report.contains("core/src/mod.capy"));
+}
+
+#[test]
+fn diagnostics_grouped_by_file() {
+    let dir = test_dir("diagnostics_grouped_by_file");
+    fs::write(
+        dir.join("main.capy"),
+        r#"
+            b :: #import("b.capy");
+            a :: #import("a.capy");
+
+            main :: () {
+                x : i32 = true;
+            }
+        "#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("a.capy"),
+        "foo :: () { x : bool = 1; y : str = 'c'; }",
+    )
+    .unwrap();
+    fs::write(dir.join("b.capy"), "bar :: () { x : char = 2.5; }").unwrap();
+
+    let output = capy_in(&dir, &["build", "main.capy"]);
+    assert!(!output.status.success());
+
+    // the root file comes first, then the imported files by their path
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let headers = stdout
+        .lines()
+        .filter(|line| line.starts_with("==="))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            "=== main.capy (1 error) ===",
+            "=== a.capy (2 errors) ===",
+            "=== b.capy (1 error) ===",
+        ]
+    );
+}
+
+#[test]
+fn progress_bar() {
+    let dir = test_dir("progress_bar");
+    let file = dir.join("main.capy");
+    fs::write(&file, "main :: () {}").unwrap();
+
+    let output = capy(&[
+        "build",
+        file.to_str().unwrap(),
+        "--progress",
+        "--no-exec",
+        "--out-dir",
+        dir.join("out").to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    // the bar goes to stderr, so it never gets mixed up with the rest of the output
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("\rparsed 1 files"), "{stderr:?}");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("parsed 1 files"));
+
+    // and it's cleared once the build is over
+    let last_line = stderr.trim_end_matches('\r').rsplit('\r').next().unwrap();
+    assert!(last_line.trim().is_empty(), "{stderr:?}");
+}
@@ -1,25 +1,36 @@
 //! This module is for JIT'ing all the code needed to calculate the value of comptime blocks
 use cranelift::{
-    codegen::ir::Endianness,
+    codegen::ir::{Endianness, LibCall},
     prelude::{settings, types, Configurable, FunctionBuilderContext},
 };
 use cranelift_jit::{JITBuilder, JITModule};
 use cranelift_module::{DataDescription, Module};
 use hir::FQComptime;
-use hir_ty::ComptimeResult;
+use hir_ty::{ComptimeLimit, ComptimeLimitExceeded, ComptimeResult, InternTyExt, Ty};
 use interner::Interner;
+use internment::Intern;
 use num_traits::ToBytes;
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::{
     alloc::Layout,
+    cell::RefCell,
     collections::VecDeque,
+    ffi::{c_char, c_void, CStr},
     mem::{self, size_of},
+    panic,
+    sync::{
+        atomic::{AtomicI32, AtomicU8, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
 };
 use uid_gen::UIDGenerator;
 
 use crate::{
-    compiler::{abi::Abi, MetaTyData, MetaTyInfoArrays},
-    convert::{FinalTy, GetFinalTy, ToTyId},
+    compiler::{abi::Abi, MetaTyData, MetaTyInfoArrays, ReadOnlyData},
+    convert::{FinalTy, GetFinalTy, NumberType, ToTyId},
     layout::GetLayoutInfo,
     mangle::Mangle,
     Verbosity,
@@ -27,38 +38,6 @@ use crate::{
 
 use super::Compiler;
 
-pub(crate) trait ComptimeBytes {
-    fn into_bytes(
-        self,
-        meta_tys: &mut MetaTyData,
-        endianness: Endianness,
-        ptr_ty: types::Type,
-    ) -> Option<Box<[u8]>>;
-}
-
-impl ComptimeBytes for ComptimeResult {
-    fn into_bytes(
-        self,
-        meta_tys: &mut MetaTyData,
-        endianness: Endianness,
-        ptr_ty: types::Type,
-    ) -> Option<Box<[u8]>> {
-        match self {
-            ComptimeResult::Type(ty) => {
-                Some(Box::new(ty.to_type_id(meta_tys, ptr_ty).to_ne_bytes()))
-            }
-            ComptimeResult::Integer { num, bit_width } => {
-                Some(num.into_bytes(endianness, bit_width).into_boxed_slice())
-            }
-            ComptimeResult::Float { num, bit_width } => {
-                Some(num.into_bytes(endianness, bit_width).into_boxed_slice())
-            }
-            ComptimeResult::Data(bytes) => Some(bytes),
-            ComptimeResult::Void => None,
-        }
-    }
-}
-
 pub(crate) trait IntBytes {
     fn into_bytes(self, endianness: Endianness, target_bitwidth: u8) -> Vec<u8>;
 }
@@ -107,6 +86,365 @@ impl IntBytes for f64 {
     }
 }
 
+/// The reverse of `IntBytes::into_bytes`, used to read constants back out of their data
+pub(crate) fn number_from_bytes(
+    bytes: &[u8],
+    number_ty: NumberType,
+    endianness: Endianness,
+) -> ComptimeResult {
+    let mut buf = [0; 16];
+    match endianness {
+        Endianness::Big => buf[16 - bytes.len()..].copy_from_slice(bytes),
+        Endianness::Little => buf[..bytes.len()].copy_from_slice(bytes),
+    }
+    let num = match endianness {
+        Endianness::Big => u128::from_be_bytes(buf),
+        Endianness::Little => u128::from_le_bytes(buf),
+    };
+
+    if number_ty.float {
+        ComptimeResult::Float {
+            num: match number_ty.bit_width() {
+                32 => f32::from_bits(num as u32) as f64,
+                64 => f64::from_bits(num as u64),
+                _ => unreachable!(),
+            },
+            bit_width: number_ty.bit_width(),
+        }
+    } else {
+        ComptimeResult::Integer {
+            num: num as u64,
+            bit_width: number_ty.bit_width(),
+        }
+    }
+}
+
+/// Comptime code runs on a thread of its own, which gets as much stack as a main thread usually does
+const COMPTIME_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// How long a single comptime block can run for, and how many bytes it can have allocated at once,
+/// before it gets reported instead of hanging the build
+#[derive(Debug, Clone, Copy)]
+pub struct ComptimeLimits {
+    pub time: Duration,
+    pub memory: u64,
+}
+
+impl Default for ComptimeLimits {
+    fn default() -> Self {
+        Self {
+            time: Duration::from_secs(10),
+            memory: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Why the comptime block stopped, stored in `Sandbox::cancel_flag`
+const RUNNING: u8 = 0;
+const TIMED_OUT: u8 = 1;
+const OUT_OF_MEMORY: u8 = 2;
+const EXITED: u8 = 3;
+const CALLED_EXTERN: u8 = 4;
+
+/// The allocations which comptime code hasn't freed yet
+#[derive(Default)]
+struct Allocations {
+    sizes: FxHashMap<usize, u64>,
+    total: u64,
+}
+
+/// Lets the compiler stop a comptime block which is still running.
+///
+/// There's no way to stop a thread from the outside, so the JIT'd code checks `cancel_flag`
+/// after every call and at the start of every loop iteration, and returns as soon as it's set
+struct Sandbox {
+    cancel_flag: AtomicU8,
+    exit_status: AtomicI32,
+    memory_limit: u64,
+    allocations: Mutex<Allocations>,
+}
+
+impl Sandbox {
+    fn new(memory_limit: u64) -> Self {
+        Self {
+            cancel_flag: AtomicU8::new(RUNNING),
+            exit_status: AtomicI32::new(0),
+            memory_limit,
+            allocations: Mutex::default(),
+        }
+    }
+
+    /// The address that the JIT'd code reads to know if it should stop
+    fn cancel_flag(&self) -> usize {
+        self.cancel_flag.as_ptr() as usize
+    }
+
+    /// Only the first reason a block was stopped for gets kept
+    fn cancel(&self, reason: u8) {
+        let _ =
+            self.cancel_flag
+                .compare_exchange(RUNNING, reason, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    fn reset(&self) {
+        self.cancel_flag.store(RUNNING, Ordering::SeqCst);
+        *self.allocations.lock().unwrap() = Allocations::default();
+    }
+
+    /// Whether the block can afford for `old_ptr` to be resized (or a new allocation made) to `size`.
+    ///
+    /// This stops the block if it can't, before anything actually gets allocated
+    fn fits(&self, old_ptr: Option<usize>, size: u64) -> bool {
+        let allocations = self.allocations.lock().unwrap();
+        let old_size = old_ptr
+            .and_then(|old_ptr| allocations.sizes.get(&old_ptr).copied())
+            .unwrap_or(0);
+
+        if (allocations.total - old_size).saturating_add(size) > self.memory_limit {
+            drop(allocations);
+            self.cancel(OUT_OF_MEMORY);
+            return false;
+        }
+
+        true
+    }
+
+    fn allocated(&self, old_ptr: Option<usize>, ptr: usize, size: u64) {
+        let mut allocations = self.allocations.lock().unwrap();
+
+        if ptr == 0 {
+            // a failed `realloc` leaves the old allocation where it was
+            return;
+        }
+        if let Some(old_size) = old_ptr.and_then(|old_ptr| allocations.sizes.remove(&old_ptr)) {
+            allocations.total -= old_size;
+        }
+        allocations.sizes.insert(ptr, size);
+        allocations.total += size;
+    }
+
+    fn freed(&self, ptr: usize) {
+        let mut allocations = self.allocations.lock().unwrap();
+
+        if let Some(size) = allocations.sizes.remove(&ptr) {
+            allocations.total -= size;
+        }
+    }
+
+    /// What the block was stopped for, if it was stopped at all
+    fn exceeded(&self, limits: ComptimeLimits) -> Option<ComptimeLimit> {
+        match self.cancel_flag.load(Ordering::SeqCst) {
+            RUNNING => None,
+            TIMED_OUT => Some(ComptimeLimit::Time(limits.time)),
+            OUT_OF_MEMORY => Some(ComptimeLimit::Memory(limits.memory)),
+            EXITED => Some(ComptimeLimit::Exit(self.exit_status.load(Ordering::SeqCst))),
+            CALLED_EXTERN => Some(ComptimeLimit::Extern),
+            _ => unreachable!(),
+        }
+    }
+}
+
+thread_local! {
+    /// The sandbox of the comptime block running on the current thread
+    static SANDBOX: RefCell<Option<Arc<Sandbox>>> = const { RefCell::new(None) };
+}
+
+fn with_sandbox<T>(f: impl FnOnce(&Sandbox) -> T) -> Option<T> {
+    SANDBOX.with_borrow(|sandbox| sandbox.as_deref().map(f))
+}
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn calloc(count: usize, size: usize) -> *mut c_void;
+    fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+/// Allocates through `alloc` if the block can afford `size` more bytes, and returns null otherwise.
+///
+/// The block gets stopped right after a null return, so it never gets to use it
+fn tracked_allocation(
+    old_ptr: Option<usize>,
+    size: u64,
+    alloc: impl FnOnce() -> *mut c_void,
+) -> *mut c_void {
+    if !with_sandbox(|sandbox| sandbox.fits(old_ptr, size)).unwrap_or(true) {
+        return std::ptr::null_mut();
+    }
+
+    let ptr = alloc();
+    with_sandbox(|sandbox| sandbox.allocated(old_ptr, ptr as usize, size));
+    ptr
+}
+
+/// What comptime code calls instead of `malloc`, so that its live memory can be tracked
+extern "C" fn comptime_malloc(size: usize) -> *mut c_void {
+    tracked_allocation(None, size as u64, || unsafe { malloc(size) })
+}
+
+extern "C" fn comptime_calloc(count: usize, size: usize) -> *mut c_void {
+    let total = (count as u64).saturating_mul(size as u64);
+    tracked_allocation(None, total, || unsafe { calloc(count, size) })
+}
+
+extern "C" fn comptime_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+    let old_ptr = (!ptr.is_null()).then_some(ptr as usize);
+    tracked_allocation(old_ptr, size as u64, || unsafe { realloc(ptr, size) })
+}
+
+extern "C" fn comptime_free(ptr: *mut c_void) {
+    with_sandbox(|sandbox| sandbox.freed(ptr as usize));
+    unsafe { free(ptr) }
+}
+
+/// What comptime code calls instead of `exit`, which would otherwise take the compiler down with it
+extern "C" fn comptime_exit(status: i32) {
+    with_sandbox(|sandbox| {
+        sandbox.exit_status.store(status, Ordering::SeqCst);
+        sandbox.cancel(EXITED);
+    });
+}
+
+/// What comptime code calls instead of the externs that aren't in `hir_ty::COMPTIME_EXTERNS`
+extern "C" fn forbidden_extern() {
+    with_sandbox(|sandbox| sandbox.cancel(CALLED_EXTERN));
+}
+
+/// Runs the JIT'd code of a comptime block on its own thread, so that the compiler can stop
+/// a block which takes too long.
+///
+/// A stopped block returns zeroes, which `run` must not look into
+fn run_with_limits<T: Send + 'static>(
+    sandbox: &Arc<Sandbox>,
+    limits: ComptimeLimits,
+    run: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, ComptimeLimit> {
+    sandbox.reset();
+
+    let (sender, receiver) = mpsc::channel();
+
+    let thread_sandbox = sandbox.clone();
+    let thread = thread::Builder::new()
+        .name("comptime".to_string())
+        .stack_size(COMPTIME_STACK_SIZE)
+        .spawn(move || {
+            SANDBOX.set(Some(thread_sandbox));
+            let _ = sender.send(run());
+        })
+        .expect("couldn't start a thread for comptime code");
+
+    let result = match receiver.recv_timeout(limits.time) {
+        Ok(result) => Some(result),
+        Err(RecvTimeoutError::Timeout) => {
+            sandbox.cancel(TIMED_OUT);
+            receiver.recv().ok()
+        }
+        Err(RecvTimeoutError::Disconnected) => None,
+    };
+
+    if let Err(payload) = thread.join() {
+        panic::resume_unwind(payload);
+    }
+
+    match sandbox.exceeded(limits) {
+        Some(limit) => Err(limit),
+        None => Ok(result.expect("the comptime thread finished without a result")),
+    }
+}
+
+/// Breaks the raw bytes of a comptime value down into its members, items, and payloads.
+///
+/// This has to happen before the JIT's memory is freed, since any strings get followed and copied
+unsafe fn result_from_bytes(
+    bytes: &[u8],
+    ty: Intern<Ty>,
+    meta_tys: &FxHashMap<u32, &Intern<Ty>>,
+    endianness: Endianness,
+) -> ComptimeResult {
+    match ty.absolute_ty_keep_variants() {
+        Ty::Type => {
+            let id = u32::from_ne_bytes(bytes[..4].try_into().unwrap());
+
+            ComptimeResult::Type(**meta_tys.get(&id).unwrap())
+        }
+        Ty::String => {
+            let text = CStr::from_ptr((bytes.as_ptr() as *const *const c_char).read_unaligned());
+
+            ComptimeResult::String(text.to_string_lossy().into_owned())
+        }
+        Ty::Array { size, sub_ty, .. } if matches!(sub_ty.as_ref(), Ty::UInt(8) | Ty::Char) => {
+            ComptimeResult::Bytes(bytes[..*size as usize].into())
+        }
+        Ty::Array { size, sub_ty, .. } => {
+            let stride = sub_ty.stride() as usize;
+
+            ComptimeResult::Array {
+                ty,
+                items: (0..*size as usize)
+                    .map(|idx| {
+                        result_from_bytes(&bytes[idx * stride..], *sub_ty, meta_tys, endianness)
+                    })
+                    .collect(),
+            }
+        }
+        Ty::Struct { .. } | Ty::Tuple { .. } => {
+            let member_tys = ty
+                .as_struct()
+                .map(|members| members.into_iter().map(|member| member.ty).collect())
+                .or_else(|| ty.as_tuple())
+                .unwrap();
+            let layout = ty.struct_layout().unwrap();
+
+            ComptimeResult::Struct {
+                ty,
+                members: member_tys
+                    .into_iter()
+                    .zip(layout.offsets())
+                    .map(|(member_ty, offset)| {
+                        result_from_bytes(
+                            &bytes[*offset as usize..],
+                            member_ty,
+                            meta_tys,
+                            endianness,
+                        )
+                    })
+                    .collect(),
+            }
+        }
+        Ty::Enum { variants, .. } => {
+            let discriminant = bytes[ty.enum_layout().unwrap().discriminant_offset() as usize];
+
+            let variant = *variants
+                .iter()
+                .find(|variant| {
+                    matches!(variant.as_ref(), Ty::Variant { discriminant: d, .. } if *d == discriminant as u64)
+                })
+                .expect("the discriminant should belong to one of the variants");
+            let Ty::Variant { sub_ty, .. } = variant.as_ref() else {
+                unreachable!()
+            };
+
+            ComptimeResult::Variant {
+                ty,
+                variant,
+                payload: Box::new(result_from_bytes(bytes, *sub_ty, meta_tys, endianness)),
+            }
+        }
+        Ty::Variant { sub_ty, .. } => ComptimeResult::Variant {
+            ty,
+            variant: ty.absolute_intern_ty(false),
+            payload: Box::new(result_from_bytes(bytes, *sub_ty, meta_tys, endianness)),
+        },
+        _ => match ty.get_final_ty() {
+            FinalTy::Number(number_ty) if number_ty.bit_width() < 128 => {
+                number_from_bytes(&bytes[..ty.size() as usize], number_ty, endianness)
+            }
+            FinalTy::Void => ComptimeResult::Void,
+            _ => ComptimeResult::Data(bytes[..ty.size() as usize].into()),
+        },
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn eval_comptime_blocks<'a>(
     verbosity: Verbosity,
@@ -117,9 +455,14 @@ pub fn eval_comptime_blocks<'a>(
     world_bodies: &'a hir::WorldBodies,
     tys: &'a hir_ty::ProjectInference,
     target_pointer_bit_width: u8,
-) {
+    limits: ComptimeLimits,
+) -> Result<(), ComptimeLimitExceeded> {
+    // blocks that were already evaluated (e.g. for an array size) would otherwise get compiled
+    // a second time, under the same name as the data holding their previous result
+    to_eval.retain(|ctc| !results.contains_key(ctc));
+
     if to_eval.is_empty() {
-        return;
+        return Ok(());
     }
 
     let mut flag_builder = settings::builder();
@@ -131,9 +474,31 @@ pub fn eval_comptime_blocks<'a>(
     let isa = isa_builder
         .finish(settings::Flags::new(flag_builder))
         .unwrap();
-    let builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
+    let mut builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
+
+    // `hir_ty` already reports comptime blocks which use any other externs,
+    // but anything it couldn't see through gets linked to a function that stops the block
+    let libcall_names = cranelift_module::default_libcall_names();
+    let allowed: FxHashSet<String> = hir_ty::COMPTIME_EXTERNS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(
+            LibCall::all_libcalls()
+                .iter()
+                .map(|libcall| libcall_names(*libcall)),
+        )
+        .collect();
+    builder.symbol_lookup_fn(Box::new(move |name| match name {
+        "malloc" => Some(comptime_malloc as *const u8),
+        "calloc" => Some(comptime_calloc as *const u8),
+        "realloc" => Some(comptime_realloc as *const u8),
+        "free" => Some(comptime_free as *const u8),
+        "exit" => Some(comptime_exit as *const u8),
+        _ => (!allowed.contains(name)).then_some(forbidden_extern as *const u8),
+    }));
 
     let mut module = JITModule::new(builder);
+    let sandbox = Arc::new(Sandbox::new(limits.memory));
     let default_abi = module.target_config().into();
 
     let mut compiler = Compiler {
@@ -151,10 +516,13 @@ pub fn eval_comptime_blocks<'a>(
         meta_tys: MetaTyData::default(),
         cmd_args_slice: None,
         functions: FxHashMap::default(),
+        instances: FxHashMap::default(),
         compiler_defined_functions: FxHashMap::default(),
         data: FxHashMap::default(),
         str_id_gen: UIDGenerator::default(),
         i128_id_gen: UIDGenerator::default(),
+        read_only_data: ReadOnlyData::default(),
+        vtables: FxHashMap::default(),
         comptime_results: results,
         comptime_data: FxHashMap::default(),
         ptr_ty: match target_pointer_bit_width {
@@ -165,6 +533,8 @@ pub fn eval_comptime_blocks<'a>(
             _ => unreachable!(),
         },
         default_abi,
+        progress: None,
+        cancel_flag: Some(sandbox.cancel_flag()),
     };
 
     compiler.finalize_tys();
@@ -189,9 +559,11 @@ pub fn eval_comptime_blocks<'a>(
             ),
             &ctc.to_mangled_name(compiler.mod_dir, compiler.interner),
             ctc.file,
+            None,
             ctc.expr,
             vec![],
             return_ty,
+            vec![],
             Abi::Simplified,
         );
 
@@ -227,8 +599,8 @@ pub fn eval_comptime_blocks<'a>(
     // This also prepares the code for JIT execution
     module.finalize_definitions().unwrap();
 
-    fn run_comptime_float<T: ToBytes + Into<f64> + Copy>(code_ptr: *const u8) -> ComptimeResult {
-        let comptime = unsafe { mem::transmute::<*const u8, fn() -> T>(code_ptr) };
+    fn run_comptime_float<T: ToBytes + Into<f64> + Copy>(code_ptr: usize) -> ComptimeResult {
+        let comptime = unsafe { mem::transmute::<usize, fn() -> T>(code_ptr) };
         let result = comptime();
 
         ComptimeResult::Float {
@@ -237,8 +609,8 @@ pub fn eval_comptime_blocks<'a>(
         }
     }
 
-    fn run_comptime_int<T: ToBytes + Into<u64> + Copy>(code_ptr: *const u8) -> ComptimeResult {
-        let comptime = unsafe { mem::transmute::<*const u8, fn() -> T>(code_ptr) };
+    fn run_comptime_int<T: ToBytes + Into<u64> + Copy>(code_ptr: usize) -> ComptimeResult {
+        let comptime = unsafe { mem::transmute::<usize, fn() -> T>(code_ptr) };
         let result = comptime();
 
         ComptimeResult::Integer {
@@ -247,68 +619,136 @@ pub fn eval_comptime_blocks<'a>(
         }
     }
 
-    while let Some((ctc, func_id, return_ty)) = comptime_funcs.pop() {
-        let code_ptr = module.get_finalized_function(func_id);
-
-        if *return_ty == hir_ty::Ty::Type {
-            let comptime = unsafe { mem::transmute::<*const u8, fn() -> u32>(code_ptr) };
-            let ty = comptime();
-
-            let ty = meta_tys.get(&ty).unwrap();
-
-            results.insert(ctc, ComptimeResult::Type(**ty));
-            continue;
-        }
+    // every block has finished (or been stopped) by the time this returns,
+    // so the JIT's memory can be freed either way
+    let evaluated = (|| {
+        while let Some((ctc, func_id, return_ty)) = comptime_funcs.pop() {
+            let exceeded = |limit| ComptimeLimitExceeded {
+                comptime: ctc,
+                limit,
+            };
+
+            // raw pointers can't be sent to another thread, but addresses can
+            let code_ptr = module.get_finalized_function(func_id) as usize;
+
+            if *return_ty == hir_ty::Ty::Type {
+                let ty = run_with_limits(&sandbox, limits, move || {
+                    let comptime = unsafe { mem::transmute::<usize, fn() -> u32>(code_ptr) };
+                    comptime()
+                })
+                .map_err(exceeded)?;
+
+                let ty = meta_tys.get(&ty).unwrap();
+
+                results.insert(ctc, ComptimeResult::Type(**ty));
+                continue;
+            }
 
-        match return_ty.get_final_ty() {
-            FinalTy::Number(number_ty) => {
-                let result = match number_ty.ty {
-                    types::F32 => run_comptime_float::<f32>(code_ptr),
-                    types::F64 => run_comptime_float::<f64>(code_ptr),
-                    types::I8 => run_comptime_int::<u8>(code_ptr),
-                    types::I16 => run_comptime_int::<u16>(code_ptr),
-                    types::I32 => run_comptime_int::<u32>(code_ptr),
-                    types::I64 => run_comptime_int::<u64>(code_ptr),
-                    types::I128 => {
+            match return_ty.absolute_ty() {
+                hir_ty::Ty::String => {
+                    // the string data lives in the JIT's memory, so it has to be copied out
+                    // before that memory gets freed
+                    let text = run_with_limits(&sandbox, limits, move || {
+                        let comptime = unsafe { mem::transmute::<usize, fn() -> usize>(code_ptr) };
+                        comptime()
+                    })
+                    .map_err(exceeded)?;
+                    let text = unsafe { CStr::from_ptr(text as *const c_char) }
+                        .to_string_lossy()
+                        .into_owned();
+
+                    results.insert(ctc, ComptimeResult::String(text));
+                    continue;
+                }
+                hir_ty::Ty::Array { sub_ty, .. }
+                    if matches!(sub_ty.as_ref(), hir_ty::Ty::UInt(8) | hir_ty::Ty::Char) =>
+                {
+                    let size = return_ty.size() as usize;
+                    let bytes = run_with_limits(&sandbox, limits, move || {
                         let comptime =
-                            unsafe { mem::transmute::<*const u8, fn() -> u128>(code_ptr) };
-                        let result = comptime();
-
-                        ComptimeResult::Data(Box::new(result.to_ne_bytes()))
-                    }
-                    _ => unreachable!(),
-                };
-
-                results.insert(ctc, result);
+                            unsafe { mem::transmute::<usize, fn(*mut u8) -> *mut u8>(code_ptr) };
+
+                        let mut bytes = vec![0_u8; size];
+                        comptime(bytes.as_mut_ptr());
+                        bytes
+                    })
+                    .map_err(exceeded)?;
+
+                    results.insert(ctc, ComptimeResult::Bytes(bytes.into_boxed_slice()));
+                    continue;
+                }
+                _ => {}
             }
-            FinalTy::Pointer(_) => {
-                let layout =
-                    Layout::from_size_align(return_ty.size() as usize, return_ty.align() as usize)
-                        .expect("Invalid layout");
-                let raw = unsafe { std::alloc::alloc(layout) };
-
-                let comptime =
-                    unsafe { mem::transmute::<*const u8, fn(*mut u8) -> *mut u8>(code_ptr) };
 
-                comptime(raw);
+            match return_ty.get_final_ty() {
+                FinalTy::Number(number_ty) => {
+                    let result = run_with_limits(&sandbox, limits, move || match number_ty.ty {
+                        types::F32 => run_comptime_float::<f32>(code_ptr),
+                        types::F64 => run_comptime_float::<f64>(code_ptr),
+                        types::I8 => run_comptime_int::<u8>(code_ptr),
+                        types::I16 => run_comptime_int::<u16>(code_ptr),
+                        types::I32 => run_comptime_int::<u32>(code_ptr),
+                        types::I64 => run_comptime_int::<u64>(code_ptr),
+                        types::I128 => {
+                            let comptime =
+                                unsafe { mem::transmute::<usize, fn() -> u128>(code_ptr) };
+                            let result = comptime();
+
+                            ComptimeResult::Data(Box::new(result.to_ne_bytes()))
+                        }
+                        _ => unreachable!(),
+                    })
+                    .map_err(exceeded)?;
+
+                    results.insert(ctc, result);
+                }
+                FinalTy::Pointer(_) => {
+                    let layout = Layout::from_size_align(
+                        return_ty.size() as usize,
+                        return_ty.align() as usize,
+                    )
+                    .expect("Invalid layout");
+
+                    let bytes = run_with_limits(&sandbox, limits, move || {
+                        let raw = unsafe { std::alloc::alloc(layout) };
 
-                let bytes = unsafe {
-                    let slice = std::ptr::slice_from_raw_parts(raw, return_ty.size() as usize)
-                        as *mut [u8];
-
-                    Box::from_raw(slice)
-                };
-
-                results.insert(ctc, ComptimeResult::Data(bytes));
-            }
-            FinalTy::Void => {
-                let comptime = unsafe { mem::transmute::<*const u8, fn()>(code_ptr) };
-                comptime();
-                results.insert(ctc, ComptimeResult::Void);
+                        let comptime =
+                            unsafe { mem::transmute::<usize, fn(*mut u8) -> *mut u8>(code_ptr) };
+
+                        comptime(raw);
+
+                        unsafe {
+                            let slice =
+                                std::ptr::slice_from_raw_parts(raw, layout.size()) as *mut [u8];
+
+                            Box::from_raw(slice)
+                        }
+                    })
+                    .map_err(exceeded)?;
+
+                    let result = unsafe {
+                        result_from_bytes(&bytes, return_ty, &meta_tys, module.isa().endianness())
+                    };
+
+                    results.insert(ctc, result);
+                }
+                FinalTy::Void => {
+                    run_with_limits(&sandbox, limits, move || {
+                        let comptime = unsafe { mem::transmute::<usize, fn()>(code_ptr) };
+                        comptime();
+                    })
+                    .map_err(exceeded)?;
+
+                    results.insert(ctc, ComptimeResult::Void);
+                }
             }
         }
-    }
+
+        Ok(())
+    })();
 
     // todo: don't do this, and instead reuse previously compiled function pointers and data
     unsafe { module.free_memory() };
+
+    evaluated
 }
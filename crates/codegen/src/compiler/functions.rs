@@ -1,20 +1,23 @@
 use std::collections::VecDeque;
 
 use cranelift::{
-    codegen::ir::{Endianness, FuncRef},
+    codegen::{
+        entity::EntityRef,
+        ir::{Endianness, FuncRef, StackSlot},
+    },
     frontend::Switch,
     prelude::{
-        types, Block, FloatCC, FunctionBuilder, InstBuilder, IntCC, MemFlags, StackSlotData,
-        StackSlotKind, TrapCode, Value, Variable,
+        types, AbiParam, Block, FloatCC, FunctionBuilder, InstBuilder, IntCC, MemFlags,
+        StackSlotData, StackSlotKind, TrapCode, Value, Variable,
     },
 };
 use cranelift_module::{DataDescription, DataId, FuncId, Linkage, Module};
-use hir::{FQComptime, LocalDef, ScopeId, SwitchLocal};
-use hir_ty::{ComptimeResult, InternTyExt, ParamTy, Ty};
+use hir::{Descendant, DescentOpts, FQComptime, ForLocal, LocalDef, ScopeId, SwitchLocal};
+use hir_ty::{ComptimeResult, InternTyExt, IntrinsicOperands, ParamTy, Ty};
 use interner::Interner;
 use internment::Intern;
 use la_arena::Idx;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use uid_gen::UIDGenerator;
 
 use crate::{
@@ -26,21 +29,32 @@ use crate::{
 
 use super::{
     abi::{Abi, FnAbi},
-    comptime::{ComptimeBytes, IntBytes},
+    comptime::{self, IntBytes},
     ComptimeData, FunctionToCompile, MemoryLoc, MetaTyData, MetaTyInfoArrays, MetaTyLayoutArrays,
+    ReadOnlyData, INLINE_MEMORY_OP_LIMIT,
 };
 
 struct UnfinishedComptimeErr;
 
+/// A pointer inside of constant data which the linker has to fill in
+#[derive(Debug, Clone, Copy)]
+enum ConstReloc {
+    Data(DataId),
+    Func(FuncId),
+}
+
 /// represents a single block containing multiple defer statements
 #[derive(Debug, Clone)]
 pub(crate) struct DeferFrame {
     id: Option<ScopeId>,
     defers: Vec<Idx<hir::Expr>>,
+    /// set when a value which might point into this block's locals gets carried out of it
+    keep_slots: bool,
 }
 
 /// todo: should this be a different number?
 const TRAP_UNREACHABLE: TrapCode = TrapCode::unwrap_user(10);
+const TRAP_MISALIGNED: TrapCode = TrapCode::unwrap_user(11);
 
 /// Compiles a Capy function into a Cranelift function.
 ///
@@ -53,7 +67,7 @@ pub(crate) struct FunctionCompiler<'a> {
     pub(crate) mod_dir: &'a std::path::Path,
     pub(crate) interner: &'a Interner,
     pub(crate) world_bodies: &'a hir::WorldBodies,
-    pub(crate) tys: &'a hir_ty::ProjectInference,
+    pub(crate) tys: hir_ty::BodyTys<'a>,
 
     pub(crate) builder: FunctionBuilder<'a>,
     pub(crate) module: &'a mut dyn Module,
@@ -65,14 +79,18 @@ pub(crate) struct FunctionCompiler<'a> {
     pub(crate) cmd_args_slice: &'a mut Option<DataId>,
 
     pub(crate) local_functions: FxHashMap<hir::Fqn, FuncRef>,
+    pub(crate) local_instances: FxHashMap<u32, FuncRef>,
     pub(crate) local_lambdas: FxHashMap<Idx<hir::Lambda>, FuncRef>,
 
     // globals
     pub(crate) functions: &'a mut FxHashMap<hir::Fqn, FuncId>,
+    pub(crate) instances: &'a mut FxHashMap<u32, FuncId>,
     pub(crate) compiler_defined_functions: &'a mut FxHashMap<BuiltinFunction, FuncId>,
     pub(crate) globals: &'a mut FxHashMap<hir::Fqn, DataId>,
     pub(crate) str_id_gen: &'a mut UIDGenerator,
     pub(crate) i128_id_gen: &'a mut UIDGenerator,
+    pub(crate) read_only_data: &'a mut ReadOnlyData,
+    pub(crate) vtables: &'a mut FxHashMap<Vec<hir::Fqn>, DataId>,
     pub(crate) comptime_results: &'a FxHashMap<FQComptime, ComptimeResult>,
     pub(crate) comptime_data: &'a mut FxHashMap<FQComptime, ComptimeData>,
 
@@ -80,23 +98,46 @@ pub(crate) struct FunctionCompiler<'a> {
     pub(crate) var_id_gen: UIDGenerator,
     pub(crate) locals: FxHashMap<Idx<LocalDef>, Value>,
     pub(crate) switch_locals: FxHashMap<Idx<SwitchLocal>, Value>,
+    /// the address of the current element of each `for` loop
+    pub(crate) for_locals: FxHashMap<Idx<ForLocal>, Value>,
     pub(crate) params: FxHashMap<u64, Variable>,
+    /// what this closure captured, in the order they're laid out in its environment
+    pub(crate) captures: Vec<Idx<hir::Capture>>,
+    /// the address of each captured value inside of the environment
+    pub(crate) captured: FxHashMap<Idx<hir::Capture>, Value>,
+    pub(crate) local_slots: FxHashMap<Idx<LocalDef>, StackSlot>,
+    /// stack slots of locals whose blocks have ended, keyed by their size and alignment
+    pub(crate) free_stack_slots: FxHashMap<(u32, u8), Vec<StackSlot>>,
+    /// the one slot that every zero sized local points to
+    pub(crate) zero_sized_slot: Option<StackSlot>,
 
     // for control flow (breaks and continues)
     pub(crate) exits: FxHashMap<ScopeId, Block>,
     pub(crate) continues: FxHashMap<ScopeId, Block>,
     pub(crate) defer_stack: Vec<DeferFrame>,
+    /// the block right after the parameters are set up, which `#tailcall` jumps back to
+    pub(crate) tail_call_block: Option<Block>,
+    /// where aggregate arguments of `#tailcall`s live for the next run through the function
+    pub(crate) tail_call_params: FxHashMap<u32, StackSlot>,
+    /// the `@inline` functions whose bodies are currently being compiled in place of a call.
+    /// a function which ends up calling itself gets a real call the second time around
+    pub(crate) inlining: Vec<hir::Fqn>,
+    /// the address of the flag that comptime code checks, see `comptime::Sandbox`
+    pub(crate) cancel_flag: Option<usize>,
+    /// where comptime code goes to return early once it's been told to stop
+    pub(crate) cancel_block: Option<Block>,
 }
 
 impl FunctionCompiler<'_> {
     pub(crate) fn finish(
         mut self,
         fn_abi: FnAbi,
-        (args, return_ty): (&Vec<ParamTy>, Intern<Ty>),
+        return_ty: Intern<Ty>,
         function_body: Idx<hir::Expr>,
         debug_print: bool,
     ) {
-        fn_abi.build_fn(&mut self, return_ty, args, function_body);
+        fn_abi.build_fn(&mut self, return_ty, function_body);
+        self.build_cancel_block();
 
         if debug_print {
             print!("{}", self.builder.func);
@@ -105,11 +146,15 @@ impl FunctionCompiler<'_> {
         self.builder.finalize();
     }
 
-    /// Returns `None` if any inner comptime blocks haven't been evaluated yet
+    /// Returns `None` if any inner comptime blocks haven't been evaluated yet.
+    ///
+    /// Any pointers within the data (strings or functions inside of structs and arrays) are
+    /// left zeroed, and are instead added to `relocs` with their offset into the data
     fn expr_to_const_data(
         &mut self,
         file_name: hir::FileName,
         expr: Idx<hir::Expr>,
+        relocs: &mut Vec<(u32, ConstReloc)>,
     ) -> Result<Box<[u8]>, UnfinishedComptimeErr> {
         if let Some(meta_ty) = self.tys[file_name].get_meta_ty(expr) {
             let id = meta_ty.to_type_id(self.meta_tys, self.ptr_ty);
@@ -163,6 +208,44 @@ impl FunctionCompiler<'_> {
                 _ => unreachable!(),
             },
             hir::Expr::BoolLiteral(b) => Box::new([b as u8]),
+            // a `null` optional is entirely zeroes, just like a `null` pointer
+            hir::Expr::NullLiteral => {
+                let ty = self.tys[file_name][expr];
+                let size = if ty.as_optional().is_some() {
+                    ty.size()
+                } else {
+                    self.ptr_ty.bytes()
+                };
+                vec![0; size as usize].into_boxed_slice()
+            }
+            hir::Expr::Cast {
+                expr: Some(inner), ..
+            } => {
+                // `get_const` only allows casts between primitives
+                let from = self.tys[file_name][inner].absolute_intern_ty(true);
+                let to = self.tys[file_name][expr].absolute_intern_ty(true);
+
+                let cast = from
+                    .primitive_cast(&to)
+                    .expect("only primitive casts can be constant");
+
+                let endianness = self.module.isa().endianness();
+                let from_number = from.get_final_ty().into_number_type().unwrap();
+                let to_number = to.get_final_ty().into_number_type().unwrap();
+
+                let bytes = self.expr_to_const_data(file_name, inner, relocs)?;
+                let value = comptime::number_from_bytes(&bytes, from_number, endianness);
+
+                match cast.fold(&value, &from, &to) {
+                    Some(ComptimeResult::Integer { num, .. }) => num
+                        .into_bytes(endianness, to_number.bit_width())
+                        .into_boxed_slice(),
+                    Some(ComptimeResult::Float { num, .. }) => num
+                        .into_bytes(endianness, to_number.bit_width())
+                        .into_boxed_slice(),
+                    _ => unreachable!("primitive casts always fold into numbers"),
+                }
+            }
             hir::Expr::StringLiteral(mut text) => {
                 text.push('\0');
                 text.into_bytes().into()
@@ -170,19 +253,22 @@ impl FunctionCompiler<'_> {
             hir::Expr::ArrayLiteral { items, .. } => {
                 assert_ne!(items.len(), 0);
 
-                let item_ty = self.tys[file_name][items[0]];
+                let (_, item_ty) = self.tys[file_name][expr]
+                    .as_array()
+                    .expect("constant array literals must have array types");
                 let item_size = item_ty.size();
                 let item_stride = item_ty.stride();
 
                 let mut array = Vec::<u8>::with_capacity(item_stride as usize * items.len());
 
                 for (idx, item) in items.into_iter().enumerate() {
-                    let item = self.expr_to_const_data(file_name, item)?;
+                    let offset = idx as u32 * item_stride;
+                    let item = self.const_item(file_name, item, item_ty, offset, relocs)?;
 
                     unsafe {
                         std::ptr::copy_nonoverlapping(
                             item.as_ptr(),
-                            array.as_mut_ptr().add(idx * item_stride as usize),
+                            array.as_mut_ptr().add(offset as usize),
                             item_size as usize,
                         );
                     }
@@ -192,6 +278,57 @@ impl FunctionCompiler<'_> {
 
                 array.into()
             }
+            hir::Expr::ArrayRepeat { item, .. } => {
+                let (count, item_ty) = self.tys[file_name][expr]
+                    .as_array()
+                    .expect("array repeats must have array types");
+                let item_size = item_ty.size() as usize;
+                let item_stride = item_ty.stride() as usize;
+
+                let mut item_relocs = Vec::new();
+                let item = self.const_item(file_name, item, item_ty, 0, &mut item_relocs)?;
+
+                let mut array = vec![0_u8; item_stride * count as usize];
+
+                for idx in 0..count as usize {
+                    let start = idx * item_stride;
+                    array[start..start + item_size].copy_from_slice(&item[..item_size]);
+
+                    relocs.extend(
+                        item_relocs
+                            .iter()
+                            .map(|(offset, reloc)| (offset + start as u32, *reloc)),
+                    );
+                }
+
+                array.into()
+            }
+            hir::Expr::StructLiteral { members, .. } => {
+                let struct_ty = self.tys[file_name][expr];
+                let member_tys = struct_ty
+                    .as_struct()
+                    .expect("constant struct literals must have struct types");
+                let layout = struct_ty.struct_layout().unwrap();
+
+                let mut data = vec![0_u8; struct_ty.size() as usize];
+
+                for hir::MemberLiteral { name, value } in members {
+                    let idx = member_tys
+                        .iter()
+                        .position(|member| member.name == name.unwrap().name)
+                        .unwrap();
+                    let member_ty = member_tys[idx].ty;
+                    let offset = layout.offsets()[idx];
+
+                    let member = self.const_item(file_name, value, member_ty, offset, relocs)?;
+
+                    let start = offset as usize;
+                    let size = member_ty.size() as usize;
+                    data[start..start + size].copy_from_slice(&member[..size]);
+                }
+
+                data.into()
+            }
             hir::Expr::Comptime(comptime) => {
                 let ctc = FQComptime {
                     file: file_name,
@@ -199,7 +336,8 @@ impl FunctionCompiler<'_> {
                     comptime,
                 };
 
-                self.comptime_results
+                let result = self
+                    .comptime_results
                     .get(&ctc)
                     .ok_or_else(|| {
                         if self.final_binary {
@@ -214,24 +352,21 @@ impl FunctionCompiler<'_> {
                             UnfinishedComptimeErr
                         }
                     })?
-                    .clone()
-                    .into_bytes(self.meta_tys, self.module.isa().endianness(), self.ptr_ty)
-                    .unwrap()
+                    .clone();
+
+                self.comptime_to_const_data(result, relocs)
             }
             hir::Expr::Local(local) => {
                 let local_def = &self.world_bodies[file_name][local];
 
                 assert!(local_def.value.is_some(), "if the value doesn't exist, `get_const` should've returned non-const, and there should be an error before codegen");
 
-                return self.expr_to_const_data(file_name, local_def.value.unwrap());
+                return self.expr_to_const_data(file_name, local_def.value.unwrap(), relocs);
             }
             hir::Expr::LocalGlobal(global) => {
-                let fqn = hir::Fqn {
-                    file: file_name,
-                    name: global.name,
-                };
+                let fqn = self.world_bodies.resolve_global(file_name, global.name);
 
-                return self.expr_to_const_data(file_name, self.world_bodies.body(fqn));
+                return self.expr_to_const_data(fqn.file, self.world_bodies.body(fqn), relocs);
             }
             hir::Expr::Member {
                 previous,
@@ -243,10 +378,33 @@ impl FunctionCompiler<'_> {
                         name: field.name,
                     };
 
-                    return self.expr_to_const_data(fqn.file, self.world_bodies.body(fqn));
+                    return self.expr_to_const_data(fqn.file, self.world_bodies.body(fqn), relocs);
+                } else if let Some(fqn) = self.tys[file_name].get_namespaced_member(expr) {
+                    return self.expr_to_const_data(fqn.file, self.world_bodies.body(fqn), relocs);
+                } else if let Some(member_tys) = self.tys[file_name][previous].as_struct() {
+                    // a member of a constant struct is just a slice of the struct's data
+                    let struct_ty = self.tys[file_name][previous];
+                    let idx = member_tys
+                        .iter()
+                        .position(|member| member.name == field.name)
+                        .unwrap();
+                    let start = struct_ty.struct_layout().unwrap().offsets()[idx];
+                    let end = start + member_tys[idx].ty.size();
+
+                    let mut struct_relocs = Vec::new();
+                    let data = self.expr_to_const_data(file_name, previous, &mut struct_relocs)?;
+
+                    relocs.extend(
+                        struct_relocs
+                            .into_iter()
+                            .filter(|(offset, _)| (start..end).contains(offset))
+                            .map(|(offset, reloc)| (offset - start, reloc)),
+                    );
+
+                    return Ok(data[start as usize..end as usize].into());
                 } else {
                     panic!(
-                        "constant members should only access files {} #{}",
+                        "constant members should only access files or struct declarations {} #{}",
                         file_name.to_string(self.mod_dir, self.interner),
                         expr.into_raw()
                     )
@@ -260,6 +418,183 @@ impl FunctionCompiler<'_> {
         })
     }
 
+    /// Gives the data of a single item within a constant struct or array, where `offset` is
+    /// where the item will be placed.
+    ///
+    /// The items of an aggregate have to be stored by value, so a string or a function inside of
+    /// an aggregate becomes a pointer to its actual data
+    fn const_item(
+        &mut self,
+        file_name: hir::FileName,
+        item: Idx<hir::Expr>,
+        item_ty: Intern<Ty>,
+        offset: u32,
+        relocs: &mut Vec<(u32, ConstReloc)>,
+    ) -> Result<Box<[u8]>, UnfinishedComptimeErr> {
+        let pointer = vec![0_u8; self.ptr_ty.bytes() as usize].into_boxed_slice();
+
+        if item_ty.is_function() {
+            let fqn = self.const_function(file_name, item);
+            let func_id = self.get_func_id(fqn);
+
+            relocs.push((offset, ConstReloc::Func(func_id)));
+
+            return Ok(pointer);
+        }
+
+        let mut item_relocs = Vec::new();
+        let data = self.expr_to_const_data(file_name, item, &mut item_relocs)?;
+
+        if matches!(item_ty.absolute_ty(), Ty::String) {
+            let name = format!(".str_{}", self.str_id_gen.generate_unique_id());
+            let text = self.create_global_data(&name, false, data, 1, Vec::new());
+
+            relocs.push((offset, ConstReloc::Data(text)));
+
+            return Ok(pointer);
+        }
+
+        relocs.extend(
+            item_relocs
+                .into_iter()
+                .map(|(item_offset, reloc)| (item_offset + offset, reloc)),
+        );
+
+        Ok(data)
+    }
+
+    /// Turns the value of a comptime block back into constant data.
+    ///
+    /// Just like with `expr_to_const_data`, a string on its own becomes its text,
+    /// but the strings within structs, arrays, and variants get their own data
+    fn comptime_to_const_data(
+        &mut self,
+        result: ComptimeResult,
+        relocs: &mut Vec<(u32, ConstReloc)>,
+    ) -> Box<[u8]> {
+        let endianness = self.module.isa().endianness();
+
+        match result {
+            ComptimeResult::Type(ty) => {
+                Box::new(ty.to_type_id(self.meta_tys, self.ptr_ty).to_ne_bytes())
+            }
+            ComptimeResult::Integer { num, bit_width } => {
+                num.into_bytes(endianness, bit_width).into_boxed_slice()
+            }
+            ComptimeResult::Float { num, bit_width } => {
+                num.into_bytes(endianness, bit_width).into_boxed_slice()
+            }
+            ComptimeResult::String(text) => {
+                let mut bytes = text.into_bytes();
+                bytes.push(0);
+                bytes.into_boxed_slice()
+            }
+            ComptimeResult::Bytes(bytes) | ComptimeResult::Data(bytes) => bytes,
+            ComptimeResult::Struct { ty, members } => {
+                let layout = ty.struct_layout().unwrap();
+
+                let mut data = vec![0_u8; ty.size() as usize];
+                for (member, offset) in members.into_iter().zip(layout.offsets()) {
+                    self.comptime_item(member, *offset, &mut data, relocs);
+                }
+
+                data.into()
+            }
+            ComptimeResult::Array { ty, items } => {
+                let (_, item_ty) = ty.as_array().unwrap();
+                let item_stride = item_ty.stride();
+
+                let mut data = vec![0_u8; ty.size() as usize];
+                for (idx, item) in items.into_iter().enumerate() {
+                    self.comptime_item(item, idx as u32 * item_stride, &mut data, relocs);
+                }
+
+                data.into()
+            }
+            ComptimeResult::Variant {
+                ty,
+                variant,
+                payload,
+            } => {
+                let mut data = vec![0_u8; ty.size() as usize];
+                self.comptime_item(*payload, 0, &mut data, relocs);
+
+                // values of a single variant don't store the discriminant, only enums do
+                if matches!(ty.absolute_ty_keep_variants(), Ty::Enum { .. }) {
+                    let Ty::Variant { discriminant, .. } = variant.as_ref() else {
+                        unreachable!("enums only contain variants")
+                    };
+                    let offset = ty.enum_layout().unwrap().discriminant_offset();
+                    data[offset as usize] = *discriminant as u8;
+                }
+
+                data.into()
+            }
+            ComptimeResult::Void => Box::new([]),
+        }
+    }
+
+    /// Writes a member or item of a comptime aggregate into `data` at the given offset.
+    /// Strings are written as pointers to new data, the same as `const_item` does
+    fn comptime_item(
+        &mut self,
+        item: ComptimeResult,
+        offset: u32,
+        data: &mut [u8],
+        relocs: &mut Vec<(u32, ConstReloc)>,
+    ) {
+        if let ComptimeResult::String(text) = item {
+            let mut bytes = text.into_bytes();
+            bytes.push(0);
+
+            let name = format!(".str_{}", self.str_id_gen.generate_unique_id());
+            let text = self.create_global_data(&name, false, bytes.into(), 1, Vec::new());
+
+            relocs.push((offset, ConstReloc::Data(text)));
+            return;
+        }
+
+        let mut item_relocs = Vec::new();
+        let item = self.comptime_to_const_data(item, &mut item_relocs);
+
+        let start = offset as usize;
+        data[start..start + item.len()].copy_from_slice(&item);
+
+        relocs.extend(
+            item_relocs
+                .into_iter()
+                .map(|(item_offset, reloc)| (item_offset + offset, reloc)),
+        );
+    }
+
+    /// Finds the global function that a constant function value refers to
+    fn const_function(&self, file_name: hir::FileName, expr: Idx<hir::Expr>) -> hir::Fqn {
+        match self.world_bodies[file_name][expr] {
+            hir::Expr::LocalGlobal(global) => {
+                self.world_bodies.resolve_global(file_name, global.name)
+            }
+            hir::Expr::Local(local) => {
+                let value = self.world_bodies[file_name][local].value.unwrap();
+                self.const_function(file_name, value)
+            }
+            hir::Expr::Paren(Some(inner)) => self.const_function(file_name, inner),
+            hir::Expr::Member { previous, name } => match self.tys[file_name][previous].as_ref() {
+                Ty::File(file) => hir::Fqn {
+                    file: *file,
+                    name: name.name,
+                },
+                _ => self.tys[file_name]
+                    .get_namespaced_member(expr)
+                    .expect("constant functions should only be globals"),
+            },
+            _ => panic!(
+                "constant functions should only be globals {}#{}",
+                file_name.to_string(self.mod_dir, self.interner),
+                expr.into_raw()
+            ),
+        }
+    }
+
     fn compile_global_binding_data(
         &mut self,
         fqn: hir::Fqn,
@@ -371,7 +706,7 @@ impl FunctionCompiler<'_> {
             let global = self
                 .module
                 .declare_data(
-                    self.interner.lookup(fqn.name.0),
+                    mangle::extern_name(fqn, self.interner),
                     Linkage::Import,
                     true,
                     false,
@@ -385,13 +720,18 @@ impl FunctionCompiler<'_> {
 
         let value = self.world_bodies.body(fqn);
 
-        let bytes = self.expr_to_const_data(fqn.file, value)?;
+        let mut relocs = Vec::new();
+        let bytes = self.expr_to_const_data(fqn.file, value, &mut relocs)?;
 
+        // globals can never be mutated, so large constant arrays and structs are shared with any
+        // other identical data instead of getting their own exported symbol
+        let global_ty = self.tys[fqn].0;
         let global = self.create_global_data(
             &fqn.to_mangled_name(self.mod_dir, self.interner),
-            true,
+            !global_ty.is_aggregate(),
             bytes,
-            self.tys[fqn].0.align() as u64,
+            global_ty.align() as u64,
+            relocs,
         );
 
         self.globals.insert(fqn, global);
@@ -405,8 +745,16 @@ impl FunctionCompiler<'_> {
         export: bool,
         data: Box<[u8]>,
         align: u64,
+        relocs: Vec<(u32, ConstReloc)>,
     ) -> DataId {
-        // todo: if the data isn't mutable, combine globals with identical definitions
+        // data that isn't exported can't be written to, so identical definitions can be shared.
+        // pointers aren't part of the bytes though, so data containing them is never shared
+        let shareable = !export && relocs.is_empty();
+        if shareable {
+            if let Some(id) = self.read_only_data.get(&data, align) {
+                return id;
+            }
+        }
 
         let id = self
             .module
@@ -422,8 +770,28 @@ impl FunctionCompiler<'_> {
             )
             .expect("error declaring data");
 
+        if shareable {
+            self.read_only_data.insert(data.clone(), align, id);
+        }
+
         self.data_description.define(data);
         self.data_description.set_align(align);
+        for (offset, reloc) in relocs {
+            match reloc {
+                ConstReloc::Data(data) => {
+                    let data = self
+                        .module
+                        .declare_data_in_data(data, self.data_description);
+                    self.data_description.write_data_addr(offset, data, 0);
+                }
+                ConstReloc::Func(func) => {
+                    let func = self
+                        .module
+                        .declare_func_in_data(func, self.data_description);
+                    self.data_description.write_function_addr(offset, func);
+                }
+            }
+        }
         self.module
             .define_data(id, self.data_description)
             .expect("error defining data");
@@ -435,7 +803,13 @@ impl FunctionCompiler<'_> {
     fn create_global_str(&mut self, mut text: String) -> DataId {
         text.push('\0');
         let name = format!(".str_{}", self.str_id_gen.generate_unique_id());
-        self.create_global_data(&name, false, text.into_bytes().into_boxed_slice(), 1)
+        self.create_global_data(
+            &name,
+            false,
+            text.into_bytes().into_boxed_slice(),
+            1,
+            Vec::new(),
+        )
     }
 
     fn create_global_i128(&mut self, num: u64) -> DataId {
@@ -446,10 +820,10 @@ impl FunctionCompiler<'_> {
             num.into_bytes(self.module.isa().endianness(), 128)
                 .into_boxed_slice(),
             1,
+            Vec::new(),
         )
     }
 
-    #[allow(unused)]
     fn build_memcpy_ty(&mut self, src: Value, dest: Value, ty: Intern<Ty>, non_overlapping: bool) {
         self.builder.emit_small_memory_copy(
             self.module.target_config(),
@@ -471,7 +845,7 @@ impl FunctionCompiler<'_> {
             self.functions,
             self.compiler_defined_functions,
             self.functions_to_compile,
-            self.tys,
+            self.tys.project(),
             self.world_bodies,
             self.interner,
             fqn,
@@ -492,8 +866,32 @@ impl FunctionCompiler<'_> {
         local_func
     }
 
+    fn get_local_instance(&mut self, instance: u32) -> FuncRef {
+        if let Some(func_ref) = self.local_instances.get(&instance) {
+            return *func_ref;
+        }
+
+        let func_id = super::get_instance_func_id(
+            self.module,
+            self.ptr_ty,
+            self.mod_dir,
+            self.instances,
+            self.functions_to_compile,
+            self.tys.project(),
+            self.world_bodies,
+            self.interner,
+            instance,
+        );
+
+        let local_func = self.module.declare_func_in_func(func_id, self.builder.func);
+
+        self.local_instances.insert(instance, local_func);
+
+        local_func
+    }
+
     fn compile_global(&mut self, fqn: hir::Fqn, no_load: bool) -> Option<Value> {
-        let ty = &self.tys[fqn].0;
+        let ty = self.tys[fqn].0;
 
         if ty.is_zero_sized() {
             return None;
@@ -523,7 +921,11 @@ impl FunctionCompiler<'_> {
 
         let final_ty = ty.get_final_ty();
 
-        if no_load || final_ty.is_pointer_type() {
+        // the contents of strings and aggregates are stored right in the global,
+        // but a global like `p :: null` holds an actual pointer that has to be loaded
+        let holds_pointer = ty.is_pointer() || matches!(ty.absolute_ty(), Ty::RawPtr { .. });
+
+        if no_load || (final_ty.is_pointer_type() && !holds_pointer) {
             Some(global_ptr)
         } else {
             Some(self.builder.ins().load(
@@ -535,11 +937,341 @@ impl FunctionCompiler<'_> {
         }
     }
 
+    /// Gives back a stack slot of a local which has gone out of scope, or creates a new one
+    fn local_stack_slot(&mut self, size: u32, align_shift: u8) -> StackSlot {
+        if let Some(slot) = self
+            .free_stack_slots
+            .get_mut(&(size, align_shift))
+            .and_then(|free| free.pop())
+        {
+            return slot;
+        }
+
+        self.builder.create_sized_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size,
+            align_shift,
+        })
+    }
+
+    /// The offset of every captured value inside of a closure's environment,
+    /// along with the size and alignment of the whole environment
+    fn env_layout(&self, captures: &[Idx<hir::Capture>]) -> (Vec<u32>, u32, u32) {
+        let mut offsets = Vec::with_capacity(captures.len());
+        let mut size = 0;
+        let mut align = 1;
+
+        for capture in captures {
+            let ty = self.tys[self.file_name][self.world_bodies[self.file_name][*capture].value];
+
+            size += layout::padding_needed_for(size, ty.align());
+            offsets.push(size);
+            size += ty.size();
+            align = align.max(ty.align());
+        }
+
+        (offsets, size, align)
+    }
+
+    /// Copies everything that a closure captured into a new environment,
+    /// and puts a pointer to that environment right after the function pointer
+    fn compile_closure(&mut self, func_addr: Value, captures: Vec<Idx<hir::Capture>>) -> Value {
+        let (offsets, size, align) = self.env_layout(&captures);
+
+        // the environment is never null, so that calls know to pass it along
+        let env = if size == 0 {
+            self.zero_sized_addr()
+        } else {
+            let env_slot = self.builder.create_sized_stack_slot(StackSlotData {
+                kind: StackSlotKind::ExplicitSlot,
+                size,
+                align_shift: align.trailing_zeros() as u8,
+            });
+
+            for (capture, offset) in captures.into_iter().zip(offsets) {
+                let value = self.world_bodies[self.file_name][capture].value;
+                let ty = self.tys[self.file_name][value];
+                if ty.is_zero_sized() {
+                    continue;
+                }
+
+                self.compile_and_cast_into_memory(
+                    value,
+                    ty,
+                    MemoryLoc::from_stack(env_slot, offset),
+                );
+            }
+
+            self.builder.ins().stack_addr(self.ptr_ty, env_slot, 0)
+        };
+
+        let closure_slot = self.builder.create_sized_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: self.ptr_ty.bytes() * 2,
+            align_shift: self.ptr_ty.bytes().min(8).trailing_zeros() as u8,
+        });
+        self.builder.ins().stack_store(func_addr, closure_slot, 0);
+        self.builder
+            .ins()
+            .stack_store(env, closure_slot, self.ptr_ty.bytes() as i32);
+
+        self.builder.ins().stack_addr(self.ptr_ty, closure_slot, 0)
+    }
+
+    /// Finds where each of the values captured by the function being compiled
+    /// are inside of the environment it was given
+    pub(crate) fn unpack_env(&mut self, env: Value) {
+        let captures = std::mem::take(&mut self.captures);
+        let (offsets, _, _) = self.env_layout(&captures);
+
+        for (capture, offset) in captures.iter().zip(offsets) {
+            let addr = self.builder.ins().iadd_imm(env, offset as i64);
+            self.captured.insert(*capture, addr);
+        }
+
+        self.captures = captures;
+    }
+
+    /// Calls the function inside of a closure.
+    /// Plain functions that were turned into closures don't have an environment,
+    /// so they're called without one
+    fn call_closure(
+        &mut self,
+        closure: Value,
+        fn_abi: &FnAbi,
+        arg_values: Vec<Value>,
+        ret_mem: Option<Value>,
+        return_ty: Intern<Ty>,
+    ) -> Option<Value> {
+        let func = self
+            .builder
+            .ins()
+            .load(self.ptr_ty, MemFlags::trusted(), closure, 0);
+        let env = self.builder.ins().load(
+            self.ptr_ty,
+            MemFlags::trusted(),
+            closure,
+            self.ptr_ty.bytes() as i32,
+        );
+
+        let call_conv = self.module.target_config().default_call_conv;
+        let env_sig = fn_abi.clone().with_env().to_cl(self.ptr_ty, call_conv);
+        let env_sig = self.builder.import_signature(env_sig);
+        let plain_sig = fn_abi.to_cl(self.ptr_ty, call_conv);
+        let plain_sig = self.builder.import_signature(plain_sig);
+
+        let env_block = self.builder.create_block();
+        let plain_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+
+        self.builder
+            .ins()
+            .brif(env, env_block, &[], plain_block, &[]);
+
+        self.builder.switch_to_block(env_block);
+        self.builder.seal_block(env_block);
+        let mut env_args = arg_values.clone();
+        env_args.push(env);
+        let call = self.builder.ins().call_indirect(env_sig, func, &env_args);
+        let ret = if return_ty.is_zero_sized() {
+            None
+        } else {
+            fn_abi.handle_ret(call, self, ret_mem)
+        };
+        if let Some(ret) = ret {
+            let ret_ty = self.builder.func.dfg.value_type(ret);
+            self.builder.append_block_param(exit_block, ret_ty);
+        }
+        self.builder.ins().jump(exit_block, ret.as_slice());
+
+        self.builder.switch_to_block(plain_block);
+        self.builder.seal_block(plain_block);
+        let call = self
+            .builder
+            .ins()
+            .call_indirect(plain_sig, func, &arg_values);
+        let ret = if return_ty.is_zero_sized() {
+            None
+        } else {
+            fn_abi.handle_ret(call, self, ret_mem)
+        };
+        self.builder.ins().jump(exit_block, ret.as_slice());
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+        self.check_cancelled();
+
+        ret.map(|_| self.builder.block_params(exit_block)[0])
+    }
+
+    /// Comptime code can't be stopped from the outside, so it stops itself by returning early
+    /// whenever the compiler has set its cancel flag. This gets checked at the start of every
+    /// function and every loop iteration, and after every call in case the flag was set by a
+    /// sandboxed extern or by a function which just returned early itself
+    pub(crate) fn check_cancelled(&mut self) {
+        let Some(cancel_flag) = self.cancel_flag else {
+            return;
+        };
+
+        let cancel_block = match self.cancel_block {
+            Some(block) => block,
+            None => {
+                let block = self.builder.create_block();
+                self.cancel_block = Some(block);
+                block
+            }
+        };
+        let continue_block = self.builder.create_block();
+
+        let addr = self.builder.ins().iconst(self.ptr_ty, cancel_flag as i64);
+        // the flag gets set from other threads, so it can't be `trusted` like other loads
+        let cancelled = self.builder.ins().load(types::I8, MemFlags::new(), addr, 0);
+        self.builder
+            .ins()
+            .brif(cancelled, cancel_block, &[], continue_block, &[]);
+
+        self.builder.switch_to_block(continue_block);
+        self.builder.seal_block(continue_block);
+    }
+
+    /// Returns zeroes from the function, which nothing will ever look at
+    fn build_cancel_block(&mut self) {
+        let Some(cancel_block) = self.cancel_block else {
+            return;
+        };
+
+        // `build_fn` already sealed this along with everything else
+        self.builder.switch_to_block(cancel_block);
+
+        let return_tys = self
+            .builder
+            .func
+            .signature
+            .returns
+            .iter()
+            .map(|ret| ret.value_type)
+            .collect::<Vec<_>>();
+        let zeroes = return_tys
+            .into_iter()
+            .map(|ty| match ty {
+                types::F32 => self.builder.ins().f32const(0.0),
+                types::F64 => self.builder.ins().f64const(0.0),
+                types::I128 => {
+                    let zero = self.builder.ins().iconst(types::I64, 0);
+                    self.builder.ins().uextend(types::I128, zero)
+                }
+                _ => self.builder.ins().iconst(ty, 0),
+            })
+            .collect::<Vec<_>>();
+
+        self.builder.ins().return_(&zeroes);
+    }
+
+    /// Zero sized values don't take up any space, so they can all share the same address
+    fn zero_sized_addr(&mut self) -> Value {
+        let slot = match self.zero_sized_slot {
+            Some(slot) => slot,
+            None => {
+                let slot = self.builder.create_sized_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: 0,
+                    align_shift: 0,
+                });
+                self.zero_sized_slot = Some(slot);
+                slot
+            }
+        };
+
+        self.builder.ins().stack_addr(self.ptr_ty, slot, 0)
+    }
+
+    /// Marks the stack slots of the locals declared directly within a block as free.
+    ///
+    /// Locals of nested blocks have already been released when those blocks ended.
+    fn release_block_slots(&mut self, block: Idx<hir::Expr>, stmts: &[Idx<hir::Stmt>]) {
+        let escaping = self.escaping_locals(block);
+
+        for stmt in stmts {
+            let hir::Stmt::LocalDef(local_def) = self.world_bodies[self.file_name][*stmt] else {
+                continue;
+            };
+            if escaping.contains(&local_def) {
+                continue;
+            }
+            let Some(slot) = self.local_slots.remove(&local_def) else {
+                continue;
+            };
+
+            let ty = self.tys[self.file_name][local_def];
+            self.free_stack_slots
+                .entry((ty.size(), ty.align_shift()))
+                .or_default()
+                .push(slot);
+        }
+    }
+
+    /// Finds the locals of a block that something might still point to once the block has ended.
+    ///
+    /// That's any local whose address is taken with `^` or which gets sliced, along with any
+    /// aggregate local that's used as a whole, since aggregates are passed around as pointers to
+    /// their stack slot. Only the block has to be searched, because that's the only place the
+    /// locals can be named
+    fn escaping_locals(&self, block: Idx<hir::Expr>) -> FxHashSet<Idx<LocalDef>> {
+        let bodies = &self.world_bodies[self.file_name];
+
+        let root_local = |mut expr: Idx<hir::Expr>| loop {
+            match bodies[expr] {
+                hir::Expr::Member { previous, .. } => expr = previous,
+                hir::Expr::Index { source, .. } => expr = source,
+                hir::Expr::Paren(Some(inner)) => expr = inner,
+                hir::Expr::Local(local_def) => return Some(local_def),
+                _ => return None,
+            }
+        };
+
+        let mut escaping = FxHashSet::default();
+        // expressions that are only used to get at one of their members or items
+        let mut places = FxHashSet::default();
+        let mut locals = Vec::new();
+
+        for descendant in bodies.descendants(block, DescentOpts::Infer) {
+            let Descendant::Expr(expr) = descendant else {
+                continue;
+            };
+
+            match bodies[expr] {
+                hir::Expr::Ref { expr: inner, .. } => escaping.extend(root_local(inner)),
+                // slicing points into the source just like `^` does
+                hir::Expr::Index { source, .. }
+                    if self.tys[self.file_name][expr].as_slice().is_some() =>
+                {
+                    escaping.extend(root_local(source));
+                }
+                hir::Expr::Index { source, .. } => {
+                    places.insert(source);
+                }
+                hir::Expr::Member { previous, .. } => {
+                    places.insert(previous);
+                }
+                hir::Expr::Local(local_def) => locals.push((expr, local_def)),
+                _ => {}
+            }
+        }
+
+        for (expr, local_def) in locals {
+            if !places.contains(&expr) && self.tys[self.file_name][local_def].is_aggregate() {
+                escaping.insert(local_def);
+            }
+        }
+
+        escaping
+    }
+
     fn compile_stmt(&mut self, stmt: &Idx<hir::Stmt>) {
         match self.world_bodies[self.file_name][*stmt] {
             hir::Stmt::Expr(expr) => {
                 match *self.tys[self.file_name][expr] {
-                    hir_ty::Ty::Unknown => unreachable!(),
+                    hir_ty::Ty::Unknown | hir_ty::Ty::Error => unreachable!(),
                     _ => {
                         self.compile_expr(expr);
                     }
@@ -550,11 +1282,19 @@ impl FunctionCompiler<'_> {
 
                 let value = self.world_bodies[self.file_name][local_def].value;
 
-                let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
-                    kind: StackSlotKind::ExplicitSlot,
-                    size: ty.size(),
-                    align_shift: ty.align_shift(),
-                });
+                if ty.is_zero_sized() {
+                    // there's nothing to store, but the value might still have side effects
+                    if let Some(value) = value {
+                        self.compile_expr(value);
+                    }
+                    // `^x` should still give the same address every time
+                    let addr = self.zero_sized_addr();
+                    self.locals.insert(local_def, addr);
+                    return;
+                }
+
+                let stack_slot = self.local_stack_slot(ty.size(), ty.align_shift());
+                self.local_slots.insert(local_def, stack_slot);
 
                 let memory = MemoryLoc::from_stack(stack_slot, 0);
 
@@ -573,20 +1313,29 @@ impl FunctionCompiler<'_> {
                 let assign_body = &self.world_bodies[self.file_name][assign];
 
                 let Some(dest) = self.compile_expr_with_args(assign_body.dest, true) else {
+                    // zero sized destinations don't exist in memory,
+                    // but the value might still have side effects
+                    self.compile_expr(assign_body.value);
                     return;
                 };
                 let dest = MemoryLoc::from_addr(dest, 0);
 
-                let dest_ty = &self.tys[self.file_name][assign_body.dest];
+                let dest_ty = self.tys[self.file_name][assign_body.dest];
 
                 if let Some(op) = assign_body.quick_assign_op {
                     let res = self.compile_binary(assign_body.dest, assign_body.value, op);
 
                     assert!(!dest_ty.is_aggregate());
 
-                    dest.write(res, *dest_ty, self.module, &mut self.builder);
+                    dest.write(res, dest_ty, self.module, &mut self.builder);
+                } else if self.value_reads_dest(assign_body.dest, assign_body.value) {
+                    // the value has to be built somewhere else first,
+                    // otherwise it would overwrite the things it's reading
+                    let value = self.compile_expr(assign_body.value);
+                    let value_ty = self.tys[self.file_name][assign_body.value];
+                    self.cast_into_memory(value, value_ty, dest_ty, dest);
                 } else {
-                    self.compile_and_cast_into_memory(assign_body.value, *dest_ty, dest);
+                    self.compile_and_cast_into_memory(assign_body.value, dest_ty, dest);
                 }
             }
             hir::Stmt::Break {
@@ -600,33 +1349,14 @@ impl FunctionCompiler<'_> {
                     let referenced_block_ty =
                         self.tys[self.file_name][self.world_bodies[self.file_name][label]];
 
-                    self.compile_and_cast(value, referenced_block_ty)
-                });
-
-                // run all the defers from here, backwards to the one we are breaking out of
-
-                let mut used_frames = Vec::new();
-
-                while let Some(frame) = self.defer_stack.last().cloned() {
-                    // the exit block of every Expr::Block contains the instructions for running
-                    // the defers. This break instruction jumps to that exit block.
-                    // therefore, we only need to insert extra defer handling for everything OTHER
-                    // than the block we are breaking to.
-                    if let Some(id) = frame.id {
-                        if id == label {
-                            break;
-                        }
-                    }
-
-                    // do this in reverse for the reasons explained in the Expr::Block code
-                    for defer in frame.defers.iter().rev() {
-                        self.compile_expr(*defer);
+                    if referenced_block_ty.is_aggregate() || referenced_block_ty.is_pointer() {
+                        self.keep_slots_up_to(label);
                     }
 
-                    used_frames.push(self.defer_stack.pop().unwrap());
-                }
+                    self.compile_and_cast(value, referenced_block_ty)
+                });
 
-                self.defer_stack.extend(used_frames.into_iter().rev());
+                self.compile_defers_up_to(label);
 
                 if let Some(value) = value {
                     self.builder.ins().jump(exit_block, &[value]);
@@ -640,6 +1370,10 @@ impl FunctionCompiler<'_> {
             } => {
                 let continue_block = self.continues[&label];
 
+                // the loop might be an outer one, so every block between here and there
+                // needs to have its defers run
+                self.compile_defers_up_to(label);
+
                 self.builder.ins().jump(continue_block, &[]);
             }
             hir::Stmt::Continue { label: None, .. } => unreachable!(),
@@ -657,16 +1391,438 @@ impl FunctionCompiler<'_> {
         }
     }
 
-    fn store_default_in_memory(&mut self, expected_ty: Intern<Ty>, memory: MemoryLoc) {
-        let value = match expected_ty.as_ref() {
-            Ty::NotYetResolved | Ty::Unknown => unreachable!(),
-            Ty::IInt(_) | Ty::UInt(_) => {
-                let number_ty = expected_ty.get_final_ty().into_number_type().unwrap();
-                match number_ty.bit_width() {
-                    128 => {
-                        let data = self.create_global_i128(0);
-
-                        let local_id = self.module.declare_data_in_func(data, self.builder.func);
+    /// Lowers `#sqrt`, `#min`, `#popcount`, etc. to their Cranelift instructions.
+    ///
+    /// Cranelift calls into libm by itself when the target has no instruction for one of them
+    fn compile_math_intrinsic(
+        &mut self,
+        name: &str,
+        expr: Idx<hir::Expr>,
+        args: &[Idx<hir::Expr>],
+    ) -> Option<Value> {
+        let ty = self.tys[self.file_name][expr];
+        let number_ty = ty.get_final_ty().into_number_type().unwrap();
+
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.compile_and_cast(*arg, ty)?);
+        }
+
+        if let ("pow", [base, exp]) = (name, values.as_slice()) {
+            return Some(if number_ty.float {
+                self.build_float_pow(*base, *exp, number_ty.ty)
+            } else {
+                self.build_int_pow(*base, *exp, number_ty.ty)
+            });
+        }
+
+        let ins = self.builder.ins();
+        Some(match (name, values.as_slice()) {
+            ("sqrt", [x]) => ins.sqrt(*x),
+            ("fma", [a, b, c]) => ins.fma(*a, *b, *c),
+            ("abs", [x]) if number_ty.float => ins.fabs(*x),
+            ("abs", [x]) if number_ty.signed => ins.iabs(*x),
+            ("abs", [x]) => *x,
+            ("min", [a, b]) if number_ty.float => ins.fmin(*a, *b),
+            ("min", [a, b]) if number_ty.signed => ins.smin(*a, *b),
+            ("min", [a, b]) => ins.umin(*a, *b),
+            ("max", [a, b]) if number_ty.float => ins.fmax(*a, *b),
+            ("max", [a, b]) if number_ty.signed => ins.smax(*a, *b),
+            ("max", [a, b]) => ins.umax(*a, *b),
+            ("ctz", [x]) => ins.ctz(*x),
+            ("clz", [x]) => ins.clz(*x),
+            ("popcount", [x]) => ins.popcnt(*x),
+            _ => unreachable!("`#{name}` was given the wrong number of arguments"),
+        })
+    }
+
+    /// Neither Cranelift nor most hardware have an instruction for raising floats to a power,
+    /// so this calls `pow` or `powf` from libm
+    fn build_float_pow(&mut self, base: Value, exp: Value, ty: types::Type) -> Value {
+        let mut sig = self.module.make_signature();
+        sig.params.push(AbiParam::new(ty));
+        sig.params.push(AbiParam::new(ty));
+        sig.returns.push(AbiParam::new(ty));
+
+        let name = if ty == types::F32 { "powf" } else { "pow" };
+        let func_id = self
+            .module
+            .declare_function(name, Linkage::Import, &sig)
+            .unwrap();
+        let local_func = self.module.declare_func_in_func(func_id, self.builder.func);
+
+        let call = self.builder.ins().call(local_func, &[base, exp]);
+        self.builder.inst_results(call)[0]
+    }
+
+    /// Raises an integer to a power by squaring, which takes one loop iteration per bit
+    /// of the exponent.
+    ///
+    /// The exponent is treated as unsigned and the result wraps on overflow,
+    /// the same as `*`
+    fn build_int_pow(&mut self, base: Value, exp: Value, ty: types::Type) -> Value {
+        let header_block = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+
+        let result = self.builder.append_block_param(header_block, ty);
+        let current_base = self.builder.append_block_param(header_block, ty);
+        let current_exp = self.builder.append_block_param(header_block, ty);
+        let final_result = self.builder.append_block_param(exit_block, ty);
+
+        let one = self.builder.ins().iconst(ty, 1);
+        self.builder.ins().jump(header_block, &[one, base, exp]);
+
+        self.builder.switch_to_block(header_block);
+        let done = self.builder.ins().icmp_imm(IntCC::Equal, current_exp, 0);
+        self.builder
+            .ins()
+            .brif(done, exit_block, &[result], body_block, &[]);
+
+        self.builder.switch_to_block(body_block);
+        self.builder.seal_block(body_block);
+        let low_bit = self.builder.ins().band_imm(current_exp, 1);
+        let multiplied = self.builder.ins().imul(result, current_base);
+        let next_result = self.builder.ins().select(low_bit, multiplied, result);
+        let next_base = self.builder.ins().imul(current_base, current_base);
+        let next_exp = self.builder.ins().ushr_imm(current_exp, 1);
+        self.builder
+            .ins()
+            .jump(header_block, &[next_result, next_base, next_exp]);
+        self.builder.seal_block(header_block);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+
+        final_result
+    }
+
+    /// Gives back the global function that `callee` refers to if it's marked `@inline`
+    /// If `callee` is a method of an interface, this returns the interface it's called on
+    /// and the index of the method within the interface's method table
+    fn interface_method(&self, callee: Idx<hir::Expr>) -> Option<(Idx<hir::Expr>, usize)> {
+        let hir::Expr::Member { previous, name } = self.world_bodies[self.file_name][callee] else {
+            return None;
+        };
+        if self.tys[self.file_name]
+            .get_namespaced_member(callee)
+            .is_some()
+        {
+            return None;
+        }
+
+        let mut ty = self.tys[self.file_name][previous];
+        while let Some((_, sub_ty)) = ty.as_pointer() {
+            ty = sub_ty;
+        }
+
+        let method_idx = ty
+            .as_interface()?
+            .iter()
+            .position(|method| method.name == name.name)?;
+
+        Some((previous, method_idx))
+    }
+
+    /// Gets the address of the data pointer and method table of an interface,
+    /// looking through any pointers to it along the way
+    fn compile_interface_addr(&mut self, interface: Idx<hir::Expr>) -> Value {
+        let mut addr = self.compile_expr(interface).unwrap();
+
+        let mut ty = self.tys[self.file_name][interface];
+        while let Some((_, sub_ty)) = ty.as_pointer() {
+            if sub_ty.is_pointer() {
+                addr = self
+                    .builder
+                    .ins()
+                    .load(self.ptr_ty, MemFlags::trusted(), addr, 0);
+            }
+            ty = sub_ty;
+        }
+
+        addr
+    }
+
+    fn inline_callee(&self, callee: Idx<hir::Expr>) -> Option<(hir::Fqn, Idx<hir::Lambda>)> {
+        // instances have types of their own, so they can't be compiled with the caller's
+        if self.tys[self.file_name].get_instance(callee).is_some() {
+            return None;
+        }
+
+        let fqn = match self.world_bodies[self.file_name][callee] {
+            _ if self.tys[self.file_name].get_overload(callee).is_some() => {
+                self.tys[self.file_name].get_overload(callee).unwrap()
+            }
+            hir::Expr::LocalGlobal(name) => {
+                self.world_bodies.resolve_global(self.file_name, name.name)
+            }
+            hir::Expr::Member { previous, name } => {
+                match self.tys[self.file_name].get_namespaced_member(callee) {
+                    Some(fqn) => fqn,
+                    None => match self.tys[self.file_name][previous].as_ref() {
+                        Ty::File(file) => hir::Fqn {
+                            file: *file,
+                            name: name.name,
+                        },
+                        _ => return None,
+                    },
+                }
+            }
+            _ => return None,
+        };
+
+        if self.inlining.contains(&fqn)
+            || !self.world_bodies.exists(fqn)
+            || self.world_bodies.is_extern(fqn)
+        {
+            return None;
+        }
+
+        let bodies = &self.world_bodies[fqn.file];
+        let hir::Expr::Lambda(lambda) = bodies[self.world_bodies.body(fqn)] else {
+            return None;
+        };
+        let hir::Lambda {
+            body,
+            is_extern,
+            inline,
+            ..
+        } = bodies[lambda];
+
+        if is_extern || inline != Some(hir::InlineHint::Always) {
+            return None;
+        }
+
+        // `#tailcall` jumps back to the start of the function it's in,
+        // which doesn't exist once that function has been inlined
+        let has_tail_call = bodies
+            .descendants(
+                body,
+                DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .any(|descendant| {
+                matches!(
+                    descendant,
+                    Descendant::Expr(expr) if matches!(
+                        &bodies[expr],
+                        hir::Expr::Directive { name, .. }
+                            if self.interner.lookup(name.name.0) == "tailcall"
+                    )
+                )
+            });
+
+        (!has_tail_call).then_some((fqn, lambda))
+    }
+
+    /// Compiles the body of an `@inline` function right where it was called,
+    /// with the already compiled arguments bound as its parameters
+    fn compile_inline_call(
+        &mut self,
+        fqn: hir::Fqn,
+        lambda: Idx<hir::Lambda>,
+        args: Vec<Option<Value>>,
+        param_tys: &[ParamTy],
+        return_ty: Intern<Ty>,
+    ) -> Option<Value> {
+        let mut params = FxHashMap::default();
+        for (idx, (arg, param_ty)) in args.into_iter().zip(param_tys).enumerate() {
+            let Some(value) = arg else {
+                continue;
+            };
+
+            // the function gets its own copy of each aggregate, just like with a real call
+            let value = if param_ty.ty.is_aggregate() {
+                let slot = self.builder.create_sized_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: param_ty.ty.size(),
+                    align_shift: param_ty.ty.align_shift(),
+                });
+                let copy = self.builder.ins().stack_addr(self.ptr_ty, slot, 0);
+                self.build_memcpy_ty(value, copy, param_ty.ty, true);
+                copy
+            } else {
+                value
+            };
+
+            let var = Variable::new(self.var_id_gen.generate_unique_id() as usize);
+            let value_ty = self.builder.func.dfg.value_type(value);
+            self.builder.declare_var(var, value_ty);
+            self.builder.def_var(var, value);
+            params.insert(idx as u64, var);
+        }
+
+        // everything keyed by the expressions of a body belongs to the caller,
+        // so it gets set aside until the inlined body is done
+        let file_name = std::mem::replace(&mut self.file_name, fqn.file);
+        let params = std::mem::replace(&mut self.params, params);
+        let local_lambdas = std::mem::take(&mut self.local_lambdas);
+        let locals = std::mem::take(&mut self.locals);
+        let switch_locals = std::mem::take(&mut self.switch_locals);
+        let for_locals = std::mem::take(&mut self.for_locals);
+        let local_slots = std::mem::take(&mut self.local_slots);
+        let exits = std::mem::take(&mut self.exits);
+        let continues = std::mem::take(&mut self.continues);
+        let defer_stack = std::mem::take(&mut self.defer_stack);
+        let tail_call_block = self.tail_call_block.take();
+        let tail_call_params = std::mem::take(&mut self.tail_call_params);
+        self.inlining.push(fqn);
+
+        let body = self.world_bodies[fqn.file][lambda].body;
+        let value = self.compile_and_cast(body, return_ty);
+
+        self.inlining.pop();
+        self.file_name = file_name;
+        self.params = params;
+        self.local_lambdas = local_lambdas;
+        self.locals = locals;
+        self.switch_locals = switch_locals;
+        self.for_locals = for_locals;
+        self.local_slots = local_slots;
+        self.exits = exits;
+        self.continues = continues;
+        self.defer_stack = defer_stack;
+        self.tail_call_block = tail_call_block;
+        self.tail_call_params = tail_call_params;
+
+        value
+    }
+
+    /// Compiles `#tailcall(f(args))` by binding the arguments as the new parameters and
+    /// jumping back to the start of the function, so the stack never grows
+    fn compile_tail_call(&mut self, call: Idx<hir::Expr>) {
+        let hir::Expr::Call { callee, args } = &self.world_bodies[self.file_name][call] else {
+            unreachable!("the argument of `#tailcall` should be a call")
+        };
+        let (param_tys, _) = self.tys[self.file_name][*callee].as_function().unwrap();
+
+        // first evaluate every argument, copying aggregates somewhere safe so that
+        // reading the old parameters can't be affected by writing the new ones
+        let mut new_params = Vec::with_capacity(args.len());
+        for (idx, (arg, param_ty)) in args.iter().zip(param_tys.iter()).enumerate() {
+            let value = self.compile_and_cast(*arg, param_ty.ty);
+
+            let value = match value {
+                Some(value) if param_ty.ty.is_aggregate() => {
+                    let temp = self.builder.create_sized_stack_slot(StackSlotData {
+                        kind: StackSlotKind::ExplicitSlot,
+                        size: param_ty.ty.size(),
+                        align_shift: param_ty.ty.align_shift(),
+                    });
+                    let temp = self.builder.ins().stack_addr(self.ptr_ty, temp, 0);
+                    self.build_memcpy_ty(value, temp, param_ty.ty, true);
+                    Some(temp)
+                }
+                value => value,
+            };
+
+            new_params.push((idx as u32, param_ty.ty, value));
+        }
+
+        // the current call is over, so every pending defer runs now
+        let frames = std::mem::take(&mut self.defer_stack);
+        for frame in frames.iter().rev() {
+            for defer in frame.defers.iter().rev() {
+                self.compile_expr(*defer);
+            }
+        }
+        self.defer_stack = frames;
+
+        for (idx, ty, value) in new_params {
+            let (Some(value), Some(var)) = (value, self.params.get(&(idx as u64)).copied()) else {
+                continue;
+            };
+
+            let value = if ty.is_aggregate() {
+                let slot = *self.tail_call_params.entry(idx).or_insert_with(|| {
+                    self.builder.create_sized_stack_slot(StackSlotData {
+                        kind: StackSlotKind::ExplicitSlot,
+                        size: ty.size(),
+                        align_shift: ty.align_shift(),
+                    })
+                });
+                let home = self.builder.ins().stack_addr(self.ptr_ty, slot, 0);
+                self.build_memcpy_ty(value, home, ty, true);
+                home
+            } else {
+                value
+            };
+
+            self.builder.def_var(var, value);
+        }
+
+        let tail_call_block = self
+            .tail_call_block
+            .expect("`#tailcall` used outside of a function body");
+        self.builder.ins().jump(tail_call_block, &[]);
+
+        // nothing after the tail call can be reached
+        let after = self.builder.create_block();
+        self.builder.switch_to_block(after);
+        self.builder.seal_block(after);
+    }
+
+    /// stops every block between here and the block or loop we are jumping out of from
+    /// releasing the stack slots of its locals
+    fn keep_slots_up_to(&mut self, label: ScopeId) {
+        for frame in self.defer_stack.iter_mut().rev() {
+            if frame.id == Some(label) {
+                break;
+            }
+            frame.keep_slots = true;
+        }
+    }
+
+    /// runs all the defers from here, backwards to the block or loop we are jumping out of
+    fn compile_defers_up_to(&mut self, label: ScopeId) {
+        let mut used_frames = Vec::new();
+
+        while let Some(frame) = self.defer_stack.last().cloned() {
+            // the exit block of every Expr::Block contains the instructions for running
+            // the defers. This break instruction jumps to that exit block.
+            // therefore, we only need to insert extra defer handling for everything OTHER
+            // than the block we are breaking to.
+            if let Some(id) = frame.id {
+                if id == label {
+                    break;
+                }
+            }
+
+            // do this in reverse for the reasons explained in the Expr::Block code
+            for defer in frame.defers.iter().rev() {
+                self.compile_expr(*defer);
+            }
+
+            used_frames.push(self.defer_stack.pop().unwrap());
+        }
+
+        self.defer_stack.extend(used_frames.into_iter().rev());
+    }
+
+    fn store_default_in_memory(&mut self, expected_ty: Intern<Ty>, memory: MemoryLoc) {
+        // every default value is made entirely of zeroes,
+        // so bigger aggregates can be cleared all at once
+        if expected_ty.is_aggregate() && expected_ty.size() > INLINE_MEMORY_OP_LIMIT {
+            memory.fill(
+                0,
+                expected_ty.size(),
+                expected_ty.align() as u8,
+                self.module,
+                &mut self.builder,
+            );
+            return;
+        }
+
+        let value = match expected_ty.as_ref() {
+            Ty::NotYetResolved | Ty::Unknown | Ty::Error => unreachable!(),
+            Ty::IInt(_) | Ty::UInt(_) => {
+                let number_ty = expected_ty.get_final_ty().into_number_type().unwrap();
+                match number_ty.bit_width() {
+                    128 => {
+                        let data = self.create_global_i128(0);
+
+                        let local_id = self.module.declare_data_in_func(data, self.builder.func);
 
                         let addr = self.builder.ins().symbol_value(self.ptr_ty, local_id);
 
@@ -694,6 +1850,18 @@ impl FunctionCompiler<'_> {
                 }
                 return;
             }
+            Ty::Range { sub_ty, .. } => {
+                self.store_default_in_memory(*sub_ty, memory);
+                self.store_default_in_memory(*sub_ty, memory.with_offset(sub_ty.stride()));
+                return;
+            }
+            // the payload of a `null` optional is never read, so only the flag gets cleared
+            Ty::Optional { .. } => {
+                let offset = expected_ty.struct_layout().unwrap().offsets()[1];
+                let is_some = self.builder.ins().iconst(types::I8, 0);
+                memory.store(&mut self.builder, is_some, offset as i32);
+                return;
+            }
             Ty::Slice { .. } => unreachable!("slices do not have default values"),
             Ty::Pointer { .. } => unreachable!("pointers do not have default values"),
             Ty::Distinct { sub_ty, .. } => {
@@ -717,7 +1885,17 @@ impl FunctionCompiler<'_> {
                 }
                 return;
             }
+            Ty::Tuple { items } => {
+                let tuple_mem = expected_ty.struct_layout().unwrap();
+
+                for (idx, ty) in items.iter().enumerate() {
+                    self.store_default_in_memory(*ty, memory.with_offset(tuple_mem.offsets()[idx]));
+                }
+                return;
+            }
             Ty::Enum { .. } => unreachable!("enums do not have default values"),
+            Ty::Interface { .. } => unreachable!("interfaces do not have default values"),
+            Ty::Closure { .. } => unreachable!("closures do not have default values"),
             Ty::Variant { sub_ty, .. } => {
                 self.store_default_in_memory(*sub_ty, memory);
                 return;
@@ -738,6 +1916,12 @@ impl FunctionCompiler<'_> {
     ) {
         let expr_ty = self.tys[self.file_name][expr];
 
+        if *expr_ty == Ty::NoEval || expected_ty.is_zero_sized() {
+            // there's nothing to store
+            self.compile_expr(expr);
+            return;
+        }
+
         // if the expression has to be casted in order to become the expected type, do that.
         // the one cast this applies to is `any` casting.
         if !expr_ty.is_functionally_equivalent_to(&expected_ty, true) {
@@ -752,12 +1936,19 @@ impl FunctionCompiler<'_> {
                     .as_array()
                     .expect("array literals should have an array type");
                 // fixed array
-                self.store_array_items(items.iter().copied(), sub_ty, memory)
+                self.store_array_items(items, sub_ty, memory)
+            }
+            hir::Expr::ArrayRepeat { item, .. } => {
+                let (count, sub_ty) = expected_ty
+                    .as_array()
+                    .expect("array repeats should have an array type");
+                self.fill_array(*item, count as u32, sub_ty, memory)
             }
             hir::Expr::StructLiteral {
                 members: member_values,
                 ..
             } => self.store_struct_fields(expected_ty, member_values, memory),
+            hir::Expr::Tuple(items) => self.store_tuple_items(expected_ty, items, memory),
             _ => {
                 let val = self.compile_expr(expr);
 
@@ -766,6 +1957,44 @@ impl FunctionCompiler<'_> {
         }
     }
 
+    /// Literals are built directly inside of their destination,
+    /// which won't work for something like `p = Point.{ x = p.y, y = p.x };`
+    fn value_reads_dest(&self, dest: Idx<hir::Expr>, value: Idx<hir::Expr>) -> bool {
+        let bodies = &self.world_bodies[self.file_name];
+
+        if !matches!(
+            bodies[value],
+            hir::Expr::StructLiteral { .. }
+                | hir::Expr::Tuple(_)
+                | hir::Expr::ArrayLiteral { .. }
+                | hir::Expr::ArrayRepeat { .. }
+        ) {
+            return false;
+        }
+
+        let mut root = dest;
+        loop {
+            root = match bodies[root] {
+                hir::Expr::Member { previous, .. } => previous,
+                hir::Expr::Index { source, .. } => source,
+                hir::Expr::Deref { pointer } => pointer,
+                hir::Expr::Paren(Some(inner)) => inner,
+                _ => break,
+            };
+        }
+
+        bodies
+            .descendants(
+                value,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .any(|descendant| {
+                matches!(descendant, hir::Descendant::Expr(expr) if bodies[expr] == bodies[root])
+            })
+    }
+
     fn store_struct_fields(
         &mut self,
         struct_ty: Intern<Ty>,
@@ -792,19 +2021,170 @@ impl FunctionCompiler<'_> {
         }
     }
 
+    fn store_tuple_items(
+        &mut self,
+        tuple_ty: Intern<Ty>,
+        items: &[Idx<hir::Expr>],
+        memory: MemoryLoc,
+    ) {
+        let item_tys = tuple_ty.as_tuple().unwrap();
+        let tuple_mem = tuple_ty.struct_layout().unwrap();
+
+        for (idx, item) in items.iter().enumerate() {
+            self.store_expr_in_memory(
+                *item,
+                item_tys[idx],
+                memory.with_offset(tuple_mem.offsets()[idx]),
+            );
+        }
+    }
+
     fn store_array_items(
         &mut self,
-        items: impl Iterator<Item = Idx<hir::Expr>>,
+        items: &[Idx<hir::Expr>],
         // this has to be given since the items may autocast into the actual sub type
         sub_ty: Intern<Ty>,
         memory: MemoryLoc,
     ) {
         let stride = sub_ty.stride();
 
-        for (idx, item) in items.enumerate() {
+        if items.len() as u32 * stride > INLINE_MEMORY_OP_LIMIT {
+            if let Some(item) = self.repeated_literal(items) {
+                self.fill_array(item, items.len() as u32, sub_ty, memory);
+                return;
+            }
+        }
+
+        for (idx, item) in items.iter().enumerate() {
             let byte_offset = idx as u32 * stride;
-            self.store_expr_in_memory(item, sub_ty, memory.with_offset(byte_offset))
+            self.store_expr_in_memory(*item, sub_ty, memory.with_offset(byte_offset))
+        }
+    }
+
+    /// Returns the first item if every item of an array literal is the exact same literal
+    fn repeated_literal(&self, items: &[Idx<hir::Expr>]) -> Option<Idx<hir::Expr>> {
+        let bodies = &self.world_bodies[self.file_name];
+        let tys = &self.tys[self.file_name];
+
+        let first = *items.first()?;
+
+        if !matches!(
+            bodies[first],
+            hir::Expr::IntLiteral(_)
+                | hir::Expr::FloatLiteral(_)
+                | hir::Expr::BoolLiteral(_)
+                | hir::Expr::CharLiteral(_)
+        ) {
+            return None;
+        }
+
+        items
+            .iter()
+            .all(|item| bodies[*item] == bodies[first] && tys[*item] == tys[first])
+            .then_some(first)
+    }
+
+    /// Stores `len` copies of `item`, which is only evaluated once.
+    /// This is a `memset` when every byte of a literal is the same,
+    /// and a loop for anything too big to store one by one.
+    fn fill_array(
+        &mut self,
+        item: Idx<hir::Expr>,
+        len: u32,
+        sub_ty: Intern<Ty>,
+        memory: MemoryLoc,
+    ) {
+        let stride = sub_ty.stride();
+
+        if let Some(byte) = self.literal_byte(item, sub_ty) {
+            memory.fill(
+                byte,
+                len * stride,
+                sub_ty.align() as u8,
+                self.module,
+                &mut self.builder,
+            );
+            return;
+        }
+
+        let Some(value) = self.compile_and_cast(item, sub_ty) else {
+            return;
+        };
+
+        if len * stride <= INLINE_MEMORY_OP_LIMIT {
+            for idx in 0..len {
+                memory.with_offset(idx * stride).write(
+                    Some(value),
+                    sub_ty,
+                    self.module,
+                    &mut self.builder,
+                );
+            }
+            return;
         }
+
+        let start = memory.into_value(&mut self.builder, self.ptr_ty);
+        let end = self.builder.ins().iadd_imm(start, (len * stride) as i64);
+
+        let header_block = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+
+        let addr = self.builder.append_block_param(header_block, self.ptr_ty);
+
+        self.builder.ins().jump(header_block, &[start]);
+
+        self.builder.switch_to_block(header_block);
+        let done = self.builder.ins().icmp(IntCC::Equal, addr, end);
+        self.builder
+            .ins()
+            .brif(done, exit_block, &[], body_block, &[]);
+
+        self.builder.switch_to_block(body_block);
+        self.builder.seal_block(body_block);
+        MemoryLoc::from_addr(addr, 0).write(Some(value), sub_ty, self.module, &mut self.builder);
+        let next = self.builder.ins().iadd_imm(addr, stride as i64);
+        self.builder.ins().jump(header_block, &[next]);
+        self.builder.seal_block(header_block);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+    }
+
+    /// If every byte of the given literal is the same once it has the type `ty`,
+    /// this returns that byte
+    fn literal_byte(&self, literal: Idx<hir::Expr>, ty: Intern<Ty>) -> Option<u8> {
+        // e.g. literals which get casted to `any`
+        if !self.tys[self.file_name][literal].is_functionally_equivalent_to(&ty, true) {
+            return None;
+        }
+
+        let number_ty = ty.get_final_ty().into_number_type();
+
+        let bits: u128 = match (&self.world_bodies[self.file_name][literal], number_ty) {
+            (hir::Expr::IntLiteral(n), Some(number_ty)) if number_ty.float => {
+                match number_ty.bit_width() {
+                    32 => (*n as f32).to_bits() as u128,
+                    _ => (*n as f64).to_bits() as u128,
+                }
+            }
+            (hir::Expr::IntLiteral(n), _) => *n as u128,
+            (hir::Expr::FloatLiteral(f), Some(number_ty)) => match number_ty.bit_width() {
+                32 => (*f as f32).to_bits() as u128,
+                _ => f.to_bits() as u128,
+            },
+            (hir::Expr::BoolLiteral(b), _) => *b as u128,
+            (hir::Expr::CharLiteral(c), _) => *c as u128,
+            _ => return None,
+        };
+
+        let bytes = bits.to_le_bytes();
+        let bytes = &bytes[..ty.size() as usize];
+
+        bytes
+            .iter()
+            .all(|byte| *byte == bytes[0])
+            .then_some(bytes[0])
     }
 
     fn compile_expr(&mut self, expr: Idx<hir::Expr>) -> Option<Value> {
@@ -820,6 +2200,38 @@ impl FunctionCompiler<'_> {
             return Some(self.builder.ins().iconst(types::I32, id as i64));
         }
 
+        // an operation with an operand that exits early never happens itself,
+        // but the operands before that exit still have to be evaluated in order
+        if *self.tys[self.file_name][expr] == Ty::NoEval {
+            let operands = match &self.world_bodies[self.file_name][expr] {
+                hir::Expr::Binary { lhs, rhs, .. } => Some(vec![*lhs, *rhs]),
+                hir::Expr::Call { callee, args } => {
+                    let mut operands = Vec::with_capacity(args.len() + 1);
+                    operands.push(*callee);
+                    operands.extend(args.iter().copied());
+                    Some(operands)
+                }
+                hir::Expr::StructLiteral { members, .. } => {
+                    Some(members.iter().map(|member| member.value).collect())
+                }
+                hir::Expr::Tuple(items) => Some(items.clone()),
+                hir::Expr::Orelse { optional, .. } => Some(vec![*optional]),
+                _ => None,
+            };
+
+            if let Some(operands) = operands {
+                for operand in operands {
+                    self.compile_expr(operand);
+
+                    if *self.tys[self.file_name][operand] == Ty::NoEval {
+                        break;
+                    }
+                }
+
+                return None;
+            }
+        }
+
         match self.world_bodies[self.file_name][expr].clone() {
             hir::Expr::Missing => unreachable!(),
             hir::Expr::IntLiteral(n) => {
@@ -866,6 +2278,25 @@ impl FunctionCompiler<'_> {
                 }
             }
             hir::Expr::BoolLiteral(b) => Some(self.builder.ins().iconst(types::I8, b as i64)),
+            hir::Expr::NullLiteral => {
+                let ty = self.tys[self.file_name][expr];
+
+                if ty.as_optional().is_some() {
+                    let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
+                        kind: StackSlotKind::ExplicitSlot,
+                        size: ty.size(),
+                        align_shift: ty.align_shift(),
+                    });
+
+                    let memory = MemoryLoc::from_stack(stack_slot, 0);
+
+                    self.store_default_in_memory(ty, memory);
+
+                    Some(memory.into_value(&mut self.builder, self.ptr_ty))
+                } else {
+                    Some(self.builder.ins().iconst(self.ptr_ty, 0))
+                }
+            }
             hir::Expr::StringLiteral(text) => {
                 let data = self.create_global_str(text);
 
@@ -892,7 +2323,50 @@ impl FunctionCompiler<'_> {
 
                 let memory = MemoryLoc::from_stack(stack_slot, 0);
 
-                self.store_array_items(items.iter().copied(), sub_ty, memory);
+                self.store_array_items(&items, sub_ty, memory);
+
+                Some(memory.into_value(&mut self.builder, self.ptr_ty))
+            }
+            hir::Expr::ArrayRepeat { item, .. } => {
+                let ty = self.tys[self.file_name][expr];
+
+                if ty.is_zero_sized() {
+                    self.compile_expr(item);
+                    return None;
+                }
+
+                let (count, sub_ty) = ty.as_array().expect("array repeats must have array types");
+
+                let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: ty.size(),
+                    align_shift: ty.align_shift(),
+                });
+
+                let memory = MemoryLoc::from_stack(stack_slot, 0);
+
+                self.fill_array(item, count as u32, sub_ty, memory);
+
+                Some(memory.into_value(&mut self.builder, self.ptr_ty))
+            }
+            hir::Expr::Range { start, end, .. } => {
+                let ty = self.tys[self.file_name][expr];
+                let (_, sub_ty) = ty.as_range().unwrap();
+
+                let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: ty.size(),
+                    align_shift: ty.align_shift(),
+                });
+
+                let memory = MemoryLoc::from_stack(stack_slot, 0);
+
+                // open ranges only exist when slicing, which doesn't compile the range itself
+                let start = start.expect("hir only allows open ranges when slicing");
+                let end = end.expect("hir only allows open ranges when slicing");
+
+                self.store_expr_in_memory(start, sub_ty, memory);
+                self.store_expr_in_memory(end, sub_ty, memory.with_offset(sub_ty.stride()));
 
                 Some(memory.into_value(&mut self.builder, self.ptr_ty))
             }
@@ -918,14 +2392,6 @@ impl FunctionCompiler<'_> {
                         .load(self.ptr_ty, MemFlags::trusted(), source, 0);
                 }
 
-                let index_ty = self.tys[self.file_name][index];
-
-                let index = self.compile_expr(index).unwrap();
-
-                // make sure that the index is a usize before proceeding
-                let naive_index =
-                    super::cast_ty_to_cranelift(&mut self.builder, index, index_ty, self.ptr_ty);
-
                 let (len, source) = if let Some((len, _)) = source_ty.as_array() {
                     (self.builder.ins().iconst(self.ptr_ty, len as i64), source)
                 } else {
@@ -943,6 +2409,18 @@ impl FunctionCompiler<'_> {
                     (len, source)
                 };
 
+                if self.tys[self.file_name][index].as_range().is_some() {
+                    return Some(self.compile_slicing(expr, source, len, index));
+                }
+
+                let index_ty = self.tys[self.file_name][index];
+
+                let index = self.compile_expr(index).unwrap();
+
+                // make sure that the index is a usize before proceeding
+                let naive_index =
+                    super::cast_ty_to_cranelift(&mut self.builder, index, index_ty, self.ptr_ty);
+
                 let good_index_block = self.builder.create_block();
                 let bad_index_block = self.builder.create_block();
 
@@ -1015,14 +2493,7 @@ impl FunctionCompiler<'_> {
                     } else {
                         // even though the expression is void, we still need to get some
                         // result
-
-                        let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
-                            kind: StackSlotKind::ExplicitSlot,
-                            size: 0,
-                            align_shift: 0, // 1 << 0 == 1
-                        });
-
-                        Some(self.builder.ins().stack_addr(self.ptr_ty, stack_slot, 0))
+                        Some(self.zero_sized_addr())
                     }
                 } else {
                     let inner_ty = self.tys[self.file_name][expr];
@@ -1073,7 +2544,10 @@ impl FunctionCompiler<'_> {
                 lhs: lhs_expr,
                 rhs: rhs_expr,
                 op,
-            } => self.compile_binary(lhs_expr, rhs_expr, op),
+            } => match self.tys[self.file_name].get_operator(expr) {
+                Some(fqn) => self.compile_operator_call(fqn, lhs_expr, rhs_expr),
+                None => self.compile_binary(lhs_expr, rhs_expr, op),
+            },
             hir::Expr::Unary { expr, op } => {
                 let expr_ty = self.tys[self.file_name][expr]
                     .get_final_ty()
@@ -1086,8 +2560,7 @@ impl FunctionCompiler<'_> {
                     match op {
                         hir::UnaryOp::Pos => Some(expr),
                         hir::UnaryOp::Neg => Some(self.builder.ins().fneg(expr)),
-                        hir::UnaryOp::BNot => Some(self.builder.ins().bnot(expr)),
-                        hir::UnaryOp::LNot => unreachable!(),
+                        hir::UnaryOp::BNot | hir::UnaryOp::LNot => unreachable!(),
                     }
                 } else {
                     match op {
@@ -1101,18 +2574,75 @@ impl FunctionCompiler<'_> {
                     }
                 }
             }
+            hir::Expr::Call { callee, args } if *self.tys[self.file_name][callee] == Ty::Type => {
+                // `Shape.Circle(r)` is compiled exactly like `Shape.Circle.(r)`
+                let variant_ty = self.tys[self.file_name][expr];
+
+                match args.first() {
+                    Some(payload) => self.compile_and_cast(*payload, variant_ty),
+                    None => self.cast(None, Ty::Void.into(), variant_ty),
+                }
+            }
             hir::Expr::Call { callee, args } => {
-                let (param_tys, return_ty) = self.tys[self.file_name][callee]
+                let (mut param_tys, return_ty) = self.tys[self.file_name][callee]
                     .clone()
-                    .as_function()
+                    .as_callable()
                     .unwrap();
+
+                // `shape.area()` calls whatever `area` is in the method table of `shape`,
+                // passing along the data pointer of `shape` as the first argument
+                let interface_method = self.interface_method(callee);
+                let interface = interface_method.map(|(interface, _)| {
+                    param_tys.insert(
+                        0,
+                        ParamTy {
+                            ty: Ty::RawPtr { mutable: true }.into(),
+                            varargs: false,
+                            impossible_to_differentiate: false,
+                        },
+                    );
+
+                    let interface = self.compile_interface_addr(interface);
+                    let data =
+                        self.builder
+                            .ins()
+                            .load(self.ptr_ty, MemFlags::trusted(), interface, 0);
+                    let vtable = self.builder.ins().load(
+                        self.ptr_ty,
+                        MemFlags::trusted(),
+                        interface,
+                        self.ptr_ty.bytes() as i32,
+                    );
+
+                    (data, vtable)
+                });
+
                 let fn_abi = Into::<Abi>::into(self.module.target_config())
                     .fn_to_target((&param_tys, return_ty));
 
+                // `p.len()` passes `p` as the first argument to `Point.len`
+                let receiver = match self.world_bodies[self.file_name][callee] {
+                    hir::Expr::Member { previous, .. }
+                        if self.tys[self.file_name]
+                            .get_namespaced_member(callee)
+                            .is_some()
+                            && *self.tys[self.file_name][previous] != Ty::Type =>
+                    {
+                        Some(previous)
+                    }
+                    _ => None,
+                };
+
                 // first, figure out how many varargs there are for each parameter
                 let mut params_iter = param_tys.iter();
                 let mut args_iter = args.iter();
 
+                if interface.is_some() {
+                    params_iter.next();
+                }
+
+                let receiver_param = receiver.map(|_| params_iter.next().unwrap());
+
                 let mut current_param = params_iter.next();
                 let mut current_arg = args_iter.next();
 
@@ -1122,7 +2652,13 @@ impl FunctionCompiler<'_> {
                     associated_param: &'a ParamTy,
                 }
 
-                let mut actual_args = Vec::with_capacity(args.len());
+                let mut actual_args = Vec::with_capacity(args.len() + 1);
+                if let (Some(receiver), Some(receiver_param)) = (receiver, receiver_param) {
+                    actual_args.push(ArgToCompile {
+                        values: vec![receiver],
+                        associated_param: receiver_param,
+                    });
+                }
                 let mut working_arg = None::<ArgToCompile>;
                 loop {
                     let Some(arg) = current_arg else {
@@ -1199,7 +2735,10 @@ impl FunctionCompiler<'_> {
                 }
 
                 // second, actually compile each argument and vararg
-                let mut arg_values = Vec::with_capacity(args.len());
+                let mut arg_values = Vec::with_capacity(args.len() + 1);
+                if let Some((data, _)) = interface {
+                    arg_values.push(Some(data));
+                }
                 for arg in actual_args {
                     if arg.associated_param.varargs {
                         let actual_sub_ty = arg.associated_param.ty.as_slice().unwrap();
@@ -1250,29 +2789,69 @@ impl FunctionCompiler<'_> {
                                 .ins()
                                 .stack_addr(self.ptr_ty, slice_stack_slot, 0);
 
-                        arg_values.push(slice_addr);
+                        arg_values.push(Some(slice_addr));
                     } else {
                         assert_eq!(arg.values.len(), 1);
 
-                        if let Some(value) =
-                            self.compile_and_cast(arg.values[0], arg.associated_param.ty)
-                        {
-                            arg_values.push(value);
-                        }
+                        // zero sized args still get pushed so the indices line up with the params
+                        arg_values
+                            .push(self.compile_and_cast(arg.values[0], arg.associated_param.ty));
                     }
                 }
 
-                let mut arg_values = fn_abi.get_arg_list(arg_values, self);
+                if let Some((fqn, lambda)) = self.inline_callee(callee) {
+                    return self
+                        .compile_inline_call(fqn, lambda, arg_values, &param_tys, return_ty);
+                }
+
+                let mut arg_values = fn_abi.get_arg_list(arg_values, &param_tys, self);
 
                 let ret_mem =
                     fn_abi.ret_addr(&mut arg_values, &mut self.builder, return_ty, self.ptr_ty);
 
+                if self.tys[self.file_name][callee].as_closure().is_some() {
+                    let closure = self.compile_expr(callee).unwrap();
+
+                    return self.call_closure(closure, &fn_abi, arg_values, ret_mem, return_ty);
+                }
+
                 let call = match self.world_bodies[self.file_name][callee] {
+                    _ if interface.is_some() => {
+                        let (_, vtable) = interface.unwrap();
+                        let (_, method_idx) = interface_method.unwrap();
+
+                        let method = self.builder.ins().load(
+                            self.ptr_ty,
+                            MemFlags::trusted(),
+                            vtable,
+                            (method_idx as u32 * self.ptr_ty.bytes()) as i32,
+                        );
+
+                        let comp_sig = fn_abi
+                            .to_cl(self.ptr_ty, self.module.target_config().default_call_conv);
+
+                        let sig_ref = self.builder.import_signature(comp_sig);
+
+                        self.builder
+                            .ins()
+                            .call_indirect(sig_ref, method, &arg_values)
+                    }
+                    _ if self.tys[self.file_name].get_instance(callee).is_some() => {
+                        let instance = self.tys[self.file_name].get_instance(callee).unwrap();
+
+                        let local_func = self.get_local_instance(instance);
+
+                        self.builder.ins().call(local_func, &arg_values)
+                    }
+                    _ if self.tys[self.file_name].get_overload(callee).is_some() => {
+                        let fqn = self.tys[self.file_name].get_overload(callee).unwrap();
+
+                        let local_func = self.get_local_func(fqn);
+
+                        self.builder.ins().call(local_func, &arg_values)
+                    }
                     hir::Expr::LocalGlobal(name) => {
-                        let fqn = hir::Fqn {
-                            file: self.file_name,
-                            name: name.name,
-                        };
+                        let fqn = self.world_bodies.resolve_global(self.file_name, name.name);
 
                         let local_func = self.get_local_func(fqn);
 
@@ -1302,6 +2881,21 @@ impl FunctionCompiler<'_> {
                                 .call_indirect(sig_ref, callee, &arg_values)
                         }
                     }
+                    hir::Expr::Member { .. }
+                        if self.tys[self.file_name]
+                            .get_namespaced_member(callee)
+                            .is_some() =>
+                    {
+                        // declarations inside of struct bodies are always called directly,
+                        // whether they're accessed through the struct or through a value
+                        let fqn = self.tys[self.file_name]
+                            .get_namespaced_member(callee)
+                            .unwrap();
+
+                        let local_func = self.get_local_func(fqn);
+
+                        self.builder.ins().call(local_func, &arg_values)
+                    }
                     hir::Expr::Member {
                         previous,
                         name: field,
@@ -1347,6 +2941,7 @@ impl FunctionCompiler<'_> {
                             .call_indirect(sig_ref, callee, &arg_values)
                     }
                 };
+                self.check_cancelled();
 
                 if return_ty.is_zero_sized() {
                     None
@@ -1373,6 +2968,7 @@ impl FunctionCompiler<'_> {
                 self.defer_stack.push(DeferFrame {
                     id: scope_id,
                     defers: Vec::new(),
+                    keep_slots: false,
                 });
 
                 self.builder.ins().jump(body_block, &[]);
@@ -1381,7 +2977,7 @@ impl FunctionCompiler<'_> {
                 self.builder.seal_block(body_block);
 
                 let mut no_eval = false;
-                for stmt in stmts {
+                for &stmt in &stmts {
                     self.compile_stmt(&stmt);
                     match self.world_bodies[self.file_name][stmt] {
                         // if the current scope id is `None`
@@ -1407,10 +3003,20 @@ impl FunctionCompiler<'_> {
                 let value = (!no_eval)
                     .then(|| {
                         tail_expr.and_then(|tail_expr| {
-                            let value = self.compile_expr_with_args(tail_expr, no_load);
-                            if scope_id.is_none()
-                                && *self.tys[self.file_name][tail_expr] == Ty::NoEval
+                            let tail_ty = self.tys[self.file_name][tail_expr];
+
+                            // something else breaks out of this block, which can make it a
+                            // different type than its tail, like a `return null` before an `i32`
+                            // tail in a function returning `?i32`
+                            let value = if scope_id.is_some()
+                                && *tail_ty != Ty::NoEval
+                                && tail_ty != expr_ty
                             {
+                                self.compile_and_cast_with_args(tail_expr, no_load, expr_ty)
+                            } else {
+                                self.compile_expr_with_args(tail_expr, no_load)
+                            };
+                            if scope_id.is_none() && *tail_ty == Ty::NoEval {
                                 no_eval = true;
                             }
                             value
@@ -1467,6 +3073,14 @@ impl FunctionCompiler<'_> {
                     }
                 }
 
+                // the locals of this block are dead now, so later locals can reuse their slots.
+                // blocks which evaluate to (or break out with) aggregates or pointers might be
+                // giving back an address into one of those slots, so they keep theirs for the
+                // whole function
+                if !defer_frame.keep_slots && !expr_ty.is_aggregate() && !expr_ty.is_pointer() {
+                    self.release_block_slots(expr, &stmts);
+                }
+
                 if final_ty.into_real_type().is_some() {
                     Some(self.builder.block_params(exit_block)[0])
                 } else {
@@ -1553,55 +3167,88 @@ impl FunctionCompiler<'_> {
                     None
                 }
             }
-            hir::Expr::While { condition, body } => {
-                let header_block = self.builder.create_block();
-                let body_block = self.builder.create_block();
-                let exit_block = self.builder.create_block();
+            hir::Expr::Orelse { optional, fallback } => {
+                let optional_ty = self.tys[self.file_name][optional];
+                let optional_val = self.compile_expr(optional).unwrap();
 
-                let ty = self.tys[self.file_name][expr].get_final_ty();
+                let is_some = self.optional_is_some(optional_ty, optional_val);
 
-                if let Some(ty) = ty.into_real_type() {
-                    self.builder.append_block_param(exit_block, ty);
-                }
-                if let Some(scope_id) = self.world_bodies[self.file_name].block_to_scope_id(expr) {
-                    self.continues.insert(scope_id, header_block);
-                    self.exits.insert(scope_id, exit_block);
+                let some_block = self.builder.create_block();
+                let null_block = self.builder.create_block();
+                let merge_block = self.builder.create_block();
+
+                let return_ty = self.tys[self.file_name][expr];
+                let return_ty_real = return_ty.get_final_ty().into_real_type();
+
+                if let Some(return_ty) = return_ty_real {
+                    self.builder.append_block_param(merge_block, return_ty);
                 }
 
-                self.builder.ins().jump(header_block, &[]);
-                self.builder.switch_to_block(header_block);
-                // don't seal the header yet
+                self.builder
+                    .ins()
+                    .brif(is_some, some_block, &[], null_block, &[]);
 
-                if let Some(condition) =
-                    condition.and_then(|condition| self.compile_expr(condition))
-                {
-                    self.builder
-                        .ins()
-                        .brif(condition, body_block, &[], exit_block, &[]);
+                // build some block
+
+                self.builder.switch_to_block(some_block);
+                self.builder.seal_block(some_block);
+
+                // when the fallback is also an optional, the whole optional is passed along
+                let some_value = if return_ty.as_optional().is_some() {
+                    Some(optional_val)
                 } else {
-                    self.builder.ins().jump(body_block, &[]);
+                    self.optional_payload(optional_ty, return_ty, optional_val)
+                };
+
+                match some_value {
+                    Some(some_value) => {
+                        self.builder.ins().jump(merge_block, &[some_value]);
+                    }
+                    None => {
+                        self.builder.ins().jump(merge_block, &[]);
+                    }
                 }
 
-                self.builder.switch_to_block(body_block);
-                self.builder.seal_block(body_block);
+                // build null block
 
-                self.compile_expr(body);
+                self.builder.switch_to_block(null_block);
+                self.builder.seal_block(null_block);
 
-                self.builder.ins().jump(header_block, &[]);
+                let fallback_value = self.compile_and_cast(fallback, return_ty);
 
-                // We've reached the bottom of the loop, so there will be no
-                // more jumps to the header
-                self.builder.seal_block(header_block);
+                if *self.tys[self.file_name][fallback] == Ty::NoEval {
+                    self.builder.ins().trap(TRAP_UNREACHABLE);
+                } else {
+                    match fallback_value {
+                        Some(fallback_value) => {
+                            self.builder.ins().jump(merge_block, &[fallback_value]);
+                        }
+                        None => {
+                            self.builder.ins().jump(merge_block, &[]);
+                        }
+                    }
+                }
 
-                self.builder.switch_to_block(exit_block);
-                self.builder.seal_block(exit_block);
+                // build merge block
 
-                if ty.into_real_type().is_some() {
-                    Some(self.builder.block_params(exit_block)[0])
+                self.builder.switch_to_block(merge_block);
+                self.builder.seal_block(merge_block);
+
+                if return_ty_real.is_some() {
+                    let phi = self.builder.block_params(merge_block)[0];
+
+                    Some(phi)
                 } else {
                     None
                 }
             }
+            hir::Expr::While { condition, body } => self.compile_loop(expr, Some(condition), body),
+            hir::Expr::Loop { body } => self.compile_loop(expr, None, body),
+            hir::Expr::For {
+                item,
+                iterable,
+                body,
+            } => self.compile_for(expr, item, iterable, body),
             hir::Expr::Switch {
                 scrutinee,
                 arms,
@@ -1623,11 +3270,16 @@ impl FunctionCompiler<'_> {
                     .collect();
                 let exit_block = self.builder.create_block();
 
-                let enum_ty = self.tys[self.file_name][scrutinee];
+                let mut required_derefs = 0;
+                let mut enum_ty = self.tys[self.file_name][scrutinee];
+                while let Some((_, sub_ty)) = enum_ty.as_pointer() {
+                    enum_ty = sub_ty;
+                    required_derefs += 1;
+                }
                 let Ty::Enum {
                     variants: variant_tys,
                     ..
-                } = enum_ty.as_ref()
+                } = enum_ty.absolute_ty_keep_variants()
                 else {
                     unreachable!("the scrutinee should be an enum")
                 };
@@ -1644,9 +3296,15 @@ impl FunctionCompiler<'_> {
                 self.builder.seal_block(body_block);
                 self.builder.switch_to_block(body_block);
 
-                let scrutinee_val = self
+                let mut scrutinee_val = self
                     .compile_expr(scrutinee)
                     .expect("enums are never zero sized");
+                for _ in 1..required_derefs {
+                    scrutinee_val =
+                        self.builder
+                            .ins()
+                            .load(self.ptr_ty, MemFlags::trusted(), scrutinee_val, 0);
+                }
                 let discrim_val = self.builder.ins().load(
                     types::I8,
                     MemFlags::trusted(),
@@ -1754,6 +3412,36 @@ impl FunctionCompiler<'_> {
                         .map(|ty| self.builder.ins().load(ty, MemFlags::trusted(), ptr, 0))
                 }
             }
+            hir::Expr::ForLocal(for_local) => {
+                let ptr = *self.for_locals.get(&for_local)?;
+
+                let ty = &self.tys[self.file_name][for_local];
+
+                if no_load || ty.is_aggregate() {
+                    Some(ptr)
+                } else {
+                    let ty = ty.get_final_ty();
+
+                    // if it isn't a real type, this will just return None
+                    ty.into_real_type()
+                        .map(|ty| self.builder.ins().load(ty, MemFlags::trusted(), ptr, 0))
+                }
+            }
+            hir::Expr::Captured(capture) => {
+                let ptr = *self.captured.get(&capture)?;
+
+                let ty = &self.tys[self.file_name][expr];
+
+                if no_load || ty.is_aggregate() {
+                    Some(ptr)
+                } else {
+                    let ty = ty.get_final_ty();
+
+                    // if it isn't a real type, this will just return None
+                    ty.into_real_type()
+                        .map(|ty| self.builder.ins().load(ty, MemFlags::trusted(), ptr, 0))
+                }
+            }
             hir::Expr::Param { idx, .. } => self
                 .params
                 .get(&(idx as u64))
@@ -1763,10 +3451,7 @@ impl FunctionCompiler<'_> {
                     return None;
                 }
 
-                let fqn = hir::Fqn {
-                    file: self.file_name,
-                    name: name.name,
-                };
+                let fqn = self.world_bodies.resolve_global(self.file_name, name.name);
 
                 self.compile_global(fqn, no_load)
             }
@@ -1775,6 +3460,10 @@ impl FunctionCompiler<'_> {
                     return None;
                 }
 
+                if let Some(fqn) = self.tys[self.file_name].get_namespaced_member(expr) {
+                    return self.compile_global(fqn, no_load);
+                }
+
                 let previous_ty = self.tys[self.file_name][previous];
                 match previous_ty.as_ref() {
                     Ty::File(file) => {
@@ -1786,7 +3475,7 @@ impl FunctionCompiler<'_> {
                         self.compile_global(fqn, no_load)
                     }
                     _ => {
-                        let field_ty = &self.tys[self.file_name][expr];
+                        let field_ty = self.tys[self.file_name][expr];
                         let field_comp_ty = field_ty.get_final_ty().into_real_type()?;
 
                         let mut required_derefs = 0;
@@ -1876,14 +3565,20 @@ impl FunctionCompiler<'_> {
                             _ => {}
                         }
 
-                        let struct_fields = source_ty.as_struct().unwrap();
-
-                        let field_idx = struct_fields
-                            .iter()
-                            .enumerate()
-                            .find(|(_, source_member)| source_member.name == name.name)
-                            .map(|(idx, _)| idx)
-                            .unwrap();
+                        let field_idx = match source_ty.as_ref() {
+                            // tuple members are named by their position (`t.0`)
+                            Ty::Tuple { .. } => {
+                                self.interner.lookup(name.name.0).parse::<usize>().unwrap()
+                            }
+                            _ => source_ty
+                                .as_struct()
+                                .unwrap()
+                                .iter()
+                                .enumerate()
+                                .find(|(_, source_member)| source_member.name == name.name)
+                                .map(|(idx, _)| idx)
+                                .unwrap(),
+                        };
 
                         let offset = source_ty.struct_layout().unwrap().offsets()[field_idx];
 
@@ -1913,8 +3608,14 @@ impl FunctionCompiler<'_> {
             }
             hir::Expr::Lambda(lambda) => {
                 let local_func = self.unnamed_func_to_local(expr, lambda);
+                let func_addr = self.builder.ins().func_addr(self.ptr_ty, local_func);
 
-                Some(self.builder.ins().func_addr(self.ptr_ty, local_func))
+                let captures = &self.world_bodies[self.file_name][lambda].captures;
+                if captures.is_empty() {
+                    Some(func_addr)
+                } else {
+                    Some(self.compile_closure(func_addr, captures.clone()))
+                }
             }
             hir::Expr::StructLiteral {
                 members: field_values,
@@ -1922,6 +3623,13 @@ impl FunctionCompiler<'_> {
             } => {
                 let ty = self.tys[self.file_name][expr];
 
+                if ty.is_zero_sized() {
+                    for hir::MemberLiteral { value, .. } in field_values {
+                        self.compile_expr(value);
+                    }
+                    return None;
+                }
+
                 let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
                     kind: StackSlotKind::ExplicitSlot,
                     size: ty.size(),
@@ -1934,15 +3642,53 @@ impl FunctionCompiler<'_> {
 
                 Some(memory.into_value(&mut self.builder, self.ptr_ty))
             }
+            hir::Expr::Tuple(items) => {
+                let ty = self.tys[self.file_name][expr];
+
+                // tuple types like `(i32, str)` don't exist at runtime
+                if *ty == Ty::Type {
+                    return None;
+                }
+
+                if ty.is_zero_sized() {
+                    for item in items {
+                        self.compile_expr(item);
+                    }
+                    return None;
+                }
+
+                let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
+                    kind: StackSlotKind::ExplicitSlot,
+                    size: ty.size(),
+                    align_shift: ty.align_shift(),
+                });
+
+                let memory = MemoryLoc::from_stack(stack_slot, 0);
+
+                self.store_tuple_items(ty, &items, memory);
+
+                Some(memory.into_value(&mut self.builder, self.ptr_ty))
+            }
             hir::Expr::PrimitiveTy { .. } => None,
             hir::Expr::Distinct { .. } => None,
+            hir::Expr::OptionalDecl { .. } => None,
+            hir::Expr::ClosureDecl { .. } => None,
             hir::Expr::StructDecl { .. } => None,
             hir::Expr::EnumDecl { .. } => None,
+            hir::Expr::InterfaceDecl { .. } => None,
             hir::Expr::Import(_) => None,
             hir::Expr::Directive { name, args } => match self.interner.lookup(name.name.0) {
                 "unwrap" => {
                     let enum_val = self.compile_expr(args[0])?;
                     let enum_ty = self.tys[self.file_name][args[0]];
+
+                    if let Some(sub_ty) = enum_ty.as_optional() {
+                        let is_some = self.optional_is_some(enum_ty, enum_val);
+
+                        self.builder.ins().trapz(is_some, TRAP_UNREACHABLE);
+
+                        return self.optional_payload(enum_ty, sub_ty, enum_val);
+                    }
                     let enum_layout = enum_ty.enum_layout().unwrap();
 
                     let variant_ty = self.tys[self.file_name].get_meta_ty(args[1]).unwrap();
@@ -1970,6 +3716,23 @@ impl FunctionCompiler<'_> {
 
                     Some(enum_val)
                 }
+                "align_cast" => {
+                    let ptr = self.compile_expr(args[0])?;
+
+                    let (_, sub_ty) = self.tys[self.file_name][expr].as_pointer().unwrap();
+                    let misalignment = self.builder.ins().band_imm(ptr, sub_ty.align() as i64 - 1);
+
+                    self.builder.ins().trapnz(misalignment, TRAP_MISALIGNED);
+
+                    Some(ptr)
+                }
+                "tailcall" => {
+                    self.compile_tail_call(args[0]);
+                    None
+                }
+                name if IntrinsicOperands::of(name).is_some() => {
+                    self.compile_math_intrinsic(name, expr, &args)
+                }
                 _ => unreachable!(),
             },
             hir::Expr::Comptime(comptime) => {
@@ -2005,12 +3768,21 @@ impl FunctionCompiler<'_> {
                                 _ => unreachable!(),
                             }
                         }
-                        ComptimeResult::Data(bytes) => {
+                        ComptimeResult::String(_)
+                        | ComptimeResult::Bytes(_)
+                        | ComptimeResult::Struct { .. }
+                        | ComptimeResult::Array { .. }
+                        | ComptimeResult::Variant { .. }
+                        | ComptimeResult::Data(_) => {
+                            let mut relocs = Vec::new();
+                            let bytes = self.comptime_to_const_data(result.clone(), &mut relocs);
+
                             let data = self.create_global_data(
                                 &ctc.to_mangled_name(self.mod_dir, self.interner),
                                 false,
-                                bytes.clone(),
+                                bytes,
                                 ty.align() as u64,
+                                relocs,
                             );
 
                             let local_id =
@@ -2133,6 +3905,438 @@ impl FunctionCompiler<'_> {
         }
     }
 
+    /// `while` loops have a condition, `loop`s don't
+    fn compile_loop(
+        &mut self,
+        expr: Idx<hir::Expr>,
+        condition: Option<Idx<hir::Expr>>,
+        body: Idx<hir::Expr>,
+    ) -> Option<Value> {
+        let header_block = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+
+        let ty = self.tys[self.file_name][expr].get_final_ty();
+
+        if let Some(ty) = ty.into_real_type() {
+            self.builder.append_block_param(exit_block, ty);
+        }
+        let scope_id = self.world_bodies[self.file_name].block_to_scope_id(expr);
+        if let Some(scope_id) = scope_id {
+            self.continues.insert(scope_id, header_block);
+            self.exits.insert(scope_id, exit_block);
+        }
+
+        // loops never have defers of their own, but breaks and continues
+        // need to know where to stop running the defers of inner blocks
+        self.defer_stack.push(DeferFrame {
+            id: scope_id,
+            defers: Vec::new(),
+            keep_slots: false,
+        });
+
+        self.builder.ins().jump(header_block, &[]);
+        self.builder.switch_to_block(header_block);
+        // don't seal the header yet
+
+        if let Some(condition) = condition.and_then(|condition| self.compile_expr(condition)) {
+            self.builder
+                .ins()
+                .brif(condition, body_block, &[], exit_block, &[]);
+        } else {
+            self.builder.ins().jump(body_block, &[]);
+        }
+
+        self.builder.switch_to_block(body_block);
+        self.builder.seal_block(body_block);
+        self.check_cancelled();
+
+        self.compile_expr(body);
+
+        self.defer_stack.pop().expect("we just pushed this");
+
+        self.builder.ins().jump(header_block, &[]);
+
+        // We've reached the bottom of the loop, so there will be no
+        // more jumps to the header
+        self.builder.seal_block(header_block);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+
+        if ty.into_real_type().is_some() {
+            Some(self.builder.block_params(exit_block)[0])
+        } else {
+            None
+        }
+    }
+
+    /// `for x in xs { ... }` walks over every element of an array or slice.
+    /// `x` points directly into `xs`, so the elements never get copied
+    fn compile_for(
+        &mut self,
+        expr: Idx<hir::Expr>,
+        item: Idx<ForLocal>,
+        iterable: Idx<hir::Expr>,
+        body: Idx<hir::Expr>,
+    ) -> Option<Value> {
+        let mut iterable_ty = self.tys[self.file_name][iterable];
+        // this will be `None` for zero-sized arrays
+        let mut source = self.compile_expr(iterable);
+
+        let mut required_derefs = 0;
+        while let Some((_, sub_ty)) = iterable_ty.as_pointer() {
+            iterable_ty = sub_ty;
+            required_derefs += 1;
+        }
+
+        for _ in 1..required_derefs {
+            source = source.map(|source| {
+                self.builder
+                    .ins()
+                    .load(self.ptr_ty, MemFlags::trusted(), source, 0)
+            });
+        }
+
+        if let Some((inclusive, sub_ty)) = iterable_ty.as_range() {
+            return self.compile_for_range(expr, item, source, inclusive, sub_ty, body);
+        }
+
+        let (len, elements, sub_ty) = if let Some((len, sub_ty)) = iterable_ty.as_array() {
+            (
+                self.builder.ins().iconst(self.ptr_ty, len as i64),
+                source,
+                sub_ty,
+            )
+        } else {
+            let sub_ty = iterable_ty
+                .as_slice()
+                .expect("hir_ty only allows iterating over arrays, slices, and ranges");
+            let source = source.unwrap();
+
+            let len = self
+                .builder
+                .ins()
+                .load(self.ptr_ty, MemFlags::trusted(), source, 0);
+            let elements = self.builder.ins().load(
+                self.ptr_ty,
+                MemFlags::trusted(),
+                source,
+                self.ptr_ty.bytes() as i32,
+            );
+
+            (len, Some(elements), sub_ty)
+        };
+        let stride = sub_ty.stride();
+
+        let header_block = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let increment_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+
+        // `continue` has to go through the increment, otherwise the loop would never end
+        let scope_id = self.world_bodies[self.file_name].block_to_scope_id(expr);
+        if let Some(scope_id) = scope_id {
+            self.continues.insert(scope_id, increment_block);
+            self.exits.insert(scope_id, exit_block);
+        }
+
+        self.defer_stack.push(DeferFrame {
+            id: scope_id,
+            defers: Vec::new(),
+            keep_slots: false,
+        });
+
+        let idx = Variable::new(self.var_id_gen.generate_unique_id() as usize);
+        self.builder.declare_var(idx, self.ptr_ty);
+        let zero = self.builder.ins().iconst(self.ptr_ty, 0);
+        self.builder.def_var(idx, zero);
+
+        self.builder.ins().jump(header_block, &[]);
+        self.builder.switch_to_block(header_block);
+        // don't seal the header yet
+
+        let current = self.builder.use_var(idx);
+        let in_bounds = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThan, current, len);
+        self.builder
+            .ins()
+            .brif(in_bounds, body_block, &[], exit_block, &[]);
+
+        self.builder.switch_to_block(body_block);
+        self.builder.seal_block(body_block);
+        self.check_cancelled();
+
+        if let Some(elements) = elements {
+            let offset = self.builder.ins().imul_imm(current, stride as i64);
+            let addr = self.builder.ins().iadd(elements, offset);
+            self.for_locals.insert(item, addr);
+        }
+
+        self.compile_expr(body);
+
+        self.defer_stack.pop().expect("we just pushed this");
+
+        self.builder.ins().jump(increment_block, &[]);
+
+        self.builder.switch_to_block(increment_block);
+        self.builder.seal_block(increment_block);
+
+        let current = self.builder.use_var(idx);
+        let next = self.builder.ins().iadd_imm(current, 1);
+        self.builder.def_var(idx, next);
+        self.builder.ins().jump(header_block, &[]);
+
+        self.builder.seal_block(header_block);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+
+        // breaks out of a `for` loop can't have values
+        None
+    }
+
+    /// `for i in start..end` counts up from `start`, storing each value in a stack slot so that
+    /// `i` can be used like the elements of an array
+    fn compile_for_range(
+        &mut self,
+        expr: Idx<hir::Expr>,
+        item: Idx<ForLocal>,
+        range: Option<Value>,
+        inclusive: bool,
+        sub_ty: Intern<Ty>,
+        body: Idx<hir::Expr>,
+    ) -> Option<Value> {
+        let range = range.unwrap();
+        let number_ty = sub_ty.get_final_ty().into_number_type().unwrap();
+
+        let start = self
+            .builder
+            .ins()
+            .load(number_ty.ty, MemFlags::trusted(), range, 0);
+        let end = self.builder.ins().load(
+            number_ty.ty,
+            MemFlags::trusted(),
+            range,
+            sub_ty.stride() as i32,
+        );
+
+        let header_block = self.builder.create_block();
+        let body_block = self.builder.create_block();
+        let increment_block = self.builder.create_block();
+        let exit_block = self.builder.create_block();
+
+        let scope_id = self.world_bodies[self.file_name].block_to_scope_id(expr);
+        if let Some(scope_id) = scope_id {
+            self.continues.insert(scope_id, increment_block);
+            self.exits.insert(scope_id, exit_block);
+        }
+
+        self.defer_stack.push(DeferFrame {
+            id: scope_id,
+            defers: Vec::new(),
+            keep_slots: false,
+        });
+
+        let item_slot = self.builder.create_sized_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: sub_ty.size(),
+            align_shift: sub_ty.align_shift(),
+        });
+
+        let current_var = Variable::new(self.var_id_gen.generate_unique_id() as usize);
+        self.builder.declare_var(current_var, number_ty.ty);
+        self.builder.def_var(current_var, start);
+
+        self.builder.ins().jump(header_block, &[]);
+        self.builder.switch_to_block(header_block);
+        // don't seal the header yet
+
+        let current = self.builder.use_var(current_var);
+        let cond = match (inclusive, number_ty.signed) {
+            (false, false) => IntCC::UnsignedLessThan,
+            (false, true) => IntCC::SignedLessThan,
+            (true, false) => IntCC::UnsignedLessThanOrEqual,
+            (true, true) => IntCC::SignedLessThanOrEqual,
+        };
+        let in_bounds = self.builder.ins().icmp(cond, current, end);
+        self.builder
+            .ins()
+            .brif(in_bounds, body_block, &[], exit_block, &[]);
+
+        self.builder.switch_to_block(body_block);
+        self.builder.seal_block(body_block);
+        self.check_cancelled();
+
+        self.builder.ins().stack_store(current, item_slot, 0);
+        let item_addr = self.builder.ins().stack_addr(self.ptr_ty, item_slot, 0);
+        self.for_locals.insert(item, item_addr);
+
+        self.compile_expr(body);
+
+        self.defer_stack.pop().expect("we just pushed this");
+
+        self.builder.ins().jump(increment_block, &[]);
+
+        self.builder.switch_to_block(increment_block);
+        self.builder.seal_block(increment_block);
+
+        let current = self.builder.use_var(current_var);
+
+        if inclusive {
+            // checking before incrementing means `0..=255` doesn't overflow and loop forever
+            let next_block = self.builder.create_block();
+
+            let at_end = self.builder.ins().icmp(IntCC::Equal, current, end);
+            self.builder
+                .ins()
+                .brif(at_end, exit_block, &[], next_block, &[]);
+
+            self.builder.switch_to_block(next_block);
+            self.builder.seal_block(next_block);
+        }
+
+        let next = self.builder.ins().iadd_imm(current, 1);
+        self.builder.def_var(current_var, next);
+        self.builder.ins().jump(header_block, &[]);
+
+        self.builder.seal_block(header_block);
+
+        self.builder.switch_to_block(exit_block);
+        self.builder.seal_block(exit_block);
+
+        None
+    }
+
+    /// Compiles `source[start..end]` into a new slice.
+    ///
+    /// `source` is the address of the first element, and a missing start or end means the start
+    /// or end of the whole array
+    fn compile_slicing(
+        &mut self,
+        expr: Idx<hir::Expr>,
+        source: Value,
+        len: Value,
+        index: Idx<hir::Expr>,
+    ) -> Value {
+        let (inclusive, bound_ty) = self.tys[self.file_name][index].as_range().unwrap();
+
+        let (start, end) = match self.world_bodies[self.file_name][index] {
+            hir::Expr::Range { start, end, .. } => {
+                let mut compile_bound = |bound: Option<Idx<hir::Expr>>| {
+                    bound.map(|bound| {
+                        let value = self.compile_expr(bound).unwrap();
+                        super::cast_ty_to_cranelift(&mut self.builder, value, bound_ty, self.ptr_ty)
+                    })
+                };
+
+                (compile_bound(start), compile_bound(end))
+            }
+            _ => {
+                let range = self.compile_expr(index).unwrap();
+                let number_ty = bound_ty.get_final_ty().into_number_type().unwrap();
+
+                let mut load_bound = |offset: u32| {
+                    let value = self.builder.ins().load(
+                        number_ty.ty,
+                        MemFlags::trusted(),
+                        range,
+                        offset as i32,
+                    );
+                    super::cast_ty_to_cranelift(&mut self.builder, value, bound_ty, self.ptr_ty)
+                };
+
+                (Some(load_bound(0)), Some(load_bound(bound_ty.stride())))
+            }
+        };
+
+        let start = start.unwrap_or_else(|| self.builder.ins().iconst(self.ptr_ty, 0));
+        let end = match end {
+            Some(end) if inclusive => self.builder.ins().iadd_imm(end, 1),
+            Some(end) => end,
+            None => len,
+        };
+
+        // the same as indexing, slicing outside of the array is a trap
+        let good_range_block = self.builder.create_block();
+        let bad_range_block = self.builder.create_block();
+
+        let ordered = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThanOrEqual, start, end);
+        let within_len = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedLessThanOrEqual, end, len);
+        let is_good_range = self.builder.ins().band(ordered, within_len);
+        self.builder
+            .ins()
+            .brif(is_good_range, good_range_block, &[], bad_range_block, &[]);
+
+        self.builder.switch_to_block(bad_range_block);
+        self.builder.set_cold_block(bad_range_block);
+        self.builder.seal_block(bad_range_block);
+
+        self.builder.ins().trap(TRAP_UNREACHABLE);
+
+        self.builder.switch_to_block(good_range_block);
+        self.builder.seal_block(good_range_block);
+
+        let slice_ty = self.tys[self.file_name][expr];
+        let sub_ty = slice_ty.as_slice().unwrap();
+
+        let byte_offset = self.builder.ins().imul_imm(start, sub_ty.stride() as i64);
+        let elements = self.builder.ins().iadd(source, byte_offset);
+        let new_len = self.builder.ins().isub(end, start);
+
+        // a slice is len (usize) + ptr (usize)
+        let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
+            kind: StackSlotKind::ExplicitSlot,
+            size: slice_ty.size(),
+            align_shift: slice_ty.align_shift(),
+        });
+
+        self.builder.ins().stack_store(new_len, stack_slot, 0);
+        self.builder
+            .ins()
+            .stack_store(elements, stack_slot, self.ptr_ty.bytes() as i32);
+
+        self.builder.ins().stack_addr(self.ptr_ty, stack_slot, 0)
+    }
+
+    /// `a + b` where `+` was overloaded by a function like `__add`
+    fn compile_operator_call(
+        &mut self,
+        fqn: hir::Fqn,
+        lhs_expr: Idx<hir::Expr>,
+        rhs_expr: Idx<hir::Expr>,
+    ) -> Option<Value> {
+        let (param_tys, return_ty) = self.tys[fqn].0.as_function().unwrap();
+        let fn_abi =
+            Into::<Abi>::into(self.module.target_config()).fn_to_target((&param_tys, return_ty));
+
+        let arg_values = vec![
+            self.compile_and_cast(lhs_expr, param_tys[0].ty),
+            self.compile_and_cast(rhs_expr, param_tys[1].ty),
+        ];
+        let mut arg_values = fn_abi.get_arg_list(arg_values, &param_tys, self);
+
+        let ret_mem = fn_abi.ret_addr(&mut arg_values, &mut self.builder, return_ty, self.ptr_ty);
+
+        let local_func = self.get_local_func(fqn);
+        let call = self.builder.ins().call(local_func, &arg_values);
+        self.check_cancelled();
+
+        if return_ty.is_zero_sized() {
+            None
+        } else {
+            fn_abi.handle_ret(call, self, ret_mem)
+        }
+    }
+
     fn compile_binary(
         &mut self,
         lhs_expr: Idx<hir::Expr>,
@@ -2154,8 +4358,13 @@ impl FunctionCompiler<'_> {
                 self.builder.switch_to_block(rhs_block);
                 self.builder.seal_block(rhs_block);
 
-                let rhs = self.compile_expr(rhs_expr).unwrap();
-                self.builder.ins().jump(exit_block, &[rhs]);
+                let rhs = self.compile_expr(rhs_expr);
+
+                if *self.tys[self.file_name][rhs_expr] == Ty::NoEval {
+                    self.builder.ins().trap(TRAP_UNREACHABLE);
+                } else {
+                    self.builder.ins().jump(exit_block, &[rhs.unwrap()]);
+                }
 
                 self.builder.switch_to_block(exit_block);
                 self.builder.seal_block(exit_block);
@@ -2177,8 +4386,13 @@ impl FunctionCompiler<'_> {
                 self.builder.switch_to_block(rhs_block);
                 self.builder.seal_block(rhs_block);
 
-                let rhs = self.compile_expr(rhs_expr).unwrap();
-                self.builder.ins().jump(exit_block, &[rhs]);
+                let rhs = self.compile_expr(rhs_expr);
+
+                if *self.tys[self.file_name][rhs_expr] == Ty::NoEval {
+                    self.builder.ins().trap(TRAP_UNREACHABLE);
+                } else {
+                    self.builder.ins().jump(exit_block, &[rhs.unwrap()]);
+                }
 
                 self.builder.switch_to_block(exit_block);
                 self.builder.seal_block(exit_block);
@@ -2186,6 +4400,31 @@ impl FunctionCompiler<'_> {
 
                 return Some(result);
             }
+            hir::BinaryOp::Eq | hir::BinaryOp::Ne => {
+                // checking an optional against `null` only has to look at its flag
+                let bodies = &self.world_bodies[self.file_name];
+                let optional = match (&bodies[lhs_expr], &bodies[rhs_expr]) {
+                    (_, hir::Expr::NullLiteral) => Some(lhs_expr),
+                    (hir::Expr::NullLiteral, _) => Some(rhs_expr),
+                    _ => None,
+                }
+                .filter(|optional| self.tys[self.file_name][*optional].as_optional().is_some());
+
+                if let Some(optional) = optional {
+                    let optional_ty = self.tys[self.file_name][optional];
+                    let optional_val = self.compile_expr(optional).unwrap();
+
+                    let is_some = self.optional_is_some(optional_ty, optional_val);
+
+                    let cond = if op == hir::BinaryOp::Eq {
+                        IntCC::Equal
+                    } else {
+                        IntCC::NotEqual
+                    };
+
+                    return Some(self.builder.ins().icmp_imm(cond, is_some, 0));
+                }
+            }
             _ => {}
         }
 
@@ -2201,12 +4440,20 @@ impl FunctionCompiler<'_> {
 
         let lhs_ty = self.tys[self.file_name][lhs_expr]
             .get_final_ty()
-            .into_number_type()
-            .unwrap();
+            .into_number_type();
         let rhs_ty = self.tys[self.file_name][rhs_expr]
             .get_final_ty()
-            .into_number_type()
-            .unwrap();
+            .into_number_type();
+
+        // pointers can only be checked for equality, like `ptr == null`
+        let (Some(lhs_ty), Some(rhs_ty)) = (lhs_ty, rhs_ty) else {
+            let cond = match op {
+                hir::BinaryOp::Eq => IntCC::Equal,
+                hir::BinaryOp::Ne => IntCC::NotEqual,
+                _ => unreachable!("pointers can only be compared"),
+            };
+            return Some(self.builder.ins().icmp(cond, lhs, rhs));
+        };
 
         let max_ty = lhs_ty.max(rhs_ty);
 
@@ -2230,10 +4477,11 @@ impl FunctionCompiler<'_> {
                     .fcmp(FloatCC::GreaterThanOrEqual, lhs, rhs),
                 hir::BinaryOp::Eq => self.builder.ins().fcmp(FloatCC::Equal, lhs, rhs),
                 hir::BinaryOp::Ne => self.builder.ins().fcmp(FloatCC::NotEqual, lhs, rhs),
-                hir::BinaryOp::BAnd => self.builder.ins().band(lhs, rhs),
-                hir::BinaryOp::BOr => self.builder.ins().bor(lhs, rhs),
-                hir::BinaryOp::Xor => self.builder.ins().bxor(lhs, rhs),
-                hir::BinaryOp::LShift | hir::BinaryOp::RShift => unreachable!(),
+                hir::BinaryOp::BAnd
+                | hir::BinaryOp::BOr
+                | hir::BinaryOp::Xor
+                | hir::BinaryOp::LShift
+                | hir::BinaryOp::RShift => unreachable!(),
                 hir::BinaryOp::LAnd | hir::BinaryOp::LOr => unreachable!(),
             })
         } else {
@@ -2316,11 +4564,17 @@ impl FunctionCompiler<'_> {
             return *func_ref;
         }
 
-        let (param_tys, return_ty) = self.tys[self.file_name][expr].as_function().unwrap();
+        let (param_tys, return_ty) = self.tys[self.file_name][expr].as_callable().unwrap();
 
-        let sig = Into::<Abi>::into(self.module.target_config())
-            .fn_to_target((&param_tys, return_ty))
-            .to_cl(self.ptr_ty, self.module.target_config().default_call_conv);
+        let mut fn_abi =
+            Into::<Abi>::into(self.module.target_config()).fn_to_target((&param_tys, return_ty));
+        if !self.world_bodies[self.file_name][lambda]
+            .captures
+            .is_empty()
+        {
+            fn_abi = fn_abi.with_env();
+        }
+        let sig = fn_abi.to_cl(self.ptr_ty, self.module.target_config().default_call_conv);
 
         let ftc = FunctionToCompile {
             file_name: self.file_name,
@@ -2328,9 +4582,10 @@ impl FunctionCompiler<'_> {
             lambda,
             param_tys,
             return_ty,
+            instance: None,
         };
 
-        let mangled = ftc.to_mangled_name(self.mod_dir, self.interner);
+        let mangled = mangle::function_symbol(&ftc, self.world_bodies, self.mod_dir, self.interner);
 
         self.functions_to_compile.push_back(ftc);
 
@@ -2346,7 +4601,44 @@ impl FunctionCompiler<'_> {
         local_func
     }
 
+    /// Loads the flag that's `false` when the optional is `null`
+    fn optional_is_some(&mut self, optional_ty: Intern<Ty>, optional: Value) -> Value {
+        let offset = optional_ty.struct_layout().unwrap().offsets()[1];
+
+        self.builder
+            .ins()
+            .load(types::I8, MemFlags::trusted(), optional, offset as i32)
+    }
+
+    fn optional_payload(
+        &mut self,
+        optional_ty: Intern<Ty>,
+        sub_ty: Intern<Ty>,
+        optional: Value,
+    ) -> Option<Value> {
+        if sub_ty.is_zero_sized() {
+            return None;
+        }
+
+        let offset = optional_ty.struct_layout().unwrap().offsets()[0];
+
+        if sub_ty.is_aggregate() {
+            Some(self.builder.ins().iadd_imm(optional, offset as i64))
+        } else {
+            Some(self.builder.ins().load(
+                sub_ty.get_final_ty().into_real_type().unwrap(),
+                MemFlags::trusted(),
+                optional,
+                offset as i32,
+            ))
+        }
+    }
+
     pub fn compile_and_cast(&mut self, expr: Idx<hir::Expr>, cast_to: Intern<Ty>) -> Option<Value> {
+        if let Some(interface) = self.compile_interface_cast(expr, cast_to, None) {
+            return Some(interface);
+        }
+
         let value = self.compile_expr(expr);
 
         self.cast(value, self.tys[self.file_name][expr], cast_to)
@@ -2358,6 +4650,10 @@ impl FunctionCompiler<'_> {
         no_load: bool,
         cast_to: Intern<Ty>,
     ) -> Option<Value> {
+        if let Some(interface) = self.compile_interface_cast(expr, cast_to, None) {
+            return Some(interface);
+        }
+
         let value = self.compile_expr_with_args(expr, no_load);
 
         self.cast(value, self.tys[self.file_name][expr], cast_to)
@@ -2369,12 +4665,30 @@ impl FunctionCompiler<'_> {
         cast_to: Intern<Ty>,
         memory: MemoryLoc,
     ) -> Option<Value> {
+        if *self.tys[self.file_name][expr] == Ty::NoEval {
+            return self.compile_expr(expr);
+        }
+
+        if let Some(interface) = self.compile_interface_cast(expr, cast_to, Some(memory)) {
+            return Some(interface);
+        }
+
         if self.tys[self.file_name][expr].is_functionally_equivalent_to(&cast_to, true) {
             self.store_expr_in_memory(expr, cast_to, memory);
 
             return Some(memory.into_value(&mut self.builder, self.ptr_ty));
         }
 
+        // struct literals can be built right inside of the destination,
+        // casting each of their members along the way
+        if let hir::Expr::StructLiteral { members, .. } = &self.world_bodies[self.file_name][expr] {
+            if cast_to.is_struct() {
+                self.store_struct_fields(cast_to, members, memory);
+
+                return Some(memory.into_value(&mut self.builder, self.ptr_ty));
+            }
+        }
+
         // todo: there should be a function similar to `store_expr_in_memory` that also casts along
         // the way. this would remove an unnecessary memcpy
         let value = self.compile_expr(expr);
@@ -2382,6 +4696,71 @@ impl FunctionCompiler<'_> {
         self.cast_into_memory(value, self.tys[self.file_name][expr], cast_to, memory)
     }
 
+    /// Puts the pointer `expr` together with the method table of the interface it's being used as.
+    /// Returns `None` if `expr` isn't being turned into an interface
+    fn compile_interface_cast(
+        &mut self,
+        expr: Idx<hir::Expr>,
+        cast_to: Intern<Ty>,
+        memory: Option<MemoryLoc>,
+    ) -> Option<Value> {
+        if !matches!(cast_to.absolute_ty(), Ty::Interface { .. }) {
+            return None;
+        }
+        let impls = self.tys[self.file_name].get_interface_cast(expr)?.to_vec();
+
+        let data = self.compile_expr(expr).unwrap();
+
+        let vtable = self.get_vtable(impls);
+        let vtable = self.module.declare_data_in_func(vtable, self.builder.func);
+        let vtable = self.builder.ins().symbol_value(self.ptr_ty, vtable);
+
+        let memory = memory.unwrap_or_else(|| {
+            let stack_slot = self.builder.create_sized_stack_slot(StackSlotData {
+                kind: StackSlotKind::ExplicitSlot,
+                size: cast_to.size(),
+                align_shift: cast_to.align_shift(),
+            });
+
+            MemoryLoc::from_stack(stack_slot, 0)
+        });
+
+        memory.store(&mut self.builder, data, 0);
+        memory.store(&mut self.builder, vtable, self.ptr_ty.bytes() as i32);
+
+        Some(memory.into_value(&mut self.builder, self.ptr_ty))
+    }
+
+    /// The method table of an interface is just an array of function pointers,
+    /// one for each method in the order that the interface declares them
+    fn get_vtable(&mut self, impls: Vec<hir::Fqn>) -> DataId {
+        if let Some(vtable) = self.vtables.get(&impls) {
+            return *vtable;
+        }
+
+        let ptr_size = self.ptr_ty.bytes();
+
+        let mut relocs = Vec::with_capacity(impls.len());
+        for (idx, fqn) in impls.iter().enumerate() {
+            relocs.push((
+                idx as u32 * ptr_size,
+                ConstReloc::Func(self.get_func_id(*fqn)),
+            ));
+        }
+
+        let vtable = self.create_global_data(
+            &format!(".vtable_{}", self.vtables.len()),
+            false,
+            vec![0; impls.len() * ptr_size as usize].into_boxed_slice(),
+            ptr_size as u64,
+            relocs,
+        );
+
+        self.vtables.insert(impls, vtable);
+
+        vtable
+    }
+
     fn cast_into_memory(
         &mut self,
         val: Option<Value>,
@@ -6,12 +6,12 @@ mod ty_info;
 use cranelift::codegen::ir::StackSlot;
 use cranelift::codegen::{self, CodegenError};
 use cranelift::prelude::{
-    types, FunctionBuilder, FunctionBuilderContext, InstBuilder, MemFlags, StackSlotData,
-    StackSlotKind, Value,
+    types, FloatCC, FunctionBuilder, FunctionBuilderContext, InstBuilder, IntCC, MemFlags,
+    StackSlotData, StackSlotKind, Value,
 };
 use cranelift_module::{DataDescription, DataId, FuncId, Linkage, Module, ModuleError};
 use hir::FQComptime;
-use hir_ty::{ComptimeResult, InternTyExt, ParamTy, Ty};
+use hir_ty::{ComptimeResult, InternTyExt, ParamTy, PrimitiveCast, Ty};
 use interner::Interner;
 use internment::Intern;
 use la_arena::Idx;
@@ -24,7 +24,7 @@ use crate::layout::{self, GetLayoutInfo};
 use crate::mangle::{self, Mangle};
 use crate::{
     convert::{self, *},
-    FinalSignature, Verbosity,
+    CodegenProgress, FinalSignature, Verbosity,
 };
 
 use self::abi::Abi;
@@ -188,6 +188,8 @@ pub(crate) struct FunctionToCompile {
     pub(crate) lambda: Idx<hir::Lambda>,
     pub(crate) param_tys: Vec<ParamTy>,
     pub(crate) return_ty: Intern<Ty>,
+    /// the instance of a function with compile-time parameters that this is
+    pub(crate) instance: Option<u32>,
 }
 
 pub(crate) struct Compiler<'a> {
@@ -211,16 +213,45 @@ pub(crate) struct Compiler<'a> {
 
     // globals
     pub(crate) functions: FxHashMap<hir::Fqn, FuncId>,
+    pub(crate) instances: FxHashMap<u32, FuncId>,
     pub(crate) compiler_defined_functions: FxHashMap<BuiltinFunction, FuncId>,
     pub(crate) data: FxHashMap<hir::Fqn, DataId>,
     pub(crate) meta_tys: MetaTyData,
     pub(crate) cmd_args_slice: Option<DataId>,
     pub(crate) str_id_gen: UIDGenerator,
     pub(crate) i128_id_gen: UIDGenerator,
+    pub(crate) read_only_data: ReadOnlyData,
+    /// the method tables of interfaces, keyed by the functions that implement them
+    pub(crate) vtables: FxHashMap<Vec<hir::Fqn>, DataId>,
     pub(crate) comptime_results: &'a FxHashMap<FQComptime, ComptimeResult>,
     pub(crate) comptime_data: FxHashMap<FQComptime, ComptimeData>,
 
     pub(crate) default_abi: Abi,
+
+    pub(crate) progress: Option<&'a mut dyn FnMut(CodegenProgress)>,
+
+    /// the address of the flag which comptime code keeps checking to know if it has to stop,
+    /// see `comptime::Sandbox`
+    pub(crate) cancel_flag: Option<usize>,
+}
+
+/// Read-only data that has already been defined, keyed by its contents and alignment.
+///
+/// Identical constants (string literals, constant arrays and structs, comptime results, etc.)
+/// are only emitted once, no matter how many files or functions they come from.
+#[derive(Default)]
+pub(crate) struct ReadOnlyData {
+    by_align: FxHashMap<u64, FxHashMap<Box<[u8]>, DataId>>,
+}
+
+impl ReadOnlyData {
+    pub(crate) fn get(&self, data: &[u8], align: u64) -> Option<DataId> {
+        self.by_align.get(&align)?.get(data).copied()
+    }
+
+    pub(crate) fn insert(&mut self, data: Box<[u8]>, align: u64, id: DataId) {
+        self.by_align.entry(align).or_default().insert(data, id);
+    }
 }
 
 /// The order of functions to call is this:
@@ -245,8 +276,18 @@ impl Compiler<'_> {
     /// It iteratively compiles every used function into cranelift IR.
     /// This only happens for functions/globals reached by the entry point.
     fn compile_queued(&mut self) {
+        let mut compiled = 0;
+
         while let Some(ftc) = self.functions_to_compile.pop_front() {
             self.compile_ftc(ftc);
+            compiled += 1;
+
+            if let Some(progress) = &mut self.progress {
+                progress(CodegenProgress {
+                    compiled,
+                    total: compiled + self.functions_to_compile.len(),
+                });
+            }
         }
     }
 
@@ -286,7 +327,10 @@ impl Compiler<'_> {
 
     fn compile_ftc(&mut self, ftc: FunctionToCompile) {
         let hir::Lambda {
-            body, is_extern, ..
+            body,
+            is_extern,
+            captures,
+            ..
         } = &self.world_bodies[ftc.file_name][ftc.lambda];
 
         if let Some(compiler_defined) =
@@ -320,7 +364,21 @@ impl Compiler<'_> {
                 name,
             };
 
-            fqn.to_string(self.mod_dir, self.interner)
+            let name = fqn.to_string(self.mod_dir, self.interner);
+
+            match ftc.instance {
+                Some(instance) => format!(
+                    "{name}({})",
+                    self.tys
+                        .instance(instance)
+                        .consts
+                        .iter()
+                        .map(u64::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                None => name,
+            }
         } else {
             format!(
                 "{}.lambda#{}",
@@ -331,11 +389,13 @@ impl Compiler<'_> {
 
         self.compile_real_function(
             &unmangled_name,
-            &ftc.to_mangled_name(self.mod_dir, self.interner),
+            &mangle::function_symbol(&ftc, self.world_bodies, self.mod_dir, self.interner),
             ftc.file_name,
+            ftc.instance,
             *body,
             ftc.param_tys,
             ftc.return_ty,
+            captures.clone(),
         );
     }
 
@@ -391,22 +451,27 @@ impl Compiler<'_> {
         self.module.clear_context(&mut self.ctx);
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn compile_real_function(
         &mut self,
         unmangled_name: &str,
         mangled_name: &str,
         module_name: hir::FileName,
+        instance: Option<u32>,
         body: Idx<hir::Expr>,
         param_tys: Vec<ParamTy>,
         return_ty: Intern<Ty>,
+        captures: Vec<Idx<hir::Capture>>,
     ) -> FuncId {
         self.compile_real_function_with_abi(
             unmangled_name,
             mangled_name,
             module_name,
+            instance,
             body,
             param_tys,
             return_ty,
+            captures,
             self.default_abi,
         )
     }
@@ -417,12 +482,17 @@ impl Compiler<'_> {
         unmangled_name: &str,
         mangled_name: &str,
         module_name: hir::FileName,
+        instance: Option<u32>,
         body: Idx<hir::Expr>,
         param_tys: Vec<ParamTy>,
         return_ty: Intern<Ty>,
+        captures: Vec<Idx<hir::Capture>>,
         abi: Abi,
     ) -> FuncId {
-        let fn_abi = abi.fn_to_target((&param_tys, return_ty));
+        let mut fn_abi = abi.fn_to_target((&param_tys, return_ty));
+        if !captures.is_empty() {
+            fn_abi = fn_abi.with_env();
+        }
         let comp_sig = fn_abi.to_cl(self.ptr_ty, self.module.target_config().default_call_conv);
         let func_id = self
             .module
@@ -441,7 +511,7 @@ impl Compiler<'_> {
             mod_dir: self.mod_dir,
             interner: self.interner,
             world_bodies: self.world_bodies,
-            tys: self.tys,
+            tys: self.tys.body_tys(instance),
             module: self.module,
             ptr_ty: self.ptr_ty,
             data_description: &mut self.data_desc,
@@ -449,21 +519,36 @@ impl Compiler<'_> {
             meta_tys: &mut self.meta_tys,
             cmd_args_slice: &mut self.cmd_args_slice,
             local_functions: FxHashMap::default(),
+            local_instances: FxHashMap::default(),
             local_lambdas: FxHashMap::default(),
             functions: &mut self.functions,
+            instances: &mut self.instances,
             compiler_defined_functions: &mut self.compiler_defined_functions,
             globals: &mut self.data,
             str_id_gen: &mut self.str_id_gen,
             i128_id_gen: &mut self.i128_id_gen,
+            read_only_data: &mut self.read_only_data,
+            vtables: &mut self.vtables,
             comptime_results: self.comptime_results,
             comptime_data: &mut self.comptime_data,
             var_id_gen: UIDGenerator::default(),
             locals: FxHashMap::default(),
+            local_slots: FxHashMap::default(),
+            free_stack_slots: FxHashMap::default(),
+            zero_sized_slot: None,
             switch_locals: FxHashMap::default(),
+            for_locals: FxHashMap::default(),
             params: FxHashMap::default(),
+            captures,
+            captured: FxHashMap::default(),
             exits: FxHashMap::default(),
             continues: FxHashMap::default(),
             defer_stack: Vec::new(),
+            tail_call_block: None,
+            tail_call_params: FxHashMap::default(),
+            inlining: Vec::new(),
+            cancel_flag: self.cancel_flag,
+            cancel_block: None,
         };
 
         let is_mod = module_name.is_mod(self.mod_dir, self.interner);
@@ -472,12 +557,7 @@ impl Compiler<'_> {
             println!("{} \x1B[90m{}\x1B[0m:", unmangled_name, mangled_name);
         }
 
-        function_compiler.finish(
-            fn_abi,
-            (&param_tys, return_ty),
-            body,
-            self.verbosity.should_show(is_mod),
-        );
+        function_compiler.finish(fn_abi, return_ty, body, self.verbosity.should_show(is_mod));
 
         if self.verbosity.include_disasm(is_mod) {
             self.ctx.want_disasm = true;
@@ -547,7 +627,11 @@ fn get_func_id(
             .to_cl(pointer_ty, module.target_config().default_call_conv);
 
         let func_id = module
-            .declare_function(interner.lookup(fqn.name.0), Linkage::Import, &comp_sig)
+            .declare_function(
+                mangle::extern_name(fqn, interner),
+                Linkage::Import,
+                &comp_sig,
+            )
             .expect("There are multiple extern functions with the same name");
 
         functions.insert(fqn, func_id);
@@ -560,10 +644,7 @@ fn get_func_id(
     let lambda = match world_bodies[fqn.file][global_body] {
         hir::Expr::Lambda(lambda) => lambda,
         hir::Expr::LocalGlobal(global) => {
-            let fqn = hir::Fqn {
-                file: fqn.file,
-                name: global.name,
-            };
+            let fqn = world_bodies.resolve_global(fqn.file, global.name);
 
             // todo: remove recursion
             return get_func_id(
@@ -589,6 +670,20 @@ fn get_func_id(
                     name: field.name,
                 };
 
+                // todo: remove recursion
+                return get_func_id(
+                    module,
+                    pointer_ty,
+                    mod_dir,
+                    functions,
+                    compiler_defined_functions,
+                    functions_to_compile,
+                    tys,
+                    world_bodies,
+                    interner,
+                    fqn,
+                );
+            } else if let Some(fqn) = tys[fqn.file].get_namespaced_member(global_body) {
                 // todo: remove recursion
                 return get_func_id(
                     module,
@@ -617,6 +712,7 @@ fn get_func_id(
         lambda,
         param_tys: param_tys.clone(),
         return_ty,
+        instance: None,
     };
 
     if let Some(compiler_defined) = as_compiler_defined_func(is_extern, &ftc, mod_dir, interner) {
@@ -643,7 +739,11 @@ fn get_func_id(
             .to_cl(pointer_ty, module.target_config().default_call_conv);
 
         let func_id = module
-            .declare_function(interner.lookup(fqn.name.0), Linkage::Import, &comp_sig)
+            .declare_function(
+                mangle::extern_name(fqn, interner),
+                Linkage::Import,
+                &comp_sig,
+            )
             .expect("There are multiple extern functions with the same name");
 
         functions.insert(fqn, func_id);
@@ -651,6 +751,8 @@ fn get_func_id(
         return func_id;
     }
 
+    let symbol = mangle::function_symbol(&ftc, world_bodies, mod_dir, interner);
+
     functions_to_compile.push_back(ftc);
 
     let comp_sig = Into::<Abi>::into(module.target_config())
@@ -658,11 +760,7 @@ fn get_func_id(
         .to_cl(pointer_ty, module.target_config().default_call_conv);
 
     let func_id = module
-        .declare_function(
-            &fqn.to_mangled_name(mod_dir, interner),
-            Linkage::Export,
-            &comp_sig,
-        )
+        .declare_function(&symbol, Linkage::Export, &comp_sig)
         .unwrap();
 
     functions.insert(fqn, func_id);
@@ -670,6 +768,56 @@ fn get_func_id(
     func_id
 }
 
+/// Each instance of a function with compile-time parameters is compiled as a function of its own
+#[allow(clippy::too_many_arguments)]
+fn get_instance_func_id(
+    module: &mut dyn Module,
+    pointer_ty: types::Type,
+    mod_dir: &std::path::Path,
+    instances: &mut FxHashMap<u32, FuncId>,
+    functions_to_compile: &mut VecDeque<FunctionToCompile>,
+    tys: &hir_ty::ProjectInference,
+    world_bodies: &hir::WorldBodies,
+    interner: &Interner,
+    id: u32,
+) -> FuncId {
+    if let Some(func_id) = instances.get(&id) {
+        return *func_id;
+    }
+
+    let instance = tys.instance(id);
+    let (param_tys, return_ty) = instance.ty().as_function().unwrap();
+
+    let comp_sig = Into::<Abi>::into(module.target_config())
+        .fn_to_target((&param_tys, return_ty))
+        .to_cl(pointer_ty, module.target_config().default_call_conv);
+
+    let ftc = FunctionToCompile {
+        file_name: instance.fqn.file,
+        function_name: Some(instance.fqn.name),
+        lambda: instance.lambda.lambda,
+        param_tys,
+        return_ty,
+        instance: Some(id),
+    };
+
+    let symbol = mangle::function_symbol(&ftc, world_bodies, mod_dir, interner);
+
+    functions_to_compile.push_back(ftc);
+
+    let func_id = module
+        .declare_function(&symbol, Linkage::Export, &comp_sig)
+        .unwrap();
+
+    instances.insert(id, func_id);
+
+    func_id
+}
+
+/// Copies and fills bigger than this many bytes are handed off to `memcpy` and `memset`
+/// instead of being unrolled into individual loads and stores
+pub(crate) const INLINE_MEMORY_OP_LIMIT: u32 = 64;
+
 #[derive(Debug, Clone, Copy)]
 enum Location {
     Stack(StackSlot),
@@ -733,6 +881,26 @@ impl MemoryLoc {
         };
     }
 
+    /// Sets `size` bytes to `byte`, which becomes a `memset` call for larger sizes
+    fn fill(
+        self,
+        byte: u8,
+        size: u32,
+        align: u8,
+        module: &mut dyn Module,
+        builder: &mut FunctionBuilder,
+    ) {
+        let addr = self.into_value(builder, module.target_config().pointer_type());
+        builder.emit_small_memset(
+            module.target_config(),
+            addr,
+            byte,
+            size as u64,
+            align,
+            MemFlags::trusted(),
+        );
+    }
+
     /// Does a simple store of a memmove if necessary
     ///
     /// By using this function, you promise that the bytes of val can fit inside
@@ -769,6 +937,19 @@ impl MemoryLoc {
                         MemFlags::trusted(),
                     )
                 }
+                Location::Stack(_) if ty.stride() > INLINE_MEMORY_OP_LIMIT => {
+                    let addr = self.into_value(builder, module.target_config().pointer_type());
+                    builder.emit_small_memory_copy(
+                        module.target_config(),
+                        addr,
+                        val,
+                        ty.stride() as u64,
+                        ty.align() as u8,
+                        ty.align() as u8,
+                        true,
+                        MemFlags::trusted(),
+                    )
+                }
                 Location::Stack(slot) => {
                     // be very explicit to cranelift what we are doing here
                     // since there is no `emit_stack_memcpy`, do it ourselves
@@ -922,6 +1103,16 @@ fn cast_into_memory(
 
             return Some(memory.into_value(builder, ptr_ty));
         }
+        (Ty::Function { .. }, Ty::Closure { .. }) => {
+            let memory = memory.unwrap_or_alloca(builder, cast_to);
+
+            // a plain function doesn't have an environment to pass along
+            let env = builder.ins().iconst(ptr_ty, 0);
+            memory.store(builder, val?, 0_i32);
+            memory.store(builder, env, ptr_ty.bytes() as i32);
+
+            return Some(memory.into_value(builder, ptr_ty));
+        }
         (Ty::Slice { .. }, Ty::Array { .. }) => {
             // todo: do a runtime check that the lengths match
 
@@ -967,6 +1158,27 @@ fn cast_into_memory(
 
             return Some(any_mem.into_value(builder, ptr_ty));
         }
+        // the value becomes the payload of an optional that isn't `null`
+        (from, Ty::Optional { sub_ty }) if from.as_optional().is_none() => {
+            let memory = memory.unwrap_or_alloca(builder, cast_to);
+            let offsets = cast_to.struct_layout().unwrap().offsets().to_vec();
+
+            cast_into_memory(
+                meta_tys,
+                module,
+                builder,
+                ptr_ty,
+                val,
+                cast_from_original,
+                *sub_ty,
+                Some(memory.with_offset(offsets[0])),
+            );
+
+            let is_some = builder.ins().iconst(types::I8, 1);
+            memory.store(builder, is_some, offsets[1] as i32);
+
+            return Some(memory.into_value(builder, ptr_ty));
+        }
         (Ty::Struct { .. }, Ty::Struct { .. }) => {
             return cast_struct_to_struct(
                 meta_tys, module, builder, ptr_ty, val, cast_from, cast_to, memory,
@@ -983,8 +1195,11 @@ fn cast_into_memory(
     // it's a simple cast
 
     let val = match (cast_from.get_final_ty(), cast_to.get_final_ty()) {
-        (FinalTy::Number(cast_from), FinalTy::Number(cast_to)) => {
-            Some(cast_num(builder, val?, cast_from, cast_to))
+        (FinalTy::Number(from_number), FinalTy::Number(to_number)) => {
+            match cast_from.primitive_cast(&cast_to) {
+                Some(PrimitiveCast::ToBool) => Some(cast_num_to_bool(builder, val?, from_number)),
+                _ => Some(cast_num(builder, val?, from_number, to_number)),
+            }
         }
         _ => val,
     };
@@ -1169,6 +1384,11 @@ fn cast_ty_to_cranelift(
     }
 }
 
+/// Converts a number into another number following the rules described by
+/// `hir_ty::PrimitiveCast`.
+///
+/// Casts into `bool` have to go through `cast_num_to_bool` instead, since a `bool` has the same
+/// `NumberType` as a `u8`.
 fn cast_num(
     builder: &mut FunctionBuilder,
     val: Value,
@@ -1192,12 +1412,12 @@ fn cast_num(
         (true, false) => {
             // float to int
 
-            // cranelift can only convert floats to i32 or i64, so we do that first,
-            // then cast the i32 or i64 to the actual one we want
-            let int_to = match cast_from.bit_width() {
-                32 => types::I32,
-                64 => types::I64,
-                _ => unreachable!(),
+            // cranelift can only convert floats to i32 or i64, so we convert to whichever one
+            // can hold the target, then cast that to the actual int type we want
+            let int_to = if cast_to.bit_width() <= 32 {
+                types::I32
+            } else {
+                types::I64
             };
 
             let first_cast = if cast_to.signed {
@@ -1206,30 +1426,47 @@ fn cast_num(
                 builder.ins().fcvt_to_uint_sat(int_to, val)
             };
 
-            // now we can convert the `first_cast` int value to the actual int type we want
-            match cast_from.bit_width().cmp(&cast_to.bit_width()) {
+            match (int_to.bits() as u8).cmp(&cast_to.bit_width()) {
                 std::cmp::Ordering::Less if cast_to.signed => {
                     builder.ins().sextend(cast_to.ty, first_cast)
                 }
                 std::cmp::Ordering::Less => builder.ins().uextend(cast_to.ty, first_cast),
                 std::cmp::Ordering::Equal => first_cast,
-                std::cmp::Ordering::Greater => builder.ins().ireduce(cast_to.ty, first_cast),
+                std::cmp::Ordering::Greater => {
+                    // clamp before reducing, so that small ints saturate instead of wrapping
+                    let bits = cast_to.bit_width() as u32;
+                    let clamped = if cast_to.signed {
+                        let max = (1_i64 << (bits - 1)) - 1;
+                        let min = -(1_i64 << (bits - 1));
+
+                        let max = builder.ins().iconst(int_to, max);
+                        let min = builder.ins().iconst(int_to, min as i32 as u32 as i64);
+
+                        let clamped = builder.ins().smin(first_cast, max);
+                        builder.ins().smax(clamped, min)
+                    } else {
+                        let max = builder.ins().iconst(int_to, (1_i64 << bits) - 1);
+
+                        builder.ins().umin(first_cast, max)
+                    };
+
+                    builder.ins().ireduce(cast_to.ty, clamped)
+                }
             }
         }
         (false, true) => {
             // int to float
 
-            // first we have to convert the int to an int that can converted to float
-            let int_to = match cast_to.bit_width() {
-                32 => types::I32,
-                64 => types::I64,
-                _ => unreachable!(),
+            // first we have to convert the int to an int that can converted to float.
+            // the extension is based on the signedness of the original int
+            let int_to = if cast_from.bit_width() <= 32 {
+                types::I32
+            } else {
+                types::I64
             };
 
-            let first_cast = match cast_from.bit_width().cmp(&cast_to.bit_width()) {
-                std::cmp::Ordering::Less if cast_from.signed && cast_to.signed => {
-                    builder.ins().sextend(int_to, val)
-                }
+            let first_cast = match cast_from.bit_width().cmp(&(int_to.bits() as u8)) {
+                std::cmp::Ordering::Less if cast_from.signed => builder.ins().sextend(int_to, val),
                 std::cmp::Ordering::Less => builder.ins().uextend(int_to, val),
                 std::cmp::Ordering::Equal => val,
                 std::cmp::Ordering::Greater => builder.ins().ireduce(int_to, val),
@@ -1245,7 +1482,7 @@ fn cast_num(
         (false, false) => {
             // int to int
             match cast_from.bit_width().cmp(&cast_to.bit_width()) {
-                std::cmp::Ordering::Less if cast_from.signed && cast_to.signed => {
+                std::cmp::Ordering::Less if cast_from.signed => {
                     builder.ins().sextend(cast_to.ty, val)
                 }
                 std::cmp::Ordering::Less => builder.ins().uextend(cast_to.ty, val),
@@ -1257,3 +1494,18 @@ fn cast_num(
 
     res
 }
+
+/// `x as bool` is the same as `x != 0`
+fn cast_num_to_bool(builder: &mut FunctionBuilder, val: Value, cast_from: NumberType) -> Value {
+    if cast_from.float {
+        let zero = match cast_from.ty {
+            types::F32 => builder.ins().f32const(0.0),
+            types::F64 => builder.ins().f64const(0.0),
+            _ => unreachable!(),
+        };
+
+        builder.ins().fcmp(FloatCC::NotEqual, val, zero)
+    } else {
+        builder.ins().icmp_imm(IntCC::NotEqual, val, 0)
+    }
+}
@@ -12,22 +12,25 @@ use rustc_hash::FxHashMap;
 use std::collections::VecDeque;
 use uid_gen::UIDGenerator;
 
-use crate::Verbosity;
+use crate::{CodegenProgress, Verbosity};
 
-use super::{ cast_ty_to_cranelift, Compiler, FunctionToCompile, MetaTyData};
+use super::{cast_ty_to_cranelift, Compiler, FunctionToCompile, MetaTyData, ReadOnlyData};
 
+/// Shared libraries don't need an entry point, in which case only the `@export` functions
+/// (and whatever they use) get compiled, and there's no C `main` to return
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn compile_program<'a>(
     verbosity: Verbosity,
-    entry_point: hir::Fqn,
+    entry_point: Option<hir::Fqn>,
     mod_dir: &'a std::path::Path,
     interner: &'a Interner,
     world_bodies: &'a hir::WorldBodies,
     tys: &'a hir_ty::ProjectInference,
     module: &'a mut dyn Module,
     comptime_results: &'a FxHashMap<FQComptime, ComptimeResult>,
-) -> FuncId {
-    let entry_point_ftc = {
+    progress: Option<&'a mut dyn FnMut(CodegenProgress)>,
+) -> Option<FuncId> {
+    let entry_point_ftc = entry_point.map(|entry_point| {
         let (param_tys, return_ty) = tys[entry_point]
             .0
             .as_function()
@@ -46,8 +49,9 @@ pub(crate) fn compile_program<'a>(
             lambda,
             param_tys: param_tys.clone(),
             return_ty,
+            instance: None,
         }
-    };
+    });
 
     let default_abi = module.target_config().into();
 
@@ -63,24 +67,32 @@ pub(crate) fn compile_program<'a>(
         ptr_ty: module.target_config().pointer_type(),
         module,
         data_desc: DataDescription::new(),
-        functions_to_compile: VecDeque::from([entry_point_ftc]),
+        functions_to_compile: VecDeque::from_iter(entry_point_ftc),
         meta_tys: MetaTyData::default(),
         cmd_args_slice: None,
         functions: FxHashMap::default(),
+        instances: FxHashMap::default(),
         compiler_defined_functions: FxHashMap::default(),
         data: FxHashMap::default(),
         str_id_gen: UIDGenerator::default(),
         i128_id_gen: UIDGenerator::default(),
+        read_only_data: ReadOnlyData::default(),
+        vtables: FxHashMap::default(),
         comptime_results,
         comptime_data: FxHashMap::default(),
         default_abi,
+        progress,
+        cancel_flag: None,
     };
 
     compiler.finalize_tys();
+    for fqn in world_bodies.find_exported_functions() {
+        compiler.get_func_id(fqn);
+    }
     compiler.compile_queued();
     compiler.compile_builtins();
 
-    generate_main_function(compiler, entry_point)
+    entry_point.map(|entry_point| generate_main_function(compiler, entry_point))
 }
 
 fn generate_main_function(mut compiler: Compiler, entry_point: hir::Fqn) -> FuncId {
@@ -1,6 +1,7 @@
 use cranelift::codegen::ir::Endianness;
 use cranelift_module::{DataDescription, DataId, Linkage, Module};
 use hir_ty::Ty;
+use internment::Intern;
 use uid_gen::UIDGenerator;
 
 use crate::{extend::ExtendWithNumBytes, layout, mangle};
@@ -221,7 +222,7 @@ fn compile_memory_layouts(compiler: &mut Compiler) {
         let data = match ty.as_ref() {
             Ty::Array { .. } => &mut array_mem_data,
             Ty::Distinct { .. } => &mut distinct_mem_data,
-            Ty::Struct { .. } => &mut struct_mem_data,
+            Ty::Struct { .. } | Ty::Tuple { .. } => &mut struct_mem_data,
             Ty::Enum { .. } => &mut enum_mem_data,
             Ty::Variant { .. } => &mut variant_mem_data,
             _ => continue,
@@ -368,18 +369,34 @@ fn compile_type_info(compiler: &mut Compiler) {
 
                 enum_info_data.finish_array_item();
             }
-            Ty::Struct { members, .. } => {
+            Ty::Struct { .. } | Ty::Tuple { .. } => {
                 let member_offsets = ty.struct_layout().unwrap();
                 let member_offsets = member_offsets.offsets();
 
+                // tuples show up as structs whose members are named after their positions
+                let members: Vec<(String, Intern<Ty>)> = match ty.as_ref() {
+                    Ty::Struct { members, .. } => members
+                        .iter()
+                        .map(|hir_ty::MemberTy { name, ty }| {
+                            (compiler.interner.lookup(name.0).to_string(), *ty)
+                        })
+                        .collect(),
+                    Ty::Tuple { items } => items
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, ty)| (idx.to_string(), *ty))
+                        .collect(),
+                    _ => unreachable!(),
+                };
+
                 let starting_offset = member_info_data.len();
                 // member_array_starting_offsets.push(member_array_data.len());
 
-                for (idx, hir_ty::MemberTy { name, ty }) in members.iter().enumerate() {
+                for (idx, (name, ty)) in members.iter().enumerate() {
                     // `name` field
 
                     // define the string bytes in the binary
-                    let mut name_str_bytes = compiler.interner.lookup(name.0).as_bytes().to_vec();
+                    let mut name_str_bytes = name.as_bytes().to_vec();
                     name_str_bytes.push(0); // null terminated strings
 
                     let name_str_id = declare(
@@ -146,11 +146,6 @@ fn calc_single(ty: Intern<Ty>, ptr_ty: types::Type) {
                 float: false,
                 signed,
             }),
-            0 => FinalTy::Number(NumberType {
-                ty: types::I32,
-                float: false,
-                signed: true,
-            }),
             8 => FinalTy::Number(NumberType {
                 ty: types::I8,
                 float: false,
@@ -182,17 +177,16 @@ fn calc_single(ty: Intern<Ty>, ptr_ty: types::Type) {
 
     let final_ty = match ty.as_ref() {
         _ if ty.is_zero_sized() => FinalTy::Void,
-        hir_ty::Ty::NotYetResolved | hir_ty::Ty::Unknown => FinalTy::Void,
+        hir_ty::Ty::NotYetResolved | hir_ty::Ty::Unknown | hir_ty::Ty::Error => FinalTy::Void,
+        // weak types follow the same defaulting rules that `hir_ty` uses for globals
+        hir_ty::Ty::IInt(0) | hir_ty::Ty::UInt(0) | hir_ty::Ty::Float(0) => {
+            let default = ty.weak_default().unwrap();
+            calc_single(default, ptr_ty);
+            default.get_final_ty()
+        }
         hir_ty::Ty::IInt(bit_width) => finalize_int(*bit_width, true),
-        // {uint} => i32
-        hir_ty::Ty::UInt(0) => finalize_int(0, true),
         hir_ty::Ty::UInt(bit_width) => finalize_int(*bit_width, false),
         hir_ty::Ty::Float(bit_width) => match bit_width {
-            0 => FinalTy::Number(NumberType {
-                ty: types::F32,
-                float: true,
-                signed: true,
-            }),
             32 => FinalTy::Number(NumberType {
                 ty: types::F32,
                 float: true,
@@ -215,11 +209,11 @@ fn calc_single(ty: Intern<Ty>, ptr_ty: types::Type) {
             calc_single(*sub_ty, ptr_ty);
             FinalTy::Pointer(ptr_ty)
         }
-        hir_ty::Ty::Slice { sub_ty, .. } => {
+        hir_ty::Ty::Slice { sub_ty, .. } | hir_ty::Ty::Range { sub_ty, .. } => {
             calc_single(*sub_ty, ptr_ty);
             FinalTy::Pointer(ptr_ty)
         }
-        hir_ty::Ty::Pointer { sub_ty, .. } => {
+        hir_ty::Ty::Pointer { sub_ty, .. } | hir_ty::Ty::Optional { sub_ty } => {
             calc_single(*sub_ty, ptr_ty);
             FinalTy::Pointer(ptr_ty)
         }
@@ -237,12 +231,28 @@ fn calc_single(ty: Intern<Ty>, ptr_ty: types::Type) {
             calc_single(*return_ty, ptr_ty);
             FinalTy::Pointer(ptr_ty)
         }
+        hir_ty::Ty::Closure {
+            param_tys,
+            return_ty,
+        } => {
+            for param in param_tys {
+                calc_single(param.ty, ptr_ty);
+            }
+            calc_single(*return_ty, ptr_ty);
+            FinalTy::Pointer(ptr_ty)
+        }
         hir_ty::Ty::Struct { members, .. } => {
             for member in members {
                 calc_single(member.ty, ptr_ty);
             }
             FinalTy::Pointer(ptr_ty)
         }
+        hir_ty::Ty::Tuple { items } => {
+            for item in items {
+                calc_single(*item, ptr_ty);
+            }
+            FinalTy::Pointer(ptr_ty)
+        }
         hir_ty::Ty::Enum { variants, .. } => {
             for variant in variants {
                 calc_single(*variant, ptr_ty);
@@ -259,6 +269,12 @@ fn calc_single(ty: Intern<Ty>, ptr_ty: types::Type) {
             signed: false,
         }),
         hir_ty::Ty::Any => FinalTy::Pointer(ptr_ty),
+        hir_ty::Ty::Interface { methods, .. } => {
+            for method in methods {
+                calc_single(method.ty, ptr_ty);
+            }
+            FinalTy::Pointer(ptr_ty)
+        }
         hir_ty::Ty::RawPtr { .. } => FinalTy::Pointer(ptr_ty),
         hir_ty::Ty::RawSlice { .. } => FinalTy::Pointer(ptr_ty),
         hir_ty::Ty::Void => FinalTy::Void,
@@ -345,8 +361,14 @@ impl ToTyId for Intern<Ty> {
             .any(|ty| ty.is_equal_to(&self)));
 
         let id = match self.as_ref() {
-            Ty::NotYetResolved | Ty::Unknown => simple_id(VOID_DISCRIMINANT, 0, false),
-            Ty::UInt(0) | Ty::IInt(0) => simple_id(INT_DISCRIMINANT, 32, true),
+            Ty::NotYetResolved | Ty::Unknown | Ty::Error => simple_id(VOID_DISCRIMINANT, 0, false),
+            // weak types share the id of whatever they default to
+            Ty::UInt(0) | Ty::IInt(0) | Ty::Float(0) => {
+                return self
+                    .weak_default()
+                    .unwrap()
+                    .to_type_id(meta_tys, pointer_ty)
+            }
             Ty::IInt(bit_width) => simple_id(
                 INT_DISCRIMINANT,
                 match *bit_width {
@@ -363,7 +385,6 @@ impl ToTyId for Intern<Ty> {
                 },
                 false,
             ),
-            Ty::Float(0) => simple_id(FLOAT_DISCRIMINANT, 32, false),
             Ty::Float(bit_width) => simple_id(FLOAT_DISCRIMINANT, *bit_width as u32, false),
             Ty::Bool => simple_id(BOOL_DISCRIMINANT, 8, false),
             Ty::String => simple_id(STRING_DISCRIMINANT, pointer_ty.bits(), false),
@@ -394,6 +415,31 @@ impl ToTyId for Intern<Ty> {
 
                 id | list_id
             }
+            // ranges share the id of the two element array they're laid out like
+            Ty::Range { sub_ty, .. } => {
+                return Intern::new(Ty::Array {
+                    anonymous: false,
+                    size: 2,
+                    sub_ty: *sub_ty,
+                })
+                .to_type_id(meta_tys, pointer_ty)
+            }
+            // optionals share the id of the `(T, bool)` tuple they're laid out like
+            Ty::Optional { sub_ty } => {
+                return Intern::new(Ty::Tuple {
+                    items: vec![*sub_ty, Intern::new(Ty::Bool)],
+                })
+                .to_type_id(meta_tys, pointer_ty)
+            }
+            // interfaces and closures share the id of the `(rawptr, rawptr)` tuple they're laid
+            // out like
+            Ty::Interface { .. } | Ty::Closure { .. } => {
+                let rawptr = Intern::new(Ty::RawPtr { mutable: false });
+                return Intern::new(Ty::Tuple {
+                    items: vec![rawptr, rawptr],
+                })
+                .to_type_id(meta_tys, pointer_ty);
+            }
             Ty::Slice { sub_ty } => {
                 let id = SLICE_DISCRIMINANT << 26;
 
@@ -443,6 +489,18 @@ impl ToTyId for Intern<Ty> {
 
                 id | list_id
             }
+            // tuples are treated as structs with members named `0`, `1`, etc.
+            Ty::Tuple { items } => {
+                let id = STRUCT_DISCRIMINANT << 26;
+
+                for item in items {
+                    item.to_type_id(meta_tys, pointer_ty);
+                }
+
+                let list_id = meta_tys.struct_uid_gen.generate_unique_id();
+
+                id | list_id
+            }
             Ty::Enum { variants, .. } => {
                 let id = ENUM_DISCRIMINANT << 26;
 
@@ -476,10 +534,13 @@ impl ToTyId for Intern<Ty> {
 
     #[track_caller]
     fn to_previous_type_id(self, meta_tys: &MetaTyData) -> u32 {
+        // weak types never get their own id, see `to_type_id`
+        let ty = self.weak_default().unwrap_or(self);
+
         meta_tys
             .type_ids
             .iter()
-            .find(|(ty, _)| ty.is_equal_to(&self))
+            .find(|(other, _)| other.is_equal_to(&ty))
             .map(|(_, id)| *id)
             .expect("to_previous_type_id can only be called on types that have already been compiled to id's")
     }
@@ -2,8 +2,8 @@ use cranelift::{
     codegen::{
         entity::EntityRef,
         ir::{
-            AbiParam, ArgumentPurpose, Inst, InstBuilder, MemFlags, Signature, StackSlotData,
-            StackSlotKind, Type, Value,
+            AbiParam, ArgumentPurpose, Inst, InstBuilder, MemFlags, Signature, StackSlot,
+            StackSlotData, StackSlotKind, Type, Value,
         },
         isa::{CallConv, TargetFrontendConfig},
     },
@@ -15,7 +15,7 @@ use la_arena::Idx;
 use tinyvec::ArrayVec;
 
 use crate::{
-    compiler::{functions::FunctionCompiler, MemoryLoc},
+    compiler::{functions::FunctionCompiler, MemoryLoc, INLINE_MEMORY_OP_LIMIT},
     layout::GetLayoutInfo,
 };
 
@@ -111,6 +111,8 @@ pub struct FnAbi {
     args: Vec<(PassMode, u16)>,
     ret: Option<PassMode>,
     simple_ret: bool,
+    /// closures get a pointer to the values they captured after all of their other arguments
+    env: bool,
 }
 
 impl FnAbi {
@@ -119,9 +121,15 @@ impl FnAbi {
             args: vec![],
             ret: None,
             simple_ret: false,
+            env: false,
         }
     }
 
+    pub fn with_env(mut self) -> Self {
+        self.env = true;
+        self
+    }
+
     pub fn to_cl(&self, ptr_ty: Type, conv: CallConv) -> Signature {
         // TODO: actually use the correct calling convention here
         let mut sig = Signature::new(conv);
@@ -143,14 +151,24 @@ impl FnAbi {
             sig.params.append(&mut arg.to_abiparam(ptr_ty));
         }
 
+        if self.env {
+            sig.params.push(AbiParam::new(ptr_ty));
+        }
+
         sig
     }
 
-    pub fn get_arg_list(&self, args: Vec<Value>, func_cmplr: &mut FunctionCompiler) -> Vec<Value> {
+    /// `args` should have one entry for every parameter, even the zero sized ones
+    pub fn get_arg_list(
+        &self,
+        args: Vec<Option<Value>>,
+        param_tys: &[ParamTy],
+        func_cmplr: &mut FunctionCompiler,
+    ) -> Vec<Value> {
         let mut arg_list = vec![];
 
         for (pass, idx) in &self.args {
-            let arg = args[*idx as usize];
+            let arg = args[*idx as usize].expect("only zero sized args don't have values");
             let arg_type = func_cmplr.builder.func.dfg.value_type(arg);
             match pass {
                 PassMode::Cast { tys, .. } => {
@@ -183,6 +201,24 @@ impl FnAbi {
                         .stack_addr(func_cmplr.ptr_ty, slot, 0);
                     arg_list.push(arg);
                 }
+                // the callee is allowed to do whatever it wants with an aggregate passed by
+                // reference, so it gets its own copy.
+                // (aggregates passed by value are already copied by cranelift)
+                PassMode::Indirect(None) => {
+                    let size = param_tys[*idx as usize].ty.stride();
+                    let copy = copy_to_stack(
+                        &mut func_cmplr.builder,
+                        func_cmplr.module.target_config(),
+                        arg,
+                        size,
+                    );
+                    arg_list.push(
+                        func_cmplr
+                            .builder
+                            .ins()
+                            .stack_addr(func_cmplr.ptr_ty, copy, 0),
+                    );
+                }
                 _ => arg_list.push(arg),
             }
         }
@@ -200,7 +236,7 @@ impl FnAbi {
             let stack_slot = builder.create_sized_stack_slot(StackSlotData {
                 kind: StackSlotKind::ExplicitSlot,
                 size: return_ty.size(),
-                align_shift: return_ty.align() as u8,
+                align_shift: return_ty.align_shift(),
             });
             let stack_slot_addr = builder.ins().stack_addr(ptr_ty, stack_slot, 0);
 
@@ -253,7 +289,6 @@ impl FnAbi {
         &self,
         func_cmplr: &mut FunctionCompiler,
         return_ty: Intern<Ty>,
-        args: &[ParamTy],
         function_body: Idx<hir::Expr>,
     ) {
         // Create the entry block, to start emitting code in.
@@ -265,13 +300,13 @@ impl FnAbi {
 
         func_cmplr.builder.switch_to_block(entry_block);
         func_cmplr.builder.seal_block(entry_block);
-        let mut idx_off = 0;
+        // zero sized params aren't passed at all, so the block params have to be counted
+        // separately from the index of the param
+        let mut param = 0;
         if let Some(PassMode::Indirect(_)) = self.ret {
-            idx_off += 1
+            param += 1
         }
         for (arg, idx) in &self.args {
-            let param = *idx + idx_off;
-
             let var = Variable::new(func_cmplr.var_id_gen.generate_unique_id() as usize);
             func_cmplr.params.insert(*idx as u64, var);
             let (val, val_ty) = match arg {
@@ -284,16 +319,14 @@ impl FnAbi {
 
                     let mut off = 0;
                     for (idx, ty) in tys.into_iter().enumerate() {
-                        let val =
-                            func_cmplr.builder.block_params(entry_block)[idx + param as usize];
+                        let val = func_cmplr.builder.block_params(entry_block)[idx + param];
                         func_cmplr
                             .builder
                             .ins()
                             .stack_store(val, stack_slot, off as i32);
                         off += ty.bytes();
-                        idx_off += 1;
                     }
-                    idx_off -= 1;
+                    param += tys.len() - 1;
                     (
                         func_cmplr
                             .builder
@@ -302,61 +335,34 @@ impl FnAbi {
                         func_cmplr.ptr_ty,
                     )
                 }
-                PassMode::Direct(ty) => (
-                    func_cmplr.builder.block_params(entry_block)[param as usize],
-                    *ty,
+                PassMode::Direct(ty) => (func_cmplr.builder.block_params(entry_block)[param], *ty),
+                // the caller already made a copy of the aggregate just for us,
+                // so there's no need to copy it again
+                PassMode::Indirect(_) => (
+                    func_cmplr.builder.block_params(entry_block)[param],
+                    func_cmplr.ptr_ty,
                 ),
-                PassMode::Indirect(sz) => {
-                    let sz = if let Some(sz) = sz {
-                        *sz
-                    } else {
-                        args[*idx as usize].ty.stride() as usize
-                    };
-                    let stack_slot = func_cmplr.builder.create_sized_stack_slot(StackSlotData {
-                        kind: StackSlotKind::ExplicitSlot,
-                        size: sz as u32,
-                        align_shift: 3,
-                    });
-                    let ptr = func_cmplr.builder.block_params(entry_block)[param as usize];
-
-                    let mut off = 0;
-                    macro_rules! mem_cpy_loop {
-                        ($width:expr) => {
-                            while (off + $width) <= (sz as i32 / $width) * $width {
-                                let bytes = func_cmplr.builder.ins().load(
-                                    Type::int_with_byte_size($width).unwrap(),
-                                    MemFlags::trusted(),
-                                    ptr,
-                                    off,
-                                );
-                                func_cmplr.builder.ins().stack_store(bytes, stack_slot, off);
-                                off += $width;
-                            }
-                        };
-                    }
-
-                    // be very explicit to cranelift what we are doing here
-                    // since there is no `emit_stack_memcpy`, do it ourselves
-
-                    mem_cpy_loop!(8);
-                    mem_cpy_loop!(4);
-                    mem_cpy_loop!(2);
-                    mem_cpy_loop!(1);
-
-                    (
-                        func_cmplr
-                            .builder
-                            .ins()
-                            .stack_addr(func_cmplr.ptr_ty, stack_slot, 0),
-                        func_cmplr.ptr_ty,
-                    )
-                }
             };
             func_cmplr.builder.declare_var(var, val_ty);
 
             func_cmplr.builder.def_var(var, val);
+
+            param += 1;
+        }
+
+        if self.env {
+            let env = func_cmplr.builder.block_params(entry_block)[param];
+            func_cmplr.unpack_env(env);
         }
 
+        // `#tailcall`s redefine the parameters and jump back to here.
+        // this block gets sealed along with everything else at the very end
+        let body_block = func_cmplr.builder.create_block();
+        func_cmplr.builder.ins().jump(body_block, &[]);
+        func_cmplr.builder.switch_to_block(body_block);
+        func_cmplr.tail_call_block = Some(body_block);
+        func_cmplr.check_cancelled();
+
         if let Some(ret) = self.ret {
             match ret {
                 PassMode::Cast { tys, orig } => {
@@ -397,3 +403,60 @@ impl FnAbi {
         func_cmplr.builder.seal_all_blocks();
     }
 }
+
+/// Copies `size` bytes from `ptr` into a brand new stack slot
+fn copy_to_stack(
+    builder: &mut FunctionBuilder,
+    config: TargetFrontendConfig,
+    ptr: Value,
+    size: u32,
+) -> StackSlot {
+    let stack_slot = builder.create_sized_stack_slot(StackSlotData {
+        kind: StackSlotKind::ExplicitSlot,
+        size,
+        align_shift: 3,
+    });
+
+    if size > INLINE_MEMORY_OP_LIMIT {
+        let dest = builder
+            .ins()
+            .stack_addr(config.pointer_type(), stack_slot, 0);
+        builder.emit_small_memory_copy(
+            config,
+            dest,
+            ptr,
+            size as u64,
+            8,
+            8,
+            true,
+            MemFlags::trusted(),
+        );
+        return stack_slot;
+    }
+
+    let mut off = 0;
+    macro_rules! mem_cpy_loop {
+        ($width:expr) => {
+            while (off + $width) <= (size as i32 / $width) * $width {
+                let bytes = builder.ins().load(
+                    Type::int_with_byte_size($width).unwrap(),
+                    MemFlags::trusted(),
+                    ptr,
+                    off,
+                );
+                builder.ins().stack_store(bytes, stack_slot, off);
+                off += $width;
+            }
+        };
+    }
+
+    // be very explicit to cranelift what we are doing here
+    // since there is no `emit_stack_memcpy`, do it ourselves
+
+    mem_cpy_loop!(8);
+    mem_cpy_loop!(4);
+    mem_cpy_loop!(2);
+    mem_cpy_loop!(1);
+
+    stack_slot
+}
@@ -83,7 +83,15 @@ fn classify_arg(ty: Intern<Ty>) -> Option<[Class; 8]> {
                     }
                 }
             }
-            Ty::Slice { .. } | Ty::RawSlice { .. } | Ty::Any => {
+            Ty::Range { sub_ty, .. } => {
+                classify_eight_byte(sub_ty, classes, offset);
+                classify_eight_byte(sub_ty, classes, offset + sub_ty.stride() as usize);
+            }
+            Ty::Slice { .. }
+            | Ty::RawSlice { .. }
+            | Ty::Any
+            | Ty::Interface { .. }
+            | Ty::Closure { .. } => {
                 classes[offset / 8] = classes[offset / 8].merge_eigthbyte(Int);
                 classes[offset / 8 + 1] = classes[offset / 8 + 1].merge_eigthbyte(Int)
             }
@@ -95,6 +103,17 @@ fn classify_arg(ty: Intern<Ty>) -> Option<[Class; 8]> {
                     classify_eight_byte(members[field].ty, classes, offset + field_off as usize)
                 }
             }
+            Ty::Tuple { items } => {
+                for (item, &item_off) in ty.struct_layout().unwrap().offsets().iter().enumerate() {
+                    classify_eight_byte(items[item], classes, offset + item_off as usize)
+                }
+            }
+            Ty::Optional { sub_ty } => {
+                let items = [sub_ty, Intern::new(Ty::Bool)];
+                for (item, &item_off) in ty.struct_layout().unwrap().offsets().iter().enumerate() {
+                    classify_eight_byte(items[item], classes, offset + item_off as usize)
+                }
+            }
             // todo: what to do for enums?
             Ty::Enum { variants, .. } => {
                 let enum_layout = ty.enum_layout().unwrap();
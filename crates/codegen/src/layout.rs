@@ -119,11 +119,15 @@ fn calc_single(ty: Intern<Ty>, pointer_bit_width: u32) {
     }
 
     let size = match ty.as_ref() {
-        Ty::NotYetResolved | Ty::Unknown => 0,
+        Ty::NotYetResolved | Ty::Unknown | Ty::Error => 0,
         Ty::IInt(u8::MAX) | Ty::UInt(u8::MAX) => pointer_bit_width / 8,
-        Ty::IInt(0) | Ty::UInt(0) => 32 / 8,
+        // weak types follow the same defaulting rules that `hir_ty` uses for globals
+        Ty::IInt(0) | Ty::UInt(0) | Ty::Float(0) => {
+            let default = ty.weak_default().unwrap();
+            calc_single(default, pointer_bit_width);
+            default.size()
+        }
         Ty::IInt(bit_width) | Ty::UInt(bit_width) => *bit_width as u32 / 8,
-        Ty::Float(0) => 32 / 8,
         Ty::Float(bit_width) => *bit_width as u32 / 8,
         Ty::Bool | Ty::Char => 1, // bools and chars are u8's
         Ty::String => pointer_bit_width / 8,
@@ -131,6 +135,11 @@ fn calc_single(ty: Intern<Ty>, pointer_bit_width: u32) {
             calc_single(*sub_ty, pointer_bit_width);
             sub_ty.stride() * *size as u32
         }
+        Ty::Range { sub_ty, .. } => {
+            // a range is start + end
+            calc_single(*sub_ty, pointer_bit_width);
+            sub_ty.stride() * 2
+        }
         Ty::Slice { .. } => {
             // a slice is len (usize) + ptr (usize)
             pointer_bit_width / 8 * 2
@@ -141,8 +150,15 @@ fn calc_single(ty: Intern<Ty>, pointer_bit_width: u32) {
             sub_ty.size()
         }
         Ty::Function { .. } => pointer_bit_width / 8,
-        Ty::Struct { members, .. } => {
-            let members = members.iter().map(|member| member.ty).collect::<Vec<_>>();
+        Ty::Struct { .. } | Ty::Tuple { .. } | Ty::Optional { .. } => {
+            // tuples are laid out exactly like structs,
+            // and optionals are laid out exactly like the tuple `(T, bool)`
+            let members = match ty.as_ref() {
+                Ty::Struct { members, .. } => members.iter().map(|member| member.ty).collect(),
+                Ty::Tuple { items } => items.clone(),
+                Ty::Optional { sub_ty } => vec![*sub_ty, Intern::new(Ty::Bool)],
+                _ => unreachable!(),
+            };
             for member_ty in &members {
                 calc_single(*member_ty, pointer_bit_width);
             }
@@ -219,21 +235,43 @@ fn calc_single(ty: Intern<Ty>, pointer_bit_width: u32) {
         Ty::RawPtr { .. } => pointer_bit_width / 8,
         // a slice is len (usize) + ptr (usize)
         Ty::RawSlice => pointer_bit_width / 8 * 2,
+        Ty::Interface { methods, .. } => {
+            for method in methods {
+                calc_single(method.ty, pointer_bit_width);
+            }
+
+            // an interface is the pointer to the value + the pointer to the method table
+            pointer_bit_width / 8 * 2
+        }
+        Ty::Closure {
+            param_tys,
+            return_ty,
+        } => {
+            for param in param_tys {
+                calc_single(param.ty, pointer_bit_width);
+            }
+            calc_single(*return_ty, pointer_bit_width);
+
+            // a closure is the pointer to the function + the pointer to the captured values
+            pointer_bit_width / 8 * 2
+        }
         Ty::Void => 0,
         Ty::NoEval => 0,
         Ty::File(_) => 0,
     };
 
     let align = match ty.as_ref() {
-        Ty::NotYetResolved | Ty::Unknown => 1,
+        Ty::NotYetResolved | Ty::Unknown | Ty::Error => 1,
         Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_) => size.min(8),
         Ty::Bool | Ty::Char => 1, // bools and chars are u8's
         Ty::String | Ty::Pointer { .. } | Ty::Function { .. } => size.min(8),
         // the sub_ty was already `calc()`ed just before
-        Ty::Array { sub_ty, .. } => sub_ty.align(),
+        Ty::Array { sub_ty, .. } | Ty::Range { sub_ty, .. } => sub_ty.align(),
         Ty::Slice { .. } => (size / 2).min(8),
         Ty::Distinct { sub_ty, .. } => sub_ty.align(),
-        Ty::Struct { .. } => ty.struct_layout().unwrap().align,
+        Ty::Struct { .. } | Ty::Tuple { .. } | Ty::Optional { .. } => {
+            ty.struct_layout().unwrap().align
+        }
         Ty::Enum { .. } => ty.enum_layout().unwrap().align,
         Ty::Variant { sub_ty, .. } => sub_ty.align(),
         Ty::Type => size,
@@ -247,7 +285,7 @@ fn calc_single(ty: Intern<Ty>, pointer_bit_width: u32) {
             typeid_align.max(rawptr_align)
         }
         Ty::RawPtr { .. } => size.min(8),
-        Ty::RawSlice => (size / 2).min(8),
+        Ty::RawSlice | Ty::Interface { .. } | Ty::Closure { .. } => (size / 2).min(8),
         Ty::Void => 1,
         Ty::NoEval => 1,
         Ty::File(_) => 1,
@@ -302,6 +340,12 @@ impl StructLayout {
         let mut current_offset = 0;
 
         for field in fields {
+            if field.is_zero_sized() {
+                // zero sized fields take up no space, so they shouldn't add any padding
+                offsets.push(current_offset);
+                continue;
+            }
+
             let field_align = field.align();
             if field_align > max_align {
                 max_align = field_align;
@@ -325,3 +369,111 @@ impl StructLayout {
         &self.offsets
     }
 }
+
+/// How a type is laid out in memory, for checking it against the C struct it's meant to match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeLayout {
+    pub size: u32,
+    pub align: u32,
+    /// the size rounded up to the alignment, which is what C's `sizeof` gives
+    pub stride: u32,
+    /// the members of a struct or tuple, in the order they're declared
+    pub fields: Vec<FieldLayout>,
+    /// the offset of the discriminant byte if this is an enum
+    pub discriminant_offset: Option<u32>,
+    pub variants: Vec<VariantLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldLayout {
+    /// `None` for the items of a tuple
+    pub name: Option<hir::Name>,
+    pub ty: Intern<Ty>,
+    pub offset: u32,
+    pub size: u32,
+    /// the bytes between the end of this field and the start of the next one,
+    /// or the end of the struct's stride for the last field
+    pub padding: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantLayout {
+    pub name: hir::Name,
+    /// the type of the variant's payload
+    pub ty: Intern<Ty>,
+    pub discriminant: u64,
+}
+
+/// Calculates the layout of a single type for the given pointer width
+pub fn layout_of(ty: Intern<Ty>, pointer_bit_width: u32) -> TypeLayout {
+    calc_layouts(std::iter::once(ty), pointer_bit_width);
+
+    let stride = ty.stride();
+
+    let mut fields: Vec<FieldLayout> = match ty.absolute_ty() {
+        Ty::Struct { members, .. } => members
+            .iter()
+            .zip(ty.struct_layout().unwrap().offsets())
+            .map(|(member, offset)| FieldLayout {
+                name: Some(member.name),
+                ty: member.ty,
+                offset: *offset,
+                size: member.ty.size(),
+                padding: 0,
+            })
+            .collect(),
+        Ty::Tuple { items } => items
+            .iter()
+            .zip(ty.struct_layout().unwrap().offsets())
+            .map(|(item, offset)| FieldLayout {
+                name: None,
+                ty: *item,
+                offset: *offset,
+                size: item.size(),
+                padding: 0,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut next_offset = stride;
+    for field in fields.iter_mut().rev() {
+        if field.size == 0 {
+            continue;
+        }
+        field.padding = next_offset - (field.offset + field.size);
+        next_offset = field.offset;
+    }
+
+    let (discriminant_offset, variants) = match ty.absolute_ty() {
+        Ty::Enum { variants, .. } => (
+            Some(ty.enum_layout().unwrap().discriminant_offset()),
+            variants
+                .iter()
+                .map(|variant| match variant.as_ref() {
+                    Ty::Variant {
+                        variant_name,
+                        sub_ty,
+                        discriminant,
+                        ..
+                    } => VariantLayout {
+                        name: *variant_name,
+                        ty: *sub_ty,
+                        discriminant: *discriminant,
+                    },
+                    _ => unreachable!("enums only contain variants"),
+                })
+                .collect(),
+        ),
+        _ => (None, Vec::new()),
+    };
+
+    TypeLayout {
+        size: ty.size(),
+        align: ty.align(),
+        stride,
+        fields,
+        discriminant_offset,
+        variants,
+    }
+}
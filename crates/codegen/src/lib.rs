@@ -47,10 +47,20 @@ impl Verbosity {
     }
 }
 
+/// How many of the functions reachable from the entry point have been compiled so far.
+/// `total` grows as newly compiled functions reference ones which haven't been seen yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodegenProgress {
+    pub compiled: usize,
+    pub total: usize,
+}
+
 pub(crate) type FinalSignature = cranelift::prelude::Signature;
 
-pub use compiler::comptime::eval_comptime_blocks;
+pub use compiler::comptime::{eval_comptime_blocks, ComptimeLimits};
+pub use layout::{layout_of, FieldLayout, TypeLayout, VariantLayout};
 
+#[allow(clippy::too_many_arguments)]
 pub fn compile_jit(
     verbosity: Verbosity,
     entry_point: hir::Fqn,
@@ -59,6 +69,7 @@ pub fn compile_jit(
     world_bodies: &hir::WorldBodies,
     tys: &hir_ty::ProjectInference,
     comptime_results: &FxHashMap<FQComptime, ComptimeResult>,
+    progress: Option<&mut dyn FnMut(CodegenProgress)>,
 ) -> fn(usize, *const *const c_char) -> usize {
     let mut flag_builder = settings::builder();
     flag_builder.set("use_colocated_libcalls", "false").unwrap();
@@ -75,14 +86,16 @@ pub fn compile_jit(
 
     let cmain = compile_program(
         verbosity,
-        entry_point,
+        Some(entry_point),
         mod_dir,
         interner,
         world_bodies,
         tys,
         &mut module,
         comptime_results,
-    );
+        progress.map(|progress| progress as &mut dyn FnMut(CodegenProgress)),
+    )
+    .unwrap();
 
     // Finalize the functions which were defined, which resolves any
     // outstanding relocations (patching in addresses, now that they're
@@ -96,19 +109,28 @@ pub fn compile_jit(
 }
 
 #[allow(clippy::too_many_arguments)]
+/// `entry_point` can only be left out when building a shared library (with `pic`)
 pub fn compile_obj(
     verbosity: Verbosity,
-    entry_point: hir::Fqn,
+    main_file: hir::FileName,
+    entry_point: Option<hir::Fqn>,
     mod_dir: &std::path::Path,
     interner: &Interner,
     world_bodies: &hir::WorldBodies,
     tys: &hir_ty::ProjectInference,
     comptime_results: &FxHashMap<FQComptime, ComptimeResult>,
     target: Triple,
+    pic: bool,
+    reproducible: bool,
+    progress: Option<&mut dyn FnMut(CodegenProgress)>,
 ) -> Result<Vec<u8>, write::Error> {
     let mut flag_builder = settings::builder();
     flag_builder.set("use_colocated_libcalls", "false").unwrap();
-    flag_builder.set("is_pic", "false").unwrap();
+    // position-independent code accesses imported functions and globals through the GOT,
+    // which is required for objects that get linked into a shared library
+    flag_builder
+        .set("is_pic", if pic { "true" } else { "false" })
+        .unwrap();
 
     let isa_builder = isa::lookup(target).unwrap_or_else(|msg| {
         println!("invalid target: {}", msg);
@@ -118,12 +140,26 @@ pub fn compile_obj(
         .finish(settings::Flags::new(flag_builder.clone()))
         .unwrap();
 
-    let builder = ObjectBuilder::new(
-        isa,
-        entry_point.file.to_string(mod_dir, interner),
-        cranelift_module::default_libcall_names(),
-    )
-    .unwrap();
+    // the object name is otherwise relative to the current directory,
+    // which shouldn't change the output of a reproducible build.
+    //
+    // everything else is already deterministic. functions and data are emitted in the order
+    // they're queued up, and the hash maps in codegen (some of which are keyed by the address of
+    // an interned type) are only ever used for lookups, never iterated over while emitting.
+    // the one exception is a pointer returned from a comptime block, which is the address it had
+    // in the comptime JIT
+    let object_name = if reproducible {
+        Path::new(interner.lookup(main_file.0))
+            .file_stem()
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    } else {
+        main_file.to_string(mod_dir, interner)
+    };
+
+    let builder =
+        ObjectBuilder::new(isa, object_name, cranelift_module::default_libcall_names()).unwrap();
     let mut module = ObjectModule::new(builder);
 
     compile_program(
@@ -135,6 +171,7 @@ pub fn compile_obj(
         tys,
         &mut module,
         comptime_results,
+        progress.map(|progress| progress as &mut dyn FnMut(CodegenProgress)),
     );
 
     // Finalize the functions which were defined, which resolves any
@@ -170,12 +207,45 @@ pub fn link_to_exec(
 
     let exe_path = object_file.parent().unwrap().join(file_name);
 
+    link(object_file, target, libs, &exe_path, false)?;
+
+    Ok(exe_path)
+}
+
+/// Links the object file into a shared library that can be loaded with `dlopen`.
+///
+/// The object file should have been compiled with `pic` set to true.
+pub fn link_to_shared_lib(
+    object_file: &PathBuf,
+    target: Triple,
+    libs: &[String],
+) -> Result<PathBuf, LinkingErr> {
+    let mut file_name = object_file.file_stem().unwrap().to_os_string();
+
+    match target.operating_system {
+        OperatingSystem::Windows => file_name.push(".dll"),
+        OperatingSystem::Darwin(_) => file_name.push(".dylib"),
+        _ => file_name.push(".so"),
+    }
+
+    let lib_path = object_file.parent().unwrap().join(file_name);
+
+    link(object_file, target, libs, &lib_path, true)?;
+
+    Ok(lib_path)
+}
+
+fn link(
+    object_file: &PathBuf,
+    target: Triple,
+    libs: &[String],
+    output_path: &Path,
+    shared: bool,
+) -> Result<(), LinkingErr> {
     if which::which("zig").is_ok() {
-        link_with_zig(object_file, libs, &exe_path)?;
-        Ok(exe_path)
+        link_with_zig(object_file, libs, output_path, shared)
     } else if which::which("gcc").is_ok() {
-        link_with_gcc(object_file, target, libs, &exe_path)?;
-        Ok(exe_path)
+        link_with_gcc(object_file, target, libs, output_path, shared)
     } else {
         Err(LinkingErr::NoCommand)
     }
@@ -184,21 +254,29 @@ pub fn link_to_exec(
 fn link_with_zig(
     object_file: &PathBuf,
     libs: &[String],
-    exe_path: &Path,
+    output_path: &Path,
+    shared: bool,
 ) -> Result<(), LinkingErr> {
-    let zig = Command::new("zig")
-        .arg("build-exe")
+    let mut zig = Command::new("zig");
+
+    if shared {
+        zig.arg("build-lib").arg("-dynamic");
+    } else {
+        zig.arg("build-exe");
+    }
+
+    let zig = zig
         .arg(object_file)
         .args(libs)
         .arg("--library")
         .arg("C")
-        .arg(format!("-femit-bin={}", exe_path.display()))
+        .arg(format!("-femit-bin={}", output_path.display()))
         .output()
         .map_err(LinkingErr::IO)?;
 
     if !zig.status.success() {
         return Err(LinkingErr::CmdFailed {
-            cmd_name: "gcc",
+            cmd_name: "zig",
             output: zig,
         });
     }
@@ -210,29 +288,41 @@ fn link_with_gcc(
     object_file: &PathBuf,
     target: Triple,
     libs: &[String],
-    exe_path: &Path,
+    output_path: &Path,
+    shared: bool,
 ) -> Result<(), LinkingErr> {
-    let linker_args: &[&str] = match target.operating_system {
+    let mut linker_args: Vec<&str> = match target.operating_system {
         OperatingSystem::Darwin(_) => {
             // check if -ld_classic is supported
             let ld_v = Command::new("ld").arg("-v").output().unwrap();
             let stderr = String::from_utf8(ld_v.stderr).expect("`ld` should have given utf8");
 
             if stderr.contains("ld-classic") {
-                &["-Xlinker", "-ld_classic"]
+                vec!["-Xlinker", "-ld_classic"]
             } else {
-                &[]
+                vec![]
             }
         }
-        _ => &[],
+        _ => vec![],
     };
 
+    if shared {
+        match target.operating_system {
+            OperatingSystem::Darwin(_) => linker_args.push("-dynamiclib"),
+            // capy references its own exported symbols directly, so they have to be bound
+            // within the library instead of being interposable
+            _ => linker_args.extend(["-shared", "-Wl,-Bsymbolic"]),
+        }
+    }
+
     let gcc = Command::new("gcc")
         .arg("-o")
-        .arg(exe_path)
+        .arg(output_path)
         .args(linker_args)
         .args(libs.iter().map(|lib| "-l".to_string() + lib))
         .arg(object_file)
+        // cranelift calls into libm for math intrinsics the target has no instruction for
+        .arg("-lm")
         .output()
         .map_err(LinkingErr::IO)?;
 
@@ -256,7 +346,6 @@ mod tests {
     use hir_ty::{InferenceCtx, InferenceResult};
     use path_clean::PathClean;
     use target_lexicon::HOST;
-    use uid_gen::UIDGenerator;
 
     use super::*;
 
@@ -421,7 +510,6 @@ mod tests {
         let mut interner = Interner::default();
         let mut world_index = hir::WorldIndex::default();
 
-        let mut uid_gen = UIDGenerator::default();
         let mut world_bodies = hir::WorldBodies::default();
 
         for (file, text) in &modules {
@@ -446,7 +534,6 @@ mod tests {
                 &tree,
                 std::path::Path::new(*file),
                 &index,
-                &mut uid_gen,
                 &mut interner,
                 &mod_dir,
                 fake_file_system,
@@ -475,7 +562,6 @@ mod tests {
             &tree,
             std::path::Path::new(main_file),
             &index,
-            &mut uid_gen,
             &mut interner,
             &mod_dir,
             fake_file_system,
@@ -505,9 +591,9 @@ mod tests {
                 &world_bodies,
                 tys,
                 HOST.pointer_width().unwrap().bits(),
-            );
-
-            comptime_results[&comptime].clone()
+                ComptimeLimits::default(),
+            )
+            .map(|()| comptime_results[&comptime].clone())
         })
         .finish(Some(entry_point), false);
         assert_eq!(diagnostics, vec![]);
@@ -526,7 +612,9 @@ mod tests {
             &world_bodies,
             &tys,
             HOST.pointer_width().unwrap().bits(),
-        );
+            ComptimeLimits::default(),
+        )
+        .unwrap();
 
         println!("actual program:");
 
@@ -534,7 +622,8 @@ mod tests {
             Verbosity::AllFunctions {
                 include_disasm: true,
             },
-            entry_point,
+            entry_point.file,
+            Some(entry_point),
             if fake_file_system {
                 Path::new("")
             } else {
@@ -545,6 +634,9 @@ mod tests {
             &tys,
             &comptime_results,
             HOST,
+            false,
+            false,
+            None,
         )
         .unwrap();
 
@@ -848,16 +940,16 @@ mod tests {
             &[],
             "main",
             expect![[r#"
-            3.141
+            3.142
 
-            ln 10 = 2.302
+            ln 10 = 2.303
             ln 50 = 3.912
             ln 100 = 4.605
-            ln 500 = 6.214
+            ln 500 = 6.215
             log 10 = 1.000
-            log 50 = 1.698
-            log 100 = 1.999
-            log 500 = 2.698
+            log 50 = 1.699
+            log 100 = 2.000
+            log 500 = 2.699
              1 / 0 = +Inf
             -1 / 0 = -Inf
              0 / 0 = NaN
@@ -926,7 +1018,20 @@ mod tests {
         )
     }
 
-    // comptime_types.capy cannot be tested as it gets user input
+    #[test]
+    fn comptime_types() {
+        check_files(
+            "../../examples/comptime_types.capy",
+            &[],
+            "main",
+            expect![[r#"
+                size: 16
+                { a = 42, b = [ 4, 8, 15 ] }
+
+            "#]],
+            0,
+        )
+    }
 
     #[test]
     fn strings() {
@@ -1380,6 +1485,162 @@ mod tests {
         )
     }
 
+    #[test]
+    fn cast_f32_to_i8_saturates() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    big : f32 = 1000.5;
+                    small : f32 = -1000.5;
+
+                    i32.(i8.(big)) + i32.(i8.(small))
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            255,
+        )
+    }
+
+    #[test]
+    fn cast_f64_to_distinct_int() {
+        check_raw(
+            r#"
+                Small :: distinct u8;
+
+                main :: () -> i32 {
+                    f : f64 = 300.75;
+                    s := Small.(f);
+
+                    i32.(s)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            255,
+        )
+    }
+
+    #[test]
+    fn cast_bool_to_int() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    yes := true;
+                    no := false;
+
+                    i32.(yes) * 10 + i32.(no) + i32.(f32.(yes) * 2.0)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            12,
+        )
+    }
+
+    #[test]
+    fn cast_int_to_bool() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    // 256 has no bits in the lowest byte,
+                    // but `as bool` is `!= 0`, not a truncation
+                    x : i32 = 256;
+                    f : f32 = 0.25;
+                    zero : u64 = 0;
+
+                    i32.(bool.(x)) * 100 + i32.(bool.(f)) * 10 + i32.(bool.(zero))
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            110,
+        )
+    }
+
+    #[test]
+    fn cast_sign_extension() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    x : i8 = -1;
+                    // the sign of the source decides the extension
+                    y := u32.(x);
+                    // truncation keeps the lower bits
+                    z := u8.(y);
+
+                    if y == 4294967295 {
+                        i32.(z) - 200
+                    } else {
+                        0
+                    }
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            55,
+        )
+    }
+
+    #[test]
+    fn cast_i64_to_f32() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    // 2^40, which doesn't fit in an i32
+                    x : i64 = 1099511627776;
+                    f := f32.(x);
+
+                    i32.(f / 1099511627776.0)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            1,
+        )
+    }
+
+    #[test]
+    fn const_casts() {
+        check_raw(
+            r#"
+                N :: usize.(4);
+                SMALL :: i8.(300.5);
+                NEGATIVE :: i64.(i8.(u8.(255)));
+
+                main :: () -> i32 {
+                    arr : [N]i32 = .[1, 2, 3, 4];
+
+                    i32.(SMALL) - 100 + arr[3] + i32.(NEGATIVE)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            30,
+        )
+    }
+
     #[test]
     fn local_tys() {
         check_raw(
@@ -1905,6 +2166,39 @@ mod tests {
         )
     }
 
+    #[test]
+    fn defers_continue_outer_loop() {
+        check_raw(
+            r#"
+                main :: () {
+                    i := 0;
+                    `outer: while i < 3 {
+                        defer printf("|");
+                        i = i + 1;
+
+                        j := 0;
+                        loop {
+                            defer printf(".");
+                            j = j + 1;
+                            if j == 2 {
+                                continue `outer;
+                            }
+                            printf("x");
+                        }
+                    }
+                }
+
+                printf :: (text: str) extern;
+            "#,
+            "main",
+            false,
+            expect![[r#"
+                x..|x..|x..|
+            "#]],
+            0,
+        )
+    }
+
     #[test]
     fn extern_fn_global() {
         check_raw(
@@ -1979,76 +2273,455 @@ mod tests {
     }
 
     #[test]
-    fn globals_in_globals() {
+    fn comptime_strings_and_bytes() {
         check_raw(
             r#"
-                foo :: 123;
-                bar :: foo;
-                baz :: bar;
+                GREETING :: comptime {
+                    "hello from comptime"
+                };
+
+                BYTES :: comptime {
+                    bytes : [3]u8 = .[1, 2, 3];
+                    bytes
+                };
 
                 main :: () -> i32 {
-                    baz
+                    puts(GREETING);
+
+                    i32.(BYTES[0] + BYTES[1] + BYTES[2])
                 }
+
+                puts :: (text: str) extern;
             "#,
             "main",
             false,
             expect![[r#"
+                hello from comptime
 
-"#]],
-            123,
+            "#]],
+            6,
         )
     }
 
     #[test]
-    fn global_referring_to_function() {
+    fn comptime_aggregates() {
         check_raw(
             r#"
-                add :: (a: i32, b: i32) -> i32 {
-                    a + b
-                }
+                Animal :: enum {
+                    Dog: str,
+                    Fish: i32,
+                };
 
-                foo :: add;
+                Config :: struct {
+                    name: str,
+                    ports: [3]i32,
+                    retries: usize,
+                    pet: Animal,
+                };
+
+                CONFIG :: comptime {
+                    name := "server";
+                    Config.{
+                        name = name,
+                        ports = .[10, 20, 30],
+                        retries = 2,
+                        pet = Animal.Dog.("George"),
+                    }
+                };
+
+                PAIR :: comptime {
+                    (Animal.(Animal.Fish.(7)), "fish")
+                };
 
                 main :: () -> i32 {
-                    add(1, 2)
+                    puts(CONFIG.name);
+
+                    switch pet in CONFIG.pet {
+                        Dog => puts(str.(pet)),
+                        Fish => puts("fish"),
+                    }
+
+                    puts(PAIR.1);
+
+                    attempts : [CONFIG.retries]str = .["first", "second"];
+                    puts(attempts[1]);
+
+                    switch fish in PAIR.0 {
+                        Dog => 0,
+                        Fish => CONFIG.ports[0] + CONFIG.ports[1] + CONFIG.ports[2] + i32.(fish),
+                    }
                 }
+
+                puts :: (text: str) extern;
             "#,
             "main",
             false,
             expect![[r#"
+                server
+                George
+                fish
+                second
 
-"#]],
-            3,
+            "#]],
+            67,
         )
     }
 
     #[test]
-    fn reorder_struct_literal_fields() {
-        // there was a bug where simply changing the order of the fields
-        // in the struct literal would also change the order of the struct.
-        // e.g.
-        // Under the bug, the following code would print this:
-        // Foo {
-        //   a = 4000000,
-        //   b = 42,
-        // }
-        // Foo {
-        //   a = 42,
-        //   b = 4000000,
-        // }
-        // even though the following code always assigns 42 to b
+    fn identical_global_constants() {
         check_raw(
             r#"
-                Foo :: struct {
-                    a: i64,
-                    b: i64,
-                };
+                FIRST :: i32.[1, 2, 3, 4];
+                SECOND :: i32.[1, 2, 3, 4];
+                THIRD :: i32.[4, 3, 2, 1];
 
-                main :: () {
-                    print_foo(Foo.{
-                        a = 4_000_000,
-                        b = 42,
-                    });
+                main :: () -> i32 {
+                    puts("shared");
+                    puts("shared");
+
+                    FIRST[0] * 100 + SECOND[1] * 10 + THIRD[0]
+                }
+
+                puts :: (text: str) extern;
+            "#,
+            "main",
+            false,
+            expect![[r#"
+                shared
+                shared
+
+            "#]],
+            124,
+        )
+    }
+
+    #[test]
+    fn globals_in_globals() {
+        check_raw(
+            r#"
+                foo :: 123;
+                bar :: foo;
+                baz :: bar;
+
+                main :: () -> i32 {
+                    baz
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            123,
+        )
+    }
+
+    #[test]
+    fn global_referring_to_function() {
+        check_raw(
+            r#"
+                add :: (a: i32, b: i32) -> i32 {
+                    a + b
+                }
+
+                foo :: add;
+
+                main :: () -> i32 {
+                    add(1, 2)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            3,
+        )
+    }
+
+    #[test]
+    fn zero_sized_types() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Padded :: struct {
+                    a: u8,
+                    nothing: [0]u64,
+                    b: u8,
+                };
+
+                Empty :: struct {};
+
+                Holder :: struct {
+                    x: i32,
+                    empty: Empty,
+                    y: i32,
+                };
+
+                make_empty :: () -> Empty {
+                    core.println("making empty");
+                    Empty.{}
+                }
+
+                main :: () {
+                    core.println(core.meta.size_of(Padded));
+                    core.println(core.meta.size_of(Empty));
+                    core.println(core.meta.size_of(Holder));
+
+                    empty := make_empty();
+                    holder := Holder.{ x = 1, empty = make_empty(), y = 2 };
+                    holder.empty = make_empty();
+
+                    core.println(holder.x + holder.y);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            2
+            0
+            8
+            making empty
+            making empty
+            making empty
+            3
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn large_aggregate_params_and_returns() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Big :: struct {
+                    a: i64,
+                    b: i64,
+                    c: i64,
+                    d: i64,
+                };
+
+                Empty :: struct {};
+
+                make :: (n: i64) -> Big {
+                    Big.{ a = n, b = n * 2, c = n * 3, d = n * 4 }
+                }
+
+                sum :: (empty: Empty, big: Big, scale: i64) -> i64 {
+                    (big.a + big.b + big.c + big.d) * scale
+                }
+
+                main :: () {
+                    big := make(5);
+
+                    core.println(sum(Empty.{}, big, 1));
+                    core.println(sum(Empty.{}, make(1), 2));
+                    core.println(big.a, " ", big.d);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            50
+            20
+            5 20
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn struct_literals_built_in_place() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                Line :: struct {
+                    start: Point,
+                    end: Point,
+                };
+
+                main :: () {
+                    p : Point = .{ y = 2, x = 1 };
+                    core.println(p.x, " ", p.y);
+
+                    p = Point.{ x = p.y, y = p.x };
+                    core.println(p.x, " ", p.y);
+
+                    line := Line.{ start = .{ x = 5, y = 6 }, end = p };
+                    line.start = .{ x = line.end.x, y = line.start.x };
+                    core.println(line.start.x, " ", line.start.y);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            1 2
+            2 1
+            2 5
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn reuse_stack_slots_of_finished_blocks() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                main :: () {
+                    first := {
+                        a := Point.{ x = 1, y = 2 };
+                        a.x + a.y
+                    };
+                    second := {
+                        b := Point.{ x = 10, y = 20 };
+                        b.x * b.y
+                    };
+                    core.println(first, " ", second);
+
+                    kept := {
+                        if first > 0 {
+                            q := Point.{ x = 7, y = 8 };
+                            break q;
+                        }
+                        Point.{ x = 0, y = 0 }
+                    };
+                    {
+                        overwrite := Point.{ x = 100, y = 200 };
+                        core.println(overwrite.x + overwrite.y);
+                    }
+                    core.println(kept.x, " ", kept.y);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            3 200
+            300
+            7 8
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn keep_stack_slots_of_escaping_locals() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                main :: () {
+                    outer := Point.{ x = 0, y = 0 };
+                    ptr := ^outer;
+                    {
+                        inner := Point.{ x = 1, y = 2 };
+                        ptr = ^inner;
+                    }
+                    {
+                        other := Point.{ x = 30, y = 40 };
+                        core.println(other.x + other.y);
+                    }
+                    core.println(ptr.x, " ", ptr.y);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![[r#"
+                70
+                1 2
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn keep_stack_slots_of_sliced_locals() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                main :: () {
+                    s : []i32 = i32.[0, 0];
+                    {
+                        arr := i32.[1, 2, 3];
+                        s = arr[0..2];
+                    }
+                    {
+                        other := i32.[7, 8, 9];
+                        core.println(other[0]);
+                    }
+                    core.println(s[0], " ", s[1]);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![[r#"
+                7
+                1 2
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn reorder_struct_literal_fields() {
+        // there was a bug where simply changing the order of the fields
+        // in the struct literal would also change the order of the struct.
+        // e.g.
+        // Under the bug, the following code would print this:
+        // Foo {
+        //   a = 4000000,
+        //   b = 42,
+        // }
+        // Foo {
+        //   a = 42,
+        //   b = 4000000,
+        // }
+        // even though the following code always assigns 42 to b
+        check_raw(
+            r#"
+                Foo :: struct {
+                    a: i64,
+                    b: i64,
+                };
+
+                main :: () {
+                    print_foo(Foo.{
+                        a = 4_000_000,
+                        b = 42,
+                    });
 
                     print_foo(Foo.{
                         b = 42,
@@ -2678,24 +3351,41 @@ mod tests {
     }
 
     #[test]
-    fn autocast_array_to_slice() {
+    fn construct_variant_with_call() {
         check_raw_with_args(
             r#"
                 core :: #mod("core");
 
-                main :: () {
-                    foo := .[1, 2, 3];
+                Shape :: enum {
+                    Circle: f32,
+                    Rect: struct {
+                        w: i32,
+                        h: i32,
+                    },
+                    Nothing,
+                };
 
-                    bar : []u128 = foo;
+                describe :: (shape: Shape) {
+                    switch s in shape {
+                        Circle => core.println("circle: ", s),
+                        Rect => core.println("rect: ", s.w, "x", s.h),
+                        Nothing => core.println("nothing"),
+                    }
+                }
 
-                    core.println(bar);
+                main :: () {
+                    describe(Shape.Circle(1.5));
+                    describe(Shape.Rect.{ w = 2, h = 3 });
+                    describe(Shape.Nothing());
                 }
             "#,
             "main",
             true,
             &[],
             expect![["
-            [ 1, 2, 3 ]
+            circle: 1.500
+            rect: 2x3
+            nothing
 
 "]],
             0,
@@ -2703,16 +3393,354 @@ mod tests {
     }
 
     #[test]
-    fn float_to_bits() {
+    fn switch_through_pointers() {
         check_raw_with_args(
             r#"
                 core :: #mod("core");
-                fmt :: core.fmt;
 
-                main :: () {
-                    bits : u32 = core.mem.f32_to_bits(42.5);
-                    core.println(fmt.binary(bits));
-                    core.println(core.mem.f32_from_bits(bits));
+                Shape :: enum {
+                    Square: i32,
+                    Circle: i32,
+                    Nothing,
+                };
+
+                describe :: (shape: ^^Shape) {
+                    switch s in shape {
+                        Square => core.println("square: ", s),
+                        Circle => core.println("circle: ", s),
+                        Nothing => core.println("nothing"),
+                    }
+                }
+
+                main :: () {
+                    circle : Shape = Shape.Circle.(3);
+                    circle_ptr := ^circle;
+                    describe(^circle_ptr);
+
+                    nothing : Shape = Shape.Nothing.();
+                    nothing_ptr := ^nothing;
+                    describe(^nothing_ptr);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            circle: 3
+            nothing
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn distinct_index_and_switch() {
+        check_raw(
+            r#"
+                Id :: distinct u32;
+                Shape :: enum { Circle: f32, Square: u8 };
+                Tagged :: distinct Shape;
+
+                main :: () -> i32 {
+                    nums := i32.[10, 20, 30];
+                    id : Id = 1;
+
+                    shape := Tagged.(Shape.Square.(7));
+                    side := switch s in shape {
+                        Circle => 0,
+                        Square => i32.(s),
+                    };
+
+                    nums[id] + side
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            27,
+        )
+    }
+
+    #[test]
+    fn optionals() {
+        check_raw(
+            r#"
+                Point :: struct { x: i32, y: i32 };
+
+                find :: (haystack: []i32, needle: i32) -> ?usize {
+                    idx := 0;
+                    while idx < haystack.len {
+                        if haystack[idx] == needle {
+                            return idx;
+                        }
+                        idx += 1;
+                    }
+                    null
+                }
+
+                origin :: (set: bool) -> ?Point {
+                    if set {
+                        Point.{ x = 3, y = 4 }
+                    } else {
+                        null
+                    }
+                }
+
+                main :: () -> i32 {
+                    nums := i32.[5, 10, 15, 20];
+
+                    a := find(nums[..], 15) orelse 100;
+                    b := find(nums[..], 99) orelse 40;
+                    c := #unwrap(find(nums[..], 20));
+
+                    p := origin(true) orelse Point.{ x = 0, y = 0 };
+                    q := origin(false);
+
+                    d : ?i32 = null;
+                    d = 7;
+
+                    missing := if q == null { 50 } else { 0 };
+                    present := if d != null { #unwrap(d) } else { 0 };
+
+                    i32.(a + b + c) + p.x + p.y + missing + present
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            109,
+        )
+    }
+
+    #[test]
+    fn optional_early_return_with_tail() {
+        check_raw(
+            r#"
+                Point :: struct { x: i32, y: i32 };
+
+                positive :: (n: i32) -> ?i32 {
+                    if n <= 0 {
+                        return null;
+                    }
+                    n
+                }
+
+                clamped :: (n: i32) -> ?i32 {
+                    if n > 100 {
+                        return 100;
+                    }
+                    if n < 0 {
+                        return null;
+                    }
+                    5
+                }
+
+                point :: (n: i32) -> ?Point {
+                    if n == 0 {
+                        return null;
+                    }
+                    Point.{ x = n, y = n * 2 }
+                }
+
+                main :: () -> i32 {
+                    a := positive(7) orelse 0;
+                    b := positive(-3) orelse 20;
+                    c := clamped(500) orelse 0;
+                    d := clamped(50) orelse 0;
+                    e := clamped(-1) orelse 100;
+                    p := point(3) orelse Point.{ x = 0, y = 0 };
+                    missing := if point(0) == null { 10 } else { 0 };
+
+                    a + b + c + d + e + p.x + p.y + missing
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            251,
+        )
+    }
+
+    #[test]
+    fn while_binding_unwraps_optionals() {
+        check_raw(
+            r#"
+                next :: (counter: ^mut i32) -> ?i32 {
+                    if counter^ >= 5 {
+                        null
+                    } else {
+                        counter^ += 1;
+                        counter^ * 10
+                    }
+                }
+
+                main :: () -> i32 {
+                    counter := 0;
+                    sum := 0;
+                    while x := next(^mut counter) {
+                        sum += x;
+                    }
+                    sum + counter
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            155,
+        )
+    }
+
+    #[test]
+    fn inline_hints() {
+        check_raw(
+            r#"
+                Vec2 :: struct { x: i32, y: i32 };
+
+                add :: (a: Vec2, b: Vec2) -> Vec2 @inline {
+                    if a.x == 0 {
+                        return b;
+                    }
+                    Vec2.{ x = a.x + b.x, y = a.y + b.y }
+                }
+
+                fact :: (n: i32) -> i32 @inline {
+                    if n <= 1 { 1 } else { n * fact(n - 1) }
+                }
+
+                bump :: (x: ^mut i32) @inline {
+                    defer { x^ += 1; };
+                    x^ *= 2;
+                }
+
+                slow :: (x: i32) -> i32 @no_inline {
+                    x + 1
+                }
+
+                main :: () -> i32 {
+                    v := add(Vec2.{ x = 1, y = 2 }, Vec2.{ x = 3, y = 4 });
+                    w := add(Vec2.{ x = 0, y = 0 }, v);
+
+                    n := 5;
+                    bump(^mut n);
+
+                    w.x + w.y + fact(4) + n + slow(2)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            48,
+        )
+    }
+
+    #[test]
+    fn export_name() {
+        check_raw(
+            r#"
+                add_one :: (x: i32) -> i32 @export("capy_add_one") {
+                    x + 1
+                }
+
+                capy_add_one :: (x: i32) -> i32 extern;
+
+                main :: () -> i32 {
+                    capy_add_one(41)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            42,
+        )
+    }
+
+    #[test]
+    fn const_aggregate_globals() {
+        check_raw(
+            r#"
+                Point :: struct { x: i32, y: i32 };
+                Line :: struct { a: Point, b: Point, tag: u8 };
+                Op :: struct { name: str, apply: (i32) -> i32 };
+
+                double :: (x: i32) -> i32 { x * 2 }
+                negate :: (x: i32) -> i32 { -x }
+
+                ORIGIN :: Point.{ x = 1, y = 2 };
+                LINE :: Line.{ a = ORIGIN, b = Point.{ x = 5, y = 6 }, tag = 3 };
+                TABLE :: Point.[ORIGIN, Point.{ x = 10, y = 20 }, Point.{ x = 7, y = 8 }];
+                WORDS :: str.["hi", "there"];
+                OPS :: Op.[Op.{ name = "double", apply = double }, Op.{ name = "negate", apply = negate }];
+                END_X :: LINE.b.x;
+
+                main :: () -> i32 {
+                    first := (^char).(WORDS[1])^;
+                    op_name := (^char).(OPS[1].name)^;
+
+                    strs_ok := if first == 't' && op_name == 'n' { 1 } else { 100 };
+
+                    ORIGIN.x + ORIGIN.y + LINE.b.y + i32.(LINE.tag) + TABLE[1].y
+                        + OPS[0].apply(5) + OPS[1].apply(1)
+                        + END_X + strs_ok
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            47,
+        )
+    }
+
+    #[test]
+    fn autocast_array_to_slice() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                main :: () {
+                    foo := .[1, 2, 3];
+
+                    bar : []u128 = foo;
+
+                    core.println(bar);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            [ 1, 2, 3 ]
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn float_to_bits() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+                fmt :: core.fmt;
+
+                main :: () {
+                    bits : u32 = core.mem.f32_to_bits(42.5);
+                    core.println(fmt.binary(bits));
+                    core.println(core.mem.f32_from_bits(bits));
 
                     bits : u64 = core.mem.f64_to_bits(42.5);
                     core.println(fmt.binary_unsigned(bits));
@@ -2774,57 +3802,1008 @@ mod tests {
     }
 
     #[test]
-    fn quick_assign_ret() {
+    fn align_cast_directive() {
         check_raw_with_args(
             r#"
-                main :: () -> u64 {
-                    foo := 5;
+                core :: #mod("core");
 
-                    foo += 25; // foo = 30
-                    foo *= 2;  // foo = 60
-                    foo -= 4;  // foo = 56
-                    foo /= 2;  // foo = 28
+                main :: () {
+                    x : u64 = 42;
+                    bytes := (^u8).(rawptr.(^x));
 
-                    foo
+                    back := #align_cast(bytes, u64);
+                    core.println(back^);
                 }
             "#,
             "main",
-            false,
+            true,
             &[],
-            expect![["
+            expect![[r#"
+                42
 
-"]],
-            28,
+            "#]],
+            0,
         )
     }
 
     #[test]
-    fn quick_assign_print() {
+    fn math_intrinsics() {
         check_raw_with_args(
             r#"
                 core :: #mod("core");
 
                 main :: () {
-                    foo : i64 = 5;
-
-                    foo += 25;      // foo = 30
-                    foo *= 2;       // foo = 60
-                    foo -= u8.(4);  // foo = 56
-                    foo /= 2;       // foo = 28
-
-                    core.println(foo);
+                    x : f64 = 16.0;
+                    core.println(#sqrt(x));
+                    core.println(#abs(-5), " ", #abs(-2.5));
+                    core.println(#min(3, 7), " ", #max(3, 7));
+                    core.println(#fma(2.0, 3.0, 1.0));
+
+                    n : u32 = 40;
+                    core.println(#ctz(n), " ", #clz(n), " ", #popcount(n));
                 }
             "#,
             "main",
             true,
             &[],
             expect![["
-            28
+            4.000
+            5 2.500
+            3 7
+            7.000
+            3 26 2
 
 "]],
             0,
         )
     }
 
+    #[test]
+    fn null_literal() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                global_ptr :: null;
+
+                main :: () {
+                    x := 5;
+                    typed := ^x;
+                    raw : rawptr = null;
+
+                    core.println(typed == null, " ", typed != null);
+                    core.println(raw == null, " ", null != raw, " ", global_ptr == null);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![[r#"
+                false true
+                true false true
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn pow_directive() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                main :: () {
+                    base : i64 = -3;
+                    core.println(#pow(base, 5), " ", #pow(2, 10), " ", #pow(7, 0));
+
+                    small : u8 = 2;
+                    core.println(#pow(small, 9));
+
+                    core.println(#pow(2.0, 0.5), " ", #pow(10.0, -2.0));
+
+                    x : f32 = 1.5;
+                    core.println(#pow(x, 2.0));
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![[r#"
+                -243 1024 1
+                0
+                1.414 0.010
+                2.250
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn overloaded_functions() {
+        check_raw(
+            r#"
+                describe :: (n: i32) -> i32 {
+                    puts("an i32");
+                    n
+                }
+
+                describe :: (x: f32) -> i32 {
+                    puts("an f32");
+                    i32.(x)
+                }
+
+                describe :: (text: str, n: i32) -> i32 {
+                    puts(text);
+                    describe(n) + 1
+                }
+
+                main :: () -> i32 {
+                    f : f32 = 2.5;
+
+                    describe(40) + describe(f) + describe("a str and", 3)
+                }
+
+                puts :: (text: str) extern;
+            "#,
+            "main",
+            false,
+            expect![[r#"
+                an i32
+                an f32
+                a str and
+                an i32
+
+            "#]],
+            46,
+        )
+    }
+
+    #[test]
+    fn overloaded_operators() {
+        check_raw(
+            r#"
+                Vec2 :: struct {
+                    x: i32,
+                    y: i32,
+
+                    __add :: (a: Vec2, b: Vec2) -> Vec2 {
+                        Vec2.{ x = a.x + b.x, y = a.y + b.y }
+                    },
+
+                    __mul :: (a: Vec2, by: i32) -> Vec2 {
+                        Vec2.{ x = a.x * by, y = a.y * by }
+                    },
+
+                    __eq :: (a: Vec2, b: Vec2) -> bool {
+                        a.x == b.x && a.y == b.y
+                    }
+                };
+
+                Cents :: distinct i32;
+
+                __sub :: (a: Cents, b: Cents) -> Cents {
+                    // never goes below zero
+                    if i32.(b) > i32.(a) { Cents.(0) } else { Cents.(i32.(a) - i32.(b)) }
+                }
+
+                main :: () -> i32 {
+                    v := Vec2.{ x = 1, y = 2 } + Vec2.{ x = 3, y = 4 } * 2;
+
+                    price : Cents = 10;
+                    discounted := price - 25;
+
+                    if v == Vec2.{ x = 7, y = 10 } {
+                        v.x * v.y + i32.(discounted)
+                    } else {
+                        0
+                    }
+                }
+            "#,
+            "main",
+            false,
+            expect![["
+
+"]],
+            70,
+        )
+    }
+
+    #[test]
+    fn method_call_syntax() {
+        check_raw(
+            r#"
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                moved :: (p: Point, dx: i32, dy: i32) -> Point {
+                    Point.{ x = p.x + dx, y = p.y + dy }
+                }
+
+                squared :: (n: i32) -> i32 { n * n }
+
+                main :: () -> i32 {
+                    p := Point.{ x = 1, y = 2 };
+                    p = p.moved(2, 1);
+
+                    p.x.squared() + p.y.squared()
+                }
+            "#,
+            "main",
+            false,
+            expect![["
+
+"]],
+            18,
+        )
+    }
+
+    #[test]
+    fn const_params() {
+        check_raw(
+            r#"
+                core :: #mod("core");
+
+                filled :: (n: usize, item: i32) -> [n]i32 {
+                    i32.[item; n]
+                }
+
+                sum :: (n: usize, arr: [n]i32) -> i32 {
+                    total := 0;
+                    for x in arr {
+                        total += x;
+                    }
+                    total
+                }
+
+                main :: () {
+                    core.println(sum(3, i32.[1, 2, 3]));
+                    core.println(sum(4, filled(4, 5)));
+                    core.println(sum(2, filled(2, 7)));
+                    core.println(comptime { sum(5, filled(5, 3)) });
+                }
+            "#,
+            "main",
+            true,
+            expect![[r#"
+                6
+                20
+                14
+                15
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn interfaces() {
+        check_raw(
+            r#"
+                core :: #mod("core");
+
+                Shape :: interface {
+                    area: () -> i32,
+                    grow: (by: i32) -> void,
+                };
+
+                Square :: struct {
+                    side: i32,
+
+                    area :: (s: ^Square) -> i32 { s.side * s.side }
+                };
+
+                Rect :: struct {
+                    w: i32,
+                    h: i32,
+                };
+
+                area :: (r: ^Rect) -> i32 { r.w * r.h }
+
+                grow :: (s: ^mut Square, by: i32) {
+                    s.side = s.side + by;
+                }
+
+                grow :: (r: ^mut Rect, by: i32) {
+                    r.w = r.w + by;
+                    r.h = r.h + by;
+                }
+
+                print_area :: (shape: Shape) {
+                    core.println(shape.area());
+                }
+
+                main :: () {
+                    square := Square.{ side = 3 };
+                    rect := Rect.{ w = 2, h = 5 };
+
+                    print_area(^mut square);
+                    print_area(^mut rect);
+
+                    shapes := Shape.[^mut square, ^mut rect];
+                    for shape in shapes {
+                        shape.grow(1);
+                        print_area(shape);
+                    }
+
+                    core.println(square.side);
+                }
+            "#,
+            "main",
+            true,
+            expect![[r#"
+                9
+                10
+                16
+                18
+                4
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn struct_layout_padding() {
+        let mut interner = Interner::default();
+
+        let mut member = |name: &str, ty: hir_ty::Ty| hir_ty::MemberTy {
+            name: hir::Name(interner.intern(name)),
+            ty: ty.into(),
+        };
+        let members = vec![
+            member("tag", hir_ty::Ty::UInt(8)),
+            member("len", hir_ty::Ty::UInt(32)),
+            member("flags", hir_ty::Ty::UInt(16)),
+        ];
+        let names: Vec<_> = members.iter().map(|member| Some(member.name)).collect();
+
+        let header = hir_ty::Ty::Struct {
+            anonymous: false,
+            fqn: None,
+            uid: 0,
+            members,
+        }
+        .into();
+
+        let layout = layout_of(header, 64);
+
+        assert_eq!((layout.size, layout.align, layout.stride), (10, 4, 12));
+        assert_eq!(
+            layout
+                .fields
+                .iter()
+                .map(|field| (field.name, field.offset, field.size, field.padding))
+                .collect::<Vec<_>>(),
+            vec![
+                (names[0], 0, 1, 3),
+                (names[1], 4, 4, 0),
+                (names[2], 8, 2, 2),
+            ]
+        );
+        assert_eq!(layout.discriminant_offset, None);
+    }
+
+    #[test]
+    fn closures() {
+        check_raw(
+            r#"
+                core :: #mod("core");
+
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                apply :: (f: closure (x: i32) -> i32, x: i32) -> i32 {
+                    f(x)
+                }
+
+                double :: (x: i32) -> i32 {
+                    x * 2
+                }
+
+                main :: () {
+                    offset := 10;
+                    p := Point.{ x = 3, y = 4 };
+
+                    add_offset := (x: i32) -> i32 { x + offset };
+                    core.println(apply(add_offset, 5));
+
+                    // the closure has its own copy of `offset`
+                    offset = 100;
+                    core.println(add_offset(5));
+
+                    core.println(apply((x: i32) -> i32 { x * p.x + p.y }, 2));
+                    core.println(apply(double, 21));
+
+                    nested := (x: i32) -> i32 {
+                        inner := (y: i32) -> i32 { y + offset };
+                        inner(x) + p.y
+                    };
+                    core.println(nested(1));
+
+                    move :: (by: i32) -> Point { Point.{ x = p.x + by, y = p.y + by } };
+                    moved := move(2);
+                    core.println(moved.x);
+                    core.println(moved.y);
+
+                    counter := 0;
+                    counter_ptr := ^mut counter;
+                    bump := () { counter_ptr^ = counter_ptr^ + 1; };
+                    bump();
+                    bump();
+                    core.println(counter);
+                }
+            "#,
+            "main",
+            true,
+            expect![[r#"
+                15
+                15
+                10
+                42
+                105
+                5
+                6
+                2
+
+            "#]],
+            0,
+        )
+    }
+
+    #[test]
+    fn tail_call_directive() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Pair :: struct {
+                    a: i64,
+                    b: i64,
+                };
+
+                fib :: (n: i64, pair: Pair) -> i64 {
+                    if n == 0 {
+                        return pair.a;
+                    }
+                    #tailcall(fib(n - 1, Pair.{ a = pair.b, b = pair.a + pair.b }))
+                }
+
+                count :: (n: i64, total: i64) -> i64 {
+                    if n == 0 {
+                        return total;
+                    }
+                    #tailcall(count(n - 1, total + 2))
+                }
+
+                main :: () {
+                    core.println(fib(50, Pair.{ a = 0, b = 1 }));
+                    core.println(count(10_000_000, 0));
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            12586269025
+            20000000
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn quick_assign_ret() {
+        check_raw_with_args(
+            r#"
+                main :: () -> u64 {
+                    foo := 5;
+
+                    foo += 25; // foo = 30
+                    foo *= 2;  // foo = 60
+                    foo -= 4;  // foo = 56
+                    foo /= 2;  // foo = 28
+
+                    foo
+                }
+            "#,
+            "main",
+            false,
+            &[],
+            expect![["
+
+"]],
+            28,
+        )
+    }
+
+    #[test]
+    fn quick_assign_print() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                main :: () {
+                    foo : i64 = 5;
+
+                    foo += 25;      // foo = 30
+                    foo *= 2;       // foo = 60
+                    foo -= u8.(4);  // foo = 56
+                    foo /= 2;       // foo = 28
+
+                    core.println(foo);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            28
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn quick_assign_remaining_ops() {
+        check_raw_with_args(
+            r#"
+                main :: () -> u64 {
+                    foo := 17;
+
+                    foo %= 5;   // foo = 2
+                    foo <<= 3;  // foo = 16
+                    foo |= 1;   // foo = 17
+                    foo ~= 3;   // foo = 18
+                    foo >>= 1;  // foo = 9
+                    foo &= 12;  // foo = 8
+
+                    done := false;
+                    done ||= true;
+                    done &&= foo == 8;
+
+                    if done { foo } else { 0 }
+                }
+            "#,
+            "main",
+            false,
+            &[],
+            expect![["
+
+"]],
+            8,
+        )
+    }
+
+    #[test]
+    fn large_array_fills_and_copies() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                main :: () {
+                    zeroes : [32]i64;
+                    zeroes[31] = 9;
+                    core.println(zeroes[0], " ", zeroes[30], " ", zeroes[31]);
+
+                    copy := zeroes;
+                    copy[0] = 5;
+                    core.println(zeroes[0], " ", copy[0], " ", copy[31]);
+
+                    halves := f64.[2.5, 2.5, 2.5, 2.5, 2.5, 2.5, 2.5, 2.5, 2.5];
+                    core.println(halves[0], " ", halves[8]);
+
+                    fives := u32.[5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5];
+                    core.println(fives[0], " ", fives[16]);
+
+                    ones := i32.[16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009, 16843009];
+                    core.println(ones[16]);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            0 0 9
+            0 5 9
+            2.500 2.500
+            5 5
+            16843009
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn array_repeat() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                bump :: (counter: ^mut i32) -> i32 {
+                    counter^ = counter^ + 1;
+                    counter^ * 10
+                };
+
+                main :: () {
+                    bytes := u8.[7; 100];
+                    core.println(bytes[0], " ", bytes[99]);
+
+                    count :: 20;
+                    nums : [count]i64 = i64.[3; count];
+                    core.println(nums[0], " ", nums[19]);
+
+                    counter := 0;
+                    tens := i32.[bump(^mut counter); 30];
+                    core.println(tens[0], " ", tens[29], " ", counter);
+
+                    points := Point.[Point.{ x = 1, y = 2 }; 12];
+                    core.println(points[11].x, " ", points[11].y);
+
+                    small := .[2.5; 3];
+                    core.println(small[2]);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            7 7
+            3 3
+            10 10 1
+            1 2
+            2.500
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn array_inferred_size() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                nums : [_]i32 : i32.[1, 2, 3];
+
+                main :: () {
+                    more : [_]u8 = .[4, 5, 6, 7];
+                    core.println(nums[2], " ", more[3]);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            3 7
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn multidimensional_array() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                main :: () {
+                    grid : [3][4]i32;
+
+                    row : usize = 0;
+                    while row < grid.len {
+                        col : usize = 0;
+                        while col < grid[row].len {
+                            grid[row][col] = i32.(row * 10 + col);
+                            col = col + 1;
+                        }
+                        row = row + 1;
+                    }
+
+                    core.println(grid.len, " ", grid[0].len, " ", grid[2][3]);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            3 4 23
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn function_tys_in_type_positions() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Handler :: struct {
+                    on_click: (i32) -> bool,
+                };
+
+                is_even :: (n: i32) -> bool {
+                    n % 2 == 0
+                };
+
+                apply :: (f: (i32) -> bool, n: i32) -> bool {
+                    f(n)
+                };
+
+                main :: () {
+                    handler := Handler.{ on_click = is_even };
+                    casted := ((i32) -> bool).(is_even);
+
+                    core.println(handler.on_click(4), " ", casted(3), " ", apply(is_even, 10));
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            true false true
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn struct_decls() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+
+                    ZERO :: 0,
+
+                    new :: (x: i32, y: i32) -> Point {
+                        Point.{ x = x, y = y }
+                    },
+
+                    sum :: (self: Point) -> i32 {
+                        self.x + self.y
+                    }
+                };
+
+                main :: () {
+                    p := Point.new(3, 4);
+
+                    core.println(p.sum(), " ", Point.sum(Point.new(0, 0)), " ", Point.ZERO);
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            7 0 0
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn modules() {
+        check_raw_with_args(
+            r#"
+                core :: #mod("core");
+
+                shapes :: module {
+                    Square :: struct {
+                        side: i32,
+
+                        area :: (self: #this()) -> i32 {
+                            self.side * self.side
+                        }
+                    };
+
+                    unit :: () -> Square {
+                        Square.{ side = 1 }
+                    }
+                };
+
+                main :: () {
+                    s := shapes.Square.{ side = 3 };
+
+                    core.println(s.area(), " ", shapes.unit().area());
+                }
+            "#,
+            "main",
+            true,
+            &[],
+            expect![["
+            9 1
+
+"]],
+            0,
+        )
+    }
+
+    #[test]
+    fn for_loops() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    nums := i32.[1, 2, 3, 4, 5];
+                    sum : i32 = 0;
+
+                    for n in nums {
+                        if n == 2 {
+                            continue;
+                        }
+                        if n == 5 {
+                            break;
+                        }
+                        sum += n;
+                    }
+
+                    slice : []i32 = nums;
+                    for n in slice {
+                        sum += n;
+                    }
+
+                    for n in ^nums {
+                        sum += n;
+                    }
+
+                    sum
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            38,
+        )
+    }
+
+    #[test]
+    fn for_over_ranges() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    sum := 0;
+
+                    for i in 0..5 {
+                        sum += i;
+                    }
+
+                    // an inclusive range that ends at the max value shouldn't overflow
+                    count := 0;
+                    for b in 250u8..=255 {
+                        count += 1;
+                    }
+
+                    for i in -3..-1 {
+                        sum += i;
+                    }
+
+                    // empty
+                    for i in 5..2 {
+                        sum += 100;
+                    }
+
+                    sum * 10 + count
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            56,
+        )
+    }
+
+    #[test]
+    fn slicing() {
+        check_raw(
+            r#"
+                sum :: (nums: []i32) -> i32 {
+                    total := 0;
+                    for n in nums {
+                        total += n;
+                    }
+                    total
+                }
+
+                main :: () -> i32 {
+                    nums := i32.[1, 2, 3, 4, 5];
+
+                    start := 1;
+                    middle := nums[start..3];
+                    r := 2..4;
+
+                    sum(middle) * 10
+                        + sum(nums[..=1])
+                        + sum(nums[3..]) * 10
+                        + i32.(nums[..].len)
+                        + sum(nums[r])
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            155,
+        )
+    }
+
+    #[test]
+    fn tuples() {
+        check_raw(
+            r#"
+                Pair :: (i32, u8);
+
+                swap :: (pair: Pair) -> (u8, i32) {
+                    (pair.1, pair.0)
+                }
+
+                main :: () -> i32 {
+                    p : Pair = (42, 3);
+                    s := swap(p);
+
+                    nested := (1.5, (true, 9));
+                    nested.1.1 += 1;
+
+                    z : (i64, i8);
+                    z.1 = 2;
+
+                    s.1 + i32.(s.0) + nested.1.1 + i32.(z.0) + i32.(z.1)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            57,
+        )
+    }
+
+    #[test]
+    fn literal_suffixes() {
+        check_raw(
+            r#"
+                main :: () -> i32 {
+                    small := 250u8;
+                    big := 0x1_0000_0000i64;
+                    half := 1.5f32;
+
+                    i32.(small) + i32.(big >> 32) + i32.(half * 2.0)
+                }
+            "#,
+            "main",
+            false,
+            expect![[r#"
+
+"#]],
+            254,
+        )
+    }
+
     // the "ptrs_to_ptrs.capy" and "comptime_types.capy" tests are not reproducible
 }
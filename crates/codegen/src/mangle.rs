@@ -28,11 +28,20 @@ impl Mangle for Fqn {
 impl Mangle for FunctionToCompile {
     fn to_mangled_name(&self, mod_dir: &std::path::Path, interner: &Interner) -> String {
         if let Some(name) = self.function_name {
-            return hir::Fqn {
+            let mut mangled = hir::Fqn {
                 file: self.file_name,
                 name,
             }
             .to_mangled_name(mod_dir, interner);
+
+            if let Some(instance) = self.instance {
+                mangled.pop();
+                mangled.push_str("i_");
+                mangled.push_str(&instance.to_string());
+                mangled.push('E');
+            }
+
+            return mangled;
         };
 
         let mut mangled = String::new();
@@ -92,6 +101,29 @@ impl Mangle for BuiltinFunction {
     }
 }
 
+/// The symbol a function is defined with. `@export` replaces the mangled name entirely,
+/// which lets C code call into Capy by a name of its choosing
+pub(crate) fn function_symbol(
+    ftc: &FunctionToCompile,
+    world_bodies: &hir::WorldBodies,
+    mod_dir: &std::path::Path,
+    interner: &Interner,
+) -> String {
+    // every instance would end up with the same name
+    match world_bodies[ftc.file_name][ftc.lambda].export_name {
+        Some(export_name) if ftc.instance.is_none() => interner.lookup(export_name).to_string(),
+        _ => ftc.to_mangled_name(mod_dir, interner),
+    }
+}
+
+/// Extern globals aren't mangled, and ones declared inside of a struct or module
+/// still link against their unqualified name
+pub(crate) fn extern_name(fqn: Fqn, interner: &Interner) -> &str {
+    let name = interner.lookup(fqn.name.0);
+
+    name.rsplit_once('.').map_or(name, |(_, name)| name)
+}
+
 pub(crate) fn mangle_internal(name: &str) -> String {
     let mut mangled = String::from("_CI");
 
@@ -1,15 +1,16 @@
-use std::vec;
+use std::{ops::Range, vec};
 
 use ast::validation::{ValidationDiagnostic, ValidationDiagnosticKind};
 use hir::{IndexingDiagnostic, IndexingDiagnosticKind, LoweringDiagnostic, LoweringDiagnosticKind};
-use hir_ty::{ExpectedTy, TyDiagnostic, TyDiagnosticHelp};
+use hir_ty::{ExpectedTy, TyDiagnostic, TyDiagnosticHelp, TyVerbosity};
 use interner::Interner;
 use line_index::{ColNr, LineIndex, LineNr};
 use parser::{ExpectedSyntax, SyntaxError, SyntaxErrorKind};
 use syntax::NodeKind;
 use text_size::{TextRange, TextSize};
 
-pub struct Diagnostic(Repr);
+/// The second field is the level set for this diagnostic's lint, if it has one
+pub struct Diagnostic(Repr, Option<LintLevel>);
 
 enum Repr {
     Syntax(SyntaxError),
@@ -19,34 +20,74 @@ enum Repr {
     Ty(TyDiagnostic),
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Severity {
     Help,
     Warning,
     Error,
 }
 
+/// How a warning is treated, which can be set for each lint in the `[lints]` section
+/// of a `capy.toml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    /// the warning isn't shown at all
+    Allow,
+    Warn,
+    /// the warning becomes an error
+    Deny,
+}
+
+impl LintLevel {
+    pub fn parse(level: &str) -> Option<Self> {
+        match level {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The name of every warning, as used in the `[lints]` section of a `capy.toml`
+pub const LINTS: &[&str] = &[
+    "always_true",
+    "always_false",
+    "loop_never_runs",
+    "constant_comparison",
+    "self_comparison",
+    "paren_in_condition",
+    "identical_switch_arm",
+    "confusable_char",
+    "unreachable_default",
+    "deprecated",
+    "struct_literal_out_of_order",
+    "misaligned_pointer_cast",
+    "large_array_copy",
+];
+
 impl Diagnostic {
     pub fn from_syntax(error: SyntaxError) -> Self {
-        Self(Repr::Syntax(error))
+        Self(Repr::Syntax(error), None)
     }
 
     pub fn from_validation(diagnostic: ValidationDiagnostic) -> Self {
-        Self(Repr::Validation(diagnostic))
+        Self(Repr::Validation(diagnostic), None)
     }
 
     pub fn from_indexing(diagnostic: IndexingDiagnostic) -> Self {
-        Self(Repr::Indexing(diagnostic))
+        Self(Repr::Indexing(diagnostic), None)
     }
 
     pub fn from_lowering(diagnostic: LoweringDiagnostic) -> Self {
-        Self(Repr::Lowering(diagnostic))
+        Self(Repr::Lowering(diagnostic), None)
     }
 
     pub fn from_ty(diagnostic: TyDiagnostic) -> Self {
-        Self(Repr::Ty(diagnostic))
+        Self(Repr::Ty(diagnostic), None)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn display(
         &self,
         filename: &str,
@@ -55,6 +96,7 @@ impl Diagnostic {
         interner: &Interner,
         line_index: &LineIndex,
         with_colors: bool,
+        width: Option<usize>,
     ) -> Vec<String> {
         let range = self.range();
 
@@ -76,17 +118,18 @@ impl Diagnostic {
             ("", "", "", "", "")
         };
 
-        let severity = match self.severity() {
-            Severity::Help => format!("{}help", ansi_blue),
-            Severity::Warning => format!("{}warning", ansi_yellow),
-            Severity::Error => format!("{}error", ansi_red),
+        let (severity_color, severity) = match self.severity() {
+            Severity::Help => (ansi_blue, "help"),
+            Severity::Warning => (ansi_yellow, "warning"),
+            Severity::Error => (ansi_red, "error"),
         };
 
         let mut lines = vec![format!(
-            "{}{}: {}{}",
+            "{}{}{}: {}{}",
+            severity_color,
             severity,
             ansi_white,
-            self.message(mod_dir, interner),
+            wrap(&self.message(mod_dir, interner), severity.len() + 2, width),
             ansi_reset,
         )];
 
@@ -101,14 +144,15 @@ impl Diagnostic {
             self.severity(),
             with_colors,
             self.arrow(),
+            width,
         );
 
-        if let Some(help) = self.help() {
+        for help in self.helps() {
             lines.push(format!(
                 "{}help{}: {}{}",
                 ansi_blue,
                 ansi_white,
-                help.message(mod_dir, interner),
+                wrap(&help.message(mod_dir, interner), "help: ".len(), width),
                 ansi_reset
             ));
 
@@ -131,9 +175,20 @@ impl Diagnostic {
                 Severity::Help,
                 with_colors,
                 false,
+                width,
             );
         }
 
+        if let Some(note) = self.note(mod_dir, interner) {
+            lines.push(format!(
+                "{}note{}: {}{}",
+                ansi_blue,
+                ansi_white,
+                wrap(&note, "note: ".len(), width),
+                ansi_reset
+            ));
+        }
+
         lines
     }
 
@@ -146,7 +201,10 @@ impl Diagnostic {
             Repr::Syntax(SyntaxError {
                 kind:
                     SyntaxErrorKind::UnexpectedToken { range, .. }
-                    | SyntaxErrorKind::UnexpectedNode { range, .. },
+                    | SyntaxErrorKind::UnexpectedNode { range, .. }
+                    | SyntaxErrorKind::IncrementOrDecrement { range, .. }
+                    | SyntaxErrorKind::AssignmentInCondition { range }
+                    | SyntaxErrorKind::UnterminatedBlockComment { range },
                 ..
             }) => range,
             Repr::Validation(ValidationDiagnostic { range, .. }) => range,
@@ -157,7 +215,7 @@ impl Diagnostic {
     }
 
     pub fn severity(&self) -> Severity {
-        match &self.0 {
+        let severity = match &self.0 {
             Repr::Syntax(_) => Severity::Error,
             Repr::Validation(_) => Severity::Warning,
             Repr::Indexing(_) => Severity::Error,
@@ -169,9 +227,57 @@ impl Diagnostic {
                     Severity::Warning
                 }
             }
+        };
+
+        match (severity, self.1) {
+            (Severity::Warning, Some(LintLevel::Deny)) => Severity::Error,
+            (severity, _) => severity,
+        }
+    }
+
+    /// The name this diagnostic goes by in `[lints]`.
+    /// Only warnings have one, errors can't be turned off
+    pub fn lint(&self) -> Option<&'static str> {
+        let lint = match &self.0 {
+            Repr::Validation(d) => match d.kind {
+                ValidationDiagnosticKind::AlwaysTrue => "always_true",
+                ValidationDiagnosticKind::AlwaysFalse => "always_false",
+                ValidationDiagnosticKind::LoopNeverRuns => "loop_never_runs",
+                ValidationDiagnosticKind::ConstantComparison => "constant_comparison",
+                ValidationDiagnosticKind::ParenInCondition => "paren_in_condition",
+                ValidationDiagnosticKind::IdenticalSwitchArm => "identical_switch_arm",
+                ValidationDiagnosticKind::ConfusableChar { .. } => "confusable_char",
+            },
+            Repr::Ty(d) => match d.kind {
+                hir_ty::TyDiagnosticKind::UnreachableDefault => "unreachable_default",
+                hir_ty::TyDiagnosticKind::Deprecated { .. } => "deprecated",
+                hir_ty::TyDiagnosticKind::StructLiteralOutOfOrder { .. } => {
+                    "struct_literal_out_of_order"
+                }
+                hir_ty::TyDiagnosticKind::MisalignedPointerCast { .. } => "misaligned_pointer_cast",
+                hir_ty::TyDiagnosticKind::LargeArrayCopy { .. } => "large_array_copy",
+                hir_ty::TyDiagnosticKind::SelfComparison => "self_comparison",
+                _ => return None,
+            },
+            Repr::Syntax(_) | Repr::Indexing(_) | Repr::Lowering(_) => return None,
+        };
+
+        debug_assert!(LINTS.contains(&lint));
+
+        Some(lint)
+    }
+
+    /// Does nothing if this diagnostic isn't a lint
+    pub fn set_lint_level(&mut self, level: LintLevel) {
+        if self.lint().is_some() {
+            self.1 = Some(level);
         }
     }
 
+    pub fn is_allowed(&self) -> bool {
+        self.1 == Some(LintLevel::Allow)
+    }
+
     pub fn arrow(&self) -> bool {
         matches!(
             self.0,
@@ -192,33 +298,346 @@ impl Diagnostic {
         }
     }
 
-    pub fn help(&self) -> Option<HelpDiagnostic> {
+    /// most diagnostics have at most one help, but some point at several places at once
+    pub fn helps(&self) -> Vec<HelpDiagnostic> {
         match &self.0 {
-            Repr::Syntax(SyntaxError { .. }) => None,
-            Repr::Validation(ValidationDiagnostic { .. }) => None,
-            Repr::Indexing(IndexingDiagnostic { .. }) => None,
-            Repr::Lowering(LoweringDiagnostic { .. }) => None,
-            Repr::Ty(TyDiagnostic { help, .. }) => help.as_ref().map(HelpDiagnostic::Ty),
+            Repr::Syntax(SyntaxError { .. }) => Vec::new(),
+            Repr::Validation(ValidationDiagnostic { .. }) => Vec::new(),
+            Repr::Indexing(IndexingDiagnostic { .. }) => Vec::new(),
+            Repr::Lowering(LoweringDiagnostic { .. }) => Vec::new(),
+            Repr::Ty(TyDiagnostic {
+                kind: hir_ty::TyDiagnosticKind::BreakMismatch { breaks },
+                ..
+            }) => breaks
+                .iter()
+                .map(|(range, ty)| HelpDiagnostic::BreakTy {
+                    range: *range,
+                    ty: ty.as_ref(),
+                })
+                .collect(),
+            Repr::Ty(TyDiagnostic { help, .. }) => {
+                help.as_ref().map(HelpDiagnostic::Ty).into_iter().collect()
+            }
+        }
+    }
+}
+
+impl Diagnostic {
+    /// an extra bit of information that isn't tied to any specific part of the code
+    pub fn note(&self, mod_dir: &std::path::Path, interner: &Interner) -> Option<String> {
+        match &self.0 {
+            Repr::Syntax(SyntaxError {
+                kind: SyntaxErrorKind::AssignmentInCondition { .. },
+                ..
+            }) => Some("replace the `=` with `==` to compare the two values".to_string()),
+            Repr::Syntax(SyntaxError {
+                kind: SyntaxErrorKind::UnterminatedBlockComment { .. },
+                ..
+            }) => Some("block comments can be nested, so each `/*` needs its own `*/`".to_string()),
+            Repr::Ty(d) => ty_diagnostic_note(d, mod_dir, interner),
+            _ => None,
+        }
+        .or_else(|| match (self.lint(), self.1) {
+            (Some(lint), Some(LintLevel::Deny)) => Some(format!(
+                "`{lint}` is set to \"deny\" in the `[lints]` of the `capy.toml`"
+            )),
+            _ => None,
+        })
+    }
+}
+
+/// An edit to the source code of a file which makes a diagnostic go away
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub range: TextRange,
+    pub replacement: String,
+}
+
+impl Diagnostic {
+    /// `input` has to be the contents of the file this diagnostic is in
+    pub fn fix(&self, input: &str) -> Option<Fix> {
+        match &self.0 {
+            Repr::Ty(TyDiagnostic {
+                kind:
+                    hir_ty::TyDiagnosticKind::StructLiteralOutOfOrder {
+                        reordered,
+                        side_effect_free: true,
+                        ..
+                    },
+                ..
+            }) => {
+                // each member is put into the place of the member that was written there before,
+                // so the commas and whitespace between members all stay the same.
+                // comments are moved along with the member they're about
+                let reordered: Vec<_> = reordered
+                    .iter()
+                    .map(|range| MemberWithComments::new(input, *range))
+                    .collect();
+                let mut written = reordered.clone();
+                written.sort_unstable_by_key(|member| member.range.start());
+
+                let (first, last) = (written.first()?, written.last()?);
+                let end = last
+                    .trailing_comment
+                    .map(|comment| comment.end())
+                    .or(last.line_end)
+                    .unwrap_or(last.range.end());
+                let range = TextRange::new(first.range.start(), end);
+
+                let mut replacement = String::new();
+                let mut prev_end = first.range.start();
+                for (slot, member) in written.iter().zip(&reordered) {
+                    replacement.push_str(&input[TextRange::new(prev_end, slot.range.start())]);
+                    replacement.push_str(&input[member.range]);
+                    prev_end = slot.range.end();
+
+                    let Some(comment) = member.trailing_comment else {
+                        if let Some(old_comment) = slot.trailing_comment {
+                            // the member being moved here has no comment, so the comment
+                            // that was here goes away with the member it was about
+                            let before_comment =
+                                &input[TextRange::new(prev_end, old_comment.start())];
+                            replacement.push_str(before_comment.trim_end_matches([' ', '\t']));
+                            prev_end = old_comment.end();
+                        }
+                        continue;
+                    };
+
+                    match (slot.trailing_comment, slot.line_end) {
+                        (Some(old_comment), _) => {
+                            replacement
+                                .push_str(&input[TextRange::new(prev_end, old_comment.start())]);
+                            prev_end = old_comment.end();
+                        }
+                        (None, Some(line_end)) => {
+                            replacement.push_str(&input[TextRange::new(prev_end, line_end)]);
+                            replacement.push(' ');
+                            prev_end = line_end;
+                        }
+                        // the comment would swallow whatever comes after it on this line
+                        (None, None) => return None,
+                    }
+                    replacement.push_str(&input[comment]);
+                }
+                replacement.push_str(&input[TextRange::new(prev_end, end)]);
+
+                Some(Fix { range, replacement })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A `name = value` member of a struct literal, along with the comments on the lines above it
+/// and the comment after it on the same line
+#[derive(Debug, Clone, Copy)]
+struct MemberWithComments {
+    /// starts at the first comment above the member, and ends at the end of the value
+    range: TextRange,
+    trailing_comment: Option<TextRange>,
+    /// where a comment could be put after this member,
+    /// if there's nothing other than a comma between the member and the end of the line
+    line_end: Option<TextSize>,
+}
+
+impl MemberWithComments {
+    fn new(input: &str, member: TextRange) -> Self {
+        let mut start = usize::from(member.start());
+        let mut line_start = input[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        if input[line_start..start].trim().is_empty() {
+            while line_start > 0 {
+                let prev_line_start = input[..line_start - 1].rfind('\n').map_or(0, |idx| idx + 1);
+                let prev_line = &input[prev_line_start..line_start - 1];
+                if !prev_line.trim_start().starts_with("//") {
+                    break;
+                }
+                start = prev_line_start + (prev_line.len() - prev_line.trim_start().len());
+                line_start = prev_line_start;
+            }
+        }
+
+        let end = usize::from(member.end());
+        let rest_of_line = input[end..].split('\n').next().unwrap_or_default();
+        let after_comma = rest_of_line.trim_start();
+        let after_comma = after_comma.strip_prefix(',').unwrap_or(after_comma);
+        let comma_end = end + (rest_of_line.len() - after_comma.len());
+        let comment = after_comma.trim_start();
+        let comment_start = comma_end + (after_comma.len() - comment.len());
+
+        let (trailing_comment, line_end) = if comment.starts_with("//") {
+            let comment = TextRange::new(
+                (comment_start as u32).into(),
+                ((comment_start + comment.trim_end().len()) as u32).into(),
+            );
+            (Some(comment), None)
+        } else if comment.is_empty() && end + rest_of_line.len() < input.len() {
+            (None, Some((comma_end as u32).into()))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            range: TextRange::new((start as u32).into(), member.end()),
+            trailing_comment,
+            line_end,
         }
     }
 }
 
 pub enum HelpDiagnostic<'a> {
     Ty(&'a TyDiagnosticHelp),
+    BreakTy {
+        range: TextRange,
+        ty: &'a hir_ty::Ty,
+    },
 }
 
 impl HelpDiagnostic<'_> {
     pub fn range(&self) -> TextRange {
         match self {
             HelpDiagnostic::Ty(d) => d.range,
+            HelpDiagnostic::BreakTy { range, .. } => *range,
         }
     }
 
     pub fn message(&self, mod_dir: &std::path::Path, interner: &Interner) -> String {
         match &self {
             HelpDiagnostic::Ty(d) => ty_diagnostic_help_message(d, mod_dir, interner),
+            HelpDiagnostic::BreakTy { ty, .. } => {
+                format!("this returns a `{}`", display_ty(ty, mod_dir, interner))
+            }
+        }
+    }
+}
+
+/// Problems with the entry point aren't tied to a single range of text,
+/// so instead of a snippet, the location of every candidate gets listed
+pub fn display_entry_point_error<'a>(
+    error: &hir::EntryPointError,
+    world_index: &hir::WorldIndex,
+    line_index: impl Fn(hir::FileName) -> &'a LineIndex,
+    mod_dir: &std::path::Path,
+    interner: &Interner,
+    with_colors: bool,
+) -> Vec<String> {
+    let (ansi_reset, ansi_red, ansi_white, ansi_blue, ansi_gray) = if with_colors {
+        (
+            "\x1B[0m",
+            "\x1B[1;91m",
+            "\x1B[1;97m",
+            "\x1B[1;94m",
+            "\x1B[90m",
+        )
+    } else {
+        ("", "", "", "", "")
+    };
+
+    let (message, name, candidates) = match error {
+        hir::EntryPointError::Missing { name } => (
+            format!("there is no `{}` function", interner.lookup(name.0)),
+            *name,
+            [].as_slice(),
+        ),
+        hir::EntryPointError::Multiple { candidates } => (
+            format!(
+                "there are multiple `{}` functions, but a program can only have one entry point",
+                interner.lookup(candidates[0].name.0)
+            ),
+            candidates[0].name,
+            candidates.as_slice(),
+        ),
+    };
+
+    let mut lines = vec![format!(
+        "{}error{}: {}{}",
+        ansi_red, ansi_white, message, ansi_reset
+    )];
+
+    for candidate in candidates {
+        let range = world_index.range_info(*candidate).name;
+        let (line, col) = line_index(candidate.file).line_col(range.start());
+
+        lines.push(format!(
+            "{}  --> at {}:{}:{}{}",
+            ansi_gray,
+            candidate.file.to_string(mod_dir, interner),
+            line.0 + 1,
+            col.0 + 1,
+            ansi_reset,
+        ));
+    }
+
+    let name = interner.lookup(name.0);
+    lines.push(format!(
+        "{}note{}: the entry point should look like `{} :: () {{ ... }}` or `{} :: () -> i32 {{ ... }}`{}",
+        ansi_blue, ansi_white, name, name, ansi_reset
+    ));
+
+    lines
+}
+
+/// Anything narrower than this isn't worth wrapping or truncating for
+const MIN_WIDTH: usize = 20;
+
+/// Breaks the text up at spaces so that no line goes past `width`.
+/// The lines after the first are indented to line up with the first one
+fn wrap(text: &str, indent: usize, width: Option<usize>) -> String {
+    let Some(available) = width
+        .map(|width| width.saturating_sub(indent))
+        .filter(|available| *available >= MIN_WIDTH)
+    else {
+        return text.to_string();
+    };
+
+    let mut result = String::new();
+    for (idx, text_line) in text.split('\n').enumerate() {
+        if idx > 0 {
+            result.push('\n');
+            result.push_str(&" ".repeat(indent));
+        }
+
+        let mut line_len = 0;
+        for word in text_line.split(' ') {
+            let word_len = word.chars().count();
+            if line_len > 0 && line_len + 1 + word_len > available {
+                result.push('\n');
+                result.push_str(&" ".repeat(indent));
+                line_len = 0;
+            } else if line_len > 0 {
+                result.push(' ');
+                line_len += 1;
+            }
+            result.push_str(word);
+            line_len += word_len;
+        }
+    }
+
+    result
+}
+
+/// How many columns the text takes up once tabs have been replaced with four spaces
+fn display_width(text: &str) -> usize {
+    text.chars().map(|ch| if ch == '\t' { 4 } else { 1 }).sum()
+}
+
+/// The bytes of the line which fit completely within the given columns
+fn byte_range_of_cols(line: &str, cols: Range<usize>) -> Range<usize> {
+    let mut col = 0;
+    let mut start = None;
+    let mut end = line.len();
+    for (idx, ch) in line.char_indices() {
+        if start.is_none() && col >= cols.start {
+            start = Some(idx);
+        }
+        let ch_width = display_width(ch.encode_utf8(&mut [0; 4]));
+        if col + ch_width > cols.end {
+            end = idx;
+            break;
         }
+        col += ch_width;
     }
+
+    start.unwrap_or(line.len()).min(end)..end
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -233,6 +652,7 @@ fn input_snippet(
     severity: Severity,
     with_colors: bool,
     missing_arrow: bool,
+    width: Option<usize>,
 ) {
     let (ansi_reset, ansi_gray, ansi_err) = if with_colors {
         (
@@ -271,6 +691,48 @@ fn input_snippet(
 
     let line_number_padding = " ".repeat(max_digits);
 
+    // lines that are too long for the terminal all get cut down to the same columns,
+    // which start a little before the highlighted text so there's still some context
+    let window = width
+        .map(|width| width.saturating_sub(max_digits + PADDING.chars().count()))
+        .filter(|code_width| *code_width >= MIN_WIDTH)
+        .and_then(|code_width| {
+            let widest = file_lines
+                .iter()
+                .take(line_end)
+                .skip(line_start)
+                .map(|file_line| display_width(file_line))
+                .max()?;
+            if widest <= code_width {
+                return None;
+            }
+
+            let cols_until = |line: LineNr, col: usize| {
+                file_lines
+                    .get(line.0 as usize)
+                    .and_then(|file_line| file_line.get(..col))
+                    .map(display_width)
+                    .unwrap_or_default()
+            };
+            let focus_start = cols_until(start_line, start_col.0 as usize);
+            // when the highlighted text can fit, all of it should be shown
+            let focus_end = if start_line == end_line {
+                cols_until(end_line, end_col.0 as usize + 1)
+            } else {
+                focus_start
+            };
+
+            let mut start = focus_start.saturating_sub(code_width / 4);
+            if focus_end + 1 > start + code_width {
+                start = (focus_end + 1)
+                    .saturating_sub(code_width)
+                    .min(focus_start.saturating_sub(1));
+            }
+            let start = start.min(widest - code_width);
+
+            Some(start..start + code_width)
+        });
+
     lines.push(format!(
         "{}{}--> at {}:{}:{}",
         ansi_gray,
@@ -301,54 +763,70 @@ fn input_snippet(
 
         let error_line = num >= start_line.0 as usize && num <= end_line.0 as usize;
         let arrow = error_line && missing_arrow;
-        let file_line = match (num == start_line.0 as usize, num == end_line.0 as usize) {
-            (true, true) => {
-                if arrow {
-                    format!("{}{}", ansi_reset, file_line)
-                } else {
-                    format!(
-                        "{}{}{}{}{}{}",
-                        ansi_reset,
-                        &file_line[..start_col.0 as usize],
-                        ansi_err,
-                        &file_line[start_col.0 as usize..end_col.0 as usize + 1],
-                        ansi_reset,
-                        &file_line[end_col.0 as usize + 1..],
-                    )
-                }
+
+        let (visible, cut_start, cut_end) = match &window {
+            Some(window) => {
+                // lines that end before the window starts are left empty
+                let cut_start = window.start > 0 && display_width(file_line) > window.start;
+                let cut_end = display_width(file_line) > window.end;
+                let cols = window.start + cut_start as usize..window.end - cut_end as usize;
+
+                (byte_range_of_cols(file_line, cols), cut_start, cut_end)
             }
-            (true, false) => {
-                format!(
-                    "{}{}{}{}",
-                    ansi_reset,
-                    &file_line[..start_col.0 as usize],
-                    ansi_err,
-                    &file_line[start_col.0 as usize..]
-                )
+            None => (0..file_line.len(), false, false),
+        };
+        let ellipsis = |cut| {
+            if cut {
+                format!("{ansi_gray}…{ansi_reset}")
+            } else {
+                String::new()
             }
-            (false, true) => {
+        };
+
+        let highlight = match (num == start_line.0 as usize, num == end_line.0 as usize) {
+            _ if !error_line || arrow => None,
+            (true, true) => Some(start_col.0 as usize..end_col.0 as usize + 1),
+            (true, false) => Some(start_col.0 as usize..file_line.len()),
+            (false, true) => Some(0..end_col.0 as usize + 1),
+            (false, false) => Some(0..file_line.len()),
+        };
+        let code = match highlight {
+            Some(highlight) => {
+                let from = highlight.start.clamp(visible.start, visible.end);
+                let to = highlight.end.clamp(visible.start, visible.end);
+
                 format!(
-                    "{}{}{}{}",
+                    "{}{}{}{}{}{}{}",
+                    ellipsis(cut_start),
+                    &file_line[visible.start..from],
                     ansi_err,
-                    &file_line[..end_col.0 as usize + 1],
+                    &file_line[from..to],
                     ansi_reset,
-                    &file_line[end_col.0 as usize + 1..]
+                    &file_line[to..visible.end],
+                    ellipsis(cut_end),
                 )
             }
-            (false, false) if error_line => format!("{}{}", ansi_err, file_line),
-            (false, false) => format!("{}{}", ansi_reset, file_line),
+            None => format!(
+                "{}{}{}",
+                ellipsis(cut_start),
+                &file_line[visible.clone()],
+                ellipsis(cut_end),
+            ),
         };
 
         lines.push(format!(
-            "{}{:>max_digits$}{}{}{}",
+            "{}{:>max_digits$}{}{}{}{}",
             if error_line { ansi_err } else { ansi_reset },
             num + 1,
             ansi_gray,
             PADDING,
-            file_line.replace('\t', "    "),
+            ansi_reset,
+            code.replace('\t', "    "),
         ));
 
         if arrow {
+            let arrow_start = (start_col.0 as usize).clamp(visible.start, visible.end);
+
             lines.push(format!(
                 "{}{}{}{}{}{}{}",
                 ansi_reset,
@@ -356,8 +834,7 @@ fn input_snippet(
                 ansi_gray,
                 PADDING,
                 " ".repeat(
-                    start_col.0 as usize
-                        + file_line.chars().filter(|char| *char == '\t').count() * 3
+                    cut_start as usize + display_width(&file_line[visible.start..arrow_start])
                 ),
                 ansi_err,
                 "^",
@@ -407,6 +884,20 @@ fn syntax_error_message(e: &SyntaxError) -> String {
             write_expected_syntax(&mut message);
             message.push_str(&format!(" but found {}", format_node(found)));
         }
+        SyntaxErrorKind::IncrementOrDecrement { decrement, .. } => {
+            let (op, fix) = if decrement {
+                ("--", "-= 1")
+            } else {
+                ("++", "+= 1")
+            };
+            message.push_str(&format!("`{op}` doesn't exist, use `{fix}` instead"));
+        }
+        SyntaxErrorKind::AssignmentInCondition { .. } => {
+            message.push_str("`=` is assignment, did you mean `==`?");
+        }
+        SyntaxErrorKind::UnterminatedBlockComment { .. } => {
+            message.push_str("this block comment is never closed");
+        }
     }
 
     message
@@ -416,9 +907,22 @@ fn validation_diagnostic_message(d: &ValidationDiagnostic) -> String {
     match d.kind {
         ValidationDiagnosticKind::AlwaysTrue => "this is always true".to_string(),
         ValidationDiagnosticKind::AlwaysFalse => "this is always false".to_string(),
+        ValidationDiagnosticKind::LoopNeverRuns => {
+            "the condition is always false, so this loop never runs".to_string()
+        }
+        ValidationDiagnosticKind::ConstantComparison => {
+            "this compares two constants, so it always has the same result".to_string()
+        }
         ValidationDiagnosticKind::ParenInCondition => {
             "you can remove the `(` and `)`, conditions don't need parentheses".to_string()
         }
+        ValidationDiagnosticKind::IdenticalSwitchArm => {
+            "this arm has exactly the same body as an earlier arm".to_string()
+        }
+        ValidationDiagnosticKind::ConfusableChar { found, looks_like } => format!(
+            "`{}` (U+{:04X}) looks like `{}` but is a different character",
+            found, found as u32, looks_like
+        ),
     }
 }
 
@@ -433,16 +937,61 @@ fn indexing_diagnostic_message(d: &IndexingDiagnostic, interner: &Interner) -> S
 fn lowering_diagnostic_message(d: &LoweringDiagnostic, interner: &Interner) -> String {
     match &d.kind {
         LoweringDiagnosticKind::OutOfRangeIntLiteral => "integer literal out of range".to_string(),
+        LoweringDiagnosticKind::OutOfRangeFloatLiteral => "float literal out of range".to_string(),
         LoweringDiagnosticKind::UndefinedRef { name } => {
             format!("undefined reference to `{}`", interner.lookup(*name))
         }
         LoweringDiagnosticKind::UndefinedLabel { name } => {
             format!("there is no label named `{}`", interner.lookup(*name))
         }
+        LoweringDiagnosticKind::DuplicateLabel { name } => {
+            format!(
+                "there is already a surrounding label named `{}`",
+                interner.lookup(*name)
+            )
+        }
         LoweringDiagnosticKind::NonGlobalExternFunc => {
             "non-global functions cannot be extern".to_string()
         }
+        LoweringDiagnosticKind::InlineHintOnExtern => {
+            "extern functions can't be marked `@inline` or `@no_inline`".to_string()
+        }
+        LoweringDiagnosticKind::ExportNameOnExtern => {
+            "extern functions are always linked by their own name and can't be given an export name"
+                .to_string()
+        }
+        LoweringDiagnosticKind::BadExportName => {
+            "export names need a non-empty symbol name, like `@export(\"my_func\")`".to_string()
+        }
+        LoweringDiagnosticKind::UnknownAttr { name } => format!(
+            "`@{}` isn't an attribute. use `@inline`, `@no_inline`, `@export` or `@deprecated`",
+            interner.lookup(*name)
+        ),
+        LoweringDiagnosticKind::FunctionAttrOnNonFunction { name } => {
+            format!(
+                "`@{}` can only be used on functions",
+                interner.lookup(*name)
+            )
+        }
+        LoweringDiagnosticKind::DeprecatedOnLambda => {
+            "`@deprecated` goes before the name of the definition, not after the signature"
+                .to_string()
+        }
         LoweringDiagnosticKind::InvalidEscape => "invalid escape".to_string(),
+        LoweringDiagnosticKind::InvalidUnicodeEscape => {
+            "unicode escapes need one to six hex digits within braces, like `\\u{1F600}`"
+                .to_string()
+        }
+        LoweringDiagnosticKind::InvalidUnicodeChar { value } => {
+            format!("`{value:X}` isn't a valid unicode character")
+        }
+        LoweringDiagnosticKind::InvalidHexEscape => {
+            "hex escapes need exactly two hex digits, like `\\x7F`".to_string()
+        }
+        LoweringDiagnosticKind::NonAsciiHexEscapeInString => {
+            "hex escapes in strings only go up to `\\x7F`. use `\\u{...}` for other characters"
+                .to_string()
+        }
         LoweringDiagnosticKind::ImportMismatchedArgCount {
             is_mod,
             found_count,
@@ -506,6 +1055,42 @@ fn lowering_diagnostic_message(d: &LoweringDiagnostic, interner: &Interner) -> S
         LoweringDiagnosticKind::MultipleDefaultArms => {
             "a switch statement cannot have multiple default arms `_ => {}`".to_string()
         }
+        LoweringDiagnosticKind::NonGlobalStructDecl => {
+            "only global structs can contain declarations".to_string()
+        }
+        LoweringDiagnosticKind::NonGlobalModule => "modules can only be global".to_string(),
+        LoweringDiagnosticKind::OpenRangeOutsideIndex => {
+            "ranges can only leave out their start or end when slicing".to_string()
+        }
+        LoweringDiagnosticKind::InclusiveRangeWithoutEnd => {
+            "a range using `..=` needs an end".to_string()
+        }
+        LoweringDiagnosticKind::ThisWithArgs => "`#this` doesn't take any arguments".to_string(),
+        LoweringDiagnosticKind::UsingNonImport => {
+            "`using` only works with `#import` or `#mod`".to_string()
+        }
+        LoweringDiagnosticKind::NonRequirementAtRoot => {
+            "only `#requires_version` and `#requires_feature` can be used outside of a definition"
+                .to_string()
+        }
+        LoweringDiagnosticKind::RequirementBadArgs { feature } => format!(
+            "`#{}` takes a single string literal",
+            if *feature {
+                "requires_feature"
+            } else {
+                "requires_version"
+            }
+        ),
+        LoweringDiagnosticKind::InvalidVersionRequirement { requirement } => {
+            format!("`{requirement}` isn't a valid version requirement, try something like `>=0.2`")
+        }
+        LoweringDiagnosticKind::UnsupportedVersion { requirement } => format!(
+            "this file requires capy `{requirement}`, but this is capy {}",
+            hir::COMPILER_VERSION
+        ),
+        LoweringDiagnosticKind::UnsupportedFeature { feature } => {
+            format!("this version of capy doesn't support the `{feature}` feature")
+        }
     }
 }
 
@@ -515,24 +1100,42 @@ fn ty_diagnostic_message(
     interner: &Interner,
 ) -> String {
     match &d.kind {
-        hir_ty::TyDiagnosticKind::Mismatch { expected, found } => {
-            format!(
+        hir_ty::TyDiagnosticKind::Mismatch { expected, found } => match expected {
+            hir_ty::ExpectedTy::Concrete(expected) => {
+                let (expected, found) = display_ty_pair(expected, found, mod_dir, interner);
+
+                format!("expected `{}` but found `{}`", expected, found)
+            }
+            _ => format!(
                 "expected {} but found `{}`",
                 format_expected_type(expected, mod_dir, interner),
-                found.display(mod_dir, interner)
-            )
-        }
+                display_ty(found, mod_dir, interner)
+            ),
+        },
         hir_ty::TyDiagnosticKind::Uncastable { from, to } => {
+            let (from, to) = display_ty_pair(from, to, mod_dir, interner);
+
+            format!("cannot cast `{}` to `{}`", from, to)
+        }
+        hir_ty::TyDiagnosticKind::MisalignedPointerCast {
+            from,
+            to,
+            from_align,
+            to_align,
+        } => {
+            let (from, to) = display_ty_pair(from, to, mod_dir, interner);
+
             format!(
-                "cannot cast `{}` to `{}`",
-                from.display(mod_dir, interner),
-                to.display(mod_dir, interner)
+                "casting `{}` (aligned to {} bytes) to `{}` (aligned to {} bytes) might give a misaligned pointer",
+                from, from_align, to, to_align
             )
         }
         hir_ty::TyDiagnosticKind::BinaryOpMismatch { op, first, second } => {
+            let (first, second) = display_ty_pair(first, second, mod_dir, interner);
+
             format!(
                 "`{}` cannot be {} `{}`",
-                first.display(mod_dir, interner),
+                first,
                 match op {
                     hir::BinaryOp::Add => "added to",
                     hir::BinaryOp::Sub => "subtracted by",
@@ -553,7 +1156,7 @@ fn ty_diagnostic_message(
                     | hir::BinaryOp::LAnd
                     | hir::BinaryOp::LOr => "compared to",
                 },
-                second.display(mod_dir, interner)
+                second
             )
         }
         hir_ty::TyDiagnosticKind::UnaryOpMismatch { op, ty } => {
@@ -565,29 +1168,53 @@ fn ty_diagnostic_message(
                     hir::UnaryOp::BNot => '~',
                     hir::UnaryOp::LNot => '!',
                 },
-                ty.display(mod_dir, interner)
+                display_ty(ty, mod_dir, interner)
             )
         }
         hir_ty::TyDiagnosticKind::IfMismatch { first, second } => {
+            let (first, second) = display_ty_pair(first, second, mod_dir, interner);
+
             format!(
                 "the first branch is `{}` but the second branch is `{}`. they must be the same",
-                first.display(mod_dir, interner),
-                second.display(mod_dir, interner),
+                first, second,
             )
         }
         hir_ty::TyDiagnosticKind::SwitchMismatch { first, second } => {
+            let (first, second) = display_ty_pair(first, second, mod_dir, interner);
+
             format!(
                 "the first branch is `{}` but this branch is `{}`. they must be the same",
-                first.display(mod_dir, interner),
-                second.display(mod_dir, interner),
+                first, second,
+            )
+        }
+        hir_ty::TyDiagnosticKind::BreakMismatch { breaks } => {
+            format!(
+                "this block returns {} different types. they must be the same",
+                breaks.len()
             )
         }
                 hir_ty::TyDiagnosticKind::IndexNonArray { found } => {
             format!(
                 "tried indexing `[]` a non-array, `{}`",
-                found.display(mod_dir, interner)
+                display_ty(found, mod_dir, interner)
             )
         }
+        hir_ty::TyDiagnosticKind::RangeBoundNotInt { found } => {
+            format!(
+                "the start and end of a range must be integers, not `{}`",
+                display_ty(found, mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::NotIterable { found } => {
+            format!(
+                "`for` loops can only iterate over arrays, slices, and ranges, not `{}`",
+                display_ty(found, mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::DivideByZero { op } => match op {
+            hir::BinaryOp::Mod => "the remainder of dividing by zero can't be taken".to_string(),
+            _ => "integers can't be divided by zero".to_string(),
+        },
         hir_ty::TyDiagnosticKind::IndexOutOfBounds {
             index,
             actual_size,
@@ -597,18 +1224,40 @@ fn ty_diagnostic_message(
                 Some(up_to) => format!(
                     "index `[{}]` is too big, `{}` can only be indexed up to `[{}]`",
                     index,
-                    array_ty.display(mod_dir, interner),
+                    display_ty(array_ty, mod_dir, interner),
                     up_to,
                 ),
                 None => format!(
                     "index `[{}]` is too big for `{}`",
                     index,
-                    array_ty.display(mod_dir, interner),
+                    display_ty(array_ty, mod_dir, interner),
+                )
+            }
+        }
+        hir_ty::TyDiagnosticKind::DimensionOutOfBounds {
+            index,
+            actual_size,
+            dimension,
+            array_ty,
+        } => {
+            match actual_size.checked_sub(1) {
+                Some(up_to) => format!(
+                    "index `[{}]` is too big, dimension {} of `{}` can only be indexed up to `[{}]`",
+                    index,
+                    dimension,
+                    display_ty(array_ty, mod_dir, interner),
+                    up_to,
+                ),
+                None => format!(
+                    "index `[{}]` is too big for dimension {} of `{}`",
+                    index,
+                    dimension,
+                    display_ty(array_ty, mod_dir, interner),
                 )
             }
         }
         hir_ty::TyDiagnosticKind::ExtraArg { found } => {
-            format!("found an extra argument of type `{}`", found.display(mod_dir, interner))
+            format!("found an extra argument of type `{}`", display_ty(found, mod_dir, interner))
         }
         hir_ty::TyDiagnosticKind::MissingArg { expected } => {
             format!("expected an argument of type {}", format_expected_type(expected, mod_dir, interner))
@@ -616,13 +1265,38 @@ fn ty_diagnostic_message(
         hir_ty::TyDiagnosticKind::CalledNonFunction { found } => {
             format!(
                 "expected a function, but found {}",
-                found.display(mod_dir, interner),
+                display_ty(found, mod_dir, interner),
+            )
+        }
+        hir_ty::TyDiagnosticKind::NoMatchingOverload { function, found } => {
+            format!(
+                "no overload of `{}` takes the arguments ({})",
+                interner.lookup(function.name.0),
+                found
+                    .iter()
+                    .map(|ty| display_ty(ty, mod_dir, interner))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+        }
+        hir_ty::TyDiagnosticKind::AmbiguousOverload {
+            function,
+            candidates,
+        } => {
+            format!(
+                "this call to `{}` could go to any of {}",
+                interner.lookup(function.name.0),
+                candidates
+                    .iter()
+                    .map(|ty| format!("`{}`", display_ty(ty, mod_dir, interner)))
+                    .collect::<Vec<_>>()
+                    .join(", "),
             )
         }
         hir_ty::TyDiagnosticKind::DerefNonPointer { found } => {
             format!(
                 "tried dereferencing `^` a non-pointer, `{}`",
-                found.display(mod_dir, interner)
+                display_ty(found, mod_dir, interner)
             )
         }
         hir_ty::TyDiagnosticKind::DerefRaw => {
@@ -633,7 +1307,7 @@ fn ty_diagnostic_message(
         hir_ty::TyDiagnosticKind::MissingElse { expected } => {
             format!(
                 "this `if` is missing an `else` with type `{}`",
-                expected.display(mod_dir, interner)
+                display_ty(expected, mod_dir, interner)
             )
         }
         hir_ty::TyDiagnosticKind::NotYetResolved { fqn } => {
@@ -651,11 +1325,16 @@ fn ty_diagnostic_message(
         hir_ty::TyDiagnosticKind::MutableRefToImmutableData => {
             "cannot get a `^mut` to immutable data".to_string()
         }
-        hir_ty::TyDiagnosticKind::IntTooBigForType { found, max, ty } => {
+        hir_ty::TyDiagnosticKind::IntTooBigForType {
+            found,
+            written,
+            max,
+            ty,
+        } => {
             format!(
                 "integer literal `{}` is too big for `{}`, which can only hold up to {}",
-                found,
-                ty.display(mod_dir, interner),
+                written.clone().unwrap_or_else(|| found.to_string()),
+                display_ty(ty, mod_dir, interner),
                 max
             )
         }
@@ -665,29 +1344,82 @@ fn ty_diagnostic_message(
                 file.to_string(mod_dir, interner)
             )
         }
+        hir_ty::TyDiagnosticKind::Deprecated { fqn, message } => match message {
+            Some(message) => format!(
+                "`{}` is deprecated: {}",
+                interner.lookup(fqn.name.0),
+                interner.lookup(*message)
+            ),
+            None => format!("`{}` is deprecated", interner.lookup(fqn.name.0)),
+        },
         hir_ty::TyDiagnosticKind::UnknownFqn { fqn } => format!(
             "`{}` does not exist within the file `{}`",
             interner.lookup(fqn.name.0),
             fqn.file.to_string(mod_dir, interner)
         ),
+        hir_ty::TyDiagnosticKind::UsingAlreadyDefined { fqn, defined_in } => format!(
+            "`{}` from `{}` is already defined in `{}`",
+            interner.lookup(fqn.name.0),
+            fqn.file.to_string(mod_dir, interner),
+            defined_in.to_string(mod_dir, interner)
+        ),
         hir_ty::TyDiagnosticKind::NonExistentMember { member, found_ty } => format!(
             "there is no member named `{}` within `{}`",
             interner.lookup(*member),
-            found_ty.display(mod_dir, interner)
+            display_ty(found_ty, mod_dir, interner)
+        ),
+        hir_ty::TyDiagnosticKind::AmbiguousMethod { method, candidates } => format!(
+            "`.{}` could refer to any of {}",
+            interner.lookup(*method),
+            candidates
+                .iter()
+                .map(|fqn| format!("`{}`", fqn.to_string(mod_dir, interner)))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        hir_ty::TyDiagnosticKind::MemberOfCollection {
+            member,
+            collection_ty,
+            element_ty,
+        } => format!(
+            "there is no member named `{}` within `{}`, but its elements of type `{}` have one",
+            interner.lookup(*member),
+            display_ty(collection_ty, mod_dir, interner),
+            display_ty(element_ty, mod_dir, interner),
         ),
         hir_ty::TyDiagnosticKind::NonExistentVariant { variant_name, enum_ty: scrutinee_ty } => format!(
             "there is no variant named `{}` within `{}`",
             interner.lookup(*variant_name),
-            scrutinee_ty.display(mod_dir, interner),
+            display_ty(scrutinee_ty, mod_dir, interner),
         ),
         hir_ty::TyDiagnosticKind::StructLiteralMissingMember { member: field, expected_ty } => format!(
             "`{}` struct literal is missing the member `{}`",
-            expected_ty.display(mod_dir, interner),
+            display_ty(expected_ty, mod_dir, interner),
             interner.lookup(*field)
         ),
         hir_ty::TyDiagnosticKind::ComptimePointer => {
             "comptime blocks cannot return pointers. the data won't exist at runtime".to_string()
         }
+        hir_ty::TyDiagnosticKind::ComptimeExtern { function } => format!(
+            "comptime blocks cannot call the extern function `{}`, since it could make each build different",
+            interner.lookup(function.name.0)
+        ),
+        hir_ty::TyDiagnosticKind::ComptimeLimitExceeded { limit } => match limit {
+            hir_ty::ComptimeLimit::Time(time) => format!(
+                "comptime evaluation exceeded limit, this ran for longer than {}s",
+                time.as_secs_f64()
+            ),
+            hir_ty::ComptimeLimit::Memory(bytes) => format!(
+                "comptime evaluation exceeded limit, this allocated more than {bytes} bytes"
+            ),
+            hir_ty::ComptimeLimit::Exit(status) => format!(
+                "comptime evaluation stopped, this called `exit({status})`"
+            ),
+            hir_ty::ComptimeLimit::Extern => {
+                "comptime evaluation stopped, this called an extern function which isn't available at compile time"
+                    .to_string()
+            }
+        },
         hir_ty::TyDiagnosticKind::GlobalNotConst => {
             "globals must be constant values. try wrapping this in `comptime { ... }`".to_string()
         }
@@ -706,6 +1438,9 @@ fn ty_diagnostic_message(
         hir_ty::TyDiagnosticKind::ArraySizeNotConst => {
             "array size must be known at compile-time".to_string()
         }
+        hir_ty::TyDiagnosticKind::ArraySizeNotInferred => {
+            "`_` can only be used as the size of an array type annotating an array".to_string()
+        }
         hir_ty::TyDiagnosticKind::DiscriminantNotInt => {
             "discriminants must be an integer".to_string()
         }
@@ -719,20 +1454,264 @@ fn ty_diagnostic_message(
             "external globals must have a type annotation".to_string()
         }
         hir_ty::TyDiagnosticKind::DeclTypeHasNoDefault { ty } => {
-            format!("`{}` does not have a default value. one must be supplied", ty.display(mod_dir, interner))
+            format!("`{}` does not have a default value. one must be supplied", display_ty(ty, mod_dir, interner))
         }
         hir_ty::TyDiagnosticKind::SwitchDoesNotCoverVariant { ty  } => {
-            format!("this switch statement does not have an arm for `{}`", ty.display(mod_dir, interner))
+            format!("this switch statement does not have an arm for `{}`", display_ty(ty, mod_dir, interner))
         }
         hir_ty::TyDiagnosticKind::SwitchAlreadyCoversVariant { ty  } => {
-            format!("this switch statement already has an arm for `{}`", ty.display(mod_dir, interner))
+            format!("this switch statement already has an arm for `{}`", display_ty(ty, mod_dir, interner))
+        }
+        hir_ty::TyDiagnosticKind::StructLiteralOutOfOrder { expected_ty, .. } => {
+            format!(
+                "the members of this struct literal aren't in the same order as they are in `{}`",
+                display_ty(expected_ty, mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::UnreachableDefault => {
+            "every variant already has an arm, so this default arm is unreachable".to_string()
+        }
+        hir_ty::TyDiagnosticKind::SelfComparison => {
+            "this compares a value to itself, so it always has the same result".to_string()
+        }
+        hir_ty::TyDiagnosticKind::LargeArrayCopy { ty, size } => {
+            format!("this copies all {} bytes of `{}`", size, display_ty(ty, mod_dir, interner))
         }
         hir_ty::TyDiagnosticKind::ImpossibleToDifferentiateVarArgs { previous_ty, current_ty } => {
-            format!("the type of this parameter, `{}`, cannot be differentiated from the var arg parameter right behind it, `...{}`", current_ty.display(mod_dir, interner), previous_ty.display(mod_dir, interner))
+            let (current_ty, previous_ty) = display_ty_pair(current_ty, previous_ty, mod_dir, interner);
+            format!("the type of this parameter, `{}`, cannot be differentiated from the var arg parameter right behind it, `...{}`", current_ty, previous_ty)
+        }
+        hir_ty::TyDiagnosticKind::UnwrapVariantMismatchEnum { variant_ty, enum_ty } => {
+            let (variant_ty, enum_ty) = display_ty_pair(variant_ty, enum_ty, mod_dir, interner);
+            format!("the variant type `{}` is not a variant of `{}`", variant_ty, enum_ty)
+        }
+        hir_ty::TyDiagnosticKind::EnumToVariant { enum_ty, variant_ty } => {
+            let (enum_ty, variant_ty) = display_ty_pair(enum_ty, variant_ty, mod_dir, interner);
+            format!("expected the variant `{}` but found the enum `{}`", variant_ty, enum_ty)
+        }
+        hir_ty::TyDiagnosticKind::VariantOfOtherEnum { variant_ty, enum_ty } => {
+            let (variant_ty, enum_ty) = display_ty_pair(variant_ty, enum_ty, mod_dir, interner);
+            format!("`{}` is not a variant of `{}`", variant_ty, enum_ty)
+        }
+        hir_ty::TyDiagnosticKind::NullToPointer { ty } => {
+            format!(
+                "`null` can't be used as the pointer type `{}`",
+                ty.display(mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::TailCallNotCall => "`#tailcall` expects a function call".to_string(),
+        hir_ty::TyDiagnosticKind::TailCallToOtherFunction => {
+            "`#tailcall` can only call the function it's in".to_string()
+        }
+        hir_ty::TyDiagnosticKind::TailCallWithVarArgs => {
+            "`#tailcall` can't be used on functions with variadic parameters".to_string()
+        }
+        hir_ty::TyDiagnosticKind::IntrinsicMismatch { name, expected, found } => {
+            let expected = match expected {
+                hir_ty::IntrinsicOperands::Floats => "floats",
+                hir_ty::IntrinsicOperands::Ints => "integers",
+                hir_ty::IntrinsicOperands::Numbers => "integers and floats",
+            };
+            format!("`#{}` only works on {}, but found `{}`", interner.lookup(*name), expected, display_ty(found, mod_dir, interner))
         }
-        hir_ty::TyDiagnosticKind::UnwrapVariantMismatchEnum { variant_ty, enum_ty } => format!("the variant type `{}` is not a variant of `{}`", variant_ty.display(mod_dir, interner), enum_ty.display(mod_dir, interner)),
         hir_ty::TyDiagnosticKind::UnknownDirective { name } => format!("there is no directive named `#{}`", interner.lookup(*name)),
+        hir_ty::TyDiagnosticKind::ConstParamNotUsize { found } => {
+            format!(
+                "parameters used in the types of other parameters must be `usize`, but found `{}`",
+                display_ty(found, mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::ConstArgNotConst => {
+            "this argument must be known at compile-time, since its parameter is used in a type"
+                .to_string()
+        }
+        hir_ty::TyDiagnosticKind::ConstParamFnNotCalled { fqn } => {
+            format!(
+                "`{}` has compile-time parameters, so it can only be called directly",
+                interner.lookup(fqn.name.0)
+            )
+        }
+        hir_ty::TyDiagnosticKind::LambdaInConstParamFn => {
+            "functions with compile-time parameters can't contain lambdas".to_string()
+        }
+        hir_ty::TyDiagnosticKind::ComptimeInConstParamFn => {
+            "functions with compile-time parameters can't contain `comptime` blocks".to_string()
+        }
+        hir_ty::TyDiagnosticKind::LocalConstParamFn => {
+            "only global functions can have compile-time parameters".to_string()
+        }
+        hir_ty::TyDiagnosticKind::DoesNotImplement {
+            ty,
+            interface,
+            method,
+            expected,
+            found: None,
+        } => {
+            format!(
+                "`{}` can't be used as `{}`, since there's no `{} :: {}`",
+                display_ty(ty, mod_dir, interner),
+                display_ty(interface, mod_dir, interner),
+                interner.lookup(*method),
+                display_ty(expected, mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::DoesNotImplement {
+            ty,
+            interface,
+            method,
+            expected,
+            found: Some(found),
+        } => {
+            format!(
+                "`{}` can't be used as `{}`, since `{}` should be `{}` but found `{}`",
+                display_ty(ty, mod_dir, interner),
+                display_ty(interface, mod_dir, interner),
+                interner.lookup(*method),
+                display_ty(expected, mod_dir, interner),
+                display_ty(found, mod_dir, interner)
+            )
+        }
+        hir_ty::TyDiagnosticKind::InterfaceMethodNotCalled { method } => {
+            format!(
+                "`{}` is the method of an interface, so it can only be called directly",
+                interner.lookup(*method)
+            )
+        }
+        hir_ty::TyDiagnosticKind::EscapingClosure { returned: true } => {
+            "closures can't be returned from the function that made them, since that's where their captured values live"
+                .to_string()
+        }
+        hir_ty::TyDiagnosticKind::EscapingClosure { returned: false } => {
+            "closures can't be stored outside of the function that made them, since that's where their captured values live"
+                .to_string()
+        }
+    }
+}
+
+fn ty_diagnostic_note(
+    d: &TyDiagnostic,
+    mod_dir: &std::path::Path,
+    interner: &Interner,
+) -> Option<String> {
+    let mentioned_tys = match &d.kind {
+        hir_ty::TyDiagnosticKind::Mismatch {
+            expected: ExpectedTy::Concrete(expected),
+            found,
+        } => vec![*expected, *found],
+        hir_ty::TyDiagnosticKind::Mismatch { found, .. } => vec![*found],
+        hir_ty::TyDiagnosticKind::Uncastable { from, to } => vec![*from, *to],
+        hir_ty::TyDiagnosticKind::BinaryOpMismatch { first, second, .. }
+        | hir_ty::TyDiagnosticKind::IfMismatch { first, second }
+        | hir_ty::TyDiagnosticKind::SwitchMismatch { first, second } => vec![*first, *second],
+        hir_ty::TyDiagnosticKind::MisalignedPointerCast { .. } => {
+            return Some(
+                "use `#align_cast(ptr, T)` to check the alignment when the program runs"
+                    .to_string(),
+            );
+        }
+        hir_ty::TyDiagnosticKind::StructLiteralOutOfOrder {
+            side_effect_free: false,
+            ..
+        } => {
+            return Some(
+                "some of these values might do something when evaluated, \
+                 so `--fix` won't reorder them"
+                    .to_string(),
+            );
+        }
+        hir_ty::TyDiagnosticKind::LargeArrayCopy { .. } => {
+            return Some(
+                "use a pointer (`^array`) or a slice (`array[..]`) to avoid the copy".to_string(),
+            );
+        }
+        hir_ty::TyDiagnosticKind::EnumToVariant { .. } => {
+            return Some(
+                "an enum could be holding any of its variants. \
+                 use `#unwrap` or a `switch` to get a specific variant out of it"
+                    .to_string(),
+            )
+        }
+        hir_ty::TyDiagnosticKind::NullToPointer { .. } => {
+            return Some(
+                "typed pointers always point to something. \
+                 use `?^T` or `rawptr` for pointers that can be `null`"
+                    .to_string(),
+            )
+        }
+        hir_ty::TyDiagnosticKind::TailCallToOtherFunction => {
+            return Some(
+                "tail calls are compiled as a jump back to the start of the current function"
+                    .to_string(),
+            )
+        }
+        _ => return None,
+    };
+
+    if let [first, second] = mentioned_tys.as_slice() {
+        if let Some(note) = distinct_sub_ty_note(first, second, mod_dir, interner) {
+            return Some(note);
+        }
     }
+
+    if let Some(optional) = mentioned_tys.iter().find(|ty| ty.as_optional().is_some()) {
+        return Some(format!(
+            "`{}` might be `null`. use `orelse` to give a fallback value, \
+             or `#unwrap` if it can never be `null`",
+            display_ty(optional, mod_dir, interner)
+        ));
+    }
+
+    let (kind, default) = mentioned_tys.iter().find_map(|ty| {
+        let default = ty.weak_default()?;
+        let kind = if ty.is_float() {
+            "{float}"
+        } else {
+            "{integer}"
+        };
+        Some((kind, default))
+    })?;
+
+    let default = display_ty(&default, mod_dir, interner);
+
+    Some(format!(
+        "`{kind}` is the type of a literal that hasn't been given a specific type yet. \
+         if nothing else decides its type, it becomes `{default}`. \
+         try adding a type annotation like `x : {default} = ...;` or casting it like `{default}.(...)`"
+    ))
+}
+
+/// Explains why a distinct type won't mix with its own sub type, like `Id` and `u32` for
+/// `Id :: distinct u32`
+fn distinct_sub_ty_note(
+    first: &hir_ty::Ty,
+    second: &hir_ty::Ty,
+    mod_dir: &std::path::Path,
+    interner: &Interner,
+) -> Option<String> {
+    let (distinct, sub_ty) = [(first, second), (second, first)].into_iter().find(
+        |(distinct, other)| match distinct {
+            hir_ty::Ty::Distinct { sub_ty, .. } => {
+                sub_ty.is_functionally_equivalent_to(other, true)
+            }
+            _ => false,
+        },
+    )?;
+
+    // these are the only places where a distinct is treated like its sub type
+    let shared = if sub_ty.is_int() {
+        " and it can index into arrays just like one,"
+    } else if matches!(sub_ty.absolute_ty(), hir_ty::Ty::Enum { .. }) {
+        " and it can be switched on just like one,"
+    } else {
+        ","
+    };
+
+    let (distinct, sub_ty) = display_ty_pair(distinct, sub_ty, mod_dir, interner);
+
+    Some(format!(
+        "`{distinct}` is a distinct version of `{sub_ty}`{shared} \
+         but otherwise the two don't mix. cast it like `{sub_ty}.(...)` or `{distinct}.(...)` first"
+    ))
 }
 
 fn ty_diagnostic_help_message(
@@ -757,12 +1736,15 @@ fn ty_diagnostic_help_message(
             "parameters are immutable".to_string()
         }
         hir_ty::TyDiagnosticHelpKind::ImmutableGlobal => "globals are immutable".to_string(),
+        hir_ty::TyDiagnosticHelpKind::ImmutableCapture => {
+            "closures only have a copy of what they capture. consider capturing a `^mut`".to_string()
+        }
         hir_ty::TyDiagnosticHelpKind::NotMutatingRefThroughDeref => {
             "this is a reference, to mutate it's inner value add a `^` at the end to dereference it first"
                 .to_string()
         }
         hir_ty::TyDiagnosticHelpKind::IfReturnsTypeHere { found } => {
-            format!("here, the `if` returns a {}", found.display(mod_dir, interner))
+            format!("here, the `if` returns a {}", display_ty(found, mod_dir, interner))
         }
         hir_ty::TyDiagnosticHelpKind::MutableVariable => {
             "`:=` bindings are immutable. consider changing it to `::`".to_string()
@@ -771,7 +1753,13 @@ fn ty_diagnostic_help_message(
             "this is the actual value that is being returned".to_string()
         }
         hir_ty::TyDiagnosticHelpKind::BreakHere { break_ty } => {
-            format!("expected because this break returns a `{}`", break_ty.display(mod_dir, interner))
+            format!("expected because this break returns a `{}`", display_ty(break_ty, mod_dir, interner))
+        }
+        hir_ty::TyDiagnosticHelpKind::IndexCollectionFirst { element_ty } => {
+            format!(
+                "index into this first to get a single `{}`. mapping over every element isn't supported yet",
+                display_ty(element_ty, mod_dir, interner)
+            )
         }
     }
 }
@@ -859,6 +1847,47 @@ fn format_node(kind: NodeKind) -> &'static str {
     }
 }
 
+/// Shows a type in the least verbose way possible
+fn display_ty(ty: &hir_ty::Ty, mod_dir: &std::path::Path, interner: &Interner) -> String {
+    ty.display_with(mod_dir, interner).short().to_string()
+}
+
+/// Shows two types in the least verbose way that still makes it clear that they're different.
+///
+/// e.g. if two different files both define `Point`, they'll be shown as `geometry::Point` and
+/// `shapes::Point` instead of `Point` and `Point`
+fn display_ty_pair(
+    first: &hir_ty::Ty,
+    second: &hir_ty::Ty,
+    mod_dir: &std::path::Path,
+    interner: &Interner,
+) -> (String, String) {
+    let mut res = (String::new(), String::new());
+
+    for verbosity in [
+        TyVerbosity::Short,
+        TyVerbosity::Qualified,
+        TyVerbosity::Structural,
+    ] {
+        res = (
+            first
+                .display_with(mod_dir, interner)
+                .verbosity(verbosity)
+                .to_string(),
+            second
+                .display_with(mod_dir, interner)
+                .verbosity(verbosity)
+                .to_string(),
+        );
+
+        if res.0 != res.1 || first == second {
+            break;
+        }
+    }
+
+    res
+}
+
 fn format_expected_type(
     expected: &ExpectedTy,
     mod_dir: &std::path::Path,
@@ -866,9 +1895,340 @@ fn format_expected_type(
 ) -> String {
     match expected {
         hir_ty::ExpectedTy::Concrete(expected) => {
-            format!("`{}`", expected.display(mod_dir, interner))
+            format!("`{}`", display_ty(expected, mod_dir, interner))
         }
         hir_ty::ExpectedTy::Enum => "an enum".to_string(),
         hir_ty::ExpectedTy::Variant => "a variant".to_string(),
+        hir_ty::ExpectedTy::Pointer => "a pointer".to_string(),
+        hir_ty::ExpectedTy::Optional => "an optional".to_string(),
+        hir_ty::ExpectedTy::Function => "a function".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ast::AstNode;
+    use expect_test::{expect, Expect};
+
+    use super::*;
+
+    fn check_snippet(input: &str, range: Range<u32>, width: Option<usize>, expect: Expect) {
+        let line_index = LineIndex::new(input);
+        let (start_line, start_col) = line_index.line_col(range.start.into());
+        let (end_line, end_col) = line_index.line_col((range.end - 1).into());
+
+        let mut lines = Vec::new();
+        input_snippet(
+            "main.capy",
+            input,
+            start_line,
+            start_col,
+            end_line,
+            end_col,
+            &mut lines,
+            Severity::Error,
+            false,
+            false,
+            width,
+        );
+
+        expect.assert_eq(&lines.join("\n"));
+    }
+
+    #[test]
+    fn snippet_fits() {
+        check_snippet(
+            "foo :: () {\n    x := 1 + true;\n}",
+            20..28,
+            Some(40),
+            expect![[r#"
+                 --> at main.capy:2:9
+
+                1 │ foo :: () {
+                2 │     x := 1 + true;
+                3 │ }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn snippet_truncated_around_highlight() {
+        check_snippet(
+            "foo :: () {\n    a_very_long_name_that_goes_on_and_on := 1 + true + 3 + 4 + 5 + 6 + 7;\n}",
+            57..65,
+            Some(32),
+            expect![[r#"
+                 --> at main.capy:2:46
+
+                1 │ 
+                2 │ …n := 1 + true + 3 + 4 + 5 …
+                3 │ 
+            "#]],
+        );
+    }
+
+    #[test]
+    fn snippet_not_truncated_without_width() {
+        check_snippet(
+            "foo :: () {\n    a_very_long_name_that_goes_on_and_on := 1 + true + 3 + 4 + 5 + 6 + 7;\n}",
+            57..65,
+            None,
+            expect![[r#"
+                 --> at main.capy:2:46
+
+                1 │ foo :: () {
+                2 │     a_very_long_name_that_goes_on_and_on := 1 + true + 3 + 4 + 5 + 6 + 7;
+                3 │ }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn wrap_message() {
+        expect![[r#"
+            a message which is long enough
+                   that it has to be wrapped onto
+                   a few separate lines"#]]
+        .assert_eq(&wrap(
+            "a message which is long enough that it has to be wrapped onto a few separate lines",
+            7,
+            Some(37),
+        ));
+    }
+
+    #[test]
+    fn lint_levels() {
+        let always_true = || {
+            Diagnostic::from_validation(ValidationDiagnostic {
+                kind: ValidationDiagnosticKind::AlwaysTrue,
+                range: TextRange::new(0.into(), 4.into()),
+            })
+        };
+
+        let mut diagnostic = always_true();
+        assert_eq!(diagnostic.lint(), Some("always_true"));
+        assert!(diagnostic.severity() == Severity::Warning);
+
+        diagnostic.set_lint_level(LintLevel::Deny);
+        assert!(diagnostic.severity() == Severity::Error);
+        assert!(!diagnostic.is_allowed());
+
+        let mut diagnostic = always_true();
+        diagnostic.set_lint_level(LintLevel::Allow);
+        assert!(diagnostic.is_allowed());
+
+        // errors aren't lints, so they can't be allowed
+        let mut diagnostic = Diagnostic::from_indexing(IndexingDiagnostic {
+            kind: IndexingDiagnosticKind::AlreadyDefined {
+                name: Interner::default().intern("foo"),
+            },
+            range: TextRange::new(0.into(), 3.into()),
+        });
+        assert_eq!(diagnostic.lint(), None);
+        diagnostic.set_lint_level(LintLevel::Allow);
+        assert!(!diagnostic.is_allowed());
+        assert!(diagnostic.severity() == Severity::Error);
+    }
+
+    /// `files` are the names and contents of every file in the program
+    fn check_entry_point_error(files: &[(&str, &str)], expect: Expect) {
+        let mut interner = Interner::default();
+        let mut world_index = hir::WorldIndex::default();
+        let mut line_indexes = std::collections::HashMap::new();
+
+        for (file_name, text) in files {
+            let parse = parser::parse_source_file(&lexer::lex(text), text);
+            let tree = parse.syntax_tree();
+            let root = ast::Root::cast(tree.root(), tree).unwrap();
+            let (index, _) = hir::index(root, tree, &mut interner);
+
+            let file = hir::FileName(interner.intern(file_name));
+            world_index.add_file(file, index);
+            line_indexes.insert(file, LineIndex::new(text));
+        }
+
+        let error = world_index
+            .entry_point(hir::Name(interner.intern("main")))
+            .unwrap_err();
+
+        expect.assert_eq(
+            &display_entry_point_error(
+                &error,
+                &world_index,
+                |file| &line_indexes[&file],
+                std::path::Path::new(""),
+                &interner,
+                false,
+            )
+            .join("\n"),
+        );
+    }
+
+    #[test]
+    fn entry_point_missing() {
+        check_entry_point_error(
+            &[("main.capy", "foo :: () {}")],
+            expect![[r#"
+                error: there is no `main` function
+                note: the entry point should look like `main :: () { ... }` or `main :: () -> i32 { ... }`"#]],
+        );
+    }
+
+    #[test]
+    fn entry_point_multiple() {
+        check_entry_point_error(
+            &[
+                ("main.capy", "main :: () {}"),
+                ("other.capy", "foo :: () {}\n\nmain :: () -> i32 { 0 }"),
+            ],
+            expect![[r#"
+                error: there are multiple `main` functions, but a program can only have one entry point
+                  --> at main:1:1
+                  --> at other:3:1
+                note: the entry point should look like `main :: () { ... }` or `main :: () -> i32 { ... }`"#]],
+        );
+    }
+
+    #[test]
+    fn ty_pair_with_the_same_name() {
+        let mut interner = Interner::default();
+        let mut point = |file: &str| hir_ty::Ty::Struct {
+            anonymous: false,
+            fqn: Some(hir::Fqn {
+                file: hir::FileName(interner.intern(file)),
+                name: hir::Name(interner.intern("Point")),
+            }),
+            uid: 0,
+            members: Vec::new(),
+        };
+        let geometry = point("geometry.capy");
+        let shapes = point("shapes.capy");
+        let mod_dir = std::path::Path::new("/mod");
+
+        assert_eq!(
+            display_ty_pair(&geometry, &hir_ty::Ty::IInt(32), mod_dir, &interner),
+            ("Point".to_string(), "i32".to_string())
+        );
+        assert_eq!(
+            display_ty_pair(&geometry, &shapes, mod_dir, &interner),
+            ("geometry::Point".to_string(), "shapes::Point".to_string())
+        );
+        assert_eq!(
+            display_ty_pair(&geometry, &geometry, mod_dir, &interner),
+            ("Point".to_string(), "Point".to_string())
+        );
+    }
+
+    /// `members` are the `name = value` members in the order the struct declares them
+    fn check_member_order_fix(
+        input: &str,
+        members: &[&str],
+        side_effect_free: bool,
+        expect: Expect,
+    ) {
+        let reordered = members
+            .iter()
+            .map(|member| {
+                let start = input.find(member).unwrap() as u32;
+                TextRange::new(start.into(), (start + member.len() as u32).into())
+            })
+            .collect();
+
+        let diagnostic = Diagnostic::from_ty(TyDiagnostic {
+            kind: hir_ty::TyDiagnosticKind::StructLiteralOutOfOrder {
+                expected_ty: hir_ty::Ty::Unknown.into(),
+                reordered,
+                side_effect_free,
+            },
+            file: hir::FileName(Interner::default().intern("main.capy")),
+            expr: None,
+            range: TextRange::new(0.into(), (input.len() as u32).into()),
+            help: None,
+        });
+
+        let fixed = match diagnostic.fix(input) {
+            Some(fix) => {
+                let mut fixed = input.to_string();
+                fixed.replace_range(std::ops::Range::<usize>::from(fix.range), &fix.replacement);
+                fixed
+            }
+            None => "no fix".to_string(),
+        };
+
+        expect.assert_eq(&fixed);
+    }
+
+    #[test]
+    fn member_order_fix_one_line() {
+        check_member_order_fix(
+            "p := Point.{ y = 2, x = 1 };",
+            &["x = 1", "y = 2"],
+            true,
+            expect![[r#"p := Point.{ x = 1, y = 2 };"#]],
+        );
+    }
+
+    #[test]
+    fn member_order_fix_moves_comments() {
+        check_member_order_fix(
+            r#"
+p := Point.{
+    // the second one
+    y = 2,
+    x = 1, // one
+    z = 3
+};"#,
+            &["x = 1", "y = 2", "z = 3"],
+            true,
+            expect![[r#"
+
+                p := Point.{
+                    x = 1, // one
+                    // the second one
+                    y = 2,
+                    z = 3
+                };"#]],
+        );
+    }
+
+    #[test]
+    fn member_order_fix_moves_comment_onto_last_line() {
+        check_member_order_fix(
+            r#"
+p := Point.{
+    y = 2, // two
+    x = 1
+};"#,
+            &["x = 1", "y = 2"],
+            true,
+            expect![[r#"
+
+                p := Point.{
+                    x = 1,
+                    y = 2 // two
+                };"#]],
+        );
+    }
+
+    #[test]
+    fn member_order_fix_comment_would_swallow_brace() {
+        check_member_order_fix(
+            r#"
+p := Point.{ y = 2, // two
+    x = 1 };"#,
+            &["x = 1", "y = 2"],
+            true,
+            expect![[r#"no fix"#]],
+        );
+    }
+
+    #[test]
+    fn member_order_fix_with_side_effects() {
+        check_member_order_fix(
+            "p := Point.{ y = { 5 }, x = next() };",
+            &["x = next()", "y = { 5 }"],
+            false,
+            expect![[r#"no fix"#]],
+        );
     }
 }
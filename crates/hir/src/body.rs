@@ -13,7 +13,7 @@ use la_arena::{Arena, ArenaMap, Idx};
 use path_clean::PathClean;
 use rustc_hash::{FxHashMap, FxHashSet};
 use syntax::SyntaxTree;
-use text_size::TextRange;
+use text_size::{TextRange, TextSize};
 
 use crate::{subdir::SubDir, FileName, Fqn, Index, Name, NameWithRange, PrimitiveTy, UIDGenerator};
 
@@ -52,6 +52,29 @@ impl WorldBodies {
         self[fqn.file].global_is_extern(fqn.name)
     }
 
+    /// Finds where a `LocalGlobal` of the given file is actually defined.
+    ///
+    /// Globals from `using`d files are referred to as if they were local,
+    /// but they still belong to the file they were written in.
+    /// The file's own globals win, and then the earliest `using` wins.
+    /// If nothing defines the name, the local fqn is returned
+    pub fn resolve_global(&self, file: FileName, name: Name) -> Fqn {
+        let local = Fqn { file, name };
+        if self.exists(local) {
+            return local;
+        }
+
+        self[file]
+            .usings
+            .iter()
+            .map(|(used_file, _)| Fqn {
+                file: *used_file,
+                name,
+            })
+            .find(|fqn| self.exists(*fqn))
+            .unwrap_or(local)
+    }
+
     pub fn add_file(&mut self, file: FileName, bodies: Bodies) {
         self.bodies.insert(file, bodies);
     }
@@ -89,24 +112,60 @@ impl WorldBodies {
             })
             .collect()
     }
+
+    /// Global functions with an `@export` are meant to be called from outside of Capy,
+    /// so they have to be compiled even when nothing else uses them
+    pub fn find_exported_functions(&self) -> Vec<Fqn> {
+        let mut exported: Vec<_> = self
+            .bodies
+            .iter()
+            .flat_map(|(file, bodies)| {
+                bodies
+                    .global_bodies
+                    .iter()
+                    .filter(|(_, body)| match bodies[**body] {
+                        Expr::Lambda(lambda) => bodies[lambda].export_name.is_some(),
+                        _ => false,
+                    })
+                    .map(|(name, _)| Fqn {
+                        file: *file,
+                        name: *name,
+                    })
+            })
+            .collect();
+
+        exported.sort_unstable();
+
+        exported
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Bodies {
     local_defs: Arena<LocalDef>,
     switch_locals: Arena<SwitchLocal>,
+    for_locals: Arena<ForLocal>,
     assigns: Arena<Assign>,
     stmts: Arena<Stmt>,
     exprs: Arena<Expr>,
     expr_ranges: ArenaMap<Idx<Expr>, TextRange>,
+    /// the types of literals written with a suffix, like `42u8`
+    literal_suffixes: ArenaMap<Idx<Expr>, PrimitiveTy>,
+    /// number literals which were written differently than their value would be printed,
+    /// like `1_000` or `0xFF`
+    written_literals: ArenaMap<Idx<Expr>, String>,
     global_tys: FxHashMap<Name, Idx<Expr>>,
     global_bodies: FxHashMap<Name, Idx<Expr>>,
     global_externs: FxHashSet<Name>,
     scope_decls: bimap::BiMap<ScopeId, Idx<Expr>>,
     scope_usages: FxHashMap<ScopeId, Vec<Idx<Stmt>>>,
+    /// the names brought into scope by each block, `for` loop, and switch arm
+    scopes: Arena<ScopeData>,
     lambdas: Arena<Lambda>,
+    captures: Arena<Capture>,
     comptimes: Arena<Comptime>,
     imports: FxHashSet<FileName>,
+    usings: Vec<(FileName, TextRange)>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -117,6 +176,8 @@ pub enum Expr {
     BoolLiteral(bool),
     StringLiteral(String),
     CharLiteral(u8),
+    /// `null`, which is only a name and so can be shadowed by other definitions
+    NullLiteral,
     Cast {
         ty: Idx<Expr>,
         expr: Option<Idx<Expr>>,
@@ -128,6 +189,13 @@ pub enum Expr {
     Deref {
         pointer: Idx<Expr>,
     },
+    /// `start..end` or `start..=end`.
+    /// the start and end can only be left out when slicing, like `arr[2..]`
+    Range {
+        start: Option<Idx<Expr>>,
+        end: Option<Idx<Expr>>,
+        inclusive: bool,
+    },
     Binary {
         lhs: Idx<Expr>,
         rhs: Idx<Expr>,
@@ -137,14 +205,29 @@ pub enum Expr {
         expr: Idx<Expr>,
         op: UnaryOp,
     },
+    /// `opt orelse fallback`, which gives the fallback when the optional is `null`
+    Orelse {
+        optional: Idx<Expr>,
+        fallback: Idx<Expr>,
+    },
     ArrayDecl {
         size: Option<Idx<Expr>>,
+        /// `[_]i32`, the size comes from the value being annotated
+        inferred_size: bool,
         ty: Idx<Expr>,
     },
     ArrayLiteral {
         ty: Option<Idx<Expr>>,
         items: Vec<Idx<Expr>>,
     },
+    /// `(1, "hi")`, or the tuple type `(i32, str)` if every item is a type
+    Tuple(Vec<Idx<Expr>>),
+    /// `i32.[item; count]`
+    ArrayRepeat {
+        ty: Option<Idx<Expr>>,
+        item: Idx<Expr>,
+        count: Idx<Expr>,
+    },
     Index {
         source: Idx<Expr>,
         index: Idx<Expr>,
@@ -160,7 +243,15 @@ pub enum Expr {
         else_branch: Option<Idx<Expr>>,
     },
     While {
-        condition: Option<Idx<Expr>>,
+        condition: Idx<Expr>,
+        body: Idx<Expr>,
+    },
+    Loop {
+        body: Idx<Expr>,
+    },
+    For {
+        item: Idx<ForLocal>,
+        iterable: Idx<Expr>,
         body: Idx<Expr>,
     },
     Switch {
@@ -172,6 +263,9 @@ pub enum Expr {
     },
     Local(Idx<LocalDef>),
     SwitchLocal(Idx<SwitchLocal>),
+    ForLocal(Idx<ForLocal>),
+    /// a value from outside of the lambda that this expression is within
+    Captured(Idx<Capture>),
     LocalGlobal(NameWithRange),
     Param {
         idx: u32,
@@ -192,11 +286,19 @@ pub enum Expr {
     /// or a pointer to a primitive type, or a distinct type
     PrimitiveTy(PrimitiveTy),
     Distinct {
-        uid: u32,
+        uid: u64,
+        ty: Idx<Expr>,
+    },
+    /// `?i32`
+    OptionalDecl {
+        ty: Idx<Expr>,
+    },
+    /// `closure (x: i32) -> i32`
+    ClosureDecl {
         ty: Idx<Expr>,
     },
     StructDecl {
-        uid: u32,
+        uid: u64,
         members: Vec<MemberDecl>,
     },
     StructLiteral {
@@ -204,9 +306,14 @@ pub enum Expr {
         members: Vec<MemberLiteral>,
     },
     EnumDecl {
-        uid: u32,
+        uid: u64,
         variants: Vec<VariantDecl>,
     },
+    /// `interface { area: () -> f32 }`, where each method is a function type
+    InterfaceDecl {
+        uid: u64,
+        methods: Vec<MemberDecl>,
+    },
     Directive {
         name: NameWithRange,
         args: Vec<Idx<Expr>>,
@@ -260,7 +367,7 @@ pub struct MemberLiteral {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct VariantDecl {
     pub name: Option<NameWithRange>,
-    pub uid: u32,
+    pub uid: u64,
     // when `None`, the default type should be `void`
     pub ty: Option<Idx<Expr>>,
     // when `None`, the variant will be one plus the previous discriminant
@@ -291,14 +398,34 @@ pub struct Lambda {
     pub params_range: TextRange,
     pub return_ty: Option<Idx<Expr>>,
     pub body: Idx<Expr>,
+    /// the locals and parameters of enclosing functions which the body refers to
+    pub captures: Vec<Idx<Capture>>,
     pub is_extern: bool,
+    pub inline: Option<InlineHint>,
+    /// `@export("foo")` makes the function's symbol `foo` instead of a mangled name
+    pub export_name: Option<Key>,
+}
+
+/// `@inline` or `@no_inline`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InlineHint {
+    Always,
+    Never,
 }
 
+/// The attributes which only make sense on functions
+const FUNCTION_ATTRS: &[&str] = &["inline", "no_inline", "export"];
+
 #[derive(Debug, Clone)]
 pub struct Param {
     pub name: Option<Name>,
     pub ty: Idx<Expr>,
     pub varargs: bool,
+    /// whether the parameter is used in the type of a later parameter (or the return type),
+    /// like the `N` in `(N: usize, buf: [N]u8)`.
+    /// its value has to be known at compile-time, and the function gets checked and compiled
+    /// again for every value it's called with
+    pub is_const: bool,
     pub range: TextRange,
 }
 
@@ -317,6 +444,16 @@ pub struct FQLambda {
     pub lambda: Idx<Lambda>,
 }
 
+/// A value which a lambda uses from the function it's inside of.
+///
+/// The value is evaluated when the lambda is, and gets kept alongside the function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture {
+    pub name: Name,
+    /// refers to something in the scope just outside of the lambda
+    pub value: Idx<Expr>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Comptime {
     pub body: Idx<Expr>,
@@ -356,6 +493,47 @@ pub enum Stmt {
 enum Local {
     Def(Idx<LocalDef>),
     SwitchArm(Idx<SwitchLocal>),
+    ForItem(Idx<ForLocal>),
+}
+
+/// Something that can be referred to by name from within a body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeDef {
+    Local(Idx<LocalDef>),
+    SwitchLocal(Idx<SwitchLocal>),
+    ForLocal(Idx<ForLocal>),
+    /// the `idx`th parameter of the lambda expression
+    Param {
+        lambda: Idx<Expr>,
+        idx: u32,
+    },
+}
+
+impl From<Local> for ScopeDef {
+    fn from(local: Local) -> Self {
+        match local {
+            Local::Def(def) => ScopeDef::Local(def),
+            Local::SwitchArm(switch_local) => ScopeDef::SwitchLocal(switch_local),
+            Local::ForItem(for_local) => ScopeDef::ForLocal(for_local),
+        }
+    }
+}
+
+/// The locals of a block, `for` loop, or switch arm.
+/// Lowering resolves names on its own, these are only kept around for the IDE layer
+#[derive(Debug, Clone)]
+struct ScopeData {
+    range: TextRange,
+    /// in the order they were defined
+    entries: Vec<ScopeEntry>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ScopeEntry {
+    name: Name,
+    def: ScopeDef,
+    /// `x := x + 1` can't refer to itself, so a local is only visible after its definition
+    visible_from: TextSize,
 }
 
 #[derive(Clone)]
@@ -380,6 +558,14 @@ pub struct SwitchLocal {
     pub range: TextRange,
 }
 
+/// The `x` in `for x in xs { ... }`
+#[derive(Debug, Clone)]
+pub struct ForLocal {
+    /// Like `SwitchLocal::scrutinee`, this is what hir_ty uses to get the type of the item
+    pub iterable: Idx<Expr>,
+    pub range: TextRange,
+}
+
 #[derive(Debug, Clone)]
 pub struct Assign {
     pub dest: Idx<Expr>,
@@ -449,6 +635,32 @@ impl BinaryOp {
             BinaryOp::LOr => "||",
         }
     }
+
+    /// The name of the function which a struct or distinct type can declare to overload this
+    /// operator, e.g. `__add` for `+`.
+    ///
+    /// `&&` and `||` can't be overloaded since they don't always evaluate both sides
+    pub fn overload_name(self) -> Option<&'static str> {
+        Some(match self {
+            BinaryOp::Add => "__add",
+            BinaryOp::Sub => "__sub",
+            BinaryOp::Mul => "__mul",
+            BinaryOp::Div => "__div",
+            BinaryOp::Mod => "__mod",
+            BinaryOp::Lt => "__lt",
+            BinaryOp::Gt => "__gt",
+            BinaryOp::Le => "__le",
+            BinaryOp::Ge => "__ge",
+            BinaryOp::Eq => "__eq",
+            BinaryOp::Ne => "__ne",
+            BinaryOp::BAnd => "__band",
+            BinaryOp::BOr => "__bor",
+            BinaryOp::Xor => "__xor",
+            BinaryOp::LShift => "__shl",
+            BinaryOp::RShift => "__shr",
+            BinaryOp::LAnd | BinaryOp::LOr => return None,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -473,10 +685,22 @@ pub struct LoweringDiagnostic {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LoweringDiagnosticKind {
     OutOfRangeIntLiteral,
+    OutOfRangeFloatLiteral,
     UndefinedRef { name: Key },
     UndefinedLabel { name: Key },
+    DuplicateLabel { name: Key },
     NonGlobalExternFunc,
+    UnknownAttr { name: Key },
+    FunctionAttrOnNonFunction { name: Key },
+    DeprecatedOnLambda,
+    InlineHintOnExtern,
+    ExportNameOnExtern,
+    BadExportName,
     InvalidEscape,
+    InvalidUnicodeEscape,
+    InvalidUnicodeChar { value: u32 },
+    InvalidHexEscape,
+    NonAsciiHexEscapeInString,
     TooManyCharsInCharLiteral,
     EmptyCharLiteral,
     NonU8CharLiteral,
@@ -493,6 +717,17 @@ pub enum LoweringDiagnosticKind {
     BreakFromDefer,
     ContinueFromDefer,
     MultipleDefaultArms,
+    NonGlobalStructDecl,
+    NonGlobalModule,
+    ThisWithArgs,
+    UsingNonImport,
+    NonRequirementAtRoot,
+    RequirementBadArgs { feature: bool },
+    InvalidVersionRequirement { requirement: String },
+    UnsupportedVersion { requirement: String },
+    UnsupportedFeature { feature: String },
+    OpenRangeOutsideIndex,
+    InclusiveRangeWithoutEnd,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -501,23 +736,26 @@ pub fn lower(
     tree: &SyntaxTree,
     file_name: &std::path::Path,
     index: &Index,
-    uid_gen: &mut UIDGenerator,
     interner: &mut Interner,
     mod_dir: &Path,
     fake_file_system: bool,
 ) -> (Bodies, Vec<LoweringDiagnostic>) {
-    let mut ctx = Ctx::new(
-        file_name,
-        index,
-        uid_gen,
-        interner,
-        tree,
-        mod_dir,
-        fake_file_system,
-    );
+    let mut ctx = Ctx::new(file_name, index, interner, tree, mod_dir, fake_file_system);
+
+    for stmt in root.stmts(tree) {
+        if let ast::Stmt::Expr(expr_stmt) = stmt {
+            ctx.lower_requirement(expr_stmt);
+        }
+    }
+
+    for using in root.usings(tree) {
+        ctx.lower_using(using);
+    }
 
     for def in root.defs(tree) {
         ctx.lower_global(
+            None,
+            def.attrs(tree).collect(),
             def.name(tree),
             def.ty(tree),
             def.r#extern(tree).is_some(),
@@ -530,6 +768,100 @@ pub fn lower(
     (ctx.bodies, ctx.diagnostics)
 }
 
+/// The version of the compiler that `#requires_version` gets checked against
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Every feature that can be asked for with `#requires_feature`
+pub const SUPPORTED_FEATURES: &[&str] =
+    &["comptime", "distinct", "struct_decls", "modules", "using"];
+
+/// Checks a comma separated list of requirements like `>=0.2, <0.4` against `version`.
+///
+/// A requirement without an operator is treated like `>=`.
+/// Pre-release and build information (`-alpha.1`) is ignored.
+/// Returns `None` if the requirement isn't valid
+fn version_matches(requirement: &str, version: &str) -> Option<bool> {
+    fn parse_version(text: &str) -> Option<[u64; 3]> {
+        let mut version = [0; 3];
+        let mut parts = text.trim().split('.');
+        for component in &mut version {
+            match parts.next() {
+                Some(part) => *component = part.parse().ok()?,
+                None => break,
+            }
+        }
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(version)
+    }
+
+    let version = parse_version(version.split(['-', '+']).next()?)?;
+
+    let mut matches = true;
+    for part in requirement.split(',') {
+        let part = part.trim();
+        let (op, required) = [">=", "<=", ">", "<", "="]
+            .into_iter()
+            .find_map(|op| part.strip_prefix(op).map(|rest| (op, rest)))
+            .unwrap_or((">=", part));
+        let ordering = version.cmp(&parse_version(required)?);
+
+        matches &= match op {
+            ">=" => ordering.is_ge(),
+            "<=" => ordering.is_le(),
+            ">" => ordering.is_gt(),
+            "<" => ordering.is_lt(),
+            _ => ordering.is_eq(),
+        };
+    }
+
+    Some(matches)
+}
+
+/// Splits a numeric literal like `42u8` into its digits and its type suffix.
+///
+/// `f` is a valid hex digit, so hex literals can only have integer suffixes
+fn split_literal_suffix(text: &str, hex: bool) -> (&str, Option<&str>) {
+    let suffix_start = text.find(|ch| matches!(ch, 'i' | 'u') || (!hex && ch == 'f'));
+
+    match suffix_start {
+        Some(idx) => (&text[..idx], Some(&text[idx..])),
+        None => (text, None),
+    }
+}
+
+/// The uid of the `local_id`th distinct, struct, enum, or variant type lowered in the global
+/// called `global` (the full name given out by the index, like `Foo.bar`).
+///
+/// The uid only depends on the file's path (relative to the module directory or the current
+/// directory), the name of the global the type is defined in, and the order of the type within
+/// that global. So the same type gets the same uid across compilations, and adding, removing or
+/// editing one global never changes the uids of the types in another.
+///
+/// This uses FNV-1a instead of `std::hash` because it has to give the same result on every
+/// machine and every version of rust. It's 64 bits wide so that two types in a project with
+/// thousands of them are still very unlikely to end up with the same uid
+pub fn type_uid(relative_file_name: &str, global: &str, local_id: u32) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x00000100000001b3;
+
+    let mut hash = FNV_OFFSET_BASIS;
+    // the zeros keep `a` + `bc` from hashing the same as `ab` + `c`
+    for byte in relative_file_name
+        .as_bytes()
+        .iter()
+        .chain(&[0])
+        .chain(global.as_bytes())
+        .chain(&[0])
+        .chain(&local_id.to_le_bytes())
+    {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
 #[derive(PartialEq)]
 enum ScopeKind {
     Block((Option<Key>, ScopeId)),
@@ -550,95 +882,236 @@ struct Ctx<'a> {
     bodies: Bodies,
     file_name: &'a Path,
     index: &'a Index,
-    uid_gen: &'a mut UIDGenerator,
+    /// the global currently being lowered, and how many types have been lowered within it
+    uid_global: Option<Name>,
+    types_in_global: u32,
+    relative_file_name: String,
     interner: &'a mut Interner,
     tree: &'a SyntaxTree,
     diagnostics: Vec<LoweringDiagnostic>,
     scopes: Vec<FxHashMap<Key, Local>>,
+    /// the `ScopeData` of each of `scopes`, other than the outermost one
+    scope_data: Vec<Idx<ScopeData>>,
     label_kinds: Vec<ScopeKind>,
     label_gen: UIDGenerator,
     params: FxHashMap<Key, (u32, ast::Param)>,
+    /// the parameters which have been referred to, used for finding out which parameters are
+    /// used in the signature of their own function
+    used_params: FxHashSet<u32>,
+    /// the functions around the lambda whose body is currently being lowered, innermost last
+    enclosing: Vec<EnclosingFn>,
+    /// the global struct or module whose body is currently being lowered
+    namespace: Option<Name>,
     mod_dir: &'a Path,
     fake_file_system: bool, // used for importing files in tests
 }
 
+/// Everything that a lambda's body can capture from the function it's inside of
+struct EnclosingFn {
+    params: FxHashMap<Key, (u32, ast::Param)>,
+    scopes: Vec<FxHashMap<Key, Local>>,
+    scope_data: Vec<Idx<ScopeData>>,
+    /// what the lambda has captured from this function so far
+    captures: Vec<Idx<Capture>>,
+}
+
 impl<'a> Ctx<'a> {
     fn new(
         file_name: &'a std::path::Path,
         index: &'a Index,
-        uid_gen: &'a mut UIDGenerator,
         interner: &'a mut Interner,
         tree: &'a SyntaxTree,
         mod_dir: &'a Path,
         fake_file_system: bool,
     ) -> Self {
+        // uids are hashed from the path relative to the module directory (or the current directory)
+        // so that they stay the same no matter where the project is
+        let relative_file_name = file_name
+            .strip_prefix(mod_dir)
+            .ok()
+            .or_else(|| {
+                env::current_dir()
+                    .ok()
+                    .and_then(|curr_dir| file_name.strip_prefix(curr_dir).ok())
+            })
+            .unwrap_or(file_name);
+
         Self {
             bodies: Bodies {
                 local_defs: Arena::new(),
                 switch_locals: Arena::new(),
+                for_locals: Arena::new(),
                 assigns: Arena::new(),
                 stmts: Arena::new(),
                 exprs: Arena::new(),
                 expr_ranges: ArenaMap::default(),
+                literal_suffixes: ArenaMap::default(),
+                written_literals: ArenaMap::default(),
                 global_tys: FxHashMap::default(),
                 global_bodies: FxHashMap::default(),
                 global_externs: FxHashSet::default(),
                 scope_decls: bimap::BiMap::default(),
                 scope_usages: FxHashMap::default(),
+                scopes: Arena::new(),
                 lambdas: Arena::new(),
+                captures: Arena::new(),
                 comptimes: Arena::new(),
                 imports: FxHashSet::default(),
+                usings: Vec::new(),
             },
             file_name,
             index,
-            uid_gen,
+            uid_global: None,
+            types_in_global: 0,
+            relative_file_name: relative_file_name.to_string_lossy().replace('\\', "/"),
             interner,
             tree,
             diagnostics: Vec::new(),
             scopes: vec![FxHashMap::default()],
+            scope_data: Vec::new(),
             label_kinds: Vec::new(),
             label_gen: UIDGenerator::default(),
             params: FxHashMap::default(),
+            used_params: FxHashSet::default(),
+            enclosing: Vec::new(),
+            namespace: None,
             mod_dir,
             fake_file_system,
         }
     }
 
+    fn generate_uid(&mut self) -> u64 {
+        let global = self
+            .uid_global
+            .map(|global| self.interner.lookup(global.0))
+            .unwrap_or_default();
+        let uid = type_uid(&self.relative_file_name, global, self.types_in_global);
+        self.types_in_global += 1;
+        uid
+    }
+
     fn lower_global(
         &mut self,
+        namespace: Option<&str>,
+        attrs: Vec<ast::Attr>,
         name_token: Option<ast::Ident>,
         ty_annotation: Option<ast::Ty>,
         is_extern: bool,
         expr: Option<ast::Expr>,
     ) {
-        let name = match name_token {
-            Some(ident) => Name(self.interner.intern(ident.text(self.tree))),
-            None => return,
+        // this has to match the names given out by the index
+        let full_name = match (namespace, name_token) {
+            (_, None) => return,
+            (Some(namespace), Some(ident)) => format!("{}.{}", namespace, ident.text(self.tree)),
+            (None, Some(ident)) => ident.text(self.tree).to_string(),
         };
+        let mut name = Name(self.interner.intern_ident(&full_name));
 
         // if we’ve already seen a global with this name,
-        // we ignore all other globals with that name
+        // we ignore all other globals with that name,
+        // unless it's an overload, which gets the next name the index gave out
         //
         // we don’t have to worry about emitting a diagnostic here
         // because indexing already handles this
         if self.bodies.global_bodies.contains_key(&name) {
-            return;
+            let is_function = !is_extern
+                && matches!(
+                    expr,
+                    Some(ast::Expr::Lambda(lambda))
+                        if lambda.body(self.tree).is_some() && lambda.r#extern(self.tree).is_none()
+                );
+            let overload =
+                self.index
+                    .overloads(name)
+                    .filter(|_| is_function)
+                    .and_then(|overloads| {
+                        overloads
+                            .iter()
+                            .find(|overload| !self.bodies.global_bodies.contains_key(overload))
+                    });
+
+            match overload {
+                Some(overload) => name = *overload,
+                None => return,
+            }
         }
 
+        // overloads have already been given their own names,
+        // so every global gets its own set of type uids
+        let old_uid_global = self.uid_global.replace(name);
+        let old_types_in_global = mem::replace(&mut self.types_in_global, 0);
+
         if let Some(ty) = ty_annotation {
             let ty = self.lower_expr(ty.expr(self.tree));
 
             self.bodies.global_tys.insert(name, ty);
         }
 
+        // `@deprecated` was already taken care of by the index
+        let attrs: Vec<_> = attrs
+            .into_iter()
+            .filter(|attr| {
+                attr.name(self.tree)
+                    .is_some_and(|name| name.text(self.tree) != "deprecated")
+            })
+            .collect();
+        if !matches!(expr, Some(ast::Expr::Lambda(_))) {
+            for attr in attrs.iter().copied() {
+                let name = attr.name(self.tree).unwrap().text(self.tree);
+                let kind = if FUNCTION_ATTRS.contains(&name) {
+                    LoweringDiagnosticKind::FunctionAttrOnNonFunction {
+                        name: self.interner.intern(name),
+                    }
+                } else {
+                    LoweringDiagnosticKind::UnknownAttr {
+                        name: self.interner.intern(name),
+                    }
+                };
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind,
+                    range: attr.range(self.tree),
+                });
+            }
+        }
+
         if is_extern {
             self.bodies.global_externs.insert(name);
+            self.uid_global = old_uid_global;
+            self.types_in_global = old_types_in_global;
             return;
         }
 
+        // `#this()` and unqualified references inside of a struct or module body refer to
+        // that struct or module
+        let decls: Vec<_> = match expr {
+            Some(ast::Expr::StructDecl(struct_decl)) => struct_decl.decls(self.tree).collect(),
+            Some(ast::Expr::ModuleDecl(module_decl)) => module_decl.decls(self.tree).collect(),
+            _ => Vec::new(),
+        };
+        let old_namespace = if matches!(
+            expr,
+            Some(ast::Expr::StructDecl(_) | ast::Expr::ModuleDecl(_))
+        ) {
+            self.namespace.replace(name)
+        } else {
+            self.namespace
+        };
+
         let body = match expr {
             Some(ast::Expr::Lambda(lambda)) => {
                 let body = self.lower_lambda(lambda, true);
+
+                // attributes before the name work just like the ones after the signature
+                if let Expr::Lambda(idx) = body {
+                    let mut inline = self.bodies.lambdas[idx].inline;
+                    let mut export_name = self.bodies.lambdas[idx].export_name;
+                    for attr in attrs {
+                        self.lower_function_attr(attr, lambda, &mut inline, &mut export_name);
+                    }
+                    self.bodies.lambdas[idx].inline = inline;
+                    self.bodies.lambdas[idx].export_name = export_name;
+                }
+
                 let body = self.bodies.exprs.alloc(body);
 
                 self.bodies
@@ -647,9 +1120,49 @@ impl<'a> Ctx<'a> {
 
                 body
             }
+            Some(ast::Expr::StructDecl(struct_decl)) => {
+                let body = self.lower_struct_declaration(struct_decl, true);
+                let body = self.bodies.exprs.alloc(body);
+
+                self.bodies
+                    .expr_ranges
+                    .insert(body, struct_decl.range(self.tree));
+
+                body
+            }
+            Some(ast::Expr::ModuleDecl(module_decl)) => {
+                // modules are just structs without any fields
+                let body = Expr::StructDecl {
+                    uid: self.generate_uid(),
+                    members: Vec::new(),
+                };
+                let body = self.bodies.exprs.alloc(body);
+
+                self.bodies
+                    .expr_ranges
+                    .insert(body, module_decl.range(self.tree));
+
+                body
+            }
             _ => self.lower_expr(expr),
         };
         self.bodies.global_bodies.insert(name, body);
+
+        self.uid_global = old_uid_global;
+        self.types_in_global = old_types_in_global;
+
+        for decl in decls {
+            self.lower_global(
+                Some(&full_name),
+                decl.attrs(self.tree).collect(),
+                decl.name(self.tree),
+                decl.ty(self.tree),
+                decl.r#extern(self.tree).is_some(),
+                decl.value(self.tree),
+            );
+        }
+
+        self.namespace = old_namespace;
     }
 
     fn lower_lambda(&mut self, lambda: ast::Lambda, allow_extern: bool) -> Expr {
@@ -659,11 +1172,18 @@ impl<'a> Ctx<'a> {
         let mut param_keys = FxHashMap::default();
         let mut param_type_ranges = Vec::new();
 
+        // the types of parameters can refer to the parameters before them, and the return type
+        // can refer to all of them. function types don't have a body to check again for each
+        // value, so they keep on seeing the parameters of whatever function they're inside of
+        let has_body = lambda.body(self.tree).is_some();
+        let old_signature_params = has_body.then(|| mem::take(&mut self.params));
+        let old_used_params = mem::take(&mut self.used_params);
+
         if let Some(param_list) = lambda.param_list(self.tree) {
             for (idx, param) in param_list.params(self.tree).enumerate() {
                 let key = param
                     .name(self.tree)
-                    .map(|name| self.interner.intern(name.text(self.tree)));
+                    .map(|name| self.interner.intern_ident(name.text(self.tree)));
 
                 let ty = param.ty(self.tree);
                 param_type_ranges.push(ty.map(|type_| type_.range(self.tree)));
@@ -674,11 +1194,16 @@ impl<'a> Ctx<'a> {
                     name: key.map(Name),
                     ty,
                     varargs: param.ellipsis(self.tree).is_some(),
+                    is_const: false,
                     range: param.range(self.tree),
                 });
 
                 if let Some(key) = key {
                     param_keys.insert(key, (idx as u32, param));
+
+                    if has_body {
+                        self.params.insert(key, (idx as u32, param));
+                    }
                 }
             }
         }
@@ -688,6 +1213,15 @@ impl<'a> Ctx<'a> {
             .and_then(|ty| ty.expr(self.tree))
             .map(|return_ty| self.lower_expr(Some(return_ty)));
 
+        let used_params = mem::replace(&mut self.used_params, old_used_params);
+        if let Some(old_params) = old_signature_params {
+            self.params = old_params;
+
+            for idx in used_params {
+                params[idx as usize].is_const = true;
+            }
+        }
+
         if !allow_extern {
             if let Some(r#extern) = lambda.r#extern(self.tree) {
                 self.diagnostics.push(LoweringDiagnostic {
@@ -697,15 +1231,32 @@ impl<'a> Ctx<'a> {
             }
         }
 
-        // todo: when parameter types are added, self.params should be cloned, and then updated in
-        // place
-        let old_params = mem::replace(&mut self.params, param_keys);
-        let old_scopes = mem::take(&mut self.scopes);
+        let mut inline = None;
+        let mut export_name = None;
+        for attr in lambda.attrs(self.tree) {
+            self.lower_function_attr(attr, lambda, &mut inline, &mut export_name);
+        }
+
+        // the locals of the enclosing function aren't in scope within the body, but they can
+        // still be captured
+        self.enclosing.push(EnclosingFn {
+            params: mem::replace(&mut self.params, param_keys),
+            scopes: mem::take(&mut self.scopes),
+            scope_data: mem::take(&mut self.scope_data),
+            captures: Vec::new(),
+        });
 
         let body = self.lower_expr(lambda.body(self.tree));
 
+        let EnclosingFn {
+            params: old_params,
+            scopes: old_scopes,
+            scope_data: old_scope_data,
+            captures,
+        } = self.enclosing.pop().unwrap();
         self.params = old_params;
         self.scopes = old_scopes;
+        self.scope_data = old_scope_data;
         self.label_kinds = old_labels;
 
         Expr::Lambda(self.bodies.lambdas.alloc(Lambda {
@@ -713,18 +1264,103 @@ impl<'a> Ctx<'a> {
             params_range: lambda.param_list(self.tree).unwrap().range(self.tree),
             return_ty,
             is_extern: lambda.r#extern(self.tree).is_some(),
+            inline,
+            export_name,
             body,
+            captures,
         }))
     }
 
+    /// `@inline`, `@no_inline` and `@export("foo")`, which can be written either in front of a
+    /// global function's name or between a function's signature and its body
+    fn lower_function_attr(
+        &mut self,
+        attr: ast::Attr,
+        lambda: ast::Lambda,
+        inline: &mut Option<InlineHint>,
+        export_name: &mut Option<Key>,
+    ) {
+        let Some(name) = attr.name(self.tree) else {
+            return;
+        };
+
+        let is_extern = lambda.r#extern(self.tree).is_some();
+
+        match name.text(self.tree) {
+            attr_name @ ("inline" | "no_inline") => {
+                // extern functions are only ever called, there's nothing to inline
+                if is_extern {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::InlineHintOnExtern,
+                        range: attr.range(self.tree),
+                    });
+                    return;
+                }
+
+                *inline = Some(if attr_name == "inline" {
+                    InlineHint::Always
+                } else {
+                    InlineHint::Never
+                });
+            }
+            "export" => {
+                // the symbol of an extern function is already just its name
+                if is_extern {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::ExportNameOnExtern,
+                        range: attr.range(self.tree),
+                    });
+                    return;
+                }
+
+                let old_diags_len = self.diagnostics.len();
+                let text =
+                    attr.value(self.tree)
+                        .map(|value| match self.lower_string_literal(value) {
+                            Expr::StringLiteral(text) => text,
+                            _ => unreachable!(),
+                        });
+                if self.diagnostics.len() != old_diags_len {
+                    return;
+                }
+
+                match text {
+                    Some(text) if !text.is_empty() && !text.contains('\0') => {
+                        *export_name = Some(self.interner.intern(&text));
+                    }
+                    _ => self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::BadExportName,
+                        range: attr.range(self.tree),
+                    }),
+                }
+            }
+            // only the definition itself can be deprecated
+            "deprecated" => self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::DeprecatedOnLambda,
+                range: attr.range(self.tree),
+            }),
+            name => self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UnknownAttr {
+                    name: self.interner.intern(name),
+                },
+                range: attr.range(self.tree),
+            }),
+        }
+    }
+
     fn lower_comptime(&mut self, comptime_expr: ast::ComptimeExpr) -> Expr {
         let old_params = mem::take(&mut self.params);
         let old_scopes = mem::take(&mut self.scopes);
+        let old_scope_data = mem::take(&mut self.scope_data);
+        // nothing from the runtime of a function exists at compile-time
+        let old_enclosing = mem::take(&mut self.enclosing);
 
         let body = self.lower_expr(comptime_expr.body(self.tree));
 
         self.params = old_params;
         self.scopes = old_scopes;
+        self.scope_data = old_scope_data;
+        self.enclosing = old_enclosing;
 
         Expr::Comptime(self.bodies.comptimes.alloc(Comptime { body }))
     }
@@ -786,26 +1422,42 @@ impl<'a> Ctx<'a> {
     }
 
     fn lower_break(&mut self, break_stmt: ast::BreakStmt) -> Stmt {
+        let label = self.label_ref(break_stmt.label(self.tree));
+        let mut value = break_stmt.value(self.tree);
+
+        // `break outer;` could either be breaking out of the `outer` label or giving back
+        // the `outer` variable. it's only a label if there isn't a variable with that name
+        let label = label.or_else(|| {
+            let Some(ast::Expr::VarRef(var_ref)) = value else {
+                return None;
+            };
+            let name = var_ref.name(self.tree)?;
+            let name = self.interner.intern_ident(name.text(self.tree));
+
+            if !self.is_label_in_scope(name)
+                || self.look_up_in_current_scope(name).is_some()
+                || self.look_up_param(name).is_some()
+            {
+                return None;
+            }
+
+            value = None;
+            Some((name, var_ref.range(self.tree)))
+        });
+
         Stmt::Break {
-            label: self.resolve_label(
-                break_stmt.range(self.tree),
-                break_stmt.label(self.tree),
-                false,
-            ),
-            value: break_stmt
-                .value(self.tree)
-                .map(|value| self.lower_expr(Some(value))),
+            label: self.resolve_label(break_stmt.range(self.tree), label, false),
+            value: value.map(|value| self.lower_expr(Some(value))),
             range: break_stmt.range(self.tree),
         }
     }
 
     fn lower_continue(&mut self, continue_stmt: ast::ContinueStmt) -> Stmt {
         Stmt::Continue {
-            label: self.resolve_label(
-                continue_stmt.range(self.tree),
-                continue_stmt.label(self.tree),
-                true,
-            ),
+            label: {
+                let label = self.label_ref(continue_stmt.label(self.tree));
+                self.resolve_label(continue_stmt.range(self.tree), label, true)
+            },
             range: continue_stmt.range(self.tree),
         }
     }
@@ -823,18 +1475,47 @@ impl<'a> Ctx<'a> {
         }
     }
 
+    /// Gives the name of the label, reporting it if a surrounding block or loop already
+    /// has a label with the same name
+    fn lower_label_decl(&mut self, label: Option<ast::LabelDecl>) -> Option<Key> {
+        let label = label?;
+        let name = label.name(self.tree)?;
+        let name = self.interner.intern_ident(name.text(self.tree));
+
+        if self.is_label_in_scope(name) {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::DuplicateLabel { name },
+                range: label.range(self.tree),
+            });
+        }
+
+        Some(name)
+    }
+
+    fn is_label_in_scope(&self, name: Key) -> bool {
+        self.label_kinds.iter().any(|kind| match kind {
+            ScopeKind::Block((label, _)) | ScopeKind::Loop((label, _)) => *label == Some(name),
+            ScopeKind::Defer => false,
+        })
+    }
+
+    fn label_ref(&mut self, label: Option<ast::LabelRef>) -> Option<(Key, TextRange)> {
+        let label = label?;
+        let name = label.name(self.tree)?;
+
+        Some((
+            self.interner.intern_ident(name.text(self.tree)),
+            label.range(self.tree),
+        ))
+    }
+
     fn resolve_label(
         &mut self,
         whole_range: TextRange,
-        label: Option<ast::LabelRef>,
+        label: Option<(Key, TextRange)>,
         require_loop: bool,
     ) -> Option<ScopeId> {
-        let label_name = label
-            .and_then(|label| label.name(self.tree))
-            .map(|name| name.text(self.tree))
-            .map(|name| self.interner.intern(name));
-
-        if let Some(label_name) = label_name {
+        if let Some((label_name, label_range)) = label {
             let mut passed_defer = false;
 
             let result = self.label_kinds.iter().rev().find_map(|scope| match scope {
@@ -842,7 +1523,7 @@ impl<'a> Ctx<'a> {
                     if require_loop {
                         self.diagnostics.push(LoweringDiagnostic {
                             kind: LoweringDiagnosticKind::ContinueNonLoop { name: Some(*name) },
-                            range: label.unwrap().range(self.tree),
+                            range: label_range,
                         });
                     }
 
@@ -859,7 +1540,7 @@ impl<'a> Ctx<'a> {
             if result.is_none() {
                 self.diagnostics.push(LoweringDiagnostic {
                     kind: LoweringDiagnosticKind::UndefinedLabel { name: label_name },
-                    range: label.unwrap().range(self.tree),
+                    range: label_range,
                 });
             } else if passed_defer {
                 self.diagnostics.push(LoweringDiagnostic {
@@ -927,7 +1608,7 @@ impl<'a> Ctx<'a> {
         });
 
         if let Some(ident) = local_def.name(self.tree) {
-            let name = self.interner.intern(ident.text(self.tree));
+            let name = self.interner.intern_ident(ident.text(self.tree));
             self.insert_into_current_scope(name, Local::Def(id));
         }
 
@@ -961,9 +1642,18 @@ impl<'a> Ctx<'a> {
 
         let (expr, scope_id) = self.lower_expr_raw(expr_ast);
 
+        let written = self.lower_written_literal(expr_ast, &expr);
+
         let id = self.bodies.exprs.alloc(expr);
         self.bodies.expr_ranges.insert(id, range);
 
+        if let Some(suffix) = self.lower_literal_suffix(expr_ast) {
+            self.bodies.literal_suffixes.insert(id, suffix);
+        }
+        if let Some(written) = written {
+            self.bodies.written_literals.insert(id, written);
+        }
+
         if scope_id.is_some_and(|id| self.bodies.scope_usages.contains_key(&id)) {
             self.bodies.scope_decls.insert(scope_id.unwrap(), id);
         }
@@ -979,16 +1669,22 @@ impl<'a> Ctx<'a> {
                 ast::Expr::Mut(mut_expr) => self.lower_mut_expr(mut_expr),
                 ast::Expr::Deref(deref_expr) => self.lower_deref_expr(deref_expr),
                 ast::Expr::Binary(binary_expr) => self.lower_binary_expr(binary_expr),
+                ast::Expr::Range(range_expr) => self.lower_range_expr(range_expr, false),
                 ast::Expr::Unary(unary_expr) => self.lower_unary_expr(unary_expr),
                 ast::Expr::ArrayDecl(array_decl) => self.lower_array_decl(array_decl),
                 ast::Expr::ArrayLiteral(array_lit) => self.lower_array_literal(array_lit),
                 ast::Expr::Paren(paren_expr) => self.lower_paren_expr(paren_expr),
+                ast::Expr::Tuple(tuple_expr) => self.lower_tuple_expr(tuple_expr),
                 ast::Expr::Block(block) => return self.lower_block(block, true),
                 ast::Expr::If(if_expr) => self.lower_if(if_expr),
                 ast::Expr::While(while_expr) => {
                     let res = self.lower_while(while_expr);
                     return (res.0, Some(res.1));
                 }
+                ast::Expr::For(for_expr) => {
+                    let res = self.lower_for(for_expr);
+                    return (res.0, Some(res.1));
+                }
                 ast::Expr::Switch(switch_expr) => self.lower_switch(switch_expr),
                 ast::Expr::Call(call) => self.lower_call(call),
                 ast::Expr::IndexExpr(index_expr) => self.lower_index_expr(index_expr),
@@ -1002,10 +1698,24 @@ impl<'a> Ctx<'a> {
                     self.lower_string_literal(string_literal)
                 }
                 ast::Expr::Distinct(distinct) => self.lower_distinct(distinct),
+                ast::Expr::OptionalDecl(optional_decl) => self.lower_optional_decl(optional_decl),
+                ast::Expr::ClosureDecl(closure_decl) => self.lower_closure_decl(closure_decl),
                 ast::Expr::Lambda(lambda) => self.lower_lambda(lambda, false),
-                ast::Expr::StructDecl(struct_decl) => self.lower_struct_declaration(struct_decl),
+                ast::Expr::StructDecl(struct_decl) => {
+                    self.lower_struct_declaration(struct_decl, false)
+                }
                 ast::Expr::StructLiteral(struct_lit) => self.lower_struct_literal(struct_lit),
+                ast::Expr::ModuleDecl(module_decl) => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::NonGlobalModule,
+                        range: module_decl.range(self.tree),
+                    });
+                    Expr::Missing
+                }
                 ast::Expr::EnumDecl(enum_decl) => self.lower_enum_declaration(enum_decl),
+                ast::Expr::InterfaceDecl(interface_decl) => {
+                    self.lower_interface_declaration(interface_decl)
+                }
                 ast::Expr::Comptime(comptime_expr) => self.lower_comptime(comptime_expr),
                 ast::Expr::Directive(directive) => self.lower_directive(directive),
             },
@@ -1060,17 +1770,47 @@ impl<'a> Ctx<'a> {
         let ty = self.lower_expr(distinct.ty(self.tree).and_then(|ty| ty.expr(self.tree)));
 
         Expr::Distinct {
-            uid: self.uid_gen.generate_unique_id(),
+            uid: self.generate_uid(),
             ty,
         }
     }
 
-    fn lower_struct_declaration(&mut self, struct_decl: ast::StructDecl) -> Expr {
+    fn lower_optional_decl(&mut self, optional_decl: ast::OptionalDecl) -> Expr {
+        let ty = self.lower_expr(
+            optional_decl
+                .ty(self.tree)
+                .and_then(|ty| ty.expr(self.tree)),
+        );
+
+        Expr::OptionalDecl { ty }
+    }
+
+    fn lower_closure_decl(&mut self, closure_decl: ast::ClosureDecl) -> Expr {
+        let ty = self.lower_expr(closure_decl.ty(self.tree).and_then(|ty| ty.expr(self.tree)));
+
+        Expr::ClosureDecl { ty }
+    }
+
+    fn lower_struct_declaration(
+        &mut self,
+        struct_decl: ast::StructDecl,
+        allow_decls: bool,
+    ) -> Expr {
+        // global structs have their declarations lowered as globals by `lower_global`
+        if !allow_decls {
+            for decl in struct_decl.decls(self.tree) {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::NonGlobalStructDecl,
+                    range: decl.range(self.tree),
+                });
+            }
+        }
+
         let members = struct_decl
             .members(self.tree)
             .map(|member| {
                 let name = member.name(self.tree).map(|ident| NameWithRange {
-                    name: Name(self.interner.intern(ident.text(self.tree))),
+                    name: Name(self.interner.intern_ident(ident.text(self.tree))),
                     range: ident.range(self.tree),
                 });
 
@@ -1081,11 +1821,32 @@ impl<'a> Ctx<'a> {
             .collect();
 
         Expr::StructDecl {
-            uid: self.uid_gen.generate_unique_id(),
+            uid: self.generate_uid(),
             members,
         }
     }
 
+    fn lower_interface_declaration(&mut self, interface_decl: ast::InterfaceDecl) -> Expr {
+        let methods = interface_decl
+            .methods(self.tree)
+            .map(|method| {
+                let name = method.name(self.tree).map(|ident| NameWithRange {
+                    name: Name(self.interner.intern_ident(ident.text(self.tree))),
+                    range: ident.range(self.tree),
+                });
+
+                let ty = self.lower_expr(method.ty(self.tree).and_then(|ty| ty.expr(self.tree)));
+
+                MemberDecl { name, ty }
+            })
+            .collect();
+
+        Expr::InterfaceDecl {
+            uid: self.generate_uid(),
+            methods,
+        }
+    }
+
     fn lower_struct_literal(&mut self, struct_lit: ast::StructLiteral) -> Expr {
         let ty = struct_lit
             .ty(self.tree)
@@ -1095,7 +1856,7 @@ impl<'a> Ctx<'a> {
 
         for member in struct_lit.members(self.tree) {
             let name = member.name(self.tree).map(|ident| NameWithRange {
-                name: Name(self.interner.intern(ident.text(self.tree))),
+                name: Name(self.interner.intern_ident(ident.text(self.tree))),
                 range: ident.range(self.tree),
             });
 
@@ -1112,7 +1873,7 @@ impl<'a> Ctx<'a> {
             .variants(self.tree)
             .map(|variant| {
                 let name = variant.name(self.tree).map(|ident| NameWithRange {
-                    name: Name(self.interner.intern(ident.text(self.tree))),
+                    name: Name(self.interner.intern_ident(ident.text(self.tree))),
                     range: ident.range(self.tree),
                 });
 
@@ -1126,7 +1887,7 @@ impl<'a> Ctx<'a> {
 
                 VariantDecl {
                     name,
-                    uid: self.uid_gen.generate_unique_id(),
+                    uid: self.generate_uid(),
                     ty,
                     discriminant,
                 }
@@ -1134,7 +1895,7 @@ impl<'a> Ctx<'a> {
             .collect();
 
         Expr::EnumDecl {
-            uid: self.uid_gen.generate_unique_id(),
+            uid: self.generate_uid(),
             variants,
         }
     }
@@ -1143,13 +1904,48 @@ impl<'a> Ctx<'a> {
         let lhs = self.lower_expr(binary_expr.lhs(self.tree));
         let rhs = self.lower_expr(binary_expr.rhs(self.tree));
 
-        let Some(op) = self.lower_binary_op(binary_expr.op(self.tree)) else {
-            return Expr::Missing;
+        if let Some(ast::BinaryOp::Orelse(_)) = binary_expr.op(self.tree) {
+            return Expr::Orelse {
+                optional: lhs,
+                fallback: rhs,
+            };
+        }
+
+        let Some(op) = self.lower_binary_op(binary_expr.op(self.tree)) else {
+            return Expr::Missing;
         };
 
         Expr::Binary { lhs, rhs, op }
     }
 
+    fn lower_range_expr(&mut self, range_expr: ast::RangeExpr, allow_open: bool) -> Expr {
+        let start = range_expr
+            .start(self.tree)
+            .map(|start| self.lower_expr(start.value(self.tree)));
+        let end = range_expr
+            .end(self.tree)
+            .map(|end| self.lower_expr(end.value(self.tree)));
+        let inclusive = range_expr.inclusive(self.tree).is_some();
+
+        if inclusive && end.is_none() {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::InclusiveRangeWithoutEnd,
+                range: range_expr.range(self.tree),
+            });
+        } else if !allow_open && (start.is_none() || end.is_none()) {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::OpenRangeOutsideIndex,
+                range: range_expr.range(self.tree),
+            });
+        }
+
+        Expr::Range {
+            start,
+            end,
+            inclusive,
+        }
+    }
+
     fn lower_binary_op(&mut self, binary_op: Option<ast::BinaryOp>) -> Option<BinaryOp> {
         match binary_op {
             Some(ast::BinaryOp::Add(_)) => Some(BinaryOp::Add),
@@ -1170,7 +1966,8 @@ impl<'a> Ctx<'a> {
             Some(ast::BinaryOp::RShift(_)) => Some(BinaryOp::RShift),
             Some(ast::BinaryOp::LAnd(_)) => Some(BinaryOp::LAnd),
             Some(ast::BinaryOp::LOr(_)) => Some(BinaryOp::LOr),
-            None => None,
+            // `orelse` isn't a regular binary operator, see `lower_binary_expr`
+            Some(ast::BinaryOp::Orelse(_)) | None => None,
         }
     }
 
@@ -1191,12 +1988,27 @@ impl<'a> Ctx<'a> {
     fn lower_array_decl(&mut self, array_decl: ast::ArrayDecl) -> Expr {
         let size = array_decl
             .size(self.tree)
-            .and_then(|size| size.size(self.tree))
+            .and_then(|size| size.size(self.tree));
+
+        let inferred_size = matches!(
+            size,
+            Some(ast::Expr::VarRef(var_ref))
+                if var_ref
+                    .name(self.tree)
+                    .is_some_and(|name| name.text(self.tree) == "_")
+        );
+
+        let size = size
+            .filter(|_| !inferred_size)
             .map(|size| self.lower_expr(Some(size)));
 
         let ty = self.lower_expr(array_decl.ty(self.tree).and_then(|ty| ty.expr(self.tree)));
 
-        Expr::ArrayDecl { size, ty }
+        Expr::ArrayDecl {
+            size,
+            inferred_size,
+            ty,
+        }
     }
 
     fn lower_array_literal(&mut self, array_lit: ast::ArrayLiteral) -> Expr {
@@ -1204,6 +2016,18 @@ impl<'a> Ctx<'a> {
             .ty(self.tree)
             .map(|ty| self.lower_expr(ty.expr(self.tree)));
 
+        if let Some(count) = array_lit.repeat_count(self.tree) {
+            let item = self.lower_expr(
+                array_lit
+                    .items(self.tree)
+                    .next()
+                    .and_then(|item| item.value(self.tree)),
+            );
+            let count = self.lower_expr(count.count(self.tree));
+
+            return Expr::ArrayRepeat { ty, item, count };
+        }
+
         let items = array_lit
             .items(self.tree)
             .map(|item| self.lower_expr(item.value(self.tree)))
@@ -1222,13 +2046,19 @@ impl<'a> Ctx<'a> {
         })
     }
 
+    fn lower_tuple_expr(&mut self, tuple_expr: ast::TupleExpr) -> Expr {
+        let items = tuple_expr
+            .items(self.tree)
+            .map(|item| self.lower_expr(Some(item)))
+            .collect();
+
+        Expr::Tuple(items)
+    }
+
     fn lower_block(&mut self, block: ast::Block, add_block_label: bool) -> (Expr, Option<ScopeId>) {
         let label_id = if add_block_label {
             let label_id = ScopeId(self.label_gen.generate_unique_id());
-            let label_name = block
-                .label(self.tree)
-                .and_then(|label| label.name(self.tree))
-                .map(|name| self.interner.intern(name.text(self.tree)));
+            let label_name = self.lower_label_decl(block.label(self.tree));
             self.label_kinds
                 .push(ScopeKind::Block((label_name, label_id)));
             Some(label_id)
@@ -1236,7 +2066,7 @@ impl<'a> Ctx<'a> {
             None
         };
 
-        self.create_new_child_scope();
+        self.create_new_child_scope(block.range(self.tree));
 
         let mut stmts = Vec::new();
 
@@ -1305,17 +2135,25 @@ impl<'a> Ctx<'a> {
 
     fn lower_while(&mut self, while_expr: ast::WhileExpr) -> (Expr, ScopeId) {
         let label_id = ScopeId(self.label_gen.generate_unique_id());
-        let label_name = while_expr
-            .label(self.tree)
-            .and_then(|label| label.name(self.tree))
-            .map(|name| self.interner.intern(name.text(self.tree)));
+        let label_name = self.lower_label_decl(while_expr.label(self.tree));
         self.label_kinds
             .push(ScopeKind::Loop((label_name, label_id)));
 
+        let binding = while_expr
+            .condition(self.tree)
+            .and_then(|condition| condition.binding(self.tree));
+        if let Some(binding) = binding {
+            let body = self.lower_while_binding(binding, while_expr.body(self.tree), label_id);
+
+            self.label_kinds.pop();
+
+            return (Expr::Loop { body }, label_id);
+        }
+
+        // `loop` doesn't have a condition node at all, while `while` always does
         let condition = while_expr
             .condition(self.tree)
-            .and_then(|condition| condition.value(self.tree))
-            .map(|condition| self.lower_expr(Some(condition)));
+            .map(|condition| self.lower_expr(condition.value(self.tree)));
 
         let body = if let Some(ast::Expr::Block(body)) = while_expr.body(self.tree) {
             let range = body.range(self.tree);
@@ -1332,14 +2170,215 @@ impl<'a> Ctx<'a> {
 
         self.label_kinds.pop();
 
-        (Expr::While { condition, body }, label_id)
+        let expr = match condition {
+            Some(condition) => Expr::While { condition, body },
+            None => Expr::Loop { body },
+        };
+
+        (expr, label_id)
+    }
+
+    fn lower_for(&mut self, for_expr: ast::ForExpr) -> (Expr, ScopeId) {
+        let label_id = ScopeId(self.label_gen.generate_unique_id());
+        let label_name = self.lower_label_decl(for_expr.label(self.tree));
+
+        // the iterable is evaluated once, before the loop, so it can't see the item
+        // or `break` out of the loop
+        let iterable = self.lower_expr(
+            for_expr
+                .iterable(self.tree)
+                .and_then(|iterable| iterable.value(self.tree)),
+        );
+
+        self.label_kinds
+            .push(ScopeKind::Loop((label_name, label_id)));
+
+        let item_name = for_expr.item_name(self.tree);
+        let for_body = for_expr.body(self.tree);
+
+        let item = self.bodies.for_locals.alloc(ForLocal {
+            iterable,
+            range: item_name
+                .map(|name| name.range(self.tree))
+                .unwrap_or_else(|| for_expr.range(self.tree)),
+        });
+
+        // the item isn't visible from the iterable
+        self.create_new_child_scope(
+            for_body.map_or_else(|| for_expr.range(self.tree), |body| body.range(self.tree)),
+        );
+
+        if let Some(item_name) = item_name {
+            let name = self.interner.intern_ident(item_name.text(self.tree));
+            self.insert_into_current_scope(name, Local::ForItem(item));
+        }
+
+        let body = if let Some(ast::Expr::Block(body)) = for_body {
+            let range = body.range(self.tree);
+
+            let (expr, _) = self.lower_block(body, false);
+
+            let id = self.bodies.exprs.alloc(expr);
+            self.bodies.expr_ranges.insert(id, range);
+
+            id
+        } else {
+            self.bodies.exprs.alloc(Expr::Missing)
+        };
+
+        self.destroy_current_scope();
+
+        self.label_kinds.pop();
+
+        (
+            Expr::For {
+                item,
+                iterable,
+                body,
+            },
+            label_id,
+        )
+    }
+
+    /// `while x := next() { ... }` is desugared into
+    /// ```text
+    /// loop {
+    ///     opt := next();
+    ///     if opt == null { break; }
+    ///     x := #unwrap(opt);
+    ///     { ... }
+    /// }
+    /// ```
+    /// so that `x` is the payload of the optional and is only in scope for the body.
+    /// `opt` isn't put into any scope, so it can't be named by the user.
+    fn lower_while_binding(
+        &mut self,
+        binding: ast::Define,
+        body: Option<ast::Expr>,
+        label_id: ScopeId,
+    ) -> Idx<Expr> {
+        let binding_range = binding.range(self.tree);
+
+        self.create_new_child_scope(body.as_ref().map_or(binding_range, |body| {
+            binding_range.cover(body.range(self.tree))
+        }));
+
+        let ty = binding
+            .ty(self.tree)
+            .and_then(|ty| ty.expr(self.tree))
+            .map(|expr| self.lower_expr(Some(expr)));
+        let value = binding
+            .value(self.tree)
+            .map(|expr| self.lower_expr(Some(expr)));
+
+        let optional_def = self.bodies.local_defs.alloc(LocalDef {
+            mutable: false,
+            ty: None,
+            value,
+            ast: binding,
+            range: binding_range,
+        });
+        let optional_stmt = self.bodies.stmts.alloc(Stmt::LocalDef(optional_def));
+
+        let alloc_expr = |bodies: &mut Bodies, expr: Expr| {
+            let id = bodies.exprs.alloc(expr);
+            bodies.expr_ranges.insert(id, binding_range);
+            id
+        };
+
+        let optional = alloc_expr(&mut self.bodies, Expr::Local(optional_def));
+        let null = alloc_expr(&mut self.bodies, Expr::NullLiteral);
+        let condition = alloc_expr(
+            &mut self.bodies,
+            Expr::Binary {
+                lhs: optional,
+                rhs: null,
+                op: BinaryOp::Eq,
+            },
+        );
+
+        let break_stmt = self.bodies.stmts.alloc(Stmt::Break {
+            label: Some(label_id),
+            value: None,
+            range: binding_range,
+        });
+        self.bodies
+            .scope_usages
+            .entry(label_id)
+            .or_default()
+            .push(break_stmt);
+
+        let break_block = alloc_expr(
+            &mut self.bodies,
+            Expr::Block {
+                stmts: vec![break_stmt],
+                tail_expr: None,
+            },
+        );
+        let if_null = alloc_expr(
+            &mut self.bodies,
+            Expr::If {
+                condition,
+                body: break_block,
+                else_branch: None,
+            },
+        );
+        let if_null_stmt = self.bodies.stmts.alloc(Stmt::Expr(if_null));
+
+        let optional = alloc_expr(&mut self.bodies, Expr::Local(optional_def));
+        let payload = alloc_expr(
+            &mut self.bodies,
+            Expr::Directive {
+                name: NameWithRange {
+                    name: Name(self.interner.intern_ident("unwrap")),
+                    range: binding_range,
+                },
+                args: vec![optional],
+            },
+        );
+
+        let payload_def = self.bodies.local_defs.alloc(LocalDef {
+            mutable: matches!(binding, ast::Define::Variable(_)),
+            ty,
+            value: Some(payload),
+            ast: binding,
+            range: binding_range,
+        });
+        if let Some(ident) = binding.name(self.tree) {
+            let name = self.interner.intern_ident(ident.text(self.tree));
+            self.insert_into_current_scope(name, Local::Def(payload_def));
+        }
+        let payload_stmt = self.bodies.stmts.alloc(Stmt::LocalDef(payload_def));
+
+        let (body, body_range) = if let Some(ast::Expr::Block(body)) = body {
+            let range = body.range(self.tree);
+
+            let (expr, _) = self.lower_block(body, false);
+
+            let id = self.bodies.exprs.alloc(expr);
+            self.bodies.expr_ranges.insert(id, range);
+
+            (id, range)
+        } else {
+            (self.bodies.exprs.alloc(Expr::Missing), binding_range)
+        };
+
+        self.destroy_current_scope();
+
+        let loop_body = self.bodies.exprs.alloc(Expr::Block {
+            stmts: vec![optional_stmt, if_null_stmt, payload_stmt],
+            tail_expr: Some(body),
+        });
+        self.bodies.expr_ranges.insert(loop_body, body_range);
+
+        loop_body
     }
 
     fn lower_switch(&mut self, switch_expr: ast::SwitchExpr) -> Expr {
         let variable_name = switch_expr
             .variable_name(self.tree)
             .map(|name| NameWithRange {
-                name: Name(self.interner.intern(name.text(self.tree))),
+                name: Name(self.interner.intern_ident(name.text(self.tree))),
                 range: name.range(self.tree),
             });
         let scrutinee = self.lower_expr(switch_expr.scrutinee(self.tree));
@@ -1350,7 +2389,7 @@ impl<'a> Ctx<'a> {
             .arms(self.tree)
             .filter_map(|arm| {
                 let variant_name = arm.variant_name(self.tree).map(|name| NameWithRange {
-                    name: Name(self.interner.intern(name.text(self.tree))),
+                    name: Name(self.interner.intern_ident(name.text(self.tree))),
                     range: name.range(self.tree),
                 });
 
@@ -1379,6 +2418,19 @@ impl<'a> Ctx<'a> {
 
                 let body = self.lower_expr(arm.body(self.tree));
 
+                if let (Some(variable_name), Some(switch_local)) = (variable_name, switch_local) {
+                    if let Some(range) = self.bodies.expr_ranges.get(body) {
+                        self.bodies.scopes.alloc(ScopeData {
+                            range: *range,
+                            entries: vec![ScopeEntry {
+                                name: variable_name.name,
+                                def: ScopeDef::SwitchLocal(switch_local),
+                                visible_from: range.start(),
+                            }],
+                        });
+                    }
+                }
+
                 let arm = SwitchArm {
                     variant_name,
                     body,
@@ -1438,7 +2490,10 @@ impl<'a> Ctx<'a> {
         if is_import || is_mod {
             return self.lower_import(directive, is_mod);
         }
-        let name_text = self.interner.intern(name_text);
+        if name_text == "this" {
+            return self.lower_this(directive);
+        }
+        let name_text = self.interner.intern_ident(name_text);
 
         let mut args = Vec::new();
 
@@ -1458,6 +2513,142 @@ impl<'a> Ctx<'a> {
         }
     }
 
+    /// `#this()` is the global struct or module it's written in, or the current file
+    fn lower_this(&mut self, directive: ast::Directive) -> Expr {
+        if let Some(arg_list) = directive
+            .arg_list(self.tree)
+            .filter(|arg_list| arg_list.args(self.tree).next().is_some())
+        {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::ThisWithArgs,
+                range: arg_list.range(self.tree),
+            });
+        }
+
+        match self.namespace {
+            Some(name) => Expr::LocalGlobal(NameWithRange {
+                name,
+                range: directive.range(self.tree),
+            }),
+            None => Expr::Import(FileName(
+                self.interner.intern(&self.file_name.to_string_lossy()),
+            )),
+        }
+    }
+
+    /// `#requires_version(">=0.2");` and `#requires_feature("using");` let a file say
+    /// which compilers are able to build it
+    fn lower_requirement(&mut self, expr_stmt: ast::ExprStmt) {
+        let Some(expr) = expr_stmt.expr(self.tree) else {
+            return;
+        };
+
+        let directive = match expr {
+            ast::Expr::Directive(directive) => directive,
+            _ => {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::NonRequirementAtRoot,
+                    range: expr.range(self.tree),
+                });
+                return;
+            }
+        };
+
+        let feature = match directive.name(self.tree).map(|name| name.text(self.tree)) {
+            Some("requires_version") => false,
+            Some("requires_feature") => true,
+            Some(_) => {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::NonRequirementAtRoot,
+                    range: expr.range(self.tree),
+                });
+                return;
+            }
+            None => return,
+        };
+
+        let args = directive
+            .arg_list(self.tree)
+            .map(|arg_list| arg_list.args(self.tree).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let string_literal = match args.as_slice() {
+            [arg] => match arg.value(self.tree) {
+                Some(ast::Expr::StringLiteral(string_literal)) => Some(string_literal),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(string_literal) = string_literal else {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::RequirementBadArgs { feature },
+                range: directive
+                    .arg_list(self.tree)
+                    .map_or(directive.range(self.tree), |arg_list| {
+                        arg_list.range(self.tree)
+                    }),
+            });
+            return;
+        };
+
+        let old_diags_len = self.diagnostics.len();
+        let text = match self.lower_string_literal(string_literal) {
+            Expr::StringLiteral(text) => text,
+            _ => unreachable!(),
+        };
+        if self.diagnostics.len() != old_diags_len {
+            return;
+        }
+
+        let kind = if feature {
+            if SUPPORTED_FEATURES.contains(&text.as_str()) {
+                return;
+            }
+            LoweringDiagnosticKind::UnsupportedFeature { feature: text }
+        } else {
+            match version_matches(&text, COMPILER_VERSION) {
+                Some(true) => return,
+                Some(false) => LoweringDiagnosticKind::UnsupportedVersion { requirement: text },
+                None => LoweringDiagnosticKind::InvalidVersionRequirement { requirement: text },
+            }
+        };
+
+        self.diagnostics.push(LoweringDiagnostic {
+            kind,
+            range: string_literal.range(self.tree),
+        });
+    }
+
+    /// `using #import("foo.capy");` brings the globals of another file into scope.
+    /// the names themselves get resolved during type checking, once every file has been indexed
+    fn lower_using(&mut self, using: ast::Using) {
+        let Some(file) = using.file(self.tree) else {
+            return;
+        };
+
+        let import = match file {
+            ast::Expr::Directive(directive) => {
+                match directive.name(self.tree).map(|name| name.text(self.tree)) {
+                    Some("import") => Some(self.lower_import(directive, false)),
+                    Some("mod") => Some(self.lower_import(directive, true)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        match import {
+            Some(Expr::Import(file_name)) => {
+                self.bodies.usings.push((file_name, using.range(self.tree)));
+            }
+            // the import has already reported what went wrong
+            Some(_) => {}
+            None => self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UsingNonImport,
+                range: file.range(self.tree),
+            }),
+        }
+    }
+
     fn lower_import(&mut self, directive: ast::Directive, is_mod: bool) -> Expr {
         let Some(arg_list) = directive.arg_list(self.tree) else {
             return Expr::Missing;
@@ -1604,8 +2795,22 @@ impl<'a> Ctx<'a> {
             Some(array) => self.lower_expr(array.value(self.tree)),
             None => unreachable!(),
         };
-        let index = match index_expr.index(self.tree) {
-            Some(index) => self.lower_expr(index.value(self.tree)),
+        let index = match index_expr
+            .index(self.tree)
+            .map(|index| index.value(self.tree))
+        {
+            // slicing is the only place where a range can leave out its start or end
+            Some(Some(ast::Expr::Range(range_expr))) => {
+                let expr = self.lower_range_expr(range_expr, true);
+
+                let id = self.bodies.exprs.alloc(expr);
+                self.bodies
+                    .expr_ranges
+                    .insert(id, range_expr.range(self.tree));
+
+                id
+            }
+            Some(index) => self.lower_expr(index),
             None => unreachable!(),
         };
 
@@ -1616,11 +2821,13 @@ impl<'a> Ctx<'a> {
     }
 
     fn lower_path(&mut self, path: ast::Path) -> Expr {
-        let field = match path.field_name(self.tree) {
-            Some(field) => field,
-            None => return Expr::Missing,
+        // `tuple.0` is a member access with the name `0`
+        let field = match (path.field_name(self.tree), path.field_index(self.tree)) {
+            (Some(field), _) => field.syntax(),
+            (None, Some(index)) => index.syntax(),
+            (None, None) => return Expr::Missing,
         };
-        let field_name = self.interner.intern(field.text(self.tree));
+        let field_name = self.interner.intern_ident(field.text(self.tree));
 
         let previous = path.previous_part(self.tree);
 
@@ -1638,21 +2845,50 @@ impl<'a> Ctx<'a> {
             Some(ident) => ident,
             None => return Expr::Missing,
         };
-        let ident_name = self.interner.intern(ident.text(self.tree));
+        let ident_name = self.interner.intern_ident(ident.text(self.tree));
 
         match self.look_up_in_current_scope(ident_name) {
             Some(Local::Def(local_def)) => return Expr::Local(local_def),
             Some(Local::SwitchArm(local_arm_var)) => return Expr::SwitchLocal(local_arm_var),
+            Some(Local::ForItem(for_local)) => return Expr::ForLocal(for_local),
             None => {}
         }
 
         if let Some((idx, ast)) = self.look_up_param(ident_name) {
+            self.used_params.insert(idx);
             return Expr::Param {
                 idx,
                 range: ast.range(self.tree),
             };
         }
 
+        if let Some(captured) = self.look_up_capture(ident_name, ident.range(self.tree)) {
+            return captured;
+        }
+
+        // declarations inside of a struct or module can refer to each other without
+        // the struct or module's name. the innermost declaration wins
+        let mut namespace = self
+            .namespace
+            .map(|namespace| self.interner.lookup(namespace.0).to_string());
+        while let Some(current) = namespace {
+            let name = self
+                .interner
+                .get(&format!("{}.{}", current, self.interner.lookup(ident_name)))
+                .map(Name);
+
+            if let Some(name) = name.filter(|name| self.index.has_definition(*name)) {
+                return Expr::LocalGlobal(NameWithRange {
+                    name,
+                    range: ident.range(self.tree),
+                });
+            }
+
+            namespace = current
+                .rsplit_once('.')
+                .map(|(parent, _)| parent.to_string());
+        }
+
         let name = Name(ident_name);
         if self.index.has_definition(name) {
             return Expr::LocalGlobal(NameWithRange {
@@ -1667,6 +2903,19 @@ impl<'a> Ctx<'a> {
             return Expr::PrimitiveTy(ty);
         }
 
+        if self.interner.lookup(ident_name) == "null" {
+            return Expr::NullLiteral;
+        }
+
+        // the name might come from a `using`d file, which can only be checked once every
+        // file has been indexed
+        if !self.bodies.usings.is_empty() {
+            return Expr::LocalGlobal(NameWithRange {
+                name,
+                range: ident.range(self.tree),
+            });
+        }
+
         self.diagnostics.push(LoweringDiagnostic {
             kind: LoweringDiagnosticKind::UndefinedRef { name: name.0 },
             range: ident.range(self.tree),
@@ -1675,6 +2924,46 @@ impl<'a> Ctx<'a> {
         Expr::Missing
     }
 
+    /// the text of a number literal's token, its range, and whether or not it's hex
+    fn literal_token(&self, literal: ast::Expr) -> Option<(&'a str, TextRange, bool)> {
+        Some(match literal {
+            ast::Expr::IntLiteral(int_literal) => match int_literal.value(self.tree)? {
+                ast::IntValue::Dec(dec) => (dec.text(self.tree), dec.range(self.tree), false),
+                ast::IntValue::Hex(hex) => (hex.text(self.tree), hex.range(self.tree), true),
+                ast::IntValue::Bin(bin) => (bin.text(self.tree), bin.range(self.tree), false),
+            },
+            ast::Expr::FloatLiteral(float_literal) => {
+                let value = float_literal.value(self.tree)?;
+                (value.text(self.tree), value.range(self.tree), false)
+            }
+            _ => return None,
+        })
+    }
+
+    fn lower_literal_suffix(&self, literal: ast::Expr) -> Option<PrimitiveTy> {
+        let (text, range, hex) = self.literal_token(literal)?;
+
+        let suffix = split_literal_suffix(text, hex).1?;
+        let range = TextRange::new(range.end() - TextSize::of(suffix), range.end());
+
+        PrimitiveTy::parse_literal_suffix(suffix, range)
+    }
+
+    /// only literals like `1_000` or `0xFF`, which can't be printed back out from their value
+    /// alone, need to remember how they were written
+    fn lower_written_literal(&self, literal: ast::Expr, lowered: &Expr) -> Option<String> {
+        let (text, _, hex) = self.literal_token(literal)?;
+        let text = split_literal_suffix(text, hex).0;
+
+        let value = match lowered {
+            Expr::IntLiteral(n) => n.to_string(),
+            Expr::FloatLiteral(n) => n.to_string(),
+            _ => return None,
+        };
+
+        (text != value).then(|| text.to_string())
+    }
+
     fn lower_int_literal(&mut self, int_literal: ast::IntLiteral) -> Expr {
         let Some(value) = int_literal.value(self.tree) else {
             return Expr::Missing;
@@ -1682,7 +2971,8 @@ impl<'a> Ctx<'a> {
 
         match value {
             ast::IntValue::Dec(dec) => {
-                let value = dec.text(self.tree).replace('_', "");
+                let (value, suffix) = split_literal_suffix(dec.text(self.tree), false);
+                let value = value.replace('_', "");
                 let mut value = value.split(['e', 'E']);
 
                 // there will always be a first part
@@ -1713,12 +3003,18 @@ impl<'a> Ctx<'a> {
                     base
                 };
 
-                Expr::IntLiteral(val)
+                // `10f32` is a float, even though it was written like an integer
+                if suffix.is_some_and(|suffix| suffix.starts_with('f')) {
+                    Expr::FloatLiteral(val as f64)
+                } else {
+                    Expr::IntLiteral(val)
+                }
             }
             ast::IntValue::Hex(hex) => {
-                let value = hex.text(self.tree).strip_prefix("0x").unwrap();
+                let (value, _) = split_literal_suffix(hex.text(self.tree), true);
+                let value = value.strip_prefix("0x").unwrap().replace('_', "");
 
-                match u64::from_str_radix(value, 16) {
+                match u64::from_str_radix(&value, 16) {
                     Ok(value) => Expr::IntLiteral(value),
                     Err(_) => {
                         self.diagnostics.push(LoweringDiagnostic {
@@ -1731,9 +3027,10 @@ impl<'a> Ctx<'a> {
                 }
             }
             ast::IntValue::Bin(bin) => {
-                let value = bin.text(self.tree).strip_prefix("0b").unwrap();
+                let (value, _) = split_literal_suffix(bin.text(self.tree), false);
+                let value = value.strip_prefix("0b").unwrap().replace('_', "");
 
-                match u64::from_str_radix(value, 2) {
+                match u64::from_str_radix(&value, 2) {
                     Ok(value) => Expr::IntLiteral(value),
                     Err(_) => {
                         self.diagnostics.push(LoweringDiagnostic {
@@ -1749,11 +3046,26 @@ impl<'a> Ctx<'a> {
     }
 
     fn lower_float_literal(&mut self, float_literal: ast::FloatLiteral) -> Expr {
-        let value = float_literal
+        let value: f64 = float_literal
             .value(self.tree)
-            .and_then(|int| int.text(self.tree).replace('_', "").parse().ok())
+            .and_then(|float| {
+                split_literal_suffix(float.text(self.tree), false)
+                    .0
+                    .replace('_', "")
+                    .parse()
+                    .ok()
+            })
             .unwrap();
 
+        // rust happily parses `1e400` as infinity
+        if value.is_infinite() {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::OutOfRangeFloatLiteral,
+                range: float_literal.range(self.tree),
+            });
+            return Expr::Missing;
+        }
+
         Expr::FloatLiteral(value)
     }
 
@@ -1771,88 +3083,116 @@ impl<'a> Ctx<'a> {
 
     fn lower_string_literal(&mut self, string_literal: ast::StringLiteral) -> Expr {
         let mut text = String::new();
+        let mut first_line = true;
 
         for component in string_literal.components(self.tree) {
             match component {
                 ast::StringComponent::Escape(escape) => {
-                    let escape_text = escape.text(self.tree);
-                    let mut chars = escape_text.chars();
-                    if cfg!(debug_assertions) {
-                        assert_eq!(chars.next(), Some('\\'));
-                    } else {
-                        chars.next();
-                    }
-
-                    let escape_char = chars.next().unwrap();
-                    debug_assert!(chars.next().is_none());
-
-                    match escape_char {
-                        '0' => text.push('\0'),   // null
-                        'a' => text.push('\x07'), // bell (BEL)
-                        'b' => text.push('\x08'), // backspace
-                        'n' => text.push('\n'),   // line feed (new line)
-                        'f' => text.push('\x0C'), // form feed (new page)
-                        'r' => text.push('\r'),   // carraige return
-                        't' => text.push('\t'),   // horizontal tab
-                        'v' => text.push('\x0B'), // vertical tab
-                        'e' => text.push('\x1B'), // escape
-                        '"' => text.push('"'),
-                        '\'' => text.push('\''),
-                        '\\' => text.push('\\'),
-                        _ => self.diagnostics.push(LoweringDiagnostic {
-                            kind: LoweringDiagnosticKind::InvalidEscape,
-                            range: escape.range(self.tree),
-                        }),
+                    if let Some(ch) = self.lower_escape(escape, true) {
+                        text.push(ch);
                     }
                 }
                 ast::StringComponent::Contents(contents) => {
                     text.push_str(contents.text(self.tree));
                 }
+                ast::StringComponent::LineLeader(_) => {
+                    if !first_line {
+                        text.push('\n');
+                    }
+                    first_line = false;
+                }
             }
         }
 
         Expr::StringLiteral(text)
     }
 
-    fn lower_char_literal(&mut self, char_literal: ast::CharLiteral) -> Expr {
-        let mut text = String::new();
-
-        let mut total_len = 0;
-        for component in char_literal.components(self.tree) {
-            match component {
-                ast::StringComponent::Escape(escape) => {
-                    // we do this instead of text.len() because just below
-                    // an escape sequence has the chance to add nothing to text
-                    total_len += 1;
-
-                    let escape_text = escape.text(self.tree);
-                    let mut chars = escape_text.chars();
-                    if cfg!(debug_assertions) {
-                        assert_eq!(chars.next(), Some('\\'));
-                    } else {
-                        chars.next();
-                    }
+    /// Gives back the character an escape sequence stands for, or `None` if it's malformed.
+    ///
+    /// A `char` is a single byte, so `'\xFF'` is fine, but `\xNN` can only be ASCII within a
+    /// string since anything higher wouldn't be valid UTF-8 on its own
+    fn lower_escape(&mut self, escape: ast::Escape, in_string: bool) -> Option<char> {
+        let escape_text = escape.text(self.tree);
+        let mut chars = escape_text.chars();
+        if cfg!(debug_assertions) {
+            assert_eq!(chars.next(), Some('\\'));
+        } else {
+            chars.next();
+        }
 
-                    let escape_char = chars.next().unwrap();
-                    debug_assert!(chars.next().is_none());
-
-                    match escape_char {
-                        '0' => text.push('\0'),   // null
-                        'a' => text.push('\x07'), // bell (BEL)
-                        'b' => text.push('\x08'), // backspace
-                        'n' => text.push('\n'),   // line feed (new line)
-                        'f' => text.push('\x0C'), // form feed (new page)
-                        'r' => text.push('\r'),   // carraige return
-                        't' => text.push('\t'),   // horizontal tab
-                        'v' => text.push('\x0B'), // vertical tab
-                        'e' => text.push('\x1B'), // escape
-                        '\'' => text.push('\''),
-                        '"' => text.push('"'),
-                        '\\' => text.push('\\'),
-                        _ => self.diagnostics.push(LoweringDiagnostic {
-                            kind: LoweringDiagnosticKind::InvalidEscape,
-                            range: escape.range(self.tree),
-                        }),
+        let mut report = |kind| {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind,
+                range: escape.range(self.tree),
+            });
+            None
+        };
+
+        // a multiline string can have a `\` right at the end of a line
+        let Some(escape_char) = chars.next() else {
+            return report(LoweringDiagnosticKind::InvalidEscape);
+        };
+        let rest = chars.as_str();
+
+        match escape_char {
+            '0' => Some('\0'),   // null
+            'a' => Some('\x07'), // bell (BEL)
+            'b' => Some('\x08'), // backspace
+            'n' => Some('\n'),   // line feed (new line)
+            'f' => Some('\x0C'), // form feed (new page)
+            'r' => Some('\r'),   // carraige return
+            't' => Some('\t'),   // horizontal tab
+            'v' => Some('\x0B'), // vertical tab
+            'e' => Some('\x1B'), // escape
+            '\'' => Some('\''),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            'x' => {
+                // the lexer only ever gives up to two digits
+                if rest.len() != 2 {
+                    return report(LoweringDiagnosticKind::InvalidHexEscape);
+                }
+
+                let value = u8::from_str_radix(rest, 16).unwrap();
+                if in_string && !value.is_ascii() {
+                    return report(LoweringDiagnosticKind::NonAsciiHexEscapeInString);
+                }
+
+                Some(value as char)
+            }
+            'u' => {
+                let digits = rest
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.strip_suffix('}'))
+                    .map(|digits| digits.replace('_', ""))
+                    .filter(|digits| (1..=6).contains(&digits.len()));
+                let Some(digits) = digits else {
+                    return report(LoweringDiagnosticKind::InvalidUnicodeEscape);
+                };
+
+                let value = u32::from_str_radix(&digits, 16).unwrap();
+                match char::from_u32(value) {
+                    Some(ch) => Some(ch),
+                    None => report(LoweringDiagnosticKind::InvalidUnicodeChar { value }),
+                }
+            }
+            _ => report(LoweringDiagnosticKind::InvalidEscape),
+        }
+    }
+
+    fn lower_char_literal(&mut self, char_literal: ast::CharLiteral) -> Expr {
+        let mut text = String::new();
+
+        let mut total_len = 0;
+        for component in char_literal.components(self.tree) {
+            match component {
+                ast::StringComponent::Escape(escape) => {
+                    // we do this instead of text.len() because just below
+                    // an escape sequence has the chance to add nothing to text
+                    total_len += 1;
+
+                    if let Some(ch) = self.lower_escape(escape, false) {
+                        text.push(ch);
                     }
                 }
                 ast::StringComponent::Contents(contents) => {
@@ -1861,6 +3201,7 @@ impl<'a> Ctx<'a> {
                     total_len += contents.chars().count();
                     text.push_str(contents);
                 }
+                ast::StringComponent::LineLeader(_) => unreachable!("char literals are one line"),
             }
         }
 
@@ -1902,6 +3243,24 @@ impl<'a> Ctx<'a> {
     fn insert_into_current_scope(&mut self, name: Key, local: Local) {
         let last_scope = self.scopes.last_mut().unwrap();
         last_scope.insert(name, local);
+
+        // switch locals get a scope of their own for each arm, see `lower_switch`
+        if matches!(local, Local::SwitchArm(_)) {
+            return;
+        }
+
+        if let Some(scope_data) = self.scope_data.last() {
+            let scope_data = &mut self.bodies.scopes[*scope_data];
+            let visible_from = match local {
+                Local::Def(def) => self.bodies.local_defs[def].range.end(),
+                _ => scope_data.range.start(),
+            };
+            scope_data.entries.push(ScopeEntry {
+                name: Name(name),
+                def: local.into(),
+                visible_from,
+            });
+        }
     }
 
     fn look_up_in_current_scope(&mut self, name: Key) -> Option<Local> {
@@ -1918,12 +3277,77 @@ impl<'a> Ctx<'a> {
         self.params.get(&name).copied()
     }
 
-    fn create_new_child_scope(&mut self) {
+    /// Finds a local or parameter of an enclosing function, and captures it into each of the
+    /// lambdas between there and here
+    fn look_up_capture(&mut self, name: Key, range: TextRange) -> Option<Expr> {
+        let (depth, mut value) =
+            self.enclosing
+                .iter()
+                .enumerate()
+                .rev()
+                .find_map(|(depth, enclosing)| {
+                    let local = enclosing
+                        .scopes
+                        .iter()
+                        .rev()
+                        .find_map(|scope| scope.get(&name));
+
+                    let value = match local {
+                        Some(Local::Def(local_def)) => Expr::Local(*local_def),
+                        Some(Local::SwitchArm(switch_local)) => Expr::SwitchLocal(*switch_local),
+                        Some(Local::ForItem(for_local)) => Expr::ForLocal(*for_local),
+                        None => {
+                            let (idx, param) = enclosing.params.get(&name)?;
+                            Expr::Param {
+                                idx: *idx,
+                                range: param.range(self.tree),
+                            }
+                        }
+                    };
+
+                    Some((depth, value))
+                })?;
+
+        for enclosing in &mut self.enclosing[depth..] {
+            let existing = enclosing
+                .captures
+                .iter()
+                .copied()
+                .find(|capture| self.bodies.captures[*capture].name == Name(name));
+
+            let capture = match existing {
+                Some(capture) => capture,
+                None => {
+                    let value = self.bodies.exprs.alloc(value);
+                    self.bodies.expr_ranges.insert(value, range);
+
+                    let capture = self.bodies.captures.alloc(Capture {
+                        name: Name(name),
+                        value,
+                    });
+                    enclosing.captures.push(capture);
+
+                    capture
+                }
+            };
+
+            value = Expr::Captured(capture);
+        }
+
+        Some(value)
+    }
+
+    fn create_new_child_scope(&mut self, range: TextRange) {
         self.scopes.push(FxHashMap::default());
+        self.scope_data.push(self.bodies.scopes.alloc(ScopeData {
+            range,
+            entries: Vec::new(),
+        }));
     }
 
     fn destroy_current_scope(&mut self) {
         self.scopes.pop();
+        self.scope_data.pop();
     }
 }
 
@@ -2024,352 +3448,432 @@ impl Iterator for Descendants<'_> {
                 actually_return,
             } = self.todo.pop()?;
 
-            let include_eval = matches!(
+            push_children(
+                self.bodies,
                 self.opts,
-                DescentOpts::Infer | DescentOpts::Reinfer | DescentOpts::All { .. }
+                next,
+                actually_return,
+                &mut self.todo,
             );
-            let include_types = matches!(
+
+            if actually_return {
+                return Some(next);
+            }
+        }
+    }
+}
+
+/// The exact reverse of `Descendants`, without having to collect everything into a `Vec` first.
+///
+/// Children will always come before their parents, and earlier statements will always come
+/// before later statements.
+pub struct ReverseDescendants<'a> {
+    bodies: &'a Bodies,
+    opts: DescentOpts<'a>,
+    /// the `bool` is true if the children of the descendant have already been pushed
+    todo: Vec<(PossibleDescendant, bool)>,
+    children: Vec<PossibleDescendant>,
+}
+
+impl Iterator for ReverseDescendants<'_> {
+    type Item = Descendant;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (possible, expanded) = self.todo.pop()?;
+
+            if expanded {
+                if possible.actually_return {
+                    return Some(possible.descendant);
+                }
+                continue;
+            }
+
+            self.todo.push((possible, true));
+
+            push_children(
+                self.bodies,
                 self.opts,
-                DescentOpts::Types { .. } | DescentOpts::All { .. }
+                possible.descendant,
+                possible.actually_return,
+                &mut self.children,
             );
-            let is_all = matches!(self.opts, DescentOpts::All { .. });
-
-            match next {
-                Descendant::Expr(expr) => {
-                    match self.bodies[expr].clone() {
-                        Expr::Missing => {}
-                        Expr::IntLiteral(_) => {}
-                        Expr::FloatLiteral(_) => {}
-                        Expr::BoolLiteral(_) => {}
-                        Expr::StringLiteral(_) => {}
-                        Expr::CharLiteral(_) => {}
-                        Expr::ArrayDecl { size, ty } => {
-                            if include_eval {
-                                if let Some(size) = size {
-                                    self.todo.push(PossibleDescendant::expr(size, true));
-                                }
-                            }
 
-                            self.todo.push(PossibleDescendant::expr(ty, include_types));
-                        }
-                        Expr::ArrayLiteral { ty, items } => {
-                            if let Some(ty) = ty {
-                                self.todo.push(PossibleDescendant::expr(ty, include_types));
-                            }
+            // `Descendants` visits the last pushed child first, so the first pushed child has to
+            // be visited first here
+            self.todo
+                .extend(self.children.drain(..).rev().map(|child| (child, false)));
+        }
+    }
+}
 
-                            if include_eval {
-                                self.todo.extend(
-                                    items
-                                        .into_iter()
-                                        .rev()
-                                        .map(|expr| PossibleDescendant::expr(expr, true)),
-                                );
-                            }
-                        }
-                        Expr::Index { source, index } => {
-                            self.todo
-                                .push(PossibleDescendant::expr(source, actually_return));
-                            self.todo
-                                .push(PossibleDescendant::expr(index, actually_return));
-                        }
-                        Expr::Ref { expr, .. } => {
-                            self.todo
-                                .push(PossibleDescendant::expr(expr, actually_return));
+fn push_children(
+    bodies: &Bodies,
+    opts: DescentOpts,
+    next: Descendant,
+    actually_return: bool,
+    todo: &mut Vec<PossibleDescendant>,
+) {
+    let include_eval = matches!(
+        opts,
+        DescentOpts::Infer | DescentOpts::Reinfer | DescentOpts::All { .. }
+    );
+    let include_types = matches!(opts, DescentOpts::Types { .. } | DescentOpts::All { .. });
+    let is_all = matches!(opts, DescentOpts::All { .. });
+
+    match next {
+        Descendant::Expr(expr) => {
+            match bodies[expr].clone() {
+                Expr::Missing => {}
+                Expr::IntLiteral(_) => {}
+                Expr::FloatLiteral(_) => {}
+                Expr::BoolLiteral(_) => {}
+                Expr::StringLiteral(_) => {}
+                Expr::CharLiteral(_) => {}
+                Expr::NullLiteral => {}
+                Expr::ArrayDecl { size, ty, .. } => {
+                    if include_eval {
+                        if let Some(size) = size {
+                            todo.push(PossibleDescendant::expr(size, true));
                         }
-                        Expr::Cast { expr: None, .. } => {}
-                        Expr::Cast {
-                            expr: Some(expr),
-                            ty,
-                        } => {
-                            self.todo.push(PossibleDescendant::expr(ty, include_types));
+                    }
+
+                    todo.push(PossibleDescendant::expr(ty, include_types));
+                }
+                Expr::ArrayLiteral { ty, items } => {
+                    if let Some(ty) = ty {
+                        todo.push(PossibleDescendant::expr(ty, include_types));
+                    }
+
+                    if include_eval {
+                        todo.extend(
+                            items
+                                .into_iter()
+                                .rev()
+                                .map(|expr| PossibleDescendant::expr(expr, true)),
+                        );
+                    }
+                }
+                Expr::ArrayRepeat { ty, item, count } => {
+                    if let Some(ty) = ty {
+                        todo.push(PossibleDescendant::expr(ty, include_types));
+                    }
+
+                    if include_eval {
+                        todo.push(PossibleDescendant::expr(count, true));
+                        todo.push(PossibleDescendant::expr(item, true));
+                    }
+                }
+                Expr::Index { source, index } => {
+                    todo.push(PossibleDescendant::expr(source, actually_return));
+                    todo.push(PossibleDescendant::expr(index, actually_return));
+                }
+                Expr::Ref { expr, .. } => {
+                    todo.push(PossibleDescendant::expr(expr, actually_return));
+                }
+                Expr::Cast { expr: None, .. } => {}
+                Expr::Cast {
+                    expr: Some(expr),
+                    ty,
+                } => {
+                    todo.push(PossibleDescendant::expr(ty, include_types));
 
-                            if include_eval {
-                                self.todo
-                                    .push(PossibleDescendant::expr(expr, actually_return));
+                    if include_eval {
+                        todo.push(PossibleDescendant::expr(expr, actually_return));
+                    }
+                }
+                Expr::Deref { pointer: expr } | Expr::Unary { expr, .. } => {
+                    if include_eval {
+                        todo.push(PossibleDescendant::expr(expr, actually_return));
+                    }
+                }
+                Expr::Member { previous, .. } => {
+                    if include_eval {
+                        todo.push(PossibleDescendant::expr(previous, true));
+                    }
+                }
+                Expr::Binary { lhs, rhs, .. } => {
+                    todo.push(PossibleDescendant::expr(lhs, actually_return));
+                    todo.push(PossibleDescendant::expr(rhs, actually_return));
+                }
+                Expr::Orelse { optional, fallback } => {
+                    todo.push(PossibleDescendant::expr(optional, actually_return));
+                    todo.push(PossibleDescendant::expr(fallback, actually_return));
+                }
+                Expr::Range { start, end, .. } => {
+                    if let Some(start) = start {
+                        todo.push(PossibleDescendant::expr(start, actually_return));
+                    }
+                    if let Some(end) = end {
+                        todo.push(PossibleDescendant::expr(end, actually_return));
+                    }
+                }
+                Expr::Paren(Some(expr)) => {
+                    todo.push(PossibleDescendant::expr(expr, actually_return))
+                }
+                Expr::Paren(None) => {}
+                Expr::Tuple(items) => {
+                    todo.extend(
+                        items
+                            .into_iter()
+                            .rev()
+                            .map(|item| PossibleDescendant::expr(item, actually_return)),
+                    );
+                }
+                Expr::Block { stmts, tail_expr } => {
+                    match opts {
+                        DescentOpts::Infer | DescentOpts::All { .. } => {
+                            todo.extend(
+                                stmts
+                                    .into_iter()
+                                    .map(|stmt| PossibleDescendant::stmt(stmt, actually_return)),
+                            );
+
+                            if let Some(tail_expr) = tail_expr {
+                                todo.push(PossibleDescendant::expr(tail_expr, actually_return));
                             }
                         }
-                        Expr::Deref { pointer: expr } | Expr::Unary { expr, .. } => {
-                            if include_eval {
-                                self.todo
-                                    .push(PossibleDescendant::expr(expr, actually_return));
+                        DescentOpts::Reinfer => {
+                            if let Some(id) = bodies.block_to_scope_id(expr) {
+                                todo.extend(
+                                    bodies.scope_id_usages(id).iter().copied().map(|stmt| {
+                                        PossibleDescendant::stmt(stmt, actually_return)
+                                    }),
+                                )
                             }
-                        }
-                        Expr::Member { previous, .. } => {
-                            if include_eval {
-                                self.todo.push(PossibleDescendant::expr(previous, true));
+
+                            if let Some(tail_expr) = tail_expr {
+                                todo.push(PossibleDescendant::expr(tail_expr, actually_return));
                             }
                         }
-                        Expr::Binary { lhs, rhs, .. } => {
-                            self.todo
-                                .push(PossibleDescendant::expr(lhs, actually_return));
-                            self.todo
-                                .push(PossibleDescendant::expr(rhs, actually_return));
+                        DescentOpts::Types { .. } => {}
+                    }
+                }
+                Expr::If {
+                    condition,
+                    body,
+                    else_branch,
+                } => {
+                    todo.push(PossibleDescendant::expr(condition, actually_return));
+                    todo.push(PossibleDescendant::expr(body, actually_return));
+                    if let Some(else_branch) = else_branch {
+                        todo.push(PossibleDescendant::expr(else_branch, actually_return));
+                    }
+                }
+                Expr::While { condition, body } => match opts {
+                    DescentOpts::Infer | DescentOpts::All { .. } => {
+                        todo.push(PossibleDescendant::expr(condition, actually_return));
+                        todo.push(PossibleDescendant::expr(body, actually_return));
+                    }
+                    DescentOpts::Reinfer | DescentOpts::Types { .. } => {}
+                },
+                Expr::For { iterable, body, .. } => match opts {
+                    DescentOpts::Infer | DescentOpts::All { .. } => {
+                        todo.push(PossibleDescendant::expr(iterable, actually_return));
+                        todo.push(PossibleDescendant::expr(body, actually_return));
+                    }
+                    DescentOpts::Reinfer | DescentOpts::Types { .. } => {}
+                },
+                Expr::Loop { body } => {
+                    match opts {
+                        DescentOpts::Infer | DescentOpts::All { .. } => {
+                            todo.push(PossibleDescendant::expr(body, actually_return));
                         }
-                        Expr::Paren(Some(expr)) => self
-                            .todo
-                            .push(PossibleDescendant::expr(expr, actually_return)),
-                        Expr::Paren(None) => {}
-                        Expr::Block { stmts, tail_expr } => match self.opts {
-                            DescentOpts::Infer | DescentOpts::All { .. } => {
-                                self.todo.extend(
-                                    stmts.into_iter().map(|stmt| {
+                        DescentOpts::Reinfer => {
+                            if let Some(id) = bodies.block_to_scope_id(expr) {
+                                todo.extend(
+                                    bodies.scope_id_usages(id).iter().copied().map(|stmt| {
                                         PossibleDescendant::stmt(stmt, actually_return)
                                     }),
                                 );
-
-                                if let Some(tail_expr) = tail_expr {
-                                    self.todo
-                                        .push(PossibleDescendant::expr(tail_expr, actually_return));
-                                }
-                            }
-                            DescentOpts::Reinfer => {
-                                if let Some(id) = self.bodies.block_to_scope_id(expr) {
-                                    self.todo.extend(
-                                        self.bodies.scope_id_usages(id).iter().copied().map(
-                                            |stmt| PossibleDescendant::stmt(stmt, actually_return),
-                                        ),
-                                    )
-                                }
-
-                                if let Some(tail_expr) = tail_expr {
-                                    self.todo
-                                        .push(PossibleDescendant::expr(tail_expr, actually_return));
-                                }
-                            }
-                            DescentOpts::Types { .. } => {}
-                        },
-                        Expr::If {
-                            condition,
-                            body,
-                            else_branch,
-                        } => {
-                            self.todo
-                                .push(PossibleDescendant::expr(condition, actually_return));
-                            self.todo
-                                .push(PossibleDescendant::expr(body, actually_return));
-                            if let Some(else_branch) = else_branch {
-                                self.todo
-                                    .push(PossibleDescendant::expr(else_branch, actually_return));
                             }
                         }
-                        Expr::While { condition, body } => match self.opts {
-                            DescentOpts::Infer | DescentOpts::All { .. } => {
-                                if let Some(condition) = condition {
-                                    self.todo
-                                        .push(PossibleDescendant::expr(condition, actually_return));
-                                }
-                                self.todo
-                                    .push(PossibleDescendant::expr(body, actually_return));
-                            }
-                            DescentOpts::Reinfer => {
-                                if condition.is_none() {
-                                    if let Some(id) = self.bodies.block_to_scope_id(expr) {
-                                        self.todo.extend(
-                                            self.bodies.scope_id_usages(id).iter().copied().map(
-                                                |stmt| {
-                                                    PossibleDescendant::stmt(stmt, actually_return)
-                                                },
-                                            ),
-                                        );
-                                    }
-                                }
-                            }
-                            DescentOpts::Types { .. } => {}
-                        },
-                        Expr::Switch {
-                            scrutinee,
-                            arms,
-                            default,
-                            ..
-                        } => match self.opts {
-                            DescentOpts::Infer | DescentOpts::All { .. } => {
-                                self.todo
-                                    .push(PossibleDescendant::expr(scrutinee, actually_return));
-                                for arm in arms {
-                                    self.todo
-                                        .push(PossibleDescendant::expr(arm.body, actually_return));
-                                }
-                                // TODO: this might cause unexpected behavior where the default is written
-                                // first but it is processed by hir_ty last.
-                                if let Some(default) = default {
-                                    self.todo.push(PossibleDescendant::expr(
-                                        default.body,
-                                        actually_return,
-                                    ));
-                                }
-                            }
-                            DescentOpts::Reinfer => {
-                                // todo: maybe don't do this
-                                self.todo
-                                    .push(PossibleDescendant::expr(scrutinee, actually_return));
-                                for arm in arms {
-                                    self.todo
-                                        .push(PossibleDescendant::expr(arm.body, actually_return));
-                                }
-                                if let Some(default) = default {
-                                    self.todo.push(PossibleDescendant::expr(
-                                        default.body,
-                                        actually_return,
-                                    ));
-                                }
-                            }
-                            DescentOpts::Types { .. } => {}
-                        },
-                        Expr::Local(local_def) => {
-                            if let DescentOpts::Types {
-                                include_local_value,
-                            } = self.opts
-                            {
-                                if include_local_value(local_def) {
-                                    let local_def = &self.bodies[local_def];
-
-                                    if let Some(value) = local_def.value {
-                                        self.todo
-                                            .push(PossibleDescendant::expr(value, actually_return));
-                                    }
-                                }
-                            }
+                        DescentOpts::Types { .. } => {}
+                    }
+                }
+                Expr::Switch {
+                    scrutinee,
+                    arms,
+                    default,
+                    ..
+                } => match opts {
+                    DescentOpts::Infer | DescentOpts::All { .. } => {
+                        todo.push(PossibleDescendant::expr(scrutinee, actually_return));
+                        for arm in arms {
+                            todo.push(PossibleDescendant::expr(arm.body, actually_return));
                         }
-                        Expr::SwitchLocal(_) => {}
-                        Expr::Param { .. } => {}
-                        Expr::LocalGlobal(_) => {}
-                        Expr::Call { callee, args } => {
-                            self.todo
-                                .push(PossibleDescendant::expr(callee, actually_return));
-                            self.todo.extend(
-                                args.into_iter()
-                                    .rev()
-                                    .map(|expr| PossibleDescendant::expr(expr, actually_return)),
-                            );
+                        // TODO: this might cause unexpected behavior where the default is written
+                        // first but it is processed by hir_ty last.
+                        if let Some(default) = default {
+                            todo.push(PossibleDescendant::expr(default.body, actually_return));
                         }
-                        Expr::Lambda(lambda) => {
-                            let lambda = &self.bodies[lambda];
-
-                            self.todo.extend(
-                                lambda
-                                    .params
-                                    .iter()
-                                    .rev()
-                                    .map(|param| PossibleDescendant::expr(param.ty, include_types)),
-                            );
-
-                            if let Some(return_ty) = lambda.return_ty {
-                                self.todo
-                                    .push(PossibleDescendant::expr(return_ty, include_types));
-                            }
-
-                            let is_type = !lambda.is_extern
-                                && lambda.return_ty.is_some()
-                                && self.bodies[lambda.body] == Expr::Missing;
-
-                            if matches!(
-                                self.opts,
-                                DescentOpts::All {
-                                    include_lambdas: true
-                                }
-                            ) && !lambda.is_extern
-                                && !is_type
-                            {
-                                self.todo
-                                    .push(PossibleDescendant::expr(lambda.body, include_types));
-                            }
+                    }
+                    DescentOpts::Reinfer => {
+                        // todo: maybe don't do this
+                        todo.push(PossibleDescendant::expr(scrutinee, actually_return));
+                        for arm in arms {
+                            todo.push(PossibleDescendant::expr(arm.body, actually_return));
                         }
-                        Expr::Comptime(comptime) => {
-                            if include_eval {
-                                let comptime = self.bodies[comptime];
-
-                                self.todo
-                                    .push(PossibleDescendant::expr(comptime.body, actually_return));
-                            }
+                        if let Some(default) = default {
+                            todo.push(PossibleDescendant::expr(default.body, actually_return));
                         }
-                        Expr::StructLiteral { ty, members, .. } => {
-                            if let Some(ty) = ty {
-                                self.todo.push(PossibleDescendant::expr(ty, is_all));
-                            }
+                    }
+                    DescentOpts::Types { .. } => {}
+                },
+                Expr::Local(local_def) => {
+                    if let DescentOpts::Types {
+                        include_local_value,
+                    } = opts
+                    {
+                        if include_local_value(local_def) {
+                            let local_def = &bodies[local_def];
 
-                            self.todo.extend(members.into_iter().rev().map(
-                                |MemberLiteral { value, .. }| {
-                                    PossibleDescendant::expr(value, actually_return)
-                                },
-                            ));
-                        }
-                        Expr::Distinct { ty, .. } => {
-                            self.todo.push(PossibleDescendant::expr(ty, include_types));
-                        }
-                        Expr::PrimitiveTy(_) => {}
-                        Expr::StructDecl { members, .. } => {
-                            self.todo.extend(members.into_iter().map(
-                                |MemberDecl { ty, .. }| PossibleDescendant::expr(ty, include_types),
-                            ));
-                        }
-                        Expr::EnumDecl { variants, .. } => {
-                            self.todo.extend(
-                                variants
-                                    .into_iter()
-                                    .flat_map(
-                                        |VariantDecl {
-                                             ty, discriminant, ..
-                                         }| {
-                                            [
-                                                PossibleDescendant::maybe_expr(ty, include_types),
-                                                PossibleDescendant::maybe_expr(
-                                                    discriminant,
-                                                    include_eval,
-                                                ),
-                                            ]
-                                        },
-                                    )
-                                    .flatten(),
-                            );
+                            if let Some(value) = local_def.value {
+                                todo.push(PossibleDescendant::expr(value, actually_return));
+                            }
                         }
-                        Expr::Directive { args, .. } => self.todo.extend(
-                            args.into_iter()
-                                .rev()
-                                .map(|expr| PossibleDescendant::expr(expr, actually_return)),
-                        ),
-                        Expr::Import(_) => {}
                     }
                 }
-                Descendant::Stmt(stmt) => match self.bodies[stmt] {
-                    Stmt::LocalDef(local_def) => {
-                        let local_def = &self.bodies[local_def];
+                Expr::SwitchLocal(_) => {}
+                Expr::ForLocal(_) => {}
+                Expr::Captured(_) => {}
+                Expr::Param { .. } => {}
+                Expr::LocalGlobal(_) => {}
+                Expr::Call { callee, args } => {
+                    todo.push(PossibleDescendant::expr(callee, actually_return));
+                    todo.extend(
+                        args.into_iter()
+                            .rev()
+                            .map(|expr| PossibleDescendant::expr(expr, actually_return)),
+                    );
+                }
+                Expr::Lambda(lambda) => {
+                    let lambda = &bodies[lambda];
+
+                    // captured values are evaluated along with the lambda itself
+                    if include_eval {
+                        todo.extend(lambda.captures.iter().rev().map(|capture| {
+                            PossibleDescendant::expr(bodies[*capture].value, actually_return)
+                        }));
+                    }
 
-                        if let Some(ty) = local_def.ty {
-                            self.todo.push(PossibleDescendant::expr(ty, is_all));
+                    // the types of a function with compile-time parameters can refer to those
+                    // parameters, so they can only be inferred along with the function itself
+                    let has_const_params = lambda.params.iter().any(|param| param.is_const);
+
+                    if include_types || !has_const_params {
+                        todo.extend(
+                            lambda
+                                .params
+                                .iter()
+                                .rev()
+                                .map(|param| PossibleDescendant::expr(param.ty, include_types)),
+                        );
+
+                        if let Some(return_ty) = lambda.return_ty {
+                            todo.push(PossibleDescendant::expr(return_ty, include_types));
                         }
+                    }
+
+                    let is_type = !lambda.is_extern
+                        && lambda.return_ty.is_some()
+                        && bodies[lambda.body] == Expr::Missing;
 
-                        if let Some(value) = local_def.value {
-                            self.todo
-                                .push(PossibleDescendant::expr(value, actually_return));
+                    if matches!(
+                        opts,
+                        DescentOpts::All {
+                            include_lambdas: true
                         }
+                    ) && !lambda.is_extern
+                        && !is_type
+                    {
+                        todo.push(PossibleDescendant::expr(lambda.body, include_types));
                     }
-                    Stmt::Assign(assign) => {
-                        let assign = &self.bodies[assign];
-                        self.todo
-                            .push(PossibleDescendant::expr(assign.dest, actually_return));
-                        self.todo
-                            .push(PossibleDescendant::expr(assign.value, actually_return));
+                }
+                Expr::Comptime(comptime) => {
+                    if include_eval {
+                        let comptime = bodies[comptime];
+
+                        todo.push(PossibleDescendant::expr(comptime.body, actually_return));
+                    }
+                }
+                Expr::StructLiteral { ty, members, .. } => {
+                    if let Some(ty) = ty {
+                        todo.push(PossibleDescendant::expr(ty, is_all));
                     }
-                    Stmt::Expr(expr) => self
-                        .todo
-                        .push(PossibleDescendant::expr(expr, actually_return)),
-                    Stmt::Break {
-                        value: Some(value), ..
-                    } => self
-                        .todo
-                        .push(PossibleDescendant::expr(value, actually_return)),
-                    Stmt::Break { value: None, .. } => {}
-                    Stmt::Continue { .. } => {}
-                    Stmt::Defer { expr, .. } => self
-                        .todo
-                        .push(PossibleDescendant::expr(expr, actually_return)),
-                },
-            }
 
-            if actually_return {
-                return Some(next);
+                    todo.extend(members.into_iter().rev().map(
+                        |MemberLiteral { value, .. }| {
+                            PossibleDescendant::expr(value, actually_return)
+                        },
+                    ));
+                }
+                Expr::Distinct { ty, .. }
+                | Expr::OptionalDecl { ty }
+                | Expr::ClosureDecl { ty } => {
+                    todo.push(PossibleDescendant::expr(ty, include_types));
+                }
+                Expr::PrimitiveTy(_) => {}
+                Expr::StructDecl { members, .. }
+                | Expr::InterfaceDecl {
+                    methods: members, ..
+                } => {
+                    todo.extend(
+                        members.into_iter().map(|MemberDecl { ty, .. }| {
+                            PossibleDescendant::expr(ty, include_types)
+                        }),
+                    );
+                }
+                Expr::EnumDecl { variants, .. } => {
+                    todo.extend(
+                        variants
+                            .into_iter()
+                            .flat_map(
+                                |VariantDecl {
+                                     ty, discriminant, ..
+                                 }| {
+                                    [
+                                        PossibleDescendant::maybe_expr(ty, include_types),
+                                        PossibleDescendant::maybe_expr(discriminant, include_eval),
+                                    ]
+                                },
+                            )
+                            .flatten(),
+                    );
+                }
+                Expr::Directive { args, .. } => todo.extend(
+                    args.into_iter()
+                        .rev()
+                        .map(|expr| PossibleDescendant::expr(expr, actually_return)),
+                ),
+                Expr::Import(_) => {}
             }
         }
+        Descendant::Stmt(stmt) => match bodies[stmt] {
+            Stmt::LocalDef(local_def) => {
+                let local_def = &bodies[local_def];
+
+                if let Some(ty) = local_def.ty {
+                    todo.push(PossibleDescendant::expr(ty, is_all));
+                }
+
+                if let Some(value) = local_def.value {
+                    todo.push(PossibleDescendant::expr(value, actually_return));
+                }
+            }
+            Stmt::Assign(assign) => {
+                let assign = &bodies[assign];
+                todo.push(PossibleDescendant::expr(assign.dest, actually_return));
+                todo.push(PossibleDescendant::expr(assign.value, actually_return));
+            }
+            Stmt::Expr(expr) => todo.push(PossibleDescendant::expr(expr, actually_return)),
+            Stmt::Break {
+                value: Some(value), ..
+            } => todo.push(PossibleDescendant::expr(value, actually_return)),
+            Stmt::Break { value: None, .. } => {}
+            Stmt::Continue { .. } => {}
+            Stmt::Defer { expr, .. } => todo.push(PossibleDescendant::expr(expr, actually_return)),
+        },
     }
 }
 
@@ -2394,6 +3898,24 @@ impl Bodies {
         }
     }
 
+    /// the same as `Bodies::descendants(..).collect_vec().into_iter().rev()`, except that it
+    /// streams the descendants instead of allocating a list of every single one of them.
+    ///
+    /// sub expressions are guarenteed to come before their parents, and early statements are
+    /// guarenteed to come before later statements.
+    pub fn descendants_rev<'a>(
+        &'a self,
+        expr: Idx<Expr>,
+        opts: DescentOpts<'a>,
+    ) -> ReverseDescendants<'a> {
+        ReverseDescendants {
+            bodies: self,
+            opts,
+            todo: vec![(PossibleDescendant::expr(expr, true), false)],
+            children: Vec::new(),
+        }
+    }
+
     pub fn global_exists(&self, name: Name) -> bool {
         self.global_bodies.contains_key(&name)
             || self.global_tys.contains_key(&name)
@@ -2434,10 +3956,26 @@ impl Bodies {
         self.comptimes.iter().map(|(idx, _)| idx)
     }
 
+    /// the type a literal was given with a suffix, like the `u8` in `42u8`
+    pub fn literal_suffix(&self, expr: Idx<Expr>) -> Option<PrimitiveTy> {
+        self.literal_suffixes.get(expr).copied()
+    }
+
+    /// how a number literal was written (without its suffix),
+    /// if that's different from how its value would be printed. e.g. `1_000` or `0xFF`
+    pub fn written_literal(&self, expr: Idx<Expr>) -> Option<&str> {
+        self.written_literals.get(expr).map(String::as_str)
+    }
+
     pub fn imports(&self) -> &FxHashSet<FileName> {
         &self.imports
     }
 
+    /// the files brought into scope with `using`, in the order they were written
+    pub fn usings(&self) -> &[(FileName, TextRange)] {
+        &self.usings
+    }
+
     /// only blocks which are actually `break`d or `continue`d out of will get a scopeid
     pub fn block_to_scope_id(&self, expr: Idx<Expr>) -> Option<ScopeId> {
         self.scope_decls.get_by_right(&expr).copied()
@@ -2452,26 +3990,95 @@ impl Bodies {
         self.scope_usages.get(&id).unwrap()
     }
 
+    /// Every local and parameter which can be referred to by name at the given position,
+    /// innermost first. Names which are shadowed at that position are left out
+    pub fn scopes_at(&self, offset: TextSize) -> Vec<(Name, ScopeDef)> {
+        // locals from outside of a lambda or comptime block can't be seen from within it
+        let boundary = self
+            .exprs
+            .iter()
+            .filter_map(|(expr, kind)| {
+                let body = match kind {
+                    Expr::Lambda(lambda) => self.lambdas[*lambda].body,
+                    Expr::Comptime(comptime) => self.comptimes[*comptime].body,
+                    _ => return None,
+                };
+                let range = *self.expr_ranges.get(body)?;
+
+                range.contains_inclusive(offset).then_some((expr, range))
+            })
+            .min_by_key(|(_, range)| range.len());
+
+        let mut scopes = self
+            .scopes
+            .values()
+            .filter(|scope| {
+                scope.range.contains_inclusive(offset)
+                    && boundary.is_none_or(|(_, boundary)| boundary.contains_range(scope.range))
+            })
+            .collect::<Vec<_>>();
+        scopes.sort_by_key(|scope| scope.range.len());
+
+        let mut seen = FxHashSet::default();
+        let mut visible = Vec::new();
+
+        for scope in scopes {
+            for entry in scope.entries.iter().rev() {
+                if entry.visible_from <= offset && seen.insert(entry.name) {
+                    visible.push((entry.name, entry.def));
+                }
+            }
+        }
+
+        if let Some((expr, _)) = boundary {
+            if let Expr::Lambda(lambda) = self[expr] {
+                for (idx, param) in self.lambdas[lambda].params.iter().enumerate() {
+                    if let Some(name) = param.name {
+                        if seen.insert(name) {
+                            visible.push((
+                                name,
+                                ScopeDef::Param {
+                                    lambda: expr,
+                                    idx: idx as u32,
+                                },
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        visible
+    }
+
     fn shrink_to_fit(&mut self) {
         let Self {
             local_defs,
             switch_locals,
+            for_locals,
             stmts,
             exprs,
             assigns,
             expr_ranges: _,
+            literal_suffixes: _,
+            written_literals: _,
             global_tys,
             global_bodies,
             global_externs,
             scope_decls: label_decls,
             scope_usages: label_usages,
+            scopes,
             lambdas,
+            captures,
             comptimes,
             imports,
+            usings,
         } = self;
 
+        scopes.shrink_to_fit();
         local_defs.shrink_to_fit();
         switch_locals.shrink_to_fit();
+        for_locals.shrink_to_fit();
         stmts.shrink_to_fit();
         exprs.shrink_to_fit();
         assigns.shrink_to_fit();
@@ -2479,8 +4086,10 @@ impl Bodies {
         global_bodies.shrink_to_fit();
         global_externs.shrink_to_fit();
         lambdas.shrink_to_fit();
+        captures.shrink_to_fit();
         comptimes.shrink_to_fit();
         imports.shrink_to_fit();
+        usings.shrink_to_fit();
         label_decls.shrink_to_fit();
         label_usages.shrink_to_fit()
     }
@@ -2497,8 +4106,16 @@ impl std::ops::Index<Idx<LocalDef>> for Bodies {
 impl std::ops::Index<Idx<SwitchLocal>> for Bodies {
     type Output = SwitchLocal;
 
-    fn index(&self, id: Idx<SwitchLocal>) -> &Self::Output {
-        &self.switch_locals[id]
+    fn index(&self, id: Idx<SwitchLocal>) -> &Self::Output {
+        &self.switch_locals[id]
+    }
+}
+
+impl std::ops::Index<Idx<ForLocal>> for Bodies {
+    type Output = ForLocal;
+
+    fn index(&self, id: Idx<ForLocal>) -> &Self::Output {
+        &self.for_locals[id]
     }
 }
 
@@ -2526,6 +4143,14 @@ impl std::ops::Index<Idx<Lambda>> for Bodies {
     }
 }
 
+impl std::ops::Index<Idx<Capture>> for Bodies {
+    type Output = Capture;
+
+    fn index(&self, id: Idx<Capture>) -> &Self::Output {
+        &self.captures[id]
+    }
+}
+
 impl std::ops::Index<Idx<Comptime>> for Bodies {
     type Output = Comptime;
 
@@ -2608,9 +4233,25 @@ impl Bodies {
             match &bodies[idx] {
                 Expr::Missing => s.push_str("<missing>"),
 
-                Expr::IntLiteral(n) => s.push_str(&format!("{}", n)),
+                Expr::IntLiteral(n) => {
+                    match bodies.written_literal(idx) {
+                        Some(written) => s.push_str(written),
+                        None => s.push_str(&format!("{}", n)),
+                    }
+                    if let Some(suffix) = bodies.literal_suffix(idx) {
+                        s.push_str(&suffix.display());
+                    }
+                }
 
-                Expr::FloatLiteral(n) => s.push_str(&format!("{}", n)),
+                Expr::FloatLiteral(n) => {
+                    match bodies.written_literal(idx) {
+                        Some(written) => s.push_str(written),
+                        None => s.push_str(&format!("{}", n)),
+                    }
+                    if let Some(suffix) = bodies.literal_suffix(idx) {
+                        s.push_str(&suffix.display());
+                    }
+                }
 
                 Expr::BoolLiteral(b) => s.push_str(&format!("{}", b)),
 
@@ -2618,8 +4259,17 @@ impl Bodies {
 
                 Expr::CharLiteral(char) => s.push_str(&format!("{:?}", Into::<char>::into(*char))),
 
-                Expr::ArrayDecl { size, ty } => {
+                Expr::NullLiteral => s.push_str("null"),
+
+                Expr::ArrayDecl {
+                    size,
+                    inferred_size,
+                    ty,
+                } => {
                     s.push('[');
+                    if *inferred_size {
+                        s.push('_');
+                    }
                     if let Some(size) = size {
                         write_expr(
                             s,
@@ -2679,6 +4329,44 @@ impl Bodies {
                     s.push(']');
                 }
 
+                Expr::ArrayRepeat { ty, item, count } => {
+                    if let Some(ty) = ty {
+                        write_expr(
+                            s,
+                            *ty,
+                            with_color,
+                            show_idx,
+                            bodies,
+                            mod_dir,
+                            interner,
+                            indentation,
+                        );
+                    }
+                    s.push_str(".[");
+                    write_expr(
+                        s,
+                        *item,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                    s.push_str("; ");
+                    write_expr(
+                        s,
+                        *count,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                    s.push(']');
+                }
+
                 Expr::Index {
                     source: array,
                     index,
@@ -2796,6 +4484,66 @@ impl Bodies {
                     );
                 }
 
+                Expr::Orelse { optional, fallback } => {
+                    write_expr(
+                        s,
+                        *optional,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+
+                    s.push_str(" orelse ");
+
+                    write_expr(
+                        s,
+                        *fallback,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                }
+
+                Expr::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => {
+                    if let Some(start) = start {
+                        write_expr(
+                            s,
+                            *start,
+                            with_color,
+                            show_idx,
+                            bodies,
+                            mod_dir,
+                            interner,
+                            indentation,
+                        );
+                    }
+
+                    s.push_str(if *inclusive { "..=" } else { ".." });
+
+                    if let Some(end) = end {
+                        write_expr(
+                            s,
+                            *end,
+                            with_color,
+                            show_idx,
+                            bodies,
+                            mod_dir,
+                            interner,
+                            indentation,
+                        );
+                    }
+                }
+
                 Expr::Unary { expr, op } => {
                     match op {
                         UnaryOp::Pos => s.push('+'),
@@ -2835,6 +4583,33 @@ impl Bodies {
                     s.push_str("()");
                 }
 
+                Expr::Tuple(items) => {
+                    s.push('(');
+
+                    for (idx, item) in items.iter().enumerate() {
+                        write_expr(
+                            s,
+                            *item,
+                            with_color,
+                            show_idx,
+                            bodies,
+                            mod_dir,
+                            interner,
+                            indentation,
+                        );
+                        if idx != items.len() - 1 {
+                            s.push_str(", ");
+                        }
+                    }
+
+                    // `(5)` would just be parentheses
+                    if items.len() == 1 {
+                        s.push(',');
+                    }
+
+                    s.push(')');
+                }
+
                 Expr::Block {
                     stmts,
                     tail_expr: None,
@@ -2980,22 +4755,73 @@ impl Bodies {
                         s.push(' ');
                     }
 
-                    if let Some(condition) = condition {
-                        s.push_str("while ");
-                        write_expr(
-                            s,
-                            *condition,
-                            with_color,
-                            show_idx,
-                            bodies,
-                            mod_dir,
-                            interner,
-                            indentation,
-                        );
+                    s.push_str("while ");
+                    write_expr(
+                        s,
+                        *condition,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                    s.push(' ');
+                    write_expr(
+                        s,
+                        *body,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                }
+
+                Expr::Loop { body } => {
+                    if let Some(label_id) = bodies.scope_decls.get_by_right(&idx) {
+                        s.push('`');
+                        s.push_str(&label_id.to_string());
+                        s.push(' ');
+                    }
+
+                    s.push_str("loop ");
+                    write_expr(
+                        s,
+                        *body,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                }
+
+                Expr::For {
+                    item,
+                    iterable,
+                    body,
+                } => {
+                    if let Some(label_id) = bodies.scope_decls.get_by_right(&idx) {
+                        s.push('`');
+                        s.push_str(&label_id.to_string());
                         s.push(' ');
-                    } else {
-                        s.push_str("loop ");
                     }
+
+                    s.push_str(&format!("for f{} in ", item.into_raw()));
+                    write_expr(
+                        s,
+                        *iterable,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                    s.push(' ');
                     write_expr(
                         s,
                         *body,
@@ -3087,6 +4913,10 @@ impl Bodies {
 
                 Expr::SwitchLocal(id) => s.push_str(&format!("s{}", id.into_raw())),
 
+                Expr::ForLocal(id) => s.push_str(&format!("f{}", id.into_raw())),
+
+                Expr::Captured(id) => s.push_str(&format!("c{}", id.into_raw())),
+
                 Expr::Param { idx, .. } => s.push_str(&format!("p{}", idx)),
 
                 Expr::Call { callee, args } => {
@@ -3172,7 +5002,10 @@ impl Bodies {
                         params,
                         return_ty,
                         body,
+                        captures,
                         is_extern,
+                        inline,
+                        export_name,
                         ..
                     } = &bodies.lambdas[*lambda];
 
@@ -3220,6 +5053,38 @@ impl Bodies {
                         s.push(' ');
                     }
 
+                    match inline {
+                        Some(InlineHint::Always) => s.push_str("@inline "),
+                        Some(InlineHint::Never) => s.push_str("@no_inline "),
+                        None => {}
+                    }
+
+                    if let Some(export_name) = export_name {
+                        s.push_str(&format!("@export({:?}) ", interner.lookup(*export_name)));
+                    }
+
+                    if !captures.is_empty() {
+                        s.push('[');
+                        for (idx, capture) in captures.iter().enumerate() {
+                            s.push_str(&format!("c{} = ", capture.into_raw()));
+                            write_expr(
+                                s,
+                                bodies[*capture].value,
+                                with_color,
+                                show_idx,
+                                bodies,
+                                mod_dir,
+                                interner,
+                                indentation,
+                            );
+
+                            if idx != captures.len() - 1 {
+                                s.push_str(", ");
+                            }
+                        }
+                        s.push_str("] ");
+                    }
+
                     if *is_extern {
                         s.push_str("extern");
                     } else {
@@ -3312,11 +5177,67 @@ impl Bodies {
                     );
                 }
 
-                Expr::StructDecl { uid, members } => {
-                    s.push_str("struct'");
+                Expr::OptionalDecl { ty } => {
+                    s.push('?');
+                    write_expr(
+                        s,
+                        *ty,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                }
+
+                Expr::ClosureDecl { ty } => {
+                    s.push_str("closure ");
+                    write_expr(
+                        s,
+                        *ty,
+                        with_color,
+                        show_idx,
+                        bodies,
+                        mod_dir,
+                        interner,
+                        indentation,
+                    );
+                }
+
+                Expr::StructDecl { uid, members } => {
+                    s.push_str("struct'");
+                    s.push_str(&uid.to_string());
+                    s.push_str(" {");
+                    for (idx, MemberDecl { name, ty }) in members.iter().enumerate() {
+                        if let Some(name) = name {
+                            s.push_str(interner.lookup(name.name.0));
+                        } else {
+                            s.push('?');
+                        }
+                        s.push_str(": ");
+                        write_expr(
+                            s,
+                            *ty,
+                            with_color,
+                            show_idx,
+                            bodies,
+                            mod_dir,
+                            interner,
+                            indentation,
+                        );
+                        if idx != members.len() - 1 {
+                            s.push_str(", ");
+                        }
+                    }
+                    s.push('}');
+                }
+
+                Expr::InterfaceDecl { uid, methods } => {
+                    s.push_str("interface'");
                     s.push_str(&uid.to_string());
                     s.push_str(" {");
-                    for (idx, MemberDecl { name, ty }) in members.iter().enumerate() {
+                    for (idx, MemberDecl { name, ty }) in methods.iter().enumerate() {
                         if let Some(name) = name {
                             s.push_str(interner.lookup(name.name.0));
                         } else {
@@ -3333,7 +5254,7 @@ impl Bodies {
                             interner,
                             indentation,
                         );
-                        if idx != members.len() - 1 {
+                        if idx != methods.len() - 1 {
                             s.push_str(", ");
                         }
                     }
@@ -3566,7 +5487,6 @@ mod tests {
         ) -> [(LoweringDiagnosticKind, std::ops::Range<u32>); N],
     ) {
         let mut interner = Interner::default();
-        let mut uid_gen = UIDGenerator::default();
 
         let tokens = lexer::lex(input);
         let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
@@ -3578,7 +5498,6 @@ mod tests {
             &tree,
             Path::new("main.capy"),
             &index,
-            &mut uid_gen,
             &mut interner,
             Path::new("/capy/modules"),
             true,
@@ -3600,40 +5519,364 @@ mod tests {
             })
             .collect();
 
+        // so that a failing test shows names instead of interner indices
+        let _scope = interner.debug_scope();
         assert_eq!(expected_diagnostics, actual_diagnostics);
     }
 
+    #[test]
+    fn written_literal_values() {
+        let input = r#"
+            foo :: () {
+                a := 4_5_6_E1_0_;
+                b := 0xFF_FF;
+                c := 0b1010_1010;
+                d := .123;
+                e := 2.5E+2;
+            };
+        "#;
+
+        let mut interner = Interner::default();
+
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = crate::index(root, &tree, &mut interner);
+
+        let (bodies, _) = lower(
+            root,
+            &tree,
+            Path::new("main.capy"),
+            &index,
+            &mut interner,
+            Path::new("/capy/modules"),
+            true,
+        );
+
+        // the debug output shows literals the way they were written,
+        // so this makes sure they still have the right values
+        let values: Vec<_> = bodies
+            .exprs
+            .iter()
+            .filter_map(|(_, expr)| match expr {
+                Expr::IntLiteral(n) => Some(*n as f64),
+                Expr::FloatLiteral(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(values, [4560000000000.0, 65535.0, 170.0, 0.123, 250.0]);
+    }
+
+    #[test]
+    fn type_uids_only_depend_on_their_global() {
+        fn struct_uids(input: &str) -> FxHashMap<String, u64> {
+            let mut interner = Interner::default();
+
+            let tokens = lexer::lex(input);
+            let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+            let root = ast::Root::cast(tree.root(), &tree).unwrap();
+            let (index, _) = crate::index(root, &tree, &mut interner);
+
+            let (bodies, _) = lower(
+                root,
+                &tree,
+                Path::new("main.capy"),
+                &index,
+                &mut interner,
+                Path::new("/capy/modules"),
+                true,
+            );
+
+            bodies
+                .global_bodies
+                .iter()
+                .filter_map(|(name, body)| match bodies[*body] {
+                    Expr::StructDecl { uid, .. } => {
+                        Some((interner.lookup(name.0).to_string(), uid))
+                    }
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let before = struct_uids(
+            r#"
+                Point :: struct { x: i32 };
+                Size :: struct { w: i32 };
+            "#,
+        );
+        // adding a type earlier in the file doesn't change the types after it
+        let after = struct_uids(
+            r#"
+                Color :: struct { r: u8 };
+                Point :: struct { x: i32 };
+                Size :: struct { w: i32, h: i32 };
+            "#,
+        );
+
+        assert_eq!(before["Point"], after["Point"]);
+        assert_eq!(before["Size"], after["Size"]);
+        assert_ne!(after["Color"], after["Point"]);
+    }
+
     #[test]
     fn empty() {
         check("", expect![""], |_| [])
     }
 
     #[test]
-    fn function() {
+    fn function() {
+        check(
+            r#"
+                foo :: () {
+                    
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {};
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn binary() {
+        check(
+            r#"
+                foo :: () {
+                    1 + 1;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    1 + 1;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn global() {
+        check(
+            r#"
+                foo :: 5;
+
+                bar :: () {
+                    foo;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: 5;
+                main::bar :: () {
+                    foo;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn local_var() {
+        check(
+            r#"
+                foo :: () {
+                    x := 5;
+
+                    x;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := 5;
+                    l0;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn local_var_normalized_name() {
+        // the first `café` is precomposed, the second uses a combining accent
+        check(
+            "
+                foo :: () {
+                    caf\u{e9} := 5;
+
+                    cafe\u{301};
+                }
+            ",
+            expect![[r#"
+                main::foo :: () {
+                    l0 := 5;
+                    l0;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn local_var_escaped_keyword() {
+        check(
+            r#"
+                foo :: () {
+                    r#while := 5;
+
+                    r#while;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := 5;
+                    l0;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn param() {
+        check(
+            r#"
+                foo :: (x: i32) {
+                    x;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: (p0: i32) {
+                    p0;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn param_in_later_param_ty() {
+        check(
+            r#"
+                foo :: (n: usize, buf: [n]u8) -> [n]u8 {
+                    buf
+                }
+            "#,
+            expect![[r#"
+                main::foo :: (p0: usize, p1: [p0]u8) -> [p0]u8 { p1 };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn import() {
+        check(
+            r#"
+                other_file :: #import("other_file.capy");
+
+                foo :: () {
+                    other_file.global;
+                }
+            "#,
+            expect![[r#"
+                main::other_file :: #import("other_file.capy");
+                main::foo :: () {
+                    other_file.global;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn import_old_syntax() {
+        check(
+            r#"
+                other_file :: import "other_file.capy";
+
+                foo :: () {
+                    other_file.global;
+                }
+            "#,
+            expect![[r#"
+                main::other_file :: #import("other_file.capy");
+                main::foo :: () {
+                    other_file.global;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn import_non_dot_capy() {
+        check(
+            r#"
+                foo :: () {
+                    other_file :: import "other_file.cap";
+
+                    other_file.global;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := <missing>;
+                    l0.global;
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::ImportMustEndInDotCapy, 70..86)],
+        )
+    }
+
+    #[test]
+    fn int_literal() {
+        check(
+            r#"
+                foo :: () {
+                    num := 18446744073709551615;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := 18446744073709551615;
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn int_literal_with_e_lower() {
         check(
             r#"
                 foo :: () {
-                    
+                    // 123 * 10^9
+                    num := 1_23_e9_;
                 }
             "#,
             expect![[r#"
-                main::foo :: () {};
+                main::foo :: () {
+                    l0 := 1_23_e9_;
+                };
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn binary() {
+    fn int_literal_with_e_upper() {
         check(
             r#"
                 foo :: () {
-                    1 + 1;
+                    // 456... * 10^(-10)
+                    num := 4_5_6_E1_0_;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    1 + 1;
+                    l0 := 4_5_6_E1_0_;
                 };
             "#]],
             |_| [],
@@ -3641,56 +5884,50 @@ mod tests {
     }
 
     #[test]
-    fn global() {
+    fn int_literal_with_e_very_large() {
         check(
             r#"
-                foo :: 5;
-
-                bar :: () {
-                    foo;
+                foo :: () {
+                    num := 1e20;
                 }
             "#,
             expect![[r#"
-                main::foo :: 5;
-                main::bar :: () {
-                    foo;
+                main::foo :: () {
+                    l0 := <missing>;
                 };
             "#]],
-            |_| [],
+            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..60)],
         )
     }
 
     #[test]
-    fn local_var() {
+    fn out_of_range_int_literal() {
         check(
             r#"
                 foo :: () {
-                    x := 5;
-
-                    x;
+                    num := 18446744073709551616;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 5;
-                    l0;
+                    l0 := <missing>;
                 };
             "#]],
-            |_| [],
+            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..76)],
         )
     }
 
     #[test]
-    fn param() {
+    fn int_literal_with_underscores() {
         check(
             r#"
-                foo :: (x: i32) {
-                    x;
+                foo :: () {
+                    num := 1_000_000;
                 }
             "#,
             expect![[r#"
-                main::foo :: (p0: i32) {
-                    p0;
+                main::foo :: () {
+                    l0 := 1_000_000;
                 };
             "#]],
             |_| [],
@@ -3698,19 +5935,16 @@ mod tests {
     }
 
     #[test]
-    fn import() {
+    fn hex_literal() {
         check(
             r#"
-                other_file :: #import("other_file.capy");
-
                 foo :: () {
-                    other_file.global;
+                    num := 0x21eFAB;
                 }
             "#,
             expect![[r#"
-                main::other_file :: #import("other_file.capy");
                 main::foo :: () {
-                    other_file.global;
+                    l0 := 0x21eFAB;
                 };
             "#]],
             |_| [],
@@ -3718,19 +5952,16 @@ mod tests {
     }
 
     #[test]
-    fn import_old_syntax() {
+    fn hex_literal_with_underscores() {
         check(
             r#"
-                other_file :: import "other_file.capy";
-
                 foo :: () {
-                    other_file.global;
+                    num := 0xFF_FF;
                 }
             "#,
             expect![[r#"
-                main::other_file :: #import("other_file.capy");
                 main::foo :: () {
-                    other_file.global;
+                    l0 := 0xFF_FF;
                 };
             "#]],
             |_| [],
@@ -3738,36 +5969,33 @@ mod tests {
     }
 
     #[test]
-    fn import_non_dot_capy() {
+    fn out_of_range_hex_literal() {
         check(
             r#"
                 foo :: () {
-                    other_file :: import "other_file.cap";
-
-                    other_file.global;
+                    num := 0x10000000000000000;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
                     l0 := <missing>;
-                    l0.global;
                 };
             "#]],
-            |_| [(LoweringDiagnosticKind::ImportMustEndInDotCapy, 70..86)],
+            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..75)],
         )
     }
 
     #[test]
-    fn int_literal() {
+    fn bin_literal() {
         check(
             r#"
                 foo :: () {
-                    num := 18446744073709551615;
+                    num := 0b001100101010101;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 18446744073709551615;
+                    l0 := 0b001100101010101;
                 };
             "#]],
             |_| [],
@@ -3775,17 +6003,16 @@ mod tests {
     }
 
     #[test]
-    fn int_literal_with_e_lower() {
+    fn bin_literal_with_underscores() {
         check(
             r#"
                 foo :: () {
-                    // 123 * 10^9
-                    num := 1_23_e9_;
+                    num := 0b1010_1010;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 123000000000;
+                    l0 := 0b1010_1010;
                 };
             "#]],
             |_| [],
@@ -3793,68 +6020,71 @@ mod tests {
     }
 
     #[test]
-    fn int_literal_with_e_upper() {
+    fn out_of_range_bin_literal() {
         check(
             r#"
                 foo :: () {
-                    // 456... * 10^(-10)
-                    num := 4_5_6_E1_0_;
+                    num := 0b10000000000000000000000000000000000000000000000000000000000000000;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 4560000000000;
+                    l0 := <missing>;
                 };
             "#]],
-            |_| [],
+            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..123)],
         )
     }
 
     #[test]
-    fn int_literal_with_e_very_large() {
+    fn float_literal() {
         check(
             r#"
                 foo :: () {
-                    num := 1e20;
+                    num := .123;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := <missing>;
+                    l0 := .123;
                 };
             "#]],
-            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..60)],
+            |_| [],
         )
     }
 
     #[test]
-    fn out_of_range_int_literal() {
+    fn float_literal_with_underscores() {
         check(
             r#"
                 foo :: () {
-                    num := 18446744073709551616;
+                    num := 1_000_000.000_00000E-3_;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := <missing>;
+                    l0 := 1_000_000.000_00000E-3_;
                 };
             "#]],
-            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..76)],
+            |_| [],
         )
     }
 
     #[test]
-    fn hex_literal() {
+    fn float_literal_with_negative_exponent() {
         check(
             r#"
                 foo :: () {
-                    num := 0x21eFAB;
+                    a := 1e-3;
+                    b := 2.5E+2;
+                    c := 5e-1f32;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 2224043;
+                    l0 := 1e-3;
+                    l1 := 2.5E+2;
+                    l2 := 5e-1f32;
                 };
             "#]],
             |_| [],
@@ -3862,11 +6092,11 @@ mod tests {
     }
 
     #[test]
-    fn out_of_range_hex_literal() {
+    fn float_literal_too_large() {
         check(
             r#"
                 foo :: () {
-                    num := 0x10000000000000000;
+                    num := 1.5e400;
                 }
             "#,
             expect![[r#"
@@ -3874,21 +6104,21 @@ mod tests {
                     l0 := <missing>;
                 };
             "#]],
-            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..75)],
+            |_| [(LoweringDiagnosticKind::OutOfRangeFloatLiteral, 56..63)],
         )
     }
 
     #[test]
-    fn bin_literal() {
+    fn int_literal_with_suffix() {
         check(
             r#"
                 foo :: () {
-                    num := 0b001100101010101;
+                    num := 1_000u16;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 6485;
+                    l0 := 1_000u16;
                 };
             "#]],
             |_| [],
@@ -3896,33 +6126,33 @@ mod tests {
     }
 
     #[test]
-    fn out_of_range_bin_literal() {
+    fn hex_literal_with_suffix() {
         check(
             r#"
                 foo :: () {
-                    num := 0b10000000000000000000000000000000000000000000000000000000000000000;
+                    num := 0xFFi64;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := <missing>;
+                    l0 := 0xFFi64;
                 };
             "#]],
-            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 56..123)],
+            |_| [],
         )
     }
 
     #[test]
-    fn float_literal() {
+    fn int_literal_with_float_suffix() {
         check(
             r#"
                 foo :: () {
-                    num := .123;
+                    num := 10f32;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 0.123;
+                    l0 := 10f32;
                 };
             "#]],
             |_| [],
@@ -3930,16 +6160,16 @@ mod tests {
     }
 
     #[test]
-    fn float_literal_with_underscores() {
+    fn float_literal_with_suffix() {
         check(
             r#"
                 foo :: () {
-                    num := 1_000_000.000_00000E-3_;
+                    num := 2.5f64;
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := 1000;
+                    l0 := 2.5f64;
                 };
             "#]],
             |_| [],
@@ -4002,6 +6232,98 @@ mod tests {
         )
     }
 
+    #[test]
+    fn string_literal_with_unicode_and_hex_escapes() {
+        check(
+            r#"
+                foo :: () {
+                    escapes := "\u{1F600}\u{10_FFFF}\x41\x7f";
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := "😀\u{10ffff}A\u{7f}";
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn string_literal_with_invalid_unicode_and_hex_escapes() {
+        check(
+            r#"
+                foo :: () {
+                    bad := "a\u{}b\u{1234567}c\u{D800}d\x4fe\x4gf\xFFg";
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := "abcdOegfg";
+                };
+            "#]],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::InvalidUnicodeEscape, 58..62),
+                    (LoweringDiagnosticKind::InvalidUnicodeEscape, 63..74),
+                    (
+                        LoweringDiagnosticKind::InvalidUnicodeChar { value: 0xD800 },
+                        75..83,
+                    ),
+                    (LoweringDiagnosticKind::InvalidHexEscape, 89..92),
+                    (LoweringDiagnosticKind::NonAsciiHexEscapeInString, 94..98),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn multiline_string_literal() {
+        check(
+            r#"
+                foo :: () {
+                    poem :=
+                        \\roses are "red"
+                        \\
+                        \\violets\tare blue
+                        ;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := "roses are \"red\"\n\nviolets\tare blue";
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn multiline_string_literal_with_invalid_escapes() {
+        check(
+            r#"
+                foo :: () {
+                    crab :=
+                        \\a
+                        \\b\jc
+                        \\d\
+                        ;
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := "a\nbc\nd";
+                };
+            "#]],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::InvalidEscape, 112..114),
+                    (LoweringDiagnosticKind::InvalidEscape, 143..144),
+                ]
+            },
+        )
+    }
+
     #[test]
     fn char_literal() {
         check(
@@ -4126,6 +6448,44 @@ mod tests {
         )
     }
 
+    #[test]
+    fn char_literal_with_unicode_and_hex_escape() {
+        check(
+            r"
+                foo :: () {
+                    a := '\x41';
+                    max := '\xFF';
+                    e := '\u{E9}';
+                }
+            ",
+            expect![[r#"
+                main::foo :: () {
+                    l0 := 'A';
+                    l1 := 'ÿ';
+                    l2 := 'é';
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn char_literal_with_non_u8_unicode_escape() {
+        check(
+            r"
+                foo :: () {
+                    crab := '\u{1F980}';
+                }
+            ",
+            expect![[r#"
+                main::foo :: () {
+                    l0 := '\0';
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::NonU8CharLiteral, 57..68)],
+        )
+    }
+
     #[test]
     fn nested_binary_expr() {
         check(
@@ -4226,16 +6586,68 @@ mod tests {
     }
 
     #[test]
-    fn recursion() {
+    fn recursion() {
+        check(
+            r#"
+                foo :: () {
+                    foo();
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    foo();
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn lambda() {
+        check(
+            r#"
+                foo :: () {
+                    bar := () {};
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := () {};
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn lambda_ty_without_param_names() {
+        check(
+            r#"
+                foo :: (callback: (i32, str) -> bool) {}
+            "#,
+            expect![[r#"
+                main::foo :: (p0: (p0: i32, p1: str) -> bool <missing>) {};
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn lambda_captures_scope() {
         check(
             r#"
-                foo :: () {
-                    foo();
+                foo :: (x: i32) {
+                    y := 5;
+
+                    bar := () -> i32 {
+                        x + y + x
+                    };
                 }
             "#,
             expect![[r#"
-                main::foo :: () {
-                    foo();
+                main::foo :: (p0: i32) {
+                    l0 := 5;
+                    l1 := () -> i32 [c0 = p0, c1 = l0] { c0 + c1 + c0 };
                 };
             "#]],
             |_| [],
@@ -4243,16 +6655,25 @@ mod tests {
     }
 
     #[test]
-    fn lambda() {
+    fn nested_lambda_captures_scope() {
         check(
             r#"
                 foo :: () {
-                    bar := () {};
+                    x := 5;
+
+                    bar := (y: i32) {
+                        baz := () -> i32 {
+                            x + y
+                        };
+                    };
                 }
             "#,
             expect![[r#"
                 main::foo :: () {
-                    l0 := () {};
+                    l0 := 5;
+                    l2 := (p0: i32) [c0 = l0] {
+                        l1 := () -> i32 [c1 = c0, c2 = p0] { c1 + c2 };
+                    };
                 };
             "#]],
             |_| [],
@@ -4260,38 +6681,30 @@ mod tests {
     }
 
     #[test]
-    fn lambda_dont_capture_scope() {
+    fn comptime_in_lambda_dont_capture_scope() {
         check(
             r#"
-                foo :: (x: i32) {
-                    y := 5;
+                foo :: () {
+                    x := 5;
 
                     bar := () -> i32 {
-                        x + y
+                        comptime { x }
                     };
                 }
             "#,
             expect![[r#"
-                main::foo :: (p0: i32) {
+                main::foo :: () {
                     l0 := 5;
-                    l1 := () -> i32 { <missing> + <missing> };
+                    l1 := () -> i32 { comptime { <missing> } };
                 };
             "#]],
             |i| {
-                [
-                    (
-                        LoweringDiagnosticKind::UndefinedRef {
-                            name: i.intern("x"),
-                        },
-                        127..128,
-                    ),
-                    (
-                        LoweringDiagnosticKind::UndefinedRef {
-                            name: i.intern("y"),
-                        },
-                        131..132,
-                    ),
-                ]
+                [(
+                    LoweringDiagnosticKind::UndefinedRef {
+                        name: i.intern("x"),
+                    },
+                    132..133,
+                )]
             },
         )
     }
@@ -4315,6 +6728,30 @@ mod tests {
         )
     }
 
+    #[test]
+    fn call_with_trailing_lambda() {
+        check(
+            r#"
+                each :: (items: []i32, f: (i32) -> void) {}
+
+                foo :: () {
+                    each(i32.[1, 2, 3]) (x: i32) {
+                        x * 2;
+                    };
+                }
+            "#,
+            expect![[r#"
+                main::each :: (p0: []i32, p1: (p0: i32) -> void <missing>) {};
+                main::foo :: () {
+                    each(i32.[1, 2, 3], (p0: i32) {
+                        p0 * 2;
+                    });
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
     #[test]
     fn extern_lambda() {
         check(
@@ -4464,6 +6901,44 @@ mod tests {
         )
     }
 
+    #[test]
+    fn array_inferred_size() {
+        check(
+            r#"
+                main :: () {
+                    my_array : [_]i32 = i32.[4, 8, 15];
+                }
+            "#,
+            expect![[r#"
+                main::main :: () {
+                    l0 : [_]i32 = i32.[4, 8, 15];
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn array_repeat() {
+        check(
+            r#"
+                main :: () {
+                    zeroes : [256]u8 = u8.[0; 256];
+                    x := 5;
+                    xs := .[x; 3];
+                }
+            "#,
+            expect![[r#"
+                main::main :: () {
+                    l0 : [256]u8 = u8.[0; 256];
+                    l1 := 5;
+                    l2 := .[l1; 3];
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
     #[test]
     fn comptime() {
         check(
@@ -4686,38 +7161,277 @@ mod tests {
                 }
             "#,
             expect![[r#"
-                main::foo :: () { `1 { {
-                            break 1`;
-                        } } };
+                main::foo :: () { `1 { {
+                            break 1`;
+                        } } };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn break_non_existent_label() {
+        check(
+            r#"
+                foo :: () {
+                    {
+                        {
+                            break blk`;
+                        }
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () { { {
+                            break <unknown>`;
+                        } } };
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::UndefinedLabel {
+                        name: i.intern("blk"),
+                    },
+                    111..115,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn bare_labels() {
+        check(
+            r#"
+                foo :: () {
+                    outer: while true {
+                        inner: loop {
+                            continue outer;
+                            break outer;
+                        }
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    `1 while true {
+                        loop {
+                            continue 1`;
+                            break 1`;
+                        }
+                    }
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn break_bare_label_or_local() {
+        check(
+            r#"
+                foo :: () -> i32 {
+                    outer: {
+                        outer := 5;
+                        break outer;
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () -> i32 { `1 {
+                        l0 := 5;
+                        break 1` l0;
+                    } };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn duplicate_label() {
+        check(
+            r#"
+                foo :: () {
+                    outer: while true {
+                        outer: loop {}
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () { while true { loop {} } };
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::DuplicateLabel {
+                        name: i.intern("outer"),
+                    },
+                    93..99,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn inline_hints() {
+        check(
+            r#"
+                double :: (x: i32) -> i32 @inline {
+                    x * 2
+                }
+
+                cold :: () @no_inline {}
+            "#,
+            expect![[r#"
+                main::double :: (p0: i32) -> i32 @inline { p0 * 2 };
+                main::cold :: () @no_inline {};
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn unknown_function_attr() {
+        check(
+            r#"
+                foo :: () @always {}
+            "#,
+            expect![[r#"
+                main::foo :: () {};
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::UnknownAttr {
+                        name: i.intern("always"),
+                    },
+                    27..34,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn inline_hint_on_extern() {
+        check(
+            r#"
+                puts :: (s: str) -> i32 @inline extern;
+            "#,
+            expect![[r#"
+                main::puts :: (p0: str) -> i32 extern;
+            "#]],
+            |_| [(LoweringDiagnosticKind::InlineHintOnExtern, 41..48)],
+        )
+    }
+
+    #[test]
+    fn export_name() {
+        check(
+            r#"
+                add :: (x: i32, y: i32) -> i32 @export("capy_add") {
+                    x + y
+                }
+            "#,
+            expect![[r#"
+                main::add :: (p0: i32, p1: i32) -> i32 @export("capy_add") { p0 + p1 };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn empty_export_name() {
+        check(
+            r#"
+                foo :: () @export("") {}
+                bar :: () @export {}
+            "#,
+            expect![[r#"
+                main::foo :: () {};
+                main::bar :: () {};
+            "#]],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::BadExportName, 27..38),
+                    (LoweringDiagnosticKind::BadExportName, 68..75),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn export_name_on_extern() {
+        check(
+            r#"
+                puts :: (s: str) -> i32 @export("my_puts") extern;
+            "#,
+            expect![[r#"
+                main::puts :: (p0: str) -> i32 extern;
+            "#]],
+            |_| [(LoweringDiagnosticKind::ExportNameOnExtern, 41..59)],
+        )
+    }
+
+    #[test]
+    fn attrs() {
+        check(
+            r#"
+                @inline double :: (x: i32) -> i32 {
+                    x * 2
+                }
+
+                @export("capy_cold")
+                @no_inline
+                cold :: () {}
+
+                add :: (x: i32, y: i32) -> i32 @export("capy_add") { x + y }
+
+                @deprecated("use `add`") old_add :: (x: i32, y: i32) -> i32 { x + y }
+            "#,
+            expect![[r#"
+                main::double :: (p0: i32) -> i32 @inline { p0 * 2 };
+                main::cold :: () @no_inline @export("capy_cold") {};
+                main::add :: (p0: i32, p1: i32) -> i32 @export("capy_add") { p0 + p1 };
+                main::old_add :: (p0: i32, p1: i32) -> i32 { p0 + p1 };
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_non_existent_label() {
+    fn misplaced_attrs() {
         check(
             r#"
-                foo :: () {
-                    {
-                        {
-                            break blk`;
-                        }
-                    }
-                }
+                @inline FOO :: 5;
+                @always bar :: () {}
+                baz :: () @deprecated @always {}
+                puts :: (s: str) -> i32 @export("my_puts") extern;
             "#,
             expect![[r#"
-                main::foo :: () { { {
-                            break <unknown>`;
-                        } } };
+                main::FOO :: 5;
+                main::bar :: () {};
+                main::baz :: () {};
+                main::puts :: (p0: str) -> i32 extern;
             "#]],
             |i| {
-                [(
-                    LoweringDiagnosticKind::UndefinedLabel {
-                        name: i.intern("blk"),
-                    },
-                    111..115,
-                )]
+                [
+                    (
+                        LoweringDiagnosticKind::FunctionAttrOnNonFunction {
+                            name: i.intern("inline"),
+                        },
+                        17..24,
+                    ),
+                    (
+                        LoweringDiagnosticKind::UnknownAttr {
+                            name: i.intern("always"),
+                        },
+                        51..58,
+                    ),
+                    (LoweringDiagnosticKind::DeprecatedOnLambda, 98..109),
+                    (
+                        LoweringDiagnosticKind::UnknownAttr {
+                            name: i.intern("always"),
+                        },
+                        110..117,
+                    ),
+                    (LoweringDiagnosticKind::ExportNameOnExtern, 161..179),
+                ]
             },
         )
     }
@@ -4771,6 +7485,55 @@ mod tests {
         )
     }
 
+    #[test]
+    fn while_binding() {
+        check(
+            r#"
+                foo :: (b: ?i32) {
+                    while x := b {
+                        x;
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo :: (p0: ?i32) {
+                    `1 loop {
+                        l0 := p0;
+                        if l0 == null {
+                            break 1`;
+                        };
+                        l1 := #unwrap(l0);
+                        {
+                            l1;
+                        }
+                    }
+                };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn for_loop() {
+        check(
+            r#"
+                foo :: (xs: bool) {
+                    for x in xs {
+                        x;
+                        continue;
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo :: (p0: bool) { `1 for f0 in p0 {
+                        f0;
+                        continue 1`;
+                    } };
+            "#]],
+            |_| [],
+        )
+    }
+
     #[test]
     fn continue_loop() {
         check(
@@ -5120,7 +7883,7 @@ mod tests {
             "#,
             expect![[r#"
                 main::bar :: () {
-                    l0 := struct'0 {x: i32, y: str};
+                    l0 := struct'4740833129795327092 {x: i32, y: str};
                     l1 := l0.{x = 42, y = 5};
                 };
             "#]],
@@ -5128,6 +7891,180 @@ mod tests {
         )
     }
 
+    #[test]
+    fn struct_decls() {
+        check(
+            r#"
+                Point :: struct {
+                    x: i32,
+                    ORIGIN :: Point.{ x = 0 },
+                    get_x :: (self: Point) -> i32 { self.x }
+                };
+            "#,
+            expect![[r#"
+                main::Point :: struct'16813187872227558801 {x: i32};
+                main::Point.ORIGIN :: Point.{x = 0};
+                main::Point.get_x :: (p0: Point) -> i32 { p0.x };
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn interface_decl() {
+        check(
+            r#"
+                Shape :: interface {
+                    area: () -> f32,
+                    scale: (by: f32) -> void,
+                };
+            "#,
+            expect![[r#"
+                main::Shape :: interface'15166354520361963668 {area: () -> f32 <missing>, scale: (p0: f32) -> void <missing>};
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn struct_decls_in_local_struct() {
+        check(
+            r#"
+                foo :: () {
+                    Bar :: struct {
+                        a: i32,
+                        X :: 5,
+                    };
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := struct'11430245015546164983 {a: i32};
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::NonGlobalStructDecl, 121..127)],
+        )
+    }
+
+    #[test]
+    fn modules() {
+        check(
+            r#"
+                math :: module {
+                    square :: (x: i32) -> i32 { x * x }
+                    fourth :: (x: i32) -> i32 { square(square(x)) }
+                    me :: #this();
+                };
+                file :: #this();
+            "#,
+            expect![[r#"
+                main::math :: struct'12582075196468943567 {};
+                main::math.square :: (p0: i32) -> i32 { p0 * p0 };
+                main::math.fourth :: (p0: i32) -> i32 { math.square(math.square(p0)) };
+                main::math.me :: math;
+                main::file :: #import("main.capy");
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn non_global_module() {
+        check(
+            r#"
+                foo :: () {
+                    m :: module {};
+                }
+            "#,
+            expect![[r#"
+                main::foo :: () {
+                    l0 := <missing>;
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::NonGlobalModule, 54..63)],
+        )
+    }
+
+    #[test]
+    fn open_ranges() {
+        check(
+            r#"
+                foo :: (array: [4]i32) {
+                    a := array[1..];
+                    b := 1..;
+                    c := array[..=];
+                }
+            "#,
+            expect![[r#"
+                main::foo :: (p0: [4]i32) {
+                    l0 := p0[1..];
+                    l1 := 1..;
+                    l2 := p0[..=];
+                };
+            "#]],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::OpenRangeOutsideIndex, 104..107),
+                    (LoweringDiagnosticKind::InclusiveRangeWithoutEnd, 140..143),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn requirements() {
+        check(
+            r#"
+                #requires_version(">=0.1");
+                #requires_version("<0.1, >=99");
+                #requires_version("0.x");
+                #requires_feature("using");
+                #requires_feature("time_travel");
+                #import("foo.capy");
+
+                foo :: 1;
+            "#,
+            expect![[r#"
+                main::foo :: 1;
+            "#]],
+            |_| {
+                [
+                    (
+                        LoweringDiagnosticKind::UnsupportedVersion {
+                            requirement: "<0.1, >=99".to_string(),
+                        },
+                        79..91,
+                    ),
+                    (
+                        LoweringDiagnosticKind::InvalidVersionRequirement {
+                            requirement: "0.x".to_string(),
+                        },
+                        128..133,
+                    ),
+                    (
+                        LoweringDiagnosticKind::UnsupportedFeature {
+                            feature: "time_travel".to_string(),
+                        },
+                        214..227,
+                    ),
+                    (LoweringDiagnosticKind::NonRequirementAtRoot, 246..265),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn version_requirements() {
+        assert_eq!(version_matches(">=0.2", "0.2.0"), Some(true));
+        assert_eq!(version_matches("0.2", "0.3.1"), Some(true));
+        assert_eq!(version_matches(">=0.2", "0.1.0-alpha.1"), Some(false));
+        assert_eq!(version_matches(">=0.1, <0.2", "0.1.0-alpha.1"), Some(true));
+        assert_eq!(version_matches("=1", "1.0.0"), Some(true));
+        assert_eq!(version_matches(">1.2.3", "1.2.3"), Some(false));
+        assert_eq!(version_matches("", "0.1.0"), None);
+        assert_eq!(version_matches(">=1.2.3.4", "0.1.0"), None);
+    }
+
     #[test]
     fn enums() {
         check(
@@ -5146,7 +8083,7 @@ mod tests {
             "#,
             expect![[r#"
                 main::bar :: () {
-                    l0 := enum'4 {Bar'0, Baz'1 | 5, Qux'2: i32, Quux'3: bool | 1000};
+                    l0 := enum'13970203958866846256 {Bar'4740833129795327092, Baz'16268548477809611013 | 5, Qux'9349519752114343318: i32, Quux'2430491026419075623: bool | 1000};
                     l1 : l0.Qux = 42;
                     l2 : l0 = l1;
                 };
@@ -5183,7 +8120,7 @@ mod tests {
             "#,
             expect![[r#"
                 main::bar :: () {
-                    l0 := enum'4 {Bar'0, Baz'1 | 5, Qux'2: i32, Quux'3: bool | 1000};
+                    l0 := enum'13970203958866846256 {Bar'4740833129795327092, Baz'16268548477809611013 | 5, Qux'9349519752114343318: i32, Quux'2430491026419075623: bool | 1000};
                     l1 : l0.Qux = l0.Qux.(42);
                     switch f in l1 {
                         Bar (s0) => 10,
@@ -5227,7 +8164,7 @@ mod tests {
             "#,
             expect![[r#"
                 main::bar :: () {
-                    l0 := enum'4 {Bar'0, Baz'1 | 5, Qux'2: i32, Quux'3: bool | 1000};
+                    l0 := enum'13970203958866846256 {Bar'4740833129795327092, Baz'16268548477809611013 | 5, Qux'9349519752114343318: i32, Quux'2430491026419075623: bool | 1000};
                     l1 : l0.Qux = l0.Quux.(true);
                     switch f in l1 {
                         Bar (s0) => 10,
@@ -5308,4 +8245,167 @@ mod tests {
             |_| [],
         )
     }
+
+    #[test]
+    fn descendants_rev_is_reversed_descendants() {
+        let input = r#"
+            Foo :: struct { a: [3]i32, b: ^mut bool };
+
+            foo :: (x: i32) -> i32 {
+                y := x * 2 + 1;
+                z := `blk: {
+                    if y > 5 {
+                        break `blk y;
+                    }
+                    loop {
+                        if y == 0 { break 7; }
+                        y -= 1;
+                    }
+                };
+                arr := .[1, 2, z];
+                f := Foo.{ a = arr, b = ^mut true };
+                bar := (a: i32) -> i32 { a + arr[0] };
+                defer bar(comptime { 2 });
+                f.a[1] + bar(y)
+            }
+        "#;
+
+        let mut interner = Interner::default();
+
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = crate::index(root, &tree, &mut interner);
+
+        let (bodies, _) = lower(
+            root,
+            &tree,
+            Path::new("main.capy"),
+            &index,
+            &mut interner,
+            Path::new("/capy/modules"),
+            true,
+        );
+
+        let include_local_value = |_: Idx<LocalDef>| true;
+
+        for body in bodies.global_bodies.values().copied() {
+            for opts in [
+                DescentOpts::Infer,
+                DescentOpts::Reinfer,
+                DescentOpts::Types {
+                    include_local_value: &include_local_value,
+                },
+                DescentOpts::All {
+                    include_lambdas: true,
+                },
+            ] {
+                let mut expected: Vec<_> = bodies.descendants(body, opts).collect();
+                expected.reverse();
+
+                let actual: Vec<_> = bodies.descendants_rev(body, opts).collect();
+
+                assert_eq!(expected, actual);
+            }
+        }
+    }
+
+    /// `$0` marks the position to look at
+    fn check_scopes(input: &str, expect: Expect) {
+        let offset = TextSize::from(input.find("$0").unwrap() as u32);
+        let input = &input.replace("$0", "");
+
+        let mut interner = Interner::default();
+
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = crate::index(root, &tree, &mut interner);
+
+        let (bodies, _) = lower(
+            root,
+            &tree,
+            Path::new("main.capy"),
+            &index,
+            &mut interner,
+            Path::new("/capy/modules"),
+            true,
+        );
+
+        let actual = bodies
+            .scopes_at(offset)
+            .into_iter()
+            .map(|(name, def)| {
+                let kind = match def {
+                    ScopeDef::Local(_) => "local",
+                    ScopeDef::SwitchLocal(_) => "switch local",
+                    ScopeDef::ForLocal(_) => "for local",
+                    ScopeDef::Param { .. } => "param",
+                };
+                format!("{} ({kind})\n", interner.lookup(name.0))
+            })
+            .collect::<String>();
+
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn scopes_at_nested_blocks() {
+        check_scopes(
+            r#"
+                foo :: (a: i32, b: i32) {
+                    x := 1;
+                    {
+                        a := x;
+                        y := $0a;
+                    }
+                    z := 3;
+                };
+            "#,
+            expect![[r#"
+                a (local)
+                x (local)
+                b (param)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn scopes_at_inner_lambda() {
+        check_scopes(
+            r#"
+                foo :: () {
+                    outer := 1;
+                    bar := (inner: i32) {
+                        $0
+                    };
+                };
+            "#,
+            expect![[r#"
+                inner (param)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn scopes_at_for_and_switch() {
+        check_scopes(
+            r#"
+                foo :: (items: [3]i32, shape: Shape) {
+                    for item in items {
+                        switch s in shape {
+                            Circle => $0s,
+                            Square => {},
+                        }
+                    }
+                };
+            "#,
+            expect![[r#"
+                s (switch local)
+                item (for local)
+                items (param)
+                shape (param)
+            "#]],
+        );
+    }
 }
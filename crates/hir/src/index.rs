@@ -10,6 +10,11 @@ use crate::Name;
 pub struct Index {
     pub(crate) definitions: FxHashSet<Name>,
     pub(crate) range_info: FxHashMap<Name, RangeInfo>,
+    pub(crate) docs: FxHashMap<Name, String>,
+    pub(crate) deprecated: FxHashMap<Name, Option<Key>>,
+    /// functions that share the name they were written with.
+    /// the first one keeps that name, and the rest are known as `add#1`, `add#2`, etc.
+    pub(crate) overloads: FxHashMap<Name, Vec<Name>>,
 }
 
 impl Index {
@@ -32,13 +37,34 @@ impl Index {
         self.range_info.iter().map(|(n, r)| (*n, r))
     }
 
+    /// The `///` doc comments written above the definition, with each line separated by `\n`
+    pub fn docs(&self, name: Name) -> Option<&str> {
+        self.docs.get(&name).map(String::as_str)
+    }
+
+    /// Whether the definition was marked with `@deprecated`, along with the message given to it
+    pub fn deprecated(&self, name: Name) -> Option<Option<Key>> {
+        self.deprecated.get(&name).copied()
+    }
+
+    /// Every function defined with the given name, if there's more than one
+    pub fn overloads(&self, name: Name) -> Option<&[Name]> {
+        self.overloads.get(&name).map(Vec::as_slice)
+    }
+
     pub fn shrink_to_fit(&mut self) {
         let Self {
             definitions,
             range_info,
+            docs,
+            deprecated,
+            overloads,
         } = self;
         definitions.shrink_to_fit();
         range_info.shrink_to_fit();
+        docs.shrink_to_fit();
+        deprecated.shrink_to_fit();
+        overloads.shrink_to_fit();
     }
 }
 
@@ -58,14 +84,18 @@ pub fn index(
         index: Index {
             definitions: FxHashSet::default(),
             range_info: FxHashMap::default(),
+            docs: FxHashMap::default(),
+            deprecated: FxHashMap::default(),
+            overloads: FxHashMap::default(),
         },
+        functions: FxHashSet::default(),
         diagnostics: Vec::new(),
         tree,
         interner,
     };
 
-    for def in root.defs(tree) {
-        ctx.index_def(def);
+    for (def, docs) in root.documented_defs(tree) {
+        ctx.index_def(def, &docs, None);
     }
 
     ctx.index.shrink_to_fit();
@@ -75,20 +105,68 @@ pub fn index(
 
 struct IndexingCtx<'a> {
     index: Index,
+    /// the names whose first definition was a function with a body, which can be overloaded
+    functions: FxHashSet<Name>,
     diagnostics: Vec<IndexingDiagnostic>,
     tree: &'a SyntaxTree,
     interner: &'a mut Interner,
 }
 
 impl IndexingCtx<'_> {
-    fn index_def(&mut self, def: ast::Define) {
+    /// declarations inside of a struct or module body are indexed under its namespace,
+    /// so `ORIGIN` inside of `Point :: struct { ... }` becomes `Point.ORIGIN`
+    fn index_def(&mut self, def: ast::Define, docs: &[ast::Comment], namespace: Option<&str>) {
         let name_token = match def.name(self.tree) {
             Some(ident) => ident,
             None => return,
         };
-        let name = Name(self.interner.intern(name_token.text(self.tree)));
+        let full_name = match namespace {
+            Some(namespace) => format!("{}.{}", namespace, name_token.text(self.tree)),
+            None => name_token.text(self.tree).to_string(),
+        };
+        let mut name = Name(self.interner.intern_ident(&full_name));
         let name_range = name_token.range(self.tree);
 
+        let is_function = def.r#extern(self.tree).is_none()
+            && matches!(
+                def.value(self.tree),
+                Some(ast::Expr::Lambda(lambda))
+                    if lambda.body(self.tree).is_some() && lambda.r#extern(self.tree).is_none()
+            );
+
+        match def.value(self.tree) {
+            Some(ast::Expr::StructDecl(struct_decl)) => {
+                for (decl, docs) in struct_decl.documented_decls(self.tree) {
+                    self.index_def(decl, &docs, Some(&full_name));
+                }
+            }
+            Some(ast::Expr::ModuleDecl(module_decl)) => {
+                for (decl, docs) in module_decl.documented_decls(self.tree) {
+                    self.index_def(decl, &docs, Some(&full_name));
+                }
+            }
+            _ => {}
+        }
+
+        if !self.index.definitions.contains(&name) {
+            if is_function {
+                self.functions.insert(name);
+            }
+        } else if is_function && self.functions.contains(&name) {
+            let overloads = self
+                .index
+                .overloads
+                .entry(name)
+                .or_insert_with(|| vec![name]);
+            let overload = Name(self.interner.intern(&format!(
+                "{}#{}",
+                full_name,
+                overloads.len()
+            )));
+            overloads.push(overload);
+            name = overload;
+        }
+
         if self.index.definitions.contains(&name) {
             self.diagnostics.push(IndexingDiagnostic {
                 kind: IndexingDiagnosticKind::AlreadyDefined { name: name.0 },
@@ -100,6 +178,31 @@ impl IndexingCtx<'_> {
             }
 
             self.index.definitions.insert(name);
+            if !docs.is_empty() {
+                let docs = docs
+                    .iter()
+                    .filter_map(|doc| doc.doc(self.tree))
+                    // `/// foo` is written with a space but the space isn't part of the doc
+                    .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.index.docs.insert(name, docs);
+            }
+            // the other attributes are checked when lowering, since they only apply to functions
+            if let Some(attr) = def.attrs(self.tree).find(|attr| {
+                attr.name(self.tree)
+                    .is_some_and(|name| name.text(self.tree) == "deprecated")
+            }) {
+                // escapes are left as they were written
+                let message = attr.value(self.tree).map(|value| {
+                    let text: String = value
+                        .components(self.tree)
+                        .map(|component| component.text(self.tree))
+                        .collect();
+                    self.interner.intern(&text)
+                });
+                self.index.deprecated.insert(name, message);
+            }
             self.index.range_info.insert(
                 name,
                 RangeInfo {
@@ -139,6 +242,20 @@ impl Index {
         defs.sort_unstable();
 
         for name in defs {
+            if let Some(docs) = self.docs(*name) {
+                for line in docs.lines() {
+                    s.push_str("/// ");
+                    s.push_str(line);
+                    s.push('\n');
+                }
+            }
+            match self.deprecated(*name) {
+                Some(Some(message)) => {
+                    s.push_str(&format!("@deprecated(\"{}\")\n", interner.lookup(message)))
+                }
+                Some(None) => s.push_str("@deprecated\n"),
+                None => {}
+            }
             s.push_str(interner.lookup(name.0));
             s.push('\n');
         }
@@ -269,4 +386,177 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn overloaded_functions() {
+        check(
+            r#"
+                add :: (a: i32, b: i32) -> i32 { a + b }
+                add :: (a: f32, b: f32) -> f32 { a + b }
+                add :: (a: i32) -> i32 { a }
+                add :: 5;
+            "#,
+            expect![[r"
+                add
+                add#1
+                add#2
+            "]],
+            |i| {
+                [(
+                    IndexingDiagnosticKind::AlreadyDefined {
+                        name: i.intern("add"),
+                    },
+                    176..179,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn struct_decls() {
+        check(
+            r#"
+                Point :: struct {
+                    x: f32,
+                    ORIGIN :: 0,
+                    len :: () {}
+                };
+            "#,
+            expect![[r"
+                Point
+                Point.ORIGIN
+                Point.len
+            "]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn nested_struct_decls() {
+        check(
+            r#"
+                A :: struct {
+                    B :: struct {
+                        C :: 5,
+                        C :: 6,
+                    },
+                };
+            "#,
+            expect![[r"
+                A
+                A.B
+                A.B.C
+            "]],
+            |i| {
+                [(
+                    IndexingDiagnosticKind::AlreadyDefined {
+                        name: i.intern("A.B.C"),
+                    },
+                    121..122,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn module_decls() {
+        check(
+            r#"
+                math :: module {
+                    PI :: 3;
+                    inner :: module {
+                        E :: 2;
+                    };
+                };
+            "#,
+            expect![[r"
+                math
+                math.PI
+                math.inner
+                math.inner.E
+            "]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn doc_comments() {
+        check(
+            r#"
+                /// the answer to everything
+                /// (probably)
+                foo :: 42;
+
+                // a regular comment
+                bar :: 5;
+
+                /// this doesn't document anything
+                // because of this comment
+                baz :: 1;
+
+                //// neither does this
+                qux :: 2;
+            "#,
+            expect![[r"
+                /// the answer to everything
+                /// (probably)
+                foo
+                bar
+                baz
+                qux
+            "]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn deprecated() {
+        check(
+            r#"
+                /// adds things
+                @deprecated("use `add` instead")
+                old_add :: (x: i32, y: i32) -> i32 { x + y }
+
+                @deprecated
+                Point :: struct {
+                    @deprecated ORIGIN :: 0;
+                };
+
+                @inline add :: (x: i32, y: i32) -> i32 { x + y }
+            "#,
+            expect![[r#"
+                /// adds things
+                @deprecated("use `add` instead")
+                old_add
+                @deprecated
+                Point
+                @deprecated
+                Point.ORIGIN
+                add
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn doc_comments_in_struct() {
+        check(
+            r#"
+                /// a point in space
+                Point :: struct {
+                    x: i32,
+
+                    /// the center of the world
+                    ORIGIN :: 0;
+                };
+            "#,
+            expect![[r"
+                /// a point in space
+                Point
+                /// the center of the world
+                Point.ORIGIN
+            "]],
+            |_| [],
+        )
+    }
 }
@@ -35,7 +35,8 @@ impl FileName {
         } else if file_name.is_sub_dir_of(&curr_dir) {
             pathdiff::diff_paths(file_name, curr_dir).unwrap()
         } else {
-            unreachable!()
+            // this can happen when the compiler is used as a library from somewhere else
+            file_name.to_path_buf()
         };
 
         let components = relative_path
@@ -123,6 +124,22 @@ impl Fqn {
             interner.lookup(self.name.0),
         )
     }
+
+    /// The name a declaration inside of this global's struct body would have been given.
+    ///
+    /// This doesn't check if that declaration actually exists.
+    pub fn namespaced(self, member: Name, interner: &Interner) -> Option<Fqn> {
+        let name = format!(
+            "{}.{}",
+            interner.lookup(self.name.0),
+            interner.lookup(member.0)
+        );
+
+        interner.get(&name).map(|key| Fqn {
+            file: self.file,
+            name: Name(key),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -199,7 +216,7 @@ impl PrimitiveTy {
         if let Some(ast::Expr::VarRef(var_ref)) = ty {
             let ident = var_ref.name(tree)?;
 
-            let key = interner.intern(ident.text(tree));
+            let key = interner.intern_ident(ident.text(tree));
             let range = ident.range(tree);
 
             if key == Key::void() {
@@ -299,6 +316,43 @@ impl PrimitiveTy {
         }
     }
 
+    /// parses the type suffix of a numeric literal, like the `u8` in `42u8`
+    pub fn parse_literal_suffix(suffix: &str, range: TextRange) -> Option<Self> {
+        let (signed, bit_width) = match suffix {
+            "isize" => (true, u8::MAX),
+            "i128" => (true, 128),
+            "i64" => (true, 64),
+            "i32" => (true, 32),
+            "i16" => (true, 16),
+            "i8" => (true, 8),
+            "usize" => (false, u8::MAX),
+            "u128" => (false, 128),
+            "u64" => (false, 64),
+            "u32" => (false, 32),
+            "u16" => (false, 16),
+            "u8" => (false, 8),
+            "f64" => {
+                return Some(PrimitiveTy::Float {
+                    bit_width: 64,
+                    range,
+                })
+            }
+            "f32" => {
+                return Some(PrimitiveTy::Float {
+                    bit_width: 32,
+                    range,
+                })
+            }
+            _ => return None,
+        };
+
+        if signed {
+            Some(PrimitiveTy::IInt { bit_width, range })
+        } else {
+            Some(PrimitiveTy::UInt { bit_width, range })
+        }
+    }
+
     pub fn display(&self) -> String {
         match self {
             Self::IInt { bit_width, .. } => {
@@ -310,9 +364,9 @@ impl PrimitiveTy {
             }
             Self::UInt { bit_width, .. } => {
                 if *bit_width != u8::MAX {
-                    format!("i{}", bit_width)
+                    format!("u{}", bit_width)
                 } else {
-                    "isize".to_string()
+                    "usize".to_string()
                 }
             }
             Self::Float { bit_width, .. } => format!("f{}", bit_width),
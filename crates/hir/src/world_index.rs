@@ -1,6 +1,7 @@
+use interner::Key;
 use rustc_hash::FxHashMap;
 
-use crate::{FileName, Fqn, Index, RangeInfo};
+use crate::{FileName, Fqn, Index, Name, RangeInfo};
 
 #[derive(Default, Debug)]
 pub struct WorldIndex(FxHashMap<FileName, Index>);
@@ -23,6 +24,19 @@ impl WorldIndex {
         &self.0[&fqn.file].range_info[&fqn.name]
     }
 
+    pub fn docs(&self, fqn: Fqn) -> Option<&str> {
+        self.0.get(&fqn.file)?.docs(fqn.name)
+    }
+
+    pub fn deprecated(&self, fqn: Fqn) -> Option<Option<Key>> {
+        self.0.get(&fqn.file)?.deprecated(fqn.name)
+    }
+
+    /// Every function defined with the same name as the given one, if there's more than one
+    pub fn overloads(&self, fqn: Fqn) -> Option<&[Name]> {
+        self.0.get(&fqn.file)?.overloads(fqn.name)
+    }
+
     pub fn get_all_files(&self) -> Vec<(FileName, &Index)> {
         self.0.iter().map(|(file, index)| (*file, index)).collect()
     }
@@ -39,6 +53,27 @@ impl WorldIndex {
         *self.0.get_mut(&file).unwrap() = index;
     }
 
+    /// Finds the global the program should start from.
+    ///
+    /// This only needs every file to have been indexed.
+    /// Whether the global is actually a function with the right signature gets checked
+    /// during type inference
+    pub fn entry_point(&self, name: Name) -> Result<Fqn, EntryPointError> {
+        let mut candidates = self
+            .0
+            .iter()
+            .filter(|(_, index)| index.has_definition(name))
+            .map(|(file, _)| Fqn { file: *file, name })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+
+        match candidates.as_slice() {
+            [] => Err(EntryPointError::Missing { name }),
+            [entry_point] => Ok(*entry_point),
+            _ => Err(EntryPointError::Multiple { candidates }),
+        }
+    }
+
     pub fn ranges(&self) -> impl Iterator<Item = (Fqn, &RangeInfo)> {
         self.0.iter().flat_map(|(file, index)| {
             index
@@ -48,6 +83,12 @@ impl WorldIndex {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryPointError {
+    Missing { name: Name },
+    Multiple { candidates: Vec<Fqn> },
+}
+
 #[derive(Debug)]
 pub enum DefinitionStatus {
     Defined,
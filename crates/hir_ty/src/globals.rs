@@ -1,8 +1,12 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashSet, VecDeque},
+    mem,
+    ops::RangeInclusive,
+};
 
 use hir::{Descendant, Expr, FQComptime, FQLambda, LocalDef, MemberLiteral, ScopeId, Stmt};
 use indexmap::IndexMap;
-use interner::Interner;
+use interner::{Interner, Key};
 use internment::Intern;
 use itertools::Itertools;
 use la_arena::{ArenaMap, Idx};
@@ -12,11 +16,18 @@ use topo::TopoSort;
 
 use crate::{
     ty::{self, BinaryOutput},
-    ComptimeResult, EvalComptimeFn, ExpectedTy, InferResult, Inferrable, InternTyExt, MemberTy,
-    ParamTy, ProjectInference, Ty, TyDiagnostic, TyDiagnosticHelp, TyDiagnosticHelpKind,
-    TyDiagnosticKind, TypedOp, UnaryOutput,
+    ComptimeLimitExceeded, ComptimeResult, EvalComptimeFn, ExpectedTy, FileInference, InferResult,
+    Inferrable, InternTyExt, IntrinsicOperands, MemberTy, ParamTy, ProjectInference, Ty,
+    TyDiagnostic, TyDiagnosticHelp, TyDiagnosticHelpKind, TyDiagnosticKind, TypedOp, UnaryOutput,
+    COMPTIME_EXTERNS, COMPTIME_TRAPPED_EXTERNS,
 };
 
+/// The type the arguments of a math intrinsic had so far, and the argument that didn't fit it
+type MathIntrinsicMismatch = (Intern<Ty>, Idx<hir::Expr>);
+
+/// Copying an array of at least this many bytes by value gives a warning
+const LARGE_ARRAY_COPY_SIZE: u32 = 1024;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ExprIsConst {
     /// the value of the expression is known at compile-time
@@ -46,6 +57,7 @@ enum ExprMutability {
     ImmutableRef(TextRange),
     ImmutableParam(TextRange, bool),
     ImmutableGlobal(TextRange),
+    ImmutableCapture(TextRange),
     CannotMutate(TextRange),
 }
 
@@ -72,6 +84,10 @@ impl ExprMutability {
                 kind: TyDiagnosticHelpKind::ImmutableGlobal,
                 range,
             }),
+            ExprMutability::ImmutableCapture(range) => Some(TyDiagnosticHelp {
+                kind: TyDiagnosticHelpKind::ImmutableCapture,
+                range,
+            }),
             ExprMutability::NotMutatingRefThroughDeref(range) => Some(TyDiagnosticHelp {
                 kind: TyDiagnosticHelpKind::NotMutatingRefThroughDeref,
                 range,
@@ -81,6 +97,158 @@ impl ExprMutability {
     }
 }
 
+/// Remembers the results of `GlobalInferenceCtx::is_safe_to_compile`.
+///
+/// A result stays valid until a new error diagnostic is pushed, since the types of everything
+/// that's already been inferred won't change.
+#[derive(Default)]
+pub(crate) struct SafeToCompileCache {
+    /// every expression that has an error diagnostic attached to it
+    error_exprs: FxHashSet<(hir::FileName, Idx<Expr>)>,
+    /// how many diagnostics have already been looked at for `error_exprs`
+    diagnostics_seen: usize,
+    /// incremented every time a new error diagnostic is found
+    generation: u32,
+    results: FxHashMap<(Inferrable, Idx<Expr>), (u32, bool)>,
+    /// the first extern outside of `COMPTIME_EXTERNS` that each checked expression can reach
+    extern_calls: FxHashMap<(Inferrable, Idx<Expr>), hir::Fqn>,
+    /// the comptime blocks which already have a `ComptimeExtern` diagnostic
+    reported_externs: FxHashSet<(hir::FileName, Idx<Expr>)>,
+    /// the comptime blocks which went over a limit, so they don't get ran a second time
+    exceeded_limits: FxHashSet<FQComptime>,
+}
+
+impl SafeToCompileCache {
+    /// looks through any diagnostics that were pushed since the last update
+    pub(crate) fn update(&mut self, diagnostics: &[TyDiagnostic]) {
+        let mut any_new_errors = false;
+
+        for diag in &diagnostics[self.diagnostics_seen..] {
+            if !diag.is_error() {
+                continue;
+            }
+
+            any_new_errors = true;
+
+            if let Some(expr) = diag.expr {
+                self.error_exprs.insert((diag.file, expr));
+            }
+        }
+
+        self.diagnostics_seen = diagnostics.len();
+
+        if any_new_errors {
+            self.generation += 1;
+        }
+    }
+
+    pub(crate) fn get(&self, inferrable: Inferrable, expr: Idx<Expr>) -> Option<bool> {
+        match self.results.get(&(inferrable, expr)) {
+            Some((generation, safe)) if *generation == self.generation => Some(*safe),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, inferrable: Inferrable, expr: Idx<Expr>, safe: bool) {
+        self.results
+            .insert((inferrable, expr), (self.generation, safe));
+    }
+}
+
+/// The statements that still need to be reinferred because the type of a local they use got
+/// stronger.
+#[derive(Default)]
+pub(crate) struct UsageWorklist {
+    queue: VecDeque<Idx<hir::Stmt>>,
+    queued: FxHashSet<Idx<hir::Stmt>>,
+    draining: bool,
+}
+
+/// Whether `previous_ty` should be replaced by `new_ty` while reinferring.
+///
+/// Types are only ever allowed to get stronger (`{uint}` -> `u8` -> `u16` ...), never weaker.
+/// Keeping the previous type whenever `new_ty` isn't stronger is what makes reinference converge.
+///
+/// Reinference doesn't know about the expected types that the first pass used, so it can come
+/// up with types unrelated to the previous ones, like the `i32` tail of a function returning
+/// `?i32`. Those are kept as they were.
+fn is_stronger_ty(previous_ty: &Ty, new_ty: &Ty) -> bool {
+    if previous_ty == new_ty || !previous_ty.is_weak_replaceable_by(new_ty) {
+        return false;
+    }
+
+    let loss_of_distinct = matches!(previous_ty, Ty::Distinct { .. })
+        && new_ty.is_functionally_equivalent_to(previous_ty, false);
+    let array_to_slice = matches!(
+        (previous_ty, new_ty),
+        (
+            Ty::Slice {
+                sub_ty: previous_sub_ty
+            },
+            Ty::Array {
+                sub_ty: new_sub_ty,
+                ..
+            }
+        ) if previous_sub_ty.is_weak_replaceable_by(new_sub_ty)
+            || previous_sub_ty.is_equal_to(new_sub_ty)
+    );
+    // this might happen in the following case:
+    // ```
+    // x : u64 = 1;
+    // y : i64 = 2;
+    //
+    // z : i64 = x + y;
+    // ```
+    // 1. since `u64 + i64` is invalid, the output of `x + y` is {uint} (the
+    //    default type of addition).
+    // 2. since the value of z is {uint}, it gets weak type replaced by {i64}.
+    // 3. reinfer_expr doesn't know about the weak type replacement, and would otherwise make
+    //    the type weak again.
+    let strong_int_to_weak_int = matches!(
+        (previous_ty, new_ty),
+        (Ty::UInt(strong_bit_width) | Ty::IInt(strong_bit_width), Ty::UInt(0) | Ty::IInt(0))
+            if *strong_bit_width != 0
+    );
+
+    !loss_of_distinct && !array_to_slice && !strong_int_to_weak_int
+}
+
+/// The item a member access like `tuple.1` refers to
+fn tuple_index(field_name: &str) -> Option<usize> {
+    if field_name.bytes().all(|b| b.is_ascii_digit()) {
+        field_name.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// The lambda of a global function which has compile-time parameters
+pub(crate) fn const_param_lambda(
+    world_bodies: &hir::WorldBodies,
+    fqn: hir::Fqn,
+) -> Option<FQLambda> {
+    if !world_bodies.exists(fqn) || world_bodies.is_extern(fqn) {
+        return None;
+    }
+
+    let body = world_bodies.body(fqn);
+    match world_bodies[fqn.file][body] {
+        Expr::Lambda(lambda)
+            if world_bodies[fqn.file][lambda]
+                .params
+                .iter()
+                .any(|param| param.is_const) =>
+        {
+            Some(FQLambda {
+                file: fqn.file,
+                expr: body,
+                lambda,
+            })
+        }
+        _ => None,
+    }
+}
+
 pub(crate) struct GlobalInferenceCtx<'a> {
     pub(crate) file: hir::FileName,
     pub(crate) currently_inferring: Inferrable,
@@ -91,13 +259,21 @@ pub(crate) struct GlobalInferenceCtx<'a> {
     // todo: what happens to this when an uninferred global is reached?
     // should this be stored in `InferenceCtx`?
     pub(crate) local_usages: ArenaMap<Idx<hir::LocalDef>, FxHashSet<Idx<hir::Stmt>>>,
+    pub(crate) usage_worklist: UsageWorklist,
+    /// a body which has to give back an optional, see `optional_body_ty`
+    pub(crate) optional_body: Option<(Idx<Expr>, Intern<Ty>)>,
     pub(crate) inferred_stmts: &'a mut FxHashSet<(hir::FileName, Idx<hir::Stmt>)>,
     pub(crate) tys: &'a mut ProjectInference,
     pub(crate) param_tys: Vec<ParamTy>,
     pub(crate) all_inferred: &'a FxHashSet<Inferrable>,
     pub(crate) to_infer: &'a mut TopoSort<Inferrable>,
     pub(crate) diagnostics: &'a mut Vec<TyDiagnostic>,
+    pub(crate) safe_to_compile: &'a mut SafeToCompileCache,
     pub(crate) eval_comptime: &'a mut dyn EvalComptimeFn,
+    pub(crate) lint_member_order: bool,
+    /// the function with compile-time parameters whose signature or body is being inferred,
+    /// along with the value of each of its parameters that's known at compile-time
+    pub(crate) const_params: Option<(Idx<hir::Lambda>, Vec<Option<u64>>)>,
 }
 
 impl GlobalInferenceCtx<'_> {
@@ -107,11 +283,20 @@ impl GlobalInferenceCtx<'_> {
         expected_ty: Option<Intern<Ty>>,
         global: bool,
     ) -> InferResult<Intern<Ty>> {
+        self.optional_body = expected_ty
+            .filter(|ty| ty.as_optional().is_some())
+            .map(|ty| (body, ty));
+
         self.infer_expr(body)?;
 
-        for (_, usages) in self.local_usages.clone() {
-            self.reinfer_usages(usages);
-        }
+        // a statement which uses multiple locals only needs to be reinferred once.
+        // statements are reinferred in the order they were lowered so that the types of
+        // earlier locals are correct by the time their later usages get reinferred
+        let mut all_usages = self.local_usages.values().flatten().copied().collect_vec();
+        all_usages.sort_unstable();
+        all_usages.dedup();
+
+        self.reinfer_usages(all_usages);
 
         let mut actual_ty = self.reinfer_expr(body);
 
@@ -120,10 +305,10 @@ impl GlobalInferenceCtx<'_> {
             self.replace_weak_tys(body, expected_ty);
 
             actual_ty = expected_ty;
-        } else if global && self.replace_weak_tys(body, *ty::I32) {
-            actual_ty = *ty::I32;
-        } else if global && self.replace_weak_tys(body, *ty::F64) {
-            actual_ty = *ty::F64;
+        } else if global && self.replace_weak_tys(body, *ty::DEFAULT_INT) {
+            actual_ty = *ty::DEFAULT_INT;
+        } else if global && self.replace_weak_tys(body, *ty::DEFAULT_FLOAT) {
+            actual_ty = *ty::DEFAULT_FLOAT;
         }
 
         if global && self.get_const(body).should_report_not_const() {
@@ -138,84 +323,144 @@ impl GlobalInferenceCtx<'_> {
             // println!("not const: {:#?}", &self.bodies[body]);
         }
 
+        self.report_divisions_by_zero(body)?;
+        self.report_uncalled_const_param_fns(body);
+        if !global {
+            self.report_escaping_closures(body);
+        }
+        self.check_interface_casts(body)?;
+        self.report_uncalled_interface_methods(body);
+
         Ok(actual_ty)
     }
 
-    fn reinfer_usages(&mut self, usages: FxHashSet<Idx<hir::Stmt>>) {
+    /// queues up the given usages to be reinferred.
+    ///
+    /// if the worklist is already being processed further up the stack, the new usages will be
+    /// picked up by that loop instead of recursing. since `replace_weak_tys` takes the usages of
+    /// a local out of `local_usages` whenever the local's type gets stronger, every usage can
+    /// only be queued a finite number of times, so this always terminates.
+    fn reinfer_usages(&mut self, usages: impl IntoIterator<Item = Idx<hir::Stmt>>) {
         for usage in usages {
-            match self.bodies[usage] {
-                hir::Stmt::LocalDef(user_local_def) => {
-                    let user_local_body = &self.bodies[user_local_def];
+            if self.usage_worklist.queued.insert(usage) {
+                self.usage_worklist.queue.push_back(usage);
+            }
+        }
 
-                    if let Some(value) = user_local_body.value {
-                        let user_local_ty = self.reinfer_expr(value);
+        if self.usage_worklist.draining {
+            return;
+        }
 
-                        // if there is no type annotation on the user, then replace it's type
-                        if user_local_body.ty.is_none() {
-                            self.tys[self.file]
-                                .local_tys
-                                .insert(user_local_def, user_local_ty);
-                        }
+        self.usage_worklist.draining = true;
+
+        while let Some(usage) = self.usage_worklist.queue.pop_front() {
+            self.usage_worklist.queued.remove(&usage);
+            self.reinfer_usage(usage);
+        }
+
+        self.usage_worklist.draining = false;
+    }
+
+    fn reinfer_usage(&mut self, usage: Idx<hir::Stmt>) {
+        match self.bodies[usage] {
+            hir::Stmt::LocalDef(user_local_def) => {
+                let user_local_body = &self.bodies[user_local_def];
+
+                if let Some(value) = user_local_body.value {
+                    let user_local_ty = self.reinfer_expr(value);
+
+                    // if there is no type annotation on the user, then replace it's type
+                    if user_local_body.ty.is_none() {
+                        self.tys[self.file]
+                            .local_tys
+                            .insert(user_local_def, user_local_ty);
                     }
                 }
-                hir::Stmt::Assign(assign) => {
-                    let assign_body = &self.bodies[assign];
-
-                    let dest_ty = self.reinfer_expr(assign_body.dest);
-                    let value_ty = self.reinfer_expr(assign_body.value);
-
-                    // this has to be done because in the following example:
-                    // ```
-                    // main::main :: (() ({
-                    //     l0 := (5 #0);
-                    //     (l0 #1) += ((1 #2) + (2 #3) #4);
-                    //     (l0 #5) -= ((2 #6) + (3 #7) #8);
-                    //     (l0 #9) *= ((i64 #11).((3 #10)) #12);
-                    //     (l0 #13) /= (4 #14);
-                    // } #15) #16);
-                    // ```
-                    // the statement at #9 will try to `replace_weak_tys` on the value and the dest
-                    // with u64, and this `replace_weak_tys` call will eventually call `reinfer_usages`
-                    // on the statements at #1, and #2, but while the dest's of these statements (#1
-                    // and #2) will get replaced with u64 as expected, their values (#4 and #8)
-                    // won't because they never get weak type replaced.
-                    //
-                    // TODO: this will probably create an infinite loop if the value of the assign
-                    // is the variable.
-                    match assign_body
-                        .quick_assign_op
-                        .map(|op| (op, op.get_possible_output_ty(&dest_ty, &value_ty)))
-                    {
-                        Some((_, Some(output_ty))) => {
-                            let max_ty = output_ty.max_ty.into();
+            }
+            hir::Stmt::Assign(assign) => {
+                let assign_body = &self.bodies[assign];
 
-                            self.replace_weak_tys(assign_body.dest, max_ty);
-                            self.replace_weak_tys(assign_body.value, max_ty);
-                        }
-                        Some((_, None)) => {}
-                        None => {
-                            if dest_ty.is_weak_replaceable_by(&value_ty) {
-                                self.replace_weak_tys(assign_body.dest, value_ty);
-                            } else if value_ty.can_fit_into(&dest_ty) {
-                                self.replace_weak_tys(assign_body.value, value_ty);
-                            }
-                        }
+                let dest_ty = self.reinfer_expr(assign_body.dest);
+                let value_ty = self.reinfer_expr(assign_body.value);
+
+                // this has to be done because in the following example:
+                // ```
+                // main::main :: (() ({
+                //     l0 := (5 #0);
+                //     (l0 #1) += ((1 #2) + (2 #3) #4);
+                //     (l0 #5) -= ((2 #6) + (3 #7) #8);
+                //     (l0 #9) *= ((i64 #11).((3 #10)) #12);
+                //     (l0 #13) /= (4 #14);
+                // } #15) #16);
+                // ```
+                // the statement at #9 will try to `replace_weak_tys` on the value and the dest
+                // with u64, and this `replace_weak_tys` call will eventually call `reinfer_usages`
+                // on the statements at #1, and #2, but while the dest's of these statements (#1
+                // and #2) will get replaced with u64 as expected, their values (#4 and #8)
+                // won't because they never get weak type replaced.
+                //
+                // if the value of the assign is the variable itself, this statement will get
+                // queued up again, but by then the variable will have a strong type and
+                // `replace_weak_tys` will stop there.
+                match assign_body
+                    .quick_assign_op
+                    .map(|op| (op, op.get_possible_output_ty(&dest_ty, &value_ty, self.tys)))
+                {
+                    Some((_, Some(output_ty))) => {
+                        let max_ty = output_ty.max_ty.into();
+
+                        self.replace_weak_tys(assign_body.dest, max_ty);
+                        self.replace_weak_tys(assign_body.value, max_ty);
                     }
-                }
-                hir::Stmt::Expr(expr) => {
-                    self.reinfer_expr(expr);
-                }
-                hir::Stmt::Break { value, .. } => {
-                    if let Some(value) = value {
-                        self.reinfer_expr(value);
+                    Some((_, None)) => {}
+                    None => {
+                        if dest_ty.is_weak_replaceable_by(&value_ty) {
+                            self.replace_weak_tys(assign_body.dest, value_ty);
+                        } else if value_ty.can_fit_into(&dest_ty) {
+                            self.replace_weak_tys(assign_body.value, value_ty);
+                        }
                     }
                 }
-                hir::Stmt::Defer { expr, .. } => {
-                    self.reinfer_expr(expr);
+            }
+            hir::Stmt::Expr(expr) => {
+                self.reinfer_expr(expr);
+            }
+            hir::Stmt::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.reinfer_expr(value);
                 }
-                hir::Stmt::Continue { .. } => {}
+            }
+            hir::Stmt::Defer { expr, .. } => {
+                self.reinfer_expr(expr);
+            }
+            hir::Stmt::Continue { .. } => {}
+        }
+    }
+
+    /// literals with a suffix like `42u8` are strongly typed from the start,
+    /// so they never go through `replace_weak_tys`
+    fn suffixed_literal_ty(&mut self, expr: Idx<hir::Expr>) -> Intern<Ty> {
+        let ty: Intern<Ty> = Ty::from_primitive(self.bodies.literal_suffix(expr).unwrap()).into();
+
+        if let (Expr::IntLiteral(num), Some(max_size)) = (&self.bodies[expr], ty.get_max_int_size())
+        {
+            if *num > max_size {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::IntTooBigForType {
+                        found: *num,
+                        written: self.bodies.written_literal(expr).map(str::to_string),
+                        max: max_size,
+                        ty,
+                    },
+                    file: self.file,
+                    expr: Some(expr),
+                    range: self.bodies.range_for_expr(expr),
+                    help: None,
+                });
             }
         }
+
+        ty
     }
 
     /// recursively replaces weakly-typed expressions with strong types.
@@ -279,6 +524,7 @@ impl GlobalInferenceCtx<'_> {
                         self.diagnostics.push(TyDiagnostic {
                             kind: TyDiagnosticKind::IntTooBigForType {
                                 found: num,
+                                written: self.bodies.written_literal(expr).map(str::to_string),
                                 max: max_size,
                                 ty: new_ty,
                             },
@@ -312,6 +558,26 @@ impl GlobalInferenceCtx<'_> {
                 }
                 _ => unreachable!(),
             },
+            Expr::ArrayRepeat { ty: None, item, .. } => match new_ty.as_ref() {
+                Ty::Array { sub_ty, .. } => {
+                    self.replace_weak_tys(item, *sub_ty);
+                }
+                Ty::Slice { sub_ty } => {
+                    if let Some((size, _)) = self.tys[self.file][expr].as_array() {
+                        let new_ty = Ty::Array {
+                            anonymous: false,
+                            size,
+                            sub_ty: *sub_ty,
+                        }
+                        .into();
+
+                        self.tys[self.file].expr_tys.insert(expr, new_ty);
+                    }
+
+                    self.replace_weak_tys(item, *sub_ty);
+                }
+                _ => unreachable!(),
+            },
             Expr::Paren(Some(expr)) => {
                 self.replace_weak_tys(expr, new_ty);
             }
@@ -339,9 +605,7 @@ impl GlobalInferenceCtx<'_> {
                     self.replace_weak_tys(else_branch, new_ty);
                 }
             }
-            Expr::While {
-                condition: None, ..
-            } => {
+            Expr::Loop { .. } => {
                 if let Some(scope_id) = self.bodies.block_to_scope_id(expr) {
                     for usage in self.bodies.scope_id_usages(scope_id) {
                         if let hir::Stmt::Break {
@@ -403,9 +667,31 @@ impl GlobalInferenceCtx<'_> {
                 self.replace_weak_tys(lhs, new_ty);
                 self.replace_weak_tys(rhs, new_ty);
             }
+            Expr::Tuple(items) => {
+                let item_tys = new_ty.as_tuple().unwrap();
+
+                for (item, item_ty) in items.into_iter().zip(item_tys) {
+                    self.replace_weak_tys(item, item_ty);
+                }
+            }
+            Expr::Range { start, end, .. } => {
+                let (_, sub_ty) = new_ty.as_range().unwrap();
+
+                for bound in [start, end].into_iter().flatten() {
+                    self.replace_weak_tys(bound, sub_ty);
+                }
+            }
             Expr::Unary { expr, .. } => {
                 self.replace_weak_tys(expr, new_ty);
             }
+            // math intrinsics give back the same type as their arguments
+            Expr::Directive { name, args }
+                if IntrinsicOperands::of(self.interner.lookup(name.name.0)).is_some() =>
+            {
+                for arg in args {
+                    self.replace_weak_tys(arg, new_ty);
+                }
+            }
             Expr::Local(local_def) => {
                 let local_body = &self.bodies[local_def];
 
@@ -415,22 +701,22 @@ impl GlobalInferenceCtx<'_> {
                     }
                 }
 
-                // now get everything that used this variable and make sure the types are correct for those things
+                // now get everything that used this variable and make sure the types are correct for those things.
+                // the usages are taken out so no nasty recursion takes place
                 let usages = self
                     .local_usages
-                    .get(local_def)
-                    .cloned()
+                    .get_mut(local_def)
+                    .map(mem::take)
                     .unwrap_or_default();
 
-                // now that we have the usages, clear them so no nasty recursion takes place
-                if let Some(usages) = self.local_usages.get_mut(local_def) {
-                    usages.clear();
-                }
-
                 self.reinfer_usages(usages);
 
                 // self.reinfer_expr(self.bodies[local_def].value);
             }
+            // the closure's copy has to be the same type as the original
+            Expr::Captured(capture) => {
+                self.replace_weak_tys(self.bodies[capture].value, new_ty);
+            }
             Expr::StructLiteral { members, .. } => {
                 let member_tys: FxHashMap<hir::Name, Intern<Ty>> = new_ty
                     .as_struct()
@@ -452,2192 +738,4229 @@ impl GlobalInferenceCtx<'_> {
         really_replaced
     }
 
-    fn get_const(&self, expr: Idx<Expr>) -> ExprIsConst {
-        let mut to_check = vec![(self.file, expr)];
+    /// The item of `for i in 0..10` is a local that can't wait for its usages to pick a type,
+    /// so a weak range being iterated over gets its default type right away.
+    ///
+    /// Returns the (possibly new) type of the iterable
+    fn default_weak_range(&mut self, iterable: Idx<Expr>) -> Intern<Ty> {
+        let iterable_ty = self.tys[self.file][iterable];
 
-        let mut idx = 0;
-        while let Some((file, expr)) = to_check.get(idx).copied() {
-            let result = match &self.world_bodies[file][expr] {
-                Expr::Missing
-                | Expr::Lambda(_)
-                | Expr::Import(_)
-                | Expr::PrimitiveTy { .. }
-                | Expr::StructDecl { .. }
-                | Expr::Distinct { .. }
-                | Expr::Comptime(_)
-                | Expr::StringLiteral(_)
-                | Expr::IntLiteral(_)
-                | Expr::FloatLiteral(_)
-                | Expr::BoolLiteral(_) => ExprIsConst::Const,
-                Expr::ArrayLiteral { items, .. } if self.tys[file][expr].is_array() => {
-                    to_check.extend(items.iter().map(|e| (file, *e)));
-                    ExprIsConst::Const
-                }
-                Expr::LocalGlobal(global) => {
-                    let fqn = hir::Fqn {
-                        file,
-                        name: global.name,
-                    };
+        let Some((inclusive, sub_ty)) = iterable_ty.as_range() else {
+            return iterable_ty;
+        };
+        let Some(default) = sub_ty.weak_default() else {
+            return iterable_ty;
+        };
 
-                    if self.world_bodies.is_extern(fqn) {
-                        ExprIsConst::Runtime
-                    } else {
-                        let inferrable = Inferrable::Global(fqn);
-                        if !self.all_inferred.contains(&inferrable) {
-                            // this can only happen if there's been a cyclic error
-                            assert_eq!(*self.tys[fqn].0, Ty::NotYetResolved);
-                            return ExprIsConst::Unknown;
-                        }
+        let new_ty = Ty::Range {
+            inclusive,
+            sub_ty: default,
+        }
+        .into();
+        self.replace_weak_tys(iterable, new_ty);
 
-                        to_check.push((file, self.world_bodies.body(fqn)));
-                        ExprIsConst::Const
-                    }
-                }
-                Expr::Local(local) => {
-                    let local_def = &self.world_bodies[file][*local];
+        new_ty
+    }
 
-                    if let Some(value) = local_def.value {
-                        to_check.push((file, value));
-                    }
+    /// Returns the global declared as `member` inside of the body of the given struct type
+    fn namespaced_global(&self, struct_ty: Intern<Ty>, member: hir::Name) -> Option<hir::Fqn> {
+        let Ty::Struct { fqn: Some(fqn), .. } = struct_ty.as_ref() else {
+            return None;
+        };
 
-                    if local_def.mutable {
-                        ExprIsConst::Runtime
-                    } else if local_def.value.is_none() {
-                        // this protects against cases like `x ::;`
-                        ExprIsConst::Unknown
-                    } else {
-                        ExprIsConst::Const
-                    }
-                }
-                Expr::Member {
-                    previous,
-                    name: field,
-                } => {
-                    let old_file = file;
+        fqn.namespaced(member, self.interner).filter(|fqn| {
+            matches!(
+                self.world_index.definition(*fqn),
+                hir::DefinitionStatus::Defined
+            )
+        })
+    }
 
-                    if let Ty::File(file) = self.tys[old_file][*previous].as_ref() {
-                        to_check.push((old_file, *previous));
+    /// The type of a lambda, going by its parameters and return type.
+    ///
+    /// If `fill_params` is set, each parameter is added to `param_tys` once its type is known,
+    /// so that the types of the parameters after it can refer to it
+    fn lambda_signature(
+        &mut self,
+        expr: Idx<Expr>,
+        lambda: Idx<hir::Lambda>,
+        fill_params: bool,
+    ) -> InferResult<Intern<Ty>> {
+        let hir::Lambda {
+            params, return_ty, ..
+        } = &self.bodies[lambda];
 
-                        let fqn = hir::Fqn {
-                            file: *file,
-                            name: field.name,
-                        };
+        let mut param_tys = Vec::with_capacity(params.len());
 
-                        if !self.world_bodies.exists(fqn) {
-                            ExprIsConst::Unknown
-                        } else if self.world_bodies.is_extern(fqn) {
-                            ExprIsConst::Runtime
-                        } else {
-                            let inferrable = Inferrable::Global(fqn);
-                            if !self.all_inferred.contains(&inferrable) {
-                                // this can only happen if there's been a cyclic error
-                                assert_eq!(*self.tys[fqn].0, Ty::NotYetResolved);
-                                return ExprIsConst::Unknown;
-                            }
+        for (idx, param) in params.iter().enumerate() {
+            let mut ty = self.const_ty(param.ty)?;
 
-                            to_check.push((*file, self.world_bodies.body(fqn)));
-                            ExprIsConst::Const
-                        }
-                    } else {
-                        ExprIsConst::Runtime
-                    }
-                }
-                _ => {
-                    if matches!(*(self.tys[file][expr]), Ty::Type | Ty::File(_)) {
-                        ExprIsConst::Const
-                    } else {
-                        ExprIsConst::Runtime
-                    }
+            let mut impossible_to_differentiate = false;
+
+            if let Some(last_param) = idx
+                .checked_sub(1)
+                .and_then(|idx| params.get(idx))
+                .filter(|p| p.varargs)
+            {
+                // we already called `const_ty` on the last param
+                let last_ty = self.tys[self.file].meta_tys[last_param.ty];
+
+                if !ty.can_differentiate(&last_ty) {
+                    impossible_to_differentiate = true;
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::ImpossibleToDifferentiateVarArgs {
+                            previous_ty: last_ty,
+                            current_ty: ty,
+                        },
+                        file: self.file,
+                        expr: Some(expr),
+                        range: param.range,
+                        help: None,
+                    });
                 }
-            };
+            }
 
-            if result == ExprIsConst::Runtime || result == ExprIsConst::Unknown {
-                return result;
+            if param.varargs {
+                ty = Ty::Slice { sub_ty: ty }.into();
             }
 
-            idx += 1;
+            let param_ty = ParamTy {
+                ty,
+                varargs: param.varargs,
+                impossible_to_differentiate,
+            };
+
+            if fill_params {
+                self.param_tys.push(param_ty);
+            }
+            param_tys.push(param_ty);
         }
 
-        ExprIsConst::Const
+        let return_ty = if let Some(return_ty) = return_ty {
+            self.const_ty(*return_ty)?
+        } else {
+            Ty::Void.into()
+        };
+
+        Ok(Ty::Function {
+            param_tys,
+            return_ty,
+        }
+        .into())
     }
 
-    /// `deref` allows certain expressions to be mutable
-    /// only if they are being mutated through a deref
-    fn get_mutability(&self, expr: Idx<Expr>, assignment: bool, deref: bool) -> ExprMutability {
-        match &self.bodies[expr] {
-            Expr::Missing => ExprMutability::Mutable,
-            Expr::ArrayLiteral { .. } => ExprMutability::Mutable,
-            Expr::StructLiteral { .. } => ExprMutability::Mutable,
-            Expr::Ref { mutable, .. } => match (*mutable, deref) {
-                (true, _) => ExprMutability::Mutable,
-                // (true, false) => ExprMutability::NotMutatingRefThroughDeref(
-                //     self.bodies.range_for_expr(expr),
-                // ),
-                _ => ExprMutability::ImmutableRef(self.bodies.range_for_expr(expr)),
-            },
-            Expr::Deref { pointer } => self.get_mutability(*pointer, assignment, true),
-            Expr::Index { source: array, .. } => self.get_mutability(
-                *array,
-                assignment,
-                deref || self.tys[self.file][*array].is_pointer(),
-            ),
-            Expr::Block {
-                tail_expr: Some(tail_expr),
-                ..
-            } => self.get_mutability(*tail_expr, assignment, deref),
-            Expr::Local(local_def) if deref => {
-                let local_def = &self.bodies[*local_def];
+    /// Reports anything that stops a function with compile-time parameters from being
+    /// checked and compiled once for each set of values it gets called with
+    fn check_const_param_fn(&mut self, expr: Idx<Expr>, lambda: Idx<hir::Lambda>) {
+        let hir::Lambda {
+            params,
+            params_range,
+            ..
+        } = &self.bodies[lambda];
+
+        // instances are only made for calls to globals
+        if !matches!(
+            self.currently_inferring,
+            Inferrable::Global(fqn) if self.world_bodies.body(fqn) == expr
+        ) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::LocalConstParamFn,
+                file: self.file,
+                expr: Some(expr),
+                range: *params_range,
+                help: None,
+            });
+            return;
+        }
 
-                if let Some(value) = local_def.value {
-                    self.get_mutability(value, false, deref)
-                } else {
-                    // todo: does this make sense?
-                    ExprMutability::Mutable
-                }
-            }
-            Expr::Local(local_def) if !deref => {
-                let local_def = &self.bodies[*local_def];
+        for param in params.iter().filter(|param| param.is_const) {
+            let ty = self.tys[self.file].meta_tys[param.ty];
 
-                if local_def.mutable {
-                    ExprMutability::Mutable
-                } else {
-                    ExprMutability::ImmutableBinding(local_def.range)
-                }
+            if ty != *ty::USIZE && !ty.is_error_or_unknown() {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::ConstParamNotUsize { found: ty },
+                    file: self.file,
+                    expr: Some(param.ty),
+                    range: self.bodies.range_for_expr(param.ty),
+                    help: None,
+                });
             }
-            Expr::Param { idx, range } => {
-                let param_ty = self.param_tys[*idx as usize];
+        }
 
-                match param_ty.ty.as_pointer() {
-                    Some((mutable, _)) if deref => {
-                        if mutable {
-                            ExprMutability::Mutable
-                        } else {
-                            // todo: change this to be the range of the param's type
-                            ExprMutability::ImmutableRef(*range)
-                        }
-                    }
-                    Some((mutable, _)) if assignment => {
-                        if mutable {
-                            ExprMutability::NotMutatingRefThroughDeref(
-                                self.bodies.range_for_expr(expr),
-                            )
-                        } else {
-                            ExprMutability::ImmutableRef(*range)
-                        }
+        for desc in self.bodies.descendants(
+            expr,
+            hir::DescentOpts::All {
+                include_lambdas: true,
+            },
+        ) {
+            let Descendant::Expr(desc) = desc else {
+                continue;
+            };
+
+            let kind = match &self.bodies[desc] {
+                Expr::Comptime(_) => TyDiagnosticKind::ComptimeInConstParamFn,
+                Expr::Lambda(inner) if desc != expr => {
+                    let hir::Lambda {
+                        return_ty,
+                        body,
+                        is_extern,
+                        ..
+                    } = &self.bodies[*inner];
+
+                    // function types are fine
+                    if !is_extern && return_ty.is_some() && self.bodies[*body] == Expr::Missing {
+                        continue;
                     }
-                    _ => ExprMutability::ImmutableParam(*range, assignment),
+
+                    TyDiagnosticKind::LambdaInConstParamFn
                 }
-            }
-            Expr::LocalGlobal(name) => {
-                let fqn = hir::Fqn {
-                    file: self.file,
-                    name: name.name,
-                };
+                _ => continue,
+            };
 
-                ExprMutability::ImmutableGlobal(self.world_index.range_info(fqn).whole)
-            }
-            Expr::Member {
-                previous,
-                name: field,
-            } => {
-                let previous_ty = self.tys[self.file][*previous];
-                match previous_ty.as_ref() {
-                    Ty::File(file) => {
-                        let fqn = hir::Fqn {
-                            file: *file,
-                            name: field.name,
-                        };
+            self.diagnostics.push(TyDiagnostic {
+                kind,
+                file: self.file,
+                expr: Some(desc),
+                range: self.bodies.range_for_expr(desc),
+                help: None,
+            });
+        }
+    }
 
-                        if *file == self.file {
-                            ExprMutability::ImmutableGlobal(self.world_index.range_info(fqn).whole)
-                        } else {
-                            ExprMutability::ImmutableGlobal(field.range)
-                        }
-                    }
-                    _ if deref => {
-                        let path_ty = &self.tys[self.file][expr];
+    /// Returns the signature of a global that's referenced by `expr`
+    /// Warns about any use of a global that was marked with `@deprecated`
+    fn check_deprecated(&mut self, fqn: hir::Fqn, expr: Idx<Expr>, range: TextRange) {
+        if let Some(message) = self.world_index.deprecated(fqn) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::Deprecated { fqn, message },
+                file: self.file,
+                expr: Some(expr),
+                range,
+                help: None,
+            });
+        }
+    }
 
-                        if path_ty
-                            .as_pointer()
-                            .map(|(mutable, _)| mutable)
-                            .unwrap_or(true)
-                        {
-                            ExprMutability::Mutable
-                        } else {
-                            // todo: use the actual range of the struct literal, not the range of this field name
-                            ExprMutability::ImmutableRef(field.range)
-                        }
-                    }
-                    _ => self.get_mutability(
-                        *previous,
-                        assignment,
-                        deref || previous_ty.is_pointer(),
-                    ),
-                }
-            }
-            Expr::Call { .. } if deref => ExprMutability::Mutable,
-            Expr::Cast { .. } if deref => {
-                let ty = self.tys[self.file][expr];
+    /// Picks which of the functions sharing a name gets called, going by the number of
+    /// arguments and their types. Gives back the signature of the overload that was picked,
+    /// or `None` if the callee isn't overloaded
+    fn resolve_overload(
+        &mut self,
+        call: Idx<Expr>,
+        callee: Idx<Expr>,
+        args: &[Idx<Expr>],
+    ) -> InferResult<Option<Intern<Ty>>> {
+        let fqn = match &self.bodies[callee] {
+            Expr::LocalGlobal(name) => self.world_bodies.resolve_global(self.file, name.name),
+            Expr::Member { previous, name } => match self.tys[self.file][*previous].as_ref() {
+                Ty::File(file) => hir::Fqn {
+                    file: *file,
+                    name: name.name,
+                },
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        let Some(overloads) = self.world_index.overloads(fqn) else {
+            return Ok(None);
+        };
 
-                match ty.as_pointer() {
-                    Some((mutable, _)) if deref => {
-                        if mutable {
-                            ExprMutability::Mutable
-                        } else {
-                            // todo: change this to be the range of the param's type
-                            ExprMutability::ImmutableRef(self.bodies.range_for_expr(expr))
-                        }
-                    }
-                    Some((mutable, _)) if assignment => {
-                        if mutable {
-                            ExprMutability::NotMutatingRefThroughDeref(
-                                self.bodies.range_for_expr(expr),
-                            )
-                        } else {
-                            ExprMutability::ImmutableRef(self.bodies.range_for_expr(expr))
-                        }
-                    }
-                    _ => ExprMutability::CannotMutate(self.bodies.range_for_expr(expr)),
-                }
+        let arg_tys = args
+            .iter()
+            .map(|arg| self.tys[self.file][*arg])
+            .collect::<Vec<_>>();
+
+        let mut candidates = Vec::new();
+        for name in overloads {
+            let overload = hir::Fqn {
+                file: fqn.file,
+                name: *name,
+            };
+            let sig = self
+                .tys
+                .signatures
+                .get(&overload)
+                .ok_or_else(|| vec![Inferrable::Global(overload)])?
+                .0;
+
+            if sig
+                .as_function()
+                .is_some_and(|(params, _)| args_fit_params(&arg_tys, &params))
+            {
+                candidates.push((overload, sig));
             }
-            Expr::Paren(Some(expr)) => self.get_mutability(*expr, assignment, deref),
-            _ => ExprMutability::CannotMutate(self.bodies.range_for_expr(expr)),
         }
-    }
 
-    fn find_usages(&mut self, exprs: &[Idx<hir::Expr>], local_usage: Idx<hir::Stmt>) {
-        let mut locals = HashSet::default();
-        for expr in exprs {
-            self.get_referenced_locals(*expr, &mut locals);
+        // weak types like `5` can fit into more than one overload,
+        // so the one taking the types they would've defaulted to wins
+        if candidates.len() > 1 {
+            let exact = candidates
+                .iter()
+                .filter(|(_, sig)| {
+                    let (params, _) = sig.as_function().unwrap();
+                    params.len() == arg_tys.len()
+                        && arg_tys.iter().zip(params.iter()).all(|(arg_ty, param)| {
+                            arg_ty.weak_default().unwrap_or(*arg_ty) == param.ty
+                        })
+                })
+                .copied()
+                .collect::<Vec<_>>();
+
+            if exact.len() == 1 {
+                candidates = exact;
+            }
         }
 
-        for local in locals {
-            if let Some(usages) = self.local_usages.get_mut(local) {
-                usages.insert(local_usage);
-            } else {
-                let mut usages = FxHashSet::default();
-                usages.insert(local_usage);
+        match candidates.as_slice() {
+            [(overload, sig)] => {
+                self.tys[self.file].overloads.insert(callee, *overload);
+                self.tys[self.file][callee] = *sig;
+                self.check_deprecated(*overload, callee, self.bodies.range_for_expr(callee));
 
-                self.local_usages.insert(local, usages);
+                return Ok(Some(*sig));
             }
+            // an argument with an error fits into everything, so that error is enough
+            _ if arg_tys.iter().any(|arg_ty| arg_ty.is_error_or_unknown()) => {}
+            [] => self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::NoMatchingOverload {
+                    function: fqn,
+                    found: arg_tys,
+                },
+                file: self.file,
+                expr: Some(call),
+                range: self.bodies.range_for_expr(call),
+                help: None,
+            }),
+            _ => self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::AmbiguousOverload {
+                    function: fqn,
+                    candidates: candidates.iter().map(|(_, sig)| *sig).collect(),
+                },
+                file: self.file,
+                expr: Some(call),
+                range: self.bodies.range_for_expr(call),
+                help: None,
+            }),
         }
+
+        Ok(Some(Ty::Error.into()))
     }
 
-    fn get_referenced_locals(&self, expr: Idx<hir::Expr>, locals: &mut FxHashSet<Idx<LocalDef>>) {
-        locals.extend(
-            self.bodies
-                .descendants(expr, hir::DescentOpts::Reinfer)
-                .filter_map(|desc| match desc {
-                    Descendant::Expr(expr) => match self.bodies[expr] {
-                        Expr::Local(local) => Some(local),
-                        _ => None,
-                    },
-                    Descendant::Stmt(_) => None,
+    /// The global that `expr` names, like `foo`, `file.foo`, `Point.new` or `p.len`
+    fn referenced_global(&self, expr: Idx<Expr>) -> Option<hir::Fqn> {
+        match &self.bodies[expr] {
+            Expr::LocalGlobal(name) => Some(self.world_bodies.resolve_global(self.file, name.name)),
+            Expr::Member { previous, name } => match self.tys[self.file][*previous].as_ref() {
+                Ty::File(file) => Some(hir::Fqn {
+                    file: *file,
+                    name: name.name,
                 }),
-        );
+                _ => self.tys[self.file].get_namespaced_member(expr),
+            },
+            _ => None,
+        }
     }
 
-    fn reinfer_expr(&mut self, expr: Idx<hir::Expr>) -> Intern<Ty> {
-        let previous_ty = self.tys[self.file][expr];
-        if *previous_ty == Ty::Unknown {
-            return previous_ty;
+    /// Functions with compile-time parameters are checked again for every set of values they're
+    /// called with. Gives back the signature of the instance that gets called, or `None` if the
+    /// callee doesn't have compile-time parameters
+    fn resolve_instance(
+        &mut self,
+        callee: Idx<Expr>,
+        args: &[Idx<Expr>],
+    ) -> InferResult<Option<Intern<Ty>>> {
+        // `p.len()` shifts the arguments along by one
+        if let Expr::Member { previous, .. } = self.bodies[callee] {
+            if !matches!(
+                self.tys[self.file][previous].as_ref(),
+                Ty::File(_) | Ty::Type
+            ) {
+                return Ok(None);
+            }
         }
 
-        fn all_usages_ty(ctx: &mut GlobalInferenceCtx, label_id: ScopeId) -> Intern<Ty> {
-            let usages = ctx.bodies.scope_id_usages(label_id);
+        let Some(fqn) = self.referenced_global(callee) else {
+            return Ok(None);
+        };
+        let Some(fql) = const_param_lambda(self.world_bodies, fqn) else {
+            return Ok(None);
+        };
 
-            let mut max_ty: Option<Intern<Ty>> = None;
-            for usage in usages.iter() {
-                let ty = match ctx.bodies[*usage] {
-                    hir::Stmt::Break {
-                        value: Some(value), ..
-                    } => ctx.tys[ctx.file][value],
-                    hir::Stmt::Break { value: None, .. } => Ty::Void.into(),
-                    _ => continue,
-                };
+        let params = &self.world_bodies[fql.file][fql.lambda].params;
+        let const_params = params.iter().filter(|param| param.is_const).count();
 
-                if let Some(max) = max_ty {
-                    max_ty = max.max(&ty).map(|ty| ty.into());
-                } else {
-                    max_ty = Some(ty);
+        let mut consts = Vec::with_capacity(const_params);
+        for (param, arg) in params.iter().zip(args) {
+            if !param.is_const {
+                continue;
+            }
+
+            if !self.expect_match(self.tys[self.file][*arg], *ty::USIZE, *arg) {
+                self.tys[self.file][callee] = Ty::Error.into();
+                return Ok(Some(Ty::Error.into()));
+            }
+            self.replace_weak_tys(*arg, *ty::USIZE);
+
+            let arg_const = self.get_const(*arg);
+            if !arg_const.is_const() {
+                if arg_const.should_report_not_const() {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::ConstArgNotConst,
+                        file: self.file,
+                        expr: Some(*arg),
+                        range: self.bodies.range_for_expr(*arg),
+                        help: None,
+                    });
                 }
+                self.tys[self.file][callee] = Ty::Error.into();
+                return Ok(Some(Ty::Error.into()));
             }
 
-            max_ty.unwrap_or_else(|| Ty::Void.into())
+            match self.const_data(self.file, *arg)? {
+                Some(ComptimeResult::Integer { num, .. }) => consts.push(num),
+                _ => {
+                    self.tys[self.file][callee] = Ty::Error.into();
+                    return Ok(Some(Ty::Error.into()));
+                }
+            }
         }
 
-        for next in self
-            .bodies
-            .descendants(expr, hir::DescentOpts::Infer)
-            .collect_vec()
-            .into_iter()
-            .rev()
-        {
-            match next {
-                Descendant::Expr(expr) => {
-                    let previous_ty = self.tys[self.file][expr];
+        // the missing arguments get reported against the signature as usual
+        if consts.len() != const_params {
+            return Ok(None);
+        }
 
-                    if *previous_ty == Ty::Unknown || *previous_ty == Ty::NoEval {
-                        continue;
-                    }
+        let id = self.tys.get_or_add_instance(fqn, fql, consts);
+        let sig = self
+            .tys
+            .instance(id)
+            .ty
+            .ok_or_else(|| vec![Inferrable::Instance(fql, id)])?;
 
-                    let new_ty = match &self.bodies[expr] {
-                        Expr::IntLiteral(num) => match *previous_ty {
-                            Ty::IInt(0) if *num > i32::MAX as u64 => Ty::IInt(64).into(),
-                            Ty::UInt(0) if *num > u32::MAX as u64 => Ty::UInt(64).into(),
-                            _ => continue,
-                        },
-                        Expr::Ref {
-                            mutable,
-                            expr: inner,
-                        } => {
-                            let inner_ty = self.tys[self.file][*inner];
+        self.tys[self.file].instances.insert(callee, id);
+        self.tys[self.file][callee] = sig;
 
-                            if *inner_ty == Ty::Type {
-                                inner_ty
-                            } else {
-                                Ty::Pointer {
-                                    mutable: *mutable,
-                                    sub_ty: inner_ty,
-                                }
-                                .into()
-                            }
-                        }
-                        Expr::Deref { pointer } => {
-                            let inner_ty = self.tys[self.file][*pointer];
+        Ok(Some(sig))
+    }
 
-                            inner_ty
-                                .as_pointer()
-                                .map(|(_, sub_ty)| sub_ty)
-                                .unwrap_or_else(|| Ty::Unknown.into())
-                        }
-                        Expr::Binary { lhs, rhs, op } => {
-                            let lhs_ty = self.tys[self.file][*lhs];
-                            let rhs_ty = self.tys[self.file][*rhs];
+    /// `a + b` calls a function named `__add` when either side is a struct or distinct type
+    /// that declares one, either within the struct's body or next to the type itself.
+    /// Gives back the return type of that function, or `None` if the operator isn't overloaded
+    fn resolve_operator(
+        &mut self,
+        binary: Idx<Expr>,
+        op: hir::BinaryOp,
+        lhs: Idx<Expr>,
+        rhs: Idx<Expr>,
+    ) -> InferResult<Option<Intern<Ty>>> {
+        let Some(overload_name) = op.overload_name() else {
+            return Ok(None);
+        };
 
-                            if let Some(output_ty) = op.get_possible_output_ty(&lhs_ty, &rhs_ty) {
-                                let max_ty = output_ty.max_ty.into();
-                                self.replace_weak_tys(*lhs, max_ty);
-                                self.replace_weak_tys(*rhs, max_ty);
+        let arg_tys = [self.tys[self.file][lhs], self.tys[self.file][rhs]];
 
-                                output_ty.final_output_ty.into()
-                            } else {
-                                op.default_ty().into()
-                            }
-                        }
-                        Expr::Unary { expr: inner, op } => {
-                            let inner_ty = self.tys[self.file][*inner];
-                            if op.can_perform(&inner_ty) {
-                                op.get_possible_output_ty(inner_ty)
-                            } else {
-                                op.default_ty().into()
-                            }
-                        }
-                        Expr::Index { source, .. } => {
-                            let mut source_ty = self.tys[self.file][*source];
+        let mut names = Vec::new();
+        for ty in arg_tys {
+            let (Ty::Struct { fqn: Some(fqn), .. } | Ty::Distinct { fqn: Some(fqn), .. }) =
+                ty.as_ref()
+            else {
+                continue;
+            };
 
-                            while let Some(ptr) = source_ty.as_pointer() {
-                                source_ty = ptr.1;
-                            }
+            let within_ty = format!("{}.{overload_name}", self.interner.lookup(fqn.name.0));
 
-                            source_ty
-                                .as_array()
-                                .map(|(_, sub_ty)| sub_ty)
-                                .or_else(|| source_ty.as_slice())
-                                .unwrap_or_else(|| Ty::Unknown.into())
-                        }
-                        Expr::Block { tail_expr, .. } => {
-                            let tail_ty = tail_expr.map(|tail_expr| self.tys[self.file][tail_expr]);
+            for name in [within_ty.as_str(), overload_name] {
+                // if the name was never interned, it was never declared
+                let Some(name) = self.interner.get(name) else {
+                    continue;
+                };
+                let name = hir::Fqn {
+                    file: fqn.file,
+                    name: hir::Name(name),
+                };
 
-                            if let Some(label_id) = self.bodies.block_to_scope_id(expr) {
-                                let usages_ty = all_usages_ty(self, label_id);
+                if !matches!(
+                    self.world_index.definition(name),
+                    hir::DefinitionStatus::Defined
+                ) || names.contains(&name)
+                {
+                    continue;
+                }
+                names.push(name);
+            }
+        }
+        let Some(&function) = names.first() else {
+            return Ok(None);
+        };
 
-                                if let Some(new_tail) = tail_ty {
-                                    usages_ty.max(&new_tail).unwrap_or(Ty::Unknown).into()
-                                } else {
-                                    usages_ty
-                                }
-                            } else {
-                                tail_ty.unwrap_or_else(|| Ty::Void.into())
-                            }
-                        }
-                        Expr::If {
-                            body, else_branch, ..
-                        } => {
-                            let body_ty = self.tys[self.file][*body];
+        let mut candidates = Vec::new();
+        for name in names {
+            let overloads = match self.world_index.overloads(name) {
+                Some(overloads) => overloads.to_vec(),
+                None => vec![name.name],
+            };
 
-                            if let Some(else_branch) = else_branch {
-                                let new_else = self.tys[self.file][*else_branch];
+            for overload in overloads {
+                let overload = hir::Fqn {
+                    file: name.file,
+                    name: overload,
+                };
+                let sig = self
+                    .tys
+                    .signatures
+                    .get(&overload)
+                    .ok_or_else(|| vec![Inferrable::Global(overload)])?
+                    .0;
 
-                                body_ty.max(&new_else).unwrap_or(Ty::Unknown).into()
-                            } else if *body_ty == Ty::NoEval {
-                                Ty::Void.into()
-                            } else {
-                                body_ty
-                            }
-                        }
-                        Expr::While { condition, .. } => {
-                            if condition.is_some() {
-                                Ty::Void.into()
-                            } else if let Some(label_id) = self.bodies.block_to_scope_id(expr) {
-                                all_usages_ty(self, label_id)
-                            } else {
-                                Ty::Void.into()
-                            }
-                        }
-                        Expr::Local(local) => self.tys[self.file].local_tys[*local],
-                        _ => {
-                            continue;
-                        }
-                    };
+                if sig.as_function().is_some_and(|(params, _)| {
+                    params.len() == 2
+                        && params.iter().all(|param| !param.varargs)
+                        && args_fit_params(&arg_tys, &params)
+                }) {
+                    candidates.push((overload, sig));
+                }
+            }
+        }
 
-                    let loss_of_distinct = matches!(previous_ty.as_ref(), Ty::Distinct { .. })
-                        && new_ty.is_functionally_equivalent_to(&previous_ty, false);
-                    let array_to_slice = matches!(
-                        (previous_ty.as_ref(), new_ty.as_ref()),
-                        (
-                            Ty::Slice {
-                                sub_ty: previous_sub_ty
-                            },
-                            Ty::Array {
-                                sub_ty: new_sub_ty,
-                                ..
-                            }
-                        ) if previous_sub_ty.is_weak_replaceable_by(new_sub_ty)
-                            || previous_sub_ty.is_equal_to(new_sub_ty)
-                    );
-                    // this might happen in the following case:
-                    // ```
-                    // x : u64 = 1;
-                    // y : i64 = 2;
-                    //
-                    // z : i64 = x + y;
-                    // ```
-                    // 1. since `u64 + i64` is invalid, the output of `x + y` is {uint} (the
-                    //    default type of addition).
-                    // 2. since the value of z is {uint}, it gets weak type replaced by {i64}.
-                    // 3. reinfer_expr doesn't know about the weak type replacement, so it
-                    //    attempts to panic here.
-                    let strong_int_to_weak_int = matches!((previous_ty.as_ref(), new_ty.as_ref()), (Ty::UInt(strong_bit_width) | Ty::IInt(strong_bit_width), Ty::UInt(0) | Ty::IInt(0)) if *strong_bit_width != 0);
-
-                    if previous_ty != new_ty
-                        && !(previous_ty.is_weak_replaceable_by(&new_ty)
-                            || loss_of_distinct
-                            || array_to_slice
-                            || strong_int_to_weak_int)
-                    {
-                        panic!(
-                            "{} #{} : {:?} is not weak replaceable by {:?}",
-                            self.file.debug(self.interner),
-                            expr.into_raw(),
-                            previous_ty,
-                            new_ty
-                        );
-                    }
+        if candidates.len() > 1 {
+            let exact = candidates
+                .iter()
+                .filter(|(_, sig)| {
+                    let (params, _) = sig.as_function().unwrap();
+                    arg_tys
+                        .iter()
+                        .zip(params.iter())
+                        .all(|(arg_ty, param)| arg_ty.weak_default().unwrap_or(*arg_ty) == param.ty)
+                })
+                .copied()
+                .collect::<Vec<_>>();
 
-                    if !loss_of_distinct && !array_to_slice && !strong_int_to_weak_int {
-                        self.tys[self.file].expr_tys.insert(expr, new_ty);
-                    }
+            if exact.len() == 1 {
+                candidates = exact;
+            }
+        }
+
+        match candidates.as_slice() {
+            // the built-in operator might still work, e.g. `==` on two distinct integers
+            [] => Ok(None),
+            [(overload, sig)] => {
+                let (params, return_ty) = sig.as_function().unwrap();
+                for ((arg, arg_ty), param) in [lhs, rhs].into_iter().zip(arg_tys).zip(params) {
+                    self.expect_match(arg_ty, param.ty, arg);
+                    self.replace_weak_tys(arg, param.ty);
                 }
-                Descendant::Stmt(stmt) => match &self.bodies[stmt] {
-                    Stmt::Expr(_) => {}
-                    Stmt::LocalDef(local_def) => {
-                        let def_body = &self.bodies[*local_def];
 
-                        if def_body.ty.is_some() {
-                            // if there's a type annotation, then even if the value changed
-                            // types, the local will always have the type of it's type annotation
-                            continue;
-                        }
+                self.tys[self.file].operators.insert(binary, *overload);
+                self.check_deprecated(*overload, binary, self.bodies.range_for_expr(binary));
 
-                        let Some(value) = def_body.value else {
-                            continue;
-                        };
+                Ok(Some(return_ty))
+            }
+            _ => {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::AmbiguousOverload {
+                        function,
+                        candidates: candidates.iter().map(|(_, sig)| *sig).collect(),
+                    },
+                    file: self.file,
+                    expr: Some(binary),
+                    range: self.bodies.range_for_expr(binary),
+                    help: None,
+                });
 
-                        let previous_ty = self.tys[self.file][*local_def];
-                        let new_ty = self.tys[self.file][value];
+                Ok(Some(Ty::Error.into()))
+            }
+        }
+    }
 
-                        let loss_of_distinct = matches!(previous_ty.as_ref(), Ty::Distinct { .. })
-                            && new_ty.is_functionally_equivalent_to(&previous_ty, false);
-                        let array_to_slice = matches!(
-                            (previous_ty.as_ref(), new_ty.as_ref()),
-                            (
-                                Ty::Slice {
-                                    sub_ty: previous_sub_ty
-                                },
-                                Ty::Array {
-                                    sub_ty: new_sub_ty,
-                                    ..
-                                }
-                            ) if previous_sub_ty.is_weak_replaceable_by(new_sub_ty)
-                                || previous_sub_ty.is_equal_to(new_sub_ty)
-                        );
-                        // this might happen in the following case:
-                        // ```
-                        // x : u64 = 1;
-                        // y : i64 = 2;
-                        //
-                        // z : i64 = x + y;
-                        // ```
-                        // 1. since `u64 + i64` is invalid, the output of `x + y` is {uint} (the
-                        //    default type of addition).
-                        // 2. since the value of z is {uint}, it gets weak type replaced by {i64}.
-                        // 3. reinfer_expr doesn't know about the weak type replacement, so it
-                        //    attempts to panic here.
-                        let strong_int_to_weak_int = matches!((previous_ty.as_ref(), new_ty.as_ref()), (Ty::UInt(strong_bit_width) | Ty::IInt(strong_bit_width), Ty::UInt(0) | Ty::IInt(0)) if *strong_bit_width != 0);
-
-                        if previous_ty != new_ty
-                            && !(previous_ty.is_weak_replaceable_by(&new_ty)
-                                || loss_of_distinct
-                                || array_to_slice
-                                || strong_int_to_weak_int)
-                        {
-                            panic!(
-                                "{} #{} : {:?} is not weak replaceable by {:?}",
-                                self.file.debug(self.interner),
-                                expr.into_raw(),
-                                previous_ty,
-                                new_ty
-                            );
-                        }
+    /// `value.method` refers to a free function named `method` which takes `value` as its first
+    /// argument, but only when `value` doesn't already have a member with that name.
+    /// The function can either be visible from the current file or be in the file that
+    /// declares the type of `value`
+    fn free_method(
+        &mut self,
+        member: Idx<Expr>,
+        receiver_ty: Intern<Ty>,
+        name: hir::Name,
+    ) -> InferResult<Option<Intern<Ty>>> {
+        if receiver_ty.is_error_or_unknown() {
+            return Ok(None);
+        }
 
-                        if !loss_of_distinct && !array_to_slice && !strong_int_to_weak_int {
-                            self.tys[self.file].local_tys.insert(*local_def, new_ty);
-                        }
-                    }
-                    Stmt::Assign(_) => {}
-                    Stmt::Break { .. } => {}
-                    Stmt::Continue { .. } => {}
-                    Stmt::Defer { .. } => {}
-                },
+        let mut candidates = Vec::new();
+        for place in self.free_method_places(receiver_ty, name) {
+            for overload in self.function_overloads(place) {
+                let sig = self
+                    .tys
+                    .signatures
+                    .get(&overload)
+                    .ok_or_else(|| vec![Inferrable::Global(overload)])?
+                    .0;
+
+                let takes_receiver = sig.as_function().is_some_and(|(params, _)| {
+                    params
+                        .first()
+                        .is_some_and(|param| receiver_ty.can_fit_into(&param.ty))
+                });
+
+                if takes_receiver && !candidates.contains(&(overload, sig)) {
+                    candidates.push((overload, sig));
+                }
             }
         }
 
-        self.tys[self.file][expr]
-    }
+        if candidates.len() > 1 {
+            let exact = candidates
+                .iter()
+                .filter(|(_, sig)| sig.as_function().unwrap().0[0].ty == receiver_ty)
+                .copied()
+                .collect::<Vec<_>>();
 
-    // This function is indent hell but it's worth it to make it stack overflow free
-    pub(crate) fn infer_expr(&mut self, expr: Idx<Expr>) -> InferResult<Intern<Ty>> {
-        if let (Some(ty), None) = (
-            self.tys[self.file].expr_tys.get(expr),
-            self.bodies.block_to_scope_id(expr),
-        ) {
-            return Ok(*ty);
+            if exact.len() == 1 {
+                candidates = exact;
+            }
         }
 
-        let descendants = self
-            .bodies
-            .descendants(expr, hir::DescentOpts::Infer)
-            .collect_vec();
+        match candidates.as_slice() {
+            [] => Ok(None),
+            [(fqn, _)] => {
+                self.tys[self.file].namespaced_members.insert(member, *fqn);
+                self.check_deprecated(*fqn, member, self.bodies.range_for_expr(member));
 
-        // println!("INFER EXPR\n{descendants:#?}");
+                self.global_signature(*fqn, member).map(Some)
+            }
+            _ => {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::AmbiguousMethod {
+                        method: name.0,
+                        candidates: candidates.iter().map(|(fqn, _)| *fqn).collect(),
+                    },
+                    file: self.file,
+                    expr: Some(member),
+                    range: self.bodies.range_for_expr(member),
+                    help: None,
+                });
 
-        // This all works because children will ALWAYS come before parents
-        for descendant in descendants.into_iter().rev() {
-            match descendant {
-                Descendant::Expr(expr) => {
-                    if self.tys[self.file].expr_tys.contains_idx(expr)
-                        && self.bodies.block_to_scope_id(expr).is_none()
-                    {
-                        continue;
-                    }
+                Ok(Some(Ty::Error.into()))
+            }
+        }
+    }
 
-                    let ty = match &self.bodies[expr] {
-                        Expr::Missing => Ty::Unknown.into(),
-                        Expr::IntLiteral(_) => Ty::UInt(0).into(),
-                        Expr::FloatLiteral(_) => Ty::Float(0).into(),
-                        Expr::BoolLiteral(_) => Ty::Bool.into(),
-                        Expr::StringLiteral(_) => Ty::String.into(),
-                        Expr::CharLiteral(_) => Ty::Char.into(),
-                        Expr::ArrayDecl { .. } => {
-                            self.const_ty(expr)?;
-                            Ty::Type.into()
-                        }
-                        Expr::ArrayLiteral {
-                            ty: Some(ty),
-                            items,
-                        } => {
-                            let sub_ty = self.const_ty(*ty)?;
-                            for item in items {
-                                let item_ty = self.tys[self.file][*item];
-                                self.expect_match(item_ty, sub_ty, *item);
-                            }
+    /// Where `value.method` looks for a free function named `method`:
+    /// the current file, then the file that declares the type of `value`
+    fn free_method_places(&self, receiver_ty: Intern<Ty>, name: hir::Name) -> Vec<hir::Fqn> {
+        let mut deref_ty = receiver_ty;
+        while let Some((_, sub_ty)) = deref_ty.as_pointer() {
+            deref_ty = sub_ty;
+        }
 
-                            Ty::Array {
-                                anonymous: false,
-                                size: items.len() as u64,
-                                sub_ty,
-                            }
-                            .into()
-                        }
-                        Expr::ArrayLiteral { ty: None, items } => {
-                            let mut max_ty = None;
-                            let mut any_error = false;
-                            for item in items {
-                                let item_ty = self.tys[self.file][*item];
+        let mut places = vec![self.world_bodies.resolve_global(self.file, name)];
+        if let Ty::Struct {
+            fqn: Some(ty_fqn), ..
+        }
+        | Ty::Distinct {
+            fqn: Some(ty_fqn), ..
+        }
+        | Ty::Enum {
+            fqn: Some(ty_fqn), ..
+        } = deref_ty.as_ref()
+        {
+            places.push(hir::Fqn {
+                file: ty_fqn.file,
+                name,
+            });
+        }
 
-                                match max_ty {
-                                    None => max_ty = Some(item_ty),
-                                    Some(previous) => {
-                                        if !any_error {
-                                            max_ty = Some(
-                                                previous
-                                                    .max(&item_ty)
-                                                    .unwrap_or_else(|| {
-                                                        if !any_error {
-                                                            self.diagnostics.push(TyDiagnostic {
-                                                                kind: TyDiagnosticKind::Mismatch {
-                                                                    expected: ExpectedTy::Concrete(
-                                                                        previous,
-                                                                    ),
-                                                                    found: item_ty,
-                                                                },
-                                                                file: self.file,
-                                                                expr: Some(*item),
-                                                                range: self
-                                                                    .bodies
-                                                                    .range_for_expr(*item),
-                                                                help: None,
-                                                            });
-                                                            any_error = true;
-                                                        }
-                                                        Ty::Unknown
-                                                    })
-                                                    .into(),
-                                            )
-                                        }
-                                    }
-                                }
-                            }
+        places
+    }
 
-                            if let Some(max_ty) = max_ty.filter(|_| !any_error) {
-                                for item in items {
-                                    self.replace_weak_tys(*item, max_ty);
-                                }
-                            }
+    /// Every function defined with the given name, or nothing if it isn't a function
+    fn function_overloads(&self, place: hir::Fqn) -> Vec<hir::Fqn> {
+        if !self.world_bodies.exists(place)
+            || !matches!(
+                self.world_bodies[place.file][self.world_bodies.body(place)],
+                Expr::Lambda(_)
+            )
+        {
+            return Vec::new();
+        }
 
-                            let sub_ty = if any_error {
-                                Ty::Unknown.into()
-                            } else {
-                                // todo: instead of void, create a new type that casts to anything
-                                max_ty.unwrap_or_else(|| Ty::Void.into())
-                            };
+        match self.world_index.overloads(place) {
+            Some(overloads) => overloads
+                .iter()
+                .map(|name| hir::Fqn {
+                    file: place.file,
+                    name: *name,
+                })
+                .collect(),
+            None => vec![place],
+        }
+    }
 
-                            Ty::Array {
-                                anonymous: true,
-                                size: items.len() as u64,
-                                sub_ty,
-                            }
-                            .into()
-                        }
-                        Expr::Index { source, index } => {
-                            let source_ty = self.tys[self.file][*source];
-                            // because it's annoying to do `foo^[0]`, this code lets you do `foo[0]`
-                            let mut deref_source_ty = source_ty;
-                            while let Some((_, sub_ty)) = deref_source_ty.as_pointer() {
-                                deref_source_ty = sub_ty;
-                            }
-
-                            let index_ty = self.tys[self.file][*index];
-
-                            if self.expect_match(index_ty, Ty::UInt(u8::MAX).into(), *index) {
-                                self.replace_weak_tys(*index, Ty::UInt(u8::MAX).into());
-                            }
+    fn global_signature(&mut self, fqn: hir::Fqn, expr: Idx<Expr>) -> InferResult<Intern<Ty>> {
+        let sig = self
+            .tys
+            .signatures
+            .get(&fqn)
+            .ok_or_else(|| vec![Inferrable::Global(fqn)])?;
 
-                            if *deref_source_ty == Ty::Unknown {
-                                Ty::Unknown.into()
-                            } else if *deref_source_ty == Ty::RawSlice {
-                                self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::IndexRaw { size: None },
-                                    file: self.file,
-                                    expr: Some(expr),
-                                    range: self.bodies.range_for_expr(expr),
-                                    help: None,
-                                });
+        if *sig.0 == Ty::NotYetResolved {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::NotYetResolved { fqn },
+                file: self.file,
+                expr: Some(expr),
+                range: self.bodies.range_for_expr(expr),
+                help: None,
+            });
 
-                                Ty::Unknown.into()
-                            } else if let Some((actual_size, array_sub_ty)) =
-                                deref_source_ty.as_array()
-                            {
-                                if let hir::Expr::IntLiteral(index) = self.bodies[*index] {
-                                    if index >= actual_size {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::IndexOutOfBounds {
-                                                index,
-                                                actual_size,
-                                                array_ty: source_ty,
-                                            },
-                                            file: self.file,
-                                            expr: Some(expr),
-                                            range: self.bodies.range_for_expr(expr),
-                                            help: None,
-                                        });
-                                    }
-                                }
+            Ok(Ty::Error.into())
+        } else {
+            Ok(sig.0)
+        }
+    }
 
-                                array_sub_ty
-                            } else if let Some(slice_sub_ty) = deref_source_ty.as_slice() {
-                                slice_sub_ty
-                            } else {
-                                self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::IndexNonArray { found: source_ty },
-                                    file: self.file,
-                                    expr: Some(expr),
-                                    range: self.bodies.range_for_expr(expr),
-                                    help: None,
-                                });
+    /// Returns the size of the array if `previous.field` is the `.len` of an array
+    fn array_len(&self, file: hir::FileName, previous: Idx<Expr>, field: hir::Name) -> Option<u64> {
+        if self.interner.lookup(field.0) != "len" {
+            return None;
+        }
 
-                                Ty::Unknown.into()
-                            }
-                        }
-                        Expr::Cast { ty, expr: None } => {
-                            let cast_ty = self.const_ty(*ty)?;
+        let mut deref_ty = self.tys[file][previous];
+        while let Some((_, sub_ty)) = deref_ty.as_pointer() {
+            deref_ty = sub_ty;
+        }
 
-                            if cast_ty.is_unknown() {
-                                Ty::Unknown.into()
-                            } else {
-                                if !Ty::Void.can_cast_to(&cast_ty) {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::Uncastable {
-                                            from: Ty::Void.into(),
-                                            to: cast_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(expr),
-                                        help: None,
-                                    });
-                                }
+        deref_ty
+            .absolute_intern_ty(true)
+            .as_array()
+            .map(|(size, _)| size)
+    }
 
-                                cast_ty
-                            }
-                        }
-                        Expr::Cast {
-                            ty,
-                            expr: Some(sub_expr),
-                        } => {
-                            let expr_ty = self.tys[self.file][*sub_expr];
+    /// The functions within a constant struct or array get stored as pointers to their
+    /// symbols, which only global functions have
+    fn const_aggregate_items(
+        &self,
+        file: hir::FileName,
+        mut items: impl Iterator<Item = Idx<Expr>>,
+    ) -> ExprIsConst {
+        if items.any(|item| matches!(self.world_bodies[file][item], Expr::Lambda(_))) {
+            ExprIsConst::Runtime
+        } else {
+            ExprIsConst::Const
+        }
+    }
 
-                            if *expr_ty == Ty::Unknown {
-                                Ty::Unknown.into()
-                            } else {
-                                let cast_ty = self.const_ty(*ty)?;
+    fn get_const(&self, expr: Idx<Expr>) -> ExprIsConst {
+        let mut to_check = vec![(self.file, expr)];
 
-                                if cast_ty.is_unknown() {
-                                    Ty::Unknown.into()
-                                } else {
-                                    if !expr_ty.can_cast_to(&cast_ty) {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::Uncastable {
-                                                from: expr_ty,
-                                                to: cast_ty,
-                                            },
-                                            file: self.file,
-                                            expr: Some(expr),
-                                            range: self.bodies.range_for_expr(expr),
-                                            help: None,
-                                        });
-                                    }
+        let mut idx = 0;
+        while let Some((file, expr)) = to_check.get(idx).copied() {
+            let result = match &self.world_bodies[file][expr] {
+                Expr::Missing
+                | Expr::Lambda(_)
+                | Expr::Import(_)
+                | Expr::PrimitiveTy { .. }
+                | Expr::StructDecl { .. }
+                | Expr::InterfaceDecl { .. }
+                | Expr::Distinct { .. }
+                | Expr::OptionalDecl { .. }
+                | Expr::ClosureDecl { .. }
+                | Expr::Comptime(_)
+                | Expr::StringLiteral(_)
+                | Expr::IntLiteral(_)
+                | Expr::FloatLiteral(_)
+                | Expr::BoolLiteral(_)
+                | Expr::NullLiteral => ExprIsConst::Const,
+                Expr::ArrayLiteral { items, .. } if self.tys[file][expr].is_array() => {
+                    to_check.extend(items.iter().map(|e| (file, *e)));
+                    self.const_aggregate_items(file, items.iter().copied())
+                }
+                Expr::ArrayRepeat { item, .. } if self.tys[file][expr].is_array() => {
+                    to_check.push((file, *item));
+                    self.const_aggregate_items(file, std::iter::once(*item))
+                }
+                // constant data is copied byte for byte, so members which would have to be
+                // converted first (like a `5` going into a `?i32`) can only be set at runtime
+                Expr::StructLiteral { members, .. }
+                    if self.tys[file][expr].as_struct().is_some_and(|member_tys| {
+                        members.iter().all(|member| {
+                            member_tys.iter().any(|member_ty| {
+                                member.name.map(|name| name.name) == Some(member_ty.name)
+                                    && member_ty.ty == self.tys[file][member.value]
+                            })
+                        })
+                    }) =>
+                {
+                    to_check.extend(members.iter().map(|member| (file, member.value)));
+                    self.const_aggregate_items(file, members.iter().map(|member| member.value))
+                }
+                // only casts between primitives can be folded by `const_data`
+                Expr::Cast {
+                    expr: Some(inner), ..
+                } if self.tys[file][*inner]
+                    .absolute_ty()
+                    .primitive_cast(self.tys[file][expr].absolute_ty())
+                    .is_some() =>
+                {
+                    to_check.push((file, *inner));
+                    ExprIsConst::Const
+                }
+                Expr::LocalGlobal(global) => {
+                    let fqn = self.world_bodies.resolve_global(file, global.name);
 
-                                    // replacing the existing type with the casted type
-                                    self.replace_weak_tys(*sub_expr, cast_ty);
+                    if !self.world_bodies.exists(fqn) {
+                        return ExprIsConst::Unknown;
+                    }
 
-                                    cast_ty
-                                }
-                            }
+                    if self.world_bodies.is_extern(fqn) {
+                        ExprIsConst::Runtime
+                    } else {
+                        let inferrable = Inferrable::Global(fqn);
+                        if !self.all_inferred.contains(&inferrable) {
+                            // this can only happen if there's been a cyclic error
+                            assert_eq!(*self.tys[fqn].0, Ty::NotYetResolved);
+                            return ExprIsConst::Unknown;
                         }
-                        Expr::Ref {
-                            mutable,
-                            expr: inner,
-                        } => {
-                            let inner_ty = self.tys[self.file][*inner];
 
-                            if *inner_ty == Ty::Type {
-                                self.const_ty(expr)?;
-                                inner_ty
-                            } else {
-                                if *mutable {
-                                    let help =
-                                        self.get_mutability(*inner, false, false).into_diagnostic();
+                        to_check.push((fqn.file, self.world_bodies.body(fqn)));
+                        ExprIsConst::Const
+                    }
+                }
+                Expr::Local(local) => {
+                    let local_def = &self.world_bodies[file][*local];
 
-                                    if help.is_some() {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::MutableRefToImmutableData,
-                                            file: self.file,
-                                            expr: Some(expr),
-                                            range: self.bodies.range_for_expr(expr),
-                                            help,
-                                        })
-                                    }
-                                }
+                    if let Some(value) = local_def.value {
+                        to_check.push((file, value));
+                    }
 
-                                Ty::Pointer {
-                                    mutable: *mutable,
-                                    sub_ty: inner_ty,
-                                }
-                                .into()
-                            }
-                        }
-                        Expr::Deref { pointer } => {
-                            let deref_ty = self.tys[self.file][*pointer];
+                    if local_def.mutable {
+                        ExprIsConst::Runtime
+                    } else if local_def.value.is_none() {
+                        // this protects against cases like `x ::;`
+                        ExprIsConst::Unknown
+                    } else {
+                        ExprIsConst::Const
+                    }
+                }
+                Expr::Member {
+                    previous,
+                    name: field,
+                } => {
+                    let old_file = file;
 
-                            match *deref_ty {
-                                Ty::RawPtr { .. } => {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::DerefRaw,
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(expr),
-                                        help: None,
-                                    });
+                    if let Ty::File(file) = self.tys[old_file][*previous].as_ref() {
+                        to_check.push((old_file, *previous));
 
-                                    Ty::Unknown.into()
-                                }
-                                Ty::Pointer { sub_ty, .. } => sub_ty,
-                                _ => {
-                                    if !deref_ty.is_unknown() {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::DerefNonPointer {
-                                                found: deref_ty,
-                                            },
-                                            file: self.file,
-                                            expr: Some(expr),
-                                            range: self.bodies.range_for_expr(expr),
-                                            help: None,
-                                        });
-                                    }
+                        let fqn = hir::Fqn {
+                            file: *file,
+                            name: field.name,
+                        };
 
-                                    Ty::Unknown.into()
-                                }
+                        if !self.world_bodies.exists(fqn) {
+                            ExprIsConst::Unknown
+                        } else if self.world_bodies.is_extern(fqn) {
+                            ExprIsConst::Runtime
+                        } else {
+                            let inferrable = Inferrable::Global(fqn);
+                            if !self.all_inferred.contains(&inferrable) {
+                                // this can only happen if there's been a cyclic error
+                                assert_eq!(*self.tys[fqn].0, Ty::NotYetResolved);
+                                return ExprIsConst::Unknown;
                             }
+
+                            to_check.push((*file, self.world_bodies.body(fqn)));
+                            ExprIsConst::Const
+                        }
+                    } else if let Some(fqn) = self.tys[old_file]
+                        .get_namespaced_member(expr)
+                        .filter(|_| *self.tys[old_file][*previous] == Ty::Type)
+                    {
+                        let inferrable = Inferrable::Global(fqn);
+                        if !self.all_inferred.contains(&inferrable) {
+                            return ExprIsConst::Unknown;
                         }
-                        Expr::Binary { lhs, rhs, op } => {
-                            let lhs_ty = self.tys[self.file][*lhs];
-                            let rhs_ty = self.tys[self.file][*rhs];
 
-                            if let Some(output_ty) = op.get_possible_output_ty(&lhs_ty, &rhs_ty) {
-                                if *lhs_ty != Ty::Unknown
-                                    && *rhs_ty != Ty::Unknown
-                                    && !op.can_perform(&output_ty.max_ty)
-                                {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::BinaryOpMismatch {
-                                            op: *op,
-                                            first: lhs_ty,
-                                            second: rhs_ty,
+                        to_check.push((fqn.file, self.world_bodies.body(fqn)));
+                        ExprIsConst::Const
+                    } else if self.array_len(old_file, *previous, field.name).is_some() {
+                        // the length of an array is part of its type, so even runtime
+                        // arrays have a constant length
+                        ExprIsConst::Const
+                    } else if self.tys[old_file][*previous].as_struct().is_some() {
+                        to_check.push((old_file, *previous));
+                        ExprIsConst::Const
+                    } else {
+                        ExprIsConst::Runtime
+                    }
+                }
+                // compile-time parameters only have a value within an instance
+                Expr::Param { idx, .. } if file == self.file && self.const_params.is_some() => {
+                    let (lambda, values) = self.const_params.as_ref().unwrap();
+
+                    if values.get(*idx as usize).is_some_and(Option::is_some) {
+                        ExprIsConst::Const
+                    } else if self.bodies[*lambda].params[*idx as usize].is_const {
+                        ExprIsConst::Unknown
+                    } else {
+                        ExprIsConst::Runtime
+                    }
+                }
+                _ => {
+                    if matches!(*(self.tys[file][expr]), Ty::Type | Ty::File(_)) {
+                        ExprIsConst::Const
+                    } else {
+                        ExprIsConst::Runtime
+                    }
+                }
+            };
+
+            if result == ExprIsConst::Runtime || result == ExprIsConst::Unknown {
+                return result;
+            }
+
+            idx += 1;
+        }
+
+        ExprIsConst::Const
+    }
+
+    /// `deref` allows certain expressions to be mutable
+    /// only if they are being mutated through a deref
+    fn get_mutability(&self, expr: Idx<Expr>, assignment: bool, deref: bool) -> ExprMutability {
+        match &self.bodies[expr] {
+            Expr::Missing => ExprMutability::Mutable,
+            Expr::ArrayLiteral { .. } => ExprMutability::Mutable,
+            Expr::ArrayRepeat { .. } => ExprMutability::Mutable,
+            Expr::StructLiteral { .. } => ExprMutability::Mutable,
+            Expr::Tuple(_) => ExprMutability::Mutable,
+            Expr::Ref { mutable, .. } => match (*mutable, deref) {
+                (true, _) => ExprMutability::Mutable,
+                // (true, false) => ExprMutability::NotMutatingRefThroughDeref(
+                //     self.bodies.range_for_expr(expr),
+                // ),
+                _ => ExprMutability::ImmutableRef(self.bodies.range_for_expr(expr)),
+            },
+            Expr::Deref { pointer } => self.get_mutability(*pointer, assignment, true),
+            Expr::Index { source: array, .. } => self.get_mutability(
+                *array,
+                assignment,
+                deref || self.tys[self.file][*array].is_pointer(),
+            ),
+            Expr::Block {
+                tail_expr: Some(tail_expr),
+                ..
+            } => self.get_mutability(*tail_expr, assignment, deref),
+            Expr::Local(local_def) if deref => {
+                let local_def = &self.bodies[*local_def];
+
+                if let Some(value) = local_def.value {
+                    self.get_mutability(value, false, deref)
+                } else {
+                    // todo: does this make sense?
+                    ExprMutability::Mutable
+                }
+            }
+            Expr::Local(local_def) if !deref => {
+                let local_def = &self.bodies[*local_def];
+
+                if local_def.mutable {
+                    ExprMutability::Mutable
+                } else {
+                    ExprMutability::ImmutableBinding(local_def.range)
+                }
+            }
+            Expr::Param { idx, range } => {
+                let param_ty = self.param_tys[*idx as usize];
+
+                match param_ty.ty.as_pointer() {
+                    Some((mutable, _)) if deref => {
+                        if mutable {
+                            ExprMutability::Mutable
+                        } else {
+                            // todo: change this to be the range of the param's type
+                            ExprMutability::ImmutableRef(*range)
+                        }
+                    }
+                    Some((mutable, _)) if assignment => {
+                        if mutable {
+                            ExprMutability::NotMutatingRefThroughDeref(
+                                self.bodies.range_for_expr(expr),
+                            )
+                        } else {
+                            ExprMutability::ImmutableRef(*range)
+                        }
+                    }
+                    _ => ExprMutability::ImmutableParam(*range, assignment),
+                }
+            }
+            Expr::LocalGlobal(name) => {
+                let fqn = self.world_bodies.resolve_global(self.file, name.name);
+
+                if !self.world_bodies.exists(fqn) {
+                    // an error has already been reported about the name not existing
+                    return ExprMutability::Mutable;
+                }
+
+                ExprMutability::ImmutableGlobal(self.world_index.range_info(fqn).whole)
+            }
+            Expr::Member {
+                previous,
+                name: field,
+            } => {
+                let previous_ty = self.tys[self.file][*previous];
+                match previous_ty.as_ref() {
+                    Ty::File(file) => {
+                        let fqn = hir::Fqn {
+                            file: *file,
+                            name: field.name,
+                        };
+
+                        if *file == self.file {
+                            ExprMutability::ImmutableGlobal(self.world_index.range_info(fqn).whole)
+                        } else {
+                            ExprMutability::ImmutableGlobal(field.range)
+                        }
+                    }
+                    _ if self.tys[self.file].get_namespaced_member(expr).is_some() => {
+                        let fqn = self.tys[self.file].get_namespaced_member(expr).unwrap();
+
+                        if fqn.file == self.file {
+                            ExprMutability::ImmutableGlobal(self.world_index.range_info(fqn).whole)
+                        } else {
+                            ExprMutability::ImmutableGlobal(field.range)
+                        }
+                    }
+                    _ if deref => {
+                        let path_ty = &self.tys[self.file][expr];
+
+                        if path_ty
+                            .as_pointer()
+                            .map(|(mutable, _)| mutable)
+                            .unwrap_or(true)
+                        {
+                            ExprMutability::Mutable
+                        } else {
+                            // todo: use the actual range of the struct literal, not the range of this field name
+                            ExprMutability::ImmutableRef(field.range)
+                        }
+                    }
+                    _ => self.get_mutability(
+                        *previous,
+                        assignment,
+                        deref || previous_ty.is_pointer(),
+                    ),
+                }
+            }
+            Expr::Call { .. } if deref => ExprMutability::Mutable,
+            Expr::Cast { .. } if deref => {
+                let ty = self.tys[self.file][expr];
+
+                match ty.as_pointer() {
+                    Some((mutable, _)) if deref => {
+                        if mutable {
+                            ExprMutability::Mutable
+                        } else {
+                            // todo: change this to be the range of the param's type
+                            ExprMutability::ImmutableRef(self.bodies.range_for_expr(expr))
+                        }
+                    }
+                    Some((mutable, _)) if assignment => {
+                        if mutable {
+                            ExprMutability::NotMutatingRefThroughDeref(
+                                self.bodies.range_for_expr(expr),
+                            )
+                        } else {
+                            ExprMutability::ImmutableRef(self.bodies.range_for_expr(expr))
+                        }
+                    }
+                    _ => ExprMutability::CannotMutate(self.bodies.range_for_expr(expr)),
+                }
+            }
+            Expr::ForLocal(for_local) => {
+                let range = self.bodies[*for_local].range;
+
+                match self.tys[self.file][expr].as_pointer() {
+                    Some((false, _)) if deref => ExprMutability::ImmutableRef(range),
+                    _ if deref => ExprMutability::Mutable,
+                    _ => ExprMutability::ImmutableBinding(range),
+                }
+            }
+            // a closure only has a copy of what it captured, so changing it wouldn't change the
+            // original
+            Expr::Captured(_) => {
+                let range = self.bodies.range_for_expr(expr);
+
+                match self.tys[self.file][expr].as_pointer() {
+                    Some((false, _)) if deref => ExprMutability::ImmutableRef(range),
+                    _ if deref => ExprMutability::Mutable,
+                    _ => ExprMutability::ImmutableCapture(range),
+                }
+            }
+            Expr::Paren(Some(expr)) => self.get_mutability(*expr, assignment, deref),
+            _ => ExprMutability::CannotMutate(self.bodies.range_for_expr(expr)),
+        }
+    }
+
+    fn find_usages(&mut self, exprs: &[Idx<hir::Expr>], local_usage: Idx<hir::Stmt>) {
+        let mut locals = HashSet::default();
+        for expr in exprs {
+            self.get_referenced_locals(*expr, &mut locals);
+        }
+
+        for local in locals {
+            if let Some(usages) = self.local_usages.get_mut(local) {
+                usages.insert(local_usage);
+            } else {
+                let mut usages = FxHashSet::default();
+                usages.insert(local_usage);
+
+                self.local_usages.insert(local, usages);
+            }
+        }
+    }
+
+    fn get_referenced_locals(&self, expr: Idx<hir::Expr>, locals: &mut FxHashSet<Idx<LocalDef>>) {
+        locals.extend(
+            self.bodies
+                .descendants(expr, hir::DescentOpts::Reinfer)
+                .filter_map(|desc| match desc {
+                    Descendant::Expr(expr) => match self.bodies[expr] {
+                        Expr::Local(local) => Some(local),
+                        _ => None,
+                    },
+                    Descendant::Stmt(_) => None,
+                }),
+        );
+    }
+
+    fn reinfer_expr(&mut self, expr: Idx<hir::Expr>) -> Intern<Ty> {
+        let previous_ty = self.tys[self.file][expr];
+        if previous_ty.is_error_or_unknown() {
+            return previous_ty;
+        }
+
+        fn all_usages_ty(ctx: &mut GlobalInferenceCtx, label_id: ScopeId) -> Intern<Ty> {
+            let usages = ctx.bodies.scope_id_usages(label_id);
+
+            let mut max_ty: Option<Intern<Ty>> = None;
+            for usage in usages.iter() {
+                let ty = match ctx.bodies[*usage] {
+                    hir::Stmt::Break {
+                        value: Some(value), ..
+                    } => ctx.tys[ctx.file][value],
+                    hir::Stmt::Break { value: None, .. } => Ty::Void.into(),
+                    _ => continue,
+                };
+
+                if let Some(max) = max_ty {
+                    max_ty = max.max(&ty, ctx.tys).map(|ty| ty.into());
+                } else {
+                    max_ty = Some(ty);
+                }
+            }
+
+            max_ty.unwrap_or_else(|| Ty::Void.into())
+        }
+
+        let bodies = self.bodies;
+
+        for next in bodies.descendants_rev(expr, hir::DescentOpts::Infer) {
+            match next {
+                Descendant::Expr(expr) => {
+                    let previous_ty = self.tys[self.file][expr];
+
+                    if previous_ty.is_error_or_unknown() || *previous_ty == Ty::NoEval {
+                        continue;
+                    }
+
+                    let new_ty = match &self.bodies[expr] {
+                        Expr::IntLiteral(num) => match *previous_ty {
+                            Ty::IInt(0) if *num > i32::MAX as u64 => Ty::IInt(64).into(),
+                            Ty::UInt(0) if *num > u32::MAX as u64 => Ty::UInt(64).into(),
+                            _ => continue,
+                        },
+                        Expr::Ref {
+                            mutable,
+                            expr: inner,
+                        } => {
+                            let inner_ty = self.tys[self.file][*inner];
+
+                            if *inner_ty == Ty::Type {
+                                inner_ty
+                            } else {
+                                Ty::Pointer {
+                                    mutable: *mutable,
+                                    sub_ty: inner_ty,
+                                }
+                                .into()
+                            }
+                        }
+                        Expr::Deref { pointer } => {
+                            let inner_ty = self.tys[self.file][*pointer];
+
+                            inner_ty
+                                .as_pointer()
+                                .map(|(_, sub_ty)| sub_ty)
+                                .unwrap_or_else(|| Ty::Unknown.into())
+                        }
+                        // the return type of an overloaded operator can't change
+                        Expr::Binary { .. } if self.tys[self.file].get_operator(expr).is_some() => {
+                            continue
+                        }
+                        Expr::Binary { lhs, rhs, op } => {
+                            let lhs_ty = self.tys[self.file][*lhs];
+                            let rhs_ty = self.tys[self.file][*rhs];
+
+                            if let Some(output_ty) =
+                                op.get_possible_output_ty(&lhs_ty, &rhs_ty, self.tys)
+                            {
+                                let max_ty = output_ty.max_ty.into();
+                                self.replace_weak_tys(*lhs, max_ty);
+                                self.replace_weak_tys(*rhs, max_ty);
+
+                                output_ty.final_output_ty.into()
+                            } else {
+                                op.default_ty().into()
+                            }
+                        }
+                        Expr::Unary { expr: inner, op } => {
+                            let inner_ty = self.tys[self.file][*inner];
+                            if op.can_perform(&inner_ty) {
+                                op.get_possible_output_ty(inner_ty)
+                            } else {
+                                op.default_ty().into()
+                            }
+                        }
+                        Expr::Tuple(items) if *previous_ty != Ty::Type => Ty::Tuple {
+                            items: items
+                                .iter()
+                                .map(|item| self.tys[self.file][*item])
+                                .collect(),
+                        }
+                        .into(),
+                        Expr::Range {
+                            start,
+                            end,
+                            inclusive,
+                        } => {
+                            let sub_ty = start
+                                .or(*end)
+                                .map(|bound| self.tys[self.file][bound])
+                                .unwrap_or_else(|| Ty::UInt(u8::MAX).into());
+
+                            Ty::Range {
+                                inclusive: *inclusive,
+                                sub_ty,
+                            }
+                            .into()
+                        }
+                        Expr::Index { source, index } => {
+                            let mut source_ty = self.tys[self.file][*source];
+
+                            while let Some(ptr) = source_ty.as_pointer() {
+                                source_ty = ptr.1;
+                            }
+
+                            let sub_ty = source_ty
+                                .as_array()
+                                .map(|(_, sub_ty)| sub_ty)
+                                .or_else(|| source_ty.as_slice());
+
+                            match sub_ty {
+                                Some(sub_ty)
+                                    if self.tys[self.file][*index].as_range().is_some() =>
+                                {
+                                    Ty::Slice { sub_ty }.into()
+                                }
+                                Some(sub_ty) => sub_ty,
+                                None => Ty::Unknown.into(),
+                            }
+                        }
+                        Expr::Block { tail_expr, .. } => {
+                            let tail_ty = tail_expr.map(|tail_expr| self.tys[self.file][tail_expr]);
+
+                            if let Some(label_id) = self.bodies.block_to_scope_id(expr) {
+                                let usages_ty = all_usages_ty(self, label_id);
+
+                                if let Some(new_tail) = tail_ty {
+                                    usages_ty
+                                        .max(&new_tail, self.tys)
+                                        .unwrap_or(Ty::Unknown)
+                                        .into()
+                                } else {
+                                    usages_ty
+                                }
+                            } else {
+                                tail_ty.unwrap_or_else(|| Ty::Void.into())
+                            }
+                        }
+                        Expr::If {
+                            body, else_branch, ..
+                        } => {
+                            let body_ty = self.tys[self.file][*body];
+
+                            if let Some(else_branch) = else_branch {
+                                let new_else = self.tys[self.file][*else_branch];
+
+                                body_ty
+                                    .max(&new_else, self.tys)
+                                    .unwrap_or(Ty::Unknown)
+                                    .into()
+                            } else if *body_ty == Ty::NoEval {
+                                Ty::Void.into()
+                            } else {
+                                body_ty
+                            }
+                        }
+                        Expr::While { .. } | Expr::For { .. } => Ty::Void.into(),
+                        Expr::Loop { .. } => match self.bodies.block_to_scope_id(expr) {
+                            Some(label_id) => all_usages_ty(self, label_id),
+                            None => Ty::NoEval.into(),
+                        },
+                        Expr::Local(local) => self.tys[self.file].local_tys[*local],
+                        Expr::Directive { name, args } => {
+                            let Some((_, operands)) =
+                                IntrinsicOperands::of(self.interner.lookup(name.name.0))
+                            else {
+                                continue;
+                            };
+                            let Ok(Some(max_ty)) = self.math_intrinsic_ty(operands, args) else {
+                                continue;
+                            };
+
+                            for arg in args {
+                                self.replace_weak_tys(*arg, max_ty);
+                            }
+
+                            max_ty
+                        }
+                        _ => {
+                            continue;
+                        }
+                    };
+
+                    if is_stronger_ty(&previous_ty, &new_ty) {
+                        self.tys[self.file].expr_tys.insert(expr, new_ty);
+                    }
+                }
+                Descendant::Stmt(stmt) => match &self.bodies[stmt] {
+                    Stmt::Expr(_) => {}
+                    Stmt::LocalDef(local_def) => {
+                        let def_body = &self.bodies[*local_def];
+
+                        if def_body.ty.is_some() {
+                            // if there's a type annotation, then even if the value changed
+                            // types, the local will always have the type of it's type annotation
+                            continue;
+                        }
+
+                        let Some(value) = def_body.value else {
+                            continue;
+                        };
+
+                        let previous_ty = self.tys[self.file][*local_def];
+                        let new_ty = self.tys[self.file][value];
+
+                        if is_stronger_ty(&previous_ty, &new_ty) {
+                            self.tys[self.file].local_tys.insert(*local_def, new_ty);
+                        }
+                    }
+                    Stmt::Assign(_) => {}
+                    Stmt::Break { .. } => {}
+                    Stmt::Continue { .. } => {}
+                    Stmt::Defer { .. } => {}
+                },
+            }
+        }
+
+        self.tys[self.file][expr]
+    }
+
+    // This function is indent hell but it's worth it to make it stack overflow free
+    pub(crate) fn infer_expr(&mut self, expr: Idx<Expr>) -> InferResult<Intern<Ty>> {
+        if let (Some(ty), None) = (
+            self.tys[self.file].expr_tys.get(expr),
+            self.bodies.block_to_scope_id(expr),
+        ) {
+            return Ok(*ty);
+        }
+
+        let bodies = self.bodies;
+
+        // This all works because children will ALWAYS come before parents
+        for descendant in bodies.descendants_rev(expr, hir::DescentOpts::Infer) {
+            match descendant {
+                Descendant::Expr(expr) => {
+                    if self.tys[self.file].expr_tys.contains_idx(expr)
+                        && self.bodies.block_to_scope_id(expr).is_none()
+                    {
+                        continue;
+                    }
+
+                    let ty = match &self.bodies[expr] {
+                        Expr::Missing => Ty::Error.into(),
+                        Expr::IntLiteral(_) | Expr::FloatLiteral(_)
+                            if self.bodies.literal_suffix(expr).is_some() =>
+                        {
+                            self.suffixed_literal_ty(expr)
+                        }
+                        Expr::IntLiteral(_) => Ty::UInt(0).into(),
+                        Expr::FloatLiteral(_) => Ty::Float(0).into(),
+                        Expr::BoolLiteral(_) => Ty::Bool.into(),
+                        Expr::StringLiteral(_) => Ty::String.into(),
+                        Expr::CharLiteral(_) => Ty::Char.into(),
+                        Expr::NullLiteral => Ty::RawPtr { mutable: true }.into(),
+                        Expr::ArrayDecl { .. } => {
+                            self.const_ty(expr)?;
+                            Ty::Type.into()
+                        }
+                        Expr::ArrayLiteral {
+                            ty: Some(ty),
+                            items,
+                        } => {
+                            let sub_ty = self.const_ty(*ty)?;
+                            for item in items {
+                                let item_ty = self.tys[self.file][*item];
+                                self.expect_match(item_ty, sub_ty, *item);
+                            }
+
+                            Ty::Array {
+                                anonymous: false,
+                                size: items.len() as u64,
+                                sub_ty,
+                            }
+                            .into()
+                        }
+                        Expr::ArrayLiteral { ty: None, items } => {
+                            let mut max_ty = None;
+                            let mut any_error = false;
+                            for item in items {
+                                let item_ty = self.tys[self.file][*item];
+
+                                match max_ty {
+                                    None => max_ty = Some(item_ty),
+                                    Some(previous) => {
+                                        if !any_error {
+                                            max_ty = Some(
+                                                previous
+                                                    .max(&item_ty, self.tys)
+                                                    .unwrap_or_else(|| {
+                                                        if !any_error {
+                                                            self.diagnostics.push(TyDiagnostic {
+                                                                kind: TyDiagnosticKind::Mismatch {
+                                                                    expected: ExpectedTy::Concrete(
+                                                                        previous,
+                                                                    ),
+                                                                    found: item_ty,
+                                                                },
+                                                                file: self.file,
+                                                                expr: Some(*item),
+                                                                range: self
+                                                                    .bodies
+                                                                    .range_for_expr(*item),
+                                                                help: None,
+                                                            });
+                                                            any_error = true;
+                                                        }
+                                                        Ty::Error
+                                                    })
+                                                    .into(),
+                                            )
+                                        }
+                                    }
+                                }
+                            }
+
+                            if let Some(max_ty) = max_ty.filter(|_| !any_error) {
+                                for item in items {
+                                    self.replace_weak_tys(*item, max_ty);
+                                }
+                            }
+
+                            let sub_ty = if any_error {
+                                Ty::Error.into()
+                            } else {
+                                // todo: instead of void, create a new type that casts to anything
+                                max_ty.unwrap_or_else(|| Ty::Void.into())
+                            };
+
+                            Ty::Array {
+                                anonymous: true,
+                                size: items.len() as u64,
+                                sub_ty,
+                            }
+                            .into()
+                        }
+                        Expr::ArrayRepeat { ty, item, count } => {
+                            let item_ty = self.tys[self.file][*item];
+
+                            let sub_ty = match ty {
+                                Some(ty) => {
+                                    let sub_ty = self.const_ty(*ty)?;
+                                    self.expect_match(item_ty, sub_ty, *item);
+                                    sub_ty
+                                }
+                                None => item_ty,
+                            };
+
+                            match self.const_array_size(*count)? {
+                                Some(size) => Ty::Array {
+                                    anonymous: ty.is_none(),
+                                    size,
+                                    sub_ty,
+                                }
+                                .into(),
+                                None => Ty::Error.into(),
+                            }
+                        }
+                        Expr::Index { source, index } => {
+                            let source_ty = self.tys[self.file][*source];
+                            // because it's annoying to do `foo^[0]`, this code lets you do `foo[0]`
+                            let mut deref_source_ty = source_ty;
+                            while let Some((_, sub_ty)) = deref_source_ty.as_pointer() {
+                                deref_source_ty = sub_ty;
+                            }
+
+                            let index_ty = self.tys[self.file][*index];
+                            // indexing sees through distinct integers so that `ids[id]` works
+                            // for `Id :: distinct u32`. nothing else mixes a distinct with its sub type
+                            let index_ty = if index_ty.is_int() {
+                                index_ty.absolute_intern_ty(true)
+                            } else {
+                                index_ty
+                            };
+
+                            // slicing with a range gives back a slice instead of a single element
+                            let slicing = index_ty.as_range();
+                            let expected_index_ty = match slicing {
+                                Some((inclusive, _)) => Ty::Range {
+                                    inclusive,
+                                    sub_ty: Ty::UInt(u8::MAX).into(),
+                                }
+                                .into(),
+                                None => Ty::UInt(u8::MAX).into(),
+                            };
+
+                            if self.expect_match(index_ty, expected_index_ty, *index) {
+                                self.replace_weak_tys(*index, expected_index_ty);
+                            }
+
+                            if deref_source_ty.is_error_or_unknown() {
+                                deref_source_ty
+                            } else if *deref_source_ty == Ty::RawSlice {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::IndexRaw { size: None },
+                                    file: self.file,
+                                    expr: Some(expr),
+                                    range: self.bodies.range_for_expr(expr),
+                                    help: None,
+                                });
+
+                                Ty::Error.into()
+                            } else if let Some(sub_ty) = deref_source_ty
+                                .as_array()
+                                .map(|(_, sub_ty)| sub_ty)
+                                .or_else(|| deref_source_ty.as_slice())
+                                .filter(|_| slicing.is_some())
+                            {
+                                Ty::Slice { sub_ty }.into()
+                            } else if let Some((actual_size, array_sub_ty)) =
+                                deref_source_ty.as_array()
+                            {
+                                if let Some(index) = self.int_range(*index)? {
+                                    // the smallest value the index could have is still too big
+                                    let index = *index.start();
+                                    if index >= actual_size {
+                                        let kind = self.index_out_of_bounds(
+                                            *source,
+                                            index,
+                                            actual_size,
+                                            array_sub_ty,
+                                        );
+
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind,
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: self.bodies.range_for_expr(expr),
+                                            help: None,
+                                        });
+                                    }
+                                }
+
+                                array_sub_ty
+                            } else if let Some(slice_sub_ty) = deref_source_ty.as_slice() {
+                                slice_sub_ty
+                            } else {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::IndexNonArray { found: source_ty },
+                                    file: self.file,
+                                    expr: Some(expr),
+                                    range: self.bodies.range_for_expr(expr),
+                                    help: None,
+                                });
+
+                                Ty::Error.into()
+                            }
+                        }
+                        Expr::Cast { ty, expr: None } => {
+                            let cast_ty = self.const_ty(*ty)?;
+
+                            if cast_ty.is_unknown() {
+                                Ty::Error.into()
+                            } else {
+                                if !Ty::Void.can_cast_to(&cast_ty) {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::Uncastable {
+                                            from: Ty::Void.into(),
+                                            to: cast_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
+                                        help: None,
+                                    });
+                                }
+
+                                cast_ty
+                            }
+                        }
+                        Expr::Cast {
+                            ty,
+                            expr: Some(sub_expr),
+                        } => {
+                            let expr_ty = self.tys[self.file][*sub_expr];
+
+                            if expr_ty.is_error_or_unknown() {
+                                expr_ty
+                            } else {
+                                let cast_ty = self.const_ty(*ty)?;
+
+                                if cast_ty.is_unknown() {
+                                    Ty::Error.into()
+                                } else {
+                                    if !expr_ty.can_cast_to(&cast_ty) {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::Uncastable {
+                                                from: expr_ty,
+                                                to: cast_ty,
+                                            },
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: self.bodies.range_for_expr(expr),
+                                            help: None,
+                                        });
+                                    } else if let Some((from, from_align, to_align)) =
+                                        self.misaligned_pointer_cast(*sub_expr, cast_ty)
+                                    {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::MisalignedPointerCast {
+                                                from,
+                                                to: cast_ty,
+                                                from_align,
+                                                to_align,
+                                            },
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: self.bodies.range_for_expr(expr),
+                                            help: None,
+                                        });
+                                    }
+
+                                    // replacing the existing type with the casted type
+                                    self.replace_weak_tys(*sub_expr, cast_ty);
+
+                                    cast_ty
+                                }
+                            }
+                        }
+                        Expr::Ref {
+                            mutable,
+                            expr: inner,
+                        } => {
+                            let inner_ty = self.tys[self.file][*inner];
+
+                            if *inner_ty == Ty::Type {
+                                self.const_ty(expr)?;
+                                inner_ty
+                            } else {
+                                if *mutable {
+                                    let help =
+                                        self.get_mutability(*inner, false, false).into_diagnostic();
+
+                                    if help.is_some() {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::MutableRefToImmutableData,
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: self.bodies.range_for_expr(expr),
+                                            help,
+                                        })
+                                    }
+                                }
+
+                                Ty::Pointer {
+                                    mutable: *mutable,
+                                    sub_ty: inner_ty,
+                                }
+                                .into()
+                            }
+                        }
+                        Expr::Deref { pointer } => {
+                            let deref_ty = self.tys[self.file][*pointer];
+
+                            match *deref_ty {
+                                Ty::RawPtr { .. } => {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::DerefRaw,
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
+                                        help: None,
+                                    });
+
+                                    Ty::Error.into()
+                                }
+                                Ty::Pointer { sub_ty, .. } => sub_ty,
+                                _ => {
+                                    if !deref_ty.is_unknown() {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::DerefNonPointer {
+                                                found: deref_ty,
+                                            },
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: self.bodies.range_for_expr(expr),
+                                            help: None,
+                                        });
+                                    }
+
+                                    Ty::Error.into()
+                                }
+                            }
+                        }
+                        Expr::Tuple(items) => {
+                            let item_tys = items
+                                .iter()
+                                .map(|item| self.tys[self.file][*item])
+                                .collect_vec();
+
+                            // `(i32, str)` is a tuple type
+                            if item_tys.iter().all(|ty| **ty == Ty::Type) {
+                                self.const_ty(expr)?;
+                                Ty::Type.into()
+                            } else {
+                                Ty::Tuple { items: item_tys }.into()
+                            }
+                        }
+                        Expr::Range {
+                            start,
+                            end,
+                            inclusive,
+                        } => {
+                            let sub_ty = match (*start, *end) {
+                                (Some(start), Some(end)) => {
+                                    let start_ty = self.tys[self.file][start];
+                                    let end_ty = self.tys[self.file][end];
+
+                                    match start_ty.max(&end_ty, self.tys) {
+                                        Some(max_ty) => {
+                                            let max_ty = max_ty.into();
+                                            self.replace_weak_tys(start, max_ty);
+                                            self.replace_weak_tys(end, max_ty);
+                                            max_ty
+                                        }
+                                        None => {
+                                            self.expect_match(end_ty, start_ty, end);
+                                            start_ty
+                                        }
+                                    }
+                                }
+                                (Some(bound), None) | (None, Some(bound)) => {
+                                    self.tys[self.file][bound]
+                                }
+                                // `arr[..]` slices the whole array
+                                (None, None) => Ty::UInt(u8::MAX).into(),
+                            };
+
+                            if sub_ty.is_error_or_unknown() {
+                                Ty::Error.into()
+                            } else if !sub_ty.is_int() {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::RangeBoundNotInt { found: sub_ty },
+                                    file: self.file,
+                                    expr: Some(expr),
+                                    range: self.bodies.range_for_expr(expr),
+                                    help: None,
+                                });
+
+                                Ty::Error.into()
+                            } else {
+                                Ty::Range {
+                                    inclusive: *inclusive,
+                                    sub_ty,
+                                }
+                                .into()
+                            }
+                        }
+                        Expr::Binary { lhs, rhs, op } => 'binary: {
+                            let lhs_ty = self.tys[self.file][*lhs];
+                            let rhs_ty = self.tys[self.file][*rhs];
+
+                            // `{ return; } + 1` never produces a value, but
+                            // `x && { return; }` does whenever `x` short-circuits
+                            let never_finishes = *lhs_ty == Ty::NoEval
+                                || (*rhs_ty == Ty::NoEval
+                                    && !matches!(op, hir::BinaryOp::LAnd | hir::BinaryOp::LOr));
+
+                            if *lhs_ty == Ty::NoEval && *rhs_ty == Ty::NoEval {
+                                break 'binary Ty::NoEval.into();
+                            }
+
+                            if let Some(output_ty) = self.resolve_operator(expr, *op, *lhs, *rhs)? {
+                                break 'binary if never_finishes {
+                                    Ty::NoEval.into()
+                                } else {
+                                    output_ty
+                                };
+                            }
+
+                            let compared_to_null = match (&self.bodies[*lhs], &self.bodies[*rhs]) {
+                                (_, Expr::NullLiteral) => Some(lhs_ty),
+                                (Expr::NullLiteral, _) => Some(rhs_ty),
+                                _ => None,
+                            };
+                            if compared_to_null.is_some_and(|ty| op.can_perform_with_null(&ty)) {
+                                break 'binary Ty::Bool.into();
+                            }
+
+                            self.check_self_comparison(expr, *op, *lhs, *rhs);
+
+                            let output_ty = if let Some(output_ty) =
+                                op.get_possible_output_ty(&lhs_ty, &rhs_ty, self.tys)
+                            {
+                                if !lhs_ty.is_error_or_unknown()
+                                    && !rhs_ty.is_error_or_unknown()
+                                    && !op.can_perform(&output_ty.max_ty)
+                                {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::BinaryOpMismatch {
+                                            op: *op,
+                                            first: lhs_ty,
+                                            second: rhs_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
+                                        help: None,
+                                    });
+                                }
+
+                                let max_ty = output_ty.max_ty.into();
+
+                                self.replace_weak_tys(*lhs, max_ty);
+                                self.replace_weak_tys(*rhs, max_ty);
+
+                                output_ty.final_output_ty.into()
+                            } else {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::BinaryOpMismatch {
+                                        op: *op,
+                                        first: lhs_ty,
+                                        second: rhs_ty,
+                                    },
+                                    file: self.file,
+                                    expr: Some(expr),
+                                    range: self.bodies.range_for_expr(expr),
+                                    help: None,
+                                });
+
+                                op.default_ty().into()
+                            };
+
+                            if never_finishes {
+                                Ty::NoEval.into()
+                            } else {
+                                output_ty
+                            }
+                        }
+                        Expr::Orelse { optional, fallback } => {
+                            let optional_ty = self.tys[self.file][*optional];
+                            let fallback_ty = self.tys[self.file][*fallback];
+
+                            if *optional_ty == Ty::NoEval {
+                                Ty::NoEval.into()
+                            } else if let Some(sub_ty) = optional_ty.as_optional() {
+                                // `a orelse b` can give back another optional
+                                // when `b` might also be `null`
+                                if fallback_ty.as_optional().is_some() {
+                                    self.expect_match(fallback_ty, optional_ty, *fallback);
+                                    optional_ty
+                                } else {
+                                    if self.expect_match(fallback_ty, sub_ty, *fallback) {
+                                        self.replace_weak_tys(*fallback, sub_ty);
+                                    }
+                                    sub_ty
+                                }
+                            } else {
+                                if !optional_ty.is_unknown() {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::Mismatch {
+                                            expected: ExpectedTy::Optional,
+                                            found: optional_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(*optional),
+                                        range: self.bodies.range_for_expr(*optional),
+                                        help: None,
+                                    });
+                                }
+
+                                Ty::Error.into()
+                            }
+                        }
+                        Expr::Unary { expr, op } => {
+                            let expr_ty = self.tys[self.file][*expr];
+
+                            if !op.can_perform(&expr_ty) {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::UnaryOpMismatch {
+                                        op: *op,
+                                        ty: expr_ty,
+                                    },
+                                    file: self.file,
+                                    expr: Some(*expr),
+                                    range: self.bodies.range_for_expr(*expr),
+                                    help: None,
+                                });
+
+                                op.default_ty().into()
+                            } else {
+                                let output = op.get_possible_output_ty(expr_ty);
+
+                                self.replace_weak_tys(*expr, output);
+
+                                output
+                            }
+                        }
+                        Expr::Paren(expr) => match expr {
+                            Some(expr) => self.tys[self.file][*expr],
+                            None => Ty::Void.into(),
+                        },
+                        Expr::Block { stmts, tail_expr } => {
+                            let label = self.bodies.block_to_scope_id(expr);
+
+                            let mut no_eval = false;
+
+                            for stmt in stmts {
+                                match &self.bodies[*stmt] {
+                                    Stmt::Break { .. } | Stmt::Continue { .. } => no_eval = true,
+                                    Stmt::Expr(expr)
+                                        if label.is_none()
+                                            && *self.tys[self.file][*expr] == Ty::NoEval =>
+                                    {
+                                        no_eval = true
+                                    }
+                                    _ => {}
+                                }
+                            }
+
+                            let block_ty = match tail_expr {
+                                Some(tail) => {
+                                    let tail_ty = self.tys[self.file][*tail];
+
+                                    // there might've been a break within this block
+                                    // that break would've set the type of this block.
+                                    // there also could've been breaks within the tail expression,
+                                    // so we have to get this here, after we processed the statements and the
+                                    // tail
+                                    let previous_ty =
+                                        self.tys[self.file].expr_tys.get(expr).copied();
+
+                                    match previous_ty {
+                                        Some(previous_ty) => {
+                                            if let Some(max) = self.expect_block_match(
+                                                Some(*tail),
+                                                tail_ty,
+                                                expr,
+                                                previous_ty,
+                                            ) {
+                                                // if there was a previous_ty, then there must've been a break,
+                                                // and so this block must have a scope id
+                                                let id =
+                                                    self.bodies.block_to_scope_id(expr).unwrap();
+                                                for usage in self.bodies.scope_id_usages(id) {
+                                                    if let hir::Stmt::Break {
+                                                        value: Some(value),
+                                                        ..
+                                                    } = self.bodies[*usage]
+                                                    {
+                                                        self.replace_weak_tys(value, max);
+                                                    }
+                                                }
+
+                                                max
+                                            } else {
+                                                Ty::Error.into()
+                                            }
+                                        }
+                                        None => tail_ty,
+                                    }
+                                }
+                                None if no_eval => {
+                                    let previous_ty =
+                                        self.tys[self.file].expr_tys.get(expr).copied();
+
+                                    // if there is no previous type but this block always breaks
+                                    // it is 100% certain that the break was for an upper block.
+                                    // it is then safe to say this block is "noeval"
+                                    // (meaning that it never reaches it's own end)
+                                    previous_ty.unwrap_or_else(|| Ty::NoEval.into())
+                                }
+                                None => {
+                                    // if there were no breaks, Void,
+                                    // if there was a break, make sure it's Void
+                                    if let Some(previous_ty) =
+                                        self.tys[self.file].expr_tys.get(expr).copied()
+                                    {
+                                        if let Some(max) = self.expect_block_match(
+                                            Some(expr),
+                                            Ty::Void.into(),
+                                            expr,
+                                            previous_ty,
+                                        ) {
+                                            max
+                                        } else {
+                                            Ty::Error.into()
+                                        }
+                                    } else {
+                                        Ty::Void.into()
+                                    }
+                                }
+                            };
+
+                            if label.is_some() && self.optional_body_ty(expr).is_none() {
+                                let tail = match tail_expr {
+                                    Some(tail) => Some((*tail, self.tys[self.file][*tail])),
+                                    None if no_eval => None,
+                                    None => Some((expr, Ty::Void.into())),
+                                };
+                                self.report_break_mismatches(expr, tail);
+                            }
+
+                            block_ty
+                        }
+                        Expr::If {
+                            condition,
+                            body,
+                            else_branch,
+                        } => {
+                            let cond_ty = self.tys[self.file][*condition];
+                            self.expect_match(cond_ty, Ty::Bool.into(), *condition);
+
+                            let body_ty = self.tys[self.file][*body];
+
+                            if let Some(else_branch) = else_branch {
+                                let else_ty = self.tys[self.file][*else_branch];
+
+                                if else_ty.is_error_or_unknown() {
+                                    else_ty
+                                } else if let Some(real_ty) = body_ty.max(&else_ty, self.tys) {
+                                    let real_ty = real_ty.into();
+                                    self.replace_weak_tys(*body, real_ty);
+                                    self.replace_weak_tys(*else_branch, real_ty);
+                                    real_ty
+                                } else if let Some(optional_ty) =
+                                    self.coerce_null_branch(*body, body_ty, *else_branch, else_ty)
+                                {
+                                    optional_ty
+                                } else {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::IfMismatch {
+                                            first: body_ty,
+                                            second: else_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
+                                        help: None,
+                                    });
+
+                                    Ty::Error.into()
+                                }
+                            } else {
+                                if *body_ty != Ty::NoEval
+                                    && !body_ty.is_void()
+                                    && !body_ty.is_unknown()
+                                {
+                                    // only get the range if the body isn't unknown
+                                    // otherwise we might be getting the range of something that doesn't exist
+                                    let help_range = match &self.bodies[*body] {
+                                        Expr::Block {
+                                            tail_expr: Some(tail_expr),
+                                            ..
+                                        } => self.bodies.range_for_expr(*tail_expr),
+                                        _ => self.bodies.range_for_expr(*body),
+                                    };
+
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::MissingElse { expected: body_ty },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
+                                        help: Some(TyDiagnosticHelp {
+                                            kind: TyDiagnosticHelpKind::IfReturnsTypeHere {
+                                                found: body_ty,
+                                            },
+                                            range: help_range,
+                                        }),
+                                    });
+                                }
+
+                                if *body_ty == Ty::NoEval {
+                                    // if the body is noeval, but there isn't an else block,
+                                    // it's uncertain whether or not the noeval will actually be
+                                    // reached.
+                                    //
+                                    // tldr; the condition could allow this block to be evaluated
+                                    Ty::Void.into()
+                                } else {
+                                    body_ty
+                                }
+                            }
+                        }
+                        Expr::While { condition, body } => {
+                            let cond_ty = self.tys[self.file][*condition];
+                            self.expect_match(cond_ty, Ty::Bool.into(), *condition);
+
+                            let body_ty = self.tys[self.file][*body];
+                            self.expect_match(body_ty, Ty::Void.into(), *body);
+
+                            // the breaks have already been checked to be void.
+                            // this could still be an error if they weren't
+                            if let Some(previous_ty) = self.tys[self.file].expr_tys.get(expr) {
+                                *previous_ty
+                            } else {
+                                Ty::Void.into()
+                            }
+                        }
+                        Expr::For { iterable, body, .. } => {
+                            let iterable_ty = self.tys[self.file][*iterable];
+
+                            let mut deref_ty = iterable_ty;
+                            while let Some((_, sub_ty)) = deref_ty.as_pointer() {
+                                deref_ty = sub_ty;
+                            }
+
+                            self.default_weak_range(*iterable);
+
+                            if !deref_ty.is_error_or_unknown()
+                                && deref_ty.as_array().is_none()
+                                && deref_ty.as_slice().is_none()
+                                && deref_ty.as_range().is_none()
+                            {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::NotIterable { found: iterable_ty },
+                                    file: self.file,
+                                    expr: Some(*iterable),
+                                    range: self.bodies.range_for_expr(*iterable),
+                                    help: None,
+                                });
+                            }
+
+                            let body_ty = self.tys[self.file][*body];
+                            self.expect_match(body_ty, Ty::Void.into(), *body);
+
+                            // just like `while`, the breaks have already been checked to be void
+                            if let Some(previous_ty) = self.tys[self.file].expr_tys.get(expr) {
+                                *previous_ty
+                            } else {
+                                Ty::Void.into()
+                            }
+                        }
+                        Expr::Loop { body } => {
+                            let body_ty = self.tys[self.file][*body];
+                            self.expect_match(body_ty, Ty::Void.into(), *body);
+
+                            self.report_break_mismatches(expr, None);
+
+                            // a loop can only be exited by breaking out of it,
+                            // so a loop without any breaks never finishes
+                            if let Some(previous_ty) = self.tys[self.file].expr_tys.get(expr) {
+                                *previous_ty
+                            } else {
+                                Ty::NoEval.into()
+                            }
+                        }
+                        Expr::Switch {
+                            scrutinee,
+                            arms,
+                            default,
+                            ..
+                        } => 'switch: {
+                            // `switch` auto-derefs the same way member access does
+                            let mut scrutinee_ty = self.tys[self.file][*scrutinee];
+                            while let Some((_, sub_ty)) = scrutinee_ty.as_pointer() {
+                                scrutinee_ty = sub_ty;
+                            }
+
+                            // switching sees through distinct enums, just like indexing sees
+                            // through distinct integers
+                            let Ty::Enum { variants, .. } =
+                                scrutinee_ty.absolute_ty_keep_variants()
+                            else {
+                                if !scrutinee_ty.is_unknown() {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::Mismatch {
+                                            expected: ExpectedTy::Enum,
+                                            found: scrutinee_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(*scrutinee),
+                                        range: self.bodies.range_for_expr(*scrutinee),
+                                        help: None,
+                                    });
+                                }
+                                break 'switch Ty::Error.into();
+                            };
+
+                            struct VariantToCheck {
+                                variant_ty: Intern<Ty>,
+                                included_in_switch: bool,
+                            }
+
+                            // this is an index map because later errors are reported while looping
+                            // over this map
+                            let mut variants: IndexMap<_, _> = variants
+                                .iter()
+                                .map(|v| {
+                                    let Ty::Variant { variant_name, .. } = v.as_ref() else {
+                                        unreachable!("all variants should be `Ty::Variant`")
+                                    };
+
+                                    (
+                                        *variant_name,
+                                        VariantToCheck {
+                                            variant_ty: *v,
+                                            included_in_switch: false,
+                                        },
+                                    )
+                                })
+                                .collect();
+
+                            let mut first_arm_ty = None;
+
+                            for arm in arms {
+                                let Some(variant_name) = arm.variant_name else {
+                                    continue;
+                                };
+
+                                let Some(variant) = variants.get_mut(&variant_name.name) else {
+                                    // todo: use NonExistantVariant for more things
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::NonExistentVariant {
+                                            variant_name: variant_name.name.0,
+                                            enum_ty: scrutinee_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(arm.body),
+                                        range: self.bodies.range_for_expr(arm.body),
+                                        help: None,
+                                    });
+                                    continue;
+                                };
+
+                                if variant.included_in_switch {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::SwitchAlreadyCoversVariant {
+                                            ty: variant.variant_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: variant_name.range,
+                                        help: None, // todo: show the previous arm
+                                    });
+                                } else {
+                                    // later, if any variants haven't been covered an error will be
+                                    // reported.
+                                    variant.included_in_switch = true;
+                                }
+
+                                let found_arm_ty = self.tys[self.file][arm.body];
+
+                                match first_arm_ty {
+                                    None => {
+                                        first_arm_ty = Some(found_arm_ty);
+                                    }
+                                    Some(first_ty) if first_ty.is_error_or_unknown() => {}
+                                    Some(first_ty) => {
+                                        if let Some(real_ty) = first_ty.max(&found_arm_ty, self.tys)
+                                        {
+                                            let real_ty = real_ty.into();
+                                            first_arm_ty = Some(real_ty);
+                                        } else {
+                                            self.diagnostics.push(TyDiagnostic {
+                                                kind: TyDiagnosticKind::SwitchMismatch {
+                                                    second: found_arm_ty,
+                                                    first: first_ty,
+                                                },
+                                                file: self.file,
+                                                expr: Some(arm.body),
+                                                range: self.bodies.range_for_expr(arm.body),
+                                                help: None,
+                                            });
+
+                                            first_arm_ty = Some(Ty::Error.into());
+                                        }
+                                    }
+                                }
+                            }
+
+                            if let Some(default) = default {
+                                if variants.values().all(|v| v.included_in_switch) {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::UnreachableDefault,
+                                        file: self.file,
+                                        expr: Some(default.body),
+                                        range: default.variant_name.map_or_else(
+                                            || self.bodies.range_for_expr(default.body),
+                                            |name| name.range,
+                                        ),
+                                        help: None,
+                                    });
+                                }
+
+                                let default_ty = self.tys[self.file][default.body];
+
+                                match first_arm_ty {
+                                    None => {
+                                        first_arm_ty = Some(default_ty);
+                                    }
+                                    Some(first_ty) if first_ty.is_error_or_unknown() => {}
+                                    Some(first_ty) => {
+                                        if let Some(real_ty) = first_ty.max(&default_ty, self.tys) {
+                                            let real_ty = real_ty.into();
+                                            self.replace_weak_tys(default.body, real_ty);
+                                            first_arm_ty = Some(real_ty);
+                                        } else {
+                                            self.diagnostics.push(TyDiagnostic {
+                                                kind: TyDiagnosticKind::SwitchMismatch {
+                                                    second: default_ty,
+                                                    first: first_ty,
+                                                },
+                                                file: self.file,
+                                                expr: Some(default.body),
+                                                range: self.bodies.range_for_expr(default.body),
+                                                help: None,
+                                            });
+
+                                            first_arm_ty = Some(Ty::Error.into());
+                                        }
+                                    }
+                                }
+                            } else {
+                                for VariantToCheck {
+                                    variant_ty,
+                                    included_in_switch,
+                                } in variants.values()
+                                {
+                                    if *included_in_switch {
+                                        continue;
+                                    }
+
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::SwitchDoesNotCoverVariant {
+                                            ty: *variant_ty,
                                         },
                                         file: self.file,
-                                        expr: Some(expr),
                                         range: self.bodies.range_for_expr(expr),
+                                        expr: Some(expr),
                                         help: None,
                                     });
                                 }
+                            }
 
-                                let max_ty = output_ty.max_ty.into();
+                            if let Some(first_arm_ty) =
+                                first_arm_ty.filter(|t| !t.is_error_or_unknown())
+                            {
+                                for arm in arms {
+                                    self.replace_weak_tys(arm.body, first_arm_ty);
+                                }
+                            }
 
-                                self.replace_weak_tys(*lhs, max_ty);
-                                self.replace_weak_tys(*rhs, max_ty);
+                            first_arm_ty.unwrap_or_else(|| Ty::Void.into())
+                        }
+                        Expr::Local(local) => self.tys[self.file].local_tys[*local],
+                        Expr::SwitchLocal(switch_local) => 'switch_local: {
+                            if let Some(ty) =
+                                self.tys[self.file].switch_local_tys.get(*switch_local)
+                            {
+                                break 'switch_local *ty;
+                            }
 
-                                output_ty.final_output_ty.into()
+                            let switch_local_body = &self.bodies[*switch_local];
+                            let Some(this_variant_name) = switch_local_body.variant_name else {
+                                break 'switch_local Ty::Error.into();
+                            };
+
+                            let mut scrutinee_ty = self.tys[self.file][switch_local_body.scrutinee];
+                            while let Some((_, sub_ty)) = scrutinee_ty.as_pointer() {
+                                scrutinee_ty = sub_ty;
+                            }
+
+                            if switch_local_body.default {
+                                // default branches just receive the (dereferenced) scrutinee as-is
+                                break 'switch_local scrutinee_ty;
+                            }
+
+                            let Ty::Enum { variants, .. } =
+                                scrutinee_ty.absolute_ty_keep_variants()
+                            else {
+                                break 'switch_local Ty::Error.into();
+                            };
+
+                            let variant_ty = variants
+                                .iter()
+                                .find(|v| {
+                                    let Ty::Variant { variant_name, .. } = v.as_ref() else {
+                                        unreachable!("all variants should be `Ty::Variant`")
+                                    };
+
+                                    *variant_name == this_variant_name.name
+                                })
+                                .copied()
+                                .unwrap_or_else(|| Ty::Error.into());
+
+                            self.tys[self.file]
+                                .switch_local_tys
+                                .insert(*switch_local, variant_ty);
+
+                            variant_ty
+                        }
+                        Expr::ForLocal(for_local) => {
+                            if let Some(ty) = self.tys[self.file].for_local_tys.get(*for_local) {
+                                *ty
                             } else {
+                                let mut iterable_ty =
+                                    self.default_weak_range(self.bodies[*for_local].iterable);
+                                while let Some((_, sub_ty)) = iterable_ty.as_pointer() {
+                                    iterable_ty = sub_ty;
+                                }
+
+                                // `Expr::For` reports an error for anything that isn't iterable
+                                let item_ty = iterable_ty
+                                    .as_array()
+                                    .map(|(_, sub_ty)| sub_ty)
+                                    .or_else(|| iterable_ty.as_slice())
+                                    .or_else(|| iterable_ty.as_range().map(|(_, sub_ty)| sub_ty))
+                                    .unwrap_or_else(|| Ty::Error.into());
+
+                                self.tys[self.file]
+                                    .for_local_tys
+                                    .insert(*for_local, item_ty);
+
+                                item_ty
+                            }
+                        }
+                        Expr::Param { idx, .. } => self.param_tys[*idx as usize].ty,
+                        // the value was inferred along with the lambda
+                        Expr::Captured(capture) => self.tys[self.file][self.bodies[*capture].value],
+                        Expr::LocalGlobal(name) => 'local_global: {
+                            let fqn = self.world_bodies.resolve_global(self.file, name.name);
+
+                            if !self.world_bodies.exists(fqn) {
+                                // only names from `using`d files are left unresolved by lowering
                                 self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::BinaryOpMismatch {
-                                        op: *op,
-                                        first: lhs_ty,
-                                        second: rhs_ty,
-                                    },
+                                    kind: TyDiagnosticKind::UnknownFqn { fqn },
                                     file: self.file,
                                     expr: Some(expr),
-                                    range: self.bodies.range_for_expr(expr),
+                                    range: name.range,
                                     help: None,
                                 });
 
-                                op.default_ty().into()
+                                break 'local_global Ty::Error.into();
                             }
-                        }
-                        Expr::Unary { expr, op } => {
-                            let expr_ty = self.tys[self.file][*expr];
 
-                            if !op.can_perform(&expr_ty) {
+                            let sig = *self
+                                .tys
+                                .signatures
+                                .get(&fqn)
+                                .ok_or_else(|| vec![Inferrable::Global(fqn)])?;
+
+                            self.check_deprecated(fqn, expr, name.range);
+
+                            if *sig.0 == Ty::NotYetResolved {
                                 self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::UnaryOpMismatch {
-                                        op: *op,
-                                        ty: expr_ty,
-                                    },
+                                    kind: TyDiagnosticKind::NotYetResolved { fqn },
                                     file: self.file,
-                                    expr: Some(*expr),
-                                    range: self.bodies.range_for_expr(*expr),
+                                    expr: Some(expr),
+                                    range: self.bodies.range_for_expr(expr),
                                     help: None,
                                 });
 
-                                op.default_ty().into()
+                                Ty::Error.into()
                             } else {
-                                let output = op.get_possible_output_ty(expr_ty);
-
-                                self.replace_weak_tys(*expr, output);
-
-                                output
+                                sig.0
                             }
                         }
-                        Expr::Paren(expr) => match expr {
-                            Some(expr) => self.tys[self.file][*expr],
-                            None => Ty::Void.into(),
-                        },
-                        Expr::Block { stmts, tail_expr } => {
-                            let label = self.bodies.block_to_scope_id(expr);
-
-                            let mut no_eval = false;
-
-                            for stmt in stmts {
-                                match &self.bodies[*stmt] {
-                                    Stmt::Break { .. } | Stmt::Continue { .. } => no_eval = true,
-                                    Stmt::Expr(expr)
-                                        if label.is_none()
-                                            && *self.tys[self.file][*expr] == Ty::NoEval =>
-                                    {
-                                        no_eval = true
-                                    }
-                                    _ => {}
-                                }
-                            }
-
-                            match tail_expr {
-                                Some(tail) => {
-                                    let tail_ty = self.tys[self.file][*tail];
-
-                                    // there might've been a break within this block
-                                    // that break would've set the type of this block.
-                                    // there also could've been breaks within the tail expression,
-                                    // so we have to get this here, after we processed the statements and the
-                                    // tail
-                                    let previous_ty =
-                                        self.tys[self.file].expr_tys.get(expr).copied();
-
-                                    match previous_ty {
-                                        Some(previous_ty) => {
-                                            if let Some(max) = self.expect_block_match(
-                                                Some(*tail),
-                                                tail_ty,
-                                                expr,
-                                                previous_ty,
-                                            ) {
-                                                // if there was a previous_ty, then there must've been a break,
-                                                // and so this block must have a scope id
-                                                let id =
-                                                    self.bodies.block_to_scope_id(expr).unwrap();
-                                                for usage in self.bodies.scope_id_usages(id) {
-                                                    if let hir::Stmt::Break {
-                                                        value: Some(value),
-                                                        ..
-                                                    } = self.bodies[*usage]
-                                                    {
-                                                        self.replace_weak_tys(value, max);
-                                                    }
-                                                }
+                        Expr::Member {
+                            previous,
+                            name: field,
+                        } => {
+                            let previous_ty = self.tys[self.file][*previous];
+                            match previous_ty.as_ref() {
+                                Ty::File(file) => {
+                                    let fqn = hir::Fqn {
+                                        file: *file,
+                                        name: field.name,
+                                    };
 
-                                                max
-                                            } else {
-                                                Ty::Unknown.into()
-                                            }
+                                    match self.world_index.definition(fqn) {
+                                        hir::DefinitionStatus::Defined => {
+                                            let sig = self.global_signature(fqn, expr)?;
+                                            self.check_deprecated(fqn, expr, field.range);
+                                            sig
                                         }
-                                        None => tail_ty,
-                                    }
-                                }
-                                None if no_eval => {
-                                    let previous_ty =
-                                        self.tys[self.file].expr_tys.get(expr).copied();
-
-                                    // if there is no previous type but this block always breaks
-                                    // it is 100% certain that the break was for an upper block.
-                                    // it is then safe to say this block is "noeval"
-                                    // (meaning that it never reaches it's own end)
-                                    previous_ty.unwrap_or_else(|| Ty::NoEval.into())
+                                        hir::DefinitionStatus::UnknownFile => {
+                                            unreachable!("a module wasn't added: {:?}", file)
+                                        }
+                                        hir::DefinitionStatus::UnknownDefinition => {
+                                            self.diagnostics.push(TyDiagnostic {
+                                                kind: TyDiagnosticKind::UnknownFqn { fqn },
+                                                file: self.file,
+                                                expr: Some(expr),
+                                                range: self.bodies.range_for_expr(expr),
+                                                help: None,
+                                            });
+
+                                            Ty::Error.into()
+                                        }
+                                    }
                                 }
-                                None => {
-                                    // if there were no breaks, Void,
-                                    // if there was a break, make sure it's Void
-                                    if let Some(previous_ty) =
-                                        self.tys[self.file].expr_tys.get(expr).copied()
+                                Ty::Type => {
+                                    let previous_meta_ty = self.const_ty(*previous)?;
+
+                                    if let Some(fqn) =
+                                        self.namespaced_global(previous_meta_ty, field.name)
                                     {
-                                        if let Some(max) = self.expect_block_match(
-                                            Some(expr),
-                                            Ty::Void.into(),
-                                            expr,
-                                            previous_ty,
-                                        ) {
-                                            max
-                                        } else {
-                                            Ty::Unknown.into()
-                                        }
+                                        self.tys[self.file].namespaced_members.insert(expr, fqn);
+                                        let sig = self.global_signature(fqn, expr)?;
+                                        self.check_deprecated(fqn, expr, field.range);
+                                        sig
                                     } else {
-                                        Ty::Void.into()
+                                        // this is included for resolving enum variants
+                                        self.const_ty(expr)?;
+                                        Ty::Type.into()
                                     }
                                 }
-                            }
-                        }
-                        Expr::If {
-                            condition,
-                            body,
-                            else_branch,
-                        } => {
-                            let cond_ty = self.tys[self.file][*condition];
-                            self.expect_match(cond_ty, Ty::Bool.into(), *condition);
+                                _ => {
+                                    // because it's annoying to do `foo^.bar`, this code lets you do `foo.bar`
+                                    let mut deref_ty = previous_ty;
+                                    while let Some((_, sub_ty)) = deref_ty.as_pointer() {
+                                        deref_ty = sub_ty;
+                                    }
+                                    deref_ty = deref_ty.absolute_intern_ty(true);
 
-                            let body_ty = self.tys[self.file][*body];
+                                    let field_name = self.interner.lookup(field.name.0);
 
-                            if let Some(else_branch) = else_branch {
-                                let else_ty = self.tys[self.file][*else_branch];
+                                    match (deref_ty.as_ref(), field_name) {
+                                        (Ty::Struct { members, .. }, _) => {
+                                            if let Some(matching_member) = members
+                                                .iter()
+                                                .find(|member_ty| member_ty.name == field.name)
+                                            {
+                                                matching_member.ty
+                                            } else if let Some(fqn) = self
+                                                .namespaced_global(deref_ty, field.name)
+                                                .filter(|fqn| {
+                                                    // only functions can be called through a value
+                                                    matches!(
+                                                        self.world_bodies[fqn.file]
+                                                            [self.world_bodies.body(*fqn)],
+                                                        Expr::Lambda(_)
+                                                    )
+                                                })
+                                            {
+                                                self.tys[self.file]
+                                                    .namespaced_members
+                                                    .insert(expr, fqn);
+                                                self.global_signature(fqn, expr)?
+                                            } else if let Some(method_ty) =
+                                                self.free_method(expr, previous_ty, field.name)?
+                                            {
+                                                method_ty
+                                            } else {
+                                                if !previous_ty.is_unknown() {
+                                                    self.diagnostics.push(TyDiagnostic {
+                                                        kind: TyDiagnosticKind::NonExistentMember {
+                                                            member: field.name.0,
+                                                            found_ty: previous_ty,
+                                                        },
+                                                        file: self.file,
+                                                        expr: Some(expr),
+                                                        range: self.bodies.range_for_expr(expr),
+                                                        help: None,
+                                                    });
+                                                }
 
-                                if *else_ty == Ty::Unknown {
-                                    else_ty
-                                } else if let Some(real_ty) = body_ty.max(&else_ty) {
-                                    let real_ty = real_ty.into();
-                                    self.replace_weak_tys(*body, real_ty);
-                                    self.replace_weak_tys(*else_branch, real_ty);
-                                    real_ty
-                                } else {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::IfMismatch {
-                                            first: body_ty,
-                                            second: else_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(expr),
-                                        help: None,
-                                    });
+                                                Ty::Error.into()
+                                            }
+                                        }
+                                        (Ty::Tuple { items }, _)
+                                            if tuple_index(field_name)
+                                                .is_some_and(|idx| idx < items.len()) =>
+                                        {
+                                            items[tuple_index(field_name).unwrap()]
+                                        }
+                                        (Ty::Slice { .. }, "len") => Ty::UInt(u8::MAX).into(),
+                                        (Ty::Slice { sub_ty }, "ptr") => Ty::Pointer {
+                                            mutable: false,
+                                            sub_ty: *sub_ty,
+                                        }
+                                        .into(),
+                                        (Ty::RawSlice, "len") => Ty::UInt(u8::MAX).into(),
+                                        (Ty::RawSlice, "ptr") => {
+                                            Ty::RawPtr { mutable: false }.into()
+                                        }
+                                        (Ty::Any, "ty") => Ty::Type.into(),
+                                        (Ty::Any, "ptr") => Ty::RawPtr { mutable: false }.into(),
+                                        (Ty::Array { .. }, "len") => Ty::UInt(u8::MAX).into(),
+                                        // the method doesn't include the pointer to the value,
+                                        // which gets passed along when it's called
+                                        (Ty::Interface { methods, .. }, _)
+                                            if methods
+                                                .iter()
+                                                .any(|method| method.name == field.name) =>
+                                        {
+                                            methods
+                                                .iter()
+                                                .find(|method| method.name == field.name)
+                                                .unwrap()
+                                                .ty
+                                        }
+                                        _ => 'no_member: {
+                                            if let Some(method_ty) =
+                                                self.free_method(expr, previous_ty, field.name)?
+                                            {
+                                                break 'no_member method_ty;
+                                            }
 
-                                    Ty::Unknown.into()
-                                }
-                            } else {
-                                if *body_ty != Ty::NoEval
-                                    && !body_ty.is_void()
-                                    && !body_ty.is_unknown()
-                                {
-                                    // only get the range if the body isn't unknown
-                                    // otherwise we might be getting the range of something that doesn't exist
-                                    let help_range = match &self.bodies[*body] {
-                                        Expr::Block {
-                                            tail_expr: Some(tail_expr),
-                                            ..
-                                        } => self.bodies.range_for_expr(*tail_expr),
-                                        _ => self.bodies.range_for_expr(*body),
-                                    };
+                                            // `points.x` is most likely a mistake for `points[i].x`
+                                            let element_ty = deref_ty
+                                                .as_array()
+                                                .map(|(_, sub_ty)| sub_ty)
+                                                .or_else(|| deref_ty.as_slice())
+                                                .filter(|sub_ty| {
+                                                    sub_ty.as_struct().is_some_and(|members| {
+                                                        members
+                                                            .iter()
+                                                            .any(|member| member.name == field.name)
+                                                    })
+                                                });
 
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::MissingElse { expected: body_ty },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(expr),
-                                        help: Some(TyDiagnosticHelp {
-                                            kind: TyDiagnosticHelpKind::IfReturnsTypeHere {
-                                                found: body_ty,
-                                            },
-                                            range: help_range,
-                                        }),
-                                    });
-                                }
+                                            if let Some(element_ty) = element_ty {
+                                                self.diagnostics.push(TyDiagnostic {
+                                                    kind: TyDiagnosticKind::MemberOfCollection {
+                                                        member: field.name.0,
+                                                        collection_ty: previous_ty,
+                                                        element_ty,
+                                                    },
+                                                    file: self.file,
+                                                    expr: Some(expr),
+                                                    range: self.bodies.range_for_expr(expr),
+                                                    help: Some(TyDiagnosticHelp {
+                                                        kind: TyDiagnosticHelpKind::IndexCollectionFirst {
+                                                            element_ty,
+                                                        },
+                                                        range: self.bodies.range_for_expr(*previous),
+                                                    }),
+                                                });
+                                            } else if !previous_ty.is_unknown() {
+                                                self.diagnostics.push(TyDiagnostic {
+                                                    kind: TyDiagnosticKind::NonExistentMember {
+                                                        member: field.name.0,
+                                                        found_ty: previous_ty,
+                                                    },
+                                                    file: self.file,
+                                                    expr: Some(expr),
+                                                    range: self.bodies.range_for_expr(expr),
+                                                    help: None,
+                                                });
+                                            }
 
-                                if *body_ty == Ty::NoEval {
-                                    // if the body is noeval, but there isn't an else block,
-                                    // it's uncertain whether or not the noeval will actually be
-                                    // reached.
-                                    //
-                                    // tldr; the condition could allow this block to be evaluated
-                                    Ty::Void.into()
-                                } else {
-                                    body_ty
+                                            Ty::Error.into()
+                                        }
+                                    }
                                 }
                             }
                         }
-                        Expr::While { condition, body } => {
-                            if let Some(condition) = condition {
-                                let cond_ty = self.tys[self.file][*condition];
-                                self.expect_match(cond_ty, Ty::Bool.into(), *condition);
-                            }
-                            let body_ty = self.tys[self.file][*body];
-                            self.expect_match(body_ty, Ty::Void.into(), *body);
-
-                            if let Some(previous_ty) = self.tys[self.file].expr_tys.get(expr) {
-                                *previous_ty
-                            } else {
-                                Ty::Void.into()
-                            }
-                        }
-                        Expr::Switch {
-                            scrutinee,
-                            arms,
-                            default,
-                            ..
-                        } => 'switch: {
-                            let scrutinee_ty = self.tys[self.file][*scrutinee];
+                        Expr::Call { callee, args } => {
+                            let callee_ty = match self.resolve_overload(expr, *callee, args)? {
+                                Some(overload_ty) => overload_ty,
+                                None => match self.resolve_instance(*callee, args)? {
+                                    Some(instance_ty) => instance_ty,
+                                    None => self.tys[self.file][*callee],
+                                },
+                            };
 
-                            let Ty::Enum { variants, .. } = scrutinee_ty.as_ref() else {
-                                break 'switch Ty::Unknown.into();
+                            // `p.len()` passes `p` as the first argument to `Point.len`
+                            let receiver = match &self.bodies[*callee] {
+                                Expr::Member { previous, .. }
+                                    if self.tys[self.file]
+                                        .get_namespaced_member(*callee)
+                                        .is_some()
+                                        && *self.tys[self.file][*previous] != Ty::Type =>
+                                {
+                                    Some(*previous)
+                                }
+                                _ => None,
                             };
 
-                            struct VariantToCheck {
-                                variant_ty: Intern<Ty>,
-                                included_in_switch: bool,
-                            }
+                            if let Some((params, return_ty)) = callee_ty.clone().as_callable() {
+                                let mut params_iter = params.iter();
+                                let mut args_iter = args.iter();
 
-                            // this is an index map because later errors are reported while looping
-                            // over this map
-                            let mut variants: IndexMap<_, _> = variants
-                                .iter()
-                                .map(|v| {
-                                    let Ty::Variant { variant_name, .. } = v.as_ref() else {
-                                        unreachable!("all variants should be `Ty::Variant`")
-                                    };
+                                if let Some(receiver) = receiver {
+                                    let receiver_ty = self.tys[self.file][receiver];
 
-                                    (
-                                        *variant_name,
-                                        VariantToCheck {
-                                            variant_ty: *v,
-                                            included_in_switch: false,
-                                        },
-                                    )
-                                })
-                                .collect();
+                                    if let Some(self_param) = params_iter.next() {
+                                        self.expect_match(receiver_ty, self_param.ty, receiver);
+                                    } else {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::ExtraArg { found: receiver_ty },
+                                            file: self.file,
+                                            expr: Some(receiver),
+                                            range: self.bodies.range_for_expr(receiver),
+                                            help: None,
+                                        });
+                                    }
+                                }
 
-                            let mut first_arm_ty = None;
+                                let mut current_param = params_iter.next();
+                                let mut current_arg = args_iter.next();
 
-                            for arm in arms {
-                                let Some(variant_name) = arm.variant_name else {
-                                    continue;
-                                };
+                                loop {
+                                    let Some(arg) = current_arg else {
+                                        if let Some(param) = current_param {
+                                            // there are more params than args
 
-                                let Some(variant) = variants.get_mut(&variant_name.name) else {
-                                    // todo: use NonExistantVariant for more things
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::NonExistentVariant {
-                                            variant_name: variant_name.name.0,
-                                            enum_ty: scrutinee_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(arm.body),
-                                        range: self.bodies.range_for_expr(arm.body),
-                                        help: None,
-                                    });
-                                    continue;
-                                };
+                                            if param.varargs {
+                                                current_param = params_iter.next();
+                                                continue; // continue without reporting error
+                                            }
 
-                                if variant.included_in_switch {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::SwitchAlreadyCoversVariant {
-                                            ty: variant.variant_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: variant_name.range,
-                                        help: None, // todo: show the previous arm
-                                    });
-                                } else {
-                                    // later, if any variants haven't been covered an error will be
-                                    // reported.
-                                    variant.included_in_switch = true;
-                                }
+                                            let param_ty = param.ty;
 
-                                let found_arm_ty = self.tys[self.file][arm.body];
+                                            let call_range = self.bodies.range_for_expr(expr);
+                                            let call_end = call_range
+                                                .end()
+                                                .checked_sub(TextSize::new(1))
+                                                .unwrap_or(call_range.end());
 
-                                match first_arm_ty {
-                                    None => {
-                                        first_arm_ty = Some(found_arm_ty);
-                                    }
-                                    Some(first_ty) if *first_ty == Ty::Unknown => {}
-                                    Some(first_ty) => {
-                                        if let Some(real_ty) = first_ty.max(&found_arm_ty) {
-                                            let real_ty = real_ty.into();
-                                            first_arm_ty = Some(real_ty);
+                                            // TODO: add tests for this not being an error
+                                            if !param.impossible_to_differentiate
+                                                && !param_ty.is_error_or_unknown()
+                                            {
+                                                self.diagnostics.push(TyDiagnostic {
+                                                    kind: TyDiagnosticKind::MissingArg {
+                                                        expected: ExpectedTy::Concrete(param_ty),
+                                                    },
+                                                    file: self.file,
+                                                    expr: Some(expr),
+                                                    range: TextRange::new(call_end, call_end),
+                                                    help: None,
+                                                });
+                                            }
                                         } else {
-                                            self.diagnostics.push(TyDiagnostic {
-                                                kind: TyDiagnosticKind::SwitchMismatch {
-                                                    second: found_arm_ty,
-                                                    first: first_ty,
-                                                },
-                                                file: self.file,
-                                                expr: Some(arm.body),
-                                                range: self.bodies.range_for_expr(arm.body),
-                                                help: None,
-                                            });
-
-                                            first_arm_ty = Some(Ty::Unknown.into());
+                                            break;
                                         }
-                                    }
-                                }
-                            }
+                                        current_param = params_iter.next();
+                                        continue;
+                                    };
+                                    let arg_ty = self.tys[self.file][*arg];
 
-                            if let Some(default) = default {
-                                let default_ty = self.tys[self.file][default.body];
+                                    let Some(param) = current_param else {
+                                        // there are more args than params
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::ExtraArg { found: arg_ty },
+                                            file: self.file,
+                                            expr: Some(*arg),
+                                            range: self.bodies.range_for_expr(*arg),
+                                            help: None,
+                                        });
+                                        current_arg = args_iter.next();
+                                        continue;
+                                    };
 
-                                match first_arm_ty {
-                                    None => {
-                                        first_arm_ty = Some(default_ty);
-                                    }
-                                    Some(first_ty) if *first_ty == Ty::Unknown => {}
-                                    Some(first_ty) => {
-                                        if let Some(real_ty) = first_ty.max(&default_ty) {
-                                            let real_ty = real_ty.into();
-                                            self.replace_weak_tys(default.body, real_ty);
-                                            first_arm_ty = Some(real_ty);
+                                    if param.varargs {
+                                        let actual_sub_ty = param.ty.as_slice().unwrap();
+
+                                        if arg_ty.can_fit_into(&actual_sub_ty) {
+                                            self.replace_weak_tys(*arg, actual_sub_ty);
+
+                                            current_arg = args_iter.next();
+                                        } else if let Some(next_param) = params_iter.next() {
+                                            // go to the next param but don't go to the next arg.
+                                            // this basically just reevaluates the current argument
+                                            // under the next parameter.
+                                            current_param = Some(next_param);
                                         } else {
+                                            // `can_fit_into` should return true for unknowns
+                                            assert!(!arg_ty.is_unknown());
+                                            // this will just return an error
                                             self.diagnostics.push(TyDiagnostic {
-                                                kind: TyDiagnosticKind::SwitchMismatch {
-                                                    second: default_ty,
-                                                    first: first_ty,
+                                                kind: TyDiagnosticKind::Mismatch {
+                                                    expected: ExpectedTy::Concrete(actual_sub_ty),
+                                                    found: arg_ty,
                                                 },
                                                 file: self.file,
-                                                expr: Some(default.body),
-                                                range: self.bodies.range_for_expr(default.body),
+                                                expr: Some(*arg),
+                                                range: self.bodies.range_for_expr(*arg),
                                                 help: None,
                                             });
-
-                                            first_arm_ty = Some(Ty::Unknown.into());
+                                            current_arg = args_iter.next();
                                         }
+                                    } else {
+                                        self.expect_match(arg_ty, param.ty, *arg);
+
+                                        self.replace_weak_tys(*arg, param.ty);
+
+                                        current_param = params_iter.next();
+                                        current_arg = args_iter.next();
                                     }
                                 }
-                            } else {
-                                for VariantToCheck {
-                                    variant_ty,
-                                    included_in_switch,
-                                } in variants.values()
-                                {
-                                    if *included_in_switch {
-                                        continue;
-                                    }
 
+                                // an argument that exits early means the call itself never happens
+                                if args
+                                    .iter()
+                                    .any(|arg| *self.tys[self.file][*arg] == Ty::NoEval)
+                                {
+                                    Ty::NoEval.into()
+                                } else {
+                                    return_ty
+                                }
+                            } else if *callee_ty == Ty::Type
+                                && matches!(self.const_ty(*callee)?.as_ref(), Ty::Variant { .. })
+                            {
+                                let variant_ty = self.const_ty(*callee)?;
+                                self.construct_variant(expr, variant_ty, args)
+                            } else if *callee_ty == Ty::NoEval {
+                                Ty::NoEval.into()
+                            } else {
+                                if !callee_ty.is_error_or_unknown() {
                                     self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::SwitchDoesNotCoverVariant {
-                                            ty: *variant_ty,
+                                        kind: TyDiagnosticKind::CalledNonFunction {
+                                            found: callee_ty,
                                         },
                                         file: self.file,
-                                        range: self.bodies.range_for_expr(expr),
                                         expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
                                         help: None,
                                     });
                                 }
+
+                                Ty::Error.into()
                             }
+                        }
+                        Expr::Lambda(lambda) => {
+                            let hir::Lambda {
+                                params,
+                                return_ty,
+                                body,
+                                is_extern,
+                                ..
+                            } = &self.bodies[*lambda];
 
-                            if let Some(first_arm_ty) = first_arm_ty.filter(|t| **t != Ty::Unknown)
-                            {
-                                for arm in arms {
-                                    self.replace_weak_tys(arm.body, first_arm_ty);
+                            let is_type = !is_extern
+                                && return_ty.is_some()
+                                && self.bodies[*body] == hir::Expr::Missing;
+
+                            let has_const_params = params.iter().any(|param| param.is_const);
+                            let is_instance = self
+                                .const_params
+                                .as_ref()
+                                .is_some_and(|(current, _)| current == lambda);
+
+                            let ty = if has_const_params {
+                                // the types of the parameters can refer to the parameters before
+                                // them, which only have a value within an instance
+                                let old_param_tys = mem::take(&mut self.param_tys);
+                                let old_const_params = (!is_instance).then(|| {
+                                    self.const_params
+                                        .replace((*lambda, vec![None; params.len()]))
+                                });
+
+                                let ty = self.lambda_signature(expr, *lambda, true);
+
+                                self.param_tys = old_param_tys;
+                                if let Some(old_const_params) = old_const_params {
+                                    self.const_params = old_const_params;
                                 }
-                            }
 
-                            first_arm_ty.unwrap_or_else(|| Ty::Void.into())
-                        }
-                        Expr::Local(local) => self.tys[self.file].local_tys[*local],
-                        Expr::SwitchLocal(switch_local) => 'switch_local: {
-                            if let Some(ty) =
-                                self.tys[self.file].switch_local_tys.get(*switch_local)
-                            {
-                                break 'switch_local *ty;
-                            }
+                                ty?
+                            } else {
+                                self.lambda_signature(expr, *lambda, false)?
+                            };
 
-                            let switch_local_body = &self.bodies[*switch_local];
-                            let Some(this_variant_name) = switch_local_body.variant_name else {
-                                break 'switch_local Ty::Unknown.into();
+                            // a lambda which captured anything has to carry those values with it
+                            let ty = match ty.as_function() {
+                                Some((param_tys, return_ty))
+                                    if !self.bodies[*lambda].captures.is_empty() =>
+                                {
+                                    Ty::Closure {
+                                        param_tys,
+                                        return_ty,
+                                    }
+                                    .into()
+                                }
+                                _ => ty,
                             };
 
-                            let scrutinee_ty = self.tys[self.file][switch_local_body.scrutinee];
+                            if is_type {
+                                self.tys[self.file].meta_tys.insert(expr, ty);
+
+                                Ty::Type.into()
+                            } else if self.const_params.is_some() && !is_instance {
+                                // `LambdaInConstParamFn` was already reported when the function
+                                // with compile-time parameters was first inferred
+                                Ty::Error.into()
+                            } else if has_const_params {
+                                // the body gets inferred separately for each instance
+                                if !is_instance {
+                                    self.check_const_param_fn(expr, *lambda);
+                                }
 
-                            if switch_local_body.default {
-                                // default branches just receive the scrutinee as-is
-                                break 'switch_local scrutinee_ty;
+                                ty
+                            } else {
+                                self.to_infer.insert(Inferrable::Lambda(FQLambda {
+                                    file: self.file,
+                                    expr,
+                                    lambda: *lambda,
+                                }));
+
+                                ty
                             }
+                        }
+                        Expr::Comptime(comptime) => {
+                            let hir::Comptime { body } = self.bodies[*comptime];
 
-                            let Ty::Enum { variants, .. } = scrutinee_ty.as_ref() else {
-                                break 'switch_local Ty::Unknown.into();
-                            };
+                            let ty = self.tys[self.file][body];
 
-                            let variant_ty = variants
-                                .iter()
-                                .find(|v| {
-                                    let Ty::Variant { variant_name, .. } = v.as_ref() else {
-                                        unreachable!("all variants should be `Ty::Variant`")
-                                    };
+                            if ty.is_pointer() || ty.is_function() {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::ComptimePointer,
+                                    file: self.file,
+                                    expr: Some(expr),
+                                    range: self.bodies.range_for_expr(expr),
+                                    help: None,
+                                });
 
-                                    *variant_name == this_variant_name.name
-                                })
+                                Ty::Error.into()
+                            } else if *ty == Ty::Type {
+                                self.const_ty(expr)?;
+                                ty
+                            } else {
+                                ty
+                            }
+                        }
+                        Expr::StructLiteral {
+                            ty: Some(ty_expr),
+                            members: member_values,
+                        } => 'struct_lit: {
+                            let expected_ty = self.const_ty(*ty_expr)?;
+
+                            // IndexMap is used to make sure errors are emitted in a logical order
+
+                            let found_member_tys = member_values
+                                .iter()
                                 .copied()
-                                .unwrap_or_else(|| Ty::Unknown.into());
+                                .filter_map(|MemberLiteral { name, value }| {
+                                    name.map(|name| {
+                                        (name.name, (name.range, value, self.tys[self.file][value]))
+                                    })
+                                })
+                                .collect::<IndexMap<_, _>>();
+
+                            let expected_tys = match expected_ty.as_struct() {
+                                Some(f) => f,
+                                None => {
+                                    self.tys[self.file].expr_tys.insert(expr, Ty::Error.into());
+
+                                    break 'struct_lit Ty::Error.into();
+                                }
+                            }
+                            .into_iter()
+                            .map(|MemberTy { name, ty }| (name, ty))
+                            .collect::<IndexMap<_, _>>();
+
+                            for (
+                                found_member_name,
+                                (found_member_range, found_member_expr, found_member_ty),
+                            ) in found_member_tys.iter()
+                            {
+                                if let Some(expected_member_ty) =
+                                    expected_tys.get(found_member_name)
+                                {
+                                    if self.expect_match(
+                                        *found_member_ty,
+                                        *expected_member_ty,
+                                        *found_member_expr,
+                                    ) {
+                                        self.replace_weak_tys(
+                                            *found_member_expr,
+                                            *expected_member_ty,
+                                        );
+                                    }
+                                } else {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::NonExistentMember {
+                                            member: found_member_name.0,
+                                            found_ty: expected_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(*found_member_expr),
+                                        range: *found_member_range,
+                                        help: None,
+                                    })
+                                }
+                            }
 
-                            self.tys[self.file]
-                                .switch_local_tys
-                                .insert(*switch_local, variant_ty);
+                            for expected_member_name in expected_tys
+                                .iter()
+                                .filter(|(_, ty)| !ty.is_unknown())
+                                .map(|(name, _)| name)
+                            {
+                                if found_member_tys.get(expected_member_name).is_none() {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::StructLiteralMissingMember {
+                                            member: expected_member_name.0,
+                                            expected_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(expr),
+                                        help: None,
+                                    })
+                                }
+                            }
 
-                            variant_ty
-                        }
-                        Expr::Param { idx, .. } => self.param_tys[*idx as usize].ty,
-                        Expr::LocalGlobal(name) => {
-                            let fqn = hir::Fqn {
-                                file: self.file,
-                                name: name.name,
-                            };
+                            // duplicate or unnamed members would make the fix ambiguous
+                            if self.lint_member_order
+                                && found_member_tys.len() == member_values.len()
+                                && found_member_tys.len() == expected_tys.len()
+                                && found_member_tys
+                                    .keys()
+                                    .all(|name| expected_tys.contains_key(name))
+                                && !found_member_tys.keys().eq(expected_tys.keys())
+                            {
+                                let reordered = expected_tys
+                                    .keys()
+                                    .map(|name| {
+                                        let (name_range, value, _) = found_member_tys[name];
+                                        TextRange::new(
+                                            name_range.start(),
+                                            self.bodies.range_for_expr(value).end(),
+                                        )
+                                    })
+                                    .collect();
 
-                            let sig = self
-                                .tys
-                                .signatures
-                                .get(&fqn)
-                                .ok_or_else(|| vec![Inferrable::Global(fqn)])?;
+                                let side_effect_free = member_values
+                                    .iter()
+                                    .all(|member| self.is_side_effect_free(member.value));
 
-                            if *sig.0 == Ty::NotYetResolved {
                                 self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::NotYetResolved { fqn },
+                                    kind: TyDiagnosticKind::StructLiteralOutOfOrder {
+                                        expected_ty,
+                                        reordered,
+                                        side_effect_free,
+                                    },
                                     file: self.file,
                                     expr: Some(expr),
                                     range: self.bodies.range_for_expr(expr),
                                     help: None,
                                 });
+                            }
 
-                                Ty::Unknown.into()
+                            // a member that exits early means the struct is never built
+                            if found_member_tys
+                                .values()
+                                .any(|(_, _, ty)| **ty == Ty::NoEval)
+                            {
+                                Ty::NoEval.into()
                             } else {
-                                sig.0
+                                expected_ty
                             }
                         }
-                        Expr::Member {
-                            previous,
-                            name: field,
+                        Expr::StructLiteral {
+                            ty: None,
+                            members: member_values,
                         } => {
-                            let previous_ty = self.tys[self.file][*previous];
-                            match previous_ty.as_ref() {
-                                Ty::File(file) => {
-                                    let fqn = hir::Fqn {
-                                        file: *file,
-                                        name: field.name,
-                                    };
+                            if member_values
+                                .iter()
+                                .any(|member| *self.tys[self.file][member.value] == Ty::NoEval)
+                            {
+                                Ty::NoEval.into()
+                            } else {
+                                Ty::Struct {
+                                    anonymous: true,
+                                    fqn: None,
+                                    uid: 0,
+                                    members: member_values
+                                        .iter()
+                                        .copied()
+                                        .filter_map(|MemberLiteral { name, value }| {
+                                            name.map(|name| MemberTy {
+                                                name: name.name,
+                                                ty: self.tys[self.file][value],
+                                            })
+                                        })
+                                        .collect(),
+                                }
+                                .into()
+                            }
+                        }
+                        Expr::Distinct { .. }
+                        | Expr::OptionalDecl { .. }
+                        | Expr::ClosureDecl { .. }
+                        | Expr::PrimitiveTy(_) => {
+                            // resolving the type might reveal diagnostics such as recursive types
+                            self.const_ty(expr)?;
+                            Ty::Type.into()
+                        }
+                        Expr::StructDecl { .. } => {
+                            self.const_ty(expr)?;
+                            Ty::Type.into()
+                        }
+                        Expr::EnumDecl { .. } | Expr::InterfaceDecl { .. } => {
+                            self.const_ty(expr)?;
+                            Ty::Type.into()
+                        }
+                        Expr::Directive { name, args } => match self.interner.lookup(name.name.0) {
+                            "unwrap" => 'blk: {
+                                let mut args = args.iter();
 
-                                    match self.world_index.definition(fqn) {
-                                        hir::DefinitionStatus::Defined => {
-                                            let sig = self
-                                                .tys
-                                                .signatures
-                                                .get(&fqn)
-                                                .ok_or_else(|| vec![Inferrable::Global(fqn)])?;
+                                let call_range = self.bodies.range_for_expr(expr);
+                                let call_end = call_range
+                                    .end()
+                                    .checked_sub(TextSize::new(1))
+                                    .unwrap_or(call_range.end());
+                                let call_end = TextRange::new(call_end, call_end);
 
-                                            if *sig.0 == Ty::NotYetResolved {
-                                                self.diagnostics.push(TyDiagnostic {
-                                                    kind: TyDiagnosticKind::NotYetResolved { fqn },
-                                                    file: self.file,
-                                                    expr: Some(expr),
-                                                    range: self.bodies.range_for_expr(expr),
-                                                    help: None,
-                                                });
+                                // first arg = enum to unwrap
 
-                                                Ty::Unknown.into()
-                                            } else {
-                                                sig.0
-                                            }
-                                        }
-                                        hir::DefinitionStatus::UnknownFile => {
-                                            unreachable!("a module wasn't added: {:?}", file)
-                                        }
-                                        hir::DefinitionStatus::UnknownDefinition => {
-                                            self.diagnostics.push(TyDiagnostic {
-                                                kind: TyDiagnosticKind::UnknownFqn { fqn },
-                                                file: self.file,
-                                                expr: Some(expr),
-                                                range: self.bodies.range_for_expr(expr),
-                                                help: None,
-                                            });
+                                let Some(enum_val) = args.next() else {
+                                    let missing =
+                                        [ExpectedTy::Enum, ExpectedTy::Concrete(Ty::Type.into())];
+                                    for expected in missing {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::MissingArg { expected },
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: call_end,
+                                            help: None,
+                                        });
+                                    }
+                                    break 'blk Ty::Error.into();
+                                };
+                                let enum_ty = self.tys[self.file][*enum_val];
 
-                                            Ty::Unknown.into()
-                                        }
+                                // `#unwrap(opt)` gets the payload out of an optional
+                                if let Some(sub_ty) = enum_ty.as_optional() {
+                                    let mut extra_args = false;
+                                    for arg in args {
+                                        extra_args = true;
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::ExtraArg {
+                                                found: self.tys[self.file][*arg],
+                                            },
+                                            file: self.file,
+                                            expr: Some(expr),
+                                            range: self.bodies.range_for_expr(*arg),
+                                            help: None,
+                                        });
                                     }
-                                }
-                                Ty::Type => {
-                                    // this is included for resolving enum variants
-                                    self.const_ty(expr)?;
-                                    Ty::Type.into()
-                                }
-                                _ => {
-                                    // because it's annoying to do `foo^.bar`, this code lets you do `foo.bar`
-                                    let mut deref_ty = previous_ty;
-                                    while let Some((_, sub_ty)) = deref_ty.as_pointer() {
-                                        deref_ty = sub_ty;
+
+                                    if extra_args {
+                                        break 'blk Ty::Error.into();
                                     }
-                                    deref_ty = deref_ty.absolute_intern_ty(true);
 
-                                    let field_name = self.interner.lookup(field.name.0);
+                                    break 'blk sub_ty;
+                                }
 
-                                    match (deref_ty.as_ref(), field_name) {
-                                        (Ty::Struct { members, .. }, _) => {
-                                            if let Some(matching_member) = members
-                                                .iter()
-                                                .find(|member_ty| member_ty.name == field.name)
-                                            {
-                                                matching_member.ty
-                                            } else {
-                                                if !previous_ty.is_unknown() {
-                                                    self.diagnostics.push(TyDiagnostic {
-                                                        kind: TyDiagnosticKind::NonExistentMember {
-                                                            member: field.name.0,
-                                                            found_ty: previous_ty,
-                                                        },
-                                                        file: self.file,
-                                                        expr: Some(expr),
-                                                        range: self.bodies.range_for_expr(expr),
-                                                        help: None,
-                                                    });
-                                                }
+                                let Ty::Enum { uid, .. } = enum_ty.absolute_ty() else {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::Mismatch {
+                                            expected: ExpectedTy::Enum,
+                                            found: enum_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(*enum_val),
+                                        help: None,
+                                    });
+                                    break 'blk Ty::Error.into();
+                                };
 
-                                                Ty::Unknown.into()
-                                            }
-                                        }
-                                        (Ty::Slice { .. }, "len") => Ty::UInt(u8::MAX).into(),
-                                        (Ty::Slice { sub_ty }, "ptr") => Ty::Pointer {
-                                            mutable: false,
-                                            sub_ty: *sub_ty,
-                                        }
-                                        .into(),
-                                        (Ty::RawSlice, "len") => Ty::UInt(u8::MAX).into(),
-                                        (Ty::RawSlice, "ptr") => {
-                                            Ty::RawPtr { mutable: false }.into()
-                                        }
-                                        (Ty::Any, "ty") => Ty::Type.into(),
-                                        (Ty::Any, "ptr") => Ty::RawPtr { mutable: false }.into(),
-                                        (Ty::Array { .. }, "len") => Ty::UInt(u8::MAX).into(),
-                                        _ => {
-                                            if !previous_ty.is_unknown() {
-                                                self.diagnostics.push(TyDiagnostic {
-                                                    kind: TyDiagnosticKind::NonExistentMember {
-                                                        member: field.name.0,
-                                                        found_ty: previous_ty,
-                                                    },
-                                                    file: self.file,
-                                                    expr: Some(expr),
-                                                    range: self.bodies.range_for_expr(expr),
-                                                    help: None,
-                                                });
-                                            }
+                                // second arg = variant type
+
+                                let Some(variant_ty_val) = args.next() else {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::MissingArg {
+                                            expected: ExpectedTy::Concrete(Ty::Type.into()),
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: call_end,
+                                        help: None,
+                                    });
+                                    break 'blk Ty::Error.into();
+                                };
+                                let variant_ty = self.tys[self.file][*variant_ty_val];
+                                if !matches!(variant_ty.absolute_ty(), Ty::Type) {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::Mismatch {
+                                            expected: ExpectedTy::Concrete(Ty::Type.into()),
+                                            found: variant_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(*variant_ty_val),
+                                        help: None,
+                                    });
+                                    break 'blk Ty::Error.into();
+                                };
+                                let variant_ty = self.const_ty(*variant_ty_val)?;
+
+                                let Ty::Variant { enum_uid, .. } =
+                                    variant_ty.absolute_ty_keep_variants()
+                                else {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::Mismatch {
+                                            expected: ExpectedTy::Variant,
+                                            found: variant_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(*variant_ty_val),
+                                        help: None,
+                                    });
+                                    break 'blk Ty::Error.into();
+                                };
+
+                                if enum_uid != uid {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::UnwrapVariantMismatchEnum {
+                                            variant_ty,
+                                            enum_ty,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(*variant_ty_val),
+                                        help: None,
+                                    });
+                                    break 'blk Ty::Error.into();
+                                }
 
-                                            Ty::Unknown.into()
-                                        }
-                                    }
+                                let mut extra_args = false;
+                                for arg in args {
+                                    extra_args = true;
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::ExtraArg {
+                                            found: self.tys[self.file][*arg],
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: self.bodies.range_for_expr(*variant_ty_val),
+                                        help: None,
+                                    });
+                                }
+
+                                if extra_args {
+                                    break 'blk Ty::Error.into();
                                 }
+
+                                variant_ty
+                            }
+                            "align_cast" => self.infer_align_cast(expr, args)?,
+                            "tailcall" => {
+                                self.check_tail_call(expr, args);
+
+                                // like `return`, a tail call never gives back a value
+                                // to the place it was written
+                                Ty::NoEval.into()
                             }
+                            text => match IntrinsicOperands::of(text) {
+                                Some(signature) => {
+                                    self.infer_math_intrinsic(expr, name.name.0, signature, args)
+                                }
+                                None => {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::UnknownDirective {
+                                            name: name.name.0,
+                                        },
+                                        file: self.file,
+                                        expr: Some(expr),
+                                        range: name.range,
+                                        help: None,
+                                    });
+
+                                    Ty::Error.into()
+                                }
+                            },
+                        },
+                        Expr::Import(file_name) => Ty::File(*file_name).into(),
+                    };
+
+                    self.tys[self.file].expr_tys.insert(expr, ty);
+                }
+                Descendant::Stmt(stmt) => {
+                    if self.inferred_stmts.contains(&(self.file, stmt)) {
+                        continue;
+                    }
+
+                    match self.bodies[stmt] {
+                        Stmt::Expr(expr) => {
+                            self.find_usages(&[expr], stmt);
                         }
-                        Expr::Call { callee, args } => {
-                            let callee_ty = self.tys[self.file][*callee];
+                        Stmt::LocalDef(local_def) => {
+                            let def_body = &self.bodies[local_def];
 
-                            if let Some((params, return_ty)) = callee_ty.clone().as_function() {
-                                let mut params_iter = params.iter();
-                                let mut args_iter = args.iter();
+                            if let Some(ty_annotation_expr) = def_body.ty {
+                                self.fill_in_array_size(ty_annotation_expr, def_body.value)?;
+                                let ty_annotation = self.const_ty(ty_annotation_expr)?;
 
-                                let mut current_param = params_iter.next();
-                                let mut current_arg = args_iter.next();
+                                // the definition has an annotation, so the value should match
+                                if let Some(value) = def_body.value {
+                                    let value_ty = self.tys[self.file][value];
+                                    if self.expect_match(value_ty, ty_annotation, value)
+                                        && self.replace_weak_tys(value, ty_annotation)
+                                    {
+                                        self.tys[self.file].expr_tys.insert(value, ty_annotation);
+                                    }
+                                } else if !ty_annotation.has_default_value() {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::DeclTypeHasNoDefault {
+                                            ty: ty_annotation,
+                                        },
+                                        file: self.file,
+                                        expr: Some(ty_annotation_expr),
+                                        range: self.bodies.range_for_expr(ty_annotation_expr),
+                                        help: None,
+                                    });
+                                }
 
-                                loop {
-                                    let Some(arg) = current_arg else {
-                                        if let Some(param) = current_param {
-                                            // there are more params than args
+                                self.tys[self.file]
+                                    .local_tys
+                                    .insert(local_def, ty_annotation);
+                            } else {
+                                // the definition does not have an annotation,
+                                // so use the type of it's value
+                                let value_ty = def_body
+                                    .value
+                                    .map(|value| self.tys[self.file][value])
+                                    .unwrap_or(Ty::Error.into());
+                                self.tys[self.file].local_tys.insert(local_def, value_ty);
+                            }
 
-                                            if param.varargs {
-                                                current_param = params_iter.next();
-                                                continue; // continue without reporting error
-                                            }
+                            if let Some(value) = def_body.value {
+                                self.check_large_array_copy(value);
+                                self.find_usages(&[value], stmt);
+                            }
+                        }
+                        Stmt::Assign(assign) => {
+                            let assign_body = &self.bodies[assign];
 
-                                            let param_ty = param.ty;
+                            let non_mut_help = self
+                                .get_mutability(assign_body.dest, true, false)
+                                .into_diagnostic();
 
-                                            let call_range = self.bodies.range_for_expr(expr);
-                                            let call_end = call_range
-                                                .end()
-                                                .checked_sub(TextSize::new(1))
-                                                .unwrap_or(call_range.end());
+                            if non_mut_help.is_some() {
+                                self.diagnostics.push(TyDiagnostic {
+                                    kind: TyDiagnosticKind::CannotMutate,
+                                    file: self.file,
+                                    // making expr the dest isn't technically correct, but it works
+                                    expr: Some(assign_body.dest),
+                                    range: assign_body.range,
+                                    help: non_mut_help,
+                                });
+                                continue;
+                            }
 
-                                            // TODO: add tests for this != Ty::Unknown
-                                            if !param.impossible_to_differentiate
-                                                && *param_ty != Ty::Unknown
-                                            {
-                                                self.diagnostics.push(TyDiagnostic {
-                                                    kind: TyDiagnosticKind::MissingArg {
-                                                        expected: ExpectedTy::Concrete(param_ty),
-                                                    },
-                                                    file: self.file,
-                                                    expr: Some(expr),
-                                                    range: TextRange::new(call_end, call_end),
-                                                    help: None,
-                                                });
-                                            }
-                                        } else {
-                                            break;
-                                        }
-                                        current_param = params_iter.next();
-                                        continue;
-                                    };
-                                    let arg_ty = self.tys[self.file][*arg];
+                            let source_ty = self.tys[self.file][assign_body.dest];
+                            let value_ty = self.tys[self.file][assign_body.value];
 
-                                    let Some(param) = current_param else {
-                                        // there are more args than params
+                            match assign_body.quick_assign_op.map(|op| {
+                                (
+                                    op,
+                                    op.get_possible_output_ty(&source_ty, &value_ty, self.tys),
+                                )
+                            }) {
+                                Some((op, Some(output_ty))) => {
+                                    if !source_ty.is_error_or_unknown()
+                                        && !value_ty.is_error_or_unknown()
+                                        && !op.can_perform(&output_ty.max_ty)
+                                    {
                                         self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::ExtraArg { found: arg_ty },
+                                            kind: TyDiagnosticKind::BinaryOpMismatch {
+                                                op,
+                                                first: source_ty,
+                                                second: value_ty,
+                                            },
                                             file: self.file,
-                                            expr: Some(*arg),
-                                            range: self.bodies.range_for_expr(*arg),
+                                            // making expr the dest isn't technically correct, but it works
+                                            expr: Some(assign_body.dest),
+                                            range: assign_body.range,
                                             help: None,
                                         });
-                                        current_arg = args_iter.next();
-                                        continue;
-                                    };
+                                    }
 
-                                    if param.varargs {
-                                        let actual_sub_ty = param.ty.as_slice().unwrap();
+                                    let max_ty = output_ty.max_ty.into();
 
-                                        if arg_ty.can_fit_into(&actual_sub_ty) {
-                                            self.replace_weak_tys(*arg, actual_sub_ty);
+                                    self.replace_weak_tys(assign_body.dest, max_ty);
+                                    self.replace_weak_tys(assign_body.value, max_ty);
+                                }
+                                Some((op, None)) => {
+                                    self.diagnostics.push(TyDiagnostic {
+                                        kind: TyDiagnosticKind::BinaryOpMismatch {
+                                            op,
+                                            first: source_ty,
+                                            second: value_ty,
+                                        },
+                                        file: self.file,
+                                        // making expr the dest isn't technically correct, but it works
+                                        expr: Some(assign_body.dest),
+                                        range: assign_body.range,
+                                        help: None,
+                                    });
+                                }
+                                None => {
+                                    if source_ty.is_weak_replaceable_by(&value_ty) {
+                                        self.replace_weak_tys(assign_body.dest, value_ty);
+                                    } else if self.expect_match(
+                                        value_ty,
+                                        source_ty,
+                                        assign_body.value,
+                                    ) {
+                                        self.replace_weak_tys(assign_body.value, value_ty);
+                                    }
+                                }
+                            }
 
-                                            current_arg = args_iter.next();
-                                        } else if let Some(next_param) = params_iter.next() {
-                                            // go to the next param but don't go to the next arg.
-                                            // this basically just reevaluates the current argument
-                                            // under the next parameter.
-                                            current_param = Some(next_param);
-                                        } else {
-                                            // `can_fit_into` should return true for unknowns
-                                            assert!(!arg_ty.is_unknown());
-                                            // this will just return an error
-                                            self.diagnostics.push(TyDiagnostic {
-                                                kind: TyDiagnosticKind::Mismatch {
-                                                    expected: ExpectedTy::Concrete(actual_sub_ty),
-                                                    found: arg_ty,
-                                                },
-                                                file: self.file,
-                                                expr: Some(*arg),
-                                                range: self.bodies.range_for_expr(*arg),
-                                                help: None,
-                                            });
-                                            current_arg = args_iter.next();
-                                        }
-                                    } else {
-                                        self.expect_match(arg_ty, param.ty, *arg);
+                            if assign_body.quick_assign_op.is_none() {
+                                self.check_large_array_copy(assign_body.value);
+                            }
 
-                                        self.replace_weak_tys(*arg, param.ty);
+                            self.find_usages(&[assign_body.dest, assign_body.value], stmt);
+                        }
+                        Stmt::Break { label: None, .. } => {}
+                        Stmt::Break {
+                            label: Some(label),
+                            value,
+                            ..
+                        } => {
+                            let referenced_expr = self.bodies[label];
+
+                            let value_ty = value.map_or_else(
+                                || Ty::Void.into(),
+                                |value| self.tys[self.file][value],
+                            );
+
+                            let must_be_void = matches!(
+                                self.bodies[referenced_expr],
+                                Expr::While { .. } | Expr::For { .. }
+                            );
+
+                            match self.tys[self.file]
+                                .expr_tys
+                                .get(referenced_expr)
+                                .copied()
+                                .or_else(|| self.optional_body_ty(referenced_expr))
+                            {
+                                Some(expected_ty) => {
+                                    self.expect_block_match(
+                                        value,
+                                        value_ty,
+                                        referenced_expr,
+                                        expected_ty,
+                                    );
+                                }
+                                None => {
+                                    if must_be_void && !value_ty.is_void() {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::Mismatch {
+                                                expected: ExpectedTy::Concrete(Ty::Void.into()),
+                                                found: value_ty,
+                                            },
+                                            file: self.file,
+                                            expr: value,
+                                            range: self.bodies.range_for_expr(value.unwrap()),
+                                            help: None,
+                                        });
 
-                                        current_param = params_iter.next();
-                                        current_arg = args_iter.next();
+                                        self.tys[self.file]
+                                            .expr_tys
+                                            .insert(referenced_expr, Ty::Error.into());
+                                    } else {
+                                        self.tys[self.file]
+                                            .expr_tys
+                                            .insert(referenced_expr, value_ty);
                                     }
                                 }
-
-                                return_ty
-                            } else {
-                                if *callee_ty != Ty::Unknown {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::CalledNonFunction {
-                                            found: callee_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(expr),
-                                        help: None,
-                                    });
-                                }
-
-                                Ty::Unknown.into()
                             }
                         }
-                        Expr::Lambda(lambda) => {
-                            let hir::Lambda {
-                                params,
-                                return_ty,
-                                body,
-                                is_extern,
-                                ..
-                            } = &self.bodies[*lambda];
+                        Stmt::Continue { .. } => {}
+                        Stmt::Defer { expr, .. } => {
+                            self.find_usages(&[expr], stmt);
+                        }
+                    }
 
-                            let is_type = !is_extern
-                                && return_ty.is_some()
-                                && self.bodies[*body] == hir::Expr::Missing;
+                    self.inferred_stmts.insert((self.file, stmt));
+                }
+            };
+        }
 
-                            let return_ty = if let Some(return_ty) = return_ty {
-                                self.const_ty(*return_ty)?
-                            } else {
-                                Ty::Void.into()
-                            };
+        Ok(self.tys[self.file][expr])
+    }
 
-                            let mut param_tys = Vec::with_capacity(params.len());
+    /// Gives `null` the type of an optional, looking through the parentheses and blocks around it
+    /// (like in `x : ?i32 = { null };`).
+    ///
+    /// Returns false if the expression wasn't `null`
+    fn coerce_null(&mut self, expr: Idx<hir::Expr>, optional_ty: Intern<Ty>) -> bool {
+        let mut inner = expr;
+        let mut wrappers = Vec::new();
+        loop {
+            match &self.bodies[inner] {
+                hir::Expr::NullLiteral => break,
+                hir::Expr::Paren(Some(next))
+                | hir::Expr::Block {
+                    tail_expr: Some(next),
+                    ..
+                } if self.bodies.block_to_scope_id(inner).is_none() => {
+                    wrappers.push(inner);
+                    inner = *next;
+                }
+                _ => return false,
+            }
+        }
 
-                            for (idx, param) in params.iter().enumerate() {
-                                let mut ty = self.const_ty(param.ty)?;
+        for expr in wrappers.into_iter().chain(std::iter::once(inner)) {
+            self.tys[self.file].expr_tys.insert(expr, optional_ty);
+        }
 
-                                let mut impossible_to_differentiate = false;
+        true
+    }
 
-                                if let Some(last_param) = idx
-                                    .checked_sub(1)
-                                    .and_then(|idx| params.get(idx))
-                                    .filter(|p| p.varargs)
-                                {
-                                    // we already called `const_ty` on the last param
-                                    let last_ty = self.tys[self.file].meta_tys[last_param.ty];
+    /// `if cond { x } else { null }` gives back an optional of `x`'s type
+    fn coerce_null_branch(
+        &mut self,
+        body: Idx<hir::Expr>,
+        body_ty: Intern<Ty>,
+        else_branch: Idx<hir::Expr>,
+        else_ty: Intern<Ty>,
+    ) -> Option<Intern<Ty>> {
+        for (null_branch, other, other_ty) in
+            [(else_branch, body, body_ty), (body, else_branch, else_ty)]
+        {
+            if *other_ty == Ty::NoEval || other_ty.as_optional().is_some() {
+                continue;
+            }
 
-                                    if !ty.can_differentiate(&last_ty) {
-                                        impossible_to_differentiate = true;
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind:
-                                                TyDiagnosticKind::ImpossibleToDifferentiateVarArgs {
-                                                    previous_ty: last_ty,
-                                                    current_ty: ty,
-                                                },
-                                            file: self.file,
-                                            expr: Some(expr),
-                                            range: param.range,
-                                            help: None,
-                                        });
-                                    }
-                                }
+            // there's nothing else to decide what a weak type becomes
+            let sub_ty = other_ty.weak_default().unwrap_or(other_ty);
+            let optional_ty = Ty::Optional { sub_ty }.into();
+            if self.coerce_null(null_branch, optional_ty) {
+                self.replace_weak_tys(other, sub_ty);
+                return Some(optional_ty);
+            }
+        }
 
-                                if param.varargs {
-                                    ty = Ty::Slice { sub_ty: ty }.into();
-                                }
+        None
+    }
 
-                                param_tys.push(ParamTy {
-                                    ty,
-                                    varargs: param.varargs,
-                                    impossible_to_differentiate,
-                                });
-                            }
+    /// When a body is expected to give back an optional, each of its `return`s gets checked
+    /// against that optional on its own instead of against each other.
+    ///
+    /// This lets `return null;` and `return x;` be mixed together
+    fn optional_body_ty(&self, block_expr: Idx<hir::Expr>) -> Option<Intern<Ty>> {
+        self.optional_body
+            .filter(|(body, _)| *body == block_expr)
+            .map(|(_, ty)| ty)
+    }
 
-                            let ty = Ty::Function {
-                                param_tys,
-                                return_ty,
-                            }
-                            .into();
+    /// Only call for blocks which had their type previously set by a `break`
+    ///
+    /// returns the max of the found expression and the current type of the block
+    fn expect_block_match(
+        &mut self,
+        found_expr: Option<Idx<hir::Expr>>,
+        found_ty: Intern<Ty>,
+        block_expr: Idx<hir::Expr>,
+        block_ty: Intern<Ty>,
+    ) -> Option<Intern<Ty>> {
+        if let Some(optional_ty) = self.optional_body_ty(block_expr) {
+            let found_expr = found_expr.unwrap_or(block_expr);
 
-                            if is_type {
-                                self.tys[self.file].meta_tys.insert(expr, ty);
+            if self.expect_match(found_ty, optional_ty, found_expr) {
+                // the `5` in `return 5;` should become an `i32` before being wrapped up as a `?i32`
+                self.replace_weak_tys(found_expr, optional_ty.as_optional().unwrap());
+            }
+            self.tys[self.file].expr_tys.insert(block_expr, optional_ty);
 
-                                Ty::Type.into()
-                            } else {
-                                self.to_infer.insert(Inferrable::Lambda(FQLambda {
-                                    file: self.file,
-                                    expr,
-                                    lambda: *lambda,
-                                }));
+            return Some(optional_ty);
+        }
 
-                                ty
-                            }
-                        }
-                        Expr::Comptime(comptime) => {
-                            let hir::Comptime { body } = self.bodies[*comptime];
+        if found_ty.is_unknown() || block_ty.is_unknown() {
+            return None;
+        }
 
-                            let ty = self.tys[self.file][body];
+        if let Some(max) = block_ty.max(&found_ty, self.tys) {
+            let max = max.into();
+            self.tys[self.file].expr_tys[block_expr] = max;
+            if let Some(found_expr) = found_expr {
+                self.replace_weak_tys(found_expr, max);
+            }
 
-                            if ty.is_pointer() || ty.is_function() {
-                                self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::ComptimePointer,
-                                    file: self.file,
-                                    expr: Some(expr),
-                                    range: self.bodies.range_for_expr(expr),
-                                    help: None,
-                                });
+            Some(max)
+        } else {
+            // the mismatch gets reported by `report_break_mismatches` once all the breaks
+            // have been seen
+            self.tys[self.file].expr_tys[block_expr] = Ty::Error.into();
 
-                                Ty::Unknown.into()
-                            } else if *ty == Ty::Type {
-                                self.const_ty(expr)?;
-                                ty
-                            } else {
-                                ty
-                            }
-                        }
-                        Expr::StructLiteral {
-                            ty: Some(ty_expr),
-                            members: member_values,
-                        } => 'struct_lit: {
-                            let expected_ty = self.const_ty(*ty_expr)?;
+            None
+        }
+    }
 
-                            // IndexMap is used to make sure errors are emitted in a logical order
+    /// `Shape.Circle(r)` constructs a variant directly from its payload,
+    /// the same way `Shape.Circle.(r)` would, but the payload must actually be
+    /// the variant's `sub_ty` instead of just being castable to it.
+    fn construct_variant(
+        &mut self,
+        call: Idx<hir::Expr>,
+        variant_ty: Intern<Ty>,
+        args: &[Idx<hir::Expr>],
+    ) -> Intern<Ty> {
+        let Ty::Variant { sub_ty, .. } = variant_ty.as_ref() else {
+            unreachable!("only variants can be constructed");
+        };
+        let sub_ty = *sub_ty;
+
+        // variants without a payload don't take any arguments
+        let extra_args = match args {
+            [] if sub_ty.is_void() || sub_ty.is_error_or_unknown() => args,
+            [] => {
+                let call_range = self.bodies.range_for_expr(call);
+                let call_end = call_range
+                    .end()
+                    .checked_sub(TextSize::new(1))
+                    .unwrap_or(call_range.end());
 
-                            let found_member_tys = member_values
-                                .iter()
-                                .copied()
-                                .filter_map(|MemberLiteral { name, value }| {
-                                    name.map(|name| {
-                                        (name.name, (name.range, value, self.tys[self.file][value]))
-                                    })
-                                })
-                                .collect::<IndexMap<_, _>>();
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::MissingArg {
+                        expected: ExpectedTy::Concrete(sub_ty),
+                    },
+                    file: self.file,
+                    expr: Some(call),
+                    range: TextRange::new(call_end, call_end),
+                    help: None,
+                });
 
-                            let expected_tys = match expected_ty.as_struct() {
-                                Some(f) => f,
-                                None => {
-                                    self.tys[self.file]
-                                        .expr_tys
-                                        .insert(expr, Ty::Unknown.into());
+                args
+            }
+            _ if sub_ty.is_void() => args,
+            [payload, rest @ ..] => {
+                let payload_ty = self.tys[self.file][*payload];
 
-                                    break 'struct_lit Ty::Unknown.into();
-                                }
-                            }
-                            .into_iter()
-                            .map(|MemberTy { name, ty }| (name, ty))
-                            .collect::<IndexMap<_, _>>();
+                if self.expect_match(payload_ty, sub_ty, *payload) {
+                    self.replace_weak_tys(*payload, sub_ty);
+                }
 
-                            for (
-                                found_member_name,
-                                (found_member_range, found_member_expr, found_member_ty),
-                            ) in found_member_tys.iter()
-                            {
-                                if let Some(expected_member_ty) =
-                                    expected_tys.get(found_member_name)
-                                {
-                                    if self.expect_match(
-                                        *found_member_ty,
-                                        *expected_member_ty,
-                                        *found_member_expr,
-                                    ) {
-                                        self.replace_weak_tys(
-                                            *found_member_expr,
-                                            *expected_member_ty,
-                                        );
-                                    }
-                                } else {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::NonExistentMember {
-                                            member: found_member_name.0,
-                                            found_ty: expected_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(*found_member_expr),
-                                        range: *found_member_range,
-                                        help: None,
-                                    })
-                                }
-                            }
+                rest
+            }
+        };
+
+        for arg in extra_args {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ExtraArg {
+                    found: self.tys[self.file][*arg],
+                },
+                file: self.file,
+                expr: Some(*arg),
+                range: self.bodies.range_for_expr(*arg),
+                help: None,
+            });
+        }
+
+        if args
+            .iter()
+            .any(|arg| *self.tys[self.file][*arg] == Ty::NoEval)
+        {
+            Ty::NoEval.into()
+        } else {
+            variant_ty
+        }
+    }
+
+    /// `#sqrt`, `#min`, `#popcount`, etc. all give back the same type as their arguments
+    fn infer_math_intrinsic(
+        &mut self,
+        directive: Idx<hir::Expr>,
+        name: Key,
+        (arg_count, operands): (usize, IntrinsicOperands),
+        args: &[Idx<hir::Expr>],
+    ) -> Intern<Ty> {
+        let (args, extra_args) = args.split_at(args.len().min(arg_count));
+
+        let mut all_fine = extra_args.is_empty();
+        for arg in extra_args {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ExtraArg {
+                    found: self.tys[self.file][*arg],
+                },
+                file: self.file,
+                expr: Some(*arg),
+                range: self.bodies.range_for_expr(*arg),
+                help: None,
+            });
+        }
+
+        let max_ty = match self.math_intrinsic_ty(operands, args) {
+            Ok(max_ty) => max_ty,
+            Err((previous_ty, arg)) => {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(previous_ty),
+                        found: self.tys[self.file][arg],
+                    },
+                    file: self.file,
+                    expr: Some(arg),
+                    range: self.bodies.range_for_expr(arg),
+                    help: None,
+                });
+                return Ty::Error.into();
+            }
+        };
 
-                            for expected_member_name in expected_tys
-                                .iter()
-                                .filter(|(_, ty)| !ty.is_unknown())
-                                .map(|(name, _)| name)
-                            {
-                                if found_member_tys.get(expected_member_name).is_none() {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::StructLiteralMissingMember {
-                                            member: expected_member_name.0,
-                                            expected_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(expr),
-                                        help: None,
-                                    })
-                                }
-                            }
+        if let Some(max_ty) = max_ty {
+            if operands.accepts(&max_ty) {
+                for arg in args {
+                    self.replace_weak_tys(*arg, max_ty);
+                }
+            } else {
+                all_fine = false;
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::IntrinsicMismatch {
+                        name,
+                        expected: operands,
+                        found: max_ty,
+                    },
+                    file: self.file,
+                    expr: Some(directive),
+                    range: self.bodies.range_for_expr(directive),
+                    help: None,
+                });
+            }
+        }
 
-                            expected_ty
-                        }
-                        Expr::StructLiteral {
-                            ty: None,
-                            members: member_values,
-                        } => Ty::Struct {
-                            anonymous: true,
-                            fqn: None,
-                            uid: 0,
-                            members: member_values
-                                .iter()
-                                .copied()
-                                .filter_map(|MemberLiteral { name, value }| {
-                                    name.map(|name| MemberTy {
-                                        name: name.name,
-                                        ty: self.tys[self.file][value],
-                                    })
-                                })
-                                .collect(),
-                        }
-                        .into(),
-                        Expr::Distinct { .. } | Expr::PrimitiveTy(_) => {
-                            // resolving the type might reveal diagnostics such as recursive types
-                            self.const_ty(expr)?;
-                            Ty::Type.into()
-                        }
-                        Expr::StructDecl { .. } => {
-                            self.const_ty(expr)?;
-                            Ty::Type.into()
-                        }
-                        Expr::EnumDecl { .. } => {
-                            self.const_ty(expr)?;
-                            Ty::Type.into()
-                        }
-                        Expr::Directive { name, args } => match self.interner.lookup(name.name.0) {
-                            "unwrap" => 'blk: {
-                                let mut args = args.iter();
+        if args.len() < arg_count {
+            all_fine = false;
 
-                                let call_range = self.bodies.range_for_expr(expr);
-                                let call_end = call_range
-                                    .end()
-                                    .checked_sub(TextSize::new(1))
-                                    .unwrap_or(call_range.end());
-                                let call_end = TextRange::new(call_end, call_end);
+            let call_range = self.bodies.range_for_expr(directive);
+            let call_end = call_range
+                .end()
+                .checked_sub(TextSize::new(1))
+                .unwrap_or(call_range.end());
 
-                                // first arg = enum to unwrap
+            let expected = max_ty.unwrap_or_else(|| match operands {
+                IntrinsicOperands::Floats => *ty::F0,
+                IntrinsicOperands::Ints | IntrinsicOperands::Numbers => *ty::I0,
+            });
+            for _ in args.len()..arg_count {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::MissingArg {
+                        expected: ExpectedTy::Concrete(expected),
+                    },
+                    file: self.file,
+                    expr: Some(directive),
+                    range: TextRange::new(call_end, call_end),
+                    help: None,
+                });
+            }
+        }
 
-                                let Some(enum_val) = args.next() else {
-                                    let missing =
-                                        [ExpectedTy::Enum, ExpectedTy::Concrete(Ty::Type.into())];
-                                    for expected in missing {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::MissingArg { expected },
-                                            file: self.file,
-                                            expr: Some(expr),
-                                            range: call_end,
-                                            help: None,
-                                        });
-                                    }
-                                    break 'blk Ty::Unknown.into();
-                                };
-                                let enum_ty = self.tys[self.file][*enum_val];
-                                let Ty::Enum { uid, .. } = enum_ty.absolute_ty() else {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::Mismatch {
-                                            expected: ExpectedTy::Enum,
-                                            found: enum_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(*enum_val),
-                                        help: None,
-                                    });
-                                    break 'blk Ty::Unknown.into();
-                                };
+        if args
+            .iter()
+            .any(|arg| *self.tys[self.file][*arg] == Ty::NoEval)
+        {
+            Ty::NoEval.into()
+        } else {
+            match max_ty {
+                Some(max_ty) if all_fine => max_ty,
+                _ => Ty::Error.into(),
+            }
+        }
+    }
 
-                                // second arg = variant type
+    /// Finds the one type that all the arguments of a math intrinsic can be.
+    ///
+    /// If an argument can't be combined with the arguments before it, the type of those
+    /// previous arguments is given back along with the argument that didn't fit
+    fn math_intrinsic_ty(
+        &self,
+        operands: IntrinsicOperands,
+        args: &[Idx<hir::Expr>],
+    ) -> Result<Option<Intern<Ty>>, MathIntrinsicMismatch> {
+        let mut max_ty: Option<Intern<Ty>> = None;
+
+        for arg in args {
+            let arg_ty = self.tys[self.file][*arg];
+            if arg_ty.is_error_or_unknown() || *arg_ty == Ty::NoEval {
+                continue;
+            }
 
-                                let Some(variant_ty_val) = args.next() else {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::MissingArg {
-                                            expected: ExpectedTy::Concrete(Ty::Type.into()),
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: call_end,
-                                        help: None,
-                                    });
-                                    break 'blk Ty::Unknown.into();
-                                };
-                                let variant_ty = self.tys[self.file][*variant_ty_val];
-                                if !matches!(variant_ty.absolute_ty(), Ty::Type) {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::Mismatch {
-                                            expected: ExpectedTy::Concrete(Ty::Type.into()),
-                                            found: variant_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(*variant_ty_val),
-                                        help: None,
-                                    });
-                                    break 'blk Ty::Unknown.into();
-                                };
-                                let variant_ty = self.const_ty(*variant_ty_val)?;
+            max_ty = match max_ty {
+                None => Some(arg_ty),
+                Some(previous_ty) => match previous_ty.max(&arg_ty, self.tys) {
+                    Some(new_max) => Some(new_max.into()),
+                    None => return Err((previous_ty, *arg)),
+                },
+            };
+        }
 
-                                let Ty::Variant { enum_uid, .. } =
-                                    variant_ty.absolute_ty_keep_variants()
-                                else {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::Mismatch {
-                                            expected: ExpectedTy::Variant,
-                                            found: variant_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(*variant_ty_val),
-                                        help: None,
-                                    });
-                                    break 'blk Ty::Unknown.into();
-                                };
+        Ok(max_ty.map(|max_ty| match (operands, max_ty.as_ref()) {
+            // `#sqrt(2)` is the same as `#sqrt(2.0)`
+            (IntrinsicOperands::Floats, Ty::IInt(0) | Ty::UInt(0)) => *ty::F0,
+            _ => max_ty,
+        }))
+    }
 
-                                if enum_uid != uid {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::UnwrapVariantMismatchEnum {
-                                            variant_ty,
-                                            enum_ty,
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(*variant_ty_val),
-                                        help: None,
-                                    });
-                                    break 'blk Ty::Unknown.into();
-                                }
+    /// `#tailcall(f(args))` is compiled as a jump back to the start of `f`,
+    /// so it has to call the exact function it's written in.
+    /// Returns the original pointer type and the alignments of both pointees if casting `from`
+    /// to `to` could give a pointer with less alignment than the new type needs.
+    ///
+    /// Pointers can only be cast to other pointer types by going through `rawptr`,
+    /// so this looks through those casts to see that `^u64.(rawptr.(bytes))` started as a `^u8`
+    fn misaligned_pointer_cast(
+        &self,
+        from: Idx<Expr>,
+        to: Intern<Ty>,
+    ) -> Option<(Intern<Ty>, u32, u32)> {
+        let (_, to_sub_ty) = to.as_pointer()?;
+
+        let mut from = from;
+        let (from_ty, from_sub_ty) = loop {
+            let from_ty = self.tys[self.file][from];
+            if let Some((_, sub_ty)) = from_ty.as_pointer() {
+                break (from_ty, sub_ty);
+            }
+            if !matches!(from_ty.absolute_ty(), Ty::RawPtr { .. }) {
+                return None;
+            }
 
-                                let mut extra_args = false;
-                                for arg in args {
-                                    extra_args = true;
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::ExtraArg {
-                                            found: self.tys[self.file][*arg],
-                                        },
-                                        file: self.file,
-                                        expr: Some(expr),
-                                        range: self.bodies.range_for_expr(*variant_ty_val),
-                                        help: None,
-                                    });
-                                }
+            match self.bodies[from] {
+                Expr::Cast {
+                    expr: Some(inner), ..
+                }
+                | Expr::Paren(Some(inner)) => from = inner,
+                _ => return None,
+            }
+        };
 
-                                if extra_args {
-                                    break 'blk Ty::Unknown.into();
-                                }
+        let from_align = from_sub_ty.known_align()?;
+        let to_align = to_sub_ty.known_align()?;
 
-                                variant_ty
-                            }
-                            _ => {
-                                self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::UnknownDirective { name: name.name.0 },
-                                    file: self.file,
-                                    expr: Some(expr),
-                                    range: name.range,
-                                    help: None,
-                                });
+        (to_align > from_align).then_some((from_ty, from_align, to_align))
+    }
 
-                                Ty::Unknown.into()
-                            }
-                        },
-                        Expr::Import(file_name) => Ty::File(*file_name).into(),
-                    };
+    /// Whether evaluating `expr` only reads values, so it could be moved before or after
+    /// other expressions without changing what the program does
+    fn is_side_effect_free(&self, expr: Idx<Expr>) -> bool {
+        match &self.bodies[expr] {
+            Expr::IntLiteral(_)
+            | Expr::FloatLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::CharLiteral(_)
+            | Expr::NullLiteral
+            | Expr::Local(_)
+            | Expr::SwitchLocal(_)
+            | Expr::ForLocal(_)
+            | Expr::Captured(_)
+            | Expr::Param { .. }
+            | Expr::LocalGlobal(_) => true,
+            Expr::Paren(Some(inner)) => self.is_side_effect_free(*inner),
+            Expr::Member { previous, .. } => self.is_side_effect_free(*previous),
+            Expr::StructLiteral { members, .. } => members
+                .iter()
+                .all(|member| self.is_side_effect_free(member.value)),
+            Expr::ArrayLiteral { items, .. } => {
+                items.iter().all(|item| self.is_side_effect_free(*item))
+            }
+            _ => false,
+        }
+    }
 
-                    self.tys[self.file].expr_tys.insert(expr, ty);
-                }
-                Descendant::Stmt(stmt) => {
-                    if self.inferred_stmts.contains(&(self.file, stmt)) {
-                        continue;
-                    }
+    /// Warns if a comparison like `x == x` or `a.b < a.b` always has the same result.
+    fn check_self_comparison(
+        &mut self,
+        expr: Idx<Expr>,
+        op: hir::BinaryOp,
+        lhs: Idx<Expr>,
+        rhs: Idx<Expr>,
+    ) {
+        if !matches!(
+            op,
+            hir::BinaryOp::Lt
+                | hir::BinaryOp::Gt
+                | hir::BinaryOp::Le
+                | hir::BinaryOp::Ge
+                | hir::BinaryOp::Eq
+                | hir::BinaryOp::Ne
+        ) {
+            return;
+        }
 
-                    match self.bodies[stmt] {
-                        Stmt::Expr(expr) => {
-                            self.find_usages(&[expr], stmt);
-                        }
-                        Stmt::LocalDef(local_def) => {
-                            let def_body = &self.bodies[local_def];
+        // NaN isn't equal to anything, even itself.
+        // comparing a type to itself is how reflection code shows off type ids
+        let lhs_ty = self.tys[self.file][lhs];
+        if lhs_ty.is_float() || *lhs_ty == Ty::Type {
+            return;
+        }
 
-                            if let Some(ty_annotation_expr) = def_body.ty {
-                                let ty_annotation = self.const_ty(ty_annotation_expr)?;
+        if self.is_same_place(lhs, rhs) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::SelfComparison,
+                file: self.file,
+                expr: Some(expr),
+                range: self.bodies.range_for_expr(expr),
+                help: None,
+            });
+        }
+    }
 
-                                // the definition has an annotation, so the value should match
-                                if let Some(value) = def_body.value {
-                                    let value_ty = self.tys[self.file][value];
-                                    if self.expect_match(value_ty, ty_annotation, value)
-                                        && self.replace_weak_tys(value, ty_annotation)
-                                    {
-                                        self.tys[self.file].expr_tys.insert(value, ty_annotation);
-                                    }
-                                } else if !ty_annotation.has_default_value() {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::DeclTypeHasNoDefault {
-                                            ty: ty_annotation,
-                                        },
-                                        file: self.file,
-                                        expr: Some(ty_annotation_expr),
-                                        range: self.bodies.range_for_expr(ty_annotation_expr),
-                                        help: None,
-                                    });
-                                }
+    /// Whether both expressions name the same variable (or the same member of the same variable)
+    fn is_same_place(&self, lhs: Idx<Expr>, rhs: Idx<Expr>) -> bool {
+        match (&self.bodies[lhs], &self.bodies[rhs]) {
+            (Expr::Local(lhs), Expr::Local(rhs)) => lhs == rhs,
+            (Expr::SwitchLocal(lhs), Expr::SwitchLocal(rhs)) => lhs == rhs,
+            (Expr::ForLocal(lhs), Expr::ForLocal(rhs)) => lhs == rhs,
+            (Expr::Captured(lhs), Expr::Captured(rhs)) => lhs == rhs,
+            (Expr::Param { idx: lhs, .. }, Expr::Param { idx: rhs, .. }) => lhs == rhs,
+            (Expr::LocalGlobal(lhs), Expr::LocalGlobal(rhs)) => lhs.name == rhs.name,
+            (
+                Expr::Member {
+                    previous: lhs_previous,
+                    name: lhs_name,
+                },
+                Expr::Member {
+                    previous: rhs_previous,
+                    name: rhs_name,
+                },
+            ) => lhs_name.name == rhs_name.name && self.is_same_place(*lhs_previous, *rhs_previous),
+            _ => false,
+        }
+    }
+
+    /// Warns if `value` is an existing array that's big enough for copying it to be costly.
+    ///
+    /// Literals are built right where they're stored, so only places like locals, members,
+    /// indexes, and derefs actually get copied
+    fn check_large_array_copy(&mut self, value: Idx<Expr>) {
+        let mut place = value;
+        loop {
+            match self.bodies[place] {
+                Expr::Paren(Some(inner)) => place = inner,
+                Expr::Local(_)
+                | Expr::Param { .. }
+                | Expr::LocalGlobal(_)
+                | Expr::Member { .. }
+                | Expr::Index { .. }
+                | Expr::Deref { .. } => break,
+                _ => return,
+            }
+        }
 
-                                self.tys[self.file]
-                                    .local_tys
-                                    .insert(local_def, ty_annotation);
-                            } else {
-                                // the definition does not have an annotation,
-                                // so use the type of it's value
-                                let value_ty = def_body
-                                    .value
-                                    .map(|value| self.tys[self.file][value])
-                                    .unwrap_or(Ty::Unknown.into());
-                                self.tys[self.file].local_tys.insert(local_def, value_ty);
-                            }
+        let ty = self.tys[self.file][value];
+        if !ty.is_array() {
+            return;
+        }
 
-                            if let Some(value) = def_body.value {
-                                self.find_usages(&[value], stmt);
-                            }
-                        }
-                        Stmt::Assign(assign) => {
-                            let assign_body = &self.bodies[assign];
+        let Some(size) = ty.known_size() else {
+            return;
+        };
 
-                            let non_mut_help = self
-                                .get_mutability(assign_body.dest, true, false)
-                                .into_diagnostic();
+        if size >= LARGE_ARRAY_COPY_SIZE {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::LargeArrayCopy { ty, size },
+                file: self.file,
+                expr: Some(value),
+                range: self.bodies.range_for_expr(value),
+                help: None,
+            });
+        }
+    }
 
-                            if non_mut_help.is_some() {
-                                self.diagnostics.push(TyDiagnostic {
-                                    kind: TyDiagnosticKind::CannotMutate,
-                                    file: self.file,
-                                    // making expr the dest isn't technically correct, but it works
-                                    expr: Some(assign_body.dest),
-                                    range: assign_body.range,
-                                    help: non_mut_help,
-                                });
-                                continue;
-                            }
+    /// `#align_cast(ptr, T)` casts `ptr` to a `^T`, checking that it's properly aligned
+    /// when the program runs
+    fn infer_align_cast(&mut self, expr: Idx<Expr>, args: &[Idx<Expr>]) -> InferResult<Intern<Ty>> {
+        let call_range = self.bodies.range_for_expr(expr);
+        let call_end = call_range
+            .end()
+            .checked_sub(TextSize::new(1))
+            .unwrap_or(call_range.end());
+        let call_end = TextRange::new(call_end, call_end);
+
+        let expected = [ExpectedTy::Pointer, ExpectedTy::Concrete(Ty::Type.into())];
+        for expected in expected.into_iter().skip(args.len()) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::MissingArg { expected },
+                file: self.file,
+                expr: Some(expr),
+                range: call_end,
+                help: None,
+            });
+        }
+        for arg in args.iter().skip(2) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ExtraArg {
+                    found: self.tys[self.file][*arg],
+                },
+                file: self.file,
+                expr: Some(expr),
+                range: self.bodies.range_for_expr(*arg),
+                help: None,
+            });
+        }
+        let [ptr, sub_ty, ..] = args else {
+            return Ok(Ty::Error.into());
+        };
 
-                            let source_ty = self.tys[self.file][assign_body.dest];
-                            let value_ty = self.tys[self.file][assign_body.value];
+        let ptr_ty = self.tys[self.file][*ptr];
+        let Some((mutable, _)) = ptr_ty.as_pointer() else {
+            if !ptr_ty.is_error_or_unknown() {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Pointer,
+                        found: ptr_ty,
+                    },
+                    file: self.file,
+                    expr: Some(*ptr),
+                    range: self.bodies.range_for_expr(*ptr),
+                    help: None,
+                });
+            }
+            return Ok(Ty::Error.into());
+        };
 
-                            match assign_body
-                                .quick_assign_op
-                                .map(|op| (op, op.get_possible_output_ty(&source_ty, &value_ty)))
-                            {
-                                Some((op, Some(output_ty))) => {
-                                    if *source_ty != Ty::Unknown
-                                        && *value_ty != Ty::Unknown
-                                        && !op.can_perform(&output_ty.max_ty)
-                                    {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::BinaryOpMismatch {
-                                                op,
-                                                first: source_ty,
-                                                second: value_ty,
-                                            },
-                                            file: self.file,
-                                            // making expr the dest isn't technically correct, but it works
-                                            expr: Some(assign_body.dest),
-                                            range: assign_body.range,
-                                            help: None,
-                                        });
-                                    }
+        let sub_ty_ty = self.tys[self.file][*sub_ty];
+        if *sub_ty_ty != Ty::Type {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::Mismatch {
+                    expected: ExpectedTy::Concrete(Ty::Type.into()),
+                    found: sub_ty_ty,
+                },
+                file: self.file,
+                expr: Some(*sub_ty),
+                range: self.bodies.range_for_expr(*sub_ty),
+                help: None,
+            });
+            return Ok(Ty::Error.into());
+        }
+        let sub_ty = self.const_ty(*sub_ty)?;
 
-                                    let max_ty = output_ty.max_ty.into();
+        Ok(Ty::Pointer { mutable, sub_ty }.into())
+    }
 
-                                    self.replace_weak_tys(assign_body.dest, max_ty);
-                                    self.replace_weak_tys(assign_body.value, max_ty);
-                                }
-                                Some((op, None)) => {
-                                    self.diagnostics.push(TyDiagnostic {
-                                        kind: TyDiagnosticKind::BinaryOpMismatch {
-                                            op,
-                                            first: source_ty,
-                                            second: value_ty,
-                                        },
-                                        file: self.file,
-                                        // making expr the dest isn't technically correct, but it works
-                                        expr: Some(assign_body.dest),
-                                        range: assign_body.range,
-                                        help: None,
-                                    });
-                                }
-                                None => {
-                                    if source_ty.is_weak_replaceable_by(&value_ty) {
-                                        self.replace_weak_tys(assign_body.dest, value_ty);
-                                    } else if self.expect_match(
-                                        value_ty,
-                                        source_ty,
-                                        assign_body.value,
-                                    ) {
-                                        self.replace_weak_tys(assign_body.value, value_ty);
-                                    }
-                                }
-                            }
+    fn check_tail_call(&mut self, directive: Idx<hir::Expr>, args: &[Idx<hir::Expr>]) {
+        let Some((call, extra_args)) = args.split_first() else {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::TailCallNotCall,
+                file: self.file,
+                expr: Some(directive),
+                range: self.bodies.range_for_expr(directive),
+                help: None,
+            });
+            return;
+        };
 
-                            self.find_usages(&[assign_body.dest, assign_body.value], stmt);
-                        }
-                        Stmt::Break { label: None, .. } => {}
-                        Stmt::Break {
-                            label: Some(label),
-                            value,
-                            ..
-                        } => {
-                            let referenced_expr = self.bodies[label];
+        for arg in extra_args {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ExtraArg {
+                    found: self.tys[self.file][*arg],
+                },
+                file: self.file,
+                expr: Some(*arg),
+                range: self.bodies.range_for_expr(*arg),
+                help: None,
+            });
+        }
 
-                            let value_ty = value.map_or_else(
-                                || Ty::Void.into(),
-                                |value| self.tys[self.file][value],
-                            );
+        let (callee, param_tys) = match &self.bodies[*call] {
+            Expr::Call { callee, .. } => match self.tys[self.file][*callee].as_function() {
+                Some((param_tys, _)) => (*callee, param_tys),
+                None => {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::TailCallNotCall,
+                        file: self.file,
+                        expr: Some(*call),
+                        range: self.bodies.range_for_expr(*call),
+                        help: None,
+                    });
+                    return;
+                }
+            },
+            _ => {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::TailCallNotCall,
+                    file: self.file,
+                    expr: Some(*call),
+                    range: self.bodies.range_for_expr(*call),
+                    help: None,
+                });
+                return;
+            }
+        };
 
-                            let must_be_void = matches!(
-                                self.bodies[referenced_expr],
-                                Expr::While {
-                                    condition: Some(_),
-                                    ..
-                                }
-                            );
+        if !self.is_current_function(callee) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::TailCallToOtherFunction,
+                file: self.file,
+                expr: Some(callee),
+                range: self.bodies.range_for_expr(callee),
+                help: None,
+            });
+        } else if param_tys.iter().any(|param| param.varargs) {
+            // the varargs would be packed into the same stack slots every time,
+            // while the previous varargs might still be getting read from them
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::TailCallWithVarArgs,
+                file: self.file,
+                expr: Some(*call),
+                range: self.bodies.range_for_expr(*call),
+                help: None,
+            });
+        }
+    }
 
-                            match self.tys[self.file].expr_tys.get(referenced_expr) {
-                                Some(expected_ty) => {
-                                    self.expect_block_match(
-                                        value,
-                                        value_ty,
-                                        referenced_expr,
-                                        *expected_ty,
-                                    );
-                                }
-                                None => {
-                                    if must_be_void && !value_ty.is_void() {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::Mismatch {
-                                                expected: ExpectedTy::Concrete(Ty::Void.into()),
-                                                found: value_ty,
-                                            },
-                                            file: self.file,
-                                            expr: value,
-                                            range: self.bodies.range_for_expr(value.unwrap()),
-                                            help: None,
-                                        });
+    /// Whether `callee` always refers to the lambda which is currently being inferred
+    fn is_current_function(&self, callee: Idx<hir::Expr>) -> bool {
+        let (Inferrable::Lambda(current) | Inferrable::InstanceBody(current, _)) =
+            self.currently_inferring
+        else {
+            return false;
+        };
 
-                                        self.tys[self.file]
-                                            .expr_tys
-                                            .insert(referenced_expr, Ty::Unknown.into());
-                                    } else {
-                                        self.tys[self.file]
-                                            .expr_tys
-                                            .insert(referenced_expr, value_ty);
-                                    }
-                                }
-                            }
-                        }
-                        Stmt::Continue { .. } => {}
-                        Stmt::Defer { expr, .. } => {
-                            self.find_usages(&[expr], stmt);
-                        }
-                    }
+        // other instances of the same function have different types
+        if let Inferrable::InstanceBody(_, id) = self.currently_inferring {
+            if self.tys[self.file].get_instance(callee) != Some(id) {
+                return false;
+            }
+        }
 
-                    self.inferred_stmts.insert((self.file, stmt));
+        let (file, value) = match &self.bodies[callee] {
+            Expr::Paren(Some(inner)) => return self.is_current_function(*inner),
+            Expr::LocalGlobal(name) => {
+                let fqn = self.world_bodies.resolve_global(self.file, name.name);
+                if !self.world_bodies.exists(fqn) {
+                    return false;
                 }
-            };
-        }
+                (fqn.file, self.world_bodies.body(fqn))
+            }
+            Expr::Member { previous, name } => {
+                let Ty::File(file) = self.tys[self.file][*previous].as_ref() else {
+                    return false;
+                };
+                let fqn = hir::Fqn {
+                    file: *file,
+                    name: name.name,
+                };
+                (*file, self.world_bodies.body(fqn))
+            }
+            Expr::Local(local) => {
+                let local_def = &self.bodies[*local];
+                match local_def.value {
+                    Some(value) if !local_def.mutable => (self.file, value),
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        };
 
-        Ok(self.tys[self.file][expr])
+        file == current.file
+            && matches!(
+                self.world_bodies[file][value],
+                Expr::Lambda(lambda) if lambda == current.lambda
+            )
     }
 
-    /// Only call for blocks which had their type previously set by a `break`
+    /// Reports the breaks (and tail expression) of a block or loop which don't agree on a type.
     ///
-    /// returns the max of the found expression and the current type of the block
-    fn expect_block_match(
+    /// This is done after the whole block has been inferred so that every conflicting break can
+    /// be listed in a single diagnostic, instead of only the first one
+    fn report_break_mismatches(
         &mut self,
-        found_expr: Option<Idx<hir::Expr>>,
-        found_ty: Intern<Ty>,
         block_expr: Idx<hir::Expr>,
-        block_ty: Intern<Ty>,
-    ) -> Option<Intern<Ty>> {
-        if found_ty.is_unknown() || block_ty.is_unknown() {
-            return None;
-        }
+        tail: Option<(Idx<hir::Expr>, Intern<Ty>)>,
+    ) {
+        let Some(id) = self.bodies.block_to_scope_id(block_expr) else {
+            return;
+        };
 
-        if let Some(max) = block_ty.max(&found_ty) {
-            let max = max.into();
-            self.tys[self.file].expr_tys[block_expr] = max;
-            if let Some(found_expr) = found_expr {
-                self.replace_weak_tys(found_expr, max);
+        // (the expression to blame, the range of the break, the type it returns)
+        let mut found = Vec::new();
+        for usage in self.bodies.scope_id_usages(id) {
+            if let Stmt::Break { value, range, .. } = &self.bodies[*usage] {
+                let ty = value.map_or_else(|| Ty::Void.into(), |value| self.tys[self.file][value]);
+
+                found.push((value.unwrap_or(block_expr), *range, ty));
             }
+        }
+        if let Some((tail, tail_ty)) = tail {
+            found.push((tail, self.bodies.range_for_expr(tail), tail_ty));
+        }
 
-            Some(max)
-        } else {
-            // there must be a usage since the block has a type
-            let id = self.bodies.block_to_scope_id(block_expr).unwrap();
-            let first_usage = self.bodies.scope_id_usages(id).iter().next().unwrap();
+        // unknown types have already been reported
+        found.retain(|(_, _, ty)| !ty.is_unknown());
 
-            self.diagnostics.push(TyDiagnostic {
+        let Some(&(_, first_range, first_ty)) = found.first() else {
+            return;
+        };
+
+        let mut max_ty = first_ty;
+        let mut mismatches = Vec::new();
+        for (expr, _, ty) in found.iter().skip(1) {
+            match max_ty.max(ty, self.tys) {
+                Some(max) => max_ty = max.into(),
+                None => mismatches.push((*expr, max_ty, *ty)),
+            }
+        }
+
+        match mismatches.as_slice() {
+            [] => {}
+            [(expr, expected, found_ty)] => self.diagnostics.push(TyDiagnostic {
                 kind: TyDiagnosticKind::Mismatch {
-                    expected: ExpectedTy::Concrete(block_ty),
-                    found: found_ty,
+                    expected: ExpectedTy::Concrete(*expected),
+                    found: *found_ty,
                 },
                 file: self.file,
-                expr: Some(found_expr.unwrap_or(block_expr)),
-                range: self.bodies.range_for_expr(found_expr.unwrap_or(block_expr)),
+                expr: Some(*expr),
+                range: self.bodies.range_for_expr(*expr),
                 help: Some(TyDiagnosticHelp {
-                    kind: TyDiagnosticHelpKind::BreakHere { break_ty: block_ty },
-                    range: self.bodies.range_for_stmt(*first_usage),
+                    kind: TyDiagnosticHelpKind::BreakHere {
+                        break_ty: *expected,
+                    },
+                    range: first_range,
                 }),
-            });
-
-            self.tys[self.file].expr_tys[block_expr] = Ty::Unknown.into();
-
-            None
+            }),
+            _ => self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::BreakMismatch {
+                    breaks: found.iter().map(|(_, range, ty)| (*range, *ty)).collect(),
+                },
+                file: self.file,
+                expr: Some(block_expr),
+                range: self.bodies.range_for_expr(block_expr),
+                help: None,
+            }),
         }
     }
 
@@ -2674,10 +4997,13 @@ impl GlobalInferenceCtx<'_> {
     ) -> bool {
         // if the expression we're checking against is an
         // int literal (which can be inferred into any int type),
-        // then we can just quickly set it's type here
-        if let (hir::Expr::IntLiteral(num), Ty::IInt(bit_width) | Ty::UInt(bit_width)) =
-            (&self.bodies[expr], expected.as_ref())
-        {
+        // then we can just quickly set it's type here.
+        // suffixed literals like `42u8` already have a strong type
+        if let (hir::Expr::IntLiteral(num), Ty::IInt(bit_width) | Ty::UInt(bit_width), None) = (
+            &self.bodies[expr],
+            expected.as_ref(),
+            self.bodies.literal_suffix(expr),
+        ) {
             if *bit_width != u8::MAX {
                 self.tys[self.file].expr_tys[expr] = expected;
             }
@@ -2687,6 +5013,7 @@ impl GlobalInferenceCtx<'_> {
                     self.diagnostics.push(TyDiagnostic {
                         kind: TyDiagnosticKind::IntTooBigForType {
                             found: *num,
+                            written: self.bodies.written_literal(expr).map(str::to_string),
                             max: max_size,
                             ty: expected,
                         },
@@ -2701,11 +5028,32 @@ impl GlobalInferenceCtx<'_> {
             return true;
         }
 
+        // `null` takes on the type of whichever optional it's being used as
+        if expected.as_optional().is_some() && self.coerce_null(expr, expected) {
+            return true;
+        }
+
         if found.is_unknown() || expected.is_unknown() {
             // return false without throwing an error
             return false;
         }
 
+        // any pointer can be turned into an interface, but whether the type it points to has
+        // the right methods is only checked once the whole body is inferred
+        if matches!(expected.absolute_ty(), Ty::Interface { .. }) && found.as_pointer().is_some() {
+            if self.tys[self.file]
+                .interface_casts
+                .get(expr)
+                .is_none_or(|(interface, _)| *interface != expected)
+            {
+                self.tys[self.file]
+                    .interface_casts
+                    .insert(expr, (expected, Vec::new()));
+            }
+
+            return true;
+        }
+
         if !found.can_fit_into(&expected) {
             let help = match self.bodies[expr] {
                 hir::Expr::Block {
@@ -2718,11 +5066,28 @@ impl GlobalInferenceCtx<'_> {
                 _ => None,
             };
 
-            self.diagnostics.push(TyDiagnostic {
-                kind: TyDiagnosticKind::Mismatch {
+            let kind = match (found.as_ref(), expected.as_ref()) {
+                (Ty::Enum { uid, .. }, Ty::Variant { enum_uid, .. }) if uid == enum_uid => {
+                    TyDiagnosticKind::EnumToVariant {
+                        enum_ty: found,
+                        variant_ty: expected,
+                    }
+                }
+                (Ty::Variant { .. }, Ty::Enum { .. }) => TyDiagnosticKind::VariantOfOtherEnum {
+                    variant_ty: found,
+                    enum_ty: expected,
+                },
+                (_, Ty::Pointer { .. }) if self.bodies[expr] == hir::Expr::NullLiteral => {
+                    TyDiagnosticKind::NullToPointer { ty: expected }
+                }
+                _ => TyDiagnosticKind::Mismatch {
                     expected: ExpectedTy::Concrete(expected),
                     found,
                 },
+            };
+
+            self.diagnostics.push(TyDiagnostic {
+                kind,
                 file: self.file,
                 expr: Some(expr),
                 range: self.bodies.range_for_expr(expr),
@@ -2752,8 +5117,8 @@ impl GlobalInferenceCtx<'_> {
                     .ok_or_else(|| vec![Inferrable::Global(fqn)])?
                     .0;
 
-                if *ty == Ty::Unknown {
-                    return Ok(Ty::Unknown.into());
+                if ty.is_error_or_unknown() {
+                    return Ok(ty);
                 }
 
                 if *ty == Ty::NotYetResolved {
@@ -2765,7 +5130,7 @@ impl GlobalInferenceCtx<'_> {
                         help: None,
                     });
 
-                    return Ok(Ty::Unknown.into());
+                    return Ok(Ty::Error.into());
                 }
 
                 if *ty != Ty::Type {
@@ -2781,7 +5146,7 @@ impl GlobalInferenceCtx<'_> {
                             help: None,
                         });
                     }
-                    return Ok(Ty::Unknown.into());
+                    return Ok(Ty::Error.into());
                 }
 
                 let global_body = self.world_bodies.body(fqn);
@@ -2832,6 +5197,16 @@ impl GlobalInferenceCtx<'_> {
                         uid: *uid,
                     }
                     .into(),
+                    Ty::Interface {
+                        fqn: None,
+                        uid,
+                        methods,
+                    } => Ty::Interface {
+                        fqn: Some(fqn),
+                        uid: *uid,
+                        methods: methods.clone(),
+                    }
+                    .into(),
                     Ty::Enum {
                         fqn: None,
                         uid,
@@ -2869,7 +5244,7 @@ impl GlobalInferenceCtx<'_> {
                         }
                         .into();
 
-                        ty::set_enum_uid(*uid, new_ty);
+                        self.tys.add_enum(new_ty);
 
                         new_ty
                     }
@@ -2884,17 +5259,627 @@ impl GlobalInferenceCtx<'_> {
                     range: self.bodies.range_for_expr(file_expr.unwrap()),
                     help: None,
                 });
-                Ok(Ty::Unknown.into())
+                Ok(Ty::Error.into())
             }
             hir::DefinitionStatus::UnknownDefinition => {
+                // `file_expr` is only `None` for names which might've come from a `using`d file
                 self.diagnostics.push(TyDiagnostic {
                     kind: TyDiagnosticKind::UnknownFqn { fqn },
                     file: self.file,
-                    expr: file_expr,
-                    range: self.bodies.range_for_expr(file_expr.unwrap()),
+                    expr: file_expr.or(Some(total_expr)),
+                    range: file_expr.map_or(name_range, |file_expr| {
+                        self.bodies.range_for_expr(file_expr)
+                    }),
+                    help: None,
+                });
+                Ok(Ty::Error.into())
+            }
+        }
+    }
+
+    /// `[_]i32` annotations take their size from the array they're annotating.
+    ///
+    /// This has to be called before `const_ty` gets called on the annotation
+    pub(crate) fn fill_in_array_size(
+        &mut self,
+        annotation: Idx<Expr>,
+        value: Option<Idx<Expr>>,
+    ) -> InferResult<()> {
+        let Expr::ArrayDecl {
+            inferred_size: true,
+            ty: sub_ty,
+            ..
+        } = self.bodies[annotation]
+        else {
+            return Ok(());
+        };
+        let Some(value) = value else {
+            return Ok(());
+        };
+
+        let Some((size, _)) = self.infer_expr(value)?.as_array() else {
+            return Ok(());
+        };
+
+        let sub_ty = self.const_ty(sub_ty)?;
+
+        self.tys[self.file].meta_tys.insert(
+            annotation,
+            Ty::Array {
+                anonymous: false,
+                size,
+                sub_ty,
+            }
+            .into(),
+        );
+
+        Ok(())
+    }
+
+    /// Evaluates the size of an array type or the count of an array repeat literal,
+    /// which must be a `usize` known at compile time
+    fn const_array_size(&mut self, size: Idx<Expr>) -> InferResult<Option<u64>> {
+        // we must infer it manually because it might not
+        // have been inferred.
+        self.infer_expr(size)?;
+        let usize_ty = Ty::UInt(u8::MAX).into();
+        if !self.expect_match(self.tys[self.file][size], usize_ty, size) {
+            return Ok(None);
+        }
+
+        self.replace_weak_tys(size, usize_ty);
+
+        let expr_const = self.get_const(size);
+
+        if !expr_const.is_const() {
+            // a size like `[n]i32` is `Unknown` until `n` gets a value in an instance
+            // of its function, at which point this gets called again
+            if expr_const.should_report_not_const() {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::ArraySizeNotConst,
+                    file: self.file,
+                    range: self.bodies.range_for_expr(size),
+                    expr: Some(size),
+                    help: None,
+                });
+            }
+            return Ok(None);
+        }
+
+        match self.const_data(self.file, size)? {
+            Some(ComptimeResult::Integer { num, .. }) => Ok(Some(num)),
+            _ => {
+                // todo: we check that the array size is a `usize` above,
+                // soo... is this even reachable?
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::ArraySizeNotInt,
+                    file: self.file,
+                    range: self.bodies.range_for_expr(size),
+                    expr: Some(size),
+                    help: None,
+                });
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Returns the value of an array index if it's known at compile time
+    fn const_index(&mut self, index: Idx<Expr>) -> InferResult<Option<u64>> {
+        if let Expr::IntLiteral(index) = self.bodies[index] {
+            return Ok(Some(index));
+        }
+
+        if !self.get_const(index).is_const() {
+            return Ok(None);
+        }
+
+        match self.const_data(self.file, index)? {
+            Some(ComptimeResult::Integer { num, .. }) => Ok(Some(num)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the smallest and largest values an integer expression (like an array index)
+    /// could have.
+    ///
+    /// This only understands constants, immutable locals, and arithmetic between them,
+    /// so `arr[len - 1]` works but anything involving a mutable local gives up
+    fn int_range(&mut self, index: Idx<Expr>) -> InferResult<Option<RangeInclusive<u64>>> {
+        if let Some(index) = self.const_index(index)? {
+            return Ok(Some(index..=index));
+        }
+
+        match self.bodies[index] {
+            Expr::Paren(Some(inner)) => self.int_range(inner),
+            Expr::Local(local) => {
+                let local_def = &self.bodies[local];
+                match local_def.value {
+                    Some(value) if !local_def.mutable => self.int_range(value),
+                    _ => Ok(None),
+                }
+            }
+            Expr::Binary { lhs, rhs, op } => {
+                let (Some(lhs), Some(rhs)) = (self.int_range(lhs)?, self.int_range(rhs)?) else {
+                    return Ok(None);
+                };
+                let (lhs_min, lhs_max) = lhs.into_inner();
+                let (rhs_min, rhs_max) = rhs.into_inner();
+
+                // anything that would overflow (or underflow) isn't worth trying to reason about
+                let range = match op {
+                    hir::BinaryOp::Add => lhs_min
+                        .checked_add(rhs_min)
+                        .zip(lhs_max.checked_add(rhs_max)),
+                    hir::BinaryOp::Sub => lhs_min
+                        .checked_sub(rhs_max)
+                        .zip(lhs_max.checked_sub(rhs_min)),
+                    hir::BinaryOp::Mul => lhs_min
+                        .checked_mul(rhs_min)
+                        .zip(lhs_max.checked_mul(rhs_max)),
+                    hir::BinaryOp::Div if rhs_min > 0 => {
+                        Some((lhs_min / rhs_max, lhs_max / rhs_min))
+                    }
+                    hir::BinaryOp::Mod if rhs_min > 0 => {
+                        if lhs_max < rhs_min {
+                            Some((lhs_min, lhs_max))
+                        } else {
+                            Some((0, lhs_max.min(rhs_max - 1)))
+                        }
+                    }
+                    _ => None,
+                };
+
+                Ok(range.map(|(min, max)| min..=max))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Functions with compile-time parameters don't have a single type they could be used as,
+    /// so they can only ever be called by name
+    fn report_uncalled_const_param_fns(&mut self, body: Idx<Expr>) {
+        let uncalled = self
+            .bodies
+            .descendants(
+                body,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .filter_map(|descendant| match descendant {
+                Descendant::Expr(expr) => Some(expr),
+                Descendant::Stmt(_) => None,
+            })
+            .filter(|expr| {
+                self.tys[self.file]
+                    .expr_tys
+                    .get(*expr)
+                    .is_some_and(|ty| !ty.is_error_or_unknown())
+                    && self.tys[self.file].get_instance(*expr).is_none()
+            })
+            .filter_map(|expr| {
+                let fqn = self.referenced_global(expr)?;
+                const_param_lambda(self.world_bodies, fqn).map(|_| (expr, fqn))
+            })
+            .collect_vec();
+
+        for (expr, fqn) in uncalled.into_iter().rev() {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ConstParamFnNotCalled { fqn },
+                file: self.file,
+                expr: Some(expr),
+                range: self.bodies.range_for_expr(expr),
+                help: None,
+            });
+        }
+    }
+
+    /// The values a closure captured are kept in the stack frame of the function that made it,
+    /// so that closure can't be returned or stored anywhere that outlives the function
+    fn report_escaping_closures(&mut self, body: Idx<Expr>) {
+        let body_scope = self.bodies.block_to_scope_id(body);
+
+        let mut escaping = Vec::new();
+        if let Some(closure) = self.made_closure(body) {
+            escaping.push((closure, true));
+        }
+
+        for descendant in self.bodies.descendants(
+            body,
+            hir::DescentOpts::All {
+                include_lambdas: false,
+            },
+        ) {
+            let Descendant::Stmt(stmt) = descendant else {
+                continue;
+            };
+
+            match &self.bodies[stmt] {
+                Stmt::Break {
+                    label: Some(label),
+                    value: Some(value),
+                    ..
+                } if Some(*label) == body_scope => {
+                    if let Some(closure) = self.made_closure(*value) {
+                        escaping.push((closure, true));
+                    }
+                }
+                Stmt::Assign(assign) => {
+                    let assign_body = &self.bodies[*assign];
+
+                    if self.outlives_body(assign_body.dest) {
+                        if let Some(closure) = self.made_closure(assign_body.value) {
+                            escaping.push((closure, false));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        escaping.sort_unstable_by_key(|(closure, _)| *closure);
+        escaping.dedup_by_key(|(closure, _)| *closure);
+
+        for (closure, returned) in escaping {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::EscapingClosure { returned },
+                file: self.file,
+                expr: Some(closure),
+                range: self.bodies.range_for_expr(closure),
+                help: None,
+            });
+        }
+    }
+
+    /// The lambda with captures made in this body which the expression gives back, if any
+    fn made_closure(&self, expr: Idx<Expr>) -> Option<Idx<Expr>> {
+        match &self.bodies[expr] {
+            Expr::Lambda(lambda) if !self.bodies[*lambda].captures.is_empty() => Some(expr),
+            Expr::Paren(Some(expr))
+            | Expr::Cast {
+                expr: Some(expr), ..
+            } => self.made_closure(*expr),
+            Expr::Block {
+                tail_expr: Some(tail_expr),
+                ..
+            } => self.made_closure(*tail_expr),
+            Expr::If {
+                body, else_branch, ..
+            } => self
+                .made_closure(*body)
+                .or_else(|| else_branch.and_then(|else_branch| self.made_closure(else_branch))),
+            Expr::Local(local) => self.bodies[*local]
+                .value
+                .and_then(|value| self.made_closure(value)),
+            Expr::Tuple(items) | Expr::ArrayLiteral { items, .. } => {
+                items.iter().find_map(|item| self.made_closure(*item))
+            }
+            Expr::StructLiteral { members, .. } => members
+                .iter()
+                .find_map(|member| self.made_closure(member.value)),
+            _ => None,
+        }
+    }
+
+    /// Whether or not the place is somewhere that's still around after this body returns
+    fn outlives_body(&self, place: Idx<Expr>) -> bool {
+        match &self.bodies[place] {
+            Expr::Local(_) | Expr::SwitchLocal(_) | Expr::ForLocal(_) | Expr::Param { .. } => false,
+            // members and items of pointers and slices could be anywhere
+            Expr::Member {
+                previous: source, ..
+            }
+            | Expr::Index { source, .. } => {
+                self.tys[self.file]
+                    .expr_tys
+                    .get(*source)
+                    .is_none_or(|ty| ty.is_pointer() || ty.as_slice().is_some())
+                    || self.outlives_body(*source)
+            }
+            Expr::Paren(Some(expr)) => self.outlives_body(*expr),
+            _ => true,
+        }
+    }
+
+    /// A pointer can be turned into an interface when the type it points to has a function for
+    /// each of the interface's methods, which takes the pointer as its first argument and
+    /// otherwise has exactly the same parameters and return type as the method.
+    ///
+    /// The functions are looked for inside of the struct first, then in the same places
+    /// that `value.method()` looks
+    fn check_interface_casts(&mut self, body: Idx<Expr>) -> InferResult<()> {
+        let casts = self
+            .bodies
+            .descendants(
+                body,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .filter_map(|descendant| match descendant {
+                Descendant::Expr(expr) => Some(expr),
+                Descendant::Stmt(_) => None,
+            })
+            .filter_map(|expr| {
+                let (interface, _) = self.tys[self.file].interface_casts.get(expr)?;
+                Some((expr, *interface))
+            })
+            .collect_vec();
+
+        // everything gets looked up before anything is reported, so that the errors aren't
+        // reported twice if this has to wait for a function's signature
+        let mut found_impls = Vec::with_capacity(casts.len());
+        for (expr, interface) in casts {
+            let pointer_ty = self.tys[self.file][expr];
+            let Ty::Interface { methods, .. } = interface.absolute_ty() else {
+                unreachable!("only interfaces are put into `interface_casts`")
+            };
+
+            let mut impls = Vec::with_capacity(methods.len());
+            for method in methods {
+                impls.push((*method, self.interface_method(pointer_ty, method)?));
+            }
+
+            found_impls.push((expr, pointer_ty, interface, impls));
+        }
+
+        for (expr, pointer_ty, interface, impls) in found_impls {
+            let mut methods = Vec::with_capacity(impls.len());
+
+            for (method, found) in impls {
+                match found {
+                    Ok(fqn) => methods.push(fqn),
+                    Err(found) => {
+                        let (params, return_ty) = method.ty.as_function().unwrap();
+                        let receiver = ParamTy {
+                            ty: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: pointer_ty.as_pointer().unwrap().1,
+                            }
+                            .into(),
+                            varargs: false,
+                            impossible_to_differentiate: false,
+                        };
+
+                        self.diagnostics.push(TyDiagnostic {
+                            kind: TyDiagnosticKind::DoesNotImplement {
+                                ty: pointer_ty,
+                                interface,
+                                method: method.name.0,
+                                expected: Ty::Function {
+                                    param_tys: std::iter::once(receiver).chain(params).collect(),
+                                    return_ty,
+                                }
+                                .into(),
+                                found,
+                            },
+                            file: self.file,
+                            expr: Some(expr),
+                            range: self.bodies.range_for_expr(expr),
+                            help: None,
+                        });
+                    }
+                }
+            }
+
+            if methods.len() == interface.as_interface().unwrap().len() {
+                self.tys[self.file]
+                    .interface_casts
+                    .insert(expr, (interface, methods));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finds the function which implements the method for the value behind the pointer.
+    ///
+    /// If there isn't one, this gives back the type of a function with the same name
+    /// which didn't fit
+    fn interface_method(
+        &self,
+        pointer_ty: Intern<Ty>,
+        method: &MemberTy,
+    ) -> InferResult<Result<hir::Fqn, Option<Intern<Ty>>>> {
+        let (params, return_ty) = method.ty.as_function().unwrap();
+
+        let mut places = Vec::new();
+        let (_, sub_ty) = pointer_ty.as_pointer().unwrap();
+        if let Some(fqn) = self.namespaced_global(sub_ty, method.name) {
+            places.push(fqn);
+        }
+        places.extend(self.free_method_places(pointer_ty, method.name));
+
+        let mut wrong_ty = None;
+        for place in places {
+            for overload in self.function_overloads(place) {
+                let sig = self
+                    .tys
+                    .signatures
+                    .get(&overload)
+                    .ok_or_else(|| vec![Inferrable::Global(overload)])?
+                    .0;
+
+                let Some((found_params, found_return_ty)) = sig.as_function() else {
+                    continue;
+                };
+
+                let fits = found_params.len() == params.len() + 1
+                    && pointer_ty.can_fit_into(&found_params[0].ty)
+                    && found_params[0].ty.as_pointer().is_some()
+                    && found_params[1..]
+                        .iter()
+                        .zip(&params)
+                        .all(|(found, expected)| {
+                            found.varargs == expected.varargs && found.ty.is_equal_to(&expected.ty)
+                        })
+                    && found_return_ty.is_equal_to(&return_ty);
+
+                if fits {
+                    return Ok(Ok(overload));
+                }
+
+                wrong_ty.get_or_insert(sig);
+            }
+        }
+
+        Ok(Err(wrong_ty))
+    }
+
+    /// Calling the method of an interface passes along the pointer inside of the interface,
+    /// so the method can't be used on its own like a normal function
+    fn report_uncalled_interface_methods(&mut self, body: Idx<Expr>) {
+        let descendants = self
+            .bodies
+            .descendants(
+                body,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .filter_map(|descendant| match descendant {
+                Descendant::Expr(expr) => Some(expr),
+                Descendant::Stmt(_) => None,
+            })
+            .collect_vec();
+
+        let callees = descendants
+            .iter()
+            .filter_map(|expr| match &self.bodies[*expr] {
+                Expr::Call { callee, .. } => Some(*callee),
+                _ => None,
+            })
+            .collect::<FxHashSet<_>>();
+
+        for expr in descendants {
+            let Expr::Member { previous, name } = &self.bodies[expr] else {
+                continue;
+            };
+            if callees.contains(&expr)
+                || self.tys[self.file].get_namespaced_member(expr).is_some()
+                || !self.tys[self.file].expr_tys.contains_idx(*previous)
+            {
+                continue;
+            }
+
+            let mut deref_ty = self.tys[self.file][*previous];
+            while let Some((_, sub_ty)) = deref_ty.as_pointer() {
+                deref_ty = sub_ty;
+            }
+
+            if let Ty::Interface { methods, .. } = deref_ty.absolute_ty() {
+                if methods.iter().any(|method| method.name == name.name) {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::InterfaceMethodNotCalled {
+                            method: name.name.0,
+                        },
+                        file: self.file,
+                        expr: Some(expr),
+                        range: self.bodies.range_for_expr(expr),
+                        help: None,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Integer division by zero would crash the program, so that gets reported if the divisor
+    /// is known to be zero at compile time.
+    /// Float division by zero is fine since it just gives infinity.
+    ///
+    /// This is done once the whole body has been inferred, since something like `0 / 0` only
+    /// finds out it's a float division after its weak type gets replaced
+    fn report_divisions_by_zero(&mut self, body: Idx<Expr>) -> InferResult<()> {
+        let divisions = self
+            .bodies
+            .descendants(
+                body,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .filter_map(|descendant| match descendant {
+                Descendant::Expr(expr) => match self.bodies[expr] {
+                    // an overloaded `/` can do whatever it wants with a zero
+                    Expr::Binary { rhs, op, .. }
+                        if self.tys[self.file].get_operator(expr).is_none() =>
+                    {
+                        Some((op, rhs))
+                    }
+                    _ => None,
+                },
+                Descendant::Stmt(stmt) => match self.bodies[stmt] {
+                    Stmt::Assign(assign) => {
+                        let assign = &self.bodies[assign];
+                        assign.quick_assign_op.map(|op| (op, assign.value))
+                    }
+                    _ => None,
+                },
+            })
+            .filter(|(op, _)| matches!(op, hir::BinaryOp::Div | hir::BinaryOp::Mod))
+            .collect_vec();
+
+        // descendants are given back to front
+        for (op, divisor) in divisions.into_iter().rev() {
+            if !matches!(
+                self.tys[self.file][divisor].absolute_ty(),
+                Ty::IInt(_) | Ty::UInt(_)
+            ) {
+                continue;
+            }
+
+            if self.int_range(divisor)? == Some(0..=0) {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::DivideByZero { op },
+                    file: self.file,
+                    expr: Some(divisor),
+                    range: self.bodies.range_for_expr(divisor),
                     help: None,
                 });
-                Ok(Ty::Unknown.into())
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Figures out which dimension of a (possibly multidimensional) array was overflowed
+    /// so that `m[1][9]` can point at the second dimension of `m`
+    fn index_out_of_bounds(
+        &self,
+        source: Idx<Expr>,
+        index: u64,
+        actual_size: u64,
+        array_sub_ty: Intern<Ty>,
+    ) -> TyDiagnosticKind {
+        let mut root = source;
+        let mut dimension = 0;
+        loop {
+            match self.bodies[root] {
+                Expr::Index { source, .. } => {
+                    root = source;
+                    dimension += 1;
+                }
+                Expr::Paren(Some(inner)) => root = inner,
+                _ => break,
+            }
+        }
+
+        let is_multidimensional =
+            dimension > 0 || array_sub_ty.absolute_intern_ty(true).as_array().is_some();
+
+        if is_multidimensional {
+            TyDiagnosticKind::DimensionOutOfBounds {
+                index,
+                actual_size,
+                dimension: dimension + 1,
+                array_ty: self.tys[self.file][root],
+            }
+        } else {
+            TyDiagnosticKind::IndexOutOfBounds {
+                index,
+                actual_size,
+                array_ty: self.tys[self.file][source],
             }
         }
     }
@@ -2911,6 +5896,9 @@ impl GlobalInferenceCtx<'_> {
             *local_ty == Ty::Type && !local_def.mutable
         };
 
+        // unlike `infer_expr`, this can't use `descendants_rev` because `include_local_value`
+        // has to look at `self.tys` while the loop below is modifying it.
+        // type expressions are usually tiny though, so this isn't a big deal
         let descendants = self
             .bodies
             .descendants(
@@ -2931,7 +5919,7 @@ impl GlobalInferenceCtx<'_> {
                     }
 
                     let ty = match &self.bodies[expr] {
-                        Expr::Missing => Ty::Unknown.into(),
+                        Expr::Missing => Ty::Error.into(),
                         Expr::Ref { mutable, expr } => {
                             let sub_ty = self.tys[self.file].get_meta_ty(*expr).unwrap();
 
@@ -2944,13 +5932,13 @@ impl GlobalInferenceCtx<'_> {
                         Expr::Local(local_def) => 'branch: {
                             let local_ty = self.tys[self.file].local_tys[*local_def];
 
-                            if *local_ty == Ty::Unknown {
-                                break 'branch Ty::Unknown.into();
+                            if local_ty.is_error_or_unknown() {
+                                break 'branch local_ty;
                             }
 
                             if *local_ty != Ty::Type {
                                 self.report_non_type(expr, local_ty);
-                                break 'branch Ty::Unknown.into();
+                                break 'branch Ty::Error.into();
                             }
 
                             let local_def = &self.bodies[*local_def];
@@ -2967,21 +5955,18 @@ impl GlobalInferenceCtx<'_> {
                                     }),
                                 });
 
-                                break 'branch Ty::Unknown.into();
+                                break 'branch Ty::Error.into();
                             }
 
                             // this protects against cases like `x ::;`
                             if let Some(value) = local_def.value {
                                 self.tys[self.file].get_meta_ty(value).unwrap()
                             } else {
-                                Ty::Unknown.into()
+                                Ty::Error.into()
                             }
                         }
                         Expr::LocalGlobal(name) => self.fqn_to_ty(
-                            hir::Fqn {
-                                file: self.file,
-                                name: name.name,
-                            },
+                            self.world_bodies.resolve_global(self.file, name.name),
                             None,
                             expr,
                             name.range,
@@ -2995,7 +5980,7 @@ impl GlobalInferenceCtx<'_> {
                                 help: None,
                             });
 
-                            Ty::Unknown.into()
+                            Ty::Error.into()
                         }
                         Expr::Member { previous, name } => {
                             // todo: eventually comment this out and remove recursion
@@ -3032,45 +6017,50 @@ impl GlobalInferenceCtx<'_> {
                                     // let const_ty =
                                     //     self.tys[self.file].get_meta_ty(*previous).unwrap();
                                     let const_ty = self.const_ty(*previous)?;
-                                    match const_ty.as_ref() {
-                                        Ty::Enum { variants, .. } => variants
-                                            .iter()
-                                            .find(|variant| {
-                                                let Ty::Variant { variant_name, .. } =
-                                                    variant.as_ref()
-                                                else {
-                                                    unreachable!(
-                                                        "all variants should be `Ty::Variant`"
-                                                    );
-                                                };
-
-                                                *variant_name == name.name
-                                            })
-                                            .copied()
-                                            .unwrap_or_else(|| {
+                                    if let Some(fqn) = self.namespaced_global(const_ty, name.name) {
+                                        self.fqn_to_ty(fqn, Some(*previous), expr, name.range)?
+                                    } else {
+                                        match const_ty.as_ref() {
+                                            Ty::Enum { variants, .. } => variants
+                                                .iter()
+                                                .find(|variant| {
+                                                    let Ty::Variant { variant_name, .. } =
+                                                        variant.as_ref()
+                                                    else {
+                                                        unreachable!(
+                                                            "all variants should be `Ty::Variant`"
+                                                        );
+                                                    };
+
+                                                    *variant_name == name.name
+                                                })
+                                                .copied()
+                                                .unwrap_or_else(|| {
+                                                    self.diagnostics.push(TyDiagnostic {
+                                                        kind:
+                                                            TyDiagnosticKind::NonExistentVariant {
+                                                                variant_name: name.name.0,
+                                                                enum_ty: const_ty,
+                                                            },
+                                                        file: self.file,
+                                                        expr: Some(expr),
+                                                        range: self.bodies.range_for_expr(expr),
+                                                        help: None,
+                                                    });
+
+                                                    Ty::Error.into()
+                                                }),
+                                            _ => {
                                                 self.diagnostics.push(TyDiagnostic {
-                                                    kind: TyDiagnosticKind::NonExistentVariant {
-                                                        variant_name: name.name.0,
-                                                        enum_ty: const_ty,
-                                                    },
+                                                    kind: TyDiagnosticKind::CantUseAsTy,
                                                     file: self.file,
                                                     expr: Some(expr),
                                                     range: self.bodies.range_for_expr(expr),
                                                     help: None,
                                                 });
 
-                                                Ty::Unknown.into()
-                                            }),
-                                        _ => {
-                                            self.diagnostics.push(TyDiagnostic {
-                                                kind: TyDiagnosticKind::CantUseAsTy,
-                                                file: self.file,
-                                                expr: Some(expr),
-                                                range: self.bodies.range_for_expr(expr),
-                                                help: None,
-                                            });
-
-                                            Ty::Unknown.into()
+                                                Ty::Error.into()
+                                            }
                                         }
                                     }
                                 }
@@ -3080,59 +6070,38 @@ impl GlobalInferenceCtx<'_> {
 
                                     self.report_non_type(expr, expr_ty);
 
-                                    Ty::Unknown.into()
+                                    Ty::Error.into()
                                 }
                             }
                         }
                         Expr::PrimitiveTy(ty) => Ty::from_primitive(*ty).into(),
-                        Expr::ArrayDecl { size, ty } => 'branch: {
+                        Expr::ArrayDecl {
+                            inferred_size: true,
+                            ..
+                        } => {
+                            // `fill_in_array_size` would've already given this a type
+                            self.diagnostics.push(TyDiagnostic {
+                                kind: TyDiagnosticKind::ArraySizeNotInferred,
+                                file: self.file,
+                                range: self.bodies.range_for_expr(expr),
+                                expr: Some(expr),
+                                help: None,
+                            });
+
+                            Ty::Error.into()
+                        }
+                        Expr::ArrayDecl { size, ty, .. } => {
                             let sub_ty = self.tys[self.file].meta_tys[*ty];
 
                             if let Some(size) = size {
-                                // we must infer it manually because it might not
-                                // have been inferred.
-                                let usize_ty = Ty::UInt(u8::MAX).into();
-                                if !self.expect_match(self.tys[self.file][*size], usize_ty, *size) {
-                                    break 'branch Ty::Unknown.into();
-                                }
-
-                                self.replace_weak_tys(*size, usize_ty);
-
-                                let expr_const = self.get_const(*size);
-                                if !expr_const.is_const() {
-                                    println!("not const {expr_const:?}");
-                                    if expr_const.should_report_not_const() {
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::ArraySizeNotConst,
-                                            file: self.file,
-                                            range: self.bodies.range_for_expr(*size),
-                                            expr: Some(*size),
-                                            help: None,
-                                        });
-                                    }
-                                    break 'branch Ty::Unknown.into();
-                                }
-
-                                match self.const_data(self.file, *size)? {
-                                    Some(ComptimeResult::Integer { num, .. }) => Ty::Array {
+                                match self.const_array_size(*size)? {
+                                    Some(size) => Ty::Array {
                                         anonymous: false,
-                                        size: num,
+                                        size,
                                         sub_ty,
                                     }
                                     .into(),
-                                    _ => {
-                                        // todo: we check that the array size is a `usize` above,
-                                        // soo... is this even reachable?
-                                        self.diagnostics.push(TyDiagnostic {
-                                            kind: TyDiagnosticKind::ArraySizeNotInt,
-                                            file: self.file,
-                                            range: self.bodies.range_for_expr(*size),
-                                            expr: Some(*size),
-                                            help: None,
-                                        });
-
-                                        Ty::Unknown.into()
-                                    }
+                                    None => Ty::Error.into(),
                                 }
                             } else {
                                 Ty::Slice { sub_ty }.into()
@@ -3144,6 +6113,36 @@ impl GlobalInferenceCtx<'_> {
                             sub_ty: self.tys[self.file].meta_tys[*ty],
                         }
                         .into(),
+                        Expr::OptionalDecl { ty } => Ty::Optional {
+                            sub_ty: self.tys[self.file].meta_tys[*ty],
+                        }
+                        .into(),
+                        Expr::ClosureDecl { ty } => {
+                            let function_ty = self.tys[self.file].meta_tys[*ty];
+
+                            match function_ty.as_function() {
+                                Some((param_tys, return_ty)) => Ty::Closure {
+                                    param_tys,
+                                    return_ty,
+                                }
+                                .into(),
+                                None => {
+                                    if !function_ty.is_error_or_unknown() {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::Mismatch {
+                                                expected: ExpectedTy::Function,
+                                                found: function_ty,
+                                            },
+                                            file: self.file,
+                                            expr: Some(*ty),
+                                            range: self.bodies.range_for_expr(*ty),
+                                            help: None,
+                                        });
+                                    }
+                                    Ty::Error.into()
+                                }
+                            }
+                        }
                         Expr::StructDecl { uid, members } => Ty::Struct {
                             anonymous: false,
                             fqn: None,
@@ -3160,6 +6159,43 @@ impl GlobalInferenceCtx<'_> {
                                 .collect(),
                         }
                         .into(),
+                        Expr::InterfaceDecl { uid, methods } => {
+                            let mut method_tys = Vec::with_capacity(methods.len());
+
+                            for hir::MemberDecl { name, ty } in methods {
+                                let method_ty = self.tys[self.file].meta_tys[*ty];
+
+                                if !method_ty.is_function() {
+                                    if !method_ty.is_error_or_unknown() {
+                                        self.diagnostics.push(TyDiagnostic {
+                                            kind: TyDiagnosticKind::Mismatch {
+                                                expected: ExpectedTy::Function,
+                                                found: method_ty,
+                                            },
+                                            file: self.file,
+                                            expr: Some(*ty),
+                                            range: self.bodies.range_for_expr(*ty),
+                                            help: None,
+                                        });
+                                    }
+                                    continue;
+                                }
+
+                                if let Some(name) = name {
+                                    method_tys.push(MemberTy {
+                                        name: name.name,
+                                        ty: method_ty,
+                                    });
+                                }
+                            }
+
+                            Ty::Interface {
+                                fqn: None,
+                                uid: *uid,
+                                methods: method_tys,
+                            }
+                            .into()
+                        }
                         Expr::EnumDecl {
                             uid: enum_uid,
                             variants,
@@ -3291,7 +6327,7 @@ impl GlobalInferenceCtx<'_> {
                             }
                             .into();
 
-                            ty::set_enum_uid(*enum_uid, enum_ty);
+                            self.tys.add_enum(enum_ty);
 
                             enum_ty
                         }
@@ -3360,7 +6396,7 @@ impl GlobalInferenceCtx<'_> {
                             if *is_extern || self.bodies[*body] != hir::Expr::Missing {
                                 self.report_non_type(expr, ty);
 
-                                Ty::Unknown.into()
+                                Ty::Error.into()
                             } else {
                                 ty
                             }
@@ -3373,24 +6409,24 @@ impl GlobalInferenceCtx<'_> {
                             if *ty == Ty::Type {
                                 self.tys[self.file].expr_tys.insert(expr, ty);
 
-                                if self.is_safe_to_compile(body)? {
-                                    match (self.eval_comptime)(
-                                        FQComptime {
-                                            file: self.file,
-                                            expr,
-                                            comptime: *comptime,
-                                        },
-                                        self.tys,
-                                    ) {
-                                        ComptimeResult::Type(ty) => ty,
-                                        _ => unreachable!(),
+                                if self.is_safe_to_compile(body)?
+                                    && self.check_comptime_externs(self.file, expr, body)?
+                                {
+                                    match self.eval_comptime_block(FQComptime {
+                                        file: self.file,
+                                        expr,
+                                        comptime: *comptime,
+                                    }) {
+                                        Some(ComptimeResult::Type(ty)) => ty,
+                                        Some(_) => unreachable!(),
+                                        None => Ty::Error.into(),
                                     }
                                 } else {
                                     // println!("#{} is not safe to compile", body.into_raw());
-                                    Ty::Unknown.into()
+                                    Ty::Error.into()
                                 }
                             } else {
-                                Ty::Unknown.into()
+                                Ty::Error.into()
                             }
                         }
                         Expr::Paren(Some(paren_expr)) => {
@@ -3398,6 +6434,13 @@ impl GlobalInferenceCtx<'_> {
                         }
                         // todo: should we remove the void type?
                         Expr::Paren(None) => Ty::Void.into(),
+                        Expr::Tuple(items) => Ty::Tuple {
+                            items: items
+                                .iter()
+                                .map(|item| self.tys[self.file].get_meta_ty(*item).unwrap())
+                                .collect(),
+                        }
+                        .into(),
                         Expr::Block { .. } => todo!("blocks as types"),
                         _ => {
                             // TODO: remove recursion
@@ -3405,7 +6448,7 @@ impl GlobalInferenceCtx<'_> {
 
                             self.report_non_type(expr, expr_ty);
 
-                            Ty::Unknown.into()
+                            Ty::Error.into()
                         }
                     };
 
@@ -3440,18 +6483,75 @@ impl GlobalInferenceCtx<'_> {
                 num: *num,
                 bit_width: 32,
             })),
+            Expr::BoolLiteral(b) => Ok(Some(ComptimeResult::Integer {
+                num: *b as u64,
+                bit_width: 8,
+            })),
+            Expr::StringLiteral(text) => Ok(Some(ComptimeResult::String(text.clone()))),
+            Expr::CharLiteral(ch) => Ok(Some(ComptimeResult::Integer {
+                num: *ch as u64,
+                bit_width: 8,
+            })),
+            Expr::ArrayLiteral { items, .. }
+                if self.tys[file][expr].as_array().is_some_and(|(_, sub_ty)| {
+                    matches!(sub_ty.as_ref(), Ty::UInt(8) | Ty::Char)
+                }) =>
+            {
+                let mut bytes = Vec::with_capacity(items.len());
+
+                for item in items {
+                    // todo: remove recursion
+                    match self.const_data(file, *item)? {
+                        Some(ComptimeResult::Integer { num, .. }) => bytes.push(num as u8),
+                        _ => return Ok(None),
+                    }
+                }
+
+                Ok(Some(ComptimeResult::Bytes(bytes.into_boxed_slice())))
+            }
+            Expr::ArrayRepeat { item, .. }
+                if self.tys[file][expr].as_array().is_some_and(|(_, sub_ty)| {
+                    matches!(sub_ty.as_ref(), Ty::UInt(8) | Ty::Char)
+                }) =>
+            {
+                let (count, _) = self.tys[file][expr].as_array().unwrap();
+
+                // todo: remove recursion
+                match self.const_data(file, *item)? {
+                    Some(ComptimeResult::Integer { num, .. }) => Ok(Some(ComptimeResult::Bytes(
+                        vec![num as u8; count as usize].into_boxed_slice(),
+                    ))),
+                    _ => Ok(None),
+                }
+            }
+            Expr::Cast {
+                expr: Some(inner), ..
+            } => {
+                let from = self.tys[file][*inner];
+                let to = self.tys[file][expr];
+
+                let Some(cast) = from.absolute_ty().primitive_cast(to.absolute_ty()) else {
+                    return Ok(None);
+                };
+
+                // todo: remove recursion
+                let Some(value) = self.const_data(file, *inner)? else {
+                    return Ok(None);
+                };
+
+                Ok(cast.fold(&value, from.absolute_ty(), to.absolute_ty()))
+            }
             Expr::Comptime(comptime) => {
                 let hir::Comptime { body } = self.world_bodies[file][*comptime];
 
-                if self.is_safe_to_compile(body)? {
-                    Ok(Some((self.eval_comptime)(
-                        FQComptime {
-                            file,
-                            expr,
-                            comptime: *comptime,
-                        },
-                        self.tys,
-                    )))
+                if self.is_safe_to_compile(body)?
+                    && self.check_comptime_externs(file, expr, body)?
+                {
+                    Ok(self.eval_comptime_block(FQComptime {
+                        file,
+                        expr,
+                        comptime: *comptime,
+                    }))
                 } else {
                     // println!("#{} is not safe to compile", body.into_raw());
                     Ok(None)
@@ -3469,13 +6569,10 @@ impl GlobalInferenceCtx<'_> {
                 self.const_data(file, local_def.value.unwrap())
             }
             Expr::LocalGlobal(global) => {
-                let fqn = hir::Fqn {
-                    file,
-                    name: global.name,
-                };
+                let fqn = self.world_bodies.resolve_global(file, global.name);
 
                 // todo: remove recursion
-                self.const_data(file, self.world_bodies.body(fqn))
+                self.const_data(fqn.file, self.world_bodies.body(fqn))
             }
             Expr::Member {
                 previous,
@@ -3490,8 +6587,33 @@ impl GlobalInferenceCtx<'_> {
                     // todo: remove recursion
                     self.const_data(*file, self.world_bodies.body(fqn))
                 }
-                _ => Ok(None),
+                _ => {
+                    if let Some(size) = self.array_len(file, *previous, field.name) {
+                        return Ok(Some(ComptimeResult::Integer {
+                            num: size,
+                            bit_width: 64,
+                        }));
+                    }
+
+                    // todo: remove recursion
+                    match self.const_data(file, *previous)? {
+                        Some(ComptimeResult::Struct { ty, members }) => Ok(ty
+                            .as_struct()
+                            .and_then(|member_tys| {
+                                member_tys
+                                    .iter()
+                                    .position(|member_ty| member_ty.name == field.name)
+                            })
+                            .and_then(|idx| members.into_iter().nth(idx))),
+                        _ => Ok(None),
+                    }
+                }
             },
+            Expr::Param { idx, .. } if file == self.file => Ok(self
+                .const_params
+                .as_ref()
+                .and_then(|(_, values)| values.get(*idx as usize).copied().flatten())
+                .map(|num| ComptimeResult::Integer { num, bit_width: 64 })),
             // todo: add the rest of the possible expressions in `is_const`
             _ => Ok(None),
         }
@@ -3501,6 +6623,135 @@ impl GlobalInferenceCtx<'_> {
     // returns true when something was actually unsafe. the fuzzer has already been updated it just
     // needs to be used.
     pub(crate) fn is_safe_to_compile(&mut self, expr: Idx<hir::Expr>) -> InferResult<bool> {
+        self.safe_to_compile.update(self.diagnostics);
+
+        if let Some(safe) = self.safe_to_compile.get(self.currently_inferring, expr) {
+            return Ok(safe);
+        }
+
+        // results which depend on something that hasn't been inferred yet aren't cached
+        let safe = self.is_safe_to_compile_uncached(expr)?;
+
+        self.safe_to_compile
+            .insert(self.currently_inferring, expr, safe);
+
+        Ok(safe)
+    }
+
+    /// Reports the comptime block if it can reach an extern that isn't in `COMPTIME_EXTERNS`.
+    /// Anything else could read a file or the time, and make the build turn out differently
+    /// each time.
+    ///
+    /// Returns `true` if the block is free of them
+    /// Gives back `None` if the block went over one of its limits, which gets reported
+    fn eval_comptime_block(&mut self, comptime: FQComptime) -> Option<ComptimeResult> {
+        // comptime blocks are compiled with the types of their file, not those of an instance.
+        // `ComptimeInConstParamFn` will have already been reported
+        if self.safe_to_compile.exceeded_limits.contains(&comptime)
+            || self
+                .const_params
+                .as_ref()
+                .is_some_and(|(_, values)| values.iter().any(Option::is_some))
+        {
+            return None;
+        }
+
+        match (self.eval_comptime)(comptime, self.tys) {
+            Ok(result) => Some(result),
+            Err(ComptimeLimitExceeded {
+                comptime: exceeded,
+                limit,
+            }) => {
+                self.safe_to_compile.exceeded_limits.insert(comptime);
+
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::ComptimeLimitExceeded { limit },
+                    file: exceeded.file,
+                    expr: Some(exceeded.expr),
+                    range: self.world_bodies[exceeded.file].range_for_expr(exceeded.expr),
+                    help: None,
+                });
+
+                None
+            }
+        }
+    }
+
+    pub(crate) fn check_comptime_externs(
+        &mut self,
+        file: hir::FileName,
+        expr: Idx<Expr>,
+        body: Idx<Expr>,
+    ) -> InferResult<bool> {
+        // the blocks inside of this one are checked first, so that an extern gets reported on the
+        // innermost block that uses it instead of on every block around it
+        let inner_comptimes = self.world_bodies[file]
+            .descendants(
+                body,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            )
+            .filter_map(|desc| match desc {
+                Descendant::Expr(inner) => match self.world_bodies[file][inner] {
+                    Expr::Comptime(comptime) => {
+                        Some((inner, self.world_bodies[file][comptime].body))
+                    }
+                    _ => None,
+                },
+                Descendant::Stmt(_) => None,
+            })
+            .collect_vec();
+
+        let mut inner_reported = false;
+        for (inner, inner_body) in inner_comptimes {
+            inner_reported |= !self.check_comptime_externs(file, inner, inner_body)?;
+        }
+
+        self.is_safe_to_compile(body)?;
+
+        let Some(function) = self
+            .safe_to_compile
+            .extern_calls
+            .get(&(self.currently_inferring, body))
+            .copied()
+        else {
+            return Ok(true);
+        };
+
+        if !inner_reported && self.safe_to_compile.reported_externs.insert((file, expr)) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ComptimeExtern { function },
+                file,
+                expr: Some(expr),
+                range: self.world_bodies[file].range_for_expr(expr),
+                help: None,
+            });
+        }
+
+        Ok(false)
+    }
+
+    /// The types that `inferrable` was inferred with. Instances have their own copy of the types
+    /// of their file, which is only swapped in while that instance is being inferred
+    fn inferred_tys(&self, inferrable: Inferrable) -> &FileInference {
+        match inferrable {
+            Inferrable::Instance(_, id) | Inferrable::InstanceBody(_, id)
+                if !matches!(
+                    self.currently_inferring,
+                    Inferrable::Instance(_, current) | Inferrable::InstanceBody(_, current)
+                        if current == id
+                ) =>
+            {
+                &self.tys.instance(id).tys
+            }
+            _ => &self.tys[inferrable.file()],
+        }
+    }
+
+    fn is_safe_to_compile_uncached(&mut self, expr: Idx<hir::Expr>) -> InferResult<bool> {
+        let root = expr;
+
         let mut checking_stack = vec![(
             self.currently_inferring,
             self.bodies
@@ -3515,13 +6766,6 @@ impl GlobalInferenceCtx<'_> {
 
         // println!("desc: {:#?}", descendants);
 
-        let error_exprs: FxHashSet<_> = self
-            .diagnostics
-            .iter()
-            .filter(|d| d.is_error())
-            .filter_map(|d| Some((d.file, d.expr?)))
-            .collect();
-
         let mut checked = FxHashSet::default();
         checked.insert(self.currently_inferring);
 
@@ -3530,7 +6774,8 @@ impl GlobalInferenceCtx<'_> {
                 break;
             };
 
-            let file = top_inferring.file();
+            let current = *top_inferring;
+            let file = current.file();
 
             let Some(desc) = top_list.pop() else {
                 checking_stack.pop();
@@ -3543,7 +6788,7 @@ impl GlobalInferenceCtx<'_> {
                 Descendant::Expr(expr) => {
                     // println!("checking #{}", expr.into_raw());
 
-                    if error_exprs.contains(&(file, expr)) {
+                    if self.safe_to_compile.error_exprs.contains(&(file, expr)) {
                         if print_dbg {
                             println!(
                                 "{}:{} unsafe {} #{}",
@@ -3556,7 +6801,7 @@ impl GlobalInferenceCtx<'_> {
                         return Ok(false);
                     }
 
-                    if let Some(ty) = self.tys[file].get_meta_ty(expr) {
+                    if let Some(ty) = self.inferred_tys(current).get_meta_ty(expr) {
                         if ty.is_unknown() {
                             if print_dbg {
                                 println!(
@@ -3573,7 +6818,7 @@ impl GlobalInferenceCtx<'_> {
                         continue;
                     }
 
-                    let Some(ty) = self.tys[file].expr_tys.get(expr) else {
+                    let Some(ty) = self.inferred_tys(current).expr_tys.get(expr) else {
                         if print_dbg {
                             println!(
                                 "{}:{} unsafe {} #{}",
@@ -3599,6 +6844,60 @@ impl GlobalInferenceCtx<'_> {
                         return Ok(false);
                     }
 
+                    // the body of an instance is checked with the types it was inferred with
+                    if let Some(id) = self.inferred_tys(current).get_instance(expr) {
+                        let lambda = self.tys.instance(id).lambda;
+                        let new_inf = Inferrable::InstanceBody(lambda, id);
+
+                        if !checked.insert(new_inf) {
+                            continue;
+                        }
+
+                        if !self.all_inferred.contains(&new_inf) {
+                            return Err(vec![new_inf]);
+                        }
+
+                        checking_stack.push((
+                            new_inf,
+                            self.world_bodies[lambda.file]
+                                .descendants(
+                                    self.world_bodies[lambda.file][lambda.lambda].body,
+                                    hir::DescentOpts::All {
+                                        include_lambdas: false,
+                                    },
+                                )
+                                .collect(),
+                        ));
+                        continue;
+                    }
+
+                    // the functions behind an interface end up being called through it
+                    if let Some(methods) = self.inferred_tys(current).get_interface_cast(expr) {
+                        for fqn in methods.iter().copied() {
+                            let new_inf = Inferrable::Global(fqn);
+
+                            if !checked.insert(new_inf) || self.world_bodies.is_extern(fqn) {
+                                continue;
+                            }
+
+                            if !self.all_inferred.contains(&new_inf) {
+                                return Err(vec![new_inf]);
+                            }
+
+                            checking_stack.push((
+                                new_inf,
+                                self.world_bodies[fqn.file]
+                                    .descendants(
+                                        self.world_bodies.body(fqn),
+                                        hir::DescentOpts::All {
+                                            include_lambdas: false,
+                                        },
+                                    )
+                                    .collect(),
+                            ));
+                        }
+                    }
+
                     match &self.world_bodies[file][expr] {
                         Expr::Missing => {
                             if print_dbg {
@@ -3617,25 +6916,40 @@ impl GlobalInferenceCtx<'_> {
                         Expr::BoolLiteral(_) => {}
                         Expr::StringLiteral(_) => {}
                         Expr::CharLiteral(_) => {}
+                        Expr::NullLiteral => {}
                         Expr::Cast { .. } => {}
                         Expr::Ref { .. } => {}
                         Expr::Deref { .. } => {}
-                        Expr::Binary { .. } => {}
+                        Expr::Range { .. } => {}
+                        Expr::Tuple(_) => {}
+                        Expr::Binary { .. }
+                            if self.inferred_tys(current).get_operator(expr).is_none() => {}
+                        Expr::Orelse { .. } => {}
                         Expr::Unary { .. } => {}
                         Expr::ArrayDecl { .. } => {}
                         Expr::ArrayLiteral { .. } => {}
+                        Expr::ArrayRepeat { .. } => {}
                         Expr::Index { .. } => {}
                         Expr::Paren(_) => {}
                         Expr::Block { .. } => {}
                         Expr::If { .. } => {}
                         Expr::While { .. } => {}
+                        Expr::Loop { .. } => {}
+                        Expr::For { .. } => {}
                         Expr::Switch { .. } => {}
                         Expr::Local(_) => {}
                         Expr::SwitchLocal(_) => {}
-                        Expr::LocalGlobal(name) => {
-                            let fqn = hir::Fqn {
-                                file,
-                                name: name.name,
+                        Expr::ForLocal(_) => {}
+                        Expr::Captured(_) => {}
+                        Expr::LocalGlobal(_) | Expr::Binary { .. } => {
+                            let fqn = match &self.world_bodies[file][expr] {
+                                Expr::LocalGlobal(name) => self
+                                    .inferred_tys(current)
+                                    .get_overload(expr)
+                                    .unwrap_or_else(|| {
+                                        self.world_bodies.resolve_global(file, name.name)
+                                    }),
+                                _ => self.inferred_tys(current).get_operator(expr).unwrap(),
                             };
 
                             let new_inf = Inferrable::Global(fqn);
@@ -3646,7 +6960,7 @@ impl GlobalInferenceCtx<'_> {
 
                             checked.insert(new_inf);
 
-                            if self.world_bodies.is_extern(fqn) {
+                            if !self.world_bodies.exists(fqn) || self.world_bodies.is_extern(fqn) {
                                 continue;
                             }
 
@@ -3673,12 +6987,13 @@ impl GlobalInferenceCtx<'_> {
                             previous,
                             name: field,
                         } => {
-                            let previous_ty = self.tys[file][*previous];
+                            let previous_ty = self.inferred_tys(current)[*previous];
+                            let overload = self.inferred_tys(current).get_overload(expr);
                             if let Ty::File(file) = previous_ty.as_ref() {
-                                let fqn = hir::Fqn {
+                                let fqn = overload.unwrap_or(hir::Fqn {
                                     file: *file,
                                     name: field.name,
-                                };
+                                });
 
                                 let new_inf = Inferrable::Global(fqn);
 
@@ -3747,6 +7062,32 @@ impl GlobalInferenceCtx<'_> {
                                         return Ok(false);
                                     }
                                 }
+                            } else if let Some(fqn) =
+                                self.inferred_tys(current).get_namespaced_member(expr)
+                            {
+                                // methods, whether they're declared within a struct or are free
+                                // functions called like `value.method()`
+                                let new_inf = Inferrable::Global(fqn);
+
+                                if !checked.insert(new_inf) || self.world_bodies.is_extern(fqn) {
+                                    continue;
+                                }
+
+                                if !self.all_inferred.contains(&new_inf) {
+                                    return Err(vec![new_inf]);
+                                }
+
+                                checking_stack.push((
+                                    new_inf,
+                                    self.world_bodies[fqn.file]
+                                        .descendants(
+                                            self.world_bodies.body(fqn),
+                                            hir::DescentOpts::All {
+                                                include_lambdas: false,
+                                            },
+                                        )
+                                        .collect(),
+                                ));
                             }
                         }
                         Expr::Call { .. } => {}
@@ -3764,6 +7105,20 @@ impl GlobalInferenceCtx<'_> {
 
                             checked.insert(lambda);
 
+                            // the interpreter doesn't compile instances
+                            if lambda_body.params.iter().any(|param| param.is_const) {
+                                if print_dbg {
+                                    println!(
+                                        "{}:{} unsafe {} #{}",
+                                        file!(),
+                                        line!(),
+                                        file.debug(self.interner),
+                                        expr.into_raw()
+                                    );
+                                }
+                                return Ok(false);
+                            }
+
                             if !self.all_inferred.contains(&lambda) {
                                 return Err(vec![lambda]);
                             }
@@ -3779,6 +7134,20 @@ impl GlobalInferenceCtx<'_> {
                             //     is_type
                             // );
 
+                            if lambda_body.is_extern {
+                                if let Inferrable::Global(fqn) = current {
+                                    let name = self.interner.lookup(fqn.name.0);
+                                    if !COMPTIME_EXTERNS.contains(&name)
+                                        && !COMPTIME_TRAPPED_EXTERNS.contains(&name)
+                                    {
+                                        self.safe_to_compile
+                                            .extern_calls
+                                            .entry((self.currently_inferring, root))
+                                            .or_insert(fqn);
+                                    }
+                                }
+                            }
+
                             // check if the lambda is extern, or it is being used as a type
                             if !lambda_body.is_extern && !is_type {
                                 checking_stack.push((
@@ -3797,8 +7166,11 @@ impl GlobalInferenceCtx<'_> {
                         Expr::Comptime(_) => {}
                         Expr::PrimitiveTy(_) => {}
                         Expr::Distinct { .. } => {}
+                        Expr::OptionalDecl { .. } => {}
+                        Expr::ClosureDecl { .. } => {}
                         Expr::StructDecl { .. } => {}
                         Expr::EnumDecl { .. } => {}
+                        Expr::InterfaceDecl { .. } => {}
                         Expr::StructLiteral { .. } => {}
                         Expr::Import(_) => {}
                         Expr::Directive { .. } => {}
@@ -3830,3 +7202,32 @@ impl GlobalInferenceCtx<'_> {
         Ok(true)
     }
 }
+
+/// Whether a call with these arguments could go to a function with these parameters
+fn args_fit_params(arg_tys: &[Intern<Ty>], params: &[ParamTy]) -> bool {
+    let mut params = params.iter().peekable();
+
+    for arg_ty in arg_tys {
+        loop {
+            let Some(param) = params.peek() else {
+                return false;
+            };
+
+            if !param.varargs {
+                if !arg_ty.can_fit_into(&param.ty) {
+                    return false;
+                }
+                params.next();
+                break;
+            }
+
+            // any argument that doesn't fit into the varargs goes to the next parameter
+            if arg_ty.can_fit_into(&param.ty.as_slice().unwrap()) {
+                break;
+            }
+            params.next();
+        }
+    }
+
+    params.all(|param| param.varargs)
+}
@@ -1,15 +1,18 @@
 mod globals;
+mod serialize;
 mod ty;
 
-use globals::GlobalInferenceCtx;
+use globals::{GlobalInferenceCtx, SafeToCompileCache};
 use hir::{FQComptime, FQLambda, FileName};
 use interner::{Interner, Key};
 use internment::Intern;
 use itertools::Itertools;
 use la_arena::{ArenaMap, Idx};
 use rustc_hash::{FxHashMap, FxHashSet};
+use std::time::Duration;
 use text_size::TextRange;
 
+pub use serialize::DeserializeError;
 use topo::TopoSort;
 pub use ty::*;
 
@@ -25,10 +28,75 @@ pub(crate) use trait_alias;
 
 pub(crate) type InferResult<T> = Result<T, Vec<Inferrable>>;
 
+/// The only externs that comptime blocks are allowed to call.
+///
+/// Printing and allocating can't change the value of a comptime block,
+/// but reading a file, the time, or user input would make every build different.
+/// The compiler-defined functions of `core` are in here too, since they're declared as externs.
+pub const COMPTIME_EXTERNS: &[&str] = &[
+    "puts",
+    "putchar",
+    "malloc",
+    "calloc",
+    "realloc",
+    "free",
+    "memcpy",
+    "memmove",
+    "memset",
+    "memcmp",
+    "strlen",
+    "len",
+    "to_raw",
+    "const_from_raw",
+    "mut_from_raw",
+    "meta_to_raw",
+];
+
+/// Externs that comptime blocks may reach, but which never actually get called.
+///
+/// `core.panic` and `core.assert` end in `exit`, so forbidding it would forbid almost everything.
+/// Instead the JIT swaps it for a function that stops the block and reports the exit status.
+pub const COMPTIME_TRAPPED_EXTERNS: &[&str] = &["exit"];
+
+/// All the types of a project.
+///
+/// `Intern<Ty>`s are stored in a process-wide table, but they're only ever compared by value.
+/// Anything else that decides the identity of a type lives in here instead of in a global,
+/// so separate compilations can be inferred on separate threads at the same time,
+/// and cloning this gives a full snapshot of the project's types.
 #[derive(Debug, Clone, Default)]
 pub struct ProjectInference {
     signatures: FxHashMap<hir::Fqn, Signature>,
     files: FxHashMap<hir::FileName, FileInference>,
+    /// a variant can't contain its enum (since the enum also contains the variant),
+    /// so this is used to get from a variant's `enum_uid` back to the enum type.
+    enums: FxHashMap<u64, Intern<Ty>>,
+    /// every set of values that a function with compile-time parameters gets called with
+    instances: Vec<Instance>,
+}
+
+/// A function with compile-time parameters, along with one set of values for those parameters.
+///
+/// Each instance is checked and compiled separately, since the types of its parameters
+/// (and of anything inside its body) can be different for each set of values
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub fqn: hir::Fqn,
+    pub lambda: FQLambda,
+    /// the values of the compile-time parameters, in the order that they're declared
+    pub consts: Vec<u64>,
+    ty: Option<Intern<Ty>>,
+    /// the types of the whole file that the function is in, except that the function's own
+    /// expressions have the types of this instance
+    tys: FileInference,
+}
+
+impl Instance {
+    /// The type of the function once the compile-time parameters have been filled in
+    #[track_caller]
+    pub fn ty(&self) -> Intern<Ty> {
+        self.ty.expect("the instance hasn't been inferred")
+    }
 }
 
 impl std::ops::Index<hir::Fqn> for ProjectInference {
@@ -60,12 +128,129 @@ pub struct FileInference {
     meta_tys: ArenaMap<Idx<hir::Expr>, Intern<Ty>>,
     local_tys: ArenaMap<Idx<hir::LocalDef>, Intern<Ty>>,
     switch_local_tys: ArenaMap<Idx<hir::SwitchLocal>, Intern<Ty>>,
+    for_local_tys: ArenaMap<Idx<hir::ForLocal>, Intern<Ty>>,
+    /// member expressions that refer to a declaration inside of a struct body,
+    /// either through the struct type (`Point.ORIGIN`) or through a value (`p.len`)
+    namespaced_members: ArenaMap<Idx<hir::Expr>, hir::Fqn>,
+    /// the callees of calls to an overloaded function, along with the overload that was picked
+    overloads: ArenaMap<Idx<hir::Expr>, hir::Fqn>,
+    /// binary expressions like `a + b` which call a function like `__add` instead
+    operators: ArenaMap<Idx<hir::Expr>, hir::Fqn>,
+    /// the callees of calls to a function with compile-time parameters, along with the index of
+    /// the instance that gets called
+    instances: ArenaMap<Idx<hir::Expr>, u32>,
+    /// pointers which get turned into an interface, along with the interface and the function
+    /// that implements each of its methods.
+    /// The functions are only filled in once the whole body has been inferred
+    interface_casts: ArenaMap<Idx<hir::Expr>, (Intern<Ty>, Vec<hir::Fqn>)>,
 }
 
 impl FileInference {
     pub fn get_meta_ty(&self, expr: Idx<hir::Expr>) -> Option<Intern<Ty>> {
         self.meta_tys.get(expr).copied()
     }
+
+    pub fn get_namespaced_member(&self, expr: Idx<hir::Expr>) -> Option<hir::Fqn> {
+        self.namespaced_members.get(expr).copied()
+    }
+
+    /// The overload that a call to an overloaded function actually calls
+    pub fn get_overload(&self, callee: Idx<hir::Expr>) -> Option<hir::Fqn> {
+        self.overloads.get(callee).copied()
+    }
+
+    /// The function that an overloaded operator calls
+    pub fn get_operator(&self, binary: Idx<hir::Expr>) -> Option<hir::Fqn> {
+        self.operators.get(binary).copied()
+    }
+
+    /// The instance that a call to a function with compile-time parameters calls
+    pub fn get_instance(&self, callee: Idx<hir::Expr>) -> Option<u32> {
+        self.instances.get(callee).copied()
+    }
+
+    /// The functions that implement each method of the interface which this expression
+    /// is turned into, in the order that the interface declares them
+    pub fn get_interface_cast(&self, expr: Idx<hir::Expr>) -> Option<&[hir::Fqn]> {
+        self.interface_casts
+            .get(expr)
+            .map(|(_, methods)| methods.as_slice())
+    }
+
+    /// Writes all of `other`'s types over the top of these ones
+    fn overlay(&mut self, other: FileInference) {
+        let FileInference {
+            expr_tys,
+            meta_tys,
+            local_tys,
+            switch_local_tys,
+            for_local_tys,
+            namespaced_members,
+            overloads,
+            operators,
+            instances,
+            interface_casts,
+        } = other;
+
+        fn extend<K, V>(map: &mut ArenaMap<Idx<K>, V>, other: ArenaMap<Idx<K>, V>) {
+            for (idx, value) in other.into_iter() {
+                map.insert(idx, value);
+            }
+        }
+
+        extend(&mut self.expr_tys, expr_tys);
+        extend(&mut self.meta_tys, meta_tys);
+        extend(&mut self.local_tys, local_tys);
+        extend(&mut self.switch_local_tys, switch_local_tys);
+        extend(&mut self.for_local_tys, for_local_tys);
+        extend(&mut self.namespaced_members, namespaced_members);
+        extend(&mut self.overloads, overloads);
+        extend(&mut self.operators, operators);
+        extend(&mut self.instances, instances);
+        extend(&mut self.interface_casts, interface_casts);
+    }
+
+    /// Leaves out everything that `base` already has
+    fn without(self, base: &FileInference) -> FileInference {
+        fn retain_new<K, V: PartialEq>(
+            map: ArenaMap<Idx<K>, V>,
+            base: &ArenaMap<Idx<K>, V>,
+        ) -> ArenaMap<Idx<K>, V> {
+            map.into_iter()
+                .filter(|(idx, value)| base.get(*idx) != Some(value))
+                .collect()
+        }
+
+        FileInference {
+            expr_tys: retain_new(self.expr_tys, &base.expr_tys),
+            meta_tys: retain_new(self.meta_tys, &base.meta_tys),
+            local_tys: retain_new(self.local_tys, &base.local_tys),
+            switch_local_tys: retain_new(self.switch_local_tys, &base.switch_local_tys),
+            for_local_tys: retain_new(self.for_local_tys, &base.for_local_tys),
+            namespaced_members: retain_new(self.namespaced_members, &base.namespaced_members),
+            overloads: retain_new(self.overloads, &base.overloads),
+            operators: retain_new(self.operators, &base.operators),
+            instances: retain_new(self.instances, &base.instances),
+            interface_casts: retain_new(self.interface_casts, &base.interface_casts),
+        }
+    }
+
+    /// The type of a local or parameter, if the body it's in has been inferred
+    pub fn get_scope_def_ty(&self, def: hir::ScopeDef) -> Option<Intern<Ty>> {
+        match def {
+            hir::ScopeDef::Local(local_def) => self.local_tys.get(local_def).copied(),
+            hir::ScopeDef::SwitchLocal(switch_local) => {
+                self.switch_local_tys.get(switch_local).copied()
+            }
+            hir::ScopeDef::ForLocal(for_local) => self.for_local_tys.get(for_local).copied(),
+            hir::ScopeDef::Param { lambda, idx } => match self.expr_tys.get(lambda)?.as_ref() {
+                Ty::Function { param_tys, .. } | Ty::Closure { param_tys, .. } => {
+                    param_tys.get(idx as usize).map(|param| param.ty)
+                }
+                _ => None,
+            },
+        }
+    }
 }
 
 impl std::ops::Index<Idx<hir::Expr>> for FileInference {
@@ -102,9 +287,65 @@ impl std::ops::Index<Idx<hir::SwitchLocal>> for FileInference {
     }
 }
 
+impl std::ops::Index<Idx<hir::ForLocal>> for FileInference {
+    type Output = Intern<Ty>;
+
+    #[track_caller]
+    fn index(&self, for_local: Idx<hir::ForLocal>) -> &Self::Output {
+        &self.for_local_tys[for_local]
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Signature(pub Intern<Ty>);
 
+/// The types of a project as seen from inside of a single function body.
+///
+/// This derefs to the `ProjectInference` it came from, and indexing it works the same way,
+/// except that the file of an instance gives back the types of that instance
+#[derive(Debug, Clone, Copy)]
+pub struct BodyTys<'a> {
+    project: &'a ProjectInference,
+    instance: Option<u32>,
+}
+
+impl<'a> BodyTys<'a> {
+    pub fn instance(&self) -> Option<u32> {
+        self.instance
+    }
+
+    pub fn project(&self) -> &'a ProjectInference {
+        self.project
+    }
+}
+
+impl std::ops::Deref for BodyTys<'_> {
+    type Target = ProjectInference;
+
+    fn deref(&self) -> &Self::Target {
+        self.project
+    }
+}
+
+impl std::ops::Index<hir::Fqn> for BodyTys<'_> {
+    type Output = Signature;
+
+    fn index(&self, fqn: hir::Fqn) -> &Self::Output {
+        &self.project[fqn]
+    }
+}
+
+impl std::ops::Index<hir::FileName> for BodyTys<'_> {
+    type Output = FileInference;
+
+    fn index(&self, file: hir::FileName) -> &Self::Output {
+        match self.instance.map(|id| self.project.instance(id)) {
+            Some(instance) if instance.lambda.file == file => &instance.tys,
+            _ => &self.project[file],
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 #[cfg_attr(not(test), derive(Debug))]
 #[cfg_attr(test, derive(derivative::Derivative), derivative(Debug))]
@@ -132,7 +373,15 @@ impl TyDiagnostic {
 
     pub fn is_error(&self) -> bool {
         // !matches!(self.kind, TyDiagnosticKind::IntTooBigForType { .. })
-        true
+        !matches!(
+            self.kind,
+            TyDiagnosticKind::UnreachableDefault
+                | TyDiagnosticKind::Deprecated { .. }
+                | TyDiagnosticKind::StructLiteralOutOfOrder { .. }
+                | TyDiagnosticKind::MisalignedPointerCast { .. }
+                | TyDiagnosticKind::LargeArrayCopy { .. }
+                | TyDiagnosticKind::SelfComparison
+        )
     }
 }
 
@@ -142,6 +391,9 @@ pub enum ExpectedTy {
     Concrete(Intern<Ty>),
     Enum,
     Variant,
+    Pointer,
+    Optional,
+    Function,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -154,6 +406,12 @@ pub enum TyDiagnosticKind {
         from: Intern<Ty>,
         to: Intern<Ty>,
     },
+    MisalignedPointerCast {
+        from: Intern<Ty>,
+        to: Intern<Ty>,
+        from_align: u32,
+        to_align: u32,
+    },
     BinaryOpMismatch {
         op: hir::BinaryOp,
         first: Intern<Ty>,
@@ -171,6 +429,11 @@ pub enum TyDiagnosticKind {
         first: Intern<Ty>,
         second: Intern<Ty>,
     },
+    /// more than one break (or tail expression) disagrees with the rest of the block.
+    /// every break and the type it returns is listed
+    BreakMismatch {
+        breaks: Vec<(TextRange, Intern<Ty>)>,
+    },
     NonExistentVariant {
         variant_name: Key,
         enum_ty: Intern<Ty>,
@@ -178,11 +441,28 @@ pub enum TyDiagnosticKind {
     IndexNonArray {
         found: Intern<Ty>,
     },
+    NotIterable {
+        found: Intern<Ty>,
+    },
+    RangeBoundNotInt {
+        found: Intern<Ty>,
+    },
     IndexOutOfBounds {
         index: u64,
         actual_size: u64,
         array_ty: Intern<Ty>,
     },
+    DivideByZero {
+        /// either `Div` or `Mod`
+        op: hir::BinaryOp,
+    },
+    DimensionOutOfBounds {
+        index: u64,
+        actual_size: u64,
+        /// starts at 1 for the outermost dimension
+        dimension: usize,
+        array_ty: Intern<Ty>,
+    },
     ExtraArg {
         found: Intern<Ty>,
     },
@@ -192,6 +472,16 @@ pub enum TyDiagnosticKind {
     CalledNonFunction {
         found: Intern<Ty>,
     },
+    /// none of the functions with this name take the given arguments
+    NoMatchingOverload {
+        function: hir::Fqn,
+        found: Vec<Intern<Ty>>,
+    },
+    /// more than one of the functions with this name take the given arguments
+    AmbiguousOverload {
+        function: hir::Fqn,
+        candidates: Vec<Intern<Ty>>,
+    },
     DerefNonPointer {
         found: Intern<Ty>,
     },
@@ -214,6 +504,8 @@ pub enum TyDiagnosticKind {
     LocalTyIsMutable,
     IntTooBigForType {
         found: u64,
+        /// how `found` was written in the source code, if it was something like `0x1FF`
+        written: Option<String>,
         max: u64,
         ty: Intern<Ty>,
     },
@@ -223,21 +515,45 @@ pub enum TyDiagnosticKind {
     UnknownFqn {
         fqn: hir::Fqn,
     },
+    /// a global from a `using`d file has the same name as one that's already in scope
+    UsingAlreadyDefined {
+        fqn: hir::Fqn,
+        defined_in: FileName,
+    },
     NonExistentMember {
         member: Key,
         found_ty: Intern<Ty>,
     },
+    /// `value.method` could refer to more than one free function taking `value`
+    AmbiguousMethod {
+        method: Key,
+        candidates: Vec<hir::Fqn>,
+    },
+    /// accessing a member of the elements of an array or slice, e.g. `points.x`
+    MemberOfCollection {
+        member: Key,
+        collection_ty: Intern<Ty>,
+        element_ty: Intern<Ty>,
+    },
     StructLiteralMissingMember {
         member: Key,
         expected_ty: Intern<Ty>,
     },
     ComptimePointer,
+    /// a comptime block can reach an extern that isn't in `COMPTIME_EXTERNS`
+    ComptimeExtern {
+        function: hir::Fqn,
+    },
+    ComptimeLimitExceeded {
+        limit: ComptimeLimit,
+    },
     GlobalNotConst,
     EntryNotFunction,
     EntryHasParams,
     EntryBadReturn,
     ArraySizeNotInt,
     ArraySizeNotConst,
+    ArraySizeNotInferred,
     DiscriminantNotInt,
     DiscriminantNotConst,
     DiscriminantUsedAlready {
@@ -253,6 +569,32 @@ pub enum TyDiagnosticKind {
     SwitchAlreadyCoversVariant {
         ty: Intern<Ty>,
     },
+    /// every variant already has an arm, so the `_` arm can never run
+    UnreachableDefault,
+    /// a global marked with `@deprecated` is being used
+    Deprecated {
+        fqn: hir::Fqn,
+        message: Option<Key>,
+    },
+    /// a comparison between a value and itself, like `x == x`.
+    /// floats are left out, since `x != x` is how you check if a float is NaN,
+    /// and so are types, since `T == T` is just showing that type ids are stable
+    SelfComparison,
+    /// a big array is copied by value, like `a := big_buffer;`
+    LargeArrayCopy {
+        ty: Intern<Ty>,
+        size: u32,
+    },
+    /// the members of a struct literal are all there, but in a different order than the
+    /// struct declares them in.
+    /// `reordered` has the range of each `name = value` member in the order they should be in.
+    /// `side_effect_free` is true when none of the values could do anything when evaluated,
+    /// so that reordering them can't change what the program does
+    StructLiteralOutOfOrder {
+        expected_ty: Intern<Ty>,
+        reordered: Vec<TextRange>,
+        side_effect_free: bool,
+    },
     ImpossibleToDifferentiateVarArgs {
         previous_ty: Intern<Ty>,
         current_ty: Intern<Ty>,
@@ -264,6 +606,67 @@ pub enum TyDiagnosticKind {
         variant_ty: Intern<Ty>,
         enum_ty: Intern<Ty>,
     },
+    /// an enum was used where one of its own variants was expected
+    EnumToVariant {
+        enum_ty: Intern<Ty>,
+        variant_ty: Intern<Ty>,
+    },
+    /// a variant was used where an enum it doesn't belong to was expected
+    VariantOfOtherEnum {
+        variant_ty: Intern<Ty>,
+        enum_ty: Intern<Ty>,
+    },
+    /// `null` was used where a typed pointer was expected
+    NullToPointer {
+        ty: Intern<Ty>,
+    },
+    /// the argument of `#tailcall` wasn't a function call
+    TailCallNotCall,
+    /// `#tailcall` was used to call a function other than the one it's in
+    TailCallToOtherFunction,
+    /// `#tailcall` was used on a function with variadic parameters
+    TailCallWithVarArgs,
+    /// a math intrinsic like `#sqrt` was given something it can't work on
+    IntrinsicMismatch {
+        name: Key,
+        expected: IntrinsicOperands,
+        found: Intern<Ty>,
+    },
+    /// a parameter used in the type of another parameter (or the return type) wasn't a `usize`
+    ConstParamNotUsize {
+        found: Intern<Ty>,
+    },
+    /// the argument for a compile-time parameter isn't known at compile-time
+    ConstArgNotConst,
+    /// a function with compile-time parameters was used for something other than calling it
+    ConstParamFnNotCalled {
+        fqn: hir::Fqn,
+    },
+    /// the body of a function with compile-time parameters is checked once for each set of
+    /// values, which doesn't work for lambdas and comptime blocks
+    LambdaInConstParamFn,
+    ComptimeInConstParamFn,
+    /// only global functions can be called with compile-time parameters
+    LocalConstParamFn,
+    /// a pointer was turned into an interface, but the type it points to doesn't have a
+    /// function for one of the interface's methods.
+    /// `found` is the type of a function with the right name that didn't fit
+    DoesNotImplement {
+        ty: Intern<Ty>,
+        interface: Intern<Ty>,
+        method: Key,
+        expected: Intern<Ty>,
+        found: Option<Intern<Ty>>,
+    },
+    /// the method of an interface value was used for something other than calling it
+    InterfaceMethodNotCalled {
+        method: Key,
+    },
+    /// a closure was returned from the function that made it, or stored somewhere that outlives
+    /// that function. either way its captured values are gone by the time it gets called
+    EscapingClosure {
+        returned: bool,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -279,29 +682,223 @@ pub enum TyDiagnosticHelpKind {
     ImmutableRef,
     ImmutableParam { assignment: bool },
     ImmutableGlobal,
+    ImmutableCapture,
     NotMutatingRefThroughDeref,
     IfReturnsTypeHere { found: Intern<Ty> },
     MutableVariable,
     TailExprReturnsHere,
     BreakHere { break_ty: Intern<Ty> },
+    IndexCollectionFirst { element_ty: Intern<Ty> },
 }
 
-// todo: I want to make this more expansive. `Data` should be removed and
-// there should be variants for structs, arrays, etc. This will allow indexing
-// and member access at compile-time.
+/// `ComptimeResult::display` cuts arrays off after this many items
+pub const MAX_DISPLAYED_ITEMS: usize = 16;
+
 #[derive(Debug, Clone)]
 pub enum ComptimeResult {
     Type(Intern<Ty>),
-    Integer { num: u64, bit_width: u8 },
-    Float { num: f64, bit_width: u8 },
+    Integer {
+        num: u64,
+        bit_width: u8,
+    },
+    Float {
+        num: f64,
+        bit_width: u8,
+    },
+    /// The contents of a `str`, without the null terminator
+    String(String),
+    /// The contents of a `[_]u8` or `[_]char`
+    Bytes(Box<[u8]>),
+    /// A struct or a tuple, with the value of each member in order
+    Struct {
+        ty: Intern<Ty>,
+        members: Vec<ComptimeResult>,
+    },
+    /// An array of anything other than bytes
+    Array {
+        ty: Intern<Ty>,
+        items: Vec<ComptimeResult>,
+    },
+    /// A value of an enum, or of one of its variants.
+    /// `variant` is the `Ty::Variant` that the value actually holds
+    Variant {
+        ty: Intern<Ty>,
+        variant: Intern<Ty>,
+        payload: Box<ComptimeResult>,
+    },
+    /// The raw bytes of anything that can't be broken down any further, like an `i128`.
+    ///
+    /// Any pointers in here pointed into the memory of the comptime JIT,
+    /// so they can't be followed
     Data(Box<[u8]>),
     Void,
 }
 
+impl ComptimeResult {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ComptimeResult::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Strings are also considered bytes
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ComptimeResult::String(text) => Some(text.as_bytes()),
+            ComptimeResult::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Shows the value the way it would be written in Capy, e.g. `Point.{ x = 1, y = 2 }`.
+    ///
+    /// `ty` is the type the value was evaluated as, which decides things like whether an
+    /// integer is signed or is actually a `bool`.
+    /// Only the first `MAX_DISPLAYED_ITEMS` items of an array are shown
+    pub fn display(
+        &self,
+        ty: Intern<Ty>,
+        mod_dir: &std::path::Path,
+        interner: &Interner,
+    ) -> String {
+        let ty_name = |ty: Intern<Ty>| ty.display_with(mod_dir, interner).short().to_string();
+
+        let list = |items: Vec<(String, Intern<Ty>, &ComptimeResult)>| {
+            let total = items.len();
+            let mut shown = items
+                .into_iter()
+                .take(MAX_DISPLAYED_ITEMS)
+                .map(|(prefix, ty, value)| prefix + &value.display(ty, mod_dir, interner))
+                .collect::<Vec<_>>();
+            if total > MAX_DISPLAYED_ITEMS {
+                shown.push(format!("... {} more", total - MAX_DISPLAYED_ITEMS));
+            }
+            shown.join(", ")
+        };
+
+        match self {
+            ComptimeResult::Type(ty) => ty_name(*ty),
+            ComptimeResult::Integer { num, bit_width } => match ty.absolute_ty() {
+                Ty::Bool => (*num != 0).to_string(),
+                Ty::Char => format!("{:?}", *num as u8 as char),
+                Ty::IInt(_) if (1..64).contains(bit_width) => {
+                    // sign extend from however many bits the integer actually has
+                    let shift = 64 - *bit_width as u32;
+                    (((*num << shift) as i64) >> shift).to_string()
+                }
+                Ty::IInt(_) => (*num as i64).to_string(),
+                _ => num.to_string(),
+            },
+            ComptimeResult::Float { num, .. } => format!("{num:?}"),
+            ComptimeResult::String(text) => format!("{text:?}"),
+            ComptimeResult::Bytes(bytes) => {
+                let sub_ty = ty
+                    .as_array()
+                    .map(|(_, sub_ty)| sub_ty)
+                    .unwrap_or_else(|| Ty::UInt(8).into());
+                let items = bytes
+                    .iter()
+                    .map(|byte| ComptimeResult::Integer {
+                        num: *byte as u64,
+                        bit_width: 8,
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "{}.[{}]",
+                    ty_name(sub_ty),
+                    list(
+                        items
+                            .iter()
+                            .map(|item| (String::new(), sub_ty, item))
+                            .collect()
+                    )
+                )
+            }
+            ComptimeResult::Struct { ty, members } => match ty.absolute_ty() {
+                Ty::Tuple { items } => format!(
+                    "({})",
+                    list(
+                        items
+                            .iter()
+                            .zip(members)
+                            .map(|(ty, value)| (String::new(), *ty, value))
+                            .collect()
+                    )
+                ),
+                Ty::Struct {
+                    anonymous,
+                    members: member_tys,
+                    ..
+                } => {
+                    let members = list(
+                        member_tys
+                            .iter()
+                            .zip(members)
+                            .map(|(member, value)| {
+                                (
+                                    format!("{} = ", interner.lookup(member.name.0)),
+                                    member.ty,
+                                    value,
+                                )
+                            })
+                            .collect(),
+                    );
+                    let prefix = if *anonymous {
+                        String::new()
+                    } else {
+                        ty_name(*ty)
+                    };
+                    if members.is_empty() {
+                        format!("{prefix}.{{}}")
+                    } else {
+                        format!("{prefix}.{{ {members} }}")
+                    }
+                }
+                _ => "<unknown>".to_string(),
+            },
+            ComptimeResult::Array { ty, items } => {
+                let sub_ty = ty.as_array().map_or(*ty, |(_, sub_ty)| sub_ty);
+                format!(
+                    "{}.[{}]",
+                    ty_name(sub_ty),
+                    list(
+                        items
+                            .iter()
+                            .map(|item| (String::new(), sub_ty, item))
+                            .collect()
+                    )
+                )
+            }
+            ComptimeResult::Variant {
+                variant, payload, ..
+            } => {
+                let Ty::Variant { sub_ty, .. } = variant.as_ref() else {
+                    return ty_name(*variant);
+                };
+                match payload.as_ref() {
+                    ComptimeResult::Void => ty_name(*variant),
+                    payload => format!(
+                        "{}.({})",
+                        ty_name(*variant),
+                        payload.display(*sub_ty, mod_dir, interner)
+                    ),
+                }
+            }
+            ComptimeResult::Data(bytes) => format!("<{} bytes>", bytes.len()),
+            ComptimeResult::Void => "{}".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Inferrable {
     Global(hir::Fqn),
     Lambda(FQLambda),
+    /// the signature of an instance of a function with compile-time parameters
+    Instance(FQLambda, u32),
+    /// the body of an instance, which can only be inferred once the signature is known
+    InstanceBody(FQLambda, u32),
 }
 
 impl Inferrable {
@@ -313,20 +910,54 @@ impl Inferrable {
                 lambda.file.debug(interner),
                 lambda.expr.into_raw()
             ),
+            Inferrable::Instance(lambda, id) => format!(
+                "instance {id} of lambda {} #{}",
+                lambda.file.debug(interner),
+                lambda.expr.into_raw()
+            ),
+            Inferrable::InstanceBody(lambda, id) => format!(
+                "body of instance {id} of lambda {} #{}",
+                lambda.file.debug(interner),
+                lambda.expr.into_raw()
+            ),
         }
     }
 
     fn file(&self) -> FileName {
         match self {
             Inferrable::Global(fqn) => fqn.file,
-            Inferrable::Lambda(fql) => fql.file,
+            Inferrable::Lambda(fql)
+            | Inferrable::Instance(fql, _)
+            | Inferrable::InstanceBody(fql, _) => fql.file,
         }
     }
 }
 
+/// A limit that the evaluation of a comptime block went over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComptimeLimit {
+    /// the block ran for longer than this
+    Time(Duration),
+    /// the block had more than this many bytes allocated at once
+    Memory(u64),
+    /// the block called `exit` with this status, which would have ended the compiler
+    Exit(i32),
+    /// the block called an extern that isn't in `COMPTIME_EXTERNS`,
+    /// in a way that `ComptimeExtern` couldn't see coming
+    Extern,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComptimeLimitExceeded {
+    /// the block that was running when the limit was hit,
+    /// which might be nested inside of the one that was asked for
+    pub comptime: FQComptime,
+    pub limit: ComptimeLimit,
+}
+
 trait_alias! {
     pub EvalComptimeFn:
-    FnMut(FQComptime, &ProjectInference) -> ComptimeResult
+    FnMut(FQComptime, &ProjectInference) -> Result<ComptimeResult, ComptimeLimitExceeded>
 }
 
 pub struct InferenceResult {
@@ -335,6 +966,18 @@ pub struct InferenceResult {
     pub any_were_unsafe_to_compile: bool,
 }
 
+/// How far along `InferenceCtx::finish` is. `total` can grow while inferring, since lambdas
+/// are only discovered once the globals containing them are inferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InferenceProgress {
+    pub inferred: usize,
+    pub total: usize,
+    /// how many globals had to be inferred as part of a dependency cycle
+    pub cyclic: usize,
+}
+
+type OnGlobalFn<'a> = dyn FnMut(hir::Fqn, Intern<Ty>) + 'a;
+
 pub struct InferenceCtx<'a, F: EvalComptimeFn> {
     world_index: &'a hir::WorldIndex,
     world_bodies: &'a hir::WorldBodies,
@@ -344,7 +987,15 @@ pub struct InferenceCtx<'a, F: EvalComptimeFn> {
     to_infer: TopoSort<Inferrable>,
     inferred_stmts: FxHashSet<(hir::FileName, Idx<hir::Stmt>)>,
     diagnostics: Vec<TyDiagnostic>,
+    safe_to_compile: SafeToCompileCache,
     eval_comptime: F,
+    lint_member_order: bool,
+    on_progress: Option<Box<dyn FnMut(InferenceProgress) + 'a>>,
+    on_global: Option<Box<OnGlobalFn<'a>>>,
+    /// the statements of each instance's body which have already been inferred
+    instance_stmts: FxHashMap<u32, FxHashSet<(hir::FileName, Idx<hir::Stmt>)>>,
+    /// the diagnostics which came from the bodies of instances
+    instance_diagnostics: Vec<usize>,
 }
 
 impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
@@ -363,12 +1014,82 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             all_inferred: Default::default(),
             to_infer: Default::default(),
             inferred_stmts: Default::default(),
+            safe_to_compile: Default::default(),
             eval_comptime,
+            lint_member_order: false,
+            on_progress: None,
+            on_global: None,
+            instance_stmts: FxHashMap::default(),
+            instance_diagnostics: Vec::new(),
         }
     }
 
+    /// Warn about struct literals which don't give their members in the same order as the
+    /// struct declares them
+    pub fn lint_member_order(mut self, lint: bool) -> Self {
+        self.lint_member_order = lint;
+        self
+    }
+
+    /// Called every time a global or lambda finishes being inferred
+    pub fn on_progress(mut self, on_progress: impl FnMut(InferenceProgress) + 'a) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    /// Called with the type of each global as soon as it's known
+    pub fn on_global(mut self, on_global: impl FnMut(hir::Fqn, Intern<Ty>) + 'a) -> Self {
+        self.on_global = Some(Box::new(on_global));
+        self
+    }
+
     /// only pass `None` to `entry_point` if your testing type checking and you don't want to worry
     /// about the entry point
+    /// Every name brought into a file by `using` has to be unique within that file,
+    /// otherwise it'd be ambiguous which global a reference is talking about
+    fn check_usings(&mut self) {
+        let mut files = self.world_index.get_all_files();
+        files.sort_unstable_by_key(|(file, _)| *file);
+
+        for (file, index) in files {
+            let mut in_scope: FxHashMap<hir::Name, FileName> =
+                index.definitions().map(|name| (name, file)).collect();
+
+            for (used_file, range) in self.world_bodies[file].usings() {
+                let Some(used_index) = self.world_index.get_file(*used_file) else {
+                    continue;
+                };
+
+                // declarations within structs and modules can't be referred to by a bare name
+                let mut names = used_index
+                    .definitions()
+                    .filter(|name| !self.interner.lookup(name.0).contains('.'))
+                    .collect_vec();
+                names.sort_unstable();
+
+                for name in names {
+                    if let Some(defined_in) = in_scope.get(&name) {
+                        self.diagnostics.push(TyDiagnostic {
+                            kind: TyDiagnosticKind::UsingAlreadyDefined {
+                                fqn: hir::Fqn {
+                                    file: *used_file,
+                                    name,
+                                },
+                                defined_in: *defined_in,
+                            },
+                            file,
+                            expr: None,
+                            range: *range,
+                            help: None,
+                        });
+                    } else {
+                        in_scope.insert(name, *used_file);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn finish(
         mut self,
         entry_point: Option<hir::Fqn>,
@@ -378,6 +1099,8 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             self.tys.files.insert(module, FileInference::default());
         }
 
+        self.check_usings();
+
         self.to_infer.extend(
             self.world_index
                 .get_all_files()
@@ -408,6 +1131,8 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
 
         const DEBUG: bool = false;
 
+        let mut cyclic_count = 0;
+
         loop {
             if DEBUG {
                 println!("another loop");
@@ -434,9 +1159,10 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
                     let nyr = Signature(Ty::NotYetResolved.into());
 
                     for inferrable in &cyclic {
-                        println!("cyclic: {:?}", inferrable);
+                        println!("cyclic: {}", inferrable.to_string(self.interner));
                         if let Inferrable::Global(fqn) = inferrable {
                             self.tys.signatures.insert(*fqn, nyr);
+                            cyclic_count += 1;
                         }
                     }
 
@@ -454,6 +1180,22 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
                 match self.infer(inferrable) {
                     Ok(_) => {
                         self.to_infer.remove(&inferrable);
+
+                        if let (Inferrable::Global(fqn), Some(on_global)) =
+                            (inferrable, &mut self.on_global)
+                        {
+                            if let Some(Signature(ty)) = self.tys.signatures.get(&fqn) {
+                                on_global(fqn, *ty);
+                            }
+                        }
+
+                        if let Some(on_progress) = &mut self.on_progress {
+                            on_progress(InferenceProgress {
+                                inferred: self.all_inferred.len(),
+                                total: self.all_inferred.len() + self.to_infer.len(),
+                                cyclic: cyclic_count,
+                            });
+                        }
                     }
                     Err(deps) => {
                         self.to_infer.insert_deps(inferrable, deps);
@@ -466,6 +1208,72 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             }
         }
 
+        // comptime blocks which weren't needed during inference still get evaluated before
+        // codegen, so they have to be checked as well
+        for (file, index) in self.world_index.get_all_files() {
+            for name in index.definitions().sorted() {
+                let fqn = hir::Fqn { file, name };
+
+                // the bodies of functions with compile-time parameters are only ever inferred
+                // as instances, and they can't contain comptime blocks anyways
+                if self.world_bodies.is_extern(fqn)
+                    || !self.world_bodies.exists(fqn)
+                    || globals::const_param_lambda(self.world_bodies, fqn).is_some()
+                {
+                    continue;
+                }
+
+                let comptimes = self.world_bodies[file]
+                    .descendants(
+                        self.world_bodies.body(fqn),
+                        hir::DescentOpts::All {
+                            include_lambdas: true,
+                        },
+                    )
+                    .filter_map(|desc| match desc {
+                        hir::Descendant::Expr(expr) => match self.world_bodies[file][expr] {
+                            hir::Expr::Comptime(comptime) => {
+                                Some((expr, self.world_bodies[file][comptime].body))
+                            }
+                            _ => None,
+                        },
+                        hir::Descendant::Stmt(_) => None,
+                    })
+                    .collect_vec();
+
+                if comptimes.is_empty() {
+                    continue;
+                }
+
+                let mut global_ctx = GlobalInferenceCtx {
+                    file,
+                    currently_inferring: Inferrable::Global(fqn),
+                    world_index: self.world_index,
+                    world_bodies: self.world_bodies,
+                    bodies: &self.world_bodies[file],
+                    interner: self.interner,
+                    local_usages: Default::default(),
+                    usage_worklist: Default::default(),
+                    optional_body: None,
+                    tys: &mut self.tys,
+                    param_tys: Vec::new(),
+                    all_inferred: &self.all_inferred,
+                    inferred_stmts: &mut self.inferred_stmts,
+                    to_infer: &mut self.to_infer,
+                    diagnostics: &mut self.diagnostics,
+                    safe_to_compile: &mut self.safe_to_compile,
+                    eval_comptime: &mut self.eval_comptime,
+                    lint_member_order: self.lint_member_order,
+                    const_params: None,
+                };
+
+                for (expr, body) in comptimes {
+                    // anything that isn't inferred will have already been reported as cyclic
+                    let _ = global_ctx.check_comptime_externs(file, expr, body);
+                }
+            }
+        }
+
         let mut any_were_unsafe_to_compile = false;
 
         if track_unsafe_to_compile {
@@ -478,7 +1286,10 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
                     _ => None,
                 })
             {
-                if self.world_bodies.is_extern(fqn) {
+                // only the instances of these get compiled
+                if self.world_bodies.is_extern(fqn)
+                    || globals::const_param_lambda(self.world_bodies, fqn).is_some()
+                {
                     continue;
                 }
 
@@ -490,13 +1301,18 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
                     bodies: &self.world_bodies[fqn.file],
                     interner: self.interner,
                     local_usages: Default::default(),
+                    usage_worklist: Default::default(),
+                    optional_body: None,
                     tys: &mut self.tys,
                     param_tys: Vec::new(),
                     all_inferred: &self.all_inferred,
                     inferred_stmts: &mut self.inferred_stmts,
                     to_infer: &mut self.to_infer,
                     diagnostics: &mut self.diagnostics,
+                    safe_to_compile: &mut self.safe_to_compile,
                     eval_comptime: &mut self.eval_comptime,
+                    lint_member_order: self.lint_member_order,
+                    const_params: None,
                 };
 
                 let body = self.world_bodies.body(fqn);
@@ -571,6 +1387,21 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             }
         }
 
+        // anything outside of an instance's function has to have the same types as the rest of
+        // the file, including whatever was inferred after the instance was
+        for id in 0..self.tys.instances.len() {
+            let mut tys = self.instance_base(self.tys.instances[id].lambda);
+            tys.overlay(std::mem::take(&mut self.tys.instances[id].tys));
+            self.tys.instances[id].tys = tys;
+        }
+
+        // instances of the same function usually run into the same problems
+        for idx in self.instance_diagnostics.iter().rev() {
+            if self.diagnostics[..*idx].contains(&self.diagnostics[*idx]) {
+                self.diagnostics.remove(*idx);
+            }
+        }
+
         self.tys.shrink_to_fit();
 
         InferenceResult {
@@ -588,6 +1419,8 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
         match inferrable {
             Inferrable::Global(fqn) => self.infer_fqn(fqn)?,
             Inferrable::Lambda(lambda) => self.infer_lambda(lambda)?,
+            Inferrable::Instance(lambda, id) => self.infer_instance(lambda, id, false)?,
+            Inferrable::InstanceBody(lambda, id) => self.infer_instance(lambda, id, true)?,
         }
 
         self.all_inferred.insert(inferrable);
@@ -604,13 +1437,18 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             bodies: &self.world_bodies[fqn.file],
             interner: self.interner,
             local_usages: Default::default(),
+            usage_worklist: Default::default(),
+            optional_body: None,
             inferred_stmts: &mut self.inferred_stmts,
             tys: &mut self.tys,
             param_tys: Default::default(),
             all_inferred: &self.all_inferred,
             to_infer: &mut self.to_infer,
             diagnostics: &mut self.diagnostics,
+            safe_to_compile: &mut self.safe_to_compile,
             eval_comptime: &mut self.eval_comptime,
+            lint_member_order: self.lint_member_order,
+            const_params: None,
         };
 
         let had_previous = global_ctx.tys.signatures.contains_key(&fqn);
@@ -625,7 +1463,13 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
         }
 
         let ty_annotation = match self.world_bodies.ty(fqn) {
-            Some(ty) => match global_ctx.const_ty(ty) {
+            Some(ty) => match global_ctx
+                .fill_in_array_size(
+                    ty,
+                    (!self.world_bodies.is_extern(fqn)).then(|| self.world_bodies.body(fqn)),
+                )
+                .and_then(|_| global_ctx.const_ty(ty))
+            {
                 Ok(ty) => Some(ty),
                 Err(why) => {
                     global_ctx.tys.signatures.remove(&fqn);
@@ -647,7 +1491,7 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
                         help: None,
                     });
 
-                    Ty::Unknown.into()
+                    Ty::Error.into()
                 }
             };
 
@@ -684,7 +1528,7 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             return Ok(());
         }
 
-        let (param_tys, return_ty) = self.tys[fql.file][fql.expr].as_function().unwrap();
+        let (param_tys, return_ty) = self.tys[fql.file][fql.expr].as_callable().unwrap();
 
         let mut global_ctx = GlobalInferenceCtx {
             file: fql.file,
@@ -694,44 +1538,234 @@ impl<'a, F: EvalComptimeFn> InferenceCtx<'a, F> {
             bodies: &self.world_bodies[fql.file],
             interner: self.interner,
             local_usages: Default::default(),
+            usage_worklist: Default::default(),
+            optional_body: None,
             inferred_stmts: &mut self.inferred_stmts,
             tys: &mut self.tys,
             param_tys,
             all_inferred: &self.all_inferred,
             to_infer: &mut self.to_infer,
             diagnostics: &mut self.diagnostics,
+            safe_to_compile: &mut self.safe_to_compile,
             eval_comptime: &mut self.eval_comptime,
+            lint_member_order: self.lint_member_order,
+            const_params: None,
         };
 
         global_ctx.finish_body(body, Some(return_ty), false)?;
 
         Ok(())
     }
-}
 
-impl ProjectInference {
-    /// This might be slightly superficial in some scenarios, I'm not sure
-    pub fn all_tys(&self) -> impl Iterator<Item = Intern<Ty>> + '_ {
-        self.signatures
-            .values()
-            .map(|Signature(ty)| *ty)
-            .chain(self.files.values().flat_map(|tys| {
+    /// The types of the file that `lambda` is in, minus those of its signature.
+    ///
+    /// The signature was already inferred once without any values for the compile-time
+    /// parameters, and none of those types should be reused by an instance
+    fn instance_base(&self, lambda: FQLambda) -> FileInference {
+        let hir::Lambda {
+            params, return_ty, ..
+        } = &self.world_bodies[lambda.file][lambda.lambda];
+        let bodies = &self.world_bodies[lambda.file];
+
+        let mut tys = self.tys.files[&lambda.file].clone();
+        tys.expr_tys.remove(lambda.expr);
+        for ty in params.iter().map(|param| param.ty).chain(*return_ty) {
+            for desc in bodies.descendants(
+                ty,
+                hir::DescentOpts::All {
+                    include_lambdas: false,
+                },
+            ) {
+                if let hir::Descendant::Expr(expr) = desc {
+                    tys.expr_tys.remove(expr);
+                    tys.meta_tys.remove(expr);
+                }
+            }
+        }
+
+        tys
+    }
+
+    /// Infers either the signature or the body of an instance.
+    ///
+    /// The types of the function's file get swapped out for the instance's own types while
+    /// this happens, so that the same expressions can have different types in each instance
+    fn infer_instance(&mut self, fql: FQLambda, id: u32, body: bool) -> InferResult<()> {
+        let hir::Lambda {
+            params,
+            body: lambda_body,
+            ..
+        } = &self.world_bodies[fql.file][fql.lambda];
+        let bodies = &self.world_bodies[fql.file];
+
+        let instance = &mut self.tys.instances[id as usize];
+        let mut consts = instance.consts.iter().copied();
+        let const_args = params
+            .iter()
+            .map(|param| param.is_const.then(|| consts.next().unwrap()))
+            .collect_vec();
+        let instance_tys = std::mem::take(&mut instance.tys);
+
+        let base = self.instance_base(fql);
+        let mut tys = base.clone();
+        tys.overlay(instance_tys);
+
+        let file_tys = std::mem::replace(&mut self.tys[fql.file], tys);
+        let mut inferred_stmts = self.instance_stmts.remove(&id).unwrap_or_default();
+        let diagnostics_before = self.diagnostics.len();
+
+        let mut global_ctx = GlobalInferenceCtx {
+            file: fql.file,
+            currently_inferring: if body {
+                Inferrable::InstanceBody(fql, id)
+            } else {
+                Inferrable::Instance(fql, id)
+            },
+            world_index: self.world_index,
+            world_bodies: self.world_bodies,
+            bodies,
+            interner: self.interner,
+            local_usages: Default::default(),
+            usage_worklist: Default::default(),
+            optional_body: None,
+            inferred_stmts: &mut inferred_stmts,
+            tys: &mut self.tys,
+            param_tys: Default::default(),
+            all_inferred: &self.all_inferred,
+            to_infer: &mut self.to_infer,
+            diagnostics: &mut self.diagnostics,
+            safe_to_compile: &mut self.safe_to_compile,
+            eval_comptime: &mut self.eval_comptime,
+            lint_member_order: self.lint_member_order,
+            const_params: Some((fql.lambda, const_args)),
+        };
+
+        let result = if body {
+            let (param_tys, return_ty) = global_ctx.tys.instances[id as usize]
+                .ty()
+                .as_function()
+                .unwrap();
+            global_ctx.param_tys = param_tys;
+
+            global_ctx
+                .finish_body(*lambda_body, Some(return_ty), false)
+                .map(|_| ())
+        } else {
+            global_ctx.infer_expr(fql.expr).map(|ty| {
+                global_ctx.tys.instances[id as usize].ty = Some(ty);
+                global_ctx
+                    .to_infer
+                    .insert(Inferrable::InstanceBody(fql, id));
+            })
+        };
+
+        // only what's different gets kept, since the rest of the file can still change
+        self.tys.instances[id as usize].tys =
+            std::mem::replace(&mut self.tys[fql.file], file_tys).without(&base);
+        self.instance_stmts.insert(id, inferred_stmts);
+        self.instance_diagnostics
+            .extend(diagnostics_before..self.diagnostics.len());
+
+        result
+    }
+}
+
+impl ProjectInference {
+    /// The same as indexing, except it doesn't panic for a global that was never inferred
+    pub fn get_signature(&self, fqn: hir::Fqn) -> Option<Signature> {
+        self.signatures.get(&fqn).copied()
+    }
+
+    /// This might be slightly superficial in some scenarios, I'm not sure
+    pub fn all_tys(&self) -> impl Iterator<Item = Intern<Ty>> + '_ {
+        self.signatures
+            .values()
+            .map(|Signature(ty)| *ty)
+            .chain(self.files.values().flat_map(|tys| {
                 tys.meta_tys
                     .values()
                     .copied()
                     .chain(tys.expr_tys.values().copied())
                     .chain(tys.local_tys.values().copied())
             }))
+            .chain(self.instances.iter().flat_map(|instance| {
+                instance
+                    .ty
+                    .into_iter()
+                    .chain(instance.tys.meta_tys.values().copied())
+                    .chain(instance.tys.expr_tys.values().copied())
+                    .chain(instance.tys.local_tys.values().copied())
+            }))
             .unique()
     }
 
+    /// A function with compile-time parameters, along with the values it was called with
+    #[track_caller]
+    pub fn instance(&self, id: u32) -> &Instance {
+        &self.instances[id as usize]
+    }
+
+    /// The types to use when compiling a function body, which are only different from the
+    /// types of the rest of the file for instances of functions with compile-time parameters
+    pub fn body_tys(&self, instance: Option<u32>) -> BodyTys<'_> {
+        BodyTys {
+            project: self,
+            instance,
+        }
+    }
+
+    /// Gives back the index of the instance of `lambda` with the given values for its
+    /// compile-time parameters, adding a new instance if it doesn't exist yet
+    pub(crate) fn get_or_add_instance(
+        &mut self,
+        fqn: hir::Fqn,
+        lambda: FQLambda,
+        consts: Vec<u64>,
+    ) -> u32 {
+        if let Some(id) = self
+            .instances
+            .iter()
+            .position(|instance| instance.fqn == fqn && instance.consts == consts)
+        {
+            return id as u32;
+        }
+
+        self.instances.push(Instance {
+            fqn,
+            lambda,
+            consts,
+            ty: None,
+            tys: FileInference::default(),
+        });
+
+        self.instances.len() as u32 - 1
+    }
+
+    #[track_caller]
+    pub(crate) fn enum_from_uid(&self, enum_uid: u64) -> Intern<Ty> {
+        self.enums[&enum_uid]
+    }
+
+    #[track_caller]
+    pub(crate) fn add_enum(&mut self, enum_ty: Intern<Ty>) {
+        let Ty::Enum { uid, .. } = enum_ty.as_ref() else {
+            panic!("passed in non-enum");
+        };
+
+        self.enums.insert(*uid, enum_ty);
+    }
+
     fn shrink_to_fit(&mut self) {
         let Self {
             signatures,
             files: modules,
+            enums,
+            instances,
         } = self;
         signatures.shrink_to_fit();
         modules.shrink_to_fit();
+        enums.shrink_to_fit();
+        instances.shrink_to_fit();
     }
 
     pub fn debug(
@@ -758,6 +1792,22 @@ impl ProjectInference {
             s.push_str(&format!("{}\n", sig.0.display(mod_dir, interner)));
         }
 
+        for instance in self
+            .instances
+            .iter()
+            .filter(|instance| include_mods || !instance.fqn.file.is_mod(mod_dir, interner))
+        {
+            s.push_str(&format!(
+                "{}({}) : {}\n",
+                instance.fqn.to_string(mod_dir, interner),
+                instance.consts.iter().join(", "),
+                instance.ty.map_or_else(
+                    || "?".to_string(),
+                    |ty| ty.display(mod_dir, interner).to_string()
+                )
+            ));
+        }
+
         let mut files = self
             .files
             .iter()
@@ -797,11 +1847,102 @@ impl ProjectInference {
     }
 }
 
+/// How much detail `TyDisplay` goes into when printing named types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum TyVerbosity {
+    /// named types are only shown by their name, e.g. `Point`.
+    /// `{int}` and `{uint}` are both shown as `{integer}`
+    Short,
+    /// named types are shown along with the file they were defined in, e.g. `geometry::Point`
+    #[default]
+    Qualified,
+    /// the same as `Qualified`, except the outermost named type also shows what it's made of,
+    /// e.g. `geometry::Point {x: i32, y: i32}`
+    Structural,
+}
+
+/// Builder for displaying a `Ty` at a specific `TyVerbosity`
+///
+/// ```ignore
+/// ty.display_with(mod_dir, interner).short().to_string()
+/// ```
+#[derive(Clone, Copy)]
+pub struct TyDisplay<'a> {
+    ty: &'a Ty,
+    mod_dir: &'a std::path::Path,
+    interner: &'a Interner,
+    verbosity: TyVerbosity,
+}
+
+impl TyDisplay<'_> {
+    pub fn verbosity(self, verbosity: TyVerbosity) -> Self {
+        Self { verbosity, ..self }
+    }
+
+    pub fn short(self) -> Self {
+        self.verbosity(TyVerbosity::Short)
+    }
+
+    pub fn qualified(self) -> Self {
+        self.verbosity(TyVerbosity::Qualified)
+    }
+
+    pub fn structural(self) -> Self {
+        self.verbosity(TyVerbosity::Structural)
+    }
+}
+
+impl std::fmt::Display for TyDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            &self
+                .ty
+                .display_impl(self.mod_dir, self.interner, self.verbosity),
+        )
+    }
+}
+
 impl Ty {
+    /// the same as `Ty::display_with(..).qualified().to_string()`
     pub fn display(&self, mod_dir: &std::path::Path, interner: &Interner) -> String {
+        self.display_impl(mod_dir, interner, TyVerbosity::Qualified)
+    }
+
+    pub fn display_with<'a>(
+        &'a self,
+        mod_dir: &'a std::path::Path,
+        interner: &'a Interner,
+    ) -> TyDisplay<'a> {
+        TyDisplay {
+            ty: self,
+            mod_dir,
+            interner,
+            verbosity: TyVerbosity::default(),
+        }
+    }
+
+    fn display_impl(
+        &self,
+        mod_dir: &std::path::Path,
+        interner: &Interner,
+        verbosity: TyVerbosity,
+    ) -> String {
+        // only the outermost type is shown structurally
+        let inner = verbosity.min(TyVerbosity::Qualified);
+
+        let name = |fqn: &hir::Fqn| match verbosity {
+            TyVerbosity::Short => interner.lookup(fqn.name.0).to_string(),
+            TyVerbosity::Qualified | TyVerbosity::Structural => fqn.to_string(mod_dir, interner),
+        };
+
         match self {
             Self::NotYetResolved => "!".to_string(),
-            Self::Unknown => "<unknown>".to_string(),
+            // to the user, an error is just another type that couldn't be figured out
+            Self::Unknown | Self::Error => "<unknown>".to_string(),
+            // the short form doesn't bother distinguishing between `{int}` and `{uint}`
+            Self::IInt(0) | Self::UInt(0) if verbosity == TyVerbosity::Short => {
+                "{integer}".to_string()
+            }
             Self::IInt(bit_width) => match *bit_width {
                 u8::MAX => "isize".to_string(),
                 0 => "{int}".to_string(),
@@ -827,65 +1968,111 @@ impl Ty {
                 format!(
                     "[{size}]{}{}",
                     if *anonymous { "~" } else { "" },
-                    sub_ty.display(mod_dir, interner)
+                    sub_ty.display_impl(mod_dir, interner, inner)
                 )
             }
-            Self::Slice { sub_ty } => format!("[]{}", sub_ty.display(mod_dir, interner)),
+            Self::Slice { sub_ty } => {
+                format!("[]{}", sub_ty.display_impl(mod_dir, interner, inner))
+            }
+            Self::Tuple { items } => {
+                let mut res = items
+                    .iter()
+                    .map(|ty| ty.display_impl(mod_dir, interner, inner))
+                    .join(", ");
+                // `(i32)` would just be parentheses
+                if items.len() == 1 {
+                    res.push(',');
+                }
+                format!("({res})")
+            }
+            Self::Optional { sub_ty } => {
+                format!("?{}", sub_ty.display_impl(mod_dir, interner, inner))
+            }
+            Self::Range { inclusive, sub_ty } => {
+                let sub_ty = sub_ty.display_impl(mod_dir, interner, inner);
+                format!("{sub_ty}{}{sub_ty}", if *inclusive { "..=" } else { ".." })
+            }
             Self::Pointer { mutable, sub_ty } => {
                 format!(
                     "^{}{}",
                     if *mutable { "mut " } else { "" },
-                    sub_ty.display(mod_dir, interner)
+                    sub_ty.display_impl(mod_dir, interner, inner)
                 )
             }
-            Self::Distinct { fqn: Some(fqn), .. } => fqn.to_string(mod_dir, interner),
+            Self::Distinct {
+                fqn: Some(fqn),
+                sub_ty,
+                ..
+            } => {
+                if verbosity == TyVerbosity::Structural {
+                    format!(
+                        "{} (distinct {})",
+                        name(fqn),
+                        sub_ty.display_impl(mod_dir, interner, inner)
+                    )
+                } else {
+                    name(fqn)
+                }
+            }
             Self::Distinct {
                 fqn: None,
                 uid,
                 sub_ty: ty,
             } => {
-                format!("distinct'{} {}", uid, ty.display(mod_dir, interner))
+                format!(
+                    "distinct'{} {}",
+                    uid,
+                    ty.display_impl(mod_dir, interner, inner)
+                )
             }
             Self::Function {
                 param_tys: params,
                 return_ty,
+            }
+            | Self::Closure {
+                param_tys: params,
+                return_ty,
             } => {
-                let mut res = "(".to_string();
+                let mut res = match self {
+                    Self::Closure { .. } => "closure (".to_string(),
+                    _ => "(".to_string(),
+                };
 
                 for (idx, param) in params.iter().enumerate() {
                     if param.varargs {
                         res.push_str("...");
                     }
 
-                    res.push_str(&param.ty.display(mod_dir, interner));
+                    res.push_str(&param.ty.display_impl(mod_dir, interner, inner));
 
                     if idx != params.len() - 1 {
                         res.push_str(", ");
                     }
                 }
                 res.push_str(") -> ");
-                res.push_str(&return_ty.display(mod_dir, interner));
+                res.push_str(&return_ty.display_impl(mod_dir, interner, inner));
 
                 res
             }
-            Self::Struct { fqn: Some(fqn), .. } => fqn.to_string(mod_dir, interner),
             Self::Struct {
                 anonymous,
-                fqn: None,
+                fqn,
                 uid,
                 members,
             } => {
-                let mut res = if *anonymous {
-                    "struct ~{".to_string()
-                } else {
-                    format!("struct'{} {{", uid)
+                let mut res = match fqn {
+                    Some(fqn) if verbosity != TyVerbosity::Structural => return name(fqn),
+                    Some(fqn) => format!("{} {{", name(fqn)),
+                    None if *anonymous => "struct ~{".to_string(),
+                    None => format!("struct'{} {{", uid),
                 };
 
+                // members are always shown in the order they were declared
                 for (idx, MemberTy { name, ty }) in members.iter().enumerate() {
                     res.push_str(interner.lookup(name.0));
                     res.push_str(": ");
 
-                    res.push_str(&ty.display(mod_dir, interner));
+                    res.push_str(&ty.display_impl(mod_dir, interner, inner));
 
                     if idx != members.len() - 1 {
                         res.push_str(", ");
@@ -896,13 +2083,34 @@ impl Ty {
 
                 res
             }
-            Self::Enum { fqn: Some(fqn), .. } => fqn.to_string(mod_dir, interner),
-            Self::Enum {
-                fqn: None,
-                uid,
-                variants,
-            } => {
-                let mut res = format!("enum '{uid} {{");
+            Self::Interface { fqn, uid, methods } => {
+                let mut res = match fqn {
+                    Some(fqn) if verbosity != TyVerbosity::Structural => return name(fqn),
+                    Some(fqn) => format!("{} {{", name(fqn)),
+                    None => format!("interface'{} {{", uid),
+                };
+
+                for (idx, MemberTy { name, ty }) in methods.iter().enumerate() {
+                    res.push_str(interner.lookup(name.0));
+                    res.push_str(": ");
+
+                    res.push_str(&ty.display_impl(mod_dir, interner, inner));
+
+                    if idx != methods.len() - 1 {
+                        res.push_str(", ");
+                    }
+                }
+
+                res.push('}');
+
+                res
+            }
+            Self::Enum { fqn, uid, variants } => {
+                let mut res = match fqn {
+                    Some(fqn) if verbosity != TyVerbosity::Structural => return name(fqn),
+                    Some(fqn) => format!("{} {{", name(fqn)),
+                    None => format!("enum '{uid} {{"),
+                };
 
                 for (idx, variant_ty) in variants.iter().enumerate() {
                     let Ty::Variant {
@@ -919,7 +2127,7 @@ impl Ty {
 
                     if !sub_ty.is_void() {
                         res.push_str(": ");
-                        res.push_str(&sub_ty.display(mod_dir, interner));
+                        res.push_str(&sub_ty.display_impl(mod_dir, interner, inner));
                     }
 
                     res.push_str(&format!(" | {discriminant}"));
@@ -937,12 +2145,13 @@ impl Ty {
                 enum_fqn,
                 variant_name,
                 uid,
+                sub_ty,
                 ..
             } => {
                 let mut res = String::new();
 
                 if let Some(enum_fqn) = enum_fqn {
-                    res.push_str(&enum_fqn.to_string(mod_dir, interner));
+                    res.push_str(&name(enum_fqn));
                 }
 
                 res.push('.');
@@ -952,6 +2161,13 @@ impl Ty {
                     res.push_str(&format!("'{uid}"));
                 }
 
+                if verbosity == TyVerbosity::Structural && !sub_ty.is_void() {
+                    res.push_str(&format!(
+                        " ({})",
+                        sub_ty.display_impl(mod_dir, interner, inner)
+                    ));
+                }
+
                 res
             }
             Self::Type => "type".to_string(),
@@ -978,7 +2194,6 @@ mod tests {
     use expect_test::{expect, Expect};
     use interner::Interner;
     use target_lexicon::Triple;
-    use uid_gen::UIDGenerator;
 
     #[track_caller]
     fn check<const N: usize>(
@@ -1011,7 +2226,6 @@ mod tests {
         let mut interner = Interner::default();
         let mut world_index = hir::WorldIndex::default();
 
-        let mut uid_gen = UIDGenerator::default();
         let mut world_bodies = hir::WorldBodies::default();
 
         let mut parse_diags = Vec::<parser::SyntaxError>::new();
@@ -1038,7 +2252,6 @@ mod tests {
                 &tree,
                 Path::new(name),
                 &index,
-                &mut uid_gen,
                 &mut interner,
                 Path::new(""),
                 true,
@@ -1066,9 +2279,8 @@ mod tests {
         let (bodies, d) = hir::lower(
             root,
             &tree,
-            Path::new("main"),
+            Path::new("main.capy"),
             &index,
-            &mut uid_gen,
             &mut interner,
             Path::new(""),
             true,
@@ -1108,14 +2320,25 @@ mod tests {
                     std::mem::transmute(tys)
                 },
                 Triple::host().pointer_width().unwrap().bits(),
-            );
-
-            unsafe { std::mem::transmute(comptime_results[&comptime].clone()) }
+                codegen::ComptimeLimits::default(),
+            )
+            .map(|()| unsafe { std::mem::transmute(comptime_results[&comptime].clone()) })
+            .map_err(|exceeded| unsafe { std::mem::transmute(exceeded) })
         })
+        .lint_member_order(true)
         .finish(entry_point, true);
 
         expect.assert_eq(&tys.debug(Path::new(""), &interner, true, false));
 
+        let bytes = tys.serialize(&interner);
+        let reloaded = ProjectInference::deserialize(&bytes, &mut interner).unwrap();
+        assert_eq!(
+            tys.debug(Path::new(""), &interner, true, false),
+            reloaded.debug(Path::new(""), &interner, true, false),
+            "the types should be the same after being serialized and deserialized"
+        );
+        assert_eq!(bytes, reloaded.serialize(&interner));
+
         let expected_diagnostics: Vec<_> = expected_diagnostics(&mut interner)
             .into_iter()
             .map(|(kind, range, help)| TyDiagnostic {
@@ -1130,8 +2353,14 @@ mod tests {
             })
             .collect();
 
-        let expected_diagnostics_text = format!("{:#?}", expected_diagnostics);
-        let actual_diagnostics_text = format!("{:#?}", actual_diagnostics);
+        // so that a failing test shows names instead of interner indices
+        let (expected_diagnostics_text, actual_diagnostics_text) = {
+            let _scope = interner.debug_scope();
+            (
+                format!("{:#?}", expected_diagnostics),
+                format!("{:#?}", actual_diagnostics),
+            )
+        };
 
         let (dist, changeset) =
             text_diff::diff(&expected_diagnostics_text, &actual_diagnostics_text, "");
@@ -2061,130 +3290,267 @@ mod tests {
     }
 
     #[test]
-    fn array_ty_with_comptime_size() {
+    fn array_ty_with_inferred_size() {
         check(
             r#"
-                main :: () {
-                    size :: comptime {
-                        if true {
-                            5
-                        } else {
-                            6
-                        }
-                    };
+                nums : [_]i32 : i32.[1, 2, 3];
 
-                    my_array : [size] i64 = i64.[1, 2, 3, 4, 5];
+                main :: () {
+                    more : [_]u8 = .[4, 5];
                 };
             "#,
             expect![[r#"
                 main::main : () -> void
-                0 : bool
-                1 : usize
-                2 : usize
-                3 : usize
-                4 : usize
-                5 : usize
-                6 : usize
-                7 : usize
-                8 : usize
-                12 : i64
-                13 : i64
-                14 : i64
-                15 : i64
-                16 : i64
-                17 : [5]i64
-                18 : void
-                19 : () -> void
-                l0 : usize
-                l1 : [5]i64
+                main::nums : [3]i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : [3]i32
+                9 : u8
+                10 : u8
+                11 : [2]u8
+                12 : void
+                13 : () -> void
+                l0 : [2]u8
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn array_ty_with_negative_size() {
+    fn array_ty_with_inferred_size_without_value() {
         check(
             r#"
                 main :: () {
-                    my_array : [-3] i32 = i32.[];
+                    nums : [_]i32;
                 };
             "#,
             expect![[r#"
                 main::main : () -> void
-                0 : {int}
-                1 : {int}
-                5 : [0]i32
-                6 : void
-                7 : () -> void
+                2 : void
+                3 : () -> void
                 l0 : <unknown>
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::UInt(u8::MAX).into()),
-                        found: Ty::IInt(0).into(),
-                    },
-                    62..64,
-                    None,
-                )]
-            },
+            |_| [(TyDiagnosticKind::ArraySizeNotInferred, 57..63, None)],
         );
     }
 
     #[test]
-    fn index() {
+    fn array_repeat() {
         check(
             r#"
-                main :: () -> i32 {
-                    my_array := i32.[4, 8, 15, 16, 23, 42];
-
-                    my_array[2]
+                main :: () {
+                    zeroes : [256]u8 = u8.[0; 256];
+                    x : i64 = 5;
+                    xs := .[x; 3];
                 };
             "#,
             expect![[r#"
-                main::main : () -> i32
-                2 : i32
-                3 : i32
-                4 : i32
-                5 : i32
-                6 : i32
-                7 : i32
-                8 : [6]i32
-                9 : [6]i32
+                main::main : () -> void
+                0 : usize
+                4 : u8
+                5 : usize
+                6 : [256]u8
+                8 : i64
+                9 : i64
                 10 : usize
-                11 : i32
-                12 : i32
-                13 : () -> i32
-                l0 : [6]i32
+                11 : [3]~i64
+                12 : void
+                13 : () -> void
+                l0 : [256]u8
+                l1 : i64
+                l2 : [3]~i64
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn index_too_large() {
+    fn array_repeat_wrong_count() {
         check(
             r#"
-                main :: () -> i32 {
-                    my_array := i32.[4, 8, 15, 16, 23, 42];
+                main :: () {
+                    size := 3;
 
-                    my_array[1000]
+                    size = size + 1;
+
+                    a : [4]i32 = i32.[1; 3];
+                    b := i32.[0; size];
                 };
             "#,
             expect![[r#"
-                main::main : () -> i32
-                2 : i32
-                3 : i32
-                4 : i32
-                5 : i32
-                6 : i32
-                7 : i32
-                8 : [6]i32
-                9 : [6]i32
-                10 : usize
-                11 : i32
-                12 : i32
+                main::main : () -> void
+                0 : usize
+                1 : usize
+                2 : usize
+                3 : usize
+                4 : usize
+                5 : usize
+                9 : i32
+                10 : usize
+                11 : [3]i32
+                13 : i32
+                14 : usize
+                15 : <unknown>
+                16 : void
+                17 : () -> void
+                l0 : usize
+                l1 : [4]i32
+                l2 : <unknown>
+            "#]],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::Mismatch {
+                            expected: ExpectedTy::Concrete(
+                                Ty::Array {
+                                    anonymous: false,
+                                    size: 4,
+                                    sub_ty: Ty::IInt(32).into(),
+                                }
+                                .into(),
+                            ),
+                            found: Ty::Array {
+                                anonymous: false,
+                                size: 3,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                        },
+                        133..143,
+                        None,
+                    ),
+                    (TyDiagnosticKind::ArraySizeNotConst, 178..182, None),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn array_ty_with_comptime_size() {
+        check(
+            r#"
+                main :: () {
+                    size :: comptime {
+                        if true {
+                            5
+                        } else {
+                            6
+                        }
+                    };
+
+                    my_array : [size] i64 = i64.[1, 2, 3, 4, 5];
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : bool
+                1 : usize
+                2 : usize
+                3 : usize
+                4 : usize
+                5 : usize
+                6 : usize
+                7 : usize
+                8 : usize
+                12 : i64
+                13 : i64
+                14 : i64
+                15 : i64
+                16 : i64
+                17 : [5]i64
+                18 : void
+                19 : () -> void
+                l0 : usize
+                l1 : [5]i64
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn array_ty_with_negative_size() {
+        check(
+            r#"
+                main :: () {
+                    my_array : [-3] i32 = i32.[];
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : {int}
+                1 : {int}
+                5 : [0]i32
+                6 : void
+                7 : () -> void
+                l0 : <unknown>
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::UInt(u8::MAX).into()),
+                        found: Ty::IInt(0).into(),
+                    },
+                    62..64,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn index() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    my_array := i32.[4, 8, 15, 16, 23, 42];
+
+                    my_array[2]
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                2 : i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : i32
+                7 : i32
+                8 : [6]i32
+                9 : [6]i32
+                10 : usize
+                11 : i32
+                12 : i32
+                13 : () -> i32
+                l0 : [6]i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn index_too_large() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    my_array := i32.[4, 8, 15, 16, 23, 42];
+
+                    my_array[1000]
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                2 : i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : i32
+                7 : i32
+                8 : [6]i32
+                9 : [6]i32
+                10 : usize
+                11 : i32
+                12 : i32
                 13 : () -> i32
                 l0 : [6]i32
             "#]],
@@ -2207,6 +3573,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn index_too_large_computed() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    my_array := i32.[4, 8, 15, 16, 23, 42];
+                    len :: 3 * 2;
+
+                    my_array[len] + my_array[(len - 1) % 4] + my_array[len / 2 + 4]
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                2 : i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : i32
+                7 : i32
+                8 : [6]i32
+                9 : usize
+                10 : usize
+                11 : usize
+                12 : [6]i32
+                13 : usize
+                14 : i32
+                15 : [6]i32
+                16 : usize
+                17 : usize
+                18 : usize
+                19 : usize
+                20 : usize
+                21 : usize
+                22 : i32
+                23 : i32
+                24 : [6]i32
+                25 : usize
+                26 : usize
+                27 : usize
+                28 : usize
+                29 : usize
+                30 : i32
+                31 : i32
+                32 : i32
+                33 : () -> i32
+                l0 : [6]i32
+                l1 : usize
+            "#]],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::IndexOutOfBounds {
+                            index: 6,
+                            actual_size: 6,
+                            array_ty: Ty::Array {
+                                anonymous: false,
+                                size: 6,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                        },
+                        152..165,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::IndexOutOfBounds {
+                            index: 7,
+                            actual_size: 6,
+                            array_ty: Ty::Array {
+                                anonymous: false,
+                                size: 6,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                        },
+                        194..215,
+                        None,
+                    ),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn index_with_mutable_local() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    my_array := i32.[4, 8, 15, 16, 23, 42];
+                    len := 6;
+
+                    my_array[len]
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                2 : i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : i32
+                7 : i32
+                8 : [6]i32
+                9 : usize
+                10 : [6]i32
+                11 : usize
+                12 : i32
+                13 : i32
+                14 : () -> i32
+                l0 : [6]i32
+                l1 : usize
+            "#]],
+            |_| [],
+        );
+    }
+
     #[test]
     fn inference_complex_by_annotation() {
         check(
@@ -2389,13 +3871,50 @@ mod tests {
     }
 
     #[test]
-    fn local_shadowing() {
+    fn local_usages_of_multiple_locals() {
+        // `c` and `d` each use two locals, so they're usages of more than one local.
+        // the `i64` has to make it all the way back to `a` and `b`
         check(
             r#"
-                foo :: () {
-                    a := 10;
-                    a := "10";
-                    a;
+                main :: () {
+                    a := 1;
+                    b := 2;
+                    c := a + b;
+                    d := c * a;
+                    e : i64 = d;
+                };
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : i64
+                1 : i64
+                2 : i64
+                3 : i64
+                4 : i64
+                5 : i64
+                6 : i64
+                7 : i64
+                9 : i64
+                10 : void
+                11 : () -> void
+                l0 : i64
+                l1 : i64
+                l2 : i64
+                l3 : i64
+                l4 : i64
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn local_shadowing() {
+        check(
+            r#"
+                foo :: () {
+                    a := 10;
+                    a := "10";
+                    a;
                 };
             "#,
             expect![[r#"
@@ -2646,63 +4165,165 @@ mod tests {
     }
 
     #[test]
-    fn binary_expr_with_missing_operand() {
+    fn bitwise_on_floats() {
         check(
             r#"
-                f :: () -> i32 { 5 + };
+                f :: () {
+                    a := 1.5 & 2.5;
+                    b := ~2.5;
+                    c := true ~ false;
+                };
             "#,
             expect![[r#"
-                main::f : () -> i32
-                1 : i32
-                2 : <unknown>
-                3 : i32
-                4 : i32
-                5 : () -> i32
+                main::f : () -> void
+                0 : {float}
+                1 : {float}
+                2 : {float}
+                3 : {float}
+                4 : {int}
+                5 : bool
+                6 : bool
+                7 : bool
+                8 : void
+                9 : () -> void
+                l0 : {float}
+                l1 : {int}
+                l2 : bool
             "#]],
-            |_| [],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::BinaryOpMismatch {
+                            op: hir::BinaryOp::BAnd,
+                            first: Ty::Float(0).into(),
+                            second: Ty::Float(0).into(),
+                        },
+                        52..61,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::UnaryOpMismatch {
+                            op: hir::UnaryOp::BNot,
+                            ty: Ty::Float(0).into(),
+                        },
+                        89..92,
+                        None,
+                    ),
+                ]
+            },
         );
     }
 
     #[test]
-    fn invalid_binary_expr_with_missing_operand() {
+    fn divide_by_zero() {
         check(
             r#"
-                f :: () -> str { "hello" + };
+                f :: () {
+                    zero :: 5 - 5;
+                    a := 10 / 0;
+                    b := 10 % zero;
+                    c := 10.0 / 0.0;
+                    a /= zero;
+                };
             "#,
             expect![[r#"
-                main::f : () -> str
-                1 : str
-                2 : <unknown>
-                3 : str
-                4 : str
-                5 : () -> str
+                main::f : () -> void
+                0 : {uint}
+                1 : {uint}
+                2 : {uint}
+                3 : {uint}
+                4 : {uint}
+                5 : {uint}
+                6 : {uint}
+                7 : {uint}
+                8 : {uint}
+                9 : {float}
+                10 : {float}
+                11 : {float}
+                12 : {uint}
+                13 : {uint}
+                14 : void
+                15 : () -> void
+                l0 : {uint}
+                l1 : {uint}
+                l2 : {uint}
+                l3 : {float}
             "#]],
-            |_| [],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::DivideByZero {
+                            op: hir::BinaryOp::Div,
+                        },
+                        92..93,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::DivideByZero {
+                            op: hir::BinaryOp::Mod,
+                        },
+                        125..129,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::DivideByZero {
+                            op: hir::BinaryOp::Div,
+                        },
+                        193..197,
+                        None,
+                    ),
+                ]
+            },
         );
     }
 
     #[test]
-    fn invalid_num_cmp_binary_expr() {
+    fn misaligned_pointer_cast() {
         check(
             r#"
-                f :: () -> bool { true < 5 };
+                f :: () {
+                    byte : u8 = 0;
+                    a := (^u64).(rawptr.(^byte));
+                    num : u64 = 0;
+                    b := (^u8).(rawptr.(^num));
+                };
             "#,
             expect![[r#"
-                main::f : () -> bool
-                1 : bool
-                2 : {uint}
-                3 : bool
-                4 : bool
-                5 : () -> bool
+                main::f : () -> void
+                1 : u8
+                2 : u8
+                3 : ^u8
+                5 : rawptr
+                9 : ^u64
+                11 : u64
+                12 : u64
+                13 : ^u64
+                15 : rawptr
+                19 : ^u8
+                20 : void
+                21 : () -> void
+                l0 : u8
+                l1 : ^u64
+                l2 : u64
+                l3 : ^u8
             "#]],
             |_| {
                 [(
-                    TyDiagnosticKind::BinaryOpMismatch {
-                        op: hir::BinaryOp::Lt,
-                        first: Ty::Bool.into(),
-                        second: Ty::UInt(0).into(),
+                    TyDiagnosticKind::MisalignedPointerCast {
+                        from: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::UInt(8).into(),
+                        }
+                        .into(),
+                        to: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::UInt(64).into(),
+                        }
+                        .into(),
+                        from_align: 1,
+                        to_align: 8,
                     },
-                    35..43,
+                    87..110,
                     None,
                 )]
             },
@@ -2710,87 +4331,438 @@ mod tests {
     }
 
     #[test]
-    fn invalid_bool_cmp_binary_expr() {
+    fn large_array_copy() {
         check(
             r#"
-                f :: () -> bool { "hello" && "world" };
+                foo :: (big: [256]i32, small: [4]i32, ptrs: [1024]usize) {
+                    a := big;
+                    b := small;
+                    c := ptrs;
+                    d : [300]u32 = u32.[0; 300];
+                    d = (d);
+                    e := ^big;
+                    f := big[..];
+                }
             "#,
             expect![[r#"
-                main::f : () -> bool
-                1 : str
-                2 : str
-                3 : bool
-                4 : bool
-                5 : () -> bool
+                main::foo : ([256]i32, [4]i32, [1024]usize) -> void
+                0 : usize
+                3 : usize
+                6 : usize
+                9 : [256]i32
+                10 : [4]i32
+                11 : [1024]usize
+                12 : usize
+                16 : u32
+                17 : usize
+                18 : [300]u32
+                19 : [300]u32
+                20 : [300]u32
+                21 : [300]u32
+                22 : [256]i32
+                23 : ^[256]i32
+                24 : [256]i32
+                25 : usize..usize
+                26 : []i32
+                27 : void
+                28 : ([256]i32, [4]i32, [1024]usize) -> void
+                l0 : [256]i32
+                l1 : [4]i32
+                l2 : [1024]usize
+                l3 : [300]u32
+                l4 : ^[256]i32
+                l5 : []i32
             "#]],
             |_| {
-                [(
-                    TyDiagnosticKind::BinaryOpMismatch {
-                        op: hir::BinaryOp::LAnd,
-                        first: Ty::String.into(),
-                        second: Ty::String.into(),
-                    },
-                    35..53,
-                    None,
-                )]
+                [
+                    (
+                        TyDiagnosticKind::LargeArrayCopy {
+                            ty: Ty::Array {
+                                anonymous: false,
+                                size: 256,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                            size: 1024,
+                        },
+                        101..104,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::LargeArrayCopy {
+                            ty: Ty::Array {
+                                anonymous: false,
+                                size: 300,
+                                sub_ty: Ty::UInt(32).into(),
+                            }
+                            .into(),
+                            size: 1200,
+                        },
+                        242..245,
+                        None,
+                    ),
+                ]
             },
         );
     }
 
     #[test]
-    fn bool_binary_expr() {
+    fn self_comparison() {
         check(
             r#"
-                both :: () -> bool { true && false };
+                Foo :: struct { bar: i32, baz: i32 };
+
+                f :: (x: i32, y: i32, foo: Foo, other: Foo, n: f64) {
+                    a := x == x;
+                    b := foo.bar >= foo.bar;
+                    c := x == y;
+                    d := foo.bar < foo.baz;
+                    e := foo.bar == other.bar;
+                    is_nan := n != n;
+                    same_ty := Foo == Foo;
+                }
             "#,
             expect![[r#"
-                main::both : () -> bool
-                1 : bool
-                2 : bool
-                3 : bool
-                4 : bool
-                5 : () -> bool
+                main::Foo : type
+                main::f : (i32, i32, main::Foo, main::Foo, f64) -> void
+                2 : type
+                8 : i32
+                9 : i32
+                10 : bool
+                11 : main::Foo
+                12 : i32
+                13 : main::Foo
+                14 : i32
+                15 : bool
+                16 : i32
+                17 : i32
+                18 : bool
+                19 : main::Foo
+                20 : i32
+                21 : main::Foo
+                22 : i32
+                23 : bool
+                24 : main::Foo
+                25 : i32
+                26 : main::Foo
+                27 : i32
+                28 : bool
+                29 : f64
+                30 : f64
+                31 : bool
+                32 : type
+                33 : type
+                34 : bool
+                35 : void
+                36 : (i32, i32, main::Foo, main::Foo, f64) -> void
+                l0 : bool
+                l1 : bool
+                l2 : bool
+                l3 : bool
+                l4 : bool
+                l5 : bool
+                l6 : bool
             "#]],
-            |_| [],
+            |_| {
+                [
+                    (TyDiagnosticKind::SelfComparison, 151..157, None),
+                    (TyDiagnosticKind::SelfComparison, 184..202, None),
+                ]
+            },
         );
     }
 
     #[test]
-    fn bool_binary_expr_with_missing_operand() {
+    fn align_cast_directive() {
         check(
             r#"
-                either :: () -> bool { true || };
+                f :: () {
+                    byte : u8 = 0;
+                    a := #align_cast(^byte, u64);
+                    b := #align_cast(5, u64);
+                    c := #align_cast(^byte);
+                };
             "#,
             expect![[r#"
-                main::either : () -> bool
-                1 : bool
-                2 : <unknown>
-                3 : bool
-                4 : bool
-                5 : () -> bool
+                main::f : () -> void
+                1 : u8
+                2 : u8
+                3 : ^u8
+                4 : type
+                5 : ^u64
+                6 : {uint}
+                7 : type
+                8 : <unknown>
+                9 : u8
+                10 : ^u8
+                11 : <unknown>
+                12 : void
+                13 : () -> void
+                l0 : u8
+                l1 : ^u64
+                l2 : <unknown>
+                l3 : <unknown>
             "#]],
-            |_| [],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::Mismatch {
+                            expected: ExpectedTy::Pointer,
+                            found: Ty::UInt(0).into(),
+                        },
+                        149..150,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::MissingArg {
+                            expected: ExpectedTy::Concrete(Ty::Type.into()),
+                        },
+                        200..200,
+                        None,
+                    ),
+                ]
+            },
         );
     }
 
     #[test]
-    fn cmp_binary_expr() {
+    fn null_literal() {
         check(
             r#"
-                less :: () -> bool { 5 <= 10 };
+                f :: (typed: ^i32, raw: rawptr) {
+                    a : rawptr = null;
+                    b : mut rawptr = null;
+                    c := typed == null;
+                    d := null != raw;
+                    e : ^i32 = null;
+                    g := typed == raw;
+                };
             "#,
             expect![[r#"
-                main::less : () -> bool
-                1 : {uint}
-                2 : {uint}
-                3 : bool
-                4 : bool
-                5 : () -> bool
+                main::f : (^i32, rawptr) -> void
+                4 : mut rawptr
+                7 : mut rawptr
+                8 : ^i32
+                9 : mut rawptr
+                10 : bool
+                11 : mut rawptr
+                12 : rawptr
+                13 : bool
+                16 : mut rawptr
+                17 : ^i32
+                18 : rawptr
+                19 : bool
+                20 : void
+                21 : (^i32, rawptr) -> void
+                l0 : rawptr
+                l1 : mut rawptr
+                l2 : bool
+                l3 : bool
+                l4 : ^i32
+                l5 : bool
             "#]],
-            |_| [],
-        );
-    }
-
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::NullToPointer {
+                            ty: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                        },
+                        242..246,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::BinaryOpMismatch {
+                            op: hir::BinaryOp::Eq,
+                            first: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                            second: Ty::RawPtr { mutable: false }.into(),
+                        },
+                        273..285,
+                        None,
+                    ),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn null_shadowed() {
+        check(
+            r#"
+                null :: () -> i32 { 5 };
+
+                f :: () -> i32 {
+                    null()
+                };
+            "#,
+            expect![[r#"
+                main::f : () -> i32
+                main::null : () -> i32
+                1 : i32
+                2 : i32
+                3 : () -> i32
+                5 : () -> i32
+                6 : i32
+                7 : i32
+                8 : () -> i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn binary_expr_with_missing_operand() {
+        check(
+            r#"
+                f :: () -> i32 { 5 + };
+            "#,
+            expect![[r#"
+                main::f : () -> i32
+                1 : i32
+                2 : <unknown>
+                3 : i32
+                4 : i32
+                5 : () -> i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn invalid_binary_expr_with_missing_operand() {
+        check(
+            r#"
+                f :: () -> str { "hello" + };
+            "#,
+            expect![[r#"
+                main::f : () -> str
+                1 : str
+                2 : <unknown>
+                3 : str
+                4 : str
+                5 : () -> str
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn invalid_num_cmp_binary_expr() {
+        check(
+            r#"
+                f :: () -> bool { true < 5 };
+            "#,
+            expect![[r#"
+                main::f : () -> bool
+                1 : bool
+                2 : {uint}
+                3 : bool
+                4 : bool
+                5 : () -> bool
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::BinaryOpMismatch {
+                        op: hir::BinaryOp::Lt,
+                        first: Ty::Bool.into(),
+                        second: Ty::UInt(0).into(),
+                    },
+                    35..43,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn invalid_bool_cmp_binary_expr() {
+        check(
+            r#"
+                f :: () -> bool { "hello" && "world" };
+            "#,
+            expect![[r#"
+                main::f : () -> bool
+                1 : str
+                2 : str
+                3 : bool
+                4 : bool
+                5 : () -> bool
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::BinaryOpMismatch {
+                        op: hir::BinaryOp::LAnd,
+                        first: Ty::String.into(),
+                        second: Ty::String.into(),
+                    },
+                    35..53,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn bool_binary_expr() {
+        check(
+            r#"
+                both :: () -> bool { true && false };
+            "#,
+            expect![[r#"
+                main::both : () -> bool
+                1 : bool
+                2 : bool
+                3 : bool
+                4 : bool
+                5 : () -> bool
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn bool_binary_expr_with_missing_operand() {
+        check(
+            r#"
+                either :: () -> bool { true || };
+            "#,
+            expect![[r#"
+                main::either : () -> bool
+                1 : bool
+                2 : <unknown>
+                3 : bool
+                4 : bool
+                5 : () -> bool
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn cmp_binary_expr() {
+        check(
+            r#"
+                less :: () -> bool { 5 <= 10 };
+            "#,
+            expect![[r#"
+                main::less : () -> bool
+                1 : {uint}
+                2 : {uint}
+                3 : bool
+                4 : bool
+                5 : () -> bool
+            "#]],
+            |_| [],
+        );
+    }
+
     #[test]
     fn cmp_binary_expr_with_missing_operands() {
         check(
@@ -3132,7 +5104,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("imaginary")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "imaginary", 0),
                             sub_ty: Ty::IInt(32).into(),
                         }
                         .into(),
@@ -3236,7 +5208,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("imaginary")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "imaginary", 0),
                             sub_ty: Ty::IInt(32).into(),
                         }
                         .into(),
@@ -3288,7 +5260,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("imaginary")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "imaginary", 0),
                             sub_ty: Ty::IInt(32).into(),
                         }
                         .into(),
@@ -3297,7 +5269,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("extra_imaginary")),
                             }),
-                            uid: 1,
+                            uid: hir::type_uid("main.capy", "extra_imaginary", 0),
                             sub_ty: Ty::IInt(32).into(),
                         }
                         .into(),
@@ -5238,11 +7210,11 @@ mod tests {
             expect![[r#"
                 main::foo : () -> void
                 1 : type
-                3 : distinct'0 i32
+                3 : distinct'11430245015546164983 i32
                 4 : void
                 5 : () -> void
                 l0 : type
-                l1 : distinct'0 i32
+                l1 : distinct'11430245015546164983 i32
             "#]],
             |_| [],
         );
@@ -5311,61 +7283,49 @@ mod tests {
     }
 
     #[test]
-    fn int_too_large_for_type() {
+    fn suffixed_literals() {
         check(
             r#"
                 foo :: () {
-                    my_num : i8 = 128;
+                    x := 42u8;
+                    y := 1.5f32;
                 };
             "#,
             expect![[r#"
                 main::foo : () -> void
-                1 : i8
+                0 : u8
+                1 : f32
                 2 : void
                 3 : () -> void
-                l0 : i8
+                l0 : u8
+                l1 : f32
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::IntTooBigForType {
-                        found: 128,
-                        max: 127,
-                        ty: Ty::IInt(8).into(),
-                    },
-                    63..66,
-                    None,
-                )]
-            },
+            |_| [],
         );
     }
 
     #[test]
-    fn int_too_large_for_type_by_inference() {
+    fn suffixed_literal_is_not_weak() {
         check(
             r#"
                 foo :: () {
-                    my_num := 128;
-
-                    my_other_num : i8 = my_num;
+                    x : i8 = 42u16;
                 };
             "#,
             expect![[r#"
                 main::foo : () -> void
-                0 : i8
-                2 : i8
-                3 : void
-                4 : () -> void
+                1 : u16
+                2 : void
+                3 : () -> void
                 l0 : i8
-                l1 : i8
             "#]],
             |_| {
                 [(
-                    TyDiagnosticKind::IntTooBigForType {
-                        found: 128,
-                        max: 127,
-                        ty: Ty::IInt(8).into(),
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::IInt(8).into()),
+                        found: Ty::UInt(16).into(),
                     },
-                    59..62,
+                    58..63,
                     None,
                 )]
             },
@@ -5373,44 +7333,168 @@ mod tests {
     }
 
     #[test]
-    fn inference_by_too_large_for_u32() {
+    fn suffixed_int_too_large_for_suffix() {
         check(
             r#"
                 foo :: () {
-                    my_num := 4_294_967_296;
+                    x := 256u8;
                 };
             "#,
             expect![[r#"
                 main::foo : () -> void
-                0 : u64
+                0 : u8
                 1 : void
                 2 : () -> void
-                l0 : u64
+                l0 : u8
             "#]],
-            |_| [],
+            |_| {
+                [(
+                    TyDiagnosticKind::IntTooBigForType {
+                        found: 256,
+                        written: None,
+                        max: 255,
+                        ty: Ty::UInt(8).into(),
+                    },
+                    54..59,
+                    None,
+                )]
+            },
         );
     }
 
     #[test]
-    fn inference_by_too_large_for_i32() {
+    fn hex_int_too_large_for_type() {
         check(
             r#"
                 foo :: () {
-                    my_num := -2_147_483_648;
+                    x : u8 = 0x1_FF;
                 };
             "#,
             expect![[r#"
                 main::foo : () -> void
-                0 : i64
-                1 : i64
+                1 : u8
                 2 : void
                 3 : () -> void
-                l0 : i64
+                l0 : u8
             "#]],
-            |_| [],
-        );
-    }
-
+            |_| {
+                [(
+                    TyDiagnosticKind::IntTooBigForType {
+                        found: 511,
+                        written: Some("0x1_FF".to_string()),
+                        max: 255,
+                        ty: Ty::UInt(8).into(),
+                    },
+                    58..64,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn int_too_large_for_type() {
+        check(
+            r#"
+                foo :: () {
+                    my_num : i8 = 128;
+                };
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                1 : i8
+                2 : void
+                3 : () -> void
+                l0 : i8
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::IntTooBigForType {
+                        found: 128,
+                        written: None,
+                        max: 127,
+                        ty: Ty::IInt(8).into(),
+                    },
+                    63..66,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn int_too_large_for_type_by_inference() {
+        check(
+            r#"
+                foo :: () {
+                    my_num := 128;
+
+                    my_other_num : i8 = my_num;
+                };
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                0 : i8
+                2 : i8
+                3 : void
+                4 : () -> void
+                l0 : i8
+                l1 : i8
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::IntTooBigForType {
+                        found: 128,
+                        written: None,
+                        max: 127,
+                        ty: Ty::IInt(8).into(),
+                    },
+                    59..62,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn inference_by_too_large_for_u32() {
+        check(
+            r#"
+                foo :: () {
+                    my_num := 4_294_967_296;
+                };
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                0 : u64
+                1 : void
+                2 : () -> void
+                l0 : u64
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn inference_by_too_large_for_i32() {
+        check(
+            r#"
+                foo :: () {
+                    my_num := -2_147_483_648;
+                };
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                0 : i64
+                1 : i64
+                2 : void
+                3 : () -> void
+                l0 : i64
+            "#]],
+            |_| [],
+        );
+    }
+
     #[test]
     fn struct_literal() {
         check(
@@ -5442,6 +7526,137 @@ mod tests {
         );
     }
 
+    #[test]
+    fn struct_literal_out_of_order() {
+        check(
+            r#"
+                Person :: struct {
+                    name: str,
+                    age: i32
+                };
+
+                foo :: () {
+                    some_guy := Person.{
+                        age = 31,
+                        name = "Joe Schmoe",
+                    };
+                };
+            "#,
+            expect![[r#"
+                main::Person : type
+                main::foo : () -> void
+                2 : type
+                4 : i32
+                5 : str
+                6 : main::Person
+                7 : void
+                8 : () -> void
+                l0 : main::Person
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::StructLiteralOutOfOrder {
+                        expected_ty: Ty::Struct {
+                            anonymous: false,
+                            fqn: Some(hir::Fqn {
+                                file: hir::FileName(i.intern("main.capy")),
+                                name: hir::Name(i.intern("Person")),
+                            }),
+                            uid: hir::type_uid("main.capy", "Person", 0),
+                            members: vec![
+                                MemberTy {
+                                    name: hir::Name(i.intern("name")),
+                                    ty: Ty::String.into(),
+                                },
+                                MemberTy {
+                                    name: hir::Name(i.intern("age")),
+                                    ty: Ty::IInt(32).into(),
+                                },
+                            ],
+                        }
+                        .into(),
+                        reordered: vec![
+                            TextRange::new(243.into(), 262.into()),
+                            TextRange::new(209.into(), 217.into()),
+                        ],
+                        side_effect_free: true,
+                    },
+                    176..285,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn struct_literal_out_of_order_with_side_effects() {
+        check(
+            r#"
+                Person :: struct {
+                    name: str,
+                    age: i32
+                };
+
+                get_age :: () -> i32 { 31 }
+
+                foo :: () {
+                    some_guy := Person.{
+                        age = get_age(),
+                        name = "Joe Schmoe",
+                    };
+                };
+            "#,
+            expect![[r#"
+                main::Person : type
+                main::foo : () -> void
+                main::get_age : () -> i32
+                2 : type
+                4 : i32
+                5 : i32
+                6 : () -> i32
+                8 : () -> i32
+                9 : i32
+                10 : str
+                11 : main::Person
+                12 : void
+                13 : () -> void
+                l0 : main::Person
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::StructLiteralOutOfOrder {
+                        expected_ty: Ty::Struct {
+                            anonymous: false,
+                            fqn: Some(hir::Fqn {
+                                file: hir::FileName(i.intern("main.capy")),
+                                name: hir::Name(i.intern("Person")),
+                            }),
+                            uid: hir::type_uid("main.capy", "Person", 0),
+                            members: vec![
+                                MemberTy {
+                                    name: hir::Name(i.intern("name")),
+                                    ty: Ty::String.into(),
+                                },
+                                MemberTy {
+                                    name: hir::Name(i.intern("age")),
+                                    ty: Ty::IInt(32).into(),
+                                },
+                            ],
+                        }
+                        .into(),
+                        reordered: vec![
+                            TextRange::new(295.into(), 314.into()),
+                            TextRange::new(254.into(), 269.into()),
+                        ],
+                        side_effect_free: false,
+                    },
+                    221..337,
+                    None,
+                )]
+            },
+        );
+    }
+
     #[test]
     fn struct_literal_wrong_fields() {
         check(
@@ -5476,7 +7691,7 @@ mod tests {
                         file: hir::FileName(i.intern("main.capy")),
                         name: hir::Name(i.intern("Person")),
                     }),
-                    uid: 0,
+                    uid: hir::type_uid("main.capy", "Person", 0),
                     members: vec![
                         MemberTy {
                             name: hir::Name(i.intern("name")),
@@ -5596,7 +7811,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Person")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "Person", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("name")),
@@ -6188,7 +8403,7 @@ mod tests {
                 1 : type
                 3 : i32
                 4 : i32
-                6 : distinct'0 i32
+                6 : distinct'11430245015546164983 i32
                 7 : void
                 8 : () -> void
                 l0 : type
@@ -6246,7 +8461,7 @@ mod tests {
                                     file: hir::FileName(i.intern("main.capy")),
                                     name: hir::Name(i.intern("Bar")),
                                 }),
-                                uid: 1,
+                                uid: hir::type_uid("main.capy", "Bar", 0),
                                 members: vec![
                                     MemberTy {
                                         name: hir::Name(i.intern("a")),
@@ -6266,7 +8481,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Foo")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "Foo", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("a")),
@@ -6468,7 +8683,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Foo")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "Foo", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("a")),
@@ -6492,7 +8707,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Bar")),
                             }),
-                            uid: 1,
+                            uid: hir::type_uid("main.capy", "Bar", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("a")),
@@ -6566,7 +8781,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Foo")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "Foo", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("a")),
@@ -6585,7 +8800,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Bar")),
                             }),
-                            uid: 1,
+                            uid: hir::type_uid("main.capy", "Bar", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("x")),
@@ -6655,7 +8870,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Foo")),
                             }),
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "Foo", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("a")),
@@ -6674,7 +8889,7 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Bar")),
                             }),
-                            uid: 1,
+                            uid: hir::type_uid("main.capy", "Bar", 0),
                             members: vec![
                                 MemberTy {
                                     name: hir::Name(i.intern("a")),
@@ -6864,139 +9079,237 @@ mod tests {
     }
 
     #[test]
-    fn non_const_global() {
-        check(
-            r#"
-                foo :: 2 + 2;
-            "#,
-            expect![[r#"
-                main::foo : i32
-                0 : i32
-                1 : i32
-                2 : i32
-            "#]],
-            |_| [(TyDiagnosticKind::GlobalNotConst, 24..29, None)],
-        );
-    }
-
-    #[test]
-    fn any_ptr() {
+    fn comptime_extern() {
         check(
             r#"
-                get_any :: () {
-                    foo : i32 = 5;
+                read :: (buf: ^mut u8, count: usize) -> isize extern;
+                puts :: (text: str) extern;
 
-                    ptr : ^i32 = ^foo;
-                    ptr : rawptr = (rawptr).(ptr);
-                    ptr : ^f32 = (^f32).(ptr);
+                read_byte :: () -> u8 {
+                    byte : u8 = 0;
+                    read(^mut byte, 1);
+                    byte
+                }
 
-                    foo : f32 = ptr^;
+                foo :: () -> u8 {
+                    comptime {
+                        puts("reading...");
+                        read_byte()
+                    }
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : i32
-                4 : i32
-                5 : ^i32
-                7 : ^i32
-                10 : rawptr
-                13 : rawptr
-                17 : ^f32
-                19 : ^f32
-                20 : f32
-                21 : void
-                22 : () -> void
-                l0 : i32
-                l1 : ^i32
-                l2 : rawptr
-                l3 : ^f32
-                l4 : f32
+                main::foo : () -> u8
+                main::puts : (str) -> void
+                main::read : (^mut u8, usize) -> isize
+                main::read_byte : () -> u8
+                5 : (^mut u8, usize) -> isize
+                8 : (str) -> void
+                11 : u8
+                12 : (^mut u8, usize) -> isize
+                13 : u8
+                14 : ^mut u8
+                15 : usize
+                16 : isize
+                17 : u8
+                18 : u8
+                19 : () -> u8
+                21 : (str) -> void
+                22 : str
+                23 : void
+                24 : () -> u8
+                25 : u8
+                26 : u8
+                27 : u8
+                28 : u8
+                29 : () -> u8
+                l0 : u8
             "#]],
-            |_| [],
+            |i| {
+                [(
+                    TyDiagnosticKind::ComptimeExtern {
+                        function: hir::Fqn {
+                            file: hir::FileName(i.intern("main.capy")),
+                            name: hir::Name(i.intern("read")),
+                        },
+                    },
+                    329..441,
+                    None,
+                )]
+            },
         );
     }
 
     #[test]
-    fn any_slice() {
+    fn comptime_extern_nested() {
         check(
             r#"
-                get_any :: () {
-                    foo : [] i32 = i32.[100, 200];
+                time :: (t: usize) -> usize extern;
 
-                    ptr : rawslice = rawslice.(foo);
+                Ty :: comptime {
+                    Inner :: comptime {
+                        if time(0) % 2 == 0 {
+                            i32
+                        } else {
+                            u8
+                        }
+                    };
 
-                    foo : [] f32 = []f32.(ptr);
+                    Inner
+                };
+            "#,
+            expect![[r#"
+                main::Ty : type
+                main::time : (usize) -> usize
+                3 : (usize) -> usize
+                4 : (usize) -> usize
+                5 : usize
+                6 : usize
+                7 : usize
+                8 : usize
+                9 : usize
+                10 : bool
+                11 : type
+                12 : type
+                13 : type
+                14 : type
+                15 : type
+                16 : type
+                17 : type
+                18 : type
+                19 : type
+                20 : type
+                l0 : type
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::ComptimeExtern {
+                        function: hir::Fqn {
+                            file: hir::FileName(i.intern("main.capy")),
+                            name: hir::Name(i.intern("time")),
+                        },
+                    },
+                    116..316,
+                    None,
+                )]
+            },
+        );
+    }
 
-                    first : f32 = foo[0];
+    #[test]
+    fn overloaded_call() {
+        check(
+            r#"
+                scale :: (x: i32, by: i32) -> i32 { x * by }
+                scale :: (x: f32, by: f32) -> f32 { x * by }
+                scale :: (x: i32) -> i32 { scale(x, 2) }
+
+                main :: () {
+                    a := scale(1, 2);
+                    b := scale(1.0, 2.0);
+                    c := scale(a);
+                    f : f32 = 3.0;
+                    d := scale(f, 2);
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
+                main::main : () -> void
+                main::scale : (i32, i32) -> i32
+                main::scale#1 : (f32, f32) -> f32
+                main::scale#2 : (i32) -> i32
                 3 : i32
                 4 : i32
-                5 : [2]i32
-                7 : []i32
-                9 : rawslice
-                12 : rawslice
-                15 : []f32
-                17 : []f32
-                18 : usize
-                19 : f32
-                20 : void
-                21 : () -> void
-                l0 : []i32
-                l1 : rawslice
-                l2 : []f32
+                5 : i32
+                6 : i32
+                7 : (i32, i32) -> i32
+                11 : f32
+                12 : f32
+                13 : f32
+                14 : f32
+                15 : (f32, f32) -> f32
+                18 : (i32, i32) -> i32
+                19 : i32
+                20 : i32
+                21 : i32
+                22 : i32
+                23 : (i32) -> i32
+                24 : (i32, i32) -> i32
+                25 : i32
+                26 : i32
+                27 : i32
+                28 : (f32, f32) -> f32
+                29 : f32
+                30 : f32
+                31 : f32
+                32 : (i32) -> i32
+                33 : i32
+                34 : i32
+                36 : f32
+                37 : (f32, f32) -> f32
+                38 : f32
+                39 : f32
+                40 : f32
+                41 : void
+                42 : () -> void
+                l0 : i32
+                l1 : f32
+                l2 : i32
                 l3 : f32
+                l4 : f32
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn cast_ptr_without_any() {
+    fn overloaded_call_ambiguous() {
         check(
             r#"
-                get_any :: () {
-                    foo : i32 = 5;
-
-                    ptr : ^i32 = ^foo;
-                    ptr : ^f32 = (^f32).(ptr);
+                small :: (x: i8) -> i8 { x }
+                small :: (x: i16) -> i16 { x }
 
-                    foo : f32 = ptr^;
+                main :: () {
+                    small(5);
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : i32
-                4 : i32
-                5 : ^i32
-                8 : ^i32
-                12 : ^f32
-                14 : ^f32
-                15 : f32
-                16 : void
-                17 : () -> void
-                l0 : i32
-                l1 : ^i32
-                l2 : ^f32
-                l3 : f32
+                main::main : () -> void
+                main::small : (i8) -> i8
+                main::small#1 : (i16) -> i16
+                2 : i8
+                3 : i8
+                4 : (i8) -> i8
+                7 : i16
+                8 : i16
+                9 : (i16) -> i16
+                10 : (i8) -> i8
+                11 : {uint}
+                12 : <unknown>
+                13 : void
+                14 : () -> void
             "#]],
-            |_| {
+            |i| {
+                let small = |param_ty: Intern<Ty>| -> Intern<Ty> {
+                    Ty::Function {
+                        param_tys: vec![ParamTy {
+                            ty: param_ty,
+                            varargs: false,
+                            impossible_to_differentiate: false,
+                        }],
+                        return_ty: param_ty,
+                    }
+                    .into()
+                };
+
                 [(
-                    TyDiagnosticKind::Uncastable {
-                        from: Ty::Pointer {
-                            mutable: false,
-                            sub_ty: Ty::IInt(32).into(),
-                        }
-                        .into(),
-                        to: Ty::Pointer {
-                            mutable: false,
-                            sub_ty: Ty::Float(32).into(),
-                        }
-                        .into(),
+                    TyDiagnosticKind::AmbiguousOverload {
+                        function: hir::Fqn {
+                            file: hir::FileName(i.intern("main.capy")),
+                            name: hir::Name(i.intern("small")),
+                        },
+                        candidates: vec![small(*ty::I8), small(Ty::IInt(16).into())],
                     },
-                    141..153,
+                    143..151,
                     None,
                 )]
             },
@@ -7004,206 +9317,346 @@ mod tests {
     }
 
     #[test]
-    fn cast_slice_without_any() {
+    fn overloaded_call_no_match() {
         check(
             r#"
-                get_any :: () {
-                    foo : [] i32 = i32.[100, 200];
+                small :: (x: i8) -> i8 { x }
+                small :: (x: i16) -> i16 { x }
 
-                    foo : [] f32 = []f32.(foo);
+                main :: () {
+                    small("five");
+                    small(1, 2);
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                3 : i32
-                4 : i32
-                5 : [2]i32
-                8 : []i32
-                11 : []f32
-                12 : void
-                13 : () -> void
-                l0 : []i32
-                l1 : []f32
+                main::main : () -> void
+                main::small : (i8) -> i8
+                main::small#1 : (i16) -> i16
+                2 : i8
+                3 : i8
+                4 : (i8) -> i8
+                7 : i16
+                8 : i16
+                9 : (i16) -> i16
+                10 : (i8) -> i8
+                11 : str
+                12 : <unknown>
+                13 : (i8) -> i8
+                14 : {uint}
+                15 : {uint}
+                16 : <unknown>
+                17 : void
+                18 : () -> void
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::Uncastable {
-                        from: Ty::Slice {
-                            sub_ty: Ty::IInt(32).into(),
-                        }
-                        .into(),
-                        to: Ty::Slice {
-                            sub_ty: Ty::Float(32).into(),
-                        }
-                        .into(),
-                    },
-                    120..131,
-                    None,
-                )]
+            |i| {
+                let small = hir::Fqn {
+                    file: hir::FileName(i.intern("main.capy")),
+                    name: hir::Name(i.intern("small")),
+                };
+
+                [
+                    (
+                        TyDiagnosticKind::NoMatchingOverload {
+                            function: small,
+                            found: vec![Ty::String.into()],
+                        },
+                        143..156,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::NoMatchingOverload {
+                            function: small,
+                            found: vec![Ty::UInt(0).into(), Ty::UInt(0).into()],
+                        },
+                        178..189,
+                        None,
+                    ),
+                ]
             },
         );
     }
 
     #[test]
-    fn deref_rawptr() {
+    fn overloaded_operator() {
         check(
             r#"
-                get_any :: () {
-                    foo : i32 = 5;
+                Meters :: distinct i32;
 
-                    ptr : ^i32 = ^foo;
-                    ptr : rawptr = (rawptr).(ptr);
+                __add :: (a: Meters, b: Meters) -> Meters { a }
 
-                    foo := ptr^;
+                Vec2 :: struct {
+                    x: i32,
+                    y: i32,
+
+                    __eq :: (a: Vec2, b: Vec2) -> bool { a.x == b.x }
+                };
+
+                main :: () {
+                    m : Meters = 5;
+                    n := m + 2;
+                    v := Vec2.{ x = 1, y = 2 };
+                    same := v == v;
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : i32
-                4 : i32
-                5 : ^i32
-                7 : ^i32
-                10 : rawptr
-                11 : rawptr
-                12 : <unknown>
-                13 : void
-                14 : () -> void
-                l0 : i32
-                l1 : ^i32
-                l2 : rawptr
-                l3 : <unknown>
+                main::Meters : type
+                main::Vec2 : type
+                main::Vec2.__eq : (main::Vec2, main::Vec2) -> bool
+                main::__add : (main::Meters, main::Meters) -> main::Meters
+                main::main : () -> void
+                1 : type
+                5 : main::Meters
+                6 : main::Meters
+                7 : (main::Meters, main::Meters) -> main::Meters
+                10 : type
+                14 : main::Vec2
+                15 : i32
+                16 : main::Vec2
+                17 : i32
+                18 : bool
+                19 : bool
+                20 : (main::Vec2, main::Vec2) -> bool
+                22 : main::Meters
+                23 : main::Meters
+                24 : main::Meters
+                25 : main::Meters
+                27 : i32
+                28 : i32
+                29 : main::Vec2
+                30 : main::Vec2
+                31 : main::Vec2
+                32 : bool
+                33 : void
+                34 : () -> void
+                l0 : main::Meters
+                l1 : main::Meters
+                l2 : main::Vec2
+                l3 : bool
             "#]],
-            |_| [(TyDiagnosticKind::DerefRaw, 187..191, None)],
+            |_| [],
         );
     }
 
     #[test]
-    fn index_rawslice() {
+    fn overloaded_operator_ambiguous() {
         check(
             r#"
-                get_any :: () {
-                    foo : [3] i32 = i32.[5, 10, 15];
+                Meters :: distinct i32;
 
-                    ptr : [] i32 = foo;
-                    ptr : rawslice = rawslice.(ptr);
+                __mul :: (a: Meters, b: i32) -> Meters { a }
+                __mul :: (a: Meters, b: i64) -> Meters { a }
 
-                    foo := ptr[0];
+                main :: () {
+                    m : Meters = 5;
+                    x : i16 = 2;
+                    n := m * x;
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                0 : usize
-                4 : i32
-                5 : i32
-                6 : i32
-                7 : [3]i32
-                10 : [3]i32
-                12 : []i32
-                14 : rawslice
-                15 : rawslice
-                16 : usize
-                17 : <unknown>
-                18 : void
-                19 : () -> void
-                l0 : [3]i32
-                l1 : []i32
-                l2 : rawslice
-                l3 : <unknown>
+                main::Meters : type
+                main::__mul : (main::Meters, i32) -> main::Meters
+                main::__mul#1 : (main::Meters, i64) -> main::Meters
+                main::main : () -> void
+                1 : type
+                5 : main::Meters
+                6 : main::Meters
+                7 : (main::Meters, i32) -> main::Meters
+                11 : main::Meters
+                12 : main::Meters
+                13 : (main::Meters, i64) -> main::Meters
+                15 : main::Meters
+                17 : i16
+                18 : main::Meters
+                19 : i16
+                20 : <unknown>
+                21 : void
+                22 : () -> void
+                l0 : main::Meters
+                l1 : i16
+                l2 : <unknown>
             "#]],
-            |_| [(TyDiagnosticKind::IndexRaw { size: None }, 208..214, None)],
+            |i| {
+                let meters: Intern<Ty> = Ty::Distinct {
+                    fqn: Some(hir::Fqn {
+                        file: hir::FileName(i.intern("main.capy")),
+                        name: hir::Name(i.intern("Meters")),
+                    }),
+                    uid: hir::type_uid("main.capy", "Meters", 0),
+                    sub_ty: Ty::IInt(32).into(),
+                }
+                .into();
+                let mul = |rhs: Ty| -> Intern<Ty> {
+                    Ty::Function {
+                        param_tys: vec![
+                            ParamTy {
+                                ty: meters,
+                                varargs: false,
+                                impossible_to_differentiate: false,
+                            },
+                            ParamTy {
+                                ty: rhs.into(),
+                                varargs: false,
+                                impossible_to_differentiate: false,
+                            },
+                        ],
+                        return_ty: meters,
+                    }
+                    .into()
+                };
+
+                [(
+                    TyDiagnosticKind::AmbiguousOverload {
+                        function: hir::Fqn {
+                            file: hir::FileName(i.intern("main.capy")),
+                            name: hir::Name(i.intern("__mul")),
+                        },
+                        candidates: vec![mul(Ty::IInt(32)), mul(Ty::IInt(64))],
+                    },
+                    288..293,
+                    None,
+                )]
+            },
         );
     }
 
-    // todo: should there be a rawarray type?
-    // #[test]
-    // fn index_any_array() {
-    //     check(
-    //         r#"
-    //             get_any :: (foo: [3] any) {
-    //                 foo : any = foo[0];
-    //             }
-    //         "#,
-    //         expect![[r#"
-    //             main::get_any : ([3]any) -> void
-    //             0 : usize
-    //             4 : [3]any
-    //             5 : usize
-    //             6 : <unknown>
-    //             7 : void
-    //             8 : ([3]any) -> void
-    //             l0 : any
-    //         "#]],
-    //         |_| [(TyDiagnosticKind::IndexRaw { size: Some(3) }, 77..83, None)],
-    //     );
-    // }
-
     #[test]
-    fn auto_real_ptr_to_any_ptr() {
+    fn method_call_syntax() {
         check(
             r#"
-                get_any :: () {
-                    foo : i32 = 5;
-                    ptr : ^i32 = ^foo;
+                #- main.capy
+                geo :: #import("geo.capy");
 
-                    ptr : rawptr = ptr;
+                double :: (x: i32) -> i32 { x * 2 }
+
+                main :: () {
+                    p := geo.Point.{ x = 1, y = 2 };
+                    a := p.moved(3).x;
+                    n : i32 = 5;
+                    b := n.double();
                 }
+                #- geo.capy
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                moved :: (p: Point, by: i32) -> Point { p }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : i32
-                4 : i32
-                5 : ^i32
-                7 : ^i32
-                8 : void
-                9 : () -> void
-                l0 : i32
-                l1 : ^i32
-                l2 : rawptr
+                geo::Point : type
+                geo::moved : (geo::Point, i32) -> geo::Point
+                main::double : (i32) -> i32
+                main::geo : file geo
+                main::main : () -> void
+                geo:
+                  2 : type
+                  6 : geo::Point
+                  7 : geo::Point
+                  8 : (geo::Point, i32) -> geo::Point
+                main:
+                  0 : file geo
+                  3 : i32
+                  4 : i32
+                  5 : i32
+                  6 : i32
+                  7 : (i32) -> i32
+                  8 : file geo
+                  10 : i32
+                  11 : i32
+                  12 : geo::Point
+                  13 : geo::Point
+                  14 : (geo::Point, i32) -> geo::Point
+                  15 : i32
+                  16 : geo::Point
+                  17 : i32
+                  19 : i32
+                  20 : i32
+                  21 : (i32) -> i32
+                  22 : i32
+                  23 : void
+                  24 : () -> void
+                  l0 : geo::Point
+                  l1 : i32
+                  l2 : i32
+                  l3 : i32
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn auto_any_ptr_to_real_ptr() {
+    fn method_call_syntax_ambiguous() {
         check(
             r#"
-                get_any :: () {
-                    foo : i32 = 5;
-                    ptr : ^i32 = ^foo;
-                    ptr : rawptr = ptr as rawptr;
+                #- main.capy
+                geo :: #import("geo.capy");
 
-                    ptr : ^i32 = ptr;
+                area :: (p: geo.Point) -> i32 { 0 }
+
+                main :: () {
+                    p := geo.Point.{ x = 1, y = 2 };
+                    a := p.area();
                 }
+                #- geo.capy
+                Point :: struct {
+                    x: i32,
+                    y: i32,
+                };
+
+                area :: (p: Point) -> i32 { p.x * p.y }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : i32
-                4 : i32
-                5 : ^i32
-                7 : ^i32
-                9 : rawptr
-                12 : rawptr
-                13 : void
-                14 : () -> void
-                l0 : i32
-                l1 : ^i32
-                l2 : rawptr
-                l3 : ^i32
+                geo::Point : type
+                geo::area : (geo::Point) -> i32
+                main::area : (geo::Point) -> i32
+                main::geo : file geo
+                main::main : () -> void
+                geo:
+                  2 : type
+                  5 : geo::Point
+                  6 : i32
+                  7 : geo::Point
+                  8 : i32
+                  9 : i32
+                  10 : i32
+                  11 : (geo::Point) -> i32
+                main:
+                  0 : file geo
+                  1 : file geo
+                  4 : i32
+                  5 : i32
+                  6 : (geo::Point) -> i32
+                  7 : file geo
+                  9 : i32
+                  10 : i32
+                  11 : geo::Point
+                  12 : geo::Point
+                  13 : <unknown>
+                  14 : <unknown>
+                  15 : void
+                  16 : () -> void
+                  l0 : geo::Point
+                  l1 : <unknown>
             "#]],
-            |_| {
+            |i| {
+                let area = i.intern("area");
+                let candidates = vec![
+                    hir::Fqn {
+                        file: hir::FileName(i.intern("main.capy")),
+                        name: hir::Name(area),
+                    },
+                    hir::Fqn {
+                        file: hir::FileName(i.intern("geo.capy")),
+                        name: hir::Name(area),
+                    },
+                ];
+
                 [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(
-                            Ty::Pointer {
-                                mutable: false,
-                                sub_ty: Ty::IInt(32).into(),
-                            }
-                            .into(),
-                        ),
-                        found: Ty::RawPtr { mutable: false }.into(),
+                    TyDiagnosticKind::AmbiguousMethod {
+                        method: area,
+                        candidates,
                     },
-                    191..194,
+                    205..211,
                     None,
                 )]
             },
@@ -7211,451 +9664,704 @@ mod tests {
     }
 
     #[test]
-    fn auto_real_slice_to_rawslice() {
+    fn const_params() {
         check(
             r#"
-                get_any :: () {
-                    foo : [] i32 = i32.[5, 10, 15];
+                filled :: (n: usize, item: u8) -> [n]u8 {
+                    u8.[item; n]
+                }
 
-                    ptr : rawslice = foo;
+                first :: (n: usize, arr: [n]i32) -> i32 {
+                    arr[0]
+                }
+
+                main :: () {
+                    a := filled(4, 1);
+                    b := filled(2, 7);
+                    c := filled(4, 0);
+                    d := first(3, i32.[5, 6, 7]);
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                3 : i32
-                4 : i32
-                5 : i32
-                6 : [3]i32
-                8 : []i32
-                9 : void
-                10 : () -> void
-                l0 : []i32
-                l1 : rawslice
+                main::filled : (usize, u8) -> <unknown>
+                main::first : (usize, <unknown>) -> i32
+                main::main : () -> void
+                main::filled(4) : (usize, u8) -> [4]u8
+                main::filled(2) : (usize, u8) -> [2]u8
+                main::first(3) : (usize, [3]i32) -> i32
+                2 : usize
+                10 : (usize, u8) -> <unknown>
+                12 : usize
+                20 : (usize, <unknown>) -> i32
+                21 : (usize, u8) -> [4]u8
+                22 : usize
+                23 : u8
+                24 : [4]u8
+                25 : (usize, u8) -> [2]u8
+                26 : usize
+                27 : u8
+                28 : [2]u8
+                29 : (usize, u8) -> [4]u8
+                30 : usize
+                31 : u8
+                32 : [4]u8
+                33 : (usize, [3]i32) -> i32
+                34 : usize
+                36 : i32
+                37 : i32
+                38 : i32
+                39 : [3]i32
+                40 : i32
+                41 : void
+                42 : () -> void
+                l0 : [4]u8
+                l1 : [2]u8
+                l2 : [4]u8
+                l3 : i32
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn auto_rawslice_to_real_slice() {
+    fn const_param_not_usize() {
         check(
             r#"
-                get_any :: () {
-                    foo : [] i32 = i32.[5, 10, 15];
-                    ptr : rawslice = rawslice.(foo);
+                sum :: (n: i32, arr: [n]i32) -> i32 {
+                    0
+                }
 
-                    ptr : [] i32 = ptr;
+                main :: () {
+                    sum(3, i32.[1, 2, 3]);
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                3 : i32
-                4 : i32
-                5 : i32
-                6 : [3]i32
-                8 : []i32
-                10 : rawslice
-                13 : rawslice
-                14 : void
-                15 : () -> void
-                l0 : []i32
-                l1 : rawslice
-                l2 : []i32
+                main::main : () -> void
+                main::sum : (i32, <unknown>) -> i32
+                main::sum(3) : (i32, <unknown>) -> i32
+                1 : i32
+                7 : (i32, <unknown>) -> i32
+                8 : (i32, <unknown>) -> i32
+                9 : i32
+                11 : i32
+                12 : i32
+                13 : i32
+                14 : [3]i32
+                15 : i32
+                16 : void
+                17 : () -> void
             "#]],
             |_| {
-                [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(
-                            Ty::Slice {
-                                sub_ty: Ty::IInt(32).into(),
-                            }
-                            .into(),
-                        ),
-                        found: Ty::RawSlice.into(),
-                    },
-                    174..177,
-                    None,
-                )]
+                [
+                    (
+                        TyDiagnosticKind::Mismatch {
+                            expected: ExpectedTy::Concrete(Ty::UInt(u8::MAX).into()),
+                            found: Ty::IInt(32).into(),
+                        },
+                        39..40,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::ConstParamNotUsize {
+                            found: Ty::IInt(32).into(),
+                        },
+                        28..31,
+                        None,
+                    ),
+                ]
             },
         );
     }
 
     #[test]
-    fn any_ptr_to_str() {
+    fn const_arg_not_const() {
         check(
             r#"
-                get_any :: () {
-                    data := char.['h', 'i', '\0'];
-                    ptr := (rawptr).(^data);
-                    str := str.(ptr);
+                first :: (n: usize, arr: [n]i32) -> i32 {
+                    arr[0]
+                }
+
+                main :: () {
+                    len := 3;
+                    first(len, i32.[1, 2, 3]);
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : char
-                2 : char
-                3 : char
-                4 : [3]char
-                5 : [3]char
-                6 : ^[3]char
-                9 : rawptr
-                10 : rawptr
-                12 : str
-                13 : void
-                14 : () -> void
-                l0 : [3]char
-                l1 : rawptr
-                l2 : str
+                main::first : (usize, <unknown>) -> i32
+                main::main : () -> void
+                1 : usize
+                9 : (usize, <unknown>) -> i32
+                10 : usize
+                11 : <unknown>
+                12 : usize
+                14 : i32
+                15 : i32
+                16 : i32
+                17 : [3]i32
+                18 : <unknown>
+                19 : void
+                20 : () -> void
+                l0 : usize
             "#]],
-            |_| [],
+            |_| [(TyDiagnosticKind::ConstArgNotConst, 190..193, None)],
         );
     }
 
     #[test]
-    fn char_ptr_to_str() {
+    fn const_param_fn_not_called() {
         check(
             r#"
-                get_any :: () {
-                    data := char.['h', 'i', '\0'];
-                    ptr := (^char).((rawptr).(^data));
-                    str := str.(ptr);
+                first :: (n: usize, arr: [n]i32) -> i32 {
+                    arr[0]
+                }
+
+                main :: () {
+                    f := first;
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : char
-                2 : char
-                3 : char
-                4 : [3]char
-                5 : [3]char
-                6 : ^[3]char
-                9 : rawptr
-                13 : ^char
-                14 : ^char
-                16 : str
-                17 : void
-                18 : () -> void
-                l0 : [3]char
-                l1 : ^char
-                l2 : str
+                main::first : (usize, <unknown>) -> i32
+                main::main : () -> void
+                1 : usize
+                9 : (usize, <unknown>) -> i32
+                10 : (usize, <unknown>) -> i32
+                11 : void
+                12 : () -> void
+                l0 : (usize, <unknown>) -> i32
             "#]],
-            |_| [],
+            |i| {
+                [(
+                    TyDiagnosticKind::ConstParamFnNotCalled {
+                        fqn: hir::Fqn {
+                            file: hir::FileName(i.intern("main.capy")),
+                            name: hir::Name(i.intern("first")),
+                        },
+                    },
+                    159..164,
+                    None,
+                )]
+            },
         );
     }
 
     #[test]
-    fn u8_ptr_to_str() {
+    fn const_param_fn_with_lambda_and_comptime() {
         check(
             r#"
-                get_any :: () {
-                    data := char.['h', 'i', '\0'];
-                    ptr := (^u8).((rawptr).(^data));
-                    str := str.(ptr);
+                first :: (n: usize, arr: [n]i32) -> i32 {
+                    get :: () -> i32 { 0 };
+                    comptime { 1 } + get()
+                }
+
+                main :: () {
+                    f := () {
+                        local :: (n: usize, arr: [n]i32) {};
+                    };
                 }
             "#,
             expect![[r#"
-                main::get_any : () -> void
-                1 : char
-                2 : char
-                3 : char
-                4 : [3]char
-                5 : [3]char
-                6 : ^[3]char
-                9 : rawptr
-                13 : ^u8
-                14 : ^u8
-                16 : str
-                17 : void
-                18 : () -> void
-                l0 : [3]char
-                l1 : ^u8
-                l2 : str
+                main::first : (usize, <unknown>) -> i32
+                main::main : () -> void
+                1 : usize
+                16 : (usize, <unknown>) -> i32
+                18 : usize
+                22 : (usize, <unknown>) -> void
+                23 : void
+                24 : () -> void
+                25 : void
+                26 : () -> void
+                l1 : (usize, <unknown>) -> void
+                l2 : () -> void
             "#]],
-            |_| [],
+            |_| {
+                [
+                    (TyDiagnosticKind::ComptimeInConstParamFn, 123..145, None),
+                    (TyDiagnosticKind::LambdaInConstParamFn, 86..101, None),
+                    (TyDiagnosticKind::LocalConstParamFn, 257..280, None),
+                ]
+            },
         );
     }
 
     #[test]
-    fn char_array_to_str() {
-        check(
-            r"
-                get_any :: () {
-                    data := char.['H', 'i', '\0'];
-                    str := str.(data);
-                }
-            ",
-            expect![[r#"
-                main::get_any : () -> void
-                1 : char
-                2 : char
-                3 : char
-                4 : [3]char
-                5 : [3]char
-                7 : str
-                8 : void
-                9 : () -> void
-                l0 : [3]char
-                l1 : str
-            "#]],
-            |_| [],
+    fn display_comptime_result() {
+        let mut interner = Interner::default();
+        let mod_dir = Path::new("");
+
+        let i32_ty: Intern<Ty> = Ty::IInt(32).into();
+        let point_ty: Intern<Ty> = Ty::Struct {
+            anonymous: false,
+            fqn: Some(hir::Fqn {
+                file: hir::FileName(interner.intern("main.capy")),
+                name: hir::Name(interner.intern("Point")),
+            }),
+            uid: 0,
+            members: vec![
+                MemberTy {
+                    name: hir::Name(interner.intern("x")),
+                    ty: i32_ty,
+                },
+                MemberTy {
+                    name: hir::Name(interner.intern("y")),
+                    ty: Ty::Bool.into(),
+                },
+            ],
+        }
+        .into();
+
+        let point = ComptimeResult::Struct {
+            ty: point_ty,
+            members: vec![
+                ComptimeResult::Integer {
+                    num: u32::MAX as u64,
+                    bit_width: 32,
+                },
+                ComptimeResult::Integer {
+                    num: 1,
+                    bit_width: 8,
+                },
+            ],
+        };
+        assert_eq!(
+            point.display(point_ty, mod_dir, &interner),
+            "Point.{ x = -1, y = true }"
         );
-    }
 
-    #[test]
-    fn char() {
-        check(
-            r"
-                foo :: () {
-                    my_char := 'A';
-                }
-            ",
-            expect![[r#"
-                main::foo : () -> void
-                0 : char
-                1 : void
-                2 : () -> void
-                l0 : char
-            "#]],
-            |_| [],
-        )
+        let array_ty: Intern<Ty> = Ty::Array {
+            anonymous: false,
+            size: 20,
+            sub_ty: i32_ty,
+        }
+        .into();
+        let array = ComptimeResult::Array {
+            ty: array_ty,
+            items: (0..20)
+                .map(|num| ComptimeResult::Integer { num, bit_width: 32 })
+                .collect(),
+        };
+        assert_eq!(
+            array.display(array_ty, mod_dir, &interner),
+            "i32.[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, ... 4 more]"
+        );
+
+        let tuple_ty: Intern<Ty> = Ty::Tuple {
+            items: vec![Ty::Float(64).into(), Ty::String.into()],
+        }
+        .into();
+        let tuple = ComptimeResult::Struct {
+            ty: tuple_ty,
+            members: vec![
+                ComptimeResult::Float {
+                    num: 1.0,
+                    bit_width: 64,
+                },
+                ComptimeResult::String("hi\n".to_string()),
+            ],
+        };
+        assert_eq!(
+            tuple.display(tuple_ty, mod_dir, &interner),
+            r#"(1.0, "hi\n")"#
+        );
     }
 
     #[test]
-    fn char_as_u8() {
+    fn interface_cast() {
         check(
-            r"
-                foo :: () {
-                    my_char := 'A';
-                    my_u8 := u8.(my_char);
+            r#"
+                Shape :: interface {
+                    area: () -> i32,
+                };
+
+                Square :: struct {
+                    side: i32,
+                };
+
+                area :: (s: ^Square) -> i32 { s.side * s.side }
+
+                main :: () -> i32 {
+                    square := Square.{ side = 3 };
+                    shape : Shape = ^square;
+                    shape.area()
                 }
-            ",
+            "#,
             expect![[r#"
-                main::foo : () -> void
-                0 : char
-                1 : char
-                3 : u8
-                4 : void
-                5 : () -> void
-                l0 : char
-                l1 : u8
+                main::Shape : type
+                main::Square : type
+                main::area : (^main::Square) -> i32
+                main::main : () -> i32
+                3 : type
+                5 : type
+                9 : ^main::Square
+                10 : i32
+                11 : ^main::Square
+                12 : i32
+                13 : i32
+                14 : i32
+                15 : (^main::Square) -> i32
+                18 : i32
+                19 : main::Square
+                21 : main::Square
+                22 : ^main::Square
+                23 : main::Shape
+                24 : () -> i32
+                25 : i32
+                26 : i32
+                27 : () -> i32
+                l0 : main::Square
+                l1 : main::Shape
             "#]],
             |_| [],
-        )
+        );
     }
 
     #[test]
-    fn no_implicit_char_to_u8() {
+    fn interface_missing_method() {
         check(
-            r"
-                foo :: () {
-                    my_char := 'A';
-                    my_u8 : u8 = my_char;
+            r#"
+                Shape :: interface {
+                    area: () -> i32,
+                };
+
+                Square :: struct {
+                    side: i32,
+                };
+
+                main :: () {
+                    square := Square.{ side = 3 };
+                    shape : Shape = ^square;
                 }
-            ",
+            "#,
             expect![[r#"
-                main::foo : () -> void
-                0 : char
-                2 : char
-                3 : void
-                4 : () -> void
-                l0 : char
-                l1 : u8
+                main::Shape : type
+                main::Square : type
+                main::main : () -> void
+                3 : type
+                5 : type
+                7 : i32
+                8 : main::Square
+                10 : main::Square
+                11 : ^main::Square
+                12 : void
+                13 : () -> void
+                l0 : main::Square
+                l1 : main::Shape
             "#]],
-            |_| {
+            |i| {
+                let square: Intern<Ty> = Ty::Struct {
+                    anonymous: false,
+                    fqn: Some(hir::Fqn {
+                        file: hir::FileName(i.intern("main.capy")),
+                        name: hir::Name(i.intern("Square")),
+                    }),
+                    uid: hir::type_uid("main.capy", "Square", 0),
+                    members: vec![MemberTy {
+                        name: hir::Name(i.intern("side")),
+                        ty: Ty::IInt(32).into(),
+                    }],
+                }
+                .into();
+                let square_ptr: Intern<Ty> = Ty::Pointer {
+                    mutable: false,
+                    sub_ty: square,
+                }
+                .into();
+
                 [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::UInt(8).into()),
-                        found: Ty::Char.into(),
+                    TyDiagnosticKind::DoesNotImplement {
+                        ty: square_ptr,
+                        interface: Ty::Interface {
+                            fqn: Some(hir::Fqn {
+                                file: hir::FileName(i.intern("main.capy")),
+                                name: hir::Name(i.intern("Shape")),
+                            }),
+                            uid: hir::type_uid("main.capy", "Shape", 0),
+                            methods: vec![MemberTy {
+                                name: hir::Name(i.intern("area")),
+                                ty: Ty::Function {
+                                    param_tys: vec![],
+                                    return_ty: Ty::IInt(32).into(),
+                                }
+                                .into(),
+                            }],
+                        }
+                        .into(),
+                        method: i.intern("area"),
+                        expected: Ty::Function {
+                            param_tys: vec![ParamTy {
+                                ty: square_ptr,
+                                varargs: false,
+                                impossible_to_differentiate: false,
+                            }],
+                            return_ty: Ty::IInt(32).into(),
+                        }
+                        .into(),
+                        found: None,
                     },
-                    98..105,
+                    297..304,
                     None,
                 )]
             },
-        )
+        );
     }
 
     #[test]
-    fn field_of_struct_ptr() {
+    fn interface_wrong_signature() {
         check(
             r#"
-                Foo :: struct {
-                    a: i32
+                Shape :: interface {
+                    area: () -> i32,
                 };
 
-                main :: () {
-                    my_foo := Foo.{
-                        a = 25
-                    };
+                Square :: struct {
+                    side: i32,
+                };
 
-                    ptr := ^my_foo;
+                area :: (s: ^Square) -> f32 { 0.0 }
 
-                    ptr.a;
+                main :: () {
+                    square := Square.{ side = 3 };
+                    shape : Shape = ^square;
                 }
             "#,
             expect![[r#"
-                main::Foo : type
+                main::Shape : type
+                main::Square : type
+                main::area : (^main::Square) -> f32
                 main::main : () -> void
-                1 : type
-                3 : i32
-                4 : main::Foo
-                5 : main::Foo
-                6 : ^main::Foo
-                7 : ^main::Foo
-                8 : i32
-                9 : void
-                10 : () -> void
-                l0 : main::Foo
-                l1 : ^main::Foo
+                3 : type
+                5 : type
+                9 : f32
+                10 : f32
+                11 : (^main::Square) -> f32
+                13 : i32
+                14 : main::Square
+                16 : main::Square
+                17 : ^main::Square
+                18 : void
+                19 : () -> void
+                l0 : main::Square
+                l1 : main::Shape
             "#]],
-            |_| [],
-        );
-    }
-
-    #[test]
-    fn field_of_struct_ptr_ptr() {
-        check(
-            r#"
-                Foo :: struct {
-                    a: i32
+            |i| {
+                let square: Intern<Ty> = Ty::Struct {
+                    anonymous: false,
+                    fqn: Some(hir::Fqn {
+                        file: hir::FileName(i.intern("main.capy")),
+                        name: hir::Name(i.intern("Square")),
+                    }),
+                    uid: hir::type_uid("main.capy", "Square", 0),
+                    members: vec![MemberTy {
+                        name: hir::Name(i.intern("side")),
+                        ty: Ty::IInt(32).into(),
+                    }],
+                }
+                .into();
+                let square_ptr: Intern<Ty> = Ty::Pointer {
+                    mutable: false,
+                    sub_ty: square,
+                }
+                .into();
+
+                [(
+                    TyDiagnosticKind::DoesNotImplement {
+                        ty: square_ptr,
+                        interface: Ty::Interface {
+                            fqn: Some(hir::Fqn {
+                                file: hir::FileName(i.intern("main.capy")),
+                                name: hir::Name(i.intern("Shape")),
+                            }),
+                            uid: hir::type_uid("main.capy", "Shape", 0),
+                            methods: vec![MemberTy {
+                                name: hir::Name(i.intern("area")),
+                                ty: Ty::Function {
+                                    param_tys: vec![],
+                                    return_ty: Ty::IInt(32).into(),
+                                }
+                                .into(),
+                            }],
+                        }
+                        .into(),
+                        method: i.intern("area"),
+                        expected: Ty::Function {
+                            param_tys: vec![ParamTy {
+                                ty: square_ptr,
+                                varargs: false,
+                                impossible_to_differentiate: false,
+                            }],
+                            return_ty: Ty::IInt(32).into(),
+                        }
+                        .into(),
+                        found: Some(
+                            Ty::Function {
+                                param_tys: vec![ParamTy {
+                                    ty: square_ptr,
+                                    varargs: false,
+                                    impossible_to_differentiate: false,
+                                }],
+                                return_ty: Ty::Float(32).into(),
+                            }
+                            .into(),
+                        ),
+                    },
+                    350..357,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn interface_method_not_called() {
+        check(
+            r#"
+                Shape :: interface {
+                    area: () -> i32,
                 };
 
-                main :: () {
-                    my_foo := Foo.{
-                        a = 25
-                    };
+                measure :: (shape: Shape) {
+                    area := shape.area;
+                }
+            "#,
+            expect![[r#"
+                main::Shape : type
+                main::measure : (main::Shape) -> void
+                3 : type
+                5 : main::Shape
+                6 : () -> i32
+                7 : void
+                8 : (main::Shape) -> void
+                l0 : () -> i32
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::InterfaceMethodNotCalled {
+                        method: i.intern("area"),
+                    },
+                    167..177,
+                    None,
+                )]
+            },
+        );
+    }
 
-                    ptr := ^^my_foo;
+    #[test]
+    fn closure_captures() {
+        check(
+            r#"
+                apply :: (f: closure (x: i32) -> i32, x: i32) -> i32 {
+                    f(x)
+                }
 
-                    ptr.a;
+                main :: () -> i32 {
+                    n := 5;
+                    apply((x: i32) -> i32 { x + n }, 2)
                 }
             "#,
             expect![[r#"
-                main::Foo : type
-                main::main : () -> void
-                1 : type
-                3 : i32
-                4 : main::Foo
-                5 : main::Foo
-                6 : ^main::Foo
-                7 : ^^main::Foo
-                8 : ^^main::Foo
+                main::apply : (closure (i32) -> i32, i32) -> i32
+                main::main : () -> i32
+                7 : closure (i32) -> i32
+                8 : i32
                 9 : i32
-                10 : void
-                11 : () -> void
-                l0 : main::Foo
-                l1 : ^^main::Foo
+                10 : i32
+                11 : (closure (i32) -> i32, i32) -> i32
+                13 : i32
+                14 : (closure (i32) -> i32, i32) -> i32
+                17 : i32
+                18 : i32
+                19 : i32
+                20 : i32
+                21 : i32
+                22 : closure (i32) -> i32
+                23 : i32
+                24 : i32
+                25 : i32
+                26 : () -> i32
+                l0 : i32
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn field_of_non_struct_ptr_ptr() {
+    fn function_into_closure() {
         check(
             r#"
-                main :: () {
-                    my_foo := 5;
-
-                    ptr := ^^my_foo;
+                double :: (x: i32) -> i32 { x * 2 }
 
-                    ptr.a;
+                main :: () {
+                    f : closure (x: i32) -> i32 = double;
+                    f(1);
                 }
             "#,
             expect![[r#"
+                main::double : (i32) -> i32
                 main::main : () -> void
-                0 : {uint}
-                1 : {uint}
-                2 : ^{uint}
-                3 : ^^{uint}
-                4 : ^^{uint}
-                5 : <unknown>
-                6 : void
-                7 : () -> void
-                l0 : {uint}
-                l1 : ^^{uint}
+                2 : i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : (i32) -> i32
+                12 : (i32) -> i32
+                13 : closure (i32) -> i32
+                14 : i32
+                15 : i32
+                16 : void
+                17 : () -> void
+                l0 : closure (i32) -> i32
             "#]],
-            |i| {
-                [(
-                    TyDiagnosticKind::NonExistentMember {
-                        member: i.intern("a"),
-                        found_ty: Ty::Pointer {
-                            mutable: false,
-                            sub_ty: Ty::Pointer {
-                                mutable: false,
-                                sub_ty: Ty::UInt(0).into(),
-                            }
-                            .into(),
-                        }
-                        .into(),
-                    },
-                    122..127,
-                    None,
-                )]
-            },
+            |_| [],
         );
     }
 
     #[test]
-    fn non_existent_field_of_struct_ptr_ptr() {
+    fn closure_into_function() {
         check(
             r#"
-                Foo :: struct {
-                    a: i32
-                };
-
                 main :: () {
-                    my_foo := Foo.{
-                        a = 25
-                    };
-
-                    ptr := ^^my_foo;
-
-                    ptr.b;
+                    n := 5;
+                    f : (x: i32) -> i32 = (x: i32) -> i32 { x + n };
                 }
             "#,
             expect![[r#"
-                main::Foo : type
                 main::main : () -> void
-                1 : type
-                3 : i32
-                4 : main::Foo
-                5 : main::Foo
-                6 : ^main::Foo
-                7 : ^^main::Foo
-                8 : ^^main::Foo
-                9 : <unknown>
-                10 : void
-                11 : () -> void
-                l0 : main::Foo
-                l1 : ^^main::Foo
+                0 : i32
+                7 : i32
+                8 : i32
+                9 : i32
+                10 : i32
+                11 : i32
+                12 : closure (i32) -> i32
+                13 : void
+                14 : () -> void
+                l0 : i32
+                l1 : (i32) -> i32
             "#]],
-            |i| {
+            |_| {
                 [(
-                    TyDiagnosticKind::NonExistentMember {
-                        member: i.intern("b"),
-                        found_ty: Ty::Pointer {
-                            mutable: false,
-                            sub_ty: Ty::Pointer {
-                                mutable: false,
-                                sub_ty: Ty::Struct {
-                                    anonymous: false,
-                                    fqn: Some(hir::Fqn {
-                                        file: hir::FileName(i.intern("main.capy")),
-                                        name: hir::Name(i.intern("Foo")),
-                                    }),
-                                    uid: 0,
-                                    members: vec![MemberTy {
-                                        name: hir::Name(i.intern("a")),
-                                        ty: Ty::IInt(32).into(),
-                                    }],
-                                }
-                                .into(),
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(
+                            Ty::Function {
+                                param_tys: vec![ParamTy {
+                                    ty: *ty::I32,
+                                    varargs: false,
+                                    impossible_to_differentiate: false,
+                                }],
+                                return_ty: *ty::I32,
                             }
                             .into(),
+                        ),
+                        found: Ty::Closure {
+                            param_tys: vec![ParamTy {
+                                ty: *ty::I32,
+                                varargs: false,
+                                impossible_to_differentiate: false,
+                            }],
+                            return_ty: *ty::I32,
                         }
                         .into(),
                     },
-                    258..263,
+                    100..125,
                     None,
                 )]
             },
@@ -7663,686 +10369,2522 @@ mod tests {
     }
 
     #[test]
-    fn mutate_field_of_struct_ptr_ptr() {
+    fn assign_captured() {
         check(
             r#"
-                Foo :: struct {
-                    a: i32
-                };
-
                 main :: () {
-                    my_foo := Foo.{
-                        a = 25
+                    n := 5;
+                    f := () {
+                        n = 6;
                     };
-
-                    ptr := ^mut ^mut my_foo;
-
-                    ptr.a = 5;
                 }
             "#,
             expect![[r#"
-                main::Foo : type
                 main::main : () -> void
-                1 : type
-                3 : i32
-                4 : main::Foo
-                5 : main::Foo
-                6 : ^mut main::Foo
-                7 : ^mut ^mut main::Foo
-                8 : ^mut ^mut main::Foo
-                9 : i32
-                10 : i32
-                11 : void
-                12 : () -> void
-                l0 : main::Foo
-                l1 : ^mut ^mut main::Foo
+                0 : {uint}
+                1 : {uint}
+                2 : {uint}
+                3 : {uint}
+                4 : void
+                5 : closure () -> void
+                6 : void
+                7 : () -> void
+                l0 : {uint}
+                l1 : closure () -> void
             "#]],
-            |_| [],
+            |_| {
+                [(
+                    TyDiagnosticKind::CannotMutate,
+                    112..118,
+                    Some((TyDiagnosticHelpKind::ImmutableCapture, 112..113)),
+                )]
+            },
         );
     }
 
     #[test]
-    fn index_of_array_ptr() {
+    fn escaping_closure() {
         check(
             r#"
-                main :: () {
-                    arr := i32.[1, 2, 3];
+                Fn :: closure () -> i32;
+                Holder :: struct { f: Fn };
 
-                    ptr := ^arr;
+                by_tail :: () -> Fn {
+                    x := 41;
+                    () -> i32 { x + 1 }
+                }
 
-                    ptr[0];
+                by_return :: (early: bool) -> Fn {
+                    x := 41;
+                    f := () -> i32 { x };
+                    if early {
+                        return f;
+                    }
+                    // only the closures made here are a problem
+                    by_tail()
+                }
+
+                by_store :: (holder: ^mut Holder) {
+                    x := 41;
+                    holder.f = () -> i32 { x };
+                }
+
+                kept_inside :: () -> i32 {
+                    x := 41;
+                    holder := Holder.{ f = () -> i32 { x + 1 } };
+                    holder.f = () -> i32 { x };
+                    holder.f()
                 }
             "#,
             expect![[r#"
-                main::main : () -> void
+                main::Fn : type
+                main::Holder : type
+                main::by_return : (bool) -> closure () -> i32
+                main::by_store : (^mut main::Holder) -> void
+                main::by_tail : () -> closure () -> i32
+                main::kept_inside : () -> i32
+                3 : type
+                5 : type
+                7 : i32
+                9 : i32
+                10 : i32
+                11 : i32
+                12 : i32
+                13 : i32
+                14 : closure () -> i32
+                15 : closure () -> i32
+                16 : () -> closure () -> i32
+                19 : i32
+                21 : i32
+                22 : i32
+                23 : i32
+                24 : closure () -> i32
+                25 : bool
+                26 : closure () -> i32
+                27 : noeval
+                28 : void
+                29 : () -> closure () -> i32
+                30 : closure () -> i32
+                31 : closure () -> i32
+                32 : (bool) -> closure () -> i32
+                35 : i32
+                36 : ^mut main::Holder
+                37 : closure () -> i32
+                39 : i32
+                40 : i32
+                41 : i32
+                42 : closure () -> i32
+                43 : void
+                44 : (^mut main::Holder) -> void
+                46 : i32
+                49 : i32
+                50 : i32
+                51 : i32
+                52 : i32
+                53 : i32
+                54 : closure () -> i32
+                55 : main::Holder
+                56 : main::Holder
+                57 : closure () -> i32
+                59 : i32
+                60 : i32
+                61 : i32
+                62 : closure () -> i32
+                63 : main::Holder
+                64 : closure () -> i32
+                65 : i32
+                66 : i32
+                67 : () -> i32
+                l0 : i32
+                l1 : i32
+                l2 : closure () -> i32
+                l3 : i32
+                l4 : i32
+                l5 : main::Holder
+            "#]],
+            |_| {
+                [
+                    (TyDiagnosticKind::EscapingClosure { returned: true }, 174..193, None),
+                    (TyDiagnosticKind::EscapingClosure { returned: true }, 318..333, None),
+                    (TyDiagnosticKind::EscapingClosure { returned: false }, 648..663, None),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn closure_of_non_function() {
+        check(
+            r#"
+                Foo :: closure i32;
+            "#,
+            expect![[r#"
+                main::Foo : type
+                1 : type
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Function,
+                        found: *ty::I32,
+                    },
+                    32..35,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn comptime_memory_limit() {
+        check(
+            r#"
+                malloc :: (size: usize) -> ^mut u8 extern;
+
+                Big :: comptime {
+                    malloc(4_000_000_000);
+                    i32
+                };
+
+                answer : Big : 42;
+            "#,
+            expect![[r#"
+                main::Big : type
+                main::answer : <unknown>
+                main::malloc : (usize) -> ^mut u8
+                4 : (usize) -> ^mut u8
+                5 : (usize) -> ^mut u8
+                6 : usize
+                7 : ^mut u8
+                8 : type
+                9 : type
+                10 : type
+                12 : {uint}
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::ComptimeLimitExceeded {
+                        limit: ComptimeLimit::Memory(codegen::ComptimeLimits::default().memory),
+                    },
+                    84..179,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn non_const_global() {
+        check(
+            r#"
+                foo :: 2 + 2;
+            "#,
+            expect![[r#"
+                main::foo : i32
+                0 : i32
                 1 : i32
                 2 : i32
+            "#]],
+            |_| [(TyDiagnosticKind::GlobalNotConst, 24..29, None)],
+        );
+    }
+
+    #[test]
+    fn const_struct_global() {
+        check(
+            r#"
+                Point :: struct { x: i32, y: i32 };
+                Line :: struct { a: Point, b: Point };
+
+                ORIGIN :: Point.{ x = 0, y = 0 };
+                LINE :: Line.{ a = ORIGIN, b = Point.{ x = 1, y = 2 } };
+                END_X :: LINE.b.x;
+            "#,
+            expect![[r#"
+                main::END_X : i32
+                main::LINE : main::Line
+                main::Line : type
+                main::ORIGIN : main::Point
+                main::Point : type
+                2 : type
+                5 : type
+                7 : i32
+                8 : i32
+                9 : main::Point
+                11 : main::Point
+                13 : i32
+                14 : i32
+                15 : main::Point
+                16 : main::Line
+                17 : main::Line
+                18 : main::Point
+                19 : i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn const_struct_global_needing_conversion() {
+        check(
+            r#"
+                Maybe :: struct { value: ?i32 };
+
+                FIVE :: Maybe.{ value = 5 };
+            "#,
+            expect![[r#"
+                main::FIVE : main::Maybe
+                main::Maybe : type
+                2 : type
+                4 : {uint}
+                5 : main::Maybe
+            "#]],
+            |_| [(TyDiagnosticKind::GlobalNotConst, 75..94, None)],
+        );
+    }
+
+    #[test]
+    fn const_array_global_with_inline_lambda() {
+        check(
+            r#"
+                double :: (x: i32) -> i32 { x * 2 }
+
+                OPS :: ((i32) -> i32).[double, (x: i32) -> i32 { x }];
+            "#,
+            expect![[r#"
+                main::OPS : [2](i32) -> i32
+                main::double : (i32) -> i32
+                2 : i32
                 3 : i32
-                4 : [3]i32
-                5 : [3]i32
-                6 : ^[3]i32
-                7 : ^[3]i32
-                8 : usize
-                9 : i32
-                10 : void
-                11 : () -> void
-                l0 : [3]i32
-                l1 : ^[3]i32
+                4 : i32
+                5 : i32
+                6 : (i32) -> i32
+                12 : (i32) -> i32
+                15 : i32
+                16 : i32
+                17 : (i32) -> i32
+                18 : [2](i32) -> i32
+            "#]],
+            |_| [(TyDiagnosticKind::GlobalNotConst, 77..123, None)],
+        );
+    }
+
+    #[test]
+    fn any_ptr() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : i32 = 5;
+
+                    ptr : ^i32 = ^foo;
+                    ptr : rawptr = (rawptr).(ptr);
+                    ptr : ^f32 = (^f32).(ptr);
+
+                    foo : f32 = ptr^;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : i32
+                4 : i32
+                5 : ^i32
+                7 : ^i32
+                10 : rawptr
+                13 : rawptr
+                17 : ^f32
+                19 : ^f32
+                20 : f32
+                21 : void
+                22 : () -> void
+                l0 : i32
+                l1 : ^i32
+                l2 : rawptr
+                l3 : ^f32
+                l4 : f32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn any_slice() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : [] i32 = i32.[100, 200];
+
+                    ptr : rawslice = rawslice.(foo);
+
+                    foo : [] f32 = []f32.(ptr);
+
+                    first : f32 = foo[0];
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                3 : i32
+                4 : i32
+                5 : [2]i32
+                7 : []i32
+                9 : rawslice
+                12 : rawslice
+                15 : []f32
+                17 : []f32
+                18 : usize
+                19 : f32
+                20 : void
+                21 : () -> void
+                l0 : []i32
+                l1 : rawslice
+                l2 : []f32
+                l3 : f32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn cast_ptr_without_any() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : i32 = 5;
+
+                    ptr : ^i32 = ^foo;
+                    ptr : ^f32 = (^f32).(ptr);
+
+                    foo : f32 = ptr^;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : i32
+                4 : i32
+                5 : ^i32
+                8 : ^i32
+                12 : ^f32
+                14 : ^f32
+                15 : f32
+                16 : void
+                17 : () -> void
+                l0 : i32
+                l1 : ^i32
+                l2 : ^f32
+                l3 : f32
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Uncastable {
+                        from: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::IInt(32).into(),
+                        }
+                        .into(),
+                        to: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::Float(32).into(),
+                        }
+                        .into(),
+                    },
+                    141..153,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn cast_slice_without_any() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : [] i32 = i32.[100, 200];
+
+                    foo : [] f32 = []f32.(foo);
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                3 : i32
+                4 : i32
+                5 : [2]i32
+                8 : []i32
+                11 : []f32
+                12 : void
+                13 : () -> void
+                l0 : []i32
+                l1 : []f32
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Uncastable {
+                        from: Ty::Slice {
+                            sub_ty: Ty::IInt(32).into(),
+                        }
+                        .into(),
+                        to: Ty::Slice {
+                            sub_ty: Ty::Float(32).into(),
+                        }
+                        .into(),
+                    },
+                    120..131,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn deref_rawptr() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : i32 = 5;
+
+                    ptr : ^i32 = ^foo;
+                    ptr : rawptr = (rawptr).(ptr);
+
+                    foo := ptr^;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : i32
+                4 : i32
+                5 : ^i32
+                7 : ^i32
+                10 : rawptr
+                11 : rawptr
+                12 : <unknown>
+                13 : void
+                14 : () -> void
+                l0 : i32
+                l1 : ^i32
+                l2 : rawptr
+                l3 : <unknown>
+            "#]],
+            |_| [(TyDiagnosticKind::DerefRaw, 187..191, None)],
+        );
+    }
+
+    #[test]
+    fn index_rawslice() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : [3] i32 = i32.[5, 10, 15];
+
+                    ptr : [] i32 = foo;
+                    ptr : rawslice = rawslice.(ptr);
+
+                    foo := ptr[0];
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                0 : usize
+                4 : i32
+                5 : i32
+                6 : i32
+                7 : [3]i32
+                10 : [3]i32
+                12 : []i32
+                14 : rawslice
+                15 : rawslice
+                16 : usize
+                17 : <unknown>
+                18 : void
+                19 : () -> void
+                l0 : [3]i32
+                l1 : []i32
+                l2 : rawslice
+                l3 : <unknown>
+            "#]],
+            |_| [(TyDiagnosticKind::IndexRaw { size: None }, 208..214, None)],
+        );
+    }
+
+    // todo: should there be a rawarray type?
+    // #[test]
+    // fn index_any_array() {
+    //     check(
+    //         r#"
+    //             get_any :: (foo: [3] any) {
+    //                 foo : any = foo[0];
+    //             }
+    //         "#,
+    //         expect![[r#"
+    //             main::get_any : ([3]any) -> void
+    //             0 : usize
+    //             4 : [3]any
+    //             5 : usize
+    //             6 : <unknown>
+    //             7 : void
+    //             8 : ([3]any) -> void
+    //             l0 : any
+    //         "#]],
+    //         |_| [(TyDiagnosticKind::IndexRaw { size: Some(3) }, 77..83, None)],
+    //     );
+    // }
+
+    #[test]
+    fn auto_real_ptr_to_any_ptr() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : i32 = 5;
+                    ptr : ^i32 = ^foo;
+
+                    ptr : rawptr = ptr;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : i32
+                4 : i32
+                5 : ^i32
+                7 : ^i32
+                8 : void
+                9 : () -> void
+                l0 : i32
+                l1 : ^i32
+                l2 : rawptr
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn auto_any_ptr_to_real_ptr() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : i32 = 5;
+                    ptr : ^i32 = ^foo;
+                    ptr : rawptr = ptr as rawptr;
+
+                    ptr : ^i32 = ptr;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : i32
+                4 : i32
+                5 : ^i32
+                7 : ^i32
+                9 : rawptr
+                12 : rawptr
+                13 : void
+                14 : () -> void
+                l0 : i32
+                l1 : ^i32
+                l2 : rawptr
+                l3 : ^i32
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(
+                            Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                        ),
+                        found: Ty::RawPtr { mutable: false }.into(),
+                    },
+                    191..194,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn auto_real_slice_to_rawslice() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : [] i32 = i32.[5, 10, 15];
+
+                    ptr : rawslice = foo;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : [3]i32
+                8 : []i32
+                9 : void
+                10 : () -> void
+                l0 : []i32
+                l1 : rawslice
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn auto_rawslice_to_real_slice() {
+        check(
+            r#"
+                get_any :: () {
+                    foo : [] i32 = i32.[5, 10, 15];
+                    ptr : rawslice = rawslice.(foo);
+
+                    ptr : [] i32 = ptr;
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : [3]i32
+                8 : []i32
+                10 : rawslice
+                13 : rawslice
+                14 : void
+                15 : () -> void
+                l0 : []i32
+                l1 : rawslice
+                l2 : []i32
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(
+                            Ty::Slice {
+                                sub_ty: Ty::IInt(32).into(),
+                            }
+                            .into(),
+                        ),
+                        found: Ty::RawSlice.into(),
+                    },
+                    174..177,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn any_ptr_to_str() {
+        check(
+            r#"
+                get_any :: () {
+                    data := char.['h', 'i', '\0'];
+                    ptr := (rawptr).(^data);
+                    str := str.(ptr);
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : char
+                2 : char
+                3 : char
+                4 : [3]char
+                5 : [3]char
+                6 : ^[3]char
+                9 : rawptr
+                10 : rawptr
+                12 : str
+                13 : void
+                14 : () -> void
+                l0 : [3]char
+                l1 : rawptr
+                l2 : str
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn char_ptr_to_str() {
+        check(
+            r#"
+                get_any :: () {
+                    data := char.['h', 'i', '\0'];
+                    ptr := (^char).((rawptr).(^data));
+                    str := str.(ptr);
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : char
+                2 : char
+                3 : char
+                4 : [3]char
+                5 : [3]char
+                6 : ^[3]char
+                9 : rawptr
+                13 : ^char
+                14 : ^char
+                16 : str
+                17 : void
+                18 : () -> void
+                l0 : [3]char
+                l1 : ^char
+                l2 : str
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn u8_ptr_to_str() {
+        check(
+            r#"
+                get_any :: () {
+                    data := char.['h', 'i', '\0'];
+                    ptr := (^u8).((rawptr).(^data));
+                    str := str.(ptr);
+                }
+            "#,
+            expect![[r#"
+                main::get_any : () -> void
+                1 : char
+                2 : char
+                3 : char
+                4 : [3]char
+                5 : [3]char
+                6 : ^[3]char
+                9 : rawptr
+                13 : ^u8
+                14 : ^u8
+                16 : str
+                17 : void
+                18 : () -> void
+                l0 : [3]char
+                l1 : ^u8
+                l2 : str
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn char_array_to_str() {
+        check(
+            r"
+                get_any :: () {
+                    data := char.['H', 'i', '\0'];
+                    str := str.(data);
+                }
+            ",
+            expect![[r#"
+                main::get_any : () -> void
+                1 : char
+                2 : char
+                3 : char
+                4 : [3]char
+                5 : [3]char
+                7 : str
+                8 : void
+                9 : () -> void
+                l0 : [3]char
+                l1 : str
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn char() {
+        check(
+            r"
+                foo :: () {
+                    my_char := 'A';
+                }
+            ",
+            expect![[r#"
+                main::foo : () -> void
+                0 : char
+                1 : void
+                2 : () -> void
+                l0 : char
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn char_as_u8() {
+        check(
+            r"
+                foo :: () {
+                    my_char := 'A';
+                    my_u8 := u8.(my_char);
+                }
+            ",
+            expect![[r#"
+                main::foo : () -> void
+                0 : char
+                1 : char
+                3 : u8
+                4 : void
+                5 : () -> void
+                l0 : char
+                l1 : u8
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn no_implicit_char_to_u8() {
+        check(
+            r"
+                foo :: () {
+                    my_char := 'A';
+                    my_u8 : u8 = my_char;
+                }
+            ",
+            expect![[r#"
+                main::foo : () -> void
+                0 : char
+                2 : char
+                3 : void
+                4 : () -> void
+                l0 : char
+                l1 : u8
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::UInt(8).into()),
+                        found: Ty::Char.into(),
+                    },
+                    98..105,
+                    None,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn field_of_struct_ptr() {
+        check(
+            r#"
+                Foo :: struct {
+                    a: i32
+                };
+
+                main :: () {
+                    my_foo := Foo.{
+                        a = 25
+                    };
+
+                    ptr := ^my_foo;
+
+                    ptr.a;
+                }
+            "#,
+            expect![[r#"
+                main::Foo : type
+                main::main : () -> void
+                1 : type
+                3 : i32
+                4 : main::Foo
+                5 : main::Foo
+                6 : ^main::Foo
+                7 : ^main::Foo
+                8 : i32
+                9 : void
+                10 : () -> void
+                l0 : main::Foo
+                l1 : ^main::Foo
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn field_of_struct_ptr_ptr() {
+        check(
+            r#"
+                Foo :: struct {
+                    a: i32
+                };
+
+                main :: () {
+                    my_foo := Foo.{
+                        a = 25
+                    };
+
+                    ptr := ^^my_foo;
+
+                    ptr.a;
+                }
+            "#,
+            expect![[r#"
+                main::Foo : type
+                main::main : () -> void
+                1 : type
+                3 : i32
+                4 : main::Foo
+                5 : main::Foo
+                6 : ^main::Foo
+                7 : ^^main::Foo
+                8 : ^^main::Foo
+                9 : i32
+                10 : void
+                11 : () -> void
+                l0 : main::Foo
+                l1 : ^^main::Foo
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn field_of_non_struct_ptr_ptr() {
+        check(
+            r#"
+                main :: () {
+                    my_foo := 5;
+
+                    ptr := ^^my_foo;
+
+                    ptr.a;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : {uint}
+                1 : {uint}
+                2 : ^{uint}
+                3 : ^^{uint}
+                4 : ^^{uint}
+                5 : <unknown>
+                6 : void
+                7 : () -> void
+                l0 : {uint}
+                l1 : ^^{uint}
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::NonExistentMember {
+                        member: i.intern("a"),
+                        found_ty: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::UInt(0).into(),
+                            }
+                            .into(),
+                        }
+                        .into(),
+                    },
+                    122..127,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn non_existent_field_of_struct_ptr_ptr() {
+        check(
+            r#"
+                Foo :: struct {
+                    a: i32
+                };
+
+                main :: () {
+                    my_foo := Foo.{
+                        a = 25
+                    };
+
+                    ptr := ^^my_foo;
+
+                    ptr.b;
+                }
+            "#,
+            expect![[r#"
+                main::Foo : type
+                main::main : () -> void
+                1 : type
+                3 : i32
+                4 : main::Foo
+                5 : main::Foo
+                6 : ^main::Foo
+                7 : ^^main::Foo
+                8 : ^^main::Foo
+                9 : <unknown>
+                10 : void
+                11 : () -> void
+                l0 : main::Foo
+                l1 : ^^main::Foo
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::NonExistentMember {
+                        member: i.intern("b"),
+                        found_ty: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::Struct {
+                                    anonymous: false,
+                                    fqn: Some(hir::Fqn {
+                                        file: hir::FileName(i.intern("main.capy")),
+                                        name: hir::Name(i.intern("Foo")),
+                                    }),
+                                    uid: hir::type_uid("main.capy", "Foo", 0),
+                                    members: vec![MemberTy {
+                                        name: hir::Name(i.intern("a")),
+                                        ty: Ty::IInt(32).into(),
+                                    }],
+                                }
+                                .into(),
+                            }
+                            .into(),
+                        }
+                        .into(),
+                    },
+                    258..263,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn member_of_array_of_structs() {
+        check(
+            r#"
+                Foo :: struct {
+                    a: i32
+                };
+
+                main :: () {
+                    foos : [2]Foo;
+
+                    foos.a;
+                }
+            "#,
+            expect![[r#"
+                main::Foo : type
+                main::main : () -> void
+                1 : type
+                2 : usize
+                5 : [2]main::Foo
+                6 : <unknown>
+                7 : void
+                8 : () -> void
+                l0 : [2]main::Foo
+            "#]],
+            |i| {
+                let foo_ty: Intern<Ty> = Ty::Struct {
+                    anonymous: false,
+                    fqn: Some(hir::Fqn {
+                        file: hir::FileName(i.intern("main.capy")),
+                        name: hir::Name(i.intern("Foo")),
+                    }),
+                    uid: hir::type_uid("main.capy", "Foo", 0),
+                    members: vec![MemberTy {
+                        name: hir::Name(i.intern("a")),
+                        ty: Ty::IInt(32).into(),
+                    }],
+                }
+                .into();
+
+                [(
+                    TyDiagnosticKind::MemberOfCollection {
+                        member: i.intern("a"),
+                        collection_ty: Ty::Array {
+                            anonymous: false,
+                            size: 2,
+                            sub_ty: foo_ty,
+                        }
+                        .into(),
+                        element_ty: foo_ty,
+                    },
+                    165..171,
+                    Some((
+                        TyDiagnosticHelpKind::IndexCollectionFirst { element_ty: foo_ty },
+                        165..169,
+                    )),
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn struct_decls() {
+        check(
+            r#"
+                Point :: struct {
+                    x: i32,
+                    ORIGIN :: Point.{ x = 0 },
+                    get_x :: (self: Point) -> i32 { self.x },
+                };
+
+                main :: () -> i32 {
+                    p := Point.ORIGIN;
+                    p.get_x() + Point.get_x(p)
+                }
+            "#,
+            expect![[r#"
+                main::Point : type
+                main::Point.ORIGIN : main::Point
+                main::Point.get_x : (main::Point) -> i32
+                main::main : () -> i32
+                1 : type
+                3 : i32
+                4 : main::Point
+                7 : main::Point
+                8 : i32
+                9 : i32
+                10 : (main::Point) -> i32
+                12 : type
+                13 : main::Point
+                14 : main::Point
+                15 : (main::Point) -> i32
+                16 : i32
+                17 : type
+                18 : (main::Point) -> i32
+                19 : main::Point
+                20 : i32
+                21 : i32
+                22 : i32
+                23 : () -> i32
+                l0 : main::Point
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn this_file() {
+        check(
+            r#"
+                foo :: () -> i32 { 5 };
+                main :: () -> i32 { #this().foo() }
+            "#,
+            expect![[r#"
+                main::foo : () -> i32
+                main::main : () -> i32
+                1 : i32
+                2 : i32
+                3 : () -> i32
+                5 : file main
+                6 : () -> i32
+                7 : i32
+                8 : i32
+                9 : () -> i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn using_import() {
+        check(
+            r#"
+                #- main.capy
+                using #import("numbers.capy");
+
+                main :: () -> i32 {
+                    x : imaginary = 5;
+                    add(1, 2)
+                }
+                #- numbers.capy
+                imaginary :: distinct i32;
+
+                add :: (a: i32, b: i32) -> i32 { a + b };
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                numbers::add : (i32, i32) -> i32
+                numbers::imaginary : type
+                numbers:
+                  1 : type
+                  5 : i32
+                  6 : i32
+                  7 : i32
+                  8 : i32
+                  9 : (i32, i32) -> i32
+                main:
+                  2 : numbers::imaginary
+                  3 : (i32, i32) -> i32
+                  4 : i32
+                  5 : i32
+                  6 : i32
+                  7 : i32
+                  8 : () -> i32
+                  l0 : numbers::imaginary
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn using_import_already_defined() {
+        check(
+            r#"
+                #- main.capy
+                using #import("numbers.capy");
+
+                foo :: 1;
+
+                main :: () -> i32 { foo + bar }
+                #- numbers.capy
+                foo :: 2;
+                bar :: 3;
+            "#,
+            expect![[r#"
+                main::foo : i32
+                main::main : () -> i32
+                numbers::bar : i32
+                numbers::foo : i32
+                numbers:
+                  0 : i32
+                  1 : i32
+                main:
+                  0 : i32
+                  2 : i32
+                  3 : i32
+                  4 : i32
+                  5 : i32
+                  6 : () -> i32
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::UsingAlreadyDefined {
+                        fqn: hir::Fqn {
+                            file: hir::FileName(i.intern("numbers.capy")),
+                            name: hir::Name(i.intern("foo")),
+                        },
+                        defined_in: hir::FileName(i.intern("main.capy")),
+                    },
+                    16..46,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn mutate_field_of_struct_ptr_ptr() {
+        check(
+            r#"
+                Foo :: struct {
+                    a: i32
+                };
+
+                main :: () {
+                    my_foo := Foo.{
+                        a = 25
+                    };
+
+                    ptr := ^mut ^mut my_foo;
+
+                    ptr.a = 5;
+                }
+            "#,
+            expect![[r#"
+                main::Foo : type
+                main::main : () -> void
+                1 : type
+                3 : i32
+                4 : main::Foo
+                5 : main::Foo
+                6 : ^mut main::Foo
+                7 : ^mut ^mut main::Foo
+                8 : ^mut ^mut main::Foo
+                9 : i32
+                10 : i32
+                11 : void
+                12 : () -> void
+                l0 : main::Foo
+                l1 : ^mut ^mut main::Foo
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn index_of_array_ptr() {
+        check(
+            r#"
+                main :: () {
+                    arr := i32.[1, 2, 3];
+
+                    ptr := ^arr;
+
+                    ptr[0];
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : [3]i32
+                5 : [3]i32
+                6 : ^[3]i32
+                7 : ^[3]i32
+                8 : usize
+                9 : i32
+                10 : void
+                11 : () -> void
+                l0 : [3]i32
+                l1 : ^[3]i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn index_of_array_ptr_ptr() {
+        check(
+            r#"
+                main :: () {
+                    arr := i32.[1, 2, 3];
+
+                    ptr := ^^arr;
+
+                    ptr[0];
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : [3]i32
+                5 : [3]i32
+                6 : ^[3]i32
+                7 : ^^[3]i32
+                8 : ^^[3]i32
+                9 : usize
+                10 : i32
+                11 : void
+                12 : () -> void
+                l0 : [3]i32
+                l1 : ^^[3]i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn index_of_non_array_ptr_ptr() {
+        check(
+            r#"
+                main :: () {
+                    arr := 5;
+
+                    ptr := ^^arr;
+
+                    ptr[0];
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : {uint}
+                1 : {uint}
+                2 : ^{uint}
+                3 : ^^{uint}
+                4 : ^^{uint}
+                5 : usize
+                6 : <unknown>
+                7 : void
+                8 : () -> void
+                l0 : {uint}
+                l1 : ^^{uint}
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::IndexNonArray {
+                        found: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::UInt(0).into(),
+                            }
+                            .into(),
+                        }
+                        .into(),
+                    },
+                    116..122,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn index_too_large_of_array_ptr_ptr() {
+        check(
+            r#"
+                main :: () {
+                    arr := i32.[1, 2, 3];
+
+                    ptr := ^^arr;
+
+                    ptr[10];
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : [3]i32
+                5 : [3]i32
+                6 : ^[3]i32
+                7 : ^^[3]i32
+                8 : ^^[3]i32
+                9 : usize
+                10 : i32
+                11 : void
+                12 : () -> void
+                l0 : [3]i32
+                l1 : ^^[3]i32
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::IndexOutOfBounds {
+                        index: 10,
+                        actual_size: 3,
+                        array_ty: Ty::Pointer {
+                            mutable: false,
+                            sub_ty: Ty::Pointer {
+                                mutable: false,
+                                sub_ty: Ty::Array {
+                                    anonymous: false,
+                                    size: 3,
+                                    sub_ty: Ty::IInt(32).into(),
+                                }
+                                .into(),
+                            }
+                            .into(),
+                        }
+                        .into(),
+                    },
+                    128..135,
+                    None,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn index_too_large_in_multidimensional_array() {
+        check(
+            r#"
+                main :: () {
+                    grid : [3][4]i32;
+
+                    a := grid[1][9];
+                    b := grid[5][0];
+
+                    cols : [grid[0].len]u8;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : usize
+                1 : usize
+                5 : [3][4]i32
+                6 : usize
+                7 : [4]i32
+                8 : usize
+                9 : i32
+                10 : [3][4]i32
+                11 : usize
+                12 : [4]i32
+                13 : usize
+                14 : i32
+                15 : [3][4]i32
+                16 : usize
+                17 : [4]i32
+                18 : usize
+                21 : void
+                22 : () -> void
+                l0 : [3][4]i32
+                l1 : i32
+                l2 : i32
+                l3 : [4]u8
+            "#]],
+            |_| {
+                let grid_ty: Intern<Ty> = Ty::Array {
+                    anonymous: false,
+                    size: 3,
+                    sub_ty: Ty::Array {
+                        anonymous: false,
+                        size: 4,
+                        sub_ty: Ty::IInt(32).into(),
+                    }
+                    .into(),
+                }
+                .into();
+
+                [
+                    (
+                        TyDiagnosticKind::DimensionOutOfBounds {
+                            index: 9,
+                            actual_size: 4,
+                            dimension: 2,
+                            array_ty: grid_ty,
+                        },
+                        94..104,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::DimensionOutOfBounds {
+                            index: 5,
+                            actual_size: 3,
+                            dimension: 1,
+                            array_ty: grid_ty,
+                        },
+                        131..138,
+                        None,
+                    ),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn mutable_index_of_array_ptr_ptr() {
+        check(
+            r#"
+                main :: () {
+                    arr := i32.[1, 2, 3];
+
+                    ptr :: ^mut ^mut arr;
+
+                    ptr[1] = 50;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : [3]i32
+                5 : [3]i32
+                6 : ^mut [3]i32
+                7 : ^mut ^mut [3]i32
+                8 : ^mut ^mut [3]i32
+                9 : usize
+                10 : i32
+                11 : i32
+                12 : void
+                13 : () -> void
+                l0 : [3]i32
+                l1 : ^mut ^mut [3]i32
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn immutable_index_of_array_ptr_ptr() {
+        check(
+            r#"
+                main :: () {
+                    arr :: i32.[1, 2, 3];
+
+                    ptr := ^^arr;
+
+                    ptr[1] = 50;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : [3]i32
+                5 : [3]i32
+                6 : ^[3]i32
+                7 : ^^[3]i32
+                8 : ^^[3]i32
+                9 : usize
+                10 : i32
+                11 : {uint}
+                12 : void
+                13 : () -> void
+                l0 : [3]i32
+                l1 : ^^[3]i32
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::CannotMutate,
+                    128..140,
+                    Some((TyDiagnosticHelpKind::ImmutableRef, 100..105)),
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn entry_point_void() {
+        check_impl(
+            r#"
+                start :: () {};
+            "#,
+            expect![[r#"
+                main::start : () -> void
+                0 : void
+                1 : () -> void
+            "#]],
+            |_| [],
+            Some("start"),
+        )
+    }
+
+    #[test]
+    fn entry_point_int() {
+        check_impl(
+            r#"
+                entry :: () -> i16 { 0 };
+            "#,
+            expect![[r#"
+                main::entry : () -> i16
+                1 : i16
+                2 : i16
+                3 : () -> i16
+            "#]],
+            |_| [],
+            Some("entry"),
+        )
+    }
+
+    #[test]
+    fn entry_point_uint() {
+        check_impl(
+            r#"
+                main :: () -> usize { 0 };
+            "#,
+            expect![[r#"
+                main::main : () -> usize
+                1 : usize
+                2 : usize
+                3 : () -> usize
+            "#]],
+            |_| [],
+            Some("main"),
+        )
+    }
+
+    #[test]
+    fn entry_point_non_function() {
+        check_impl(
+            r#"
+                main :: 5;
+            "#,
+            expect![[r#"
+                main::main : i32
+                0 : i32
+            "#]],
+            |_| [(TyDiagnosticKind::EntryNotFunction, 17..27, None)],
+            Some("main"),
+        )
+    }
+
+    #[test]
+    fn entry_point_bad_params_and_return() {
+        check_impl(
+            r#"
+                foo :: (x: i32, y: bool) -> str {
+                    "Hello!"
+                }
+            "#,
+            expect![[r#"
+                main::foo : (i32, bool) -> str
+                3 : str
+                4 : str
+                5 : (i32, bool) -> str
+            "#]],
+            |_| {
+                [
+                    (TyDiagnosticKind::EntryHasParams, 24..41, None),
+                    (TyDiagnosticKind::EntryBadReturn, 45..48, None),
+                ]
+            },
+            Some("foo"),
+        )
+    }
+
+    #[test]
+    fn array_of_local_ty() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    int :: i32;
+                    imaginary :: distinct int;
+                    imaginary_vec3 :: distinct [3] imaginary;
+
+                    arr : imaginary_vec3 = imaginary.[1, 2, 3];
+
+                    i32.(arr[0])
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                1 : type
+                3 : type
+                4 : usize
+                7 : type
+                10 : {uint}
+                11 : {uint}
+                12 : {uint}
+                13 : [3]distinct'18053702345348569414 i32
+                14 : distinct'11134673619653301719 [3]distinct'18053702345348569414 i32
+                15 : usize
+                16 : distinct'18053702345348569414 i32
+                18 : i32
+                19 : i32
+                20 : () -> i32
+                l0 : type
+                l1 : type
+                l2 : type
+                l3 : distinct'11134673619653301719 [3]distinct'18053702345348569414 i32
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn struct_of_local_tys() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    int :: i32;
+                    imaginary :: distinct int;
+                    complex :: struct {
+                        real_part: int,
+                        imaginary_part: imaginary,
+                    };
+
+                    my_complex := complex.{
+                        real_part = 5,
+                        imaginary_part = 42,
+                    };
+
+                    i32.(my_complex.real_part) + i32.(my_complex.imaginary_part)
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                1 : type
+                3 : type
+                6 : type
+                8 : i32
+                9 : distinct'18053702345348569414 i32
+                10 : struct'11134673619653301719 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                11 : struct'11134673619653301719 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                12 : i32
+                14 : i32
+                15 : struct'11134673619653301719 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                16 : distinct'18053702345348569414 i32
+                18 : i32
+                19 : i32
+                20 : i32
+                21 : () -> i32
+                l0 : type
+                l1 : type
+                l2 : type
+                l3 : struct'11134673619653301719 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn lambda_with_local_ty() {
+        check(
+            r#"
+                main :: () -> i32 {
+                    int :: i32;
+                    imaginary :: distinct int;
+                    imaginary_vec3 :: distinct [3] imaginary;
+                    complex :: struct {
+                        real_part: int,
+                        imaginary_part: imaginary,
+                    };
+                
+                    my_complex := complex.{
+                        real_part = 5,
+                        imaginary_part = 42,
+                    };
+                
+                    do_math :: (c: complex) -> imaginary_vec3 {
+                        // this is kind of akward because while we can access locals
+                        // in the parameters and return type, we can't access `imaginary`
+                        // from inside the body of this lambda
+                        // this could be alleviated by adding a `type_of` builtin
+                        i32.[1, c.real_part * i32.(c.imaginary_part), 3]
+                    };
+                
+                    i32.(do_math(my_complex)[1])
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> i32
+                1 : type
+                3 : type
+                4 : usize
+                7 : type
+                10 : type
+                12 : i32
+                13 : distinct'18053702345348569414 i32
+                14 : struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                18 : i32
+                19 : struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                20 : i32
+                21 : struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                22 : distinct'18053702345348569414 i32
+                24 : i32
+                25 : i32
+                26 : i32
+                27 : [3]i32
+                28 : [3]i32
+                29 : (struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}) -> distinct'11134673619653301719 [3]distinct'18053702345348569414 i32
+                30 : (struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}) -> distinct'11134673619653301719 [3]distinct'18053702345348569414 i32
+                31 : struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                32 : distinct'11134673619653301719 [3]distinct'18053702345348569414 i32
+                33 : usize
+                34 : distinct'18053702345348569414 i32
+                36 : i32
+                37 : i32
+                38 : () -> i32
+                l0 : type
+                l1 : type
+                l2 : type
+                l3 : type
+                l4 : struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}
+                l5 : (struct'13445015723029553188 {real_part: i32, imaginary_part: distinct'18053702345348569414 i32}) -> distinct'11134673619653301719 [3]distinct'18053702345348569414 i32
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn break_void_block_no_tail_match() {
+        check(
+            r#"
+                foo :: () {
+                    {
+                        break;
+                        break {};
+                        break {};
+                    }               
+                }
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                0 : void
+                1 : void
+                2 : void
+                3 : void
+                4 : () -> void
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn break_i32_block_no_tail_mismatch() {
+        check(
+            r#"
+                foo :: () {
+                    {
+                        break 123;
+                        break {};
+                        break true;
+                    }               
+                }
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                0 : {uint}
+                1 : void
+                2 : bool
+                3 : <unknown>
+                4 : <unknown>
+                5 : () -> void
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::BreakMismatch {
+                        breaks: vec![
+                            (TextRange::new(75.into(), 85.into()), Ty::UInt(0).into()),
+                            (TextRange::new(110.into(), 119.into()), Ty::Void.into()),
+                            (TextRange::new(144.into(), 155.into()), Ty::Bool.into()),
+                        ],
+                    },
+                    49..177,
+                    None,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn paren_infer() {
+        check(
+            r#"
+                foo :: () -> u16 {
+                    (42 * (11 / 2))
+                }
+            "#,
+            expect![[r#"
+                main::foo : () -> u16
+                1 : u16
+                2 : u16
+                3 : u16
+                4 : u16
+                5 : u16
+                6 : u16
+                7 : u16
+                8 : u16
+                9 : () -> u16
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn paren_spread() {
+        check(
+            r#"
+                foo :: () {
+                    x : i8 = 42;
+                    (42 * ((2 >> x) / 2));
+                }
+            "#,
+            expect![[r#"
+                main::foo : () -> void
+                1 : i8
+                2 : i8
+                3 : i8
+                4 : i8
+                5 : i8
+                6 : i8
+                7 : i8
+                8 : i8
+                9 : i8
+                10 : i8
+                11 : i8
+                12 : void
+                13 : () -> void
+                l0 : i8
             "#]],
             |_| [],
-        );
+        )
     }
 
     #[test]
-    fn index_of_array_ptr_ptr() {
+    fn break_i32_block_tail_match() {
         check(
             r#"
-                main :: () {
-                    arr := i32.[1, 2, 3];
-
-                    ptr := ^^arr;
-
-                    ptr[0];
+                foo :: () -> i32 {
+                    {
+                        break 123;
+                        42
+                    }               
                 }
             "#,
             expect![[r#"
-                main::main : () -> void
+                main::foo : () -> i32
                 1 : i32
                 2 : i32
                 3 : i32
-                4 : [3]i32
-                5 : [3]i32
-                6 : ^[3]i32
-                7 : ^^[3]i32
-                8 : ^^[3]i32
-                9 : usize
-                10 : i32
-                11 : void
-                12 : () -> void
-                l0 : [3]i32
-                l1 : ^^[3]i32
+                4 : i32
+                5 : () -> i32
             "#]],
             |_| [],
-        );
+        )
     }
 
     #[test]
-    fn index_of_non_array_ptr_ptr() {
+    fn break_void_block_tail_mismatch() {
         check(
             r#"
-                main :: () {
-                    arr := 5;
-
-                    ptr := ^^arr;
-
-                    ptr[0];
+                foo :: () {
+                    {
+                        break {};
+                        42
+                    }
                 }
             "#,
             expect![[r#"
-                main::main : () -> void
-                0 : {uint}
+                main::foo : () -> void
+                0 : void
                 1 : {uint}
-                2 : ^{uint}
-                3 : ^^{uint}
-                4 : ^^{uint}
-                5 : usize
-                6 : <unknown>
-                7 : void
-                8 : () -> void
-                l0 : {uint}
-                l1 : ^^{uint}
+                2 : <unknown>
+                3 : <unknown>
+                4 : () -> void
             "#]],
             |_| {
                 [(
-                    TyDiagnosticKind::IndexNonArray {
-                        found: Ty::Pointer {
-                            mutable: false,
-                            sub_ty: Ty::Pointer {
-                                mutable: false,
-                                sub_ty: Ty::UInt(0).into(),
-                            }
-                            .into(),
-                        }
-                        .into(),
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::Void.into()),
+                        found: Ty::UInt(0).into(),
                     },
-                    116..122,
-                    None,
+                    109..111,
+                    Some((
+                        TyDiagnosticHelpKind::BreakHere {
+                            break_ty: Ty::Void.into(),
+                        },
+                        75..84,
+                    )),
                 )]
             },
-        );
+        )
     }
 
     #[test]
-    fn index_too_large_of_array_ptr_ptr() {
+    fn break_i32_block_from_inner() {
         check(
             r#"
-                main :: () {
-                    arr := i32.[1, 2, 3];
-
-                    ptr := ^^arr;
+                foo :: () {
+                    `blk: {
+                        {
+                            break `blk {};
+                        }
 
-                    ptr[10];
+                        42
+                    }               
                 }
             "#,
             expect![[r#"
-                main::main : () -> void
-                1 : i32
-                2 : i32
-                3 : i32
-                4 : [3]i32
-                5 : [3]i32
-                6 : ^[3]i32
-                7 : ^^[3]i32
-                8 : ^^[3]i32
-                9 : usize
-                10 : i32
-                11 : void
-                12 : () -> void
-                l0 : [3]i32
-                l1 : ^^[3]i32
+                main::foo : () -> void
+                0 : void
+                1 : noeval
+                2 : {uint}
+                3 : <unknown>
+                4 : <unknown>
+                5 : () -> void
             "#]],
             |_| {
                 [(
-                    TyDiagnosticKind::IndexOutOfBounds {
-                        index: 10,
-                        actual_size: 3,
-                        array_ty: Ty::Pointer {
-                            mutable: false,
-                            sub_ty: Ty::Pointer {
-                                mutable: false,
-                                sub_ty: Ty::Array {
-                                    anonymous: false,
-                                    size: 3,
-                                    sub_ty: Ty::IInt(32).into(),
-                                }
-                                .into(),
-                            }
-                            .into(),
-                        }
-                        .into(),
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::Void.into()),
+                        found: Ty::UInt(0).into(),
                     },
-                    128..135,
-                    None,
+                    177..179,
+                    Some((
+                        TyDiagnosticHelpKind::BreakHere {
+                            break_ty: Ty::Void.into(),
+                        },
+                        111..125,
+                    )),
                 )]
             },
-        );
-    }
-
-    #[test]
-    fn mutable_index_of_array_ptr_ptr() {
-        check(
-            r#"
-                main :: () {
-                    arr := i32.[1, 2, 3];
-
-                    ptr :: ^mut ^mut arr;
-
-                    ptr[1] = 50;
-                }
-            "#,
-            expect![[r#"
-                main::main : () -> void
-                1 : i32
-                2 : i32
-                3 : i32
-                4 : [3]i32
-                5 : [3]i32
-                6 : ^mut [3]i32
-                7 : ^mut ^mut [3]i32
-                8 : ^mut ^mut [3]i32
-                9 : usize
-                10 : i32
-                11 : i32
-                12 : void
-                13 : () -> void
-                l0 : [3]i32
-                l1 : ^mut ^mut [3]i32
-            "#]],
-            |_| [],
-        );
+        )
     }
 
     #[test]
-    fn immutable_index_of_array_ptr_ptr() {
+    fn break_i32_block_from_inner_tail() {
         check(
             r#"
-                main :: () {
-                    arr :: i32.[1, 2, 3];
-
-                    ptr := ^^arr;
-
-                    ptr[1] = 50;
+            foo :: () -> i32 {
+                `blk: {
+                    {
+                        break `blk true;
+                        break 5;
+                        42
+                    }
                 }
+            }
             "#,
             expect![[r#"
-                main::main : () -> void
-                1 : i32
-                2 : i32
-                3 : i32
-                4 : [3]i32
-                5 : [3]i32
-                6 : ^[3]i32
-                7 : ^^[3]i32
-                8 : ^^[3]i32
-                9 : usize
-                10 : i32
-                11 : {uint}
-                12 : void
-                13 : () -> void
-                l0 : [3]i32
-                l1 : ^^[3]i32
+                main::foo : () -> i32
+                1 : bool
+                2 : {uint}
+                3 : {uint}
+                4 : {uint}
+                5 : <unknown>
+                6 : <unknown>
+                7 : () -> i32
             "#]],
             |_| {
                 [(
-                    TyDiagnosticKind::CannotMutate,
-                    128..140,
-                    Some((TyDiagnosticHelpKind::ImmutableRef, 100..105)),
-                )]
-            },
-        );
-    }
-
-    #[test]
-    fn entry_point_void() {
-        check_impl(
-            r#"
-                start :: () {};
-            "#,
-            expect![[r#"
-                main::start : () -> void
-                0 : void
-                1 : () -> void
-            "#]],
-            |_| [],
-            Some("start"),
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::Bool.into()),
+                        found: Ty::UInt(0).into(),
+                    },
+                    76..200,
+                    Some((
+                        TyDiagnosticHelpKind::BreakHere {
+                            break_ty: Ty::Bool.into(),
+                        },
+                        102..118,
+                    )),
+                )]
+            },
         )
     }
 
     #[test]
-    fn entry_point_int() {
-        check_impl(
+    fn break_inside_operands() {
+        check(
             r#"
-                entry :: () -> i16 { 0 };
+                bar :: (x: i32, y: i32) {};
+
+                foo :: (a: i32, b: bool) -> i32 {
+                    `blk: {
+                        b && { break `blk a; };
+                        a + { break `blk a; };
+                        bar(a, { break `blk a; });
+                        a
+                    }
+                }
             "#,
             expect![[r#"
-                main::entry : () -> i16
-                1 : i16
-                2 : i16
-                3 : () -> i16
+                main::bar : (i32, i32) -> void
+                main::foo : (i32, bool) -> i32
+                2 : void
+                3 : (i32, i32) -> void
+                7 : bool
+                8 : i32
+                9 : noeval
+                10 : bool
+                11 : i32
+                12 : i32
+                13 : noeval
+                14 : noeval
+                15 : (i32, i32) -> void
+                16 : i32
+                17 : i32
+                18 : noeval
+                19 : noeval
+                20 : i32
+                21 : i32
+                22 : i32
+                23 : (i32, bool) -> i32
             "#]],
             |_| [],
-            Some("entry"),
         )
     }
 
     #[test]
-    fn entry_point_uint() {
-        check_impl(
+    fn break_many_mismatches() {
+        check(
             r#"
-                main :: () -> usize { 0 };
+                foo :: () {
+                    `blk: {
+                        break `blk 1;
+                        break `blk true;
+                        break `blk "hello";
+                    }
+                }
             "#,
             expect![[r#"
-                main::main : () -> usize
-                1 : usize
-                2 : usize
-                3 : () -> usize
+                main::foo : () -> void
+                0 : {uint}
+                1 : bool
+                2 : str
+                3 : <unknown>
+                4 : <unknown>
+                5 : () -> void
             "#]],
-            |_| [],
-            Some("main"),
+            |_| {
+                [(
+                    TyDiagnosticKind::BreakMismatch {
+                        breaks: vec![
+                            (TextRange::new(81.into(), 94.into()), Ty::UInt(0).into()),
+                            (TextRange::new(119.into(), 135.into()), Ty::Bool.into()),
+                            (TextRange::new(160.into(), 179.into()), Ty::String.into()),
+                        ],
+                    },
+                    49..201,
+                    None,
+                )]
+            },
         )
     }
 
     #[test]
-    fn entry_point_non_function() {
-        check_impl(
+    fn break_unknown_label() {
+        check(
             r#"
-                main :: 5;
+                foo :: () {
+                    break `blk 42;
+                }
             "#,
             expect![[r#"
-                main::main : i32
-                0 : i32
+                main::foo : () -> void
+                0 : {uint}
+                1 : noeval
+                2 : () -> void
             "#]],
-            |_| [(TyDiagnosticKind::EntryNotFunction, 17..27, None)],
-            Some("main"),
+            |_| [],
         )
     }
 
     #[test]
-    fn entry_point_bad_params_and_return() {
-        check_impl(
+    fn return_match() {
+        check(
             r#"
-                foo :: (x: i32, y: bool) -> str {
-                    "Hello!"
+                foo :: () -> i32 {
+                    return 100;
+                    42
                 }
             "#,
             expect![[r#"
-                main::foo : (i32, bool) -> str
-                3 : str
-                4 : str
-                5 : (i32, bool) -> str
+                main::foo : () -> i32
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : () -> i32
             "#]],
-            |_| {
-                [
-                    (TyDiagnosticKind::EntryHasParams, 24..41, None),
-                    (TyDiagnosticKind::EntryBadReturn, 45..48, None),
-                ]
-            },
-            Some("foo"),
+            |_| [],
         )
     }
 
     #[test]
-    fn array_of_local_ty() {
+    fn return_mismatch() {
         check(
             r#"
-                main :: () -> i32 {
-                    int :: i32;
-                    imaginary :: distinct int;
-                    imaginary_vec3 :: distinct [3] imaginary;
-
-                    arr : imaginary_vec3 = imaginary.[1, 2, 3];
-
-                    i32.(arr[0])
+                foo :: () -> i32 {
+                    return "hello";
+                    42
                 }
             "#,
             expect![[r#"
-                main::main : () -> i32
-                1 : type
-                3 : type
-                4 : usize
-                7 : type
-                10 : {uint}
-                11 : {uint}
-                12 : {uint}
-                13 : [3]distinct'0 i32
-                14 : distinct'1 [3]distinct'0 i32
-                15 : usize
-                16 : distinct'0 i32
-                18 : i32
-                19 : i32
-                20 : () -> i32
-                l0 : type
-                l1 : type
-                l2 : type
-                l3 : distinct'1 [3]distinct'0 i32
+                main::foo : () -> i32
+                1 : str
+                2 : {uint}
+                3 : <unknown>
+                4 : () -> i32
             "#]],
-            |_| [],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Concrete(Ty::String.into()),
+                        found: Ty::UInt(0).into(),
+                    },
+                    92..94,
+                    Some((
+                        TyDiagnosticHelpKind::BreakHere {
+                            break_ty: Ty::String.into(),
+                        },
+                        56..71,
+                    )),
+                )]
+            },
         )
     }
 
     #[test]
-    fn struct_of_local_tys() {
+    fn return_only() {
         check(
             r#"
-                main :: () -> i32 {
-                    int :: i32;
-                    imaginary :: distinct int;
-                    complex :: struct {
-                        real_part: int,
-                        imaginary_part: imaginary,
-                    };
-
-                    my_complex := complex.{
-                        real_part = 5,
-                        imaginary_part = 42,
-                    };
-
-                    i32.(my_complex.real_part) + i32.(my_complex.imaginary_part)
+                foo :: () -> i32 {
+                    return 42;
                 }
             "#,
             expect![[r#"
-                main::main : () -> i32
-                1 : type
-                3 : type
-                6 : type
-                8 : i32
-                9 : distinct'0 i32
-                10 : struct'1 {real_part: i32, imaginary_part: distinct'0 i32}
-                11 : struct'1 {real_part: i32, imaginary_part: distinct'0 i32}
-                12 : i32
-                14 : i32
-                15 : struct'1 {real_part: i32, imaginary_part: distinct'0 i32}
-                16 : distinct'0 i32
-                18 : i32
-                19 : i32
-                20 : i32
-                21 : () -> i32
-                l0 : type
-                l1 : type
-                l2 : type
-                l3 : struct'1 {real_part: i32, imaginary_part: distinct'0 i32}
+                main::foo : () -> i32
+                1 : i32
+                2 : i32
+                3 : () -> i32
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn lambda_with_local_ty() {
+    fn return_with_globals() {
         check(
             r#"
-                main :: () -> i32 {
-                    int :: i32;
-                    imaginary :: distinct int;
-                    imaginary_vec3 :: distinct [3] imaginary;
-                    complex :: struct {
-                        real_part: int,
-                        imaginary_part: imaginary,
-                    };
-                
-                    my_complex := complex.{
-                        real_part = 5,
-                        imaginary_part = 42,
-                    };
-                
-                    do_math :: (c: complex) -> imaginary_vec3 {
-                        // this is kind of akward because while we can access locals
-                        // in the parameters and return type, we can't access `imaginary`
-                        // from inside the body of this lambda
-                        // this could be alleviated by adding a `type_of` builtin
-                        i32.[1, c.real_part * i32.(c.imaginary_part), 3]
-                    };
-                
-                    i32.(do_math(my_complex)[1])
+                a :: 42;
+                b :: comptime a;
+                c :: comptime b;
+                d :: comptime c;
+
+                foo :: () -> i32 {
+                    return 42;
+                    d;
+                    return 5;
                 }
             "#,
             expect![[r#"
-                main::main : () -> i32
-                1 : type
-                3 : type
-                4 : usize
-                7 : type
-                10 : type
-                12 : i32
-                13 : distinct'0 i32
-                14 : struct'2 {real_part: i32, imaginary_part: distinct'0 i32}
-                18 : i32
-                19 : struct'2 {real_part: i32, imaginary_part: distinct'0 i32}
-                20 : i32
-                21 : struct'2 {real_part: i32, imaginary_part: distinct'0 i32}
-                22 : distinct'0 i32
-                24 : i32
-                25 : i32
-                26 : i32
-                27 : [3]i32
-                28 : [3]i32
-                29 : (struct'2 {real_part: i32, imaginary_part: distinct'0 i32}) -> distinct'1 [3]distinct'0 i32
-                30 : (struct'2 {real_part: i32, imaginary_part: distinct'0 i32}) -> distinct'1 [3]distinct'0 i32
-                31 : struct'2 {real_part: i32, imaginary_part: distinct'0 i32}
-                32 : distinct'1 [3]distinct'0 i32
-                33 : usize
-                34 : distinct'0 i32
-                36 : i32
-                37 : i32
-                38 : () -> i32
-                l0 : type
-                l1 : type
-                l2 : type
-                l3 : type
-                l4 : struct'2 {real_part: i32, imaginary_part: distinct'0 i32}
-                l5 : (struct'2 {real_part: i32, imaginary_part: distinct'0 i32}) -> distinct'1 [3]distinct'0 i32
+                main::a : i32
+                main::b : i32
+                main::c : i32
+                main::d : i32
+                main::foo : () -> i32
+                0 : i32
+                1 : i32
+                2 : i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : i32
+                8 : i32
+                9 : i32
+                10 : i32
+                11 : i32
+                12 : () -> i32
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_void_block_no_tail_match() {
+    fn break_from_loop() {
         check(
             r#"
                 foo :: () {
-                    {
-                        break;
-                        break {};
-                        break {};
-                    }               
+                    `my_loop: loop {
+                        break `my_loop;
+                    }
                 }
             "#,
             expect![[r#"
                 main::foo : () -> void
-                0 : void
+                0 : noeval
                 1 : void
                 2 : void
-                3 : void
-                4 : () -> void
+                3 : () -> void
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_i32_block_no_tail_mismatch() {
+    fn break_from_loop_with_value() {
         check(
             r#"
-                foo :: () {
-                    {
-                        break 123;
-                        break {};
-                        break true;
-                    }               
+                foo :: () -> i32 {
+                    `my_loop: loop {
+                        break `my_loop 42;
+                    }
                 }
             "#,
             expect![[r#"
-                main::foo : () -> void
-                0 : {uint}
-                1 : void
-                2 : bool
-                3 : <unknown>
-                4 : <unknown>
-                5 : () -> void
+                main::foo : () -> i32
+                1 : i32
+                2 : noeval
+                3 : i32
+                4 : i32
+                5 : () -> i32
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::UInt(0).into()),
-                        found: Ty::Void.into(),
-                    },
-                    116..118,
-                    Some((
-                        TyDiagnosticHelpKind::BreakHere {
-                            break_ty: Ty::UInt(0).into(),
-                        },
-                        75..85,
-                    )),
-                )]
-            },
+            |_| [],
         )
     }
 
     #[test]
-    fn paren_infer() {
+    fn break_from_loop_with_multiple_values() {
         check(
             r#"
-                foo :: () -> u16 {
-                    (42 * (11 / 2))
+                foo :: () {
+                    loop {
+                        x : i16 = 42;
+                        break x;
+
+                        break 15;
+
+                        x : i32 = 23;
+                        break x;
+                    };
                 }
             "#,
             expect![[r#"
-                main::foo : () -> u16
-                1 : u16
-                2 : u16
-                3 : u16
-                4 : u16
-                5 : u16
-                6 : u16
-                7 : u16
-                8 : u16
-                9 : () -> u16
+                main::foo : () -> void
+                1 : i16
+                2 : i16
+                3 : i16
+                5 : i32
+                6 : i32
+                7 : noeval
+                8 : i32
+                9 : void
+                10 : () -> void
+                l0 : i16
+                l1 : i32
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn paren_spread() {
+    fn break_from_while() {
         check(
             r#"
                 foo :: () {
-                    x : i8 = 42;
-                    (42 * ((2 >> x) / 2));
+                    `my_loop: while 2 + 2 == 4 {
+                        break `my_loop;
+                    }
                 }
             "#,
             expect![[r#"
                 main::foo : () -> void
-                1 : i8
-                2 : i8
-                3 : i8
-                4 : i8
-                5 : i8
-                6 : i8
-                7 : i8
-                8 : i8
-                9 : i8
-                10 : i8
-                11 : i8
-                12 : void
-                13 : () -> void
-                l0 : i8
+                0 : {uint}
+                1 : {uint}
+                2 : {uint}
+                3 : {uint}
+                4 : bool
+                5 : noeval
+                6 : void
+                7 : void
+                8 : () -> void
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_i32_block_tail_match() {
+    fn break_from_while_with_void() {
         check(
             r#"
-                foo :: () -> i32 {
-                    {
-                        break 123;
-                        42
-                    }               
+                foo :: () {
+                    `my_loop: while 2 + 2 == 4 {
+                        break `my_loop {};
+                    }
                 }
             "#,
             expect![[r#"
-                main::foo : () -> i32
-                1 : i32
-                2 : i32
-                3 : i32
-                4 : i32
-                5 : () -> i32
+                main::foo : () -> void
+                0 : {uint}
+                1 : {uint}
+                2 : {uint}
+                3 : {uint}
+                4 : bool
+                5 : void
+                6 : noeval
+                7 : void
+                8 : void
+                9 : () -> void
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_void_block_tail_mismatch() {
+    fn break_from_while_with_value() {
         check(
             r#"
                 foo :: () {
-                    {
-                        break {};
-                        42
+                    `my_loop: while 2 + 2 == 4 {
+                        break `my_loop 42;
                     }
                 }
             "#,
             expect![[r#"
                 main::foo : () -> void
-                0 : void
+                0 : {uint}
                 1 : {uint}
-                2 : <unknown>
-                3 : <unknown>
-                4 : () -> void
+                2 : {uint}
+                3 : {uint}
+                4 : bool
+                5 : {uint}
+                6 : noeval
+                7 : <unknown>
+                8 : <unknown>
+                9 : () -> void
             "#]],
             |_| {
                 [(
@@ -8350,418 +12892,521 @@ mod tests {
                         expected: ExpectedTy::Concrete(Ty::Void.into()),
                         found: Ty::UInt(0).into(),
                     },
-                    109..111,
-                    Some((
-                        TyDiagnosticHelpKind::BreakHere {
-                            break_ty: Ty::Void.into(),
-                        },
-                        75..84,
-                    )),
+                    117..119,
+                    None,
                 )]
             },
         )
     }
 
     #[test]
-    fn break_i32_block_from_inner() {
+    fn continue_works() {
         check(
             r#"
-                foo :: () {
-                    `blk: {
-                        {
-                            break `blk {};
-                        }
-
-                        42
-                    }               
+                foo :: () -> i32 {
+                    loop {
+                        continue;
+                    }
+                    42
                 }
             "#,
             expect![[r#"
-                main::foo : () -> void
-                0 : void
+                main::foo : () -> i32
                 1 : noeval
-                2 : {uint}
-                3 : <unknown>
-                4 : <unknown>
-                5 : () -> void
+                2 : noeval
+                3 : i32
+                4 : i32
+                5 : () -> i32
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::Void.into()),
-                        found: Ty::UInt(0).into(),
-                    },
-                    177..179,
-                    Some((
-                        TyDiagnosticHelpKind::BreakHere {
-                            break_ty: Ty::Void.into(),
-                        },
-                        111..125,
-                    )),
-                )]
-            },
+            |_| [],
         )
     }
 
     #[test]
-    fn break_i32_block_from_inner_tail() {
+    fn loop_without_break() {
         check(
             r#"
-            foo :: () -> i32 {
-                `blk: {
-                    {
-                        break `blk true;
-                        break 5;
-                        42
-                    }
+                foo :: () -> i32 {
+                    loop {}
                 }
-            }
             "#,
             expect![[r#"
                 main::foo : () -> i32
-                1 : bool
-                2 : {uint}
-                3 : {uint}
-                4 : {uint}
-                5 : <unknown>
-                6 : <unknown>
-                7 : () -> i32
+                1 : void
+                2 : noeval
+                3 : noeval
+                4 : () -> i32
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn for_over_array() {
+        check(
+            r#"
+                foo :: (array: [3]i32) {
+                    for x in array {
+                        y := x;
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo : ([3]i32) -> void
+                0 : usize
+                3 : [3]i32
+                4 : i32
+                5 : void
+                6 : void
+                7 : void
+                8 : ([3]i32) -> void
+                l0 : i32
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::Bool.into()),
-                        found: Ty::UInt(0).into(),
-                    },
-                    76..200,
-                    Some((
-                        TyDiagnosticHelpKind::BreakHere {
-                            break_ty: Ty::Bool.into(),
-                        },
-                        102..118,
-                    )),
-                )]
-            },
+            |_| [],
         )
     }
 
     #[test]
-    fn break_unknown_label() {
+    fn for_over_non_iterable() {
         check(
             r#"
                 foo :: () {
-                    break `blk 42;
+                    for x in 5 {
+                        y := x;
+                    }
                 }
             "#,
             expect![[r#"
                 main::foo : () -> void
                 0 : {uint}
-                1 : noeval
-                2 : () -> void
+                1 : <unknown>
+                2 : void
+                3 : void
+                4 : void
+                5 : () -> void
+                l0 : <unknown>
             "#]],
-            |_| [],
+            |_| {
+                [(
+                    TyDiagnosticKind::NotIterable {
+                        found: Ty::UInt(0).into(),
+                    },
+                    58..59,
+                    None,
+                )]
+            },
         )
     }
 
     #[test]
-    fn return_match() {
+    fn for_over_range() {
         check(
             r#"
-                foo :: () -> i32 {
-                    return 100;
-                    42
+                foo :: (n: i64) {
+                    for i in 0..n {
+                        x := i;
+                    }
+                    for j in 1..=10 {
+                        y := j;
+                    }
                 }
             "#,
             expect![[r#"
-                main::foo : () -> i32
-                1 : i32
-                2 : i32
-                3 : i32
-                4 : () -> i32
+                main::foo : (i64) -> void
+                1 : i64
+                2 : i64
+                3 : i64..i64
+                4 : i64
+                5 : void
+                6 : void
+                7 : i32
+                8 : i32
+                9 : i32..=i32
+                10 : i32
+                11 : void
+                12 : void
+                13 : void
+                14 : (i64) -> void
+                l0 : i64
+                l1 : i32
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn return_mismatch() {
+    fn range_of_floats() {
         check(
             r#"
-                foo :: () -> i32 {
-                    return "hello";
-                    42
+                foo :: () {
+                    r := 0.5..2.0;
                 }
             "#,
             expect![[r#"
-                main::foo : () -> i32
-                1 : str
-                2 : {uint}
-                3 : <unknown>
-                4 : () -> i32
+                main::foo : () -> void
+                0 : {float}
+                1 : {float}
+                2 : <unknown>
+                3 : void
+                4 : () -> void
+                l0 : <unknown>
             "#]],
             |_| {
                 [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::String.into()),
-                        found: Ty::UInt(0).into(),
+                    TyDiagnosticKind::RangeBoundNotInt {
+                        found: Ty::Float(0).into(),
                     },
-                    92..94,
-                    Some((
-                        TyDiagnosticHelpKind::BreakHere {
-                            break_ty: Ty::String.into(),
-                        },
-                        56..71,
-                    )),
+                    54..62,
+                    None,
                 )]
             },
         )
     }
 
     #[test]
-    fn return_only() {
+    fn index_with_distinct_int() {
         check(
             r#"
-                foo :: () -> i32 {
-                    return 42;
+                Id :: distinct u32;
+
+                foo :: (names: []str, id: Id) -> str {
+                    names[id]
                 }
             "#,
             expect![[r#"
-                main::foo : () -> i32
-                1 : i32
-                2 : i32
-                3 : () -> i32
+                main::Id : type
+                main::foo : ([]str, main::Id) -> str
+                1 : type
+                6 : []str
+                7 : main::Id
+                8 : str
+                9 : str
+                10 : ([]str, main::Id) -> str
             "#]],
             |_| [],
-        )
+        );
     }
 
     #[test]
-    fn return_with_globals() {
+    fn slice_with_range() {
         check(
             r#"
-                a :: 42;
-                b :: comptime a;
-                c :: comptime b;
-                d :: comptime c;
-
-                foo :: () -> i32 {
-                    return 42;
-                    d;
-                    return 5;
+                foo :: (array: [5]i32, slice: []u8) {
+                    a := array[1..3];
+                    b := array[..=2];
+                    c := slice[2..];
+                    d := slice[..];
                 }
             "#,
             expect![[r#"
-                main::a : i32
-                main::b : i32
-                main::c : i32
-                main::d : i32
-                main::foo : () -> i32
-                0 : i32
-                1 : i32
-                2 : i32
-                3 : i32
-                4 : i32
-                5 : i32
-                6 : i32
-                8 : i32
-                9 : i32
-                10 : i32
-                11 : i32
-                12 : () -> i32
+                main::foo : ([5]i32, []u8) -> void
+                0 : usize
+                5 : [5]i32
+                6 : usize
+                7 : usize
+                8 : usize..usize
+                9 : []i32
+                10 : [5]i32
+                11 : usize
+                12 : usize..=usize
+                13 : []i32
+                14 : []u8
+                15 : usize
+                16 : usize..usize
+                17 : []u8
+                18 : []u8
+                19 : usize..usize
+                20 : []u8
+                21 : void
+                22 : ([5]i32, []u8) -> void
+                l0 : []i32
+                l1 : []i32
+                l2 : []u8
+                l3 : []u8
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_from_loop() {
+    fn tuples() {
         check(
             r#"
+                Pair :: (i32, str);
+
+                swap :: (pair: Pair) -> (str, i32) {
+                    (pair.1, pair.0)
+                }
+
                 foo :: () {
-                    `my_loop: loop {
-                        break `my_loop;
-                    }
+                    single := (1,);
+                    nested := (1.5, (true, 'a'));
+                    c := nested.1.1;
+                    p : Pair = (5, "hello");
                 }
             "#,
             expect![[r#"
+                main::Pair : type
                 main::foo : () -> void
-                0 : noeval
-                1 : void
-                2 : void
-                3 : () -> void
+                main::swap : ((i32, str)) -> (str, i32)
+                0 : type
+                1 : type
+                2 : type
+                7 : (i32, str)
+                8 : str
+                9 : (i32, str)
+                10 : i32
+                11 : (str, i32)
+                12 : (str, i32)
+                13 : ((i32, str)) -> (str, i32)
+                14 : {uint}
+                15 : ({uint},)
+                16 : {float}
+                17 : bool
+                18 : char
+                19 : (bool, char)
+                20 : ({float}, (bool, char))
+                21 : ({float}, (bool, char))
+                22 : (bool, char)
+                23 : char
+                25 : i32
+                26 : str
+                27 : (i32, str)
+                28 : void
+                29 : () -> void
+                l0 : ({uint},)
+                l1 : ({float}, (bool, char))
+                l2 : char
+                l3 : (i32, str)
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_from_loop_with_value() {
+    fn tuple_member_out_of_range() {
         check(
             r#"
-                foo :: () -> i32 {
-                    `my_loop: loop {
-                        break `my_loop 42;
-                    }
+                foo :: () {
+                    t := (1, 2);
+                    x := t.2;
                 }
             "#,
             expect![[r#"
-                main::foo : () -> i32
-                1 : i32
-                2 : noeval
-                3 : i32
-                4 : i32
-                5 : () -> i32
+                main::foo : () -> void
+                0 : {uint}
+                1 : {uint}
+                2 : ({uint}, {uint})
+                3 : ({uint}, {uint})
+                4 : <unknown>
+                5 : void
+                6 : () -> void
+                l0 : ({uint}, {uint})
+                l1 : <unknown>
             "#]],
-            |_| [],
+            |i| {
+                [(
+                    TyDiagnosticKind::NonExistentMember {
+                        member: i.intern("2"),
+                        found_ty: Ty::Tuple {
+                            items: vec![Ty::UInt(0).into(), Ty::UInt(0).into()],
+                        }
+                        .into(),
+                    },
+                    87..90,
+                    None,
+                )]
+            },
         )
     }
 
     #[test]
-    fn break_from_loop_with_multiple_values() {
+    fn optionals() {
         check(
             r#"
-                foo :: () {
-                    loop {
-                        x : i16 = 42;
-                        break x;
-
-                        break 15;
+                find :: (haystack: []i32, needle: i32) -> ?usize {
+                    idx := 0;
+                    while idx < haystack.len {
+                        if haystack[idx] == needle {
+                            return idx;
+                        }
+                        idx += 1;
+                    }
+                    null
+                }
 
-                        x : i32 = 23;
-                        break x;
-                    };
+                foo :: (nums: []i32) -> usize {
+                    a := find(nums, 5) orelse 0;
+                    b := find(nums, 6) orelse find(nums, 7) orelse 1;
+                    c := #unwrap(find(nums, 8));
+                    a + b + c
                 }
             "#,
             expect![[r#"
-                main::foo : () -> void
-                1 : i16
-                2 : i16
-                3 : i16
-                5 : i32
-                6 : i32
-                7 : noeval
-                8 : i32
-                9 : void
-                10 : () -> void
-                l0 : i16
-                l1 : i32
+                main::find : ([]i32, i32) -> ?usize
+                main::foo : ([]i32) -> usize
+                5 : usize
+                6 : usize
+                7 : []i32
+                8 : usize
+                9 : bool
+                10 : []i32
+                11 : usize
+                12 : i32
+                13 : i32
+                14 : bool
+                15 : usize
+                16 : noeval
+                17 : void
+                18 : usize
+                19 : usize
+                20 : void
+                21 : void
+                22 : ?usize
+                23 : ?usize
+                24 : ([]i32, i32) -> ?usize
+                28 : ([]i32, i32) -> ?usize
+                29 : []i32
+                30 : i32
+                31 : ?usize
+                32 : usize
+                33 : usize
+                34 : ([]i32, i32) -> ?usize
+                35 : []i32
+                36 : i32
+                37 : ?usize
+                38 : ([]i32, i32) -> ?usize
+                39 : []i32
+                40 : i32
+                41 : ?usize
+                42 : ?usize
+                43 : usize
+                44 : usize
+                45 : ([]i32, i32) -> ?usize
+                46 : []i32
+                47 : i32
+                48 : ?usize
+                49 : usize
+                50 : usize
+                51 : usize
+                52 : usize
+                53 : usize
+                54 : usize
+                55 : usize
+                56 : ([]i32) -> usize
+                l0 : usize
+                l1 : usize
+                l2 : usize
+                l3 : usize
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_from_while() {
+    fn optional_payload_without_unwrapping() {
         check(
             r#"
-                foo :: () {
-                    `my_loop: while 2 + 2 == 4 {
-                        break `my_loop;
-                    }
+                foo :: (x: ?i32) -> i32 {
+                    y : i32 = x;
+                    x + 1
                 }
             "#,
-            expect![[r#"
-                main::foo : () -> void
-                0 : {uint}
-                1 : {uint}
-                2 : {uint}
-                3 : {uint}
-                4 : bool
-                5 : noeval
-                6 : void
-                7 : void
-                8 : () -> void
+            expect![[r#"
+                main::foo : (?i32) -> i32
+                4 : ?i32
+                5 : ?i32
+                6 : i32
+                7 : i32
+                8 : i32
+                9 : (?i32) -> i32
+                l0 : i32
             "#]],
-            |_| [],
+            |_| {
+                let optional = Intern::new(Ty::Optional {
+                    sub_ty: Ty::IInt(32).into(),
+                });
+
+                [
+                    (
+                        TyDiagnosticKind::Mismatch {
+                            expected: ExpectedTy::Concrete(Ty::IInt(32).into()),
+                            found: optional,
+                        },
+                        73..74,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::BinaryOpMismatch {
+                            op: hir::BinaryOp::Add,
+                            first: optional,
+                            second: Ty::UInt(0).into(),
+                        },
+                        96..101,
+                        None,
+                    ),
+                ]
+            },
         )
     }
 
     #[test]
-    fn break_from_while_with_void() {
+    fn optional_early_return_with_tail() {
+        // reinference only sees the `i32` tail, not the `?i32` that the function expected it to be
         check(
             r#"
-                foo :: () {
-                    `my_loop: while 2 + 2 == 4 {
-                        break `my_loop {};
+                foo :: (n: i32) -> ?i32 {
+                    if n > 0 {
+                        return 7;
                     }
+                    n
                 }
             "#,
             expect![[r#"
-                main::foo : () -> void
-                0 : {uint}
-                1 : {uint}
-                2 : {uint}
-                3 : {uint}
-                4 : bool
-                5 : void
-                6 : noeval
-                7 : void
+                main::foo : (i32) -> ?i32
+                3 : i32
+                4 : i32
+                5 : bool
+                6 : i32
+                7 : noeval
                 8 : void
-                9 : () -> void
+                9 : i32
+                10 : ?i32
+                11 : (i32) -> ?i32
             "#]],
             |_| [],
         )
     }
 
     #[test]
-    fn break_from_while_with_value() {
+    fn orelse_on_non_optional() {
         check(
             r#"
-                foo :: () {
-                    `my_loop: while 2 + 2 == 4 {
-                        break `my_loop 42;
-                    }
+                foo :: (x: i32) -> i32 {
+                    x orelse 0
                 }
             "#,
             expect![[r#"
-                main::foo : () -> void
-                0 : {uint}
-                1 : {uint}
-                2 : {uint}
+                main::foo : (i32) -> i32
+                2 : i32
                 3 : {uint}
-                4 : bool
-                5 : {uint}
-                6 : noeval
-                7 : <unknown>
-                8 : <unknown>
-                9 : () -> void
+                4 : <unknown>
+                5 : <unknown>
+                6 : (i32) -> i32
             "#]],
             |_| {
                 [(
                     TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Concrete(Ty::Void.into()),
-                        found: Ty::UInt(0).into(),
+                        expected: ExpectedTy::Optional,
+                        found: Ty::IInt(32).into(),
                     },
-                    117..119,
+                    62..63,
                     None,
                 )]
             },
         )
     }
 
-    #[test]
-    fn continue_works() {
-        check(
-            r#"
-                foo :: () -> i32 {
-                    loop {
-                        continue;
-                    }
-                    42
-                }
-            "#,
-            expect![[r#"
-                main::foo : () -> i32
-                1 : noeval
-                2 : void
-                3 : i32
-                4 : i32
-                5 : () -> i32
-            "#]],
-            |_| [],
-        )
-    }
-
     #[test]
     fn break_inner_if_no_else() {
         check(
@@ -9455,7 +14100,7 @@ mod tests {
                 1 : usize
                 14 : void
                 15 : () -> void
-                l0 : distinct'3 distinct'2 struct'1 {a: [2][4]u8, b: i16, c: distinct'0 f32, d: bool, e: char, f: void}
+                l0 : distinct'4810829830013056716 distinct'16338545178027340637 struct'9419516452332072942 {a: [2][4]u8, b: i16, c: distinct'2500487726636805247 f32, d: bool, e: char, f: void}
             "#]],
             |_| [],
         )
@@ -9503,14 +14148,14 @@ mod tests {
                 main::defaults : () -> void
                 3 : void
                 4 : () -> void
-                l0 : distinct'0 []bool
+                l0 : distinct'2500487726636805247 []bool
             "#]],
             |_| {
                 [(
                     TyDiagnosticKind::DeclTypeHasNoDefault {
                         ty: Ty::Distinct {
                             fqn: None,
-                            uid: 0,
+                            uid: hir::type_uid("main.capy", "defaults", 0),
                             sub_ty: Ty::Slice {
                                 sub_ty: Ty::Bool.into(),
                             }
@@ -9540,18 +14185,18 @@ mod tests {
                 main::defaults : () -> void
                 4 : void
                 5 : () -> void
-                l0 : distinct'1 struct'0 {foo: str, bar: u8}
+                l0 : distinct'9419516452332072942 struct'2500487726636805247 {foo: str, bar: u8}
             "#]],
             |i| {
                 [(
                     TyDiagnosticKind::DeclTypeHasNoDefault {
                         ty: Ty::Distinct {
                             fqn: None,
-                            uid: 1,
+                            uid: hir::type_uid("main.capy", "defaults", 1),
                             sub_ty: Ty::Struct {
                                 anonymous: false,
                                 fqn: None,
-                                uid: 0,
+                                uid: hir::type_uid("main.capy", "defaults", 0),
                                 members: vec![
                                     MemberTy {
                                         name: hir::Name(i.intern("foo")),
@@ -9869,7 +14514,7 @@ mod tests {
                                     file: hir::FileName(i.intern("main.capy")),
                                     name: hir::Name(i.intern("Foo_Type")),
                                 }),
-                                uid: 0,
+                                uid: hir::type_uid("main.capy", "Foo_Type", 0),
                                 members: vec![
                                     MemberTy {
                                         name: hir::Name(i.intern("a")),
@@ -9953,7 +14598,7 @@ mod tests {
                                     file: hir::FileName(i.intern("main.capy")),
                                     name: hir::Name(i.intern("Foo_Type")),
                                 }),
-                                uid: 0,
+                                uid: hir::type_uid("main.capy", "Foo_Type", 0),
                                 members: vec![
                                     MemberTy {
                                         name: hir::Name(i.intern("a")),
@@ -10043,7 +14688,7 @@ mod tests {
                                     file: hir::FileName(i.intern("main.capy")),
                                     name: hir::Name(i.intern("Foo_Type")),
                                 }),
-                                uid: 0,
+                                uid: hir::type_uid("main.capy", "Foo_Type", 0),
                                 members: vec![
                                     MemberTy {
                                         name: hir::Name(i.intern("a")),
@@ -10994,7 +15639,360 @@ mod tests {
                 l0 : main::Web_Event
             "#]],
             |_| [],
-        )
+        )
+    }
+
+    #[test]
+    fn switch_through_pointers() {
+        check(
+            r#"
+                Foo :: enum {
+                    A: i32,
+                    B,
+                };
+
+                foo :: (f: ^^Foo) {
+                    switch e in f {
+                        A => {
+                            e;
+                        },
+                        B => {
+                            e;
+                        },
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::Foo : type
+                main::foo : (^^main::Foo) -> void
+                1 : type
+                5 : ^^main::Foo
+                6 : main::Foo.A
+                7 : void
+                8 : main::Foo.B
+                9 : void
+                10 : void
+                11 : void
+                12 : (^^main::Foo) -> void
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn construct_variant_with_call() {
+        check(
+            r#"
+                Shape :: enum {
+                    Circle: f32,
+                    Nothing,
+                };
+
+                foo :: () {
+                    a := Shape.Circle(5.0);
+                    b := Shape.Nothing();
+                }
+            "#,
+            expect![[r#"
+                main::Shape : type
+                main::foo : () -> void
+                1 : type
+                2 : type
+                3 : type
+                4 : f32
+                5 : main::Shape.Circle
+                6 : type
+                7 : type
+                8 : main::Shape.Nothing
+                9 : void
+                10 : () -> void
+                l0 : main::Shape.Circle
+                l1 : main::Shape.Nothing
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn construct_variant_with_call_bad_payload() {
+        check(
+            r#"
+                Shape :: enum {
+                    Circle: f32,
+                    Nothing,
+                };
+
+                foo :: () {
+                    a := Shape.Circle(true);
+                    b := Shape.Circle();
+                    c := Shape.Nothing(1);
+                }
+            "#,
+            expect![[r#"
+                main::Shape : type
+                main::foo : () -> void
+                1 : type
+                2 : type
+                3 : type
+                4 : bool
+                5 : main::Shape.Circle
+                6 : type
+                7 : type
+                8 : main::Shape.Circle
+                9 : type
+                10 : type
+                11 : {uint}
+                12 : main::Shape.Nothing
+                13 : void
+                14 : () -> void
+                l0 : main::Shape.Circle
+                l1 : main::Shape.Circle
+                l2 : main::Shape.Nothing
+            "#]],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::Mismatch {
+                            expected: ExpectedTy::Concrete(Ty::Float(32).into()),
+                            found: Ty::Bool.into(),
+                        },
+                        181..185,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::MissingArg {
+                            expected: ExpectedTy::Concrete(Ty::Float(32).into()),
+                        },
+                        226..226,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::ExtraArg {
+                            found: Ty::UInt(0).into(),
+                        },
+                        268..269,
+                        None,
+                    ),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn enum_variant_coercion() {
+        check(
+            r#"
+                Shape :: enum {
+                    Circle: f32,
+                    Nothing,
+                };
+
+                Animal :: enum {
+                    Dog,
+                    Cat,
+                };
+
+                foo :: () {
+                    shape : Shape = Shape.Circle(1.0);
+                    circle : Shape.Circle = shape;
+                    animal : Animal = Shape.Nothing();
+                }
+            "#,
+            expect![[r#"
+                main::Animal : type
+                main::Shape : type
+                main::foo : () -> void
+                1 : type
+                2 : type
+                4 : type
+                5 : type
+                6 : f32
+                7 : main::Shape.Circle
+                8 : type
+                10 : main::Shape
+                12 : type
+                13 : type
+                14 : main::Shape.Nothing
+                15 : void
+                16 : () -> void
+                l0 : main::Shape
+                l1 : main::Shape.Circle
+                l2 : main::Animal
+            "#]],
+            |i| {
+                let main_file = hir::FileName(i.intern("main.capy"));
+                let shape_fqn = Some(hir::Fqn {
+                    file: main_file,
+                    name: hir::Name(i.intern("Shape")),
+                });
+                let animal_fqn = Some(hir::Fqn {
+                    file: main_file,
+                    name: hir::Name(i.intern("Animal")),
+                });
+
+                let circle: Intern<Ty> = Ty::Variant {
+                    enum_fqn: shape_fqn,
+                    enum_uid: hir::type_uid("main.capy", "Shape", 2),
+                    variant_name: hir::Name(i.intern("Circle")),
+                    uid: hir::type_uid("main.capy", "Shape", 0),
+                    sub_ty: Ty::Float(32).into(),
+                    discriminant: 0,
+                }
+                .into();
+                let nothing: Intern<Ty> = Ty::Variant {
+                    enum_fqn: shape_fqn,
+                    enum_uid: hir::type_uid("main.capy", "Shape", 2),
+                    variant_name: hir::Name(i.intern("Nothing")),
+                    uid: hir::type_uid("main.capy", "Shape", 1),
+                    sub_ty: Ty::Void.into(),
+                    discriminant: 1,
+                }
+                .into();
+                let shape: Intern<Ty> = Ty::Enum {
+                    fqn: shape_fqn,
+                    uid: hir::type_uid("main.capy", "Shape", 2),
+                    variants: vec![circle, nothing],
+                }
+                .into();
+
+                let animal: Intern<Ty> = Ty::Enum {
+                    fqn: animal_fqn,
+                    uid: hir::type_uid("main.capy", "Animal", 2),
+                    variants: vec![
+                        Ty::Variant {
+                            enum_fqn: animal_fqn,
+                            enum_uid: hir::type_uid("main.capy", "Animal", 2),
+                            variant_name: hir::Name(i.intern("Dog")),
+                            uid: hir::type_uid("main.capy", "Animal", 0),
+                            sub_ty: Ty::Void.into(),
+                            discriminant: 0,
+                        }
+                        .into(),
+                        Ty::Variant {
+                            enum_fqn: animal_fqn,
+                            enum_uid: hir::type_uid("main.capy", "Animal", 2),
+                            variant_name: hir::Name(i.intern("Cat")),
+                            uid: hir::type_uid("main.capy", "Animal", 1),
+                            sub_ty: Ty::Void.into(),
+                            discriminant: 1,
+                        }
+                        .into(),
+                    ],
+                }
+                .into();
+
+                [
+                    (
+                        TyDiagnosticKind::EnumToVariant {
+                            enum_ty: shape,
+                            variant_ty: circle,
+                        },
+                        345..350,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::VariantOfOtherEnum {
+                            variant_ty: nothing,
+                            enum_ty: animal,
+                        },
+                        390..405,
+                        None,
+                    ),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn variants_only_fit_into_their_enum() {
+        let mut interner = Interner::default();
+
+        let variant: Intern<Ty> = Ty::Variant {
+            enum_fqn: None,
+            enum_uid: 0,
+            variant_name: hir::Name(interner.intern("Foo")),
+            uid: 1,
+            sub_ty: Ty::IInt(8).into(),
+            discriminant: 0,
+        }
+        .into();
+        let enum_ty = Ty::Enum {
+            fqn: None,
+            uid: 0,
+            variants: vec![variant],
+        };
+        let other_enum = Ty::Enum {
+            fqn: None,
+            uid: 2,
+            variants: vec![],
+        };
+
+        assert!(variant.can_fit_into(&enum_ty));
+        assert!(!enum_ty.can_fit_into(&variant));
+        assert!(!variant.can_fit_into(&other_enum));
+    }
+
+    #[test]
+    fn switch_distinct_enum() {
+        check(
+            r#"
+                Shape :: enum { Circle: f32, Square: u8 };
+                Tagged :: distinct Shape;
+
+                foo :: (t: Tagged) -> u8 {
+                    switch s in t {
+                        Circle => 0,
+                        Square => u8.(s),
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::Shape : type
+                main::Tagged : type
+                main::foo : (main::Tagged) -> u8
+                2 : type
+                4 : type
+                7 : main::Tagged
+                8 : u8
+                9 : main::Shape.Square
+                11 : u8
+                12 : u8
+                13 : u8
+                14 : (main::Tagged) -> u8
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn switch_non_enum() {
+        check(
+            r#"
+                foo :: (x: i32) {
+                    switch v in x {
+                        _ => {},
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::foo : (i32) -> void
+                1 : i32
+                2 : void
+                3 : <unknown>
+                4 : <unknown>
+                5 : (i32) -> void
+            "#]],
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Enum,
+                        found: Ty::IInt(32).into(),
+                    },
+                    67..68,
+                    None,
+                )]
+            },
+        );
     }
 
     #[test]
@@ -11242,9 +16240,9 @@ mod tests {
                                     file: hir::FileName(i.intern("main.capy")),
                                     name: hir::Name(i.intern("Web_Event")),
                                 }),
-                                enum_uid: 6,
+                                enum_uid: hir::type_uid("main.capy", "Web_Event", 6),
                                 variant_name: hir::Name(i.intern("Paste")),
-                                uid: 3,
+                                uid: hir::type_uid("main.capy", "Web_Event", 3),
                                 sub_ty: Ty::String.into(),
                                 discriminant: 3,
                             }
@@ -11260,13 +16258,13 @@ mod tests {
                                     file: hir::FileName(i.intern("main.capy")),
                                     name: hir::Name(i.intern("Web_Event")),
                                 }),
-                                enum_uid: 6,
+                                enum_uid: hir::type_uid("main.capy", "Web_Event", 6),
                                 variant_name: hir::Name(i.intern("Click")),
-                                uid: 5,
+                                uid: hir::type_uid("main.capy", "Web_Event", 5),
                                 sub_ty: Ty::Struct {
                                     anonymous: false,
                                     fqn: None,
-                                    uid: 4,
+                                    uid: hir::type_uid("main.capy", "Web_Event", 4),
                                     members: vec![
                                         MemberTy {
                                             name: hir::Name(i.intern("x")),
@@ -11381,9 +16379,9 @@ mod tests {
                                 file: hir::FileName(i.intern("main.capy")),
                                 name: hir::Name(i.intern("Web_Event")),
                             }),
-                            enum_uid: 6,
+                            enum_uid: hir::type_uid("main.capy", "Web_Event", 6),
                             variant_name: hir::Name(i.intern("Page_Unload")),
-                            uid: 1,
+                            uid: hir::type_uid("main.capy", "Web_Event", 1),
                             sub_ty: Ty::Void.into(),
                             discriminant: 1,
                         }
@@ -11463,6 +16461,94 @@ mod tests {
         )
     }
 
+    #[test]
+    fn switch_default_unreachable() {
+        check(
+            r#"
+                Dir :: enum {
+                    Left,
+                    Right,
+                };
+
+                foo :: (d: Dir) -> u8 {
+                    switch d {
+                        Left => 1,
+                        Right => 2,
+                        _ => 3,
+                    }
+                }
+            "#,
+            expect![[r#"
+                main::Dir : type
+                main::foo : (main::Dir) -> u8
+                0 : type
+                3 : main::Dir
+                4 : u8
+                5 : u8
+                6 : u8
+                7 : u8
+                8 : u8
+                9 : (main::Dir) -> u8
+            "#]],
+            |_| [(TyDiagnosticKind::UnreachableDefault, 270..271, None)],
+        )
+    }
+
+    #[test]
+    fn deprecated_global() {
+        check(
+            r#"
+                @deprecated("use `add` instead")
+                old_add :: (x: i32, y: i32) -> i32 { x + y }
+
+                add :: (x: i32, y: i32) -> i32 { x + y }
+
+                main :: () -> i32 {
+                    old_add(1, 2) + add(3, 4)
+                }
+            "#,
+            expect![[r#"
+                main::add : (i32, i32) -> i32
+                main::main : () -> i32
+                main::old_add : (i32, i32) -> i32
+                3 : i32
+                4 : i32
+                5 : i32
+                6 : i32
+                7 : (i32, i32) -> i32
+                11 : i32
+                12 : i32
+                13 : i32
+                14 : i32
+                15 : (i32, i32) -> i32
+                17 : (i32, i32) -> i32
+                18 : i32
+                19 : i32
+                20 : i32
+                21 : (i32, i32) -> i32
+                22 : i32
+                23 : i32
+                24 : i32
+                25 : i32
+                26 : i32
+                27 : () -> i32
+            "#]],
+            |i| {
+                [(
+                    TyDiagnosticKind::Deprecated {
+                        fqn: hir::Fqn {
+                            file: hir::FileName(i.intern("main.capy")),
+                            name: hir::Name(i.intern("old_add")),
+                        },
+                        message: Some(i.intern("use `add` instead")),
+                    },
+                    226..233,
+                    None,
+                )]
+            },
+        )
+    }
+
     #[test]
     fn switch_default_mismatch() {
         check(
@@ -11569,11 +16655,11 @@ mod tests {
                 11 : type
                 13 : i64
                 14 : i64
-                15 : .Click'5
+                15 : .Click'9131900496603400226
                 16 : void
                 17 : () -> void
                 l0 : type
-                l1 : enum '8 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 2, Paste: str | 10, Click: struct'4 {x: i64, y: i64} | 11, Unclick: struct'6 {x: i64, y: i64} | 12}
+                l1 : enum '11418247431112678271 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 2, Paste: str | 10, Click: struct'2212871770908132531 {x: i64, y: i64} | 11, Unclick: struct'16050929222298667921 {x: i64, y: i64} | 12}
             "#]],
             |_| [],
         )
@@ -11610,11 +16696,11 @@ mod tests {
                 12 : type
                 14 : i64
                 15 : i64
-                16 : .Click'5
+                16 : .Click'9131900496603400226
                 17 : void
                 18 : () -> void
                 l0 : type
-                l1 : enum '8 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 2, Paste: str | 10, Click: struct'4 {x: i64, y: i64} | 11, Unclick: struct'6 {x: i64, y: i64} | 12}
+                l1 : enum '11418247431112678271 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 2, Paste: str | 10, Click: struct'2212871770908132531 {x: i64, y: i64} | 11, Unclick: struct'16050929222298667921 {x: i64, y: i64} | 12}
             "#]],
             |_| {
                 [(
@@ -11656,11 +16742,11 @@ mod tests {
                 11 : type
                 13 : i64
                 14 : i64
-                15 : .Click'5
+                15 : .Click'9131900496603400226
                 16 : void
                 17 : () -> void
                 l0 : type
-                l1 : enum '8 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 3, Paste: str | 4, Click: struct'4 {x: i64, y: i64} | 2, Unclick: struct'6 {x: i64, y: i64} | 5}
+                l1 : enum '11418247431112678271 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 3, Paste: str | 4, Click: struct'2212871770908132531 {x: i64, y: i64} | 2, Unclick: struct'16050929222298667921 {x: i64, y: i64} | 5}
             "#]],
             |_| [],
         )
@@ -11697,11 +16783,11 @@ mod tests {
                 12 : type
                 14 : i64
                 15 : i64
-                16 : .Click'5
+                16 : .Click'9131900496603400226
                 17 : void
                 18 : () -> void
                 l0 : type
-                l1 : enum '8 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 3, Paste: str | 2, Click: struct'4 {x: i64, y: i64} | 4, Unclick: struct'6 {x: i64, y: i64} | 5}
+                l1 : enum '11418247431112678271 {Page_Load | 0, Page_Unload | 1, Key_Press: char | 3, Paste: str | 2, Click: struct'2212871770908132531 {x: i64, y: i64} | 4, Unclick: struct'16050929222298667921 {x: i64, y: i64} | 5}
             "#]],
             |_| {
                 [(
@@ -12096,76 +17182,259 @@ mod tests {
                 l0 : main::Web_Event
                 l1 : <unknown>
             "#]],
-            |_| {
-                [(
-                    TyDiagnosticKind::Mismatch {
-                        expected: ExpectedTy::Variant,
-                        found: Ty::IInt(32).into(),
-                    },
-                    538..541,
-                    None,
-                )]
-            },
+            |_| {
+                [(
+                    TyDiagnosticKind::Mismatch {
+                        expected: ExpectedTy::Variant,
+                        found: Ty::IInt(32).into(),
+                    },
+                    538..541,
+                    None,
+                )]
+            },
+        )
+    }
+
+    #[test]
+    fn unwrap_directive_extra_arg() {
+        check(
+            r#"
+                Web_Event :: enum {
+                    Page_Load,
+                    Page_Unload,
+                    Key_Press: char,
+                    Paste: str,
+                    Click: struct {
+                        x: i64,
+                        y: i64,
+                    },
+                };
+
+                foo :: () {
+                    clicked : Web_Event = Web_Event.Click.{
+                        x = 20,
+                        y = 80
+                    };
+
+                    unwrapped := #unwrap(clicked, Web_Event.Click, 42, bool);
+                }
+            "#,
+            expect![[r#"
+                main::Web_Event : type
+                main::foo : () -> void
+                5 : type
+                7 : type
+                9 : i64
+                10 : i64
+                11 : main::Web_Event.Click
+                12 : main::Web_Event
+                13 : type
+                14 : type
+                15 : {uint}
+                16 : type
+                17 : <unknown>
+                18 : void
+                19 : () -> void
+                l0 : main::Web_Event
+                l1 : <unknown>
+            "#]],
+            |_| {
+                [
+                    (
+                        TyDiagnosticKind::ExtraArg {
+                            found: Ty::UInt(0).into(),
+                        },
+                        538..553,
+                        None,
+                    ),
+                    (
+                        TyDiagnosticKind::ExtraArg {
+                            found: Ty::Type.into(),
+                        },
+                        538..553,
+                        None,
+                    ),
+                ]
+            },
+        )
+    }
+
+    #[test]
+    fn tail_call() {
+        check(
+            r#"
+                count :: (n: i32, done: bool) -> i32 {
+                    if done {
+                        return n;
+                    }
+                    #tailcall(count(n, true))
+                }
+            "#,
+            expect![[r#"
+                main::count : (i32, bool) -> i32
+                3 : bool
+                4 : i32
+                5 : noeval
+                6 : void
+                7 : (i32, bool) -> i32
+                8 : i32
+                9 : bool
+                10 : i32
+                11 : noeval
+                12 : i32
+                13 : (i32, bool) -> i32
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn tail_call_to_other_function() {
+        check(
+            r#"
+                foo :: (n: i32) -> i32 {
+                    #tailcall(bar(n))
+                }
+
+                bar :: (n: i32) -> i32 { n }
+            "#,
+            expect![[r#"
+                main::bar : (i32) -> i32
+                main::foo : (i32) -> i32
+                2 : (i32) -> i32
+                3 : i32
+                4 : i32
+                5 : noeval
+                6 : noeval
+                7 : (i32) -> i32
+                10 : i32
+                11 : i32
+                12 : (i32) -> i32
+            "#]],
+            |_| [(TyDiagnosticKind::TailCallToOtherFunction, 72..75, None)],
+        )
+    }
+
+    #[test]
+    fn tail_call_not_call() {
+        check(
+            r#"
+                foo :: (n: i32) -> i32 {
+                    #tailcall(n)
+                }
+            "#,
+            expect![[r#"
+                main::foo : (i32) -> i32
+                2 : i32
+                3 : noeval
+                4 : noeval
+                5 : (i32) -> i32
+            "#]],
+            |_| [(TyDiagnosticKind::TailCallNotCall, 72..73, None)],
+        )
+    }
+
+    #[test]
+    fn math_intrinsics() {
+        check(
+            r#"
+                foo :: (x: f32, n: u8) {
+                    root := #sqrt(x);
+                    biggest := #max(n, 200);
+                    zeros := #ctz(n);
+                    fused := #fma(x, 2, 1.5);
+                }
+            "#,
+            expect![[r#"
+                main::foo : (f32, u8) -> void
+                2 : f32
+                3 : f32
+                4 : u8
+                5 : u8
+                6 : u8
+                7 : u8
+                8 : u8
+                9 : f32
+                10 : f32
+                11 : f32
+                12 : f32
+                13 : void
+                14 : (f32, u8) -> void
+                l0 : f32
+                l1 : u8
+                l2 : u8
+                l3 : f32
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn pow_intrinsic() {
+        check(
+            r#"
+                foo :: (x: f64, n: i16) {
+                    a := #pow(n, 3);
+                    b := #pow(x, 2);
+                    c := #pow(2, 0.5);
+                }
+            "#,
+            expect![[r#"
+                main::foo : (f64, i16) -> void
+                2 : i16
+                3 : i16
+                4 : i16
+                5 : f64
+                6 : f64
+                7 : f64
+                8 : {float}
+                9 : {float}
+                10 : {float}
+                11 : void
+                12 : (f64, i16) -> void
+                l0 : i16
+                l1 : f64
+                l2 : {float}
+            "#]],
+            |_| [],
         )
     }
 
     #[test]
-    fn unwrap_directive_extra_arg() {
+    fn math_intrinsics_bad_args() {
         check(
             r#"
-                Web_Event :: enum {
-                    Page_Load,
-                    Page_Unload,
-                    Key_Press: char,
-                    Paste: str,
-                    Click: struct {
-                        x: i64,
-                        y: i64,
-                    },
-                };
-
-                foo :: () {
-                    clicked : Web_Event = Web_Event.Click.{
-                        x = 20,
-                        y = 80
-                    };
-
-                    unwrapped := #unwrap(clicked, Web_Event.Click, 42, bool);
+                foo :: (x: f32) {
+                    bits := #popcount(x);
+                    nothing := #abs();
                 }
             "#,
             expect![[r#"
-                main::Web_Event : type
-                main::foo : () -> void
-                5 : type
-                7 : type
-                9 : i64
-                10 : i64
-                11 : main::Web_Event.Click
-                12 : main::Web_Event
-                13 : type
-                14 : type
-                15 : {uint}
-                16 : type
-                17 : <unknown>
-                18 : void
-                19 : () -> void
-                l0 : main::Web_Event
+                main::foo : (f32) -> void
+                1 : f32
+                2 : <unknown>
+                3 : <unknown>
+                4 : void
+                5 : (f32) -> void
+                l0 : <unknown>
                 l1 : <unknown>
             "#]],
-            |_| {
+            |i| {
                 [
                     (
-                        TyDiagnosticKind::ExtraArg {
-                            found: Ty::UInt(0).into(),
+                        TyDiagnosticKind::IntrinsicMismatch {
+                            name: i.intern("popcount"),
+                            expected: IntrinsicOperands::Ints,
+                            found: Ty::Float(32).into(),
                         },
-                        538..553,
+                        63..75,
                         None,
                     ),
                     (
-                        TyDiagnosticKind::ExtraArg {
-                            found: Ty::Type.into(),
+                        TyDiagnosticKind::MissingArg {
+                            expected: ExpectedTy::Concrete(Ty::IInt(0).into()),
                         },
-                        538..553,
+                        113..113,
                         None,
                     ),
                 ]
@@ -12260,6 +17529,40 @@ mod tests {
         )
     }
 
+    #[test]
+    fn quick_assign_logical() {
+        check(
+            r#"
+                main :: () {
+                    done := false;
+                    big := 5;
+
+                    done ||= true;
+                    done &&= big > 3;
+                    big %= 2;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : bool
+                1 : {uint}
+                2 : bool
+                3 : bool
+                4 : bool
+                5 : {uint}
+                6 : {uint}
+                7 : bool
+                8 : {uint}
+                9 : {uint}
+                10 : void
+                11 : () -> void
+                l0 : bool
+                l1 : {uint}
+            "#]],
+            |_| [],
+        )
+    }
+
     #[test]
     fn quick_assign_reinfer() {
         check(
@@ -12298,6 +17601,109 @@ mod tests {
         )
     }
 
+    #[test]
+    fn quick_assign_reinfer_self() {
+        // the value of the first quick assign is the local itself, so weak type replacing the
+        // local queues that statement up again
+        check(
+            r#"
+                main :: () {
+                    foo := 5;
+
+                    foo += foo;
+                    foo *= u8.(2);
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : u8
+                1 : u8
+                2 : u8
+                3 : u8
+                4 : u8
+                6 : u8
+                7 : void
+                8 : () -> void
+                l0 : u8
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn reinfer_usages_declared_before() {
+        // `d` makes `c` an `i16`, which has to go back up through `b` and `a`,
+        // even though they were inferred before it
+        check(
+            r#"
+                main :: () {
+                    a := 1;
+                    b := a;
+                    c := b + a;
+                    d : i16 = c;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : i16
+                1 : i16
+                2 : i16
+                3 : i16
+                4 : i16
+                6 : i16
+                7 : void
+                8 : () -> void
+                l0 : i16
+                l1 : i16
+                l2 : i16
+                l3 : i16
+            "#]],
+            |_| [],
+        )
+    }
+
+    #[test]
+    fn reinfer_usages_in_loop() {
+        // `x` and `y` are each assigned to the other, so strengthening either one
+        // leads back to itself
+        check(
+            r#"
+                main :: () {
+                    x := 0;
+                    y := x;
+                    while y < 10 {
+                        x = y + 1;
+                        y = x;
+                    }
+                    z : u8 = y;
+                }
+            "#,
+            expect![[r#"
+                main::main : () -> void
+                0 : u8
+                1 : u8
+                2 : u8
+                3 : u8
+                4 : bool
+                5 : u8
+                6 : u8
+                7 : u8
+                8 : u8
+                9 : u8
+                10 : u8
+                11 : void
+                12 : void
+                14 : u8
+                15 : void
+                16 : () -> void
+                l0 : u8
+                l1 : u8
+                l2 : u8
+            "#]],
+            |_| [],
+        )
+    }
+
     #[test]
     fn quick_assign_cannot_perform() {
         check(
@@ -12382,4 +17788,390 @@ mod tests {
             },
         )
     }
+
+    #[test]
+    fn primitive_cast_matrix() {
+        use PrimitiveCast::*;
+
+        let tys = [
+            Ty::Bool,
+            Ty::IInt(8),
+            Ty::UInt(u8::MAX),
+            Ty::IInt(0),
+            Ty::Char,
+            Ty::Float(32),
+            Ty::Float(0),
+        ];
+
+        // rows are what's being casted from, columns are what's being casted into
+        #[rustfmt::skip]
+        let expected = [
+            [BoolToBool, FromBool,   FromBool,   FromBool,   FromBool,   FromBool,     FromBool    ],
+            [ToBool,     IntToInt,   IntToInt,   IntToInt,   IntToInt,   IntToFloat,   IntToFloat  ],
+            [ToBool,     IntToInt,   IntToInt,   IntToInt,   IntToInt,   IntToFloat,   IntToFloat  ],
+            [ToBool,     IntToInt,   IntToInt,   IntToInt,   IntToInt,   IntToFloat,   IntToFloat  ],
+            [ToBool,     IntToInt,   IntToInt,   IntToInt,   IntToInt,   IntToFloat,   IntToFloat  ],
+            [ToBool,     FloatToInt, FloatToInt, FloatToInt, FloatToInt, FloatToFloat, FloatToFloat],
+            [ToBool,     FloatToInt, FloatToInt, FloatToInt, FloatToInt, FloatToFloat, FloatToFloat],
+        ];
+
+        for (from, row) in tys.iter().zip(expected) {
+            for (to, cast) in tys.iter().zip(row) {
+                assert_eq!(from.primitive_cast(to), Some(cast), "{from:?} as {to:?}");
+                assert!(from.can_cast_to(to), "{from:?} as {to:?}");
+            }
+        }
+
+        // distincts aren't looked through by the table, but are still castable
+        let distinct = Ty::Distinct {
+            fqn: None,
+            uid: 0,
+            sub_ty: Ty::IInt(8).into(),
+        };
+        for ty in &tys {
+            assert_eq!(distinct.primitive_cast(ty), None);
+            assert_eq!(ty.primitive_cast(&distinct), None);
+            assert!(distinct.can_cast_to(ty), "{distinct:?} as {ty:?}");
+            assert!(ty.can_cast_to(&distinct), "{ty:?} as {distinct:?}");
+        }
+
+        // enums never cast to or from primitives
+        let variant: Intern<Ty> = Ty::Variant {
+            enum_fqn: None,
+            enum_uid: 0,
+            variant_name: hir::Name(Interner::default().intern("Foo")),
+            uid: 1,
+            sub_ty: Ty::IInt(8).into(),
+            discriminant: 0,
+        }
+        .into();
+        let enum_ty = Ty::Enum {
+            fqn: None,
+            uid: 0,
+            variants: vec![variant],
+        };
+        for ty in &tys {
+            assert_eq!(enum_ty.primitive_cast(ty), None);
+            assert!(!enum_ty.can_cast_to(ty), "{enum_ty:?} as {ty:?}");
+            assert!(!ty.can_cast_to(&enum_ty), "{ty:?} as {enum_ty:?}");
+        }
+    }
+
+    #[test]
+    fn primitive_cast_fold() {
+        let fold = |value: ComptimeResult, from: Ty, to: Ty| {
+            from.primitive_cast(&to).unwrap().fold(&value, &from, &to)
+        };
+        let int = |num: u64, bit_width: u8| ComptimeResult::Integer { num, bit_width };
+        let float = |num: f64, bit_width: u8| ComptimeResult::Float { num, bit_width };
+
+        let results = [
+            // truncation
+            fold(int(300, 32), Ty::IInt(32), Ty::UInt(8)),
+            // sign extension comes from the source
+            fold(int(0xFF, 8), Ty::IInt(8), Ty::UInt(16)),
+            fold(int(0xFF, 8), Ty::UInt(8), Ty::IInt(16)),
+            // floats round toward zero and saturate
+            fold(float(-2.75, 64), Ty::Float(64), Ty::IInt(32)),
+            fold(float(1000.0, 32), Ty::Float(32), Ty::IInt(8)),
+            fold(float(-5.0, 32), Ty::Float(32), Ty::UInt(8)),
+            fold(float(f64::NAN, 64), Ty::Float(64), Ty::UInt(64)),
+            fold(float(0.1, 64), Ty::Float(64), Ty::Float(32)),
+            fold(int(0xFF, 8), Ty::IInt(8), Ty::Float(64)),
+            // bools
+            fold(int(256, 32), Ty::IInt(32), Ty::Bool),
+            fold(float(0.0, 32), Ty::Float(32), Ty::Bool),
+            fold(int(1, 8), Ty::Bool, Ty::Float(32)),
+        ];
+
+        expect![[r#"
+            [
+                Some(
+                    Integer {
+                        num: 44,
+                        bit_width: 8,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 65535,
+                        bit_width: 16,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 255,
+                        bit_width: 16,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 4294967294,
+                        bit_width: 32,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 127,
+                        bit_width: 8,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 0,
+                        bit_width: 8,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 0,
+                        bit_width: 64,
+                    },
+                ),
+                Some(
+                    Float {
+                        num: 0.10000000149011612,
+                        bit_width: 32,
+                    },
+                ),
+                Some(
+                    Float {
+                        num: -1.0,
+                        bit_width: 64,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 1,
+                        bit_width: 8,
+                    },
+                ),
+                Some(
+                    Integer {
+                        num: 0,
+                        bit_width: 8,
+                    },
+                ),
+                Some(
+                    Float {
+                        num: 1.0,
+                        bit_width: 32,
+                    },
+                ),
+            ]
+        "#]]
+        .assert_debug_eq(&results);
+    }
+
+    #[test]
+    fn enums_are_per_project() {
+        let mut interner = Interner::default();
+
+        // both of these compilations have an enum as the first type of `main`,
+        // so they have the exact same uids
+        let enum_uid = hir::type_uid("main.capy", "main", 2);
+
+        let make_enum = |sub_tys: [Intern<Ty>; 2], interner: &mut Interner| {
+            let variants: Vec<Intern<Ty>> = sub_tys
+                .into_iter()
+                .enumerate()
+                .map(|(idx, sub_ty)| {
+                    Ty::Variant {
+                        enum_fqn: None,
+                        enum_uid,
+                        variant_name: hir::Name(interner.intern(&format!("V{idx}"))),
+                        uid: hir::type_uid("main.capy", "main", idx as u32),
+                        sub_ty,
+                        discriminant: idx as u64,
+                    }
+                    .into()
+                })
+                .collect();
+
+            let enum_ty: Intern<Ty> = Ty::Enum {
+                fqn: None,
+                uid: enum_uid,
+                variants: variants.clone(),
+            }
+            .into();
+
+            (enum_ty, variants)
+        };
+
+        let first = make_enum([Ty::String.into(), Ty::IInt(32).into()], &mut interner);
+        let second = make_enum([Ty::Bool.into(), Ty::Float(64).into()], &mut interner);
+        assert_ne!(first.0, second.0);
+
+        std::thread::scope(|scope| {
+            for (enum_ty, variants) in [&first, &second] {
+                scope.spawn(move || {
+                    let mut tys = ProjectInference::default();
+                    tys.add_enum(*enum_ty);
+
+                    for _ in 0..1000 {
+                        assert_eq!(
+                            variants[0].max(&variants[1], &tys),
+                            Some((**enum_ty).clone())
+                        );
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn error_ty_gives_way() {
+        let error = Ty::Error;
+        let int = Ty::IInt(32);
+
+        assert!(error.can_fit_into(&int));
+        assert!(int.can_fit_into(&error));
+        assert_eq!(
+            error.max(&int, &ProjectInference::default()),
+            Some(int.clone())
+        );
+        assert_eq!(
+            int.max(&error, &ProjectInference::default()),
+            Some(int.clone())
+        );
+
+        assert!(error.is_error_or_unknown());
+        assert!(Ty::Array {
+            anonymous: false,
+            size: 3,
+            sub_ty: error.clone().into(),
+        }
+        .is_unknown());
+        assert!(!Ty::Array {
+            anonymous: false,
+            size: 3,
+            sub_ty: error.into(),
+        }
+        .is_error_or_unknown());
+    }
+
+    #[test]
+    fn deserialize_bad_type_cache() {
+        let mut interner = Interner::default();
+        let bytes = ProjectInference::default().serialize(&interner);
+
+        assert_eq!(
+            ProjectInference::deserialize(b"not types", &mut interner).unwrap_err(),
+            DeserializeError::NotATypeCache
+        );
+
+        let mut future = bytes.clone();
+        future[8] = 99;
+        assert_eq!(
+            ProjectInference::deserialize(&future, &mut interner).unwrap_err(),
+            DeserializeError::UnsupportedVersion(99)
+        );
+
+        assert_eq!(
+            ProjectInference::deserialize(&bytes[..bytes.len() - 1], &mut interner).unwrap_err(),
+            DeserializeError::UnexpectedEnd
+        );
+
+        assert!(ProjectInference::deserialize(&bytes, &mut interner).is_ok());
+    }
+    /// `struct { x: i32, y: i32 }` declared as `name` in `file`
+    fn named_point(file: &str, name: &str, interner: &mut Interner) -> Ty {
+        Ty::Struct {
+            anonymous: false,
+            fqn: Some(hir::Fqn {
+                file: hir::FileName(interner.intern(file)),
+                name: hir::Name(interner.intern(name)),
+            }),
+            uid: 0,
+            members: vec![
+                MemberTy {
+                    name: hir::Name(interner.intern("x")),
+                    ty: Ty::IInt(32).into(),
+                },
+                MemberTy {
+                    name: hir::Name(interner.intern("y")),
+                    ty: Ty::IInt(32).into(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn display_verbosities() {
+        let mut interner = Interner::default();
+        let point = named_point("geometry.capy", "Point", &mut interner);
+        let ptr = Ty::Pointer {
+            mutable: true,
+            sub_ty: point.clone().into(),
+        };
+        let mod_dir = Path::new("/mod");
+
+        let display = |ty: &Ty, verbosity| {
+            ty.display_with(mod_dir, &interner)
+                .verbosity(verbosity)
+                .to_string()
+        };
+
+        assert_eq!(display(&point, TyVerbosity::Short), "Point");
+        assert_eq!(display(&point, TyVerbosity::Qualified), "geometry::Point");
+        assert_eq!(
+            display(&point, TyVerbosity::Structural),
+            "geometry::Point {x: i32, y: i32}"
+        );
+
+        // only the outermost type is shown structurally
+        assert_eq!(display(&ptr, TyVerbosity::Short), "^mut Point");
+        assert_eq!(
+            display(&ptr, TyVerbosity::Structural),
+            "^mut geometry::Point"
+        );
+
+        assert_eq!(display(&Ty::IInt(0), TyVerbosity::Short), "{integer}");
+        assert_eq!(display(&Ty::UInt(0), TyVerbosity::Short), "{integer}");
+        assert_eq!(display(&Ty::UInt(0), TyVerbosity::Qualified), "{uint}");
+
+        assert_eq!(point.display(mod_dir, &interner), "geometry::Point");
+    }
+
+    #[test]
+    fn safe_to_compile_cache_until_new_errors() {
+        let mut interner = Interner::default();
+        let file = hir::FileName(interner.intern("main.capy"));
+        let global = Inferrable::Global(hir::Fqn {
+            file,
+            name: hir::Name(interner.intern("foo")),
+        });
+        let expr = Idx::from_raw(la_arena::RawIdx::from(0));
+        let diagnostic = |kind| TyDiagnostic {
+            kind,
+            file,
+            expr: Some(Idx::from_raw(la_arena::RawIdx::from(1))),
+            range: TextRange::default(),
+            help: None,
+        };
+
+        let mut cache = SafeToCompileCache::default();
+        let mut diagnostics = Vec::new();
+
+        cache.update(&diagnostics);
+        assert_eq!(cache.get(global, expr), None);
+        cache.insert(global, expr, true);
+        assert_eq!(cache.get(global, expr), Some(true));
+
+        // a warning can't make anything unsafe to compile
+        diagnostics.push(diagnostic(TyDiagnosticKind::SelfComparison));
+        cache.update(&diagnostics);
+        assert_eq!(cache.get(global, expr), Some(true));
+
+        diagnostics.push(diagnostic(TyDiagnosticKind::DivideByZero {
+            op: hir::BinaryOp::Div,
+        }));
+        cache.update(&diagnostics);
+        assert_eq!(cache.get(global, expr), None);
+
+        // nothing new, so the next result sticks around
+        cache.insert(global, expr, false);
+        cache.update(&diagnostics);
+        assert_eq!(cache.get(global, expr), Some(false));
+    }
 }
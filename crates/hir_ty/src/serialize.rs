@@ -0,0 +1,801 @@
+//! A compact binary format for `ProjectInference`, so that the types of a project can be saved
+//! to disk and loaded again later (by the build cache, or by tools that want to look at the
+//! types without running the compiler).
+//!
+//! Neither interner keys nor `Intern<Ty>` pointers mean anything outside of the process that
+//! made them, so the format has two tables up front:
+//!
+//! - every string used by a name, in order of first use
+//! - every type, written after all of its sub-types so a type only ever refers backwards
+//!
+//! Everything after that refers to strings and types by their position in those tables.
+//! Globals and files are written in order of their names, so the same project always gives
+//! the same bytes no matter what order things were interned in.
+
+use std::fmt;
+
+use hir::{FQLambda, FileName, Fqn, Name};
+use indexmap::IndexSet;
+use interner::{Interner, Key};
+use internment::Intern;
+use la_arena::{ArenaMap, Idx, RawIdx};
+use rustc_hash::FxHashMap;
+
+use crate::{FileInference, Instance, MemberTy, ParamTy, ProjectInference, Signature, Ty};
+
+const MAGIC: &[u8; 8] = b"capytys\0";
+/// This should be bumped every time the format changes
+const VERSION: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeserializeError {
+    /// the bytes don't start with the header of a serialized `ProjectInference`
+    NotATypeCache,
+    /// the bytes were written by a different version of the compiler
+    UnsupportedVersion(u32),
+    UnexpectedEnd,
+    InvalidString,
+    InvalidTyTag(u8),
+    /// a string or a type refers to something that isn't in its table
+    InvalidIndex(u32),
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeError::NotATypeCache => write!(f, "not a type cache"),
+            DeserializeError::UnsupportedVersion(version) => write!(
+                f,
+                "type cache is version {version}, but only version {VERSION} is supported"
+            ),
+            DeserializeError::UnexpectedEnd => write!(f, "type cache ended unexpectedly"),
+            DeserializeError::InvalidString => write!(f, "type cache contains invalid UTF-8"),
+            DeserializeError::InvalidTyTag(tag) => write!(f, "unknown type tag {tag}"),
+            DeserializeError::InvalidIndex(idx) => write!(f, "index {idx} is out of bounds"),
+        }
+    }
+}
+
+impl ProjectInference {
+    pub fn serialize(&self, interner: &Interner) -> Vec<u8> {
+        let mut serializer = Serializer {
+            interner,
+            strings: IndexSet::default(),
+            tys: FxHashMap::default(),
+            ty_table: Encoder::default(),
+        };
+        let mut body = Encoder::default();
+
+        let mut signatures = self.signatures.iter().collect::<Vec<_>>();
+        signatures.sort_by_key(|(fqn, _)| serializer.fqn_sort_key(**fqn));
+        body.u32(signatures.len() as u32);
+        for (fqn, Signature(ty)) in signatures {
+            serializer.fqn(&mut body, *fqn);
+            let ty = serializer.ty(*ty);
+            body.u32(ty);
+        }
+
+        let mut enums = self.enums.iter().collect::<Vec<_>>();
+        enums.sort_by_key(|(uid, _)| **uid);
+        body.u32(enums.len() as u32);
+        for (uid, ty) in enums {
+            body.u64(*uid);
+            let ty = serializer.ty(*ty);
+            body.u32(ty);
+        }
+
+        let mut files = self.files.iter().collect::<Vec<_>>();
+        files.sort_by_key(|(file, _)| interner.lookup(file.0));
+        body.u32(files.len() as u32);
+        for (file, inference) in files {
+            let file = serializer.string(file.0);
+            body.u32(file);
+
+            serializer.file_inference(&mut body, inference);
+        }
+
+        body.u32(self.instances.len() as u32);
+        for instance in &self.instances {
+            serializer.fqn(&mut body, instance.fqn);
+            serializer.lambda(&mut body, instance.lambda);
+
+            body.u32(instance.consts.len() as u32);
+            for value in &instance.consts {
+                body.u64(*value);
+            }
+
+            body.bool(instance.ty.is_some());
+            if let Some(ty) = instance.ty {
+                let ty = serializer.ty(ty);
+                body.u32(ty);
+            }
+
+            serializer.file_inference(&mut body, &instance.tys);
+        }
+
+        let mut result = Encoder::default();
+        result.bytes.extend_from_slice(MAGIC);
+        result.u32(VERSION);
+
+        result.u32(serializer.strings.len() as u32);
+        for key in &serializer.strings {
+            let text = interner.lookup(*key);
+            result.u32(text.len() as u32);
+            result.bytes.extend_from_slice(text.as_bytes());
+        }
+
+        result.u32(serializer.tys.len() as u32);
+        result.bytes.extend(serializer.ty_table.bytes);
+        result.bytes.extend(body.bytes);
+
+        result.bytes
+    }
+
+    /// The strings within the bytes get interned into the given interner,
+    /// so it doesn't have to be the same interner that was used to serialize them.
+    pub fn deserialize(bytes: &[u8], interner: &mut Interner) -> Result<Self, DeserializeError> {
+        let mut decoder = Decoder { bytes, pos: 0 };
+
+        if decoder.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(DeserializeError::NotATypeCache);
+        }
+        let version = decoder.u32()?;
+        if version != VERSION {
+            return Err(DeserializeError::UnsupportedVersion(version));
+        }
+
+        let string_count = decoder.u32()?;
+        let mut strings = Vec::new();
+        for _ in 0..string_count {
+            let len = decoder.u32()? as usize;
+            let text = std::str::from_utf8(decoder.take(len)?)
+                .map_err(|_| DeserializeError::InvalidString)?;
+            strings.push(interner.intern(text));
+        }
+
+        let mut deserializer = Deserializer {
+            decoder,
+            strings,
+            tys: Vec::new(),
+        };
+
+        let ty_count = deserializer.decoder.u32()?;
+        for _ in 0..ty_count {
+            let ty = deserializer.ty_def()?;
+            deserializer.tys.push(Intern::new(ty));
+        }
+
+        let mut project = ProjectInference::default();
+
+        for _ in 0..deserializer.decoder.u32()? {
+            let fqn = deserializer.fqn()?;
+            let ty = deserializer.ty()?;
+            project.signatures.insert(fqn, Signature(ty));
+        }
+
+        for _ in 0..deserializer.decoder.u32()? {
+            let uid = deserializer.decoder.u64()?;
+            let ty = deserializer.ty()?;
+            project.enums.insert(uid, ty);
+        }
+
+        for _ in 0..deserializer.decoder.u32()? {
+            let file = FileName(deserializer.string()?);
+            let inference = deserializer.file_inference()?;
+            project.files.insert(file, inference);
+        }
+
+        for _ in 0..deserializer.decoder.u32()? {
+            let fqn = deserializer.fqn()?;
+            let lambda = deserializer.lambda()?;
+
+            let mut consts = Vec::new();
+            for _ in 0..deserializer.decoder.u32()? {
+                consts.push(deserializer.decoder.u64()?);
+            }
+
+            let ty = if deserializer.decoder.bool()? {
+                Some(deserializer.ty()?)
+            } else {
+                None
+            };
+
+            let tys = deserializer.file_inference()?;
+
+            project.instances.push(Instance {
+                fqn,
+                lambda,
+                consts,
+                ty,
+                tys,
+            });
+        }
+
+        Ok(project)
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+struct Serializer<'a> {
+    interner: &'a Interner,
+    strings: IndexSet<Key>,
+    tys: FxHashMap<Intern<Ty>, u32>,
+    ty_table: Encoder,
+}
+
+impl Serializer<'_> {
+    fn fqn_sort_key(&self, fqn: Fqn) -> (&str, &str) {
+        (
+            self.interner.lookup(fqn.file.0),
+            self.interner.lookup(fqn.name.0),
+        )
+    }
+
+    fn string(&mut self, key: Key) -> u32 {
+        self.strings.insert_full(key).0 as u32
+    }
+
+    fn fqn(&mut self, encoder: &mut Encoder, fqn: Fqn) {
+        let file = self.string(fqn.file.0);
+        let name = self.string(fqn.name.0);
+        encoder.u32(file);
+        encoder.u32(name);
+    }
+
+    fn opt_fqn(&mut self, encoder: &mut Encoder, fqn: Option<Fqn>) {
+        encoder.bool(fqn.is_some());
+        if let Some(fqn) = fqn {
+            self.fqn(encoder, fqn);
+        }
+    }
+
+    fn lambda(&mut self, encoder: &mut Encoder, lambda: FQLambda) {
+        let file = self.string(lambda.file.0);
+        encoder.u32(file);
+        encoder.u32(lambda.expr.into_raw().into());
+        encoder.u32(lambda.lambda.into_raw().into());
+    }
+
+    fn file_inference(&mut self, encoder: &mut Encoder, inference: &FileInference) {
+        self.ty_map(encoder, &inference.expr_tys);
+        self.ty_map(encoder, &inference.meta_tys);
+        self.ty_map(encoder, &inference.local_tys);
+        self.ty_map(encoder, &inference.switch_local_tys);
+        self.ty_map(encoder, &inference.for_local_tys);
+
+        encoder.u32(inference.namespaced_members.iter().count() as u32);
+        for (idx, fqn) in inference.namespaced_members.iter() {
+            encoder.u32(idx.into_raw().into());
+            self.fqn(encoder, *fqn);
+        }
+
+        encoder.u32(inference.overloads.iter().count() as u32);
+        for (idx, fqn) in inference.overloads.iter() {
+            encoder.u32(idx.into_raw().into());
+            self.fqn(encoder, *fqn);
+        }
+
+        encoder.u32(inference.operators.iter().count() as u32);
+        for (idx, fqn) in inference.operators.iter() {
+            encoder.u32(idx.into_raw().into());
+            self.fqn(encoder, *fqn);
+        }
+
+        encoder.u32(inference.instances.iter().count() as u32);
+        for (idx, instance) in inference.instances.iter() {
+            encoder.u32(idx.into_raw().into());
+            encoder.u32(*instance);
+        }
+
+        encoder.u32(inference.interface_casts.iter().count() as u32);
+        for (idx, (interface, methods)) in inference.interface_casts.iter() {
+            encoder.u32(idx.into_raw().into());
+            let interface = self.ty(*interface);
+            encoder.u32(interface);
+            encoder.u32(methods.len() as u32);
+            for fqn in methods {
+                self.fqn(encoder, *fqn);
+            }
+        }
+    }
+
+    fn ty_map<T>(&mut self, encoder: &mut Encoder, map: &ArenaMap<Idx<T>, Intern<Ty>>) {
+        encoder.u32(map.iter().count() as u32);
+        for (idx, ty) in map.iter() {
+            encoder.u32(idx.into_raw().into());
+            let ty = self.ty(*ty);
+            encoder.u32(ty);
+        }
+    }
+
+    /// Adds the type (and all of its sub-types) to the type table and returns its index.
+    fn ty(&mut self, ty: Intern<Ty>) -> u32 {
+        if let Some(idx) = self.tys.get(&ty) {
+            return *idx;
+        }
+
+        let mut def = Encoder::default();
+
+        match ty.as_ref() {
+            Ty::NotYetResolved => def.u8(0),
+            Ty::Unknown => def.u8(1),
+            Ty::Error => def.u8(2),
+            Ty::IInt(bit_width) => {
+                def.u8(3);
+                def.u8(*bit_width);
+            }
+            Ty::UInt(bit_width) => {
+                def.u8(4);
+                def.u8(*bit_width);
+            }
+            Ty::Float(bit_width) => {
+                def.u8(5);
+                def.u8(*bit_width);
+            }
+            Ty::Bool => def.u8(6),
+            Ty::String => def.u8(7),
+            Ty::Char => def.u8(8),
+            Ty::Array {
+                anonymous,
+                size,
+                sub_ty,
+            } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(9);
+                def.bool(*anonymous);
+                def.u64(*size);
+                def.u32(sub_ty);
+            }
+            Ty::Slice { sub_ty } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(10);
+                def.u32(sub_ty);
+            }
+            Ty::Pointer { mutable, sub_ty } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(11);
+                def.bool(*mutable);
+                def.u32(sub_ty);
+            }
+            Ty::Range { inclusive, sub_ty } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(12);
+                def.bool(*inclusive);
+                def.u32(sub_ty);
+            }
+            Ty::Optional { sub_ty } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(13);
+                def.u32(sub_ty);
+            }
+            Ty::Distinct { fqn, uid, sub_ty } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(14);
+                self.opt_fqn(&mut def, *fqn);
+                def.u64(*uid);
+                def.u32(sub_ty);
+            }
+            Ty::Type => def.u8(15),
+            Ty::Any => def.u8(16),
+            Ty::RawPtr { mutable } => {
+                def.u8(17);
+                def.bool(*mutable);
+            }
+            Ty::RawSlice => def.u8(18),
+            Ty::File(file) => {
+                def.u8(19);
+                let file = self.string(file.0);
+                def.u32(file);
+            }
+            Ty::Function {
+                param_tys,
+                return_ty,
+            } => {
+                let param_tys = param_tys
+                    .iter()
+                    .map(|param| (self.ty(param.ty), param))
+                    .collect::<Vec<_>>();
+                let return_ty = self.ty(*return_ty);
+                def.u8(20);
+                def.u32(param_tys.len() as u32);
+                for (ty, param) in param_tys {
+                    def.u32(ty);
+                    def.bool(param.varargs);
+                    def.bool(param.impossible_to_differentiate);
+                }
+                def.u32(return_ty);
+            }
+            Ty::Closure {
+                param_tys,
+                return_ty,
+            } => {
+                let param_tys = param_tys
+                    .iter()
+                    .map(|param| (self.ty(param.ty), param))
+                    .collect::<Vec<_>>();
+                let return_ty = self.ty(*return_ty);
+                def.u8(28);
+                def.u32(param_tys.len() as u32);
+                for (ty, param) in param_tys {
+                    def.u32(ty);
+                    def.bool(param.varargs);
+                    def.bool(param.impossible_to_differentiate);
+                }
+                def.u32(return_ty);
+            }
+            Ty::Tuple { items } => {
+                let items = items.iter().map(|item| self.ty(*item)).collect::<Vec<_>>();
+                def.u8(21);
+                def.u32(items.len() as u32);
+                for item in items {
+                    def.u32(item);
+                }
+            }
+            Ty::Struct {
+                anonymous,
+                fqn,
+                uid,
+                members,
+            } => {
+                let members = members
+                    .iter()
+                    .map(|MemberTy { name, ty }| (self.string(name.0), self.ty(*ty)))
+                    .collect::<Vec<_>>();
+                def.u8(22);
+                def.bool(*anonymous);
+                self.opt_fqn(&mut def, *fqn);
+                def.u64(*uid);
+                def.u32(members.len() as u32);
+                for (name, ty) in members {
+                    def.u32(name);
+                    def.u32(ty);
+                }
+            }
+            Ty::Interface { fqn, uid, methods } => {
+                let methods = methods
+                    .iter()
+                    .map(|MemberTy { name, ty }| (self.string(name.0), self.ty(*ty)))
+                    .collect::<Vec<_>>();
+                def.u8(27);
+                self.opt_fqn(&mut def, *fqn);
+                def.u64(*uid);
+                def.u32(methods.len() as u32);
+                for (name, ty) in methods {
+                    def.u32(name);
+                    def.u32(ty);
+                }
+            }
+            Ty::Enum { fqn, uid, variants } => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| self.ty(*variant))
+                    .collect::<Vec<_>>();
+                def.u8(23);
+                self.opt_fqn(&mut def, *fqn);
+                def.u64(*uid);
+                def.u32(variants.len() as u32);
+                for variant in variants {
+                    def.u32(variant);
+                }
+            }
+            Ty::Variant {
+                enum_fqn,
+                enum_uid,
+                variant_name,
+                uid,
+                sub_ty,
+                discriminant,
+            } => {
+                let sub_ty = self.ty(*sub_ty);
+                def.u8(24);
+                self.opt_fqn(&mut def, *enum_fqn);
+                def.u64(*enum_uid);
+                let variant_name = self.string(variant_name.0);
+                def.u32(variant_name);
+                def.u64(*uid);
+                def.u32(sub_ty);
+                def.u64(*discriminant);
+            }
+            Ty::Void => def.u8(25),
+            Ty::NoEval => def.u8(26),
+        }
+
+        let idx = self.tys.len() as u32;
+        self.tys.insert(ty, idx);
+        self.ty_table.bytes.extend(def.bytes);
+
+        idx
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DeserializeError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(DeserializeError::UnexpectedEnd)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, DeserializeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, DeserializeError> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u32(&mut self) -> Result<u32, DeserializeError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, DeserializeError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+struct Deserializer<'a> {
+    decoder: Decoder<'a>,
+    strings: Vec<Key>,
+    tys: Vec<Intern<Ty>>,
+}
+
+impl Deserializer<'_> {
+    fn string(&mut self) -> Result<Key, DeserializeError> {
+        let idx = self.decoder.u32()?;
+        self.strings
+            .get(idx as usize)
+            .copied()
+            .ok_or(DeserializeError::InvalidIndex(idx))
+    }
+
+    fn fqn(&mut self) -> Result<Fqn, DeserializeError> {
+        Ok(Fqn {
+            file: FileName(self.string()?),
+            name: Name(self.string()?),
+        })
+    }
+
+    fn opt_fqn(&mut self) -> Result<Option<Fqn>, DeserializeError> {
+        if self.decoder.bool()? {
+            self.fqn().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn lambda(&mut self) -> Result<FQLambda, DeserializeError> {
+        Ok(FQLambda {
+            file: FileName(self.string()?),
+            expr: Idx::from_raw(RawIdx::from(self.decoder.u32()?)),
+            lambda: Idx::from_raw(RawIdx::from(self.decoder.u32()?)),
+        })
+    }
+
+    fn file_inference(&mut self) -> Result<FileInference, DeserializeError> {
+        let mut inference = FileInference {
+            expr_tys: self.ty_map()?,
+            meta_tys: self.ty_map()?,
+            local_tys: self.ty_map()?,
+            switch_local_tys: self.ty_map()?,
+            for_local_tys: self.ty_map()?,
+            namespaced_members: ArenaMap::default(),
+            overloads: ArenaMap::default(),
+            operators: ArenaMap::default(),
+            instances: ArenaMap::default(),
+            interface_casts: ArenaMap::default(),
+        };
+
+        for _ in 0..self.decoder.u32()? {
+            let idx = Idx::from_raw(RawIdx::from(self.decoder.u32()?));
+            let fqn = self.fqn()?;
+            inference.namespaced_members.insert(idx, fqn);
+        }
+
+        for _ in 0..self.decoder.u32()? {
+            let idx = Idx::from_raw(RawIdx::from(self.decoder.u32()?));
+            let fqn = self.fqn()?;
+            inference.overloads.insert(idx, fqn);
+        }
+
+        for _ in 0..self.decoder.u32()? {
+            let idx = Idx::from_raw(RawIdx::from(self.decoder.u32()?));
+            let fqn = self.fqn()?;
+            inference.operators.insert(idx, fqn);
+        }
+
+        for _ in 0..self.decoder.u32()? {
+            let idx = Idx::from_raw(RawIdx::from(self.decoder.u32()?));
+            let instance = self.decoder.u32()?;
+            inference.instances.insert(idx, instance);
+        }
+
+        for _ in 0..self.decoder.u32()? {
+            let idx = Idx::from_raw(RawIdx::from(self.decoder.u32()?));
+            let interface = self.ty()?;
+            let mut methods = Vec::new();
+            for _ in 0..self.decoder.u32()? {
+                methods.push(self.fqn()?);
+            }
+            inference.interface_casts.insert(idx, (interface, methods));
+        }
+
+        Ok(inference)
+    }
+
+    /// Reads a reference to a type that's already been read
+    fn ty(&mut self) -> Result<Intern<Ty>, DeserializeError> {
+        let idx = self.decoder.u32()?;
+        self.tys
+            .get(idx as usize)
+            .copied()
+            .ok_or(DeserializeError::InvalidIndex(idx))
+    }
+
+    fn ty_map<T>(&mut self) -> Result<ArenaMap<Idx<T>, Intern<Ty>>, DeserializeError> {
+        let mut map = ArenaMap::default();
+        for _ in 0..self.decoder.u32()? {
+            let idx = Idx::from_raw(RawIdx::from(self.decoder.u32()?));
+            let ty = self.ty()?;
+            map.insert(idx, ty);
+        }
+        Ok(map)
+    }
+
+    /// Reads an entry of the type table
+    fn ty_def(&mut self) -> Result<Ty, DeserializeError> {
+        let ty = match self.decoder.u8()? {
+            0 => Ty::NotYetResolved,
+            1 => Ty::Unknown,
+            2 => Ty::Error,
+            3 => Ty::IInt(self.decoder.u8()?),
+            4 => Ty::UInt(self.decoder.u8()?),
+            5 => Ty::Float(self.decoder.u8()?),
+            6 => Ty::Bool,
+            7 => Ty::String,
+            8 => Ty::Char,
+            9 => Ty::Array {
+                anonymous: self.decoder.bool()?,
+                size: self.decoder.u64()?,
+                sub_ty: self.ty()?,
+            },
+            10 => Ty::Slice { sub_ty: self.ty()? },
+            11 => Ty::Pointer {
+                mutable: self.decoder.bool()?,
+                sub_ty: self.ty()?,
+            },
+            12 => Ty::Range {
+                inclusive: self.decoder.bool()?,
+                sub_ty: self.ty()?,
+            },
+            13 => Ty::Optional { sub_ty: self.ty()? },
+            14 => Ty::Distinct {
+                fqn: self.opt_fqn()?,
+                uid: self.decoder.u64()?,
+                sub_ty: self.ty()?,
+            },
+            15 => Ty::Type,
+            16 => Ty::Any,
+            17 => Ty::RawPtr {
+                mutable: self.decoder.bool()?,
+            },
+            18 => Ty::RawSlice,
+            19 => Ty::File(FileName(self.string()?)),
+            20 => {
+                let mut param_tys = Vec::new();
+                for _ in 0..self.decoder.u32()? {
+                    param_tys.push(ParamTy {
+                        ty: self.ty()?,
+                        varargs: self.decoder.bool()?,
+                        impossible_to_differentiate: self.decoder.bool()?,
+                    });
+                }
+                Ty::Function {
+                    param_tys,
+                    return_ty: self.ty()?,
+                }
+            }
+            21 => {
+                let mut items = Vec::new();
+                for _ in 0..self.decoder.u32()? {
+                    items.push(self.ty()?);
+                }
+                Ty::Tuple { items }
+            }
+            22 => {
+                let anonymous = self.decoder.bool()?;
+                let fqn = self.opt_fqn()?;
+                let uid = self.decoder.u64()?;
+                let mut members = Vec::new();
+                for _ in 0..self.decoder.u32()? {
+                    members.push(MemberTy {
+                        name: Name(self.string()?),
+                        ty: self.ty()?,
+                    });
+                }
+                Ty::Struct {
+                    anonymous,
+                    fqn,
+                    uid,
+                    members,
+                }
+            }
+            23 => {
+                let fqn = self.opt_fqn()?;
+                let uid = self.decoder.u64()?;
+                let mut variants = Vec::new();
+                for _ in 0..self.decoder.u32()? {
+                    variants.push(self.ty()?);
+                }
+                Ty::Enum { fqn, uid, variants }
+            }
+            24 => Ty::Variant {
+                enum_fqn: self.opt_fqn()?,
+                enum_uid: self.decoder.u64()?,
+                variant_name: Name(self.string()?),
+                uid: self.decoder.u64()?,
+                sub_ty: self.ty()?,
+                discriminant: self.decoder.u64()?,
+            },
+            25 => Ty::Void,
+            26 => Ty::NoEval,
+            27 => {
+                let fqn = self.opt_fqn()?;
+                let uid = self.decoder.u64()?;
+                let mut methods = Vec::new();
+                for _ in 0..self.decoder.u32()? {
+                    methods.push(MemberTy {
+                        name: Name(self.string()?),
+                        ty: self.ty()?,
+                    });
+                }
+                Ty::Interface { fqn, uid, methods }
+            }
+            28 => {
+                let mut param_tys = Vec::new();
+                for _ in 0..self.decoder.u32()? {
+                    param_tys.push(ParamTy {
+                        ty: self.ty()?,
+                        varargs: self.decoder.bool()?,
+                        impossible_to_differentiate: self.decoder.bool()?,
+                    });
+                }
+                Ty::Closure {
+                    param_tys,
+                    return_ty: self.ty()?,
+                }
+            }
+            tag => return Err(DeserializeError::InvalidTyTag(tag)),
+        };
+
+        Ok(ty)
+    }
+}
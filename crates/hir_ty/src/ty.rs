@@ -1,38 +1,10 @@
-use std::{cell::RefCell, sync::LazyLock};
+use std::sync::LazyLock;
 
 use hir::{PrimitiveTy, UnaryOp};
 use internment::Intern;
 use rustc_hash::FxHashMap;
 
-// map of enum uid's to enum types
-//
-// this has to be thread local because all the capy unit tests run in multiple threads,
-// and the enum data will overwrite each other and cause errors.
-//
-// todo: there is probably a much, much cleaner way to do all this
-// the only reason I do this is because `Ty::max` needs to convert a `Ty::Variant` back into
-// its `Ty::Enum`, but this is impossible because they'd both contain references to each other.
-thread_local! {
-    static ENUM_MAP: RefCell<FxHashMap<u32, Intern<Ty>>> = RefCell::new(FxHashMap::default());
-}
-
-#[track_caller]
-pub(crate) fn get_enum_from_uid(enum_uid: u32) -> Intern<Ty> {
-    ENUM_MAP.with(|map| map.borrow()[&enum_uid])
-}
-
-#[track_caller]
-pub(crate) fn set_enum_uid(enum_uid: u32, ty: Intern<Ty>) {
-    let Ty::Enum { uid, .. } = ty.as_ref() else {
-        panic!("passed in non-enum");
-    };
-
-    assert_eq!(enum_uid, *uid);
-
-    ENUM_MAP.with(|map| {
-        map.borrow_mut().insert(enum_uid, ty);
-    });
-}
+use crate::{ComptimeResult, ProjectInference};
 
 // Some commonly used types, defined in LazyLocks so that `.into()` is only called once
 
@@ -52,6 +24,11 @@ pub static F0: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::Float(0).into());
 pub static F32: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::Float(32).into());
 pub static F64: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::Float(64).into());
 
+/// The type that `{int}` and `{uint}` become when nothing else decides what they should be
+pub static DEFAULT_INT: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::IInt(32).into());
+/// The type that `{float}` becomes when nothing else decides what it should be
+pub static DEFAULT_FLOAT: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::Float(64).into());
+
 pub static BOOL: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::Bool.into());
 pub static STRING: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::String.into());
 pub static CHAR: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::Char.into());
@@ -63,7 +40,13 @@ pub static NO_EVAL: LazyLock<Intern<Ty>> = LazyLock::new(|| Ty::NoEval.into());
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub enum Ty {
     NotYetResolved,
+    /// a type that couldn't be figured out, even though nothing went wrong (yet)
     Unknown,
+    /// the type of anything that had an error.
+    ///
+    /// diagnostics involving this type are silenced,
+    /// so that a single mistake is only ever reported once
+    Error,
     /// a bit-width of u32::MAX represents an isize
     /// a bit-width of 0 represents ANY signed integer type
     IInt(u8),
@@ -93,9 +76,27 @@ pub enum Ty {
         mutable: bool,
         sub_ty: Intern<Ty>,
     },
+    // a range is laid out exactly like an array of its start and end
+    // offset       field
+    // --------     -----
+    // 0            start
+    // size_of(T)   end
+    Range {
+        inclusive: bool,
+        sub_ty: Intern<Ty>,
+    },
+    // an optional is laid out exactly like the tuple `(T, bool)`,
+    // where the bool is `false` when the optional is `null`
+    // offset       field
+    // --------     -----
+    // 0            payload
+    // size_of(T)   is_some
+    Optional {
+        sub_ty: Intern<Ty>,
+    },
     Distinct {
         fqn: Option<hir::Fqn>,
-        uid: u32,
+        uid: u64,
         sub_ty: Intern<Ty>,
     },
     Type,
@@ -115,28 +116,49 @@ pub enum Ty {
         param_tys: Vec<ParamTy>,
         return_ty: Intern<Ty>,
     },
+    /// A function along with the values it captured.
+    /// This is laid out as the pointer to the function followed by a pointer to the captured
+    /// values, which is null if the function was never a closure to begin with
+    Closure {
+        param_tys: Vec<ParamTy>,
+        return_ty: Intern<Ty>,
+    },
+    /// laid out exactly like a struct with the same members
+    Tuple {
+        items: Vec<Intern<Ty>>,
+    },
     Struct {
         /// if anonymous is set to `true`, `uid` is useless
         anonymous: bool,
         fqn: Option<hir::Fqn>,
-        uid: u32,
+        uid: u64,
         members: Vec<MemberTy>,
     },
     Enum {
         fqn: Option<hir::Fqn>,
-        uid: u32,
+        uid: u64,
         /// this is always an array of `Ty::Variant`s
         variants: Vec<Intern<Ty>>,
     },
+    /// A pointer to a value of any type which has the given methods.
+    /// This is laid out as the pointer to the value followed by a pointer to the table of
+    /// functions which implement each method for that value's type
+    Interface {
+        fqn: Option<hir::Fqn>,
+        uid: u64,
+        /// the type of each method doesn't include the pointer that gets passed as the
+        /// first argument
+        methods: Vec<MemberTy>,
+    },
     /// An enum variant is very similar to a distinct type.
     /// the main difference is more strict autocasting rules,
     /// and more information in the type related to the enum
     /// (like the enum's fqn and the discriminant)
     Variant {
         enum_fqn: Option<hir::Fqn>,
-        enum_uid: u32,
+        enum_uid: u64,
         variant_name: hir::Name,
-        uid: u32,
+        uid: u64,
         sub_ty: Intern<Ty>,
         discriminant: u64,
     },
@@ -169,6 +191,146 @@ pub struct ParamTy {
     pub impossible_to_differentiate: bool,
 }
 
+/// How a primitive value is converted into another primitive by the `as` operator.
+///
+/// `char` behaves exactly like `u8` in every one of these casts.
+/// Enums can't be casted to or from primitives, but a variant can be casted as if it were its
+/// payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveCast {
+    /// `int -> int`
+    ///
+    /// A wider target is sign extended if the *source* is signed and zero extended otherwise.
+    /// A narrower target keeps only the lower bits (truncation).
+    IntToInt,
+    /// `int -> float`
+    ///
+    /// The integer is rounded to the nearest representable float.
+    IntToFloat,
+    /// `float -> int`
+    ///
+    /// The float is rounded toward zero and saturates at the bounds of the target.
+    /// `NaN` becomes `0`.
+    FloatToInt,
+    /// `float -> float`
+    ///
+    /// Widening is exact, narrowing rounds to the nearest representable float.
+    FloatToFloat,
+    /// `int/float/char -> bool`
+    ///
+    /// The result is `true` if the value isn't zero, it is *not* a truncation of the lower bits.
+    ToBool,
+    /// `bool -> int/float/char`
+    ///
+    /// `true` becomes `1` and `false` becomes `0`.
+    FromBool,
+    /// `bool -> bool`
+    BoolToBool,
+}
+
+impl PrimitiveCast {
+    /// Applies the cast to a value that's already known at compile time,
+    /// with the same semantics the cast would have at runtime.
+    ///
+    /// `from` and `to` should be the absolute types of the value before and after the cast.
+    /// Integers wider than 64 bits, along with `isize` and `usize`, are folded as 64 bit integers.
+    pub fn fold(self, value: &ComptimeResult, from: &Ty, to: &Ty) -> Option<ComptimeResult> {
+        fn int_info(ty: &Ty) -> Option<(u8, bool)> {
+            let fold_bit_width = |bit_width: u8| match bit_width {
+                8 | 16 | 32 => bit_width,
+                _ => 64,
+            };
+
+            match ty {
+                Ty::IInt(bit_width) => Some((fold_bit_width(*bit_width), true)),
+                Ty::UInt(bit_width) => Some((fold_bit_width(*bit_width), false)),
+                Ty::Bool | Ty::Char => Some((8, false)),
+                _ => None,
+            }
+        }
+
+        // reads the raw bits of an integer as its actual value
+        let int_value = |num: u64, ty: &Ty| -> Option<i128> {
+            let (bit_width, signed) = int_info(ty)?;
+            let shift = 64 - bit_width as u32;
+
+            Some(if signed {
+                (((num << shift) as i64) >> shift) as i128
+            } else {
+                ((num << shift) >> shift) as i128
+            })
+        };
+
+        // truncates a value into the raw bits of `ty`
+        let int_result = |value: i128, ty: &Ty| -> Option<ComptimeResult> {
+            let (bit_width, _) = int_info(ty)?;
+            let shift = 64 - bit_width as u32;
+
+            Some(ComptimeResult::Integer {
+                num: ((value as u64) << shift) >> shift,
+                bit_width,
+            })
+        };
+
+        let float_result = |num: f64, ty: &Ty| -> Option<ComptimeResult> {
+            match ty {
+                Ty::Float(32) => Some(ComptimeResult::Float {
+                    num: num as f32 as f64,
+                    bit_width: 32,
+                }),
+                Ty::Float(_) => Some(ComptimeResult::Float { num, bit_width: 64 }),
+                _ => None,
+            }
+        };
+
+        let bool_result = |value: bool| ComptimeResult::Integer {
+            num: value as u64,
+            bit_width: 8,
+        };
+
+        match (self, value) {
+            (
+                PrimitiveCast::IntToInt | PrimitiveCast::BoolToBool,
+                ComptimeResult::Integer { num, .. },
+            ) => int_result(int_value(*num, from)?, to),
+            (PrimitiveCast::IntToFloat, ComptimeResult::Integer { num, .. }) => {
+                float_result(int_value(*num, from)? as f64, to)
+            }
+            (PrimitiveCast::FloatToInt, ComptimeResult::Float { num, .. }) => {
+                let (bit_width, signed) = int_info(to)?;
+                let shift = 64 - bit_width as u32;
+
+                // rust's `as` already rounds toward zero, saturates, and turns NaN into 0,
+                // so it just needs to be clamped to the bounds of the target
+                let value = if signed {
+                    (*num as i64).clamp(i64::MIN >> shift, i64::MAX >> shift) as i128
+                } else {
+                    (*num as u64).min(u64::MAX >> shift) as i128
+                };
+
+                int_result(value, to)
+            }
+            (PrimitiveCast::FloatToFloat, ComptimeResult::Float { num, .. }) => {
+                float_result(*num, to)
+            }
+            (PrimitiveCast::ToBool, ComptimeResult::Integer { num, .. }) => {
+                Some(bool_result(int_value(*num, from)? != 0))
+            }
+            (PrimitiveCast::ToBool, ComptimeResult::Float { num, .. }) => {
+                Some(bool_result(*num != 0.0))
+            }
+            (PrimitiveCast::FromBool, ComptimeResult::Integer { num, .. }) => {
+                let value = *num != 0;
+                match to {
+                    Ty::Float(_) => float_result(value as u8 as f64, to),
+                    _ => int_result(value as i128, to),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct BinaryOutputTy {
     pub(crate) max_ty: Ty,
@@ -195,7 +357,7 @@ impl Ty {
     pub fn has_default_value(&self) -> bool {
         match self {
             Ty::NotYetResolved => true,
-            Ty::Unknown => true,
+            Ty::Unknown | Ty::Error => true,
             Ty::IInt(_) => true,
             Ty::UInt(_) => true,
             Ty::Float(_) => true,
@@ -203,6 +365,9 @@ impl Ty {
             Ty::String => false,
             Ty::Char => true,
             Ty::Array { sub_ty, .. } => sub_ty.has_default_value(),
+            Ty::Range { sub_ty, .. } => sub_ty.has_default_value(),
+            // optionals default to `null`
+            Ty::Optional { .. } => true,
             Ty::Slice { .. } => false,
             Ty::Pointer { .. } => false,
             Ty::Distinct { sub_ty, .. } => sub_ty.has_default_value(),
@@ -212,11 +377,14 @@ impl Ty {
             Ty::RawSlice => false,
             Ty::File(_) => false,
             Ty::Function { .. } => false,
+            Ty::Closure { .. } => false,
+            Ty::Tuple { items } => items.iter().all(|ty| ty.has_default_value()),
             Ty::Struct { members, .. } => members
                 .iter()
                 .all(|MemberTy { ty, .. }| ty.has_default_value()),
             // todo: create an @(default) annotation that allows you to set a default variant
             Ty::Enum { .. } => false,
+            Ty::Interface { .. } => false,
             Ty::Variant { sub_ty, .. } => sub_ty.has_default_value(),
             Ty::Void => true,
             Ty::NoEval => true,
@@ -249,6 +417,14 @@ impl Ty {
     }
 
     /// If self is a struct, this returns the fields
+    /// If self is a tuple, this returns the types of its items
+    pub fn as_tuple(&self) -> Option<Vec<Intern<Ty>>> {
+        match self.absolute_ty() {
+            Ty::Tuple { items } => Some(items.clone()),
+            _ => None,
+        }
+    }
+
     pub fn as_struct(&self) -> Option<Vec<MemberTy>> {
         match self.absolute_ty() {
             Ty::Struct { members, .. } => Some(members.clone()),
@@ -256,6 +432,30 @@ impl Ty {
         }
     }
 
+    /// If self is an interface, this returns its methods
+    pub fn as_interface(&self) -> Option<Vec<MemberTy>> {
+        match self.absolute_ty() {
+            Ty::Interface { methods, .. } => Some(methods.clone()),
+            _ => None,
+        }
+    }
+
+    /// If self is a closure, this returns the parameters and return type
+    pub fn as_closure(&self) -> Option<(Vec<ParamTy>, Intern<Ty>)> {
+        match self.absolute_ty() {
+            Ty::Closure {
+                param_tys: params,
+                return_ty,
+            } => Some((params.clone(), *return_ty)),
+            _ => None,
+        }
+    }
+
+    /// If self is a function or a closure, this returns the parameters and return type
+    pub fn as_callable(&self) -> Option<(Vec<ParamTy>, Intern<Ty>)> {
+        self.as_function().or_else(|| self.as_closure())
+    }
+
     /// If self is a function, this returns the parameters and return type
     pub fn as_function(&self) -> Option<(Vec<ParamTy>, Intern<Ty>)> {
         match self.absolute_ty() {
@@ -291,6 +491,14 @@ impl Ty {
         }
     }
 
+    /// If self is an optional, this returns the sub type
+    pub fn as_optional(&self) -> Option<Intern<Ty>> {
+        match self.absolute_ty() {
+            Ty::Optional { sub_ty } => Some(*sub_ty),
+            _ => None,
+        }
+    }
+
     pub fn is_any(&self) -> bool {
         matches!(self.absolute_ty(), Ty::Any)
     }
@@ -305,9 +513,14 @@ impl Ty {
             Ty::Struct { .. }
                 | Ty::Enum { .. }
                 | Ty::Array { .. }
+                | Ty::Range { .. }
+                | Ty::Optional { .. }
+                | Ty::Tuple { .. }
                 | Ty::Slice { .. }
                 | Ty::RawSlice
                 | Ty::Any
+                | Ty::Interface { .. }
+                | Ty::Closure { .. }
         )
     }
 
@@ -319,6 +532,14 @@ impl Ty {
         matches!(self.absolute_ty(), Ty::Slice { .. })
     }
 
+    /// If self is a range, this returns whether it's inclusive and the sub type
+    pub fn as_range(&self) -> Option<(bool, Intern<Ty>)> {
+        match self.absolute_ty() {
+            Ty::Range { inclusive, sub_ty } => Some((*inclusive, *sub_ty)),
+            _ => None,
+        }
+    }
+
     pub fn is_pointer(&self) -> bool {
         matches!(self.absolute_ty(), Ty::Pointer { .. })
     }
@@ -331,14 +552,98 @@ impl Ty {
         matches!(self.absolute_ty(), Ty::Struct { .. })
     }
 
+    /// The alignment of the type, if it can be known without knowing the target.
+    ///
+    /// This follows the same rules as the layouts in `codegen`,
+    /// but anything containing a `usize` or a pointer gives back `None`
+    pub fn known_align(&self) -> Option<u32> {
+        match self.absolute_ty_keep_variants() {
+            Ty::IInt(0) | Ty::UInt(0) | Ty::Float(0) => None,
+            Ty::IInt(u8::MAX) | Ty::UInt(u8::MAX) => None,
+            Ty::IInt(bit_width) | Ty::UInt(bit_width) | Ty::Float(bit_width) => {
+                Some((*bit_width as u32 / 8).min(8))
+            }
+            Ty::Bool | Ty::Char => Some(1),
+            Ty::Array { sub_ty, .. } | Ty::Range { sub_ty, .. } | Ty::Variant { sub_ty, .. } => {
+                sub_ty.known_align()
+            }
+            Ty::Optional { sub_ty } if sub_ty.is_zero_sized() => Some(1),
+            Ty::Optional { sub_ty } => sub_ty.known_align(),
+            Ty::Tuple { items } => items
+                .iter()
+                .try_fold(1, |align, ty| Some(ty.known_align()?.max(align))),
+            Ty::Struct { members, .. } => members
+                .iter()
+                .try_fold(1, |align, member| Some(member.ty.known_align()?.max(align))),
+            _ => None,
+        }
+    }
+
+    /// The size of the type in bytes, if it can be known without knowing the target.
+    ///
+    /// Like `known_align`, this follows the same rules as the layouts in `codegen`
+    pub fn known_size(&self) -> Option<u32> {
+        fn stride(ty: &Ty) -> Option<u32> {
+            let ty = ty.absolute_ty_keep_variants();
+            // weak types follow the same defaulting rules that `codegen` uses
+            let ty = ty.weak_default().map_or(ty, |default| default.as_ref());
+
+            let size = ty.known_size()?;
+            let align = if ty.is_zero_sized() {
+                1
+            } else {
+                ty.known_align()?
+            };
+            Some(size.next_multiple_of(align))
+        }
+
+        let ty = self.absolute_ty_keep_variants();
+        match ty {
+            _ if ty.is_zero_sized() => Some(0),
+            Ty::IInt(0) | Ty::UInt(0) | Ty::Float(0) => ty.weak_default()?.known_size(),
+            Ty::IInt(u8::MAX) | Ty::UInt(u8::MAX) => None,
+            Ty::IInt(bit_width) | Ty::UInt(bit_width) | Ty::Float(bit_width) => {
+                Some(*bit_width as u32 / 8)
+            }
+            Ty::Bool | Ty::Char => Some(1),
+            Ty::Array { size, sub_ty, .. } => {
+                stride(sub_ty)?.checked_mul(u32::try_from(*size).ok()?)
+            }
+            Ty::Range { sub_ty, .. } => Some(stride(sub_ty)? * 2),
+            // the flag comes right after the payload
+            Ty::Optional { sub_ty } => Some(sub_ty.known_size()? + 1),
+            Ty::Variant { sub_ty, .. } => sub_ty.known_size(),
+            Ty::Tuple { .. } | Ty::Struct { .. } => {
+                let fields = match ty {
+                    Ty::Tuple { items } => items.clone(),
+                    Ty::Struct { members, .. } => members.iter().map(|member| member.ty).collect(),
+                    _ => unreachable!(),
+                };
+
+                let mut offset: u32 = 0;
+                for field in fields {
+                    if field.is_zero_sized() {
+                        continue;
+                    }
+                    let field = field.weak_default().unwrap_or(field);
+                    offset = offset.next_multiple_of(field.known_align()?);
+                    offset = offset.checked_add(field.known_size()?)?;
+                }
+                Some(offset)
+            }
+            _ => None,
+        }
+    }
+
     /// returns true if the type is zero-sized
     pub fn is_zero_sized(&self) -> bool {
         match self.absolute_ty() {
-            Ty::NotYetResolved | Ty::Unknown => true,
+            Ty::NotYetResolved | Ty::Unknown | Ty::Error => true,
             Ty::Void => true,
             Ty::File(_) => true,
             Ty::NoEval => true,
             Ty::Array { size, sub_ty, .. } => *size == 0 || sub_ty.is_zero_sized(),
+            Ty::Tuple { items } => items.iter().all(|ty| ty.is_zero_sized()),
             Ty::Struct { members, .. } => {
                 members.is_empty() || members.iter().all(|MemberTy { ty, .. }| ty.is_zero_sized())
             }
@@ -359,19 +664,33 @@ impl Ty {
         matches!(self.absolute_ty(), Ty::Float(_))
     }
 
-    /// returns true if the type is unknown, or contains unknown, or is an unknown array, etc.
+    /// returns true if the type is either `Ty::Error` or `Ty::Unknown`.
+    ///
+    /// unlike `is_unknown`, this doesn't look at the types within this type
+    pub fn is_error_or_unknown(&self) -> bool {
+        matches!(self, Ty::Error | Ty::Unknown)
+    }
+
+    /// returns true if the type is unknown or an error, or contains one, like an unknown array, etc.
     pub fn is_unknown(&self) -> bool {
         // todo: make this iterative like `Ty::absolute_ty()`
         match self {
             Ty::NotYetResolved => true,
-            Ty::Unknown => true,
+            Ty::Unknown | Ty::Error => true,
             Ty::Pointer { sub_ty, .. } => sub_ty.is_unknown(),
             Ty::Array { sub_ty, .. } => sub_ty.is_unknown(),
+            Ty::Range { sub_ty, .. } => sub_ty.is_unknown(),
+            Ty::Optional { sub_ty } => sub_ty.is_unknown(),
+            Ty::Tuple { items } => items.iter().any(|ty| ty.is_unknown()),
             Ty::Struct { members, .. } => members.iter().any(|MemberTy { ty, .. }| ty.is_unknown()),
             Ty::Distinct { sub_ty, .. } => sub_ty.is_unknown(),
             Ty::Function {
                 param_tys,
                 return_ty,
+            }
+            | Ty::Closure {
+                param_tys,
+                return_ty,
             } => param_tys.iter().any(|p| p.ty.is_unknown()) || return_ty.is_unknown(),
             _ => false,
         }
@@ -409,6 +728,24 @@ impl Ty {
                     sub_ty: second_sub_ty,
                 },
             ) => first_mutable == second_mutable && first_sub_ty.is_equal_to(second_sub_ty),
+            (
+                Ty::Range {
+                    inclusive: first_inclusive,
+                    sub_ty: first_sub_ty,
+                },
+                Ty::Range {
+                    inclusive: second_inclusive,
+                    sub_ty: second_sub_ty,
+                },
+            ) => first_inclusive == second_inclusive && first_sub_ty.is_equal_to(second_sub_ty),
+            (
+                Ty::Optional {
+                    sub_ty: first_sub_ty,
+                },
+                Ty::Optional {
+                    sub_ty: second_sub_ty,
+                },
+            ) => first_sub_ty.is_equal_to(second_sub_ty),
             (Ty::Distinct { uid: first, .. }, Ty::Distinct { uid: second, .. }) => first == second,
             (
                 Ty::Function {
@@ -419,6 +756,16 @@ impl Ty {
                     param_tys: second_params,
                     return_ty: second_return_ty,
                 },
+            )
+            | (
+                Ty::Closure {
+                    param_tys: first_params,
+                    return_ty: first_return_ty,
+                },
+                Ty::Closure {
+                    param_tys: second_params,
+                    return_ty: second_return_ty,
+                },
             ) => {
                 first_return_ty.is_equal_to(second_return_ty)
                     && first_params.len() == second_params.len()
@@ -429,6 +776,18 @@ impl Ty {
                         },
                     )
             }
+            (
+                Ty::Tuple { items: first_items },
+                Ty::Tuple {
+                    items: second_items,
+                },
+            ) => {
+                first_items.len() == second_items.len()
+                    && first_items
+                        .iter()
+                        .zip(second_items.iter())
+                        .all(|(first, second)| first.is_equal_to(second))
+            }
             (
                 Ty::Struct {
                     anonymous: true,
@@ -462,6 +821,9 @@ impl Ty {
                 },
             ) => first_uid == second_uid,
             (Ty::Enum { uid: first, .. }, Ty::Enum { uid: second, .. }) => first == second,
+            (Ty::Interface { uid: first, .. }, Ty::Interface { uid: second, .. }) => {
+                first == second
+            }
             (Ty::Variant { uid: first, .. }, Ty::Variant { uid: second, .. }) => first == second,
             _ => self == other,
         }
@@ -506,6 +868,27 @@ impl Ty {
                 first_mutable == second_mutable
                     && first_sub_ty.is_functionally_equivalent_to(second_sub_ty, two_way)
             }
+            (
+                Ty::Range {
+                    inclusive: first_inclusive,
+                    sub_ty: first_sub_ty,
+                },
+                Ty::Range {
+                    inclusive: second_inclusive,
+                    sub_ty: second_sub_ty,
+                },
+            ) => {
+                first_inclusive == second_inclusive
+                    && first_sub_ty.is_functionally_equivalent_to(second_sub_ty, two_way)
+            }
+            (
+                Ty::Optional {
+                    sub_ty: first_sub_ty,
+                },
+                Ty::Optional {
+                    sub_ty: second_sub_ty,
+                },
+            ) => first_sub_ty.is_functionally_equivalent_to(second_sub_ty, two_way),
             (Ty::Distinct { sub_ty: first, .. }, Ty::Distinct { sub_ty: second, .. }) => {
                 first.is_functionally_equivalent_to(second, two_way)
             }
@@ -545,6 +928,18 @@ impl Ty {
                                 && first.ty.is_functionally_equivalent_to(&second.ty, two_way)
                         })
             }
+            (
+                Ty::Tuple { items: first_items },
+                Ty::Tuple {
+                    items: second_items,
+                },
+            ) => {
+                first_items.len() == second_items.len()
+                    && first_items
+                        .iter()
+                        .zip(second_items.iter())
+                        .all(|(first, second)| first.is_functionally_equivalent_to(second, two_way))
+            }
             (first, second) => first.is_equal_to(second),
         }
     }
@@ -585,7 +980,9 @@ impl Ty {
     /// ```
     ///
     /// diagram stolen from vlang docs bc i liked it
-    pub(crate) fn max(&self, other: &Ty) -> Option<Ty> {
+    ///
+    /// `tys` is needed to get the enum of two variants
+    pub(crate) fn max(&self, other: &Ty, tys: &ProjectInference) -> Option<Ty> {
         if self.is_equal_to(other) {
             return Some(self.clone());
         }
@@ -631,6 +1028,41 @@ impl Ty {
             (Ty::Float(first_bit_width), Ty::Float(second_bit_width)) => {
                 Some(Ty::Float(*first_bit_width.max(second_bit_width)))
             }
+            (
+                Ty::Range {
+                    inclusive: first_inclusive,
+                    sub_ty: first_sub_ty,
+                },
+                Ty::Range {
+                    inclusive: second_inclusive,
+                    sub_ty: second_sub_ty,
+                },
+            ) if first_inclusive == second_inclusive => Some(Ty::Range {
+                inclusive: *first_inclusive,
+                sub_ty: first_sub_ty.max(second_sub_ty, tys)?.into(),
+            }),
+            (
+                Ty::Optional {
+                    sub_ty: first_sub_ty,
+                },
+                Ty::Optional {
+                    sub_ty: second_sub_ty,
+                },
+            ) => Some(Ty::Optional {
+                sub_ty: first_sub_ty.max(second_sub_ty, tys)?.into(),
+            }),
+            (
+                Ty::Tuple { items: first_items },
+                Ty::Tuple {
+                    items: second_items,
+                },
+            ) if first_items.len() == second_items.len() => Some(Ty::Tuple {
+                items: first_items
+                    .iter()
+                    .zip(second_items.iter())
+                    .map(|(first, second)| first.max(second, tys).map(Intern::new))
+                    .collect::<Option<_>>()?,
+            }),
             (
                 Ty::Distinct {
                     fqn,
@@ -669,7 +1101,7 @@ impl Ty {
                 },
             ) => {
                 if first_enum_uid == second_enum_uid {
-                    Some((*get_enum_from_uid(*first_enum_uid)).clone())
+                    Some((*tys.enum_from_uid(*first_enum_uid)).clone())
                 } else {
                     None
                 }
@@ -680,9 +1112,16 @@ impl Ty {
             (Ty::Enum { uid, .. }, Ty::Variant { enum_uid, .. }) if enum_uid == uid => {
                 Some(self.clone())
             }
-            (Ty::Unknown | Ty::NoEval, other) | (other, Ty::Unknown | Ty::NoEval) => {
+            (Ty::Function { .. }, Ty::Closure { .. }) if self.can_fit_into(other) => {
                 Some(other.clone())
             }
+            (Ty::Closure { .. }, Ty::Function { .. }) if other.can_fit_into(self) => {
+                Some(self.clone())
+            }
+            // errors give way to the other type, which keeps one mistake
+            // from turning everything around it into an error as well
+            (Ty::Unknown | Ty::Error | Ty::NoEval, other)
+            | (other, Ty::Unknown | Ty::Error | Ty::NoEval) => Some(other.clone()),
             _ => None,
         }
     }
@@ -716,7 +1155,7 @@ impl Ty {
         match (self, expected) {
             // the callers of can_fit_into should probably
             // execute their own logic if one of the types is unknown
-            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Unknown | Ty::Error, _) | (_, Ty::Unknown | Ty::Error) => true,
             (Ty::NoEval, _) => true,
             (Ty::IInt(found_bit_width), Ty::IInt(expected_bit_width))
             | (Ty::UInt(found_bit_width), Ty::UInt(expected_bit_width)) => {
@@ -765,6 +1204,20 @@ impl Ty {
                     (true, _) | (false, false)
                 ) && !found_ty.might_be_weak()
             }
+            (
+                Ty::Range {
+                    inclusive: found_inclusive,
+                    sub_ty: found_ty,
+                },
+                Ty::Range {
+                    inclusive: expected_inclusive,
+                    sub_ty: expected_ty,
+                },
+            ) => {
+                found_inclusive == expected_inclusive
+                    && (found_ty.is_weak_replaceable_by(expected_ty)
+                        || found_ty.is_functionally_equivalent_to(expected_ty, false))
+            }
             (
                 Ty::RawPtr {
                     mutable: found_mutable,
@@ -829,6 +1282,38 @@ impl Ty {
                     && found_ty.is_functionally_equivalent_to(expected_ty, false)
             }
             (_, Ty::Any) => true,
+            (
+                Ty::Optional { sub_ty: found_ty },
+                Ty::Optional {
+                    sub_ty: expected_ty,
+                },
+            ) => {
+                found_ty.is_weak_replaceable_by(expected_ty)
+                    || found_ty.is_functionally_equivalent_to(expected_ty, false)
+            }
+            // a value gets wrapped up to become an optional that isn't `null`
+            (
+                found,
+                Ty::Optional {
+                    sub_ty: expected_ty,
+                },
+            ) => found.can_fit_into(expected_ty),
+            // the items of a tuple have to keep their exact bytes
+            (
+                Ty::Tuple { items: found_items },
+                Ty::Tuple {
+                    items: expected_items,
+                },
+            ) => {
+                found_items.len() == expected_items.len()
+                    && found_items
+                        .iter()
+                        .zip(expected_items.iter())
+                        .all(|(found, expected)| {
+                            found.is_weak_replaceable_by(expected)
+                                || found.is_functionally_equivalent_to(expected, false)
+                        })
+            }
             (
                 Ty::Struct {
                     anonymous: false,
@@ -897,14 +1382,30 @@ impl Ty {
                     uid: expected_uid, ..
                 },
             ) => found_uid == expected_uid,
+            // a variant is wrapped up with its discriminant to become its enum
             (Ty::Variant { enum_uid, .. }, Ty::Enum { uid, .. }) => enum_uid == uid,
+            // but an enum could be holding any of its variants,
+            // so getting one out requires `#unwrap` or a `switch`
+            (Ty::Enum { .. }, Ty::Variant { .. }) => false,
+            // a function is just a closure that didn't capture anything
+            (
+                Ty::Function {
+                    param_tys,
+                    return_ty,
+                },
+                Ty::Closure { .. },
+            ) => Ty::Closure {
+                param_tys: param_tys.clone(),
+                return_ty: *return_ty,
+            }
+            .is_equal_to(expected),
             (found, expected) => found.is_functionally_equivalent_to(expected, false),
         }
     }
 
     pub(crate) fn can_differentiate(&self, other: &Ty) -> bool {
         match (self, other) {
-            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Unknown | Ty::Error, _) | (_, Ty::Unknown | Ty::Error) => true,
             (
                 Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_),
                 Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_),
@@ -952,7 +1453,27 @@ impl Ty {
                     ..
                 },
             ) => left_size != right_size || left_ty.can_differentiate(right_ty),
+            (
+                Ty::Range {
+                    inclusive: left_inclusive,
+                    sub_ty: left_ty,
+                },
+                Ty::Range {
+                    inclusive: right_inclusive,
+                    sub_ty: right_ty,
+                },
+            ) => left_inclusive != right_inclusive || left_ty.can_differentiate(right_ty),
+            (Ty::Optional { sub_ty: left_ty }, Ty::Optional { sub_ty: right_ty }) => {
+                left_ty.can_differentiate(right_ty)
+            }
             (_, Ty::Any) | (Ty::Any, _) => false,
+            (Ty::Tuple { items: left_items }, Ty::Tuple { items: right_items }) => {
+                left_items.len() != right_items.len()
+                    || left_items
+                        .iter()
+                        .zip(right_items.iter())
+                        .any(|(left, right)| left.can_differentiate(right))
+            }
             (
                 Ty::Struct {
                     anonymous: false,
@@ -1025,6 +1546,37 @@ impl Ty {
         }
     }
 
+    /// Returns how `self` gets converted into `cast_into` if they're both primitive numbers,
+    /// bools, or chars.
+    ///
+    /// Distincts and variants are *not* looked through, callers should use `absolute_ty` first
+    /// if that's what they want.
+    pub fn primitive_cast(&self, cast_into: &Ty) -> Option<PrimitiveCast> {
+        #[derive(Clone, Copy)]
+        enum Class {
+            Int,
+            Float,
+            Bool,
+        }
+
+        let class = |ty: &Ty| match ty {
+            Ty::IInt(_) | Ty::UInt(_) | Ty::Char => Some(Class::Int),
+            Ty::Float(_) => Some(Class::Float),
+            Ty::Bool => Some(Class::Bool),
+            _ => None,
+        };
+
+        Some(match (class(self)?, class(cast_into)?) {
+            (Class::Int, Class::Int) => PrimitiveCast::IntToInt,
+            (Class::Int, Class::Float) => PrimitiveCast::IntToFloat,
+            (Class::Float, Class::Int) => PrimitiveCast::FloatToInt,
+            (Class::Float, Class::Float) => PrimitiveCast::FloatToFloat,
+            (Class::Int | Class::Float, Class::Bool) => PrimitiveCast::ToBool,
+            (Class::Bool, Class::Int | Class::Float) => PrimitiveCast::FromBool,
+            (Class::Bool, Class::Bool) => PrimitiveCast::BoolToBool,
+        })
+    }
+
     /// This is used for the `as` operator to see whether something can be casted into something else
     ///
     /// This only allows primitives to be casted to each other, or types that are already equal.
@@ -1033,12 +1585,11 @@ impl Ty {
             return true;
         }
 
-        match (self, cast_into) {
-            (
-                Ty::Bool | Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_) | Ty::Char,
-                Ty::Bool | Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_) | Ty::Char,
-            ) => true,
+        if self.primitive_cast(cast_into).is_some() {
+            return true;
+        }
 
+        match (self, cast_into) {
             // distincts
             (Ty::Distinct { sub_ty: from, .. }, Ty::Distinct { sub_ty: to, .. }) => {
                 from.can_cast_to(to)
@@ -1234,6 +1785,18 @@ impl Ty {
         self.can_fit_into(expected)
     }
 
+    /// The strong type that this weak type defaults to, or `None` if this type isn't weak.
+    ///
+    /// These rules are the same everywhere. Globals get their defaults during type checking,
+    /// while everything else gets them during codegen.
+    pub fn weak_default(&self) -> Option<Intern<Ty>> {
+        match self {
+            Ty::IInt(0) | Ty::UInt(0) => Some(*DEFAULT_INT),
+            Ty::Float(0) => Some(*DEFAULT_FLOAT),
+            _ => None,
+        }
+    }
+
     /// JUST BECAUSE THIS FUNCTION RETURNS TRUE, THAT DOES NOT MEAN THAT THIS TYPE IS *ACTUALLY*
     /// WEAK TYPE REPLACEABLE BY ANOTHER OTHER TYPE
     ///
@@ -1242,6 +1805,9 @@ impl Ty {
         match self {
             Ty::IInt(0) | Ty::UInt(0) | Ty::Float(0) => true,
             Ty::Array { sub_ty, .. } => sub_ty.might_be_weak(),
+            Ty::Range { sub_ty, .. } => sub_ty.might_be_weak(),
+            Ty::Optional { sub_ty } => sub_ty.might_be_weak(),
+            Ty::Tuple { items } => items.iter().any(|ty| ty.might_be_weak()),
             // todo: is this slice branch needed? i just added it because i thought it was missing
             Ty::Slice { sub_ty, .. } => sub_ty.might_be_weak(),
             Ty::Pointer { sub_ty, .. } => sub_ty.might_be_weak(),
@@ -1306,6 +1872,41 @@ impl Ty {
                     (true, _) | (false, false)
                 ) && found_sub_ty.is_weak_replaceable_by(expected_sub_ty)
             }
+            (
+                Ty::Range {
+                    inclusive: found_inclusive,
+                    sub_ty: found_sub_ty,
+                },
+                Ty::Range {
+                    inclusive: expected_inclusive,
+                    sub_ty: expected_sub_ty,
+                },
+            ) => {
+                found_inclusive == expected_inclusive
+                    && found_sub_ty.is_weak_replaceable_by(expected_sub_ty)
+            }
+            (
+                Ty::Optional {
+                    sub_ty: found_sub_ty,
+                },
+                Ty::Optional {
+                    sub_ty: expected_sub_ty,
+                },
+            ) => found_sub_ty.is_weak_replaceable_by(expected_sub_ty),
+            (
+                Ty::Tuple { items: found_items },
+                Ty::Tuple {
+                    items: expected_items,
+                },
+            ) => {
+                found_items.len() == expected_items.len()
+                    && found_items
+                        .iter()
+                        .zip(expected_items.iter())
+                        .all(|(found, expected)| {
+                            found.is_weak_replaceable_by(expected) || found.is_equal_to(expected)
+                        })
+            }
             (
                 Ty::Struct {
                     anonymous: true, ..
@@ -1346,13 +1947,23 @@ impl InternTyExt for Intern<Ty> {
 }
 
 pub(crate) trait BinaryOutput {
-    fn get_possible_output_ty(&self, first: &Ty, second: &Ty) -> Option<BinaryOutputTy>;
+    fn get_possible_output_ty(
+        &self,
+        first: &Ty,
+        second: &Ty,
+        tys: &ProjectInference,
+    ) -> Option<BinaryOutputTy>;
 }
 
 impl BinaryOutput for hir::BinaryOp {
     /// should check with `can_perform` before actually using the type emitted from this function
-    fn get_possible_output_ty(&self, first: &Ty, second: &Ty) -> Option<BinaryOutputTy> {
-        first.max(second).map(|max_ty| BinaryOutputTy {
+    fn get_possible_output_ty(
+        &self,
+        first: &Ty,
+        second: &Ty,
+        tys: &ProjectInference,
+    ) -> Option<BinaryOutputTy> {
+        first.max(second, tys).map(|max_ty| BinaryOutputTy {
             max_ty: max_ty.clone(),
             final_output_ty: match self {
                 hir::BinaryOp::Add
@@ -1378,6 +1989,37 @@ impl BinaryOutput for hir::BinaryOp {
     }
 }
 
+/// The kinds of numbers a math intrinsic such as `#sqrt` or `#popcount` can be used on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IntrinsicOperands {
+    Floats,
+    Ints,
+    Numbers,
+}
+
+impl IntrinsicOperands {
+    /// Returns how many arguments the math intrinsic called `name` takes,
+    /// and what those arguments can be
+    pub fn of(name: &str) -> Option<(usize, IntrinsicOperands)> {
+        match name {
+            "sqrt" => Some((1, IntrinsicOperands::Floats)),
+            "fma" => Some((3, IntrinsicOperands::Floats)),
+            "abs" => Some((1, IntrinsicOperands::Numbers)),
+            "min" | "max" | "pow" => Some((2, IntrinsicOperands::Numbers)),
+            "ctz" | "clz" | "popcount" => Some((1, IntrinsicOperands::Ints)),
+            _ => None,
+        }
+    }
+
+    pub fn accepts(self, ty: &Ty) -> bool {
+        match self {
+            IntrinsicOperands::Floats => ty.is_float(),
+            IntrinsicOperands::Ints => ty.is_int(),
+            IntrinsicOperands::Numbers => ty.is_int() || ty.is_float(),
+        }
+    }
+}
+
 pub(crate) trait UnaryOutput {
     fn get_possible_output_ty(&self, input: Intern<Ty>) -> Intern<Ty>;
 }
@@ -1397,24 +2039,28 @@ impl UnaryOutput for UnaryOp {
 pub(crate) trait TypedOp {
     fn can_perform(&self, ty: &Ty) -> bool;
 
+    /// whether the operation can be done between `null` and something of the given type
+    fn can_perform_with_null(&self, _ty: &Ty) -> bool {
+        false
+    }
+
     fn default_ty(&self) -> Ty;
 }
 
 impl TypedOp for hir::BinaryOp {
     fn can_perform(&self, found: &Ty) -> bool {
         match self {
-            hir::BinaryOp::Add
-            | hir::BinaryOp::Sub
-            | hir::BinaryOp::Mul
-            | hir::BinaryOp::Div
-            | hir::BinaryOp::Xor => matches!(
-                found.absolute_ty(),
-                Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_)
-            ),
-            hir::BinaryOp::BAnd | hir::BinaryOp::BOr => matches!(
-                found.absolute_ty(),
-                Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_) | Ty::Bool
-            ),
+            hir::BinaryOp::Add | hir::BinaryOp::Sub | hir::BinaryOp::Mul | hir::BinaryOp::Div => {
+                matches!(
+                    found.absolute_ty(),
+                    Ty::IInt(_) | Ty::UInt(_) | Ty::Float(_)
+                )
+            }
+            // the bits of a float aren't something you'd usually want to mess with,
+            // so that has to be done explicitly by casting to an integer first
+            hir::BinaryOp::BAnd | hir::BinaryOp::BOr | hir::BinaryOp::Xor => {
+                matches!(found.absolute_ty(), Ty::IInt(_) | Ty::UInt(_) | Ty::Bool)
+            }
             hir::BinaryOp::Mod | hir::BinaryOp::LShift | hir::BinaryOp::RShift => {
                 matches!(found.absolute_ty(), Ty::IInt(_) | Ty::UInt(_))
             }
@@ -1436,6 +2082,16 @@ impl TypedOp for hir::BinaryOp {
         }
     }
 
+    fn can_perform_with_null(&self, ty: &Ty) -> bool {
+        // even though pointers and optionals can't be compared to each other,
+        // they can always be checked against `null`
+        matches!(self, hir::BinaryOp::Eq | hir::BinaryOp::Ne)
+            && matches!(
+                ty.absolute_ty(),
+                Ty::Pointer { .. } | Ty::RawPtr { .. } | Ty::Optional { .. }
+            )
+    }
+
     fn default_ty(&self) -> Ty {
         match self {
             hir::BinaryOp::Add
@@ -1460,9 +2116,8 @@ impl TypedOp for hir::BinaryOp {
 impl TypedOp for hir::UnaryOp {
     fn can_perform(&self, found: &Ty) -> bool {
         let expected: &[Ty] = match self {
-            hir::UnaryOp::Neg | hir::UnaryOp::Pos | hir::UnaryOp::BNot => {
-                &[Ty::IInt(0), Ty::Float(0)]
-            }
+            hir::UnaryOp::Neg | hir::UnaryOp::Pos => &[Ty::IInt(0), Ty::Float(0)],
+            hir::UnaryOp::BNot => &[Ty::IInt(0)],
             hir::UnaryOp::LNot => &[Ty::Bool],
         };
 
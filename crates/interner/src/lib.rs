@@ -1,6 +1,7 @@
-use std::mem;
+use std::{cell::Cell, fmt, marker::PhantomData, mem, ptr::NonNull};
 
 use lasso::Spur;
+use unicode_normalization::UnicodeNormalization;
 
 macro_rules! impl_interner {
     ($($keyword:ident => $text:expr,)*) => {
@@ -26,11 +27,39 @@ macro_rules! impl_interner {
     (@step $_idx:expr,) => {};
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Key(lasso::Spur);
 
 pub struct Interner(lasso::Rodeo);
 
+/// How much has been interned so far.
+///
+/// Nothing ever gets removed from the interner, so this only grows over a compilation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InternerStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+thread_local! {
+    static DEBUG_INTERNER: Cell<Option<NonNull<Interner>>> = const { Cell::new(None) };
+}
+
+/// While this is alive, every `Key` on this thread gets debug printed as the string it refers
+/// to instead of as an opaque index.
+///
+/// Created with `Interner::debug_scope`
+pub struct DebugScope<'a> {
+    prev: Option<NonNull<Interner>>,
+    _interner: PhantomData<&'a Interner>,
+}
+
+impl Drop for DebugScope<'_> {
+    fn drop(&mut self) {
+        DEBUG_INTERNER.with(|interner| interner.set(self.prev));
+    }
+}
+
 impl_interner! {
     void => "void",
     isize => "isize",
@@ -61,9 +90,60 @@ impl Interner {
         Key(self.0.get_or_intern(s))
     }
 
+    /// Identifiers are normalized to NFC before being interned, so that names which look the
+    /// same (e.g. a precomposed `é` and an `e` followed by a combining accent) get the same key.
+    ///
+    /// Escaped keywords like `r#while` are interned without their `r#`
+    pub fn intern_ident(&mut self, s: &str) -> Key {
+        let s = s.strip_prefix("r#").unwrap_or(s);
+
+        if s.is_ascii() {
+            return self.intern(s);
+        }
+
+        let normalized = s.nfc().collect::<String>();
+        self.intern(&normalized)
+    }
+
     pub fn lookup(&self, key: Key) -> &str {
         self.0.resolve(&key.0)
     }
+
+    pub fn get(&self, s: &str) -> Option<Key> {
+        self.0.get(s).map(Key)
+    }
+
+    pub fn stats(&self) -> InternerStats {
+        InternerStats {
+            count: self.0.len(),
+            bytes: self.0.strings().map(str::len).sum(),
+        }
+    }
+
+    /// Makes `Key`s (and so `Name`s, `Fqn`s, etc.) print as their strings in `{:?}` output
+    /// until the returned scope is dropped.
+    pub fn debug_scope(&self) -> DebugScope<'_> {
+        let prev = DEBUG_INTERNER.with(|interner| interner.replace(Some(NonNull::from(self))));
+
+        DebugScope {
+            prev,
+            _interner: PhantomData,
+        }
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // SAFETY: the `DebugScope` borrows the interner for as long as the pointer is set
+        let text = DEBUG_INTERNER
+            .with(Cell::get)
+            .and_then(|interner| unsafe { interner.as_ref() }.0.try_resolve(&self.0));
+
+        match text {
+            Some(text) => write!(f, "{:?}", text),
+            None => f.debug_tuple("Key").field(&self.0).finish(),
+        }
+    }
 }
 
 impl Key {
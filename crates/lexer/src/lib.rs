@@ -11,9 +11,18 @@ pub fn lex(text: &str) -> Tokens {
     let mut starts = Vec::new();
 
     let mut lexer = LexerTokenKind::lexer(text);
+
+    // the shebang is kept as its own token so that everything after it keeps the same offsets
+    if text.starts_with("#!") {
+        kinds.push(TokenKind::Shebang);
+        starts.push(0.into());
+        lexer.bump(text.find('\n').unwrap_or(text.len()));
+    }
+
     while let Some(kind) = lexer.next() {
         let range = lexer.span();
         let start = (range.start as u32).into();
+        let previous = kinds.last().copied();
 
         let mut handler = |k, s| {
             kinds.push(k);
@@ -23,7 +32,26 @@ pub fn lex(text: &str) -> Tokens {
         match kind {
             Ok(LexerTokenKind::__InternalChar) => lex_char(lexer.slice(), start, handler),
             Ok(LexerTokenKind::__InternalString) => lex_string(lexer.slice(), start, handler),
-            Ok(LexerTokenKind::__InternalComment) => lex_comment(start, range.len(), handler),
+            Ok(LexerTokenKind::__InternalMultilineString) => {
+                lex_multiline_string(lexer.slice(), start, handler)
+            }
+            Ok(LexerTokenKind::__InternalComment) => lex_comment(lexer.slice(), start, handler),
+            Ok(LexerTokenKind::__InternalBlockCommentStart) => {
+                lexer.bump(block_comment_len(lexer.remainder()));
+                handler(TokenKind::BlockComment, start)
+            }
+            // the `r#` is only stripped away once the name gets interned
+            Ok(LexerTokenKind::__InternalRawIdent) => handler(TokenKind::Ident, start),
+            // `tuple.0` would otherwise be lexed as `tuple` followed by the float `.0`
+            Ok(LexerTokenKind::Float)
+                if matches!(
+                    previous,
+                    Some(TokenKind::Ident | TokenKind::RParen | TokenKind::RBrack | TokenKind::Int)
+                ) && lexer.slice()[1..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                handler(TokenKind::Dot, start);
+                handler(TokenKind::Int, start + TextSize::from(1));
+            }
             Ok(kind) => {
                 let transmuted = unsafe { mem::transmute::<LexerTokenKind, TokenKind>(kind) };
                 // we compare the debug names of the two values to ensure that no transmutation bugs occurred
@@ -43,25 +71,83 @@ pub fn lex(text: &str) -> Tokens {
     Tokens::new(kinds, starts)
 }
 
+/// How far along the lexer is within an escape sequence.
+/// most are just `\` and a single character, but `\u{1F600}` and `\x7F` go on for longer
+#[derive(Clone, Copy)]
+enum EscapeMode {
+    /// right after the `\`
+    Start,
+    /// right after `\u`
+    UnicodeStart,
+    /// within the braces of `\u{...}`
+    UnicodeDigits,
+    /// after `\x` and the given number of hex digits
+    Hex(u8),
+}
+
+enum EscapeStep {
+    Continue(EscapeMode),
+    End,
+    /// the escape sequence ended before this character, which should be lexed normally.
+    /// malformed escapes like `\u{12` are reported once they get lowered
+    NotPart,
+}
+
+fn continue_escape(mode: EscapeMode, c: char) -> EscapeStep {
+    match (mode, c) {
+        (EscapeMode::Start, 'u') => EscapeStep::Continue(EscapeMode::UnicodeStart),
+        (EscapeMode::Start, 'x') => EscapeStep::Continue(EscapeMode::Hex(0)),
+        (EscapeMode::Start, _) => EscapeStep::End,
+        (EscapeMode::UnicodeStart, '{') => EscapeStep::Continue(EscapeMode::UnicodeDigits),
+        (EscapeMode::UnicodeDigits, '}') => EscapeStep::End,
+        (EscapeMode::UnicodeDigits, c) if c.is_ascii_hexdigit() || c == '_' => {
+            EscapeStep::Continue(EscapeMode::UnicodeDigits)
+        }
+        (EscapeMode::Hex(digits), c) if c.is_ascii_hexdigit() => {
+            if digits == 1 {
+                EscapeStep::End
+            } else {
+                EscapeStep::Continue(EscapeMode::Hex(digits + 1))
+            }
+        }
+        (EscapeMode::UnicodeStart | EscapeMode::UnicodeDigits | EscapeMode::Hex(_), _) => {
+            EscapeStep::NotPart
+        }
+    }
+}
+
 fn lex_char(s: &str, offset: TextSize, mut f: impl FnMut(TokenKind, TextSize)) {
     #[derive(Clone, Copy)]
     enum Mode {
         StartContents,
         InContents,
-        Escape,
+        Escape(EscapeMode),
     }
 
     let mut mode = Mode::InContents;
     let mut pos = offset;
 
     for c in s.chars() {
+        if let Mode::Escape(escape) = mode {
+            let step = continue_escape(escape, c);
+            mode = match step {
+                EscapeStep::Continue(escape) => Mode::Escape(escape),
+                EscapeStep::End | EscapeStep::NotPart => Mode::StartContents,
+            };
+
+            if !matches!(step, EscapeStep::NotPart) {
+                pos += TextSize::from(c.len_utf8() as u32);
+                continue;
+            }
+        }
+
         match (mode, c) {
             (Mode::InContents | Mode::StartContents, '\'') => {
                 mode = Mode::StartContents;
                 f(TokenKind::SingleQuote, pos);
             }
             (Mode::InContents | Mode::StartContents, '\\') => {
-                mode = Mode::Escape;
+                mode = Mode::Escape(EscapeMode::Start);
                 f(TokenKind::Escape, pos);
             }
             (Mode::StartContents, _) => {
@@ -69,7 +155,7 @@ fn lex_char(s: &str, offset: TextSize, mut f: impl FnMut(TokenKind, TextSize)) {
                 f(TokenKind::StringContents, pos);
             }
             (Mode::InContents, _) => {}
-            (Mode::Escape, _) => mode = Mode::StartContents,
+            (Mode::Escape(_), _) => unreachable!("escapes are handled above"),
         }
 
         pos += TextSize::from(c.len_utf8() as u32);
@@ -81,20 +167,33 @@ fn lex_string(s: &str, offset: TextSize, mut f: impl FnMut(TokenKind, TextSize))
     enum Mode {
         StartContents,
         InContents,
-        Escape,
+        Escape(EscapeMode),
     }
 
     let mut mode = Mode::InContents;
     let mut pos = offset;
 
     for c in s.chars() {
+        if let Mode::Escape(escape) = mode {
+            let step = continue_escape(escape, c);
+            mode = match step {
+                EscapeStep::Continue(escape) => Mode::Escape(escape),
+                EscapeStep::End | EscapeStep::NotPart => Mode::StartContents,
+            };
+
+            if !matches!(step, EscapeStep::NotPart) {
+                pos += TextSize::from(c.len_utf8() as u32);
+                continue;
+            }
+        }
+
         match (mode, c) {
             (Mode::InContents | Mode::StartContents, '"') => {
                 mode = Mode::StartContents;
                 f(TokenKind::DoubleQuote, pos);
             }
             (Mode::InContents | Mode::StartContents, '\\') => {
-                mode = Mode::Escape;
+                mode = Mode::Escape(EscapeMode::Start);
                 f(TokenKind::Escape, pos);
             }
             (Mode::StartContents, _) => {
@@ -102,18 +201,102 @@ fn lex_string(s: &str, offset: TextSize, mut f: impl FnMut(TokenKind, TextSize))
                 f(TokenKind::StringContents, pos);
             }
             (Mode::InContents, _) => {}
-            (Mode::Escape, _) => mode = Mode::StartContents,
+            (Mode::Escape(_), _) => unreachable!("escapes are handled above"),
         }
 
         pos += TextSize::from(c.len_utf8() as u32);
     }
 }
 
-fn lex_comment(offset: TextSize, len: usize, mut f: impl FnMut(TokenKind, TextSize)) {
-    f(TokenKind::CommentLeader, offset);
+fn lex_multiline_string(s: &str, offset: TextSize, mut f: impl FnMut(TokenKind, TextSize)) {
+    #[derive(Clone, Copy)]
+    enum Mode {
+        StartContents,
+        InContents,
+        Escape(EscapeMode),
+    }
+
+    f(TokenKind::MultilineStringLeader, offset);
+
+    let mut mode = Mode::StartContents;
+    let mut pos = offset + TextSize::from(2);
+
+    // unlike normal strings, a `"` is just another character here
+    for c in s[2..].chars() {
+        if let Mode::Escape(escape) = mode {
+            let step = continue_escape(escape, c);
+            mode = match step {
+                EscapeStep::Continue(escape) => Mode::Escape(escape),
+                EscapeStep::End | EscapeStep::NotPart => Mode::StartContents,
+            };
+
+            if !matches!(step, EscapeStep::NotPart) {
+                pos += TextSize::from(c.len_utf8() as u32);
+                continue;
+            }
+        }
+
+        match (mode, c) {
+            (Mode::InContents | Mode::StartContents, '\\') => {
+                mode = Mode::Escape(EscapeMode::Start);
+                f(TokenKind::Escape, pos);
+            }
+            (Mode::StartContents, _) => {
+                mode = Mode::InContents;
+                f(TokenKind::StringContents, pos);
+            }
+            (Mode::InContents, _) => {}
+            (Mode::Escape(_), _) => unreachable!("escapes are handled above"),
+        }
+
+        pos += TextSize::from(c.len_utf8() as u32);
+    }
+}
+
+/// the length of the rest of a block comment after the opening `/*`,
+/// including any nested block comments and the final `*/`.
+/// an unclosed block comment goes until the end of the file
+fn block_comment_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut depth = 1;
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match &bytes[idx..] {
+            [b'/', b'*', ..] => {
+                depth += 1;
+                idx += 2;
+            }
+            [b'*', b'/', ..] => {
+                depth -= 1;
+                idx += 2;
+
+                if depth == 0 {
+                    return idx;
+                }
+            }
+            _ => idx += 1,
+        }
+    }
+
+    s.len()
+}
+
+/// `///` starts a doc comment, but `////` (or more) is still a regular comment
+fn lex_comment(s: &str, offset: TextSize, mut f: impl FnMut(TokenKind, TextSize)) {
+    let leader_len = if s.starts_with("///") && !s.starts_with("////") {
+        f(TokenKind::DocCommentLeader, offset);
+        3
+    } else {
+        f(TokenKind::CommentLeader, offset);
+        2
+    };
 
-    if len > 1 {
-        f(TokenKind::CommentContents, offset + TextSize::from(2));
+    if s.len() > leader_len {
+        f(
+            TokenKind::CommentContents,
+            offset + TextSize::from(leader_len as u32),
+        );
     }
 }
 
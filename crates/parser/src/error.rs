@@ -11,9 +11,30 @@ pub struct SyntaxError {
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SyntaxErrorKind {
-    Missing { offset: TextSize },
-    UnexpectedToken { found: TokenKind, range: TextRange },
-    UnexpectedNode { found: NodeKind, range: TextRange },
+    Missing {
+        offset: TextSize,
+    },
+    UnexpectedToken {
+        found: TokenKind,
+        range: TextRange,
+    },
+    UnexpectedNode {
+        found: NodeKind,
+        range: TextRange,
+    },
+    IncrementOrDecrement {
+        decrement: bool,
+        range: TextRange,
+    },
+    /// `if x = 5 {}`, where `==` was almost certainly meant
+    AssignmentInCondition {
+        range: TextRange,
+    },
+    /// a `/*` which is never closed, so the comment swallows the rest of the file.
+    /// the range is only the opening `/*`
+    UnterminatedBlockComment {
+        range: TextRange,
+    },
 }
 
 impl fmt::Debug for SyntaxError {
@@ -22,7 +43,10 @@ impl fmt::Debug for SyntaxError {
         match self.kind {
             SyntaxErrorKind::Missing { offset } => write!(f, "{}", u32::from(offset))?,
             SyntaxErrorKind::UnexpectedToken { range, .. }
-            | SyntaxErrorKind::UnexpectedNode { range, .. } => {
+            | SyntaxErrorKind::UnexpectedNode { range, .. }
+            | SyntaxErrorKind::IncrementOrDecrement { range, .. }
+            | SyntaxErrorKind::AssignmentInCondition { range }
+            | SyntaxErrorKind::UnterminatedBlockComment { range } => {
                 write!(
                     f,
                     "{}..{}",
@@ -53,6 +77,21 @@ impl fmt::Debug for SyntaxError {
                 format_expected_syntax(f)?;
                 write!(f, " but found {:?}", found)?;
             }
+            SyntaxErrorKind::IncrementOrDecrement { decrement, .. } => {
+                write!(f, "expected ")?;
+                format_expected_syntax(f)?;
+                write!(f, " but found {}", if decrement { "`--`" } else { "`++`" })?;
+            }
+            SyntaxErrorKind::AssignmentInCondition { .. } => {
+                write!(f, "expected ")?;
+                format_expected_syntax(f)?;
+                write!(f, " but found `=`")?;
+            }
+            SyntaxErrorKind::UnterminatedBlockComment { .. } => {
+                write!(f, "missing ")?;
+                format_expected_syntax(f)?;
+                write!(f, " at the end of a block comment")?;
+            }
         }
 
         Ok(())
@@ -22,6 +22,14 @@ pub(crate) fn source_file(p: &mut Parser<'_>) {
             p.error_with_recovery_set_no_default(TokenSet::NONE);
             continue;
         }
+        if p.at(TokenKind::Hash) {
+            stmt::parse_file_directive(p);
+            continue;
+        }
+        if p.at(TokenKind::Using) {
+            stmt::parse_using(p);
+            continue;
+        }
         stmt::parse_decl(p, true);
     }
 
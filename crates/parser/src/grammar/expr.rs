@@ -44,6 +44,37 @@ fn parse_expr_for_prefix(
     Some(parse_post_operators(p, recovery_set, cm, true, false))
 }
 
+const RANGE_OPERATORS: TokenSet = TokenSet::new([TokenKind::DoubleDot, TokenKind::DoubleDotEquals]);
+
+// the end of a range is optional, so `arr[2..]` needs to know when the range is over.
+// `{` is here so that `for i in 0.. {}` doesn't treat the loop body as the end
+const RANGE_END_TOKENS: TokenSet = TokenSet::new([
+    TokenKind::RBrack,
+    TokenKind::RParen,
+    TokenKind::RBrace,
+    TokenKind::LBrace,
+    TokenKind::Semicolon,
+    TokenKind::Comma,
+]);
+
+// ranges have the lowest precedence of all, and can't be chained,
+// so `a + 1..b * 2` is `(a + 1)..(b * 2)`
+fn parse_range_end(
+    p: &mut Parser,
+    range: Marker,
+    recovery_set: TokenSet,
+) -> Option<CompletedMarker> {
+    p.bump(); // bump `..` or `..=`
+
+    if !p.at_set(RANGE_END_TOKENS) && !p.at_eof() {
+        let end = p.start();
+        parse_expr_bp(p, 1, recovery_set, "range end");
+        end.complete(p, NodeKind::RangeEnd);
+    }
+
+    Some(range.complete(p, NodeKind::RangeExpr))
+}
+
 // bp stands for binding power
 // look at online resources for more
 fn parse_expr_bp(
@@ -52,6 +83,12 @@ fn parse_expr_bp(
     recovery_set: TokenSet,
     expected_syntax_name: &'static str,
 ) -> Option<CompletedMarker> {
+    if minimum_bp == 0 && p.at_set(RANGE_OPERATORS) {
+        // a range with no start, like `..5`
+        let range = p.start();
+        return parse_range_end(p, range, recovery_set);
+    }
+
     let mut lhs = parse_lhs(p, recovery_set, expected_syntax_name)?;
 
     loop {
@@ -64,6 +101,11 @@ fn parse_expr_bp(
             break;
         }
 
+        if stmt::at_increment_or_decrement(p) {
+            // let `stmt.rs` report an error for `x++` and `x--`
+            break;
+        }
+
         let (left_bp, right_bp) = if p.at(TokenKind::DoublePipe) {
             (1, 2)
         } else if p.at(TokenKind::DoubleAnd) {
@@ -77,13 +119,15 @@ fn parse_expr_bp(
             TokenKind::BangEquals,
         ])) {
             (5, 6)
+        } else if p.at(TokenKind::Orelse) {
+            (7, 8)
         } else if p.at_set(TokenSet::new([
             TokenKind::Plus,
             TokenKind::Hyphen,
             TokenKind::Pipe,
             TokenKind::Tilde,
         ])) {
-            (7, 8)
+            (9, 10)
         } else if p.at_set(TokenSet::new([
             TokenKind::Asterisk,
             TokenKind::Slash,
@@ -92,7 +136,7 @@ fn parse_expr_bp(
             TokenKind::DoubleLeft,
             TokenKind::DoubleRight,
         ])) {
-            (9, 10)
+            (11, 12)
         } else {
             break;
         };
@@ -108,9 +152,23 @@ fn parse_expr_bp(
         lhs = m.complete(p, NodeKind::BinaryExpr);
     }
 
+    if minimum_bp == 0 && p.at_set(RANGE_OPERATORS) {
+        let range = lhs.precede(p).complete(p, NodeKind::RangeStart).precede(p);
+        return parse_range_end(p, range, recovery_set);
+    }
+
     Some(lhs)
 }
 
+const LOOP_TOKENS: TokenSet = TokenSet::new([TokenKind::While, TokenKind::Loop, TokenKind::For]);
+
+/// checks for a label without a backtick, like the `outer:` in `outer: while true { ... }`
+pub(crate) fn at_bare_label(p: &mut Parser) -> bool {
+    p.at(TokenKind::Ident)
+        && p.at_ahead(1, TokenSet::new([TokenKind::Colon]))
+        && p.at_ahead(2, LOOP_TOKENS.union(TokenSet::new([TokenKind::LBrace])))
+}
+
 fn parse_lhs(
     p: &mut Parser,
     recovery_set: TokenSet,
@@ -120,7 +178,6 @@ fn parse_lhs(
 
     // println!("parse_lhs @ {:?}", p.peek());
 
-    const LOOP_TOKENS: TokenSet = TokenSet::new([TokenKind::While, TokenKind::Loop]);
     const PREFIX_TOKENS: TokenSet = TokenSet::new([
         TokenKind::Hyphen,
         TokenKind::Plus,
@@ -136,9 +193,11 @@ fn parse_lhs(
         parse_bool_literal(p)
     } else if p.at(TokenKind::DoubleQuote) {
         parse_string_literal(p)
+    } else if p.at(TokenKind::MultilineStringLeader) {
+        parse_multiline_string_literal(p)
     } else if p.at(TokenKind::SingleQuote) {
         parse_char_literal(p)
-    } else if p.at(TokenKind::Ident) {
+    } else if p.at(TokenKind::Ident) && !at_bare_label(p) {
         parse_var_ref(p)
     } else if p.at(TokenKind::Caret) {
         parse_ref(p, recovery_set)
@@ -148,10 +207,18 @@ fn parse_lhs(
         parse_directive(p)
     } else if p.at(TokenKind::Distinct) {
         parse_distinct(p, recovery_set)
+    } else if p.at(TokenKind::Question) {
+        parse_optional_decl(p, recovery_set)
+    } else if p.at(TokenKind::Closure) {
+        parse_closure_decl(p, recovery_set)
     } else if p.at(TokenKind::Comptime) {
         parse_comptime(p)
     } else if p.at(TokenKind::Struct) {
         parse_struct_decl(p, recovery_set)
+    } else if p.at(TokenKind::Module) {
+        parse_module_decl(p, recovery_set)
+    } else if p.at(TokenKind::Interface) {
+        parse_interface_decl(p, recovery_set)
     } else if p.at(TokenKind::Enum) {
         parse_enum_decl(p, recovery_set)
     } else if p.at_set(PREFIX_TOKENS) {
@@ -177,9 +244,11 @@ fn parse_lhs(
         parse_array_literal(p, None, recovery_set, None)
     } else if p.at(TokenKind::Dot) && p.at_ahead(1, TokenSet::new([TokenKind::LBrace])) {
         parse_struct_literal(p, None, recovery_set)
-    } else if p.at(TokenKind::Backtick) {
+    } else if p.at(TokenKind::Backtick) || at_bare_label(p) {
         let label = p.start();
-        p.bump();
+        if p.at(TokenKind::Backtick) {
+            p.bump();
+        }
 
         let _guard = p.expected_syntax_name("label name");
         p.expect_with_no_skip(TokenKind::Ident);
@@ -272,6 +341,13 @@ fn parse_post_operators(
 
                 p.expect(TokenKind::RParen);
 
+                // the last argument can be a lambda written after the parentheses
+                if at_trailing_lambda(p, recovery_set) {
+                    parse_lambda(p, recovery_set)
+                        .precede(p)
+                        .complete(p, NodeKind::Arg);
+                }
+
                 arg_list_m.complete(p, NodeKind::ArgList);
 
                 cm = call.complete(p, NodeKind::Call);
@@ -323,7 +399,8 @@ fn parse_post_operators(
                     let path = cm.precede(p);
                     p.bump();
 
-                    if p.at(TokenKind::Ident) {
+                    // tuples are indexed with `.0`, `.1`, etc.
+                    if p.at(TokenKind::Ident) || p.at(TokenKind::Int) {
                         p.bump();
                     } else {
                         let _guard = p.expected_syntax_name("field name");
@@ -384,6 +461,21 @@ pub(crate) fn parse_string_literal(p: &mut Parser) -> CompletedMarker {
     m.complete(p, NodeKind::StringLiteral)
 }
 
+fn parse_multiline_string_literal(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::MultilineStringLeader));
+    let m = p.start();
+
+    while p.at(TokenKind::MultilineStringLeader) {
+        p.bump();
+
+        while p.at(TokenKind::StringContents) || p.at(TokenKind::Escape) {
+            p.bump();
+        }
+    }
+
+    m.complete(p, NodeKind::StringLiteral)
+}
+
 pub(crate) fn parse_char_literal(p: &mut Parser) -> CompletedMarker {
     assert!(p.at(TokenKind::SingleQuote));
     let m = p.start();
@@ -440,6 +532,26 @@ fn parse_distinct(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
     m.complete(p, NodeKind::Distinct)
 }
 
+fn parse_optional_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
+    assert!(p.at(TokenKind::Question));
+    let m = p.start();
+    p.bump();
+
+    parse_ty(p, "type", recovery_set);
+
+    m.complete(p, NodeKind::OptionalDecl)
+}
+
+fn parse_closure_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
+    assert!(p.at(TokenKind::Closure));
+    let m = p.start();
+    p.bump();
+
+    parse_ty(p, "function type", recovery_set);
+
+    m.complete(p, NodeKind::ClosureDecl)
+}
+
 // fn parse_import_or_mod(p: &mut Parser) -> CompletedMarker {
 //     assert!(p.at_set(TokenSet::new([TokenKind::Import, TokenKind::Mod])));
 //     let m = p.start();
@@ -501,6 +613,119 @@ fn parse_prefix_expr(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker
     m.complete(p, NodeKind::UnaryExpr)
 }
 
+/// Looks past the parentheses after a call to see if they're the parameters of a lambda that's
+/// being passed as the last argument, e.g. `each(list) (x: i32) { ... }`
+///
+/// This isn't allowed where a `{` would start a block (like in an `if` condition),
+/// so `if done() () {` still calls the result of `done()`
+fn at_trailing_lambda(p: &mut Parser, recovery_set: TokenSet) -> bool {
+    if !p.at(TokenKind::LParen) || recovery_set.contains(TokenKind::LBrace) {
+        return false;
+    }
+
+    let saved_idx = p.token_idx;
+
+    // lambda parameters always need types, so `(x)` is still an argument list
+    let mut is_params = p.at_ahead(1, TokenSet::new([TokenKind::RParen]));
+
+    let mut depth = 0;
+    while let Some(kind) = p.peek() {
+        p.token_idx += 1;
+
+        match kind {
+            TokenKind::LParen | TokenKind::LBrack | TokenKind::LBrace => depth += 1,
+            TokenKind::RParen | TokenKind::RBrack | TokenKind::RBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            TokenKind::Colon if depth == 1 => is_params = true,
+            _ => {}
+        }
+    }
+
+    const AFTER_PARAMS: TokenSet = TokenSet::new([
+        TokenKind::Arrow,
+        TokenKind::LBrace,
+        TokenKind::Hash,
+        TokenKind::At,
+    ]);
+
+    let is_lambda = is_params && p.at_set(AFTER_PARAMS);
+
+    p.token_idx = saved_idx;
+
+    is_lambda && !at_function_ty(p)
+}
+
+/// Looks past the parameter list at the opening parenthesis to see if it's followed by a
+/// return type but not a body, e.g. `(i32, str) -> void`
+fn at_function_ty(p: &mut Parser) -> bool {
+    assert!(p.at(TokenKind::LParen));
+
+    let saved_idx = p.token_idx;
+
+    let mut depth = 0;
+    while let Some(kind) = p.peek() {
+        p.token_idx += 1;
+
+        match kind {
+            TokenKind::LParen | TokenKind::LBrack | TokenKind::LBrace => depth += 1,
+            TokenKind::RParen | TokenKind::RBrack | TokenKind::RBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut is_function_ty = p.at(TokenKind::Arrow);
+
+    if is_function_ty {
+        p.token_idx += 1;
+
+        // the braces of `struct { ... }` or `enum { ... }` are part of the return type
+        let mut in_ty_body = false;
+        let mut depth = 0;
+        while let Some(kind) = p.peek() {
+            match kind {
+                TokenKind::Struct | TokenKind::Enum => in_ty_body = true,
+                TokenKind::LBrace if depth == 0 && !in_ty_body => {
+                    is_function_ty = false;
+                    break;
+                }
+                TokenKind::Extern | TokenKind::Hash | TokenKind::At if depth == 0 => {
+                    is_function_ty = false;
+                    break;
+                }
+                TokenKind::LParen | TokenKind::LBrack | TokenKind::LBrace => {
+                    in_ty_body = false;
+                    depth += 1;
+                }
+                TokenKind::RParen | TokenKind::RBrack | TokenKind::RBrace => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                TokenKind::Semicolon | TokenKind::Comma | TokenKind::Equals if depth == 0 => {
+                    break;
+                }
+                _ => {}
+            }
+
+            p.token_idx += 1;
+        }
+    }
+
+    p.token_idx = saved_idx;
+
+    is_function_ty
+}
+
 fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
     assert!(p.at(TokenKind::LParen));
 
@@ -511,6 +736,8 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
 
         let mut had_param_tokens = false;
         let mut had_non_param_tokens = false;
+        // commas could also be part of a tuple
+        let mut had_commas = false;
 
         // this is a hard distinction to make, the main telltale is if the lambda has a return type or
         // if it has a body
@@ -531,11 +758,12 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
             };
 
             // only a parameter list could have these tokens
-            const PARAM_ONLY: TokenSet =
-                TokenSet::new([TokenKind::Colon, TokenKind::Comma, TokenKind::Ellipsis]);
+            const PARAM_ONLY: TokenSet = TokenSet::new([TokenKind::Colon, TokenKind::Ellipsis]);
 
             if depth == 1 && kind != TokenKind::RParen {
-                if PARAM_ONLY.contains(kind) {
+                if kind == TokenKind::Comma {
+                    had_commas = true;
+                } else if PARAM_ONLY.contains(kind) {
                     had_param_tokens = true;
                 } else {
                     had_non_param_tokens = true;
@@ -553,8 +781,28 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
             }
         }
 
+        // only a parameter list can be followed by an arrow, so even if there weren't any
+        // colons or commas, `(i32) -> void` is still a function type
+        let at_arrow = p.at(TokenKind::Arrow);
+
+        const AFTER_PARAMS: TokenSet = TokenSet::new([
+            TokenKind::Arrow,
+            TokenKind::LBrace,
+            TokenKind::Extern,
+            TokenKind::Hash,
+            TokenKind::At,
+        ]);
+
+        // `(1, 2)` is a tuple unless it's followed by an arrow. a body isn't enough since
+        // lambda parameters always need types, and `-> (i32, str) {` has to keep working
+        if had_commas && !had_param_tokens && !at_arrow {
+            p.token_idx = saved_idx;
+            return parse_paren(p, recovery_set);
+        }
+        had_param_tokens |= had_commas;
+
         // the top level of the parentheses contained no idents, colons, or commas
-        if !had_param_tokens && had_non_param_tokens {
+        if !had_param_tokens && had_non_param_tokens && !at_arrow {
             p.token_idx = saved_idx;
             return parse_paren(p, recovery_set);
         }
@@ -569,9 +817,6 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
             return parse_paren(p, recovery_set);
         };
 
-        const AFTER_PARAMS: TokenSet =
-            TokenSet::new([TokenKind::Arrow, TokenKind::LBrace, TokenKind::Extern]);
-
         if !AFTER_PARAMS.contains(kind) {
             p.token_idx = saved_idx;
             return parse_paren(p, recovery_set);
@@ -580,6 +825,10 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
         }
     }
 
+    // a function type like `(i32, str) -> void` doesn't have to name its parameters,
+    // but a lambda always does
+    let is_function_ty = at_function_ty(p);
+
     let m = p.start();
 
     let param_list_m = p.start();
@@ -595,12 +844,20 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
         }
 
         let param_m = p.start();
-        {
-            let _guard = p.expected_syntax_name("parameter name");
-            p.expect_with_recovery_set(TokenKind::Ident, PARAM_RECOVERY_SET);
-        }
 
-        p.expect_with_no_skip(TokenKind::Colon);
+        let unnamed = is_function_ty
+            && !p.at_eof()
+            && !p.at(TokenKind::Comma)
+            && (!p.at(TokenKind::Ident) || !p.at_ahead(1, TokenSet::new([TokenKind::Colon])));
+
+        if !unnamed {
+            {
+                let _guard = p.expected_syntax_name("parameter name");
+                p.expect_with_recovery_set(TokenKind::Ident, PARAM_RECOVERY_SET);
+            }
+
+            p.expect_with_no_skip(TokenKind::Colon);
+        }
 
         if p.at(TokenKind::Ellipsis) {
             p.bump();
@@ -625,7 +882,7 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
 
     param_list_m.complete(p, NodeKind::ParamList);
 
-    const BODY: TokenSet = TokenSet::new([TokenKind::LBrace, TokenKind::Extern]);
+    const BODY: TokenSet = TokenSet::new([TokenKind::LBrace, TokenKind::Extern, TokenKind::At]);
 
     if !p.at_set(BODY) {
         p.expect_with_no_skip(TokenKind::Arrow);
@@ -642,6 +899,11 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
         }
     }
 
+    // attributes like `@inline` or `@export("foo")` can go between the signature and the body
+    while p.at(TokenKind::At) {
+        parse_attr(p);
+    }
+
     if p.at(TokenKind::LBrace) {
         parse_block(p, None, recovery_set);
     } else if p.at(TokenKind::Extern) {
@@ -651,6 +913,43 @@ fn parse_lambda(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
     m.complete(p, NodeKind::Lambda)
 }
 
+/// `@inline` or `@export("foo")`
+pub(crate) fn parse_attr(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::At));
+
+    let m = p.start();
+    p.bump();
+
+    {
+        let _guard = p.expected_syntax_name("attribute");
+        p.expect_with_no_skip(TokenKind::Ident);
+    }
+    parse_attr_arg(p);
+
+    m.complete(p, NodeKind::Attr)
+}
+
+/// the `("foo")` of `@export("foo")`, which is always a string
+fn parse_attr_arg(p: &mut Parser) {
+    if !p.at(TokenKind::LParen) {
+        return;
+    }
+    p.bump();
+
+    if p.at(TokenKind::DoubleQuote) {
+        parse_string_literal(p);
+    } else {
+        let _guard = p.expected_syntax_name("string");
+        p.error_with_recovery_set_no_default(TokenSet::new([
+            TokenKind::RParen,
+            TokenKind::LBrace,
+            TokenKind::Extern,
+        ]));
+    }
+
+    p.expect_with_no_skip(TokenKind::RParen);
+}
+
 fn parse_paren(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
     assert!(p.at(TokenKind::LParen));
 
@@ -664,6 +963,22 @@ fn parse_paren(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
 
     parse_expr_with_recovery_set(p, "expression", recovery_set);
 
+    if p.at(TokenKind::Comma) {
+        while p.at(TokenKind::Comma) {
+            p.bump();
+
+            // `(1,)` is a tuple with one item
+            if p.at(TokenKind::RParen) {
+                break;
+            }
+
+            parse_expr_with_recovery_set(p, "tuple item", recovery_set);
+        }
+
+        p.expect_with_no_skip(TokenKind::RParen);
+        return m.complete(p, NodeKind::TupleExpr);
+    }
+
     p.expect_with_no_skip(TokenKind::RParen);
     m.complete(p, NodeKind::ParenExpr)
 }
@@ -727,6 +1042,46 @@ fn parse_struct_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker
             break;
         }
 
+        // declarations inside the struct body, e.g. `ORIGIN :: Point.{ x = 0, y = 0 }`
+        if p.at(TokenKind::At)
+            || p.at(TokenKind::Ident)
+                && p.at_ahead(1, TokenSet::new([TokenKind::Colon]))
+                && p.at_ahead(2, TokenSet::new([TokenKind::Colon]))
+        {
+            let decl_m = p.start();
+            while p.at(TokenKind::At) {
+                parse_attr(p);
+            }
+            {
+                let _guard = p.expected_syntax_name("name");
+                p.expect_with_no_skip(TokenKind::Ident);
+            }
+            p.expect_with_no_skip(TokenKind::Colon);
+            p.expect_with_no_skip(TokenKind::Colon);
+
+            let value = parse_expr_bp(
+                p,
+                0,
+                recovery_set.union(TokenSet::new([TokenKind::Comma, TokenKind::RBrace])),
+                "value",
+            );
+
+            decl_m.complete(p, NodeKind::Binding);
+
+            if p.at_eof() || p.at_default_recovery_set() {
+                break;
+            }
+
+            // just like at the top level, functions don't need anything after their body
+            let is_lambda = value.is_some_and(|value| value.kind() == NodeKind::Lambda);
+
+            if !p.at(TokenKind::RBrace) && (!is_lambda || p.at(TokenKind::Comma)) {
+                p.expect_with_no_skip(TokenKind::Comma);
+            }
+
+            continue;
+        }
+
         let field_m = p.start();
         let _guard = p.expected_syntax_name("field name");
         p.expect(TokenKind::Ident);
@@ -754,6 +1109,89 @@ fn parse_struct_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker
     m.complete(p, NodeKind::StructDecl)
 }
 
+fn parse_interface_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
+    assert!(p.at(TokenKind::Interface));
+
+    let m = p.start();
+
+    p.bump();
+
+    if p.at(TokenKind::LBrace) {
+        p.bump();
+    } else {
+        let _guard = p.expected_syntax_name("interface body");
+        p.error_with_recovery_set(recovery_set);
+
+        return m.complete(p, NodeKind::InterfaceDecl);
+    }
+
+    // each method is written just like a struct field, e.g. `area: () -> f32`
+    loop {
+        if p.at(TokenKind::RBrace) {
+            break;
+        }
+
+        let method_m = p.start();
+        let _guard = p.expected_syntax_name("method name");
+        p.expect(TokenKind::Ident);
+
+        p.expect_with_no_skip(TokenKind::Colon);
+
+        parse_ty(
+            p,
+            "method type",
+            recovery_set.union(TokenSet::new([TokenKind::Comma, TokenKind::RBrace])),
+        );
+
+        method_m.complete(p, NodeKind::MemberDecl);
+
+        if p.at_eof() || p.at_default_recovery_set() {
+            break;
+        }
+
+        if !p.at(TokenKind::RBrace) {
+            p.expect_with_no_skip(TokenKind::Comma);
+        }
+    }
+    p.expect(TokenKind::RBrace);
+
+    m.complete(p, NodeKind::InterfaceDecl)
+}
+
+fn parse_module_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
+    assert!(p.at(TokenKind::Module));
+
+    let m = p.start();
+
+    p.bump();
+
+    if p.at(TokenKind::LBrace) {
+        p.bump();
+    } else {
+        let _guard = p.expected_syntax_name("module body");
+        p.error_with_recovery_set(recovery_set);
+
+        return m.complete(p, NodeKind::ModuleDecl);
+    }
+
+    // this is the same as the top level of a file
+    while !p.at_eof() && !p.at(TokenKind::RBrace) {
+        if p.at(TokenKind::Semicolon) {
+            p.bump();
+            continue;
+        }
+        if p.at_default_recovery_set() {
+            let _guard = p.expected_syntax_name("definition");
+            p.error_with_recovery_set_no_default(TokenSet::NONE);
+            continue;
+        }
+        stmt::parse_decl(p, true);
+    }
+    p.expect(TokenKind::RBrace);
+
+    m.complete(p, NodeKind::ModuleDecl)
+}
+
 fn parse_struct_literal(
     p: &mut Parser,
     previous_ty: Option<CompletedMarker>,
@@ -880,6 +1318,8 @@ fn parse_enum_decl(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
 ///
 /// If previous_ty is set to None, this will be treated as an untyped array literal
 /// `.[1, 2, 3]`
+///
+/// A single item followed by a semicolon and a count repeats that item, `i32.[0; 5]`
 fn parse_array_literal(
     p: &mut Parser,
     previous_ty: Option<CompletedMarker>,
@@ -913,6 +1353,7 @@ fn parse_array_literal(
     // if we are `at_lbrace` this will report an error
     p.expect_with_recovery_set_no_default(TokenKind::LBrack, DEFAULT_NO_BRACES);
 
+    let mut first = true;
     loop {
         if p.at(TokenKind::RBrack) || p.at(TokenKind::RBrace) {
             break;
@@ -920,7 +1361,19 @@ fn parse_array_literal(
 
         if let Some(item) = parse_expr_with_recovery_set(p, "array item", recovery_set) {
             item.precede(p).complete(p, NodeKind::ArrayItem);
+
+            // `u8.[0; 256]` repeats a single item
+            if first && p.at(TokenKind::Semicolon) {
+                p.bump();
+
+                let count = p.start();
+                parse_expr_with_recovery_set(p, "repeat count", recovery_set);
+                count.complete(p, NodeKind::ArrayRepeatCount);
+
+                break;
+            }
         }
+        first = false;
 
         if p.at_eof() || p.at_default_recovery_set() {
             break;
@@ -995,11 +1448,7 @@ fn parse_if(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
     let m = p.start();
     p.bump();
 
-    parse_expr_with_recovery_set(
-        p,
-        "condition",
-        recovery_set.union(TokenSet::new([TokenKind::LBrace])),
-    );
+    parse_condition(p, recovery_set);
 
     if p.at(TokenKind::LBrace) {
         parse_block(p, None, recovery_set);
@@ -1027,6 +1476,25 @@ fn parse_if(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
     m.complete(p, NodeKind::IfExpr)
 }
 
+/// `if x = 5 {}` is a common enough mistake that it gets its own error.
+/// the whole assignment is put inside of an error node so the body can still be parsed
+fn parse_condition(p: &mut Parser, recovery_set: TokenSet) {
+    let recovery_set = recovery_set.union(TokenSet::new([TokenKind::LBrace]));
+
+    let cm = parse_expr_with_recovery_set(p, "condition", recovery_set);
+
+    if let Some(cm) = cm.filter(|_| p.at(TokenKind::Equals)) {
+        let m = cm.precede(p);
+
+        p.mark_assignment_in_condition(p.token_idx);
+        p.bump();
+
+        parse_expr_with_recovery_set(p, "value", recovery_set);
+
+        m.complete(p, NodeKind::Error);
+    }
+}
+
 fn parse_loop(
     p: &mut Parser,
     label: Option<CompletedMarker>,
@@ -1034,7 +1502,8 @@ fn parse_loop(
 ) -> CompletedMarker {
     let at_while = p.at(TokenKind::While);
     let at_loop = p.at(TokenKind::Loop);
-    assert!(at_while || at_loop);
+    let at_for = p.at(TokenKind::For);
+    assert!(at_while || at_loop || at_for);
 
     let m = if let Some(label) = label {
         label.precede(p)
@@ -1045,13 +1514,45 @@ fn parse_loop(
     p.bump();
 
     if at_while {
+        let m = p.start();
+
+        // `while x := next() { ... }` binds the condition for the body
+        let binding = p.at(TokenKind::Ident)
+            && p.at_ahead(1, TokenSet::new([TokenKind::Colon]))
+            && p.at_ahead(2, TokenSet::new([TokenKind::Equals]));
+
+        if binding {
+            let def = p.start();
+            p.expect_with_no_skip(TokenKind::Ident);
+            p.expect_with_no_skip(TokenKind::Colon);
+            p.expect_with_no_skip(TokenKind::Equals);
+            parse_expr_with_recovery_set(
+                p,
+                "condition",
+                recovery_set.union(TokenSet::new([TokenKind::LBrace])),
+            );
+            def.complete(p, NodeKind::VarDef);
+        } else {
+            parse_condition(p, recovery_set);
+        }
+
+        m.complete(p, NodeKind::Condition);
+    }
+
+    if at_for {
+        {
+            let _guard = p.expected_syntax_name("item name");
+            p.expect_with_no_skip(TokenKind::Ident);
+        }
+        p.expect_with_no_skip(TokenKind::In);
+
         let m = p.start();
         parse_expr_with_recovery_set(
             p,
-            "condition",
+            "iterable",
             recovery_set.union(TokenSet::new([TokenKind::LBrace])),
         );
-        m.complete(p, NodeKind::Condition);
+        m.complete(p, NodeKind::Iterable);
     }
 
     if p.at(TokenKind::LBrace) {
@@ -1059,13 +1560,19 @@ fn parse_loop(
     } else {
         let _guard = if at_while {
             p.expected_syntax_name("while body")
+        } else if at_for {
+            p.expected_syntax_name("for body")
         } else {
             p.expected_syntax_name("loop body")
         };
         p.error_with_recovery_set(recovery_set);
     }
 
-    m.complete(p, NodeKind::WhileExpr)
+    if at_for {
+        m.complete(p, NodeKind::ForExpr)
+    } else {
+        m.complete(p, NodeKind::WhileExpr)
+    }
 }
 
 fn parse_switch(p: &mut Parser, recovery_set: TokenSet) -> CompletedMarker {
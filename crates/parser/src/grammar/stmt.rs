@@ -17,8 +17,24 @@ pub(crate) const QUICK_ASSIGN_OPERATORS: TokenSet = TokenSet::new([
     TokenKind::And,
     TokenKind::DoubleLeft,
     TokenKind::DoubleRight,
+    TokenKind::DoubleAnd,
+    TokenKind::DoublePipe,
 ]);
 
+/// checks for a C-style `x++` or `x--`, which doesn't exist but is common enough
+/// in other languages that it deserves its own error
+pub(crate) fn at_increment_or_decrement(p: &mut Parser) -> bool {
+    [TokenKind::Plus, TokenKind::Hyphen].into_iter().any(|op| {
+        p.at(op)
+            && p.at_ahead(1, TokenSet::new([op]))
+            && (p.at_eof_ahead(2)
+                || p.at_ahead(
+                    2,
+                    TokenSet::new([TokenKind::Semicolon, TokenKind::RBrace, TokenKind::RParen]),
+                ))
+    })
+}
+
 /// this function would only be called in a REPL or code block
 pub(crate) fn parse_stmt(p: &mut Parser, repl: bool) -> Option<CompletedMarker> {
     while p.at(TokenKind::Semicolon) {
@@ -46,9 +62,15 @@ pub(crate) fn parse_stmt(p: &mut Parser, repl: bool) -> Option<CompletedMarker>
             && p.at_ahead(1, TokenSet::new([TokenKind::Ident]))
             && !p.at_ahead(2, TokenSet::new([TokenKind::Colon]));
 
-        if !at_return && (old_label_syntax || new_label_syntax) {
+        // `continue` never has a value, so a name after it can only be a label.
+        // `break outer;` is ambiguous with a value, so that gets figured out while lowering
+        let bare_label_syntax = at_continue && p.at(TokenKind::Ident) && !old_label_syntax;
+
+        if !at_return && (old_label_syntax || new_label_syntax || bare_label_syntax) {
             let label = p.start();
-            p.expect_with_no_skip(TokenKind::Backtick);
+            if !bare_label_syntax {
+                p.expect_with_no_skip(TokenKind::Backtick);
+            }
             p.expect_with_no_skip(TokenKind::Ident);
             if old_label_syntax && p.at(TokenKind::Backtick) {
                 let _guard = p.expected_syntax_name("nothing");
@@ -96,7 +118,10 @@ pub(crate) fn parse_stmt(p: &mut Parser, repl: bool) -> Option<CompletedMarker>
 
     // Idents can start expressions AND definitions
     // this code tells the difference by looking ahead
-    if p.at(TokenKind::Ident) && p.at_ahead(1, TokenSet::new([TokenKind::Colon])) {
+    if p.at(TokenKind::Ident)
+        && p.at_ahead(1, TokenSet::new([TokenKind::Colon]))
+        && !expr::at_bare_label(p)
+    {
         let res = parse_decl(p, false);
         while p.at(TokenKind::Semicolon) {
             p.bump();
@@ -114,6 +139,24 @@ pub(crate) fn parse_stmt(p: &mut Parser, repl: bool) -> Option<CompletedMarker>
 
     let m = expr_cm.precede(p);
 
+    if at_increment_or_decrement(p) {
+        let decrement = p.at(TokenKind::Hyphen);
+        let start_token = p.token_idx;
+
+        let error = p.start();
+        p.bump();
+        p.bump();
+        error.complete(p, NodeKind::Error);
+
+        p.mark_increment_or_decrement(decrement, start_token, p.token_idx - 1);
+
+        if !(p.at(TokenKind::RBrace) || repl && p.at_eof()) {
+            p.expect_with_no_skip(TokenKind::Semicolon);
+        }
+
+        return Some(m.complete(p, NodeKind::ExprStmt));
+    }
+
     let quick_assign =
         p.at_set(QUICK_ASSIGN_OPERATORS) && p.at_ahead(1, TokenSet::new([TokenKind::Equals]));
     let regular_assign = p.at(TokenKind::Equals);
@@ -139,6 +182,7 @@ pub(crate) fn parse_stmt(p: &mut Parser, repl: bool) -> Option<CompletedMarker>
             expr_cm.kind(),
             NodeKind::IfExpr
                 | NodeKind::WhileExpr
+                | NodeKind::ForExpr
                 | NodeKind::SwitchExpr
                 | NodeKind::ComptimeExpr
                 | NodeKind::Block
@@ -157,9 +201,38 @@ pub(crate) fn parse_stmt(p: &mut Parser, repl: bool) -> Option<CompletedMarker>
     res
 }
 
+/// directives which apply to the whole file, like `#requires_version(">=0.2");`
+pub(crate) fn parse_file_directive(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::Hash));
+    let m = p.start();
+
+    expr::parse_expr(p, "directive");
+
+    p.expect_with_no_skip(TokenKind::Semicolon);
+
+    m.complete(p, NodeKind::ExprStmt)
+}
+
+/// `using #import("foo.capy");`
+pub(crate) fn parse_using(p: &mut Parser) -> CompletedMarker {
+    assert!(p.at(TokenKind::Using));
+    let m = p.start();
+    p.bump();
+
+    expr::parse_expr(p, "file");
+
+    p.expect_with_no_skip(TokenKind::Semicolon);
+
+    m.complete(p, NodeKind::Using)
+}
+
 pub(crate) fn parse_decl(p: &mut Parser, top_level: bool) -> CompletedMarker {
     let m = p.start();
 
+    while p.at(TokenKind::At) {
+        expr::parse_attr(p);
+    }
+
     // todo: this is not very descriptive, but i don't think "variable name" fits either
     let _guard = p.expected_syntax_name("name");
     p.expect_with_no_skip(TokenKind::Ident);
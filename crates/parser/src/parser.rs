@@ -6,7 +6,7 @@ use std::rc::Rc;
 
 use syntax::NodeKind;
 use syntax::TokenKind;
-use text_size::TextRange;
+use text_size::{TextRange, TextSize};
 use token::Tokens;
 
 use crate::error::ExpectedSyntax;
@@ -50,6 +50,15 @@ impl<'tokens> Parser<'tokens> {
     pub(crate) fn parse(mut self, grammar: impl Fn(&mut Self)) -> (Vec<Event>, Vec<SyntaxError>) {
         grammar(&mut self);
 
+        if let Some(range) = self.unterminated_block_comment() {
+            self.errors.push(SyntaxError {
+                expected_syntax: ExpectedSyntax::Named("`*/`"),
+                kind: SyntaxErrorKind::UnterminatedBlockComment {
+                    range: TextRange::at(range.start(), TextSize::from(2)),
+                },
+            });
+        }
+
         for event in &self.events {
             assert!(event.is_some());
         }
@@ -68,6 +77,38 @@ impl<'tokens> Parser<'tokens> {
         )
     }
 
+    /// The range of the block comment at the very end of the file,
+    /// if it never got closed.
+    ///
+    /// The lexer only ends a block comment early once all of its `/*`s have been closed,
+    /// so only one running into the end of the file needs to be checked
+    fn unterminated_block_comment(&self) -> Option<TextRange> {
+        let last_idx = self.tokens.len().checked_sub(1)?;
+        if self.tokens.kind(last_idx) != TokenKind::BlockComment {
+            return None;
+        }
+
+        let bytes = self.text(last_idx).as_bytes();
+        let mut depth = 0;
+        let mut idx = 0;
+
+        while idx < bytes.len() {
+            match &bytes[idx..] {
+                [b'/', b'*', ..] => {
+                    depth += 1;
+                    idx += 2;
+                }
+                [b'*', b'/', ..] => {
+                    depth -= 1;
+                    idx += 2;
+                }
+                _ => idx += 1,
+            }
+        }
+
+        (depth > 0).then(|| self.tokens.range(last_idx))
+    }
+
     pub(crate) fn text(&self, token_idx: usize) -> &'tokens str {
         let range = self.tokens.range(token_idx);
         &self.input[range.start().into()..range.end().into()]
@@ -135,6 +176,12 @@ impl<'tokens> Parser<'tokens> {
         self.expected_syntax_tracking_state
             .set(ExpectedSyntaxTrackingState::Unnamed);
 
+        // whatever was missing got swallowed by the comment,
+        // which gets its own error once parsing is over
+        if self.at_eof() && self.unterminated_block_comment().is_some() {
+            return None;
+        }
+
         if self.at_eof() || self.at_set(recovery_set) {
             let range = self.previous_token_range();
             self.errors.push(SyntaxError {
@@ -179,6 +226,33 @@ impl<'tokens> Parser<'tokens> {
         });
     }
 
+    pub(crate) fn mark_increment_or_decrement(
+        &mut self,
+        decrement: bool,
+        start_token: usize,
+        end_token: usize,
+    ) {
+        self.errors.push(SyntaxError {
+            expected_syntax: ExpectedSyntax::Named(if decrement { "`-= 1`" } else { "`+= 1`" }),
+            kind: SyntaxErrorKind::IncrementOrDecrement {
+                decrement,
+                range: self
+                    .tokens
+                    .range(start_token)
+                    .cover(self.tokens.range(end_token)),
+            },
+        });
+    }
+
+    pub(crate) fn mark_assignment_in_condition(&mut self, equals_token: usize) {
+        self.errors.push(SyntaxError {
+            expected_syntax: ExpectedSyntax::Named("`==`"),
+            kind: SyntaxErrorKind::AssignmentInCondition {
+                range: self.tokens.range(equals_token),
+            },
+        });
+    }
+
     pub(crate) fn mark_old_missing(&mut self, start_token: usize, expected: ExpectedSyntax) {
         self.errors.push(SyntaxError {
             expected_syntax: expected,
@@ -287,8 +361,12 @@ impl<'tokens> Parser<'tokens> {
             return self.tokens.range(self.token_idx);
         };
 
-        while let TokenKind::Whitespace | TokenKind::CommentLeader | TokenKind::CommentContents =
-            self.tokens.kind(previous_token_idx)
+        while let TokenKind::Whitespace
+        | TokenKind::CommentLeader
+        | TokenKind::DocCommentLeader
+        | TokenKind::CommentContents
+        | TokenKind::BlockComment
+        | TokenKind::Shebang = self.tokens.kind(previous_token_idx)
         {
             previous_token_idx = if let Some(idx) = previous_token_idx.checked_sub(1) {
                 idx
@@ -307,8 +385,12 @@ impl<'tokens> Parser<'tokens> {
             return self.tokens.kind(self.token_idx);
         };
 
-        while let TokenKind::Whitespace | TokenKind::CommentLeader | TokenKind::CommentContents =
-            self.tokens.kind(previous_token_idx)
+        while let TokenKind::Whitespace
+        | TokenKind::CommentLeader
+        | TokenKind::DocCommentLeader
+        | TokenKind::CommentContents
+        | TokenKind::BlockComment
+        | TokenKind::Shebang = self.tokens.kind(previous_token_idx)
         {
             previous_token_idx = if let Some(idx) = previous_token_idx.checked_sub(1) {
                 idx
@@ -323,7 +405,10 @@ impl<'tokens> Parser<'tokens> {
     fn skip_trivia(&mut self) {
         while self.at_raw(TokenKind::Whitespace)
             || self.at_raw(TokenKind::CommentLeader)
+            || self.at_raw(TokenKind::DocCommentLeader)
             || self.at_raw(TokenKind::CommentContents)
+            || self.at_raw(TokenKind::BlockComment)
+            || self.at_raw(TokenKind::Shebang)
         {
             self.token_idx += 1;
         }
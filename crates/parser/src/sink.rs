@@ -93,7 +93,7 @@ impl<'tokens> Sink<'tokens> {
                 // then we finish the node in the CommentContents case;
                 // if it does not have contents,
                 // then we finish the node straight away in the CommentLeader case
-                Some(TokenKind::CommentLeader) => {
+                Some(TokenKind::CommentLeader | TokenKind::DocCommentLeader) => {
                     self.builder.start_node(NodeKind::Comment);
                     self.add_token();
                     // look ahead one token
@@ -106,13 +106,23 @@ impl<'tokens> Sink<'tokens> {
                     self.add_token();
                     self.builder.finish_node();
                 }
+                Some(TokenKind::BlockComment | TokenKind::Shebang) => {
+                    self.builder.start_node(NodeKind::Comment);
+                    self.add_token();
+                    self.builder.finish_node();
+                }
 
                 Some(_) | None => break,
             }
         }
 
         while let Some(
-            TokenKind::Whitespace | TokenKind::CommentLeader | TokenKind::CommentContents,
+            TokenKind::Whitespace
+            | TokenKind::CommentLeader
+            | TokenKind::DocCommentLeader
+            | TokenKind::CommentContents
+            | TokenKind::BlockComment
+            | TokenKind::Shebang,
         ) = self.tokens.get_kind(self.token_idx)
         {
             self.add_token();
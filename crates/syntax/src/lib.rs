@@ -52,17 +52,23 @@ pub enum NodeKind {
     ArrayLiteral,
     ArrayBody,
     ArrayItem,
-    IndexExpr, // the entire expression of indexing. e.g. `my_array[6]`
-    Index,     // the actual index. `6` in `my_array[6]`
+    ArrayRepeatCount, // the `256` in `u8.[0; 256]`
+    IndexExpr,        // the entire expression of indexing. e.g. `my_array[6]`
+    Index,            // the actual index. `6` in `my_array[6]`
     Source,
     Distinct,
+    OptionalDecl, // `?i32`
+    ClosureDecl,  // `closure (x: i32) -> i32`
     ComptimeExpr,
     ParenExpr,
+    TupleExpr, // `(1, "hi")`, which is also how tuple types like `(i32, str)` are written
     Block,
     IfExpr,
     ElseBranch,
     WhileExpr,
     Condition,
+    ForExpr,
+    Iterable, // the `xs` in `for x in xs { ... }`
     SwitchExpr,
     SwitchArm,
     LabelDecl,
@@ -77,9 +83,13 @@ pub enum NodeKind {
     MutExpr, // `mut rawptr` (yes, that's the only thing its used for)
     DerefExpr,
     BinaryExpr,
+    RangeExpr,  // `a..b` or `a..=b`, either bound can be left out
+    RangeStart, // the `a` in `a..b`
+    RangeEnd,   // the `b` in `a..b`
     UnaryExpr,
     Binding, // `x :: 5`
     VarDef,  // `x := 5`
+    Using,   // `using #import("foo.capy");`
     Assign,
     ExprStmt,
     ReturnStmt, // todo: change these to void expressions
@@ -89,10 +99,13 @@ pub enum NodeKind {
     Lambda,
     ParamList,
     Param,
+    Attr,          // `@inline`, `@deprecated` or `@export("foo")`
     StructDecl,    // `struct { foo: i32 }`
     MemberDecl,    // `foo: i32`
     StructLiteral, // `My_Struct { foo: 123 }`
     MemberLiteral, // `foo: 123`
+    ModuleDecl,    // `module { foo :: 123; }`
+    InterfaceDecl, // `interface { area: () -> f32 }`
     EnumDecl,
     VariantDecl,
     Discriminant,